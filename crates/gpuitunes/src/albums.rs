@@ -0,0 +1,377 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{Album, AlbumRating, ArtworkStore, Audition, Library, TrackId};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use crate::{assets::Icon, element::*};
+
+const COVER_SIZE: f32 = 150.;
+
+type AlbumKey = (SharedString, SharedString);
+
+pub enum AlbumsEvent {
+    ShowAlbum(SharedString, SharedString),
+}
+
+/// Every album as a grid of covers. Clicking one shows its tracks, and
+/// the play button on a hovered cover previews its first track on the
+/// audition output until the pointer moves away.
+pub struct AlbumsView {
+    library: Model<Library>,
+    audition: Model<Audition>,
+    artwork_store: Arc<ArtworkStore>,
+    albums: Vec<Album>,
+    // By the artwork they're made from. `None` while one is being made, or
+    // when it couldn't be.
+    thumbnails: HashMap<PathBuf, Option<PathBuf>>,
+    // "9 of 12" for the albums missing tracks
+    incomplete: HashMap<AlbumKey, String>,
+    // By artist and title rather than place, which moves as the library
+    // changes
+    hovered: Option<AlbumKey>,
+    // The album whose preview is playing
+    previewing: Option<AlbumKey>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl EventEmitter<AlbumsEvent> for AlbumsView {}
+
+impl AlbumsView {
+    pub fn new(
+        library: Model<Library>,
+        audition: Model<Audition>,
+        artwork_store: Arc<ArtworkStore>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let subscriptions = vec![
+            cx.observe(&library, |this, library, cx| {
+                this.albums = library.read(cx).albums();
+                this.incomplete = incomplete_albums(library.read(cx));
+                this.load_thumbnails(cx);
+                cx.notify();
+            }),
+            // A preview started elsewhere, or stopped from the status bar
+            cx.observe(&audition, |this, audition, cx| {
+                if !audition.read(cx).is_preview() {
+                    this.previewing = None;
+                    cx.notify();
+                }
+            }),
+            // Leaving the grid counts as moving away
+            cx.on_release(|this, cx| {
+                this.audition.update(cx, |audition, cx| {
+                    audition.stop_preview();
+                    cx.notify();
+                })
+            }),
+        ];
+
+        let mut view = AlbumsView {
+            albums: library.read(cx).albums(),
+            incomplete: incomplete_albums(library.read(cx)),
+            library,
+            audition,
+            artwork_store,
+            thumbnails: HashMap::new(),
+            hovered: None,
+            previewing: None,
+            _subscriptions: subscriptions,
+        };
+        view.load_thumbnails(cx);
+        view
+    }
+
+    // Made off the main thread, each cover is decoded in full the first time
+    fn load_thumbnails(&mut self, cx: &mut ViewContext<Self>) {
+        let library = self.library.read(cx);
+        let wanted: Vec<_> = self
+            .albums
+            .iter()
+            .filter_map(|album| {
+                let artwork = album.artwork.clone()?;
+                if self.thumbnails.contains_key(&artwork) {
+                    return None;
+                }
+                let track = album
+                    .tracks
+                    .iter()
+                    .filter_map(|id| library.track(id))
+                    .find(|track| track.artwork() == Some(artwork.as_path()))?;
+                Some((artwork, track.clone()))
+            })
+            .collect();
+        if wanted.is_empty() {
+            return;
+        }
+        for (artwork, _) in &wanted {
+            self.thumbnails.insert(artwork.clone(), None);
+        }
+
+        let artwork_store = self.artwork_store.clone();
+        cx.spawn(|this, mut cx| async move {
+            let thumbnails = cx
+                .background_executor()
+                .spawn(async move {
+                    wanted
+                        .into_iter()
+                        .map(|(artwork, track)| {
+                            let thumbnail = artwork_store
+                                .album_thumbnail(&track)
+                                .map_err(|e| {
+                                    eprintln!(
+                                        "Failed to make a thumbnail of {}: {}",
+                                        artwork.display(),
+                                        e
+                                    )
+                                })
+                                .ok()
+                                .flatten();
+                            (artwork, thumbnail)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.thumbnails.extend(thumbnails);
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn hover_album(&mut self, key: AlbumKey, hovered: bool, cx: &mut ViewContext<Self>) {
+        if !hovered && self.previewing.as_ref() == Some(&key) {
+            self.previewing = None;
+            self.audition.update(cx, |audition, cx| {
+                audition.stop_preview();
+                cx.notify();
+            });
+        }
+        if hovered {
+            self.hovered = Some(key);
+        } else if self.hovered.as_ref() == Some(&key) {
+            self.hovered = None;
+        }
+        cx.notify();
+    }
+
+    // Leaves Up Next and whatever is playing alone
+    fn preview_album(&mut self, key: AlbumKey, cx: &mut ViewContext<Self>) {
+        let Some(track) = self
+            .albums
+            .iter()
+            .find(|album| album_key(album) == key)
+            .and_then(|album| album.tracks.first())
+            .and_then(|id| self.library.read(cx).track(id))
+            .cloned()
+        else {
+            return;
+        };
+        self.audition.update(cx, |audition, cx| {
+            audition.start_preview(track);
+            cx.notify();
+        });
+        self.previewing = Some(key);
+        cx.notify();
+    }
+
+    fn rate_album(&mut self, track_id: &TrackId, stars: u8, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            library.rate_album(track_id, stars);
+            cx.notify();
+        });
+    }
+
+    fn stop_preview(&mut self, cx: &mut ViewContext<Self>) {
+        self.previewing = None;
+        self.audition.update(cx, |audition, cx| {
+            audition.stop_preview();
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    fn render_play_button(
+        &self,
+        ix: usize,
+        key: AlbumKey,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let previewing = self.previewing.as_ref() == Some(&key);
+        let icon = if previewing { Icon::Pause } else { Icon::Play };
+        circle(px(28.))
+            .id(("album-preview", ix))
+            .absolute()
+            .right(px(6.))
+            .bottom(px(6.))
+            .flex()
+            .items_center()
+            .justify_center()
+            .bg(rgba(0x000000A0))
+            .active(|button| button.opacity(0.8))
+            .child(
+                svg()
+                    .size(px(11.))
+                    .path(icon.path())
+                    .text_color(rgb(0xFFFFFF)),
+            )
+            .on_click(cx.listener(move |this, _, cx| {
+                cx.stop_propagation();
+                if previewing {
+                    this.stop_preview(cx);
+                } else {
+                    this.preview_album(key.clone(), cx);
+                }
+            }))
+    }
+
+    fn render_album(
+        &self,
+        ix: usize,
+        album: &Album,
+        rating: Option<AlbumRating>,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let thumbnail = album
+            .artwork
+            .as_ref()
+            .and_then(|artwork| self.thumbnails.get(artwork).cloned().flatten());
+        let key = album_key(album);
+        let show_play =
+            self.hovered.as_ref() == Some(&key) || self.previewing.as_ref() == Some(&key);
+        let incomplete = self.incomplete.get(&key).cloned();
+        let (artist, title) = key.clone();
+
+        v_stack()
+            .id(("album", ix))
+            .w(px(COVER_SIZE))
+            .gap(px(2.))
+            .cursor_pointer()
+            .on_hover(cx.listener({
+                let key = key.clone();
+                move |this, hovered: &bool, cx| this.hover_album(key.clone(), *hovered, cx)
+            }))
+            .on_click(cx.listener(move |_, _, cx| {
+                cx.emit(AlbumsEvent::ShowAlbum(artist.clone(), title.clone()))
+            }))
+            .child(
+                div()
+                    .relative()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .size(px(COVER_SIZE))
+                    .rounded(px(3.))
+                    .overflow_hidden()
+                    .border_1()
+                    .border_color(rgb(0xD0D0D0))
+                    .bg(rgb(0xE8E8E8))
+                    .map(|cover| match thumbnail {
+                        Some(thumbnail) => {
+                            cover.child(img(thumbnail).size_full().object_fit(ObjectFit::Cover))
+                        }
+                        None => cover.child(
+                            svg()
+                                .size(px(40.))
+                                .path(Icon::MusicNote.path())
+                                .text_color(rgb(0xA8A8A8)),
+                        ),
+                    })
+                    .when_some(incomplete, |cover, summary| {
+                        cover.child(
+                            div()
+                                .absolute()
+                                .left(px(6.))
+                                .top(px(6.))
+                                .px(px(5.))
+                                .rounded(px(8.))
+                                .bg(rgba(0x000000A0))
+                                .text_color(rgb(0xFFFFFF))
+                                .text_size(px(10.))
+                                .child(summary),
+                        )
+                    })
+                    .when(show_play, |cover| {
+                        cover.child(self.render_play_button(ix, key, cx))
+                    }),
+            )
+            .child(
+                div()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .font_weight(FontWeight::BOLD)
+                    .child(album.album.clone()),
+            )
+            .child(
+                div()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .opacity(0.6)
+                    .child(album.artist.clone()),
+            )
+            .when_some(album.tracks.first().cloned(), |album, track_id| {
+                album.child(h_stack().children(album_stars(rating).map(|(stars, star)| {
+                    let track_id = track_id.clone();
+                    star.id(SharedString::from(format!("album-rating-{}-{}", ix, stars)))
+                        .on_click(cx.listener(move |this, _, cx| {
+                            cx.stop_propagation();
+                            this.rate_album(&track_id, stars, cx)
+                        }))
+                })))
+            })
+    }
+}
+
+impl Render for AlbumsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let albums = self.albums.clone();
+        let library = self.library.read(cx);
+        let ratings: Vec<_> = albums
+            .iter()
+            .map(|album| library.rating_of_album(album))
+            .collect();
+
+        v_stack()
+            .id("albums")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Albums"),
+            )
+            .when(albums.is_empty(), |view| {
+                view.child(div().opacity(0.6).child("No albums in the library yet."))
+            })
+            .child(
+                h_stack().flex_wrap().items_start().gap(px(16.)).children(
+                    albums
+                        .iter()
+                        .zip(ratings)
+                        .enumerate()
+                        .map(|(ix, (album, rating))| self.render_album(ix, album, rating, cx)),
+                ),
+            )
+    }
+}
+
+fn album_key(album: &Album) -> AlbumKey {
+    (album.artist.clone(), album.album.clone())
+}
+
+fn incomplete_albums(library: &Library) -> HashMap<AlbumKey, String> {
+    library
+        .incomplete_albums()
+        .into_iter()
+        .map(|album| {
+            let summary = album.summary();
+            ((album.artist, album.album), summary)
+        })
+        .collect()
+}
@@ -0,0 +1,95 @@
+use std::{
+    io,
+    process::{Child, Command, Stdio},
+};
+
+use crate::settings::AnnouncementSettings;
+
+/// Speaks each new song through the system's text-to-speech, holding the
+/// music down underneath until it's done.
+#[derive(Default)]
+pub struct Announcer {
+    speaking: Option<Child>,
+}
+
+impl Announcer {
+    /// Cuts off anything still being said for the last song.
+    pub fn announce(&mut self, text: &str, settings: &AnnouncementSettings) {
+        self.stop();
+        if text.trim().is_empty() {
+            return;
+        }
+        match speak(text, settings.voice.as_deref()) {
+            Ok(child) => self.speaking = Some(child),
+            Err(e) => eprintln!("Failed to announce the track: {}", e),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut child) = self.speaking.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+
+    pub fn is_speaking(&mut self) -> bool {
+        let finished = match &mut self.speaking {
+            Some(child) => !matches!(child.try_wait(), Ok(None)),
+            None => return false,
+        };
+        if finished {
+            self.speaking = None;
+        }
+        !finished
+    }
+}
+
+impl Drop for Announcer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn speak(text: &str, voice: Option<&str>) -> io::Result<Child> {
+    let mut command = Command::new("say");
+    if let Some(voice) = voice {
+        command.args(["-v", voice]);
+    }
+    command.arg(text).stdout(Stdio::null()).spawn()
+}
+
+// Passed through the environment so nothing in a title is read as script
+#[cfg(target_os = "windows")]
+fn speak(text: &str, voice: Option<&str>) -> io::Result<Child> {
+    let script = "Add-Type -AssemblyName System.Speech; \
+        $speech = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+        if ($env:GPUITUNES_VOICE) { $speech.SelectVoice($env:GPUITUNES_VOICE) }; \
+        $speech.Speak($env:GPUITUNES_ANNOUNCE)";
+    Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .env("GPUITUNES_ANNOUNCE", text)
+        .env("GPUITUNES_VOICE", voice.unwrap_or_default())
+        .stdout(Stdio::null())
+        .spawn()
+}
+
+// Speech Dispatcher where it's set up, otherwise eSpeak
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn speak(text: &str, voice: Option<&str>) -> io::Result<Child> {
+    let mut command = Command::new("spd-say");
+    command.arg("--wait");
+    if let Some(voice) = voice {
+        command.args(["--synthesis-voice", voice]);
+    }
+    match command.arg(text).stdout(Stdio::null()).spawn() {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mut command = Command::new("espeak-ng");
+            if let Some(voice) = voice {
+                command.args(["-v", voice]);
+            }
+            command.arg(text).stdout(Stdio::null()).spawn()
+        }
+        result => result,
+    }
+}
@@ -1,13 +1,109 @@
 #![allow(unused, dead_code)]
 
-use gpui::*;
-use library::{Library, NowPlaying};
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{
+    audio_files_in, date_for_timestamp, detect_key, embed_folder_artwork, export_embedded_artwork,
+    extract_archive, file_checksum, find_artwork, format_playback_time, format_size, index_letter,
+    ingest_purchase, is_audio_file, matches_filters, musicbrainz_album_url, musicbrainz_track_url,
+    today, AlbumSize, ArtworkEmbedOptions, ArtworkExportOptions, ArtworkStore, Audition,
+    ChangeSource, Column, ColumnAlignment, ColumnKind, CurrentTrack, DeepLink, ImportKind,
+    ImportedFile, Library, LibraryCommand, LibraryManifest, LibraryStore, MetadataField,
+    NowPlaying, Playlist, PlaylistIcon, PlaylistId, PurchaseWatcher, ShuffleMode, SmartCriteria,
+    SortDirection, Track, TrackFilter, TrackId, VersionKind, INDEX_LETTERS, MAX_RATING,
+    MIN_COLUMN_WIDTH,
+};
+use playback::{
+    DspChain, DynamicsSettings, EngineEvent, PlaybackEngine, StreamCache, StreamStatus,
+    SuspendDetector,
+};
+use remote::{RemoteCommand, RemoteNowPlaying, RemoteServer, RemoteSnapshot, RemoteTrack};
+use rhai::Array;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use crate::title_bar::TitleBar;
+use crate::{
+    activate_profile,
+    albums::{AlbumsEvent, AlbumsView},
+    announce::Announcer,
+    artist_info::ArtistInfos,
+    assets::Icon,
+    capture::{CaptureEvent, CaptureView},
+    cleanup::TagCleanupView,
+    completeness::{IncompleteAlbumsEvent, IncompleteAlbumsView},
+    crash,
+    custom_fields::{CustomFieldsEditor, CustomFieldsEvent},
+    diagnostics::DiagnosticsView,
+    duplicates::DuplicatesView,
+    effects::{Effects, EffectsView},
+    element::*,
+    get_info::{GetInfoEvent, GetInfoView},
+    import_rules::ImportRulesView,
+    imports::{confirm_roll_back, ImportsView},
+    media_controls::{MediaCommand, MediaControls},
+    moods::{MoodPicker, MoodPickerEvent, MoodsEvent, MoodsView},
+    new_releases::{set_following, NewReleases, NewReleasesView},
+    overlay::{OverlayFields, StreamOverlay},
+    paths,
+    platform::{client_side_decorations, FileDragSource},
+    playlist_history::PlaylistHistoryView,
+    power,
+    profile::{config_dir, data_dir, Profiles},
+    progress::{JobProgress, TaskbarProgress},
+    remote_settings::RemoteSettingsView,
+    scripting::{self, track_map, ScriptCommand, Scripts},
+    scrollbar::{ScrollbarState, SCROLLBAR_WIDTH},
+    search::{SearchEvent, SearchField},
+    session::{SavedSource, SavedTrack, Session},
+    settings::{
+        ImportSettings, OutputProfile, RadioStation, RemoteSettings, Settings, StartupSource,
+    },
+    share::{self, OpenedLinks},
+    storage::{relocate_files, transcode_files, StorageEvent, StorageReportView},
+    stream_cache::StreamCacheView,
+    title_bar::{
+        BalanceLeft, BalanceRight, HideVolumeOptions, Pause, Play, ResetBalance, Restart,
+        SetBalance, SetVolume, SkipNext, SkipPrev, TitleBar, ToggleLimiter, ToggleMute,
+        ToggleNightMode, TogglePlayback, ToggleVolumeOptions, VolumeDecrease, VolumeIncrease,
+    },
+    up_next::UpNextPanel,
+    verify::VerifyFilesView,
+    volume::{Volume, VolumeSlider},
+    widget::WidgetFeed,
+    wishlist::WishlistView,
+    AddLibraryFolder, AnalyzeKeys, CheckLibraryIntegrity, CleanUpArtwork, ClearUpNext,
+    CompareLibraryManifest, CopyAlbumLink, CopyLink, CopySortAsManualOrder, DeleteSelection,
+    EmbedFolderArtwork, EmptyRecentlyDeleted, ExportEmbeddedArtwork, ExportLibraryManifest,
+    ExportLibraryStatistics, ExportNowPlaying, FocusSearch, FullScreen, GetInfo, ImportArchive,
+    MarkAsPlayed, MarkAsUnplayed, Maximize, Minimize, NewPlaylist, NewProfile, PageDown, PageUp,
+    PlayFolder, PlaySelection, RecordFromInput, Redo, ReportLastCrash, SaveArtworkAs,
+    SaveUpNextAsPlaylist, ScrollToBottom, ScrollToTop, SelectAll, SelectNext, SelectPrevious,
+    ShareLink, ShowAlbums, ShowArtwork, ShowDiagnostics, ShowDuplicates, ShowEffects,
+    ShowImportRules, ShowImports, ShowIncompleteAlbums, ShowLibrary, ShowMoods, ShowNewReleases,
+    ShowRecentlyDeleted, ShowRemoteSettings, ShowStorageReport, ShowStreamCache, ShowTagCleanup,
+    ShowWishlist, ShuffleByAlbum, ShuffleByGrouping, ShuffleOff, ShuffleSongs, SwitchProfile,
+    TagMood, ToggleAnnouncements, ToggleAutoplay, ToggleOutputProfiles, TogglePrivateListening,
+    ToggleUpNext, Undo, UndoLastImport, VerifyLibraryFiles, WorkOffline,
+};
 
-const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+const DOWNLOADS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+// Quiet time after a change before the library is saved, so a scan or a
+// run of edits is written once
+const SERIALIZE_DELAY: Duration = Duration::from_secs(2);
+// How long a message stays in the status bar before the summary returns
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+// Going back any later than this restarts the track instead
+const SKIP_PREV_RESTART_SECS: i32 = 3;
+// Changes listed in a track's context menu, the full log is kept regardless
+const MAX_HISTORY_ITEMS: usize = 8;
+const ROW_HEIGHT: Pixels = px(16.);
 
 pub struct AppState {
     pending_update: Option<Task<()>>,
@@ -31,7 +127,8 @@ impl AppState {
     fn start_updates(&self, cx: &mut ModelContext<Self>) -> Task<()> {
         cx.spawn(|this, mut cx| async move {
             loop {
-                cx.background_executor().timer(UPDATE_INTERVAL).await;
+                let interval = power::update_interval(&cx);
+                cx.background_executor().timer(interval).await;
                 this.update(&mut cx, |this, cx| {
                     cx.emit(UpdateTriggered);
                 })
@@ -43,10 +140,54 @@ impl AppState {
 
 impl EventEmitter<UpdateTriggered> for AppState {}
 
+const PLAYLIST_EMOJI: &[&str] = &[
+    "🎸", "🎹", "🎷", "🎻", "🥁", "🎧", "🎤", "💿", "❤️", "⭐", "🔥", "🌙", "☀️", "🌊", "🚗", "🏃",
+    "☕", "🎉",
+];
+const PLAYLIST_SYMBOLS: &[Icon] = &[Icon::MusicNote, Icon::Gear, Icon::Radio, Icon::Podcast];
+
+/// The kinds of source the sidebar can list, each with its own icon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    Library,
+    Playlist,
+    SmartPlaylist,
+    Radio,
+    Podcasts,
+    RecentlyDeleted,
+    DailyMix,
+}
+
+impl SourceKind {
+    pub fn icon(&self) -> Icon {
+        match self {
+            SourceKind::Library => Icon::Library,
+            SourceKind::Playlist => Icon::MusicNote,
+            SourceKind::SmartPlaylist => Icon::Gear,
+            SourceKind::Radio => Icon::Radio,
+            SourceKind::Podcasts => Icon::Podcast,
+            SourceKind::RecentlyDeleted => Icon::Trash,
+            SourceKind::DailyMix => Icon::Play,
+        }
+    }
+}
+
 pub struct Sidebar {
     window: WeakView<AppWindow>,
     library: Model<Library>,
     now_playing: Model<NowPlaying>,
+    icon_menu: Option<PlaylistIconMenu>,
+    focus_handle: FocusHandle,
+    // The playlist being renamed in place, with the name typed so far
+    renaming: Option<(PlaylistId, String)>,
+    // Checked when the library changes rather than on every render
+    has_incomplete_albums: bool,
+    _subscriptions: Vec<Subscription>,
+}
+
+struct PlaylistIconMenu {
+    playlist_id: PlaylistId,
+    position: Point<Pixels>,
 }
 
 impl Sidebar {
@@ -54,26 +195,608 @@ impl Sidebar {
         window: WeakView<AppWindow>,
         library: Model<Library>,
         now_playing: Model<NowPlaying>,
+        cx: &mut ViewContext<Self>,
     ) -> Self {
+        let subscriptions = vec![
+            cx.observe(&library, |this, library, cx| {
+                this.has_incomplete_albums = !library.read(cx).incomplete_albums().is_empty();
+                cx.notify();
+            }),
+            cx.observe_global::<Settings>(|_, cx| cx.notify()),
+        ];
+
         Sidebar {
             window,
+            has_incomplete_albums: !library.read(cx).incomplete_albums().is_empty(),
             library,
             now_playing,
+            icon_menu: None,
+            focus_handle: cx.focus_handle(),
+            renaming: None,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    pub fn rename_playlist(&mut self, playlist_id: PlaylistId, cx: &mut ViewContext<Self>) {
+        let Some(name) = self
+            .library
+            .read(cx)
+            .playlist(&playlist_id)
+            .map(|playlist| playlist.name().to_string())
+        else {
+            return;
+        };
+        self.icon_menu = None;
+        self.renaming = Some((playlist_id, name));
+        cx.focus(&self.focus_handle);
+        cx.notify();
+    }
+
+    fn finish_renaming(&mut self, cx: &mut ViewContext<Self>) {
+        let Some((playlist_id, name)) = self.renaming.take() else {
+            return;
+        };
+        self.library.update(cx, |library, cx| {
+            library.rename_playlist(&playlist_id, &name);
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let Some((_, name)) = &mut self.renaming else {
+            return;
+        };
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.platform || keystroke.modifiers.control {
+            return;
+        }
+        match keystroke.key.as_str() {
+            "enter" => self.finish_renaming(cx),
+            "escape" => self.renaming = None,
+            "backspace" => {
+                name.pop();
+            }
+            _ => match &keystroke.key_char {
+                Some(text) if !text.chars().any(char::is_control) => name.push_str(text),
+                _ => return,
+            },
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    fn delete_playlist(&mut self, playlist_id: PlaylistId, cx: &mut ViewContext<Self>) {
+        self.icon_menu = None;
+        self.window
+            .update(cx, |window, cx| window.delete_playlist(playlist_id, cx))
+            .ok();
+        cx.notify();
+    }
+
+    fn show_source(&mut self, source: Source, cx: &mut ViewContext<Self>) {
+        self.window
+            .update(cx, |window, cx| window.show_source(source, cx))
+            .ok();
+    }
+
+    fn show_new_releases(&mut self, cx: &mut ViewContext<Self>) {
+        self.window
+            .update(cx, |window, cx| {
+                window.show_new_releases(&ShowNewReleases, cx)
+            })
+            .ok();
+    }
+
+    fn play_station(&mut self, station: RadioStation, cx: &mut ViewContext<Self>) {
+        self.window
+            .update(cx, |window, cx| window.play_station(station, cx))
+            .ok();
+    }
+
+    fn show_albums(&mut self, cx: &mut ViewContext<Self>) {
+        self.window
+            .update(cx, |window, cx| window.show_albums(&ShowAlbums, cx))
+            .ok();
+    }
+
+    fn show_incomplete_albums(&mut self, cx: &mut ViewContext<Self>) {
+        self.window
+            .update(cx, |window, cx| {
+                window.show_incomplete_albums(&ShowIncompleteAlbums, cx)
+            })
+            .ok();
+    }
+
+    fn show_playlist_history(&mut self, playlist_id: PlaylistId, cx: &mut ViewContext<Self>) {
+        self.icon_menu = None;
+        self.window
+            .update(cx, |window, cx| {
+                window.show_playlist_history(playlist_id, cx)
+            })
+            .ok();
+        cx.notify();
+    }
+
+    fn deploy_icon_menu(
+        &mut self,
+        playlist_id: PlaylistId,
+        position: Point<Pixels>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.icon_menu = Some(PlaylistIconMenu {
+            playlist_id,
+            position,
+        });
+        cx.notify();
+    }
+
+    fn dismiss_icon_menu(&mut self, cx: &mut ViewContext<Self>) {
+        self.icon_menu = None;
+        cx.notify();
+    }
+
+    fn set_playlist_icon(&mut self, icon: Option<PlaylistIcon>, cx: &mut ViewContext<Self>) {
+        let Some(playlist_id) = self.icon_menu.take().map(|menu| menu.playlist_id) else {
+            return;
+        };
+        self.library.update(cx, |library, cx| {
+            library.set_playlist_icon(&playlist_id, icon);
+            cx.notify();
+        });
+    }
+
+    fn render_source(
+        &self,
+        id: impl Into<ElementId>,
+        icon: AnyElement,
+        name: impl Into<SharedString>,
+        selected: bool,
+    ) -> Stateful<Div> {
+        h_stack()
+            .id(id)
+            .h(px(20.))
+            .px(px(10.))
+            .gap(px(6.))
+            .text_size(px(12.))
+            .cursor_pointer()
+            .when(selected, |source| {
+                source
+                    .border_t_1()
+                    .border_color(rgb(0x4F86D0))
+                    .bg(vertical_linear_gradient(rgb(0x6D9FE0), rgb(0x2E68C6)))
+                    .text_color(rgb(0xFFFFFF))
+                    .font_weight(FontWeight::BOLD)
+            })
+            .child(icon)
+            .child(div().flex_1().overflow_hidden().child(name.into()))
+    }
+
+    fn render_icon_menu(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let menu = self.icon_menu.as_ref()?;
+        let playlist_id = menu.playlist_id.clone();
+        let is_smart = self
+            .library
+            .read(cx)
+            .playlist(&playlist_id)
+            .map_or(true, |playlist| playlist.is_smart());
+
+        let emoji: Vec<_> = PLAYLIST_EMOJI
+            .iter()
+            .enumerate()
+            .map(|(ix, emoji)| {
+                div()
+                    .id(("playlist-emoji", ix))
+                    .size(px(22.))
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .rounded(px(3.))
+                    .cursor_pointer()
+                    .hover(|style| style.bg(rgb(0xDDE4EE)))
+                    .child(*emoji)
+                    .on_click(cx.listener(move |this, _, cx| {
+                        this.set_playlist_icon(Some(PlaylistIcon::Emoji(emoji.to_string())), cx)
+                    }))
+            })
+            .collect();
+        let symbols: Vec<_> = PLAYLIST_SYMBOLS
+            .iter()
+            .enumerate()
+            .map(|(ix, icon)| {
+                let name: &'static str = icon.into();
+                div()
+                    .id(("playlist-symbol", ix))
+                    .size(px(22.))
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .rounded(px(3.))
+                    .cursor_pointer()
+                    .hover(|style| style.bg(rgb(0xDDE4EE)))
+                    .child(small_icon(*icon))
+                    .on_click(cx.listener(move |this, _, cx| {
+                        this.set_playlist_icon(Some(PlaylistIcon::Symbol(name.to_string())), cx)
+                    }))
+            })
+            .collect();
+
+        Some(deferred(
+            anchored().position(menu.position).child(
+                v_stack()
+                    .occlude()
+                    .py(px(4.))
+                    .w(px(160.))
+                    .rounded(px(4.))
+                    .border_1()
+                    .border_color(rgb(0xA0A0A0))
+                    .bg(rgb(0xFFFFFF))
+                    .shadow_lg()
+                    .text_size(px(13.))
+                    .on_mouse_down_out(cx.listener(|this, _, cx| this.dismiss_icon_menu(cx)))
+                    .child(context_menu_label("Icon"))
+                    .child(h_stack().flex_wrap().px(px(6.)).children(emoji))
+                    .child(h_stack().flex_wrap().px(px(6.)).children(symbols))
+                    .child(context_menu_separator())
+                    .child(
+                        context_menu_item("default-playlist-icon", "Use Default Icon")
+                            .on_click(cx.listener(|this, _, cx| this.set_playlist_icon(None, cx))),
+                    )
+                    .when(!is_smart, |menu| {
+                        let playlist_id = playlist_id.clone();
+                        menu.child(context_menu_separator()).child(
+                            context_menu_item("playlist-history", "Version History…").on_click(
+                                cx.listener(move |this, _, cx| {
+                                    this.show_playlist_history(playlist_id.clone(), cx)
+                                }),
+                            ),
+                        )
+                    })
+                    .child(context_menu_separator())
+                    .child(context_menu_item("rename-playlist", "Rename").on_click({
+                        let playlist_id = playlist_id.clone();
+                        cx.listener(move |this, _, cx| {
+                            this.rename_playlist(playlist_id.clone(), cx)
+                        })
+                    }))
+                    .child(
+                        context_menu_item("delete-playlist", "Delete Playlist…").on_click(
+                            cx.listener(move |this, _, cx| {
+                                this.delete_playlist(playlist_id.clone(), cx)
+                            }),
+                        ),
+                    ),
+            ),
+        ))
+    }
+}
+
+fn section_header(name: &'static str) -> Div {
+    div()
+        .pt(px(10.))
+        .pb(px(2.))
+        .px(px(10.))
+        .text_size(px(11.))
+        .font_weight(FontWeight::BOLD)
+        .text_color(rgb(0x5E6878))
+        .child(name)
+}
+
+// A custom icon wins over the one for the kind of playlist. Symbols this
+// version doesn't know about fall back to it too.
+fn playlist_icon(playlist: &Playlist) -> AnyElement {
+    let kind = if playlist.is_smart() {
+        SourceKind::SmartPlaylist
+    } else {
+        SourceKind::Playlist
+    };
+    match playlist.icon() {
+        Some(PlaylistIcon::Emoji(emoji)) => div()
+            .w(px(14.))
+            .flex_none()
+            .text_size(px(12.))
+            .child(emoji.clone())
+            .into_any_element(),
+        Some(PlaylistIcon::Symbol(name)) => {
+            small_icon(name.parse().unwrap_or(kind.icon())).into_any_element()
         }
+        None => small_icon(kind.icon()).into_any_element(),
     }
 }
 
 impl Render for Sidebar {
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        div()
+        let selected = self
+            .window
+            .upgrade()
+            .and_then(|window| window.read(cx).sidebar_item(cx));
+        let is_selected = |source: Source| selected == Some(SidebarItem::Source(source));
+        let library = self.library.read(cx);
+        let has_deleted = !library.recently_deleted().is_empty();
+        let playlists: Vec<_> = library
+            .playlists()
+            .iter()
+            .enumerate()
+            .map(|(ix, playlist)| {
+                let show_id = playlist.id().clone();
+                let menu_id = playlist.id().clone();
+                if let Some((_, name)) = self
+                    .renaming
+                    .as_ref()
+                    .filter(|(renaming, _)| renaming == playlist.id())
+                {
+                    return h_stack()
+                        .id(("playlist", ix))
+                        .h(px(20.))
+                        .px(px(10.))
+                        .gap(px(6.))
+                        .text_size(px(12.))
+                        .child(playlist_icon(playlist))
+                        .child(
+                            div()
+                                .flex_1()
+                                .px(px(2.))
+                                .overflow_hidden()
+                                .border_1()
+                                .border_color(rgb(0x3D80DF))
+                                .bg(rgb(0xFFFFFF))
+                                .child(name.clone()),
+                        )
+                        .on_mouse_down_out(cx.listener(|this, _, cx| this.finish_renaming(cx)));
+                }
+                self.render_source(
+                    ("playlist", ix),
+                    playlist_icon(playlist),
+                    playlist.name(),
+                    is_selected(Source::Playlist(playlist.id().clone())),
+                )
+                // Double-clicking a playlist's name renames it
+                .on_click(cx.listener(move |this, event: &ClickEvent, cx| {
+                    if event.up.click_count == 2 {
+                        this.rename_playlist(show_id.clone(), cx)
+                    } else {
+                        this.show_source(Source::Playlist(show_id.clone()), cx)
+                    }
+                }))
+                .on_mouse_down(
+                    MouseButton::Right,
+                    cx.listener(move |this, event: &MouseDownEvent, cx| {
+                        this.deploy_icon_menu(menu_id.clone(), event.position, cx)
+                    }),
+                )
+            })
+            .collect();
+        let daily_mixes: Vec<_> = library
+            .daily_mixes()
+            .iter()
+            .enumerate()
+            .map(|(ix, mix)| {
+                self.render_source(
+                    ("daily-mix", ix),
+                    small_icon(SourceKind::DailyMix.icon()).into_any_element(),
+                    mix.name(),
+                    is_selected(Source::DailyMix(ix)),
+                )
+                .on_click(
+                    cx.listener(move |this, _, cx| this.show_source(Source::DailyMix(ix), cx)),
+                )
+            })
+            .collect();
+        let following = !cx
+            .global::<Settings>()
+            .new_releases
+            .followed_artists
+            .is_empty();
+        let stations: Vec<_> = cx
+            .global::<Settings>()
+            .radio
+            .stations
+            .iter()
+            .enumerate()
+            .map(|(ix, station)| {
+                let played = station.clone();
+                self.render_source(
+                    ("station", ix),
+                    small_icon(SourceKind::Radio.icon()).into_any_element(),
+                    station.name.clone(),
+                    false,
+                )
+                .on_click(cx.listener(move |this, _, cx| this.play_station(played.clone(), cx)))
+            })
+            .collect();
+
+        v_stack()
+            .id("sidebar")
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .flex_none()
+            .w(px(180.))
+            .h_full()
+            .py(px(4.))
+            .overflow_y_scroll()
+            .border_r_1()
+            .border_color(rgb(0x969696))
+            .bg(rgb(0xD1D7E2))
+            .child(section_header("LIBRARY"))
+            .child(
+                self.render_source(
+                    "library",
+                    small_icon(SourceKind::Library.icon()).into_any_element(),
+                    "Music",
+                    is_selected(Source::Library),
+                )
+                .on_click(cx.listener(|this, _, cx| this.show_source(Source::Library, cx))),
+            )
+            .child(
+                self.render_source(
+                    "albums",
+                    small_icon(Icon::Library).into_any_element(),
+                    "Albums",
+                    selected == Some(SidebarItem::Albums),
+                )
+                .on_click(cx.listener(|this, _, cx| this.show_albums(cx))),
+            )
+            .when(has_deleted, |sidebar| {
+                sidebar.child(
+                    self.render_source(
+                        "recently-deleted",
+                        small_icon(SourceKind::RecentlyDeleted.icon()).into_any_element(),
+                        "Recently Deleted",
+                        is_selected(Source::RecentlyDeleted),
+                    )
+                    .on_click(
+                        cx.listener(|this, _, cx| this.show_source(Source::RecentlyDeleted, cx)),
+                    ),
+                )
+            })
+            .when(self.has_incomplete_albums, |sidebar| {
+                sidebar.child(
+                    self.render_source(
+                        "incomplete-albums",
+                        small_icon(Icon::Library).into_any_element(),
+                        "Incomplete Albums",
+                        selected == Some(SidebarItem::IncompleteAlbums),
+                    )
+                    .on_click(cx.listener(|this, _, cx| this.show_incomplete_albums(cx))),
+                )
+            })
+            .when(!playlists.is_empty(), |sidebar| {
+                sidebar
+                    .child(section_header("PLAYLISTS"))
+                    .children(playlists)
+            })
+            .when(!stations.is_empty(), |sidebar| {
+                sidebar.child(section_header("RADIO")).children(stations)
+            })
+            .when(!daily_mixes.is_empty() || following, |sidebar| {
+                sidebar
+                    .child(section_header("MADE FOR YOU"))
+                    .children(daily_mixes)
+                    .when(following, |sidebar| {
+                        sidebar.child(
+                            self.render_source(
+                                "new-releases",
+                                small_icon(Icon::MusicNote).into_any_element(),
+                                "New Releases",
+                                selected == Some(SidebarItem::NewReleases),
+                            )
+                            .on_click(cx.listener(|this, _, cx| this.show_new_releases(cx))),
+                        )
+                    })
+            })
+            .children(self.render_icon_menu(cx))
     }
 }
 
+/// The row highlighted in the sidebar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SidebarItem {
+    Source(Source),
+    Albums,
+    NewReleases,
+    IncompleteAlbums,
+}
+
+/// What the track list is showing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Library,
+    Playlist(PlaylistId),
+    RecentlyDeleted,
+    // By position among today's mixes
+    DailyMix(usize),
+}
+
 struct LibraryView {
     window: WeakView<AppWindow>,
     library: Model<Library>,
     now_playing: Model<NowPlaying>,
+    audition: Model<Audition>,
+    artist_infos: Model<ArtistInfos>,
     focus_handle: FocusHandle,
+    source: Source,
+    // `None` and `ColumnKind::Index` both show the manual order
+    sort: Option<ColumnKind>,
+    sort_direction: SortDirection,
+    // Each visible track with its position in the manual order
+    rows: Vec<(usize, TrackId)>,
+    scroll_handle: UniformListScrollHandle,
+    scrollbar: ScrollbarState,
+    // Where each letter starts in `rows`, while sorted alphabetically
+    letter_index: Vec<(SharedString, usize)>,
+    // Pinned browser selections, kept when switching sources
+    filters: Vec<TrackFilter>,
+    search: Option<SmartCriteria>,
+    // Clicked rows, in the order they were clicked
+    selection: Vec<TrackId>,
+    // Where a shift-click range starts, the last row clicked without shift
+    selection_anchor: Option<TrackId>,
+    // The track marked with the speaker in the Playing column
+    playing: Option<TrackId>,
+    context_menu: Option<TrackContextMenu>,
+    mood_picker: Option<(View<MoodPicker>, Subscription)>,
+    custom_fields_editor: Option<(View<CustomFieldsEditor>, Subscription)>,
+    get_info: Option<(View<GetInfoView>, Subscription)>,
+    // Where the column headers were right-clicked
+    column_menu: Option<Point<Pixels>>,
+    column_resize: Option<ColumnResize>,
+    _subscriptions: Vec<Subscription>,
+}
+
+// A header's edge being dragged. The width is only previewed until it's
+// let go, so the library isn't saved on every move.
+struct ColumnResize {
+    kind: ColumnKind,
+    start_x: Pixels,
+    start_width: f32,
+    width: f32,
+}
+
+#[derive(Clone)]
+struct DraggedColumn {
+    // Among all the columns, hidden ones included
+    position: usize,
+    name: String,
+}
+
+impl Render for DraggedColumn {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        div()
+            .px(px(6.))
+            .py(px(1.))
+            .rounded(px(3.))
+            .bg(rgb(0xD5E1F2))
+            .border_1()
+            .border_color(rgb(0x7FA6DD))
+            .opacity(0.9)
+            .text_size(px(11.))
+            .child(self.name.clone())
+    }
+}
+
+struct TrackContextMenu {
+    track_id: TrackId,
+    position: Point<Pixels>,
+}
+
+#[derive(Clone)]
+struct DraggedTrack {
+    index: usize,
+    title: SharedString,
+}
+
+impl Render for DraggedTrack {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        div()
+            .px(px(6.))
+            .py(px(1.))
+            .rounded(px(3.))
+            .bg(rgb(0x3D80DF))
+            .opacity(0.8)
+            .text_color(rgb(0xFFFFFF))
+            .text_size(px(12.))
+            .child(self.title.clone())
+    }
 }
 
 impl LibraryView {
@@ -81,162 +804,5619 @@ impl LibraryView {
         window: WeakView<AppWindow>,
         library: Model<Library>,
         now_playing: Model<NowPlaying>,
+        audition: Model<Audition>,
+        artist_infos: Model<ArtistInfos>,
         cx: &mut ViewContext<Self>,
     ) -> Self {
         let focus_handle = cx.focus_handle();
+        let subscriptions = vec![
+            cx.observe(&library, |this, _, cx| this.update_rows(cx)),
+            cx.observe(&artist_infos, |_, _, cx| cx.notify()),
+            // Only a change of track redraws, not the time ticking along
+            cx.observe(&now_playing, |this, now_playing, cx| {
+                let playing = now_playing
+                    .read(cx)
+                    .current_track()
+                    .map(|current_track| current_track.track().id().clone());
+                if this.playing != playing {
+                    this.playing = playing;
+                    cx.notify();
+                }
+            }),
+            cx.observe_global::<Settings>(|_, cx| cx.notify()),
+        ];
+        let scroll_handle = UniformListScrollHandle::new();
+        let playing = now_playing
+            .read(cx)
+            .current_track()
+            .map(|current_track| current_track.track().id().clone());
 
-        LibraryView {
+        let mut this = LibraryView {
             window,
             library,
             now_playing,
+            audition,
+            artist_infos,
             focus_handle,
-        }
+            source: Source::Library,
+            sort: None,
+            sort_direction: SortDirection::Ascending,
+            rows: Vec::new(),
+            scrollbar: ScrollbarState::new(scroll_handle.clone()),
+            scroll_handle,
+            letter_index: Vec::new(),
+            filters: Vec::new(),
+            search: None,
+            selection: Vec::new(),
+            selection_anchor: None,
+            playing,
+            context_menu: None,
+            mood_picker: None,
+            custom_fields_editor: None,
+            get_info: None,
+            column_menu: None,
+            column_resize: None,
+            _subscriptions: subscriptions,
+        };
+        this.update_rows(cx);
+        this
     }
 
     pub fn focus_handle(&mut self) {
         self.focus_handle.clone();
     }
-}
 
-impl Render for LibraryView {
-    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        div()
+    fn track_ids<'a>(&self, library: &'a Library) -> Cow<'a, [TrackId]> {
+        match &self.source {
+            Source::Library => Cow::Borrowed(library.track_order()),
+            Source::Playlist(id) => match library.playlist(id) {
+                Some(playlist) if playlist.is_smart() => Cow::Owned(library.playlist_tracks(id)),
+                Some(playlist) => Cow::Borrowed(playlist.tracks()),
+                None => Cow::Borrowed(library.track_order()),
+            },
+            // Most recently deleted first
+            Source::RecentlyDeleted => library
+                .recently_deleted()
+                .iter()
+                .rev()
+                .map(|deleted| deleted.track().id().clone())
+                .collect(),
+            Source::DailyMix(ix) => Cow::Borrowed(
+                library
+                    .daily_mix(*ix)
+                    .map(|mix| mix.tracks())
+                    .unwrap_or_default(),
+            ),
+        }
     }
-}
 
-impl FocusableView for LibraryView {
-    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
-        self.focus_handle.clone()
+    fn is_manual_order(&self) -> bool {
+        matches!(self.sort, None | Some(ColumnKind::Index))
     }
-}
 
-struct StatusBar {
-    window: WeakView<AppWindow>,
-    library: Model<Library>,
-}
+    // Recently Deleted and the Daily Mixes have no order of their own to change
+    fn can_reorder(&self) -> bool {
+        self.is_manual_order() && matches!(self.source, Source::Library | Source::Playlist(_))
+    }
 
-impl StatusBar {
-    pub fn new(window: WeakView<AppWindow>, library: Model<Library>) -> Self {
-        StatusBar { window, library }
+    fn update_rows(&mut self, cx: &mut ViewContext<Self>) {
+        let library = self.library.read(cx);
+        let ids = self.track_ids(library);
+
+        self.rows = match &self.sort {
+            Some(kind) if self.source != Source::RecentlyDeleted && !self.is_manual_order() => {
+                let mut positions = HashMap::new();
+                for (ix, id) in ids.iter().enumerate().rev() {
+                    positions.insert(id, ix);
+                }
+                library
+                    .sorted(&ids, kind, self.sort_direction)
+                    .into_iter()
+                    .map(|id| (positions[&id], id))
+                    .collect()
+            }
+            _ => ids.iter().cloned().enumerate().collect(),
+        };
+        if !self.filters.is_empty() {
+            self.rows.retain(|(_, id)| {
+                library
+                    .track(id)
+                    .or_else(|| library.deleted_track(id))
+                    .is_some_and(|track| matches_filters(track, &self.filters))
+            });
+        }
+        if let Some(search) = &self.search {
+            self.rows.retain(|(_, id)| {
+                library
+                    .track(id)
+                    .or_else(|| library.deleted_track(id))
+                    .is_some_and(|track| search.matches(track))
+            });
+        }
+        self.letter_index = match &self.sort {
+            Some(kind) if kind.is_alphabetical() && self.source != Source::RecentlyDeleted => {
+                let ids: Vec<TrackId> = self.rows.iter().map(|(_, id)| id.clone()).collect();
+                library.letter_index(&ids, kind)
+            }
+            _ => Vec::new(),
+        };
+        cx.notify();
     }
-}
 
-impl Render for StatusBar {
-    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        div()
+    pub fn set_sort(&mut self, sort: Option<ColumnKind>, cx: &mut ViewContext<Self>) {
+        self.sort = sort;
+        self.sort_direction = SortDirection::Ascending;
+        self.update_rows(cx);
     }
-}
 
-pub struct AppWindow {
-    weak_self: WeakView<Self>,
-    sidebar: View<Sidebar>,
-    // For now is just the library, but could
-    // be a slot for any activated view
-    active_view: View<LibraryView>,
-    status_bar: View<StatusBar>,
-    library: Model<Library>,
-    now_playing: Model<NowPlaying>,
-    app_state: Arc<AppState>,
-    _subscriptions: Vec<Subscription>,
-    // _schedule_serialize: Option<Task<()>>,
-}
+    // Clicking the column already sorted by turns it around
+    fn click_column_header(&mut self, kind: ColumnKind, cx: &mut ViewContext<Self>) {
+        if kind == ColumnKind::Index {
+            self.set_sort(None, cx);
+        } else if self.sort.as_ref() == Some(&kind) {
+            self.sort_direction = self.sort_direction.reversed();
+            self.update_rows(cx);
+        } else {
+            self.set_sort(Some(kind), cx);
+        }
+    }
 
-impl AppWindow {
-    pub fn new(
-        library: Model<Library>,
-        app_state: Arc<AppState>,
-        cx: &mut ViewContext<Self>,
-    ) -> Self {
-        // Watch for changes to the library, update the ui when they occur
-        cx.observe(&library, |_, _, cx| cx.notify()).detach();
-        // cx.subscribe(&library, move |this, _, event, cx| {
-        //     match event {
-        //         Event::LibraryUpdated => {
-        //             // todo!(): something
-        //         }
-        //         _ => {}
-        //     }
-        // });
+    fn copy_sort_as_manual_order(&mut self, _: &CopySortAsManualOrder, cx: &mut ViewContext<Self>) {
+        let Some(kind) = self.sort.clone().filter(|_| !self.is_manual_order()) else {
+            return;
+        };
+        let source = self.source.clone();
+        let direction = self.sort_direction;
+        self.set_sort(None, cx);
+        self.library.update(cx, |library, cx| {
+            match &source {
+                Source::Library => {
+                    let before = library.track_order().to_vec();
+                    library.sort_by_column(kind, direction);
+                    library.record_library_order(before);
+                }
+                Source::Playlist(playlist) => {
+                    let before = playlist_contents(library, playlist);
+                    library.sort_playlist_by_column(playlist, &kind, direction);
+                    library.record_playlist_change(playlist, before);
+                }
+                Source::RecentlyDeleted | Source::DailyMix(_) => {}
+            }
+            cx.notify();
+        });
+    }
 
-        // Ensure _something_ always has focus
-        cx.on_focus_lost(|this, cx| {
-            let focus_handle = this.focus_handle(cx);
-            cx.focus(&focus_handle);
-        })
-        .detach();
+    fn move_track(&mut self, from: usize, to: usize, cx: &mut ViewContext<Self>) {
+        let source = self.source.clone();
+        self.library.update(cx, |library, cx| {
+            match &source {
+                Source::Library => {
+                    let before = library.track_order().to_vec();
+                    library.move_track(from, to);
+                    library.record_library_order(before);
+                }
+                Source::Playlist(playlist) => {
+                    let before = playlist_contents(library, playlist);
+                    library.move_in_playlist(playlist, from, to);
+                    library.record_playlist_change(playlist, before);
+                }
+                Source::RecentlyDeleted | Source::DailyMix(_) => {}
+            }
+            cx.notify();
+        });
+    }
 
-        let weak_handle = cx.view().downgrade();
+    pub fn show_source(&mut self, source: Source, cx: &mut ViewContext<Self>) {
+        self.source = source;
+        self.selection.clear();
+        self.update_rows(cx);
+    }
 
-        let app_state = Arc::new(AppState::new(cx));
+    pub fn set_search(&mut self, search: Option<SmartCriteria>, cx: &mut ViewContext<Self>) {
+        self.search = search;
+        self.update_rows(cx);
+    }
 
-        let now_playing = cx.new_model(|_| NowPlaying::default());
+    fn add_filter(&mut self, filter: TrackFilter, cx: &mut ViewContext<Self>) {
+        if !self.filters.contains(&filter) {
+            self.filters.push(filter);
+        }
+        self.dismiss_context_menu(cx);
+        self.update_rows(cx);
+        self.fetch_artist_info(false, cx);
+    }
+
+    fn remove_filter(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        if ix < self.filters.len() {
+            self.filters.remove(ix);
+            self.update_rows(cx);
+            self.fetch_artist_info(false, cx);
+        }
+    }
+
+    // Narrowed down to one artist, whose photo and bio go over their songs
+    fn shown_artist(&self) -> Option<&str> {
+        match self.filters.as_slice() {
+            [filter]
+                if matches!(
+                    filter.field,
+                    MetadataField::Artist | MetadataField::AlbumArtist
+                ) =>
+            {
+                Some(filter.value.as_str())
+            }
+            _ => None,
+        }
+    }
 
-        let sidebar = cx.new_view(|_cx| {
-            Sidebar::new(weak_handle.clone(), library.clone(), now_playing.clone())
+    fn fetch_artist_info(&mut self, refresh: bool, cx: &mut ViewContext<Self>) {
+        let Some(artist) = self.shown_artist().map(str::to_string) else {
+            return;
+        };
+        self.artist_infos.update(cx, |artist_infos, cx| {
+            artist_infos.fetch(&artist, refresh, cx)
         });
-        let library_view = cx.new_view(|cx| {
-            LibraryView::new(
-                weak_handle.clone(),
-                library.clone(),
-                now_playing.clone(),
-                cx,
+    }
+
+    fn clear_filters(&mut self, cx: &mut ViewContext<Self>) {
+        self.filters.clear();
+        self.update_rows(cx);
+    }
+
+    fn page_up(&mut self, _: &PageUp, cx: &mut ViewContext<Self>) {
+        self.scrollbar.page(-1., ROW_HEIGHT, cx);
+    }
+
+    fn page_down(&mut self, _: &PageDown, cx: &mut ViewContext<Self>) {
+        self.scrollbar.page(1., ROW_HEIGHT, cx);
+    }
+
+    fn scroll_to_top(&mut self, _: &ScrollToTop, cx: &mut ViewContext<Self>) {
+        self.scrollbar.animate_to(px(0.), cx);
+    }
+
+    fn scroll_to_bottom(&mut self, _: &ScrollToBottom, cx: &mut ViewContext<Self>) {
+        self.scrollbar.animate_to(Pixels::MAX, cx);
+    }
+
+    // Moves a single selected row up or down, starting from the top or
+    // bottom when nothing was selected
+    fn move_selection(&mut self, delta: isize, cx: &mut ViewContext<Self>) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let current = self
+            .selection_anchor
+            .as_ref()
+            .and_then(|anchor| self.rows.iter().position(|(_, id)| id == anchor));
+        let ix = match current {
+            Some(ix) => ix.saturating_add_signed(delta).min(self.rows.len() - 1),
+            None if delta > 0 => 0,
+            None => self.rows.len() - 1,
+        };
+        let (_, track_id) = self.rows[ix].clone();
+        self.selection_anchor = Some(track_id.clone());
+        self.scroll_handle.scroll_to_item(ix);
+        self.set_selection(vec![track_id], cx);
+    }
+
+    fn select_previous(&mut self, _: &SelectPrevious, cx: &mut ViewContext<Self>) {
+        self.move_selection(-1, cx);
+    }
+
+    fn select_next(&mut self, _: &SelectNext, cx: &mut ViewContext<Self>) {
+        self.move_selection(1, cx);
+    }
+
+    // Several selected tracks play on their own, in list order. A single
+    // one plays along with the rest of the list after it.
+    fn play_selection(&mut self, _: &PlaySelection, cx: &mut ViewContext<Self>) {
+        let selected: HashSet<&TrackId> = self.selection.iter().collect();
+        let Some(start) = self.rows.iter().position(|(_, id)| selected.contains(id)) else {
+            return;
+        };
+        let ids: Vec<TrackId> = self.rows[start..]
+            .iter()
+            .map(|(_, id)| id)
+            .filter(|id| selected.len() == 1 || selected.contains(id))
+            .cloned()
+            .collect();
+        self.play_ids(ids, cx);
+    }
+
+    // Double-clicking a row plays it and queues the rest of the list after.
+    // A part of a grouped work plays on through the work's later parts in
+    // order first, wherever they are in the list.
+    fn play_from(&mut self, track_id: &TrackId, cx: &mut ViewContext<Self>) {
+        let Some(start) = self.rows.iter().position(|(_, id)| id == track_id) else {
+            return;
+        };
+        let work = self.library.read(cx).work(track_id);
+        let parts = work
+            .iter()
+            .position(|id| id == track_id)
+            .map_or(&work[..0], |ix| &work[ix..]);
+        let ids: Vec<TrackId> = parts
+            .iter()
+            .cloned()
+            .chain(
+                self.rows[start..]
+                    .iter()
+                    .map(|(_, id)| id)
+                    .filter(|id| !parts.contains(id))
+                    .cloned(),
             )
+            .collect();
+        self.play_ids(ids, cx);
+    }
+
+    fn play_ids(&mut self, ids: Vec<TrackId>, cx: &mut ViewContext<Self>) {
+        let library = self.library.read(cx);
+        let tracks: Vec<Track> = ids
+            .iter()
+            .filter_map(|id| library.track(id).cloned())
+            .collect();
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.play_tracks(tracks);
+            cx.notify();
         });
-        let status_bar = cx.new_view(|_cx| StatusBar::new(weak_handle.clone(), library.clone()));
+    }
 
-        AppWindow {
-            weak_self: weak_handle,
-            sidebar,
-            active_view: library_view,
-            status_bar,
-            library,
-            now_playing,
-            app_state,
-            _subscriptions: Vec::new(),
+    fn jump_to_row(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        self.scrollbar.scroll_to(ROW_HEIGHT * ix as f32);
+        cx.notify();
+    }
+
+    fn end_scrollbar_drag(&mut self, cx: &mut ViewContext<Self>) {
+        if self.scrollbar.is_dragging() {
+            self.scrollbar.end_drag();
+            cx.notify();
         }
     }
-}
 
-impl AppWindow {
-    pub fn app_state(&self) -> &Arc<AppState> {
-        &self.app_state
+    // The letter of the top row, shown beside the thumb while dragging
+    // through an alphabetical sort
+    fn letter_at_top(&self, cx: &AppContext) -> Option<SharedString> {
+        let kind = self.sort.as_ref().filter(|kind| kind.is_alphabetical())?;
+        let ix = (self.scrollbar.offset() / ROW_HEIGHT).floor() as usize;
+        let (_, id) = self.rows.get(ix.min(self.rows.len().saturating_sub(1)))?;
+        let library = self.library.read(cx);
+        let track = library.track(id).or_else(|| library.deleted_track(id))?;
+        Some(index_letter(&track.sort_text(kind)?))
     }
 
-    pub fn library(&self) -> &Model<Library> {
-        &self.library
+    fn add_to_playlist(&mut self, playlist_id: PlaylistId, cx: &mut ViewContext<Self>) {
+        let tracks = self.popover_tracks();
+        self.library.update(cx, |library, cx| {
+            library.add_to_playlist(&playlist_id, &tracks);
+            cx.notify();
+        });
+        cx.notify();
     }
-}
 
-impl FocusableView for AppWindow {
-    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
-        self.active_view.focus_handle(cx)
+    fn show_in_playlist(
+        &mut self,
+        playlist_id: PlaylistId,
+        track_id: TrackId,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.show_source(Source::Playlist(playlist_id), cx);
+
+        if let Some(ix) = self.rows.iter().position(|(_, id)| id == &track_id) {
+            self.scroll_handle.scroll_to_item(ix);
+        }
+        self.dismiss_context_menu(cx);
     }
-}
 
-impl Render for AppWindow {
-    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        // This should be more like 4.0, but later macOS versions have
-        // a higher default window border radius
-        let window_rounding = px(10.0);
-        let state = cx.new_model(|cx| AppState::new(cx));
+    // The tracks an action applies to: the right-clicked track, along with
+    // the rest of the selection when it's part of it, or else the selection
+    fn target_tracks(&self) -> Vec<TrackId> {
+        match &self.context_menu {
+            Some(menu) if !self.selection.contains(&menu.track_id) => {
+                vec![menu.track_id.clone()]
+            }
+            _ => self.selection.clone(),
+        }
+    }
 
-        let title_bar = cx.new_view(|cx| TitleBar::new(state.clone(), cx));
+    // Platform-click adds to the selection or takes the track back out of
+    // it, shift-click selects every row from the last one clicked
+    fn select_track(
+        &mut self,
+        track_id: TrackId,
+        modifiers: Modifiers,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let mut selection = if modifiers.platform {
+            self.selection.clone()
+        } else {
+            Vec::new()
+        };
+        let range = self
+            .selection_anchor
+            .as_ref()
+            .filter(|_| modifiers.shift)
+            .and_then(|anchor| {
+                let start = self.rows.iter().position(|(_, id)| id == anchor)?;
+                let end = self.rows.iter().position(|(_, id)| id == &track_id)?;
+                Some(start.min(end)..=start.max(end))
+            });
+        if let Some(range) = range {
+            let mut selected: HashSet<TrackId> = selection.iter().cloned().collect();
+            for (_, id) in &self.rows[range] {
+                if selected.insert(id.clone()) {
+                    selection.push(id.clone());
+                }
+            }
+        } else {
+            if let Some(ix) = selection.iter().position(|id| id == &track_id) {
+                selection.remove(ix);
+            } else {
+                selection.push(track_id.clone());
+            }
+            self.selection_anchor = Some(track_id);
+        }
+        cx.focus(&self.focus_handle);
+        self.set_selection(selection, cx);
+    }
 
-        div()
-            .id("gpuitunes-window")
-            .track_focus(&self.focus_handle(cx))
-            .flex()
-            .flex_col()
-            .rounded(window_rounding)
-            // .relative()
-            .bg(rgb(0xFEFFFF))
-            .size_full()
-            .font_family("Helvetica")
-            .line_height(px(14.))
-            .text_color(rgb(0x0F1219))
-            .text_size(px(14.))
-            .child(title_bar)
+    fn select_all(&mut self, _: &SelectAll, cx: &mut ViewContext<Self>) {
+        let selection = self.rows.iter().map(|(_, id)| id.clone()).collect();
+        self.set_selection(selection, cx);
     }
+
+    fn set_selection(&mut self, selection: Vec<TrackId>, cx: &mut ViewContext<Self>) {
+        self.selection = selection.clone();
+        self.window
+            .update(cx, |window, cx| window.set_selection(selection, cx))
+            .ok();
+        cx.notify();
+    }
+
+    // The right-clicked track, along with the rest of the selection when it's
+    // part of it
+    fn popover_tracks(&mut self) -> Vec<TrackId> {
+        let tracks = self.target_tracks();
+        self.context_menu = None;
+        tracks
+    }
+
+    fn tag_mood(&mut self, _: &TagMood, cx: &mut ViewContext<Self>) {
+        let tracks = self.popover_tracks();
+        if tracks.is_empty() {
+            return;
+        }
+
+        let library = self.library.clone();
+        let picker = cx.new_view(|cx| MoodPicker::new(library, tracks, cx));
+        let subscription = cx.subscribe(&picker, |this, _, event, cx| match event {
+            MoodPickerEvent::Dismissed => {
+                this.mood_picker = None;
+                cx.focus(&this.focus_handle);
+                cx.notify();
+            }
+        });
+        cx.focus(&picker.read(cx).focus_handle());
+        self.mood_picker = Some((picker, subscription));
+        cx.notify();
+    }
+
+    fn analyze_keys(&mut self, cx: &mut ViewContext<Self>) {
+        let tracks = self.popover_tracks();
+        self.window
+            .update(cx, |window, cx| window.analyze_keys(tracks, cx))
+            .ok();
+        cx.notify();
+    }
+
+    fn edit_custom_fields(&mut self, cx: &mut ViewContext<Self>) {
+        let tracks = self.popover_tracks();
+        if tracks.is_empty() {
+            return;
+        }
+
+        let library = self.library.clone();
+        let editor = cx.new_view(|cx| CustomFieldsEditor::new(library, tracks, cx));
+        let subscription = cx.subscribe(&editor, |this, _, event, cx| match event {
+            CustomFieldsEvent::Dismissed => {
+                this.custom_fields_editor = None;
+                cx.focus(&this.focus_handle);
+                cx.notify();
+            }
+        });
+        cx.focus(&editor.read(cx).focus_handle());
+        self.custom_fields_editor = Some((editor, subscription));
+        cx.notify();
+    }
+
+    fn get_info(&mut self, _: &GetInfo, cx: &mut ViewContext<Self>) {
+        let tracks = self.popover_tracks();
+        if tracks.is_empty() {
+            return;
+        }
+
+        let library = self.library.clone();
+        let view = cx.new_view(|cx| GetInfoView::new(library, tracks, cx));
+        let subscription = cx.subscribe(&view, |this, _, event, cx| match event {
+            GetInfoEvent::Dismissed => {
+                this.get_info = None;
+                cx.focus(&this.focus_handle);
+                cx.notify();
+            }
+        });
+        cx.focus(&view.read(cx).focus_handle());
+        self.get_info = Some((view, subscription));
+        cx.notify();
+    }
+
+    fn deploy_context_menu(
+        &mut self,
+        track_id: TrackId,
+        position: Point<Pixels>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.context_menu = Some(TrackContextMenu { track_id, position });
+        cx.notify();
+    }
+
+    fn dismiss_context_menu(&mut self, cx: &mut ViewContext<Self>) {
+        self.context_menu = None;
+        cx.notify();
+    }
+
+    fn mark_as_played(&mut self, _: &MarkAsPlayed, cx: &mut ViewContext<Self>) {
+        let ids = self.target_tracks();
+        self.library.update(cx, |library, cx| {
+            library.mark_played(&ids);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    fn mark_as_unplayed(&mut self, _: &MarkAsUnplayed, cx: &mut ViewContext<Self>) {
+        let ids = self.target_tracks();
+        self.library.update(cx, |library, cx| {
+            library.mark_unplayed(&ids);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    // Deleting from the library keeps tracks in Recently Deleted, from a playlist just drops them
+    fn delete_tracks(&mut self, cx: &mut ViewContext<Self>) {
+        let ids = self.target_tracks();
+        let source = self.source.clone();
+        if ids.is_empty() || matches!(source, Source::RecentlyDeleted | Source::DailyMix(_)) {
+            return;
+        }
+        self.library.update(cx, |library, cx| {
+            match &source {
+                Source::Library => {
+                    library.delete_tracks(&ids);
+                    library.record_undo(LibraryCommand::DeleteTracks(ids.clone()));
+                }
+                Source::Playlist(playlist) => {
+                    let before = playlist_contents(library, playlist);
+                    library.remove_from_playlist(playlist, &ids);
+                    library.record_playlist_change(playlist, before);
+                }
+                Source::RecentlyDeleted | Source::DailyMix(_) => {}
+            }
+            cx.notify();
+        });
+        let deleted: HashSet<&TrackId> = ids.iter().collect();
+        let selection = self
+            .selection
+            .iter()
+            .filter(|id| !deleted.contains(id))
+            .cloned()
+            .collect();
+        self.set_selection(selection, cx);
+        self.dismiss_context_menu(cx);
+    }
+
+    fn delete_selection(&mut self, _: &DeleteSelection, cx: &mut ViewContext<Self>) {
+        self.delete_tracks(cx);
+    }
+
+    fn queue_tracks(&mut self, next: bool, cx: &mut ViewContext<Self>) {
+        let library = self.library.read(cx);
+        let tracks: Vec<Track> = self
+            .target_tracks()
+            .iter()
+            .filter_map(|id| library.track(id).cloned())
+            .collect();
+        self.now_playing.update(cx, |now_playing, cx| {
+            if next {
+                now_playing.play_next(tracks);
+            } else {
+                now_playing.queue_tracks(tracks);
+            }
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    fn set_following(&mut self, artist: String, follow: bool, cx: &mut ViewContext<Self>) {
+        self.window
+            .update(cx, |window, cx| {
+                set_following(&artist, follow, &window.new_releases, cx)
+            })
+            .ok();
+        self.dismiss_context_menu(cx);
+    }
+
+    // Keeps the tracks, or everything by their artists, out of the mixes
+    // from now on, and makes today's again without them
+    fn exclude_from_daily_mixes(&mut self, by_artist: bool, cx: &mut ViewContext<Self>) {
+        let ids = self.target_tracks();
+        let artists: Vec<String> = if by_artist {
+            let library = self.library.read(cx);
+            ids.iter()
+                .filter_map(|id| library.track(id))
+                .map(|track| track.artist().to_string())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        cx.update_global::<Settings, _>(|settings, _| {
+            let exclusions = &mut settings.daily_mix.exclusions;
+            if by_artist {
+                exclusions.artists.extend(artists);
+                exclusions.artists.sort();
+                exclusions.artists.dedup();
+            } else {
+                exclusions
+                    .tracks
+                    .extend(ids.into_iter().map(|id| id.into()));
+                exclusions.tracks.sort();
+                exclusions.tracks.dedup();
+            }
+        });
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+
+        let settings = cx.global::<Settings>().daily_mix.clone();
+        self.library.update(cx, |library, cx| {
+            library.generate_daily_mixes(&settings.day(), &settings.exclusions);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    fn restore_tracks(&mut self, cx: &mut ViewContext<Self>) {
+        let ids = self.target_tracks();
+        self.library.update(cx, |library, cx| {
+            library.restore_tracks(&ids);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    // Clicking the track's current rating again clears it
+    fn rate_track(&mut self, track_id: &TrackId, stars: u8, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            let rating = match library.track(track_id).and_then(Track::rating) {
+                Some(current) if current == stars => None,
+                _ => Some(stars),
+            };
+            library.set_rating(std::slice::from_ref(track_id), rating);
+            cx.notify();
+        });
+    }
+
+    fn rating_stars(&self, track: &Track, cx: &mut ViewContext<Self>) -> Vec<Stateful<Div>> {
+        let rating = track.rating().unwrap_or(0);
+        (1..=MAX_RATING)
+            .map(|stars| {
+                let track_id = track.id().clone();
+                star(stars <= rating, false)
+                    .id(("track-rating", stars as usize))
+                    .cursor_pointer()
+                    .on_click(cx.listener(move |this, _, cx| this.rate_track(&track_id, stars, cx)))
+            })
+            .collect()
+    }
+
+    fn rate_album(&mut self, track_id: &TrackId, stars: u8, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            library.rate_album(track_id, stars);
+            cx.notify();
+        });
+    }
+
+    fn revert_metadata_change(&mut self, change_id: u64, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            library.revert_metadata_change(change_id);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    fn link_versions(&mut self, track_ids: Vec<TrackId>, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            library.link_versions(&track_ids);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    fn unlink_versions(&mut self, cx: &mut ViewContext<Self>) {
+        let ids = self.target_tracks();
+        self.library.update(cx, |library, cx| {
+            library.unlink_versions(&ids);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    fn set_version_kind(&mut self, kind: VersionKind, cx: &mut ViewContext<Self>) {
+        let ids = self.target_tracks();
+        self.library.update(cx, |library, cx| {
+            library.set_version_kind(&ids, kind);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    // Versions can live anywhere in the library, so this falls back to it
+    // when the current source doesn't have the track
+    fn reveal_track(&mut self, track_id: TrackId, cx: &mut ViewContext<Self>) {
+        if !self.rows.iter().any(|(_, id)| id == &track_id) {
+            self.show_source(Source::Library, cx);
+        }
+        if let Some(ix) = self.rows.iter().position(|(_, id)| id == &track_id) {
+            self.scroll_handle.scroll_to_item(ix);
+        }
+        self.dismiss_context_menu(cx);
+    }
+
+    // Undoes everything an automated pass changed, across every track it touched
+    fn revert_metadata_batch(&mut self, batch: u64, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            library.revert_metadata_batch(batch);
+            cx.notify();
+        });
+        self.dismiss_context_menu(cx);
+    }
+
+    fn save_artwork_as(&mut self, cx: &mut ViewContext<Self>) {
+        let track = self
+            .context_menu
+            .as_ref()
+            .and_then(|menu| self.library.read(cx).track(&menu.track_id).cloned());
+        if let Some(track) = track {
+            save_artwork_as(&track, cx);
+        }
+        self.dismiss_context_menu(cx);
+    }
+
+    fn render_row(
+        &self,
+        ix: usize,
+        index: usize,
+        track: &Track,
+        columns: &[Column],
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let id: String = track.id().clone().into();
+        let track_id = track.id().clone();
+        let audition_track = track.clone();
+        // Its drive is unplugged, it's kept but can't play until it's back
+        let available = self.library.read(cx).is_available(track);
+
+        let selected = self.selection.contains(&track_id);
+        let clicked_track = track_id.clone();
+
+        let mut row = h_stack()
+            .id(ElementId::Name(id.into()))
+            .when(ix % 2 != 0, |row| row.bg(rgb(0xF0F0F0)))
+            .when(!available, |row| row.text_color(rgb(0xA0A0A0)))
+            .when(selected, |row| {
+                row.bg(rgb(0x3D80DF)).text_color(rgb(0xFFFFFF))
+            })
+            .min_w_full()
+            .h(ROW_HEIGHT)
+            .overflow_hidden()
+            .text_size(px(12.))
+            // Option-click pre-listens on the audition output, leaving main playback alone
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, event: &MouseDownEvent, cx| {
+                    if event.modifiers.alt {
+                        let track = audition_track.clone();
+                        this.audition.update(cx, |audition, cx| {
+                            audition.start(track);
+                            cx.notify();
+                        });
+                    } else {
+                        this.select_track(clicked_track.clone(), event.modifiers, cx);
+                        let modified = event.modifiers.platform || event.modifiers.shift;
+                        if event.click_count == 2 && !modified {
+                            this.play_from(&clicked_track, cx);
+                        }
+                    }
+                }),
+            )
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(move |this, event: &MouseDownEvent, cx| {
+                    this.deploy_context_menu(track_id.clone(), event.position, cx);
+                }),
+            );
+
+        // Rows can only be dragged into place while the manual order is showing
+        if self.can_reorder() {
+            let dragged = DraggedTrack {
+                index,
+                title: track.title(),
+            };
+            row = row
+                .on_drag(dragged, |dragged, _, cx| cx.new_view(|_| dragged.clone()))
+                .drag_over::<DraggedTrack>(|style, _, _| style.bg(rgb(0xC5D9F5)))
+                .on_drop(cx.listener(move |this, dragged: &DraggedTrack, cx| {
+                    this.move_track(dragged.index, index, cx)
+                }));
+        }
+
+        let date_format = cx.global::<Settings>().display.date_format;
+        for column in columns.iter().filter(|c| c.enabled()) {
+            let cell = div()
+                .flex()
+                .flex_none()
+                .items_center()
+                .overflow_hidden()
+                .w(px(column.width()))
+                .h_full()
+                .px(px(3.))
+                .border_r_1()
+                .border_color(rgb(0xD9D9D9))
+                .map(|cell| match column.alignment() {
+                    ColumnAlignment::Left => cell,
+                    ColumnAlignment::Center => cell.justify_center(),
+                    ColumnAlignment::Right => cell.justify_end(),
+                })
+                .when(column.kind().is_numeric(), |cell| {
+                    cell.font(tabular_figures("Helvetica"))
+                });
+
+            row = row.child(match column.kind() {
+                ColumnKind::Index => cell.child(format!("{}", index + 1)),
+                ColumnKind::Playing if self.playing.as_ref() == Some(track.id()) => cell.child(
+                    small_icon(Icon::VolumeHigh)
+                        .size(px(12.))
+                        .when(selected, |icon| icon.text_color(rgb(0xFFFFFF))),
+                ),
+                ColumnKind::Playing => cell.when(track.is_unplayed(), |cell| {
+                    cell.child(circle(px(7.)).bg(rgb(0x3D80DF)))
+                }),
+                ColumnKind::Title => cell.child(track.title()),
+                ColumnKind::Artist => cell.child(track.artist()),
+                ColumnKind::Album => cell.child(track.album()),
+                ColumnKind::Duration => cell.child(format_playback_time(track.duration())),
+                ColumnKind::TrackNumber => cell.child(format!("{}", track.track_number())),
+                ColumnKind::Kind => cell.child(track.kind().to_string()),
+                ColumnKind::DateAdded => cell.child(date_format.format(track.date_added())),
+                ColumnKind::Plays => cell.child(format!("{}", track.plays())),
+                ColumnKind::Rating => cell.children(self.rating_stars(track, cx)),
+                ColumnKind::Size => cell.child(format_size(track.size())),
+                ColumnKind::OriginalReleaseDate => cell.children(
+                    track
+                        .original_release_date()
+                        .or_else(|| track.release_date())
+                        .map(|date| date_format.format(date))
+                        .or_else(|| track.original_year().map(|year| year.to_string())),
+                ),
+                ColumnKind::Language => cell.children(track.language().map(str::to_string)),
+                ColumnKind::Key => cell.children(track.key().map(|key| key.camelot())),
+                ColumnKind::Custom(name) => {
+                    cell.children(track.custom_field(name).map(str::to_string))
+                }
+            });
+        }
+
+        row
+    }
+
+    // Aqua-style, with a thumb sized to how much of the list is showing
+    fn render_scrollbar(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let (top, height) = self.scrollbar.thumb()?;
+        let letter = self
+            .scrollbar
+            .is_dragging()
+            .then(|| self.letter_at_top(cx))
+            .flatten();
+
+        Some(
+            div()
+                .id("library-scrollbar")
+                .absolute()
+                .top_0()
+                .right_0()
+                .bottom_0()
+                .w(SCROLLBAR_WIDTH)
+                .border_l_1()
+                .border_color(rgb(0xC8C8C8))
+                .bg(rgb(0xEEEEEE))
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|this, event: &MouseDownEvent, cx| {
+                        this.scrollbar.mouse_down(event.position, cx);
+                        cx.stop_propagation();
+                        cx.notify();
+                    }),
+                )
+                .child(
+                    div()
+                        .absolute()
+                        .top(top)
+                        .left(px(2.))
+                        .right(px(2.))
+                        .h(height)
+                        .rounded_full()
+                        .border_1()
+                        .border_color(rgb(0x2A5DAB))
+                        .bg(vertical_linear_gradient(rgb(0x9CC3F5), rgb(0x3D80DF))),
+                )
+                .children(letter.map(|letter| {
+                    div()
+                        .absolute()
+                        .top(top + height / 2. - px(16.))
+                        .right(SCROLLBAR_WIDTH + px(6.))
+                        .size(px(32.))
+                        .flex()
+                        .items_center()
+                        .justify_center()
+                        .rounded(px(6.))
+                        .bg(rgb(0x3D80DF))
+                        .opacity(0.9)
+                        .text_color(rgb(0xFFFFFF))
+                        .text_size(px(18.))
+                        .font_weight(FontWeight::BOLD)
+                        .child(letter)
+                })),
+        )
+    }
+
+    fn render_filter_bar(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        if self.filters.is_empty() {
+            return None;
+        }
+
+        let chips: Vec<_> = self
+            .filters
+            .iter()
+            .enumerate()
+            .map(|(ix, filter)| {
+                h_stack()
+                    .flex_none()
+                    .h(px(18.))
+                    .pl(px(8.))
+                    .pr(px(4.))
+                    .gap(px(4.))
+                    .rounded_full()
+                    .bg(rgb(0xC5D9F5))
+                    .border_1()
+                    .border_color(rgb(0x7FA6DD))
+                    .child(filter.label())
+                    .child(
+                        div()
+                            .id(("remove-filter", ix))
+                            .cursor_pointer()
+                            .child(small_icon(Icon::XCircle).text_color(rgb(0x5A7FB5)))
+                            .on_click(cx.listener(move |this, _, cx| this.remove_filter(ix, cx))),
+                    )
+            })
+            .collect();
+
+        Some(
+            h_stack()
+                .flex_none()
+                .flex_wrap()
+                .gap(px(4.))
+                .px(px(6.))
+                .py(px(3.))
+                .border_b_1()
+                .border_color(rgb(0xC0C0C0))
+                .bg(rgb(0xF0F0F0))
+                .text_size(px(11.))
+                .children(chips)
+                .child(
+                    div()
+                        .id("clear-filters")
+                        .ml(px(4.))
+                        .cursor_pointer()
+                        .text_color(rgb(0x3D80DF))
+                        .child("Clear")
+                        .on_click(cx.listener(|this, _, cx| this.clear_filters(cx))),
+                ),
+        )
+    }
+
+    fn render_artist_header(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let artist = self.shown_artist()?;
+        let artist_infos = self.artist_infos.read(cx);
+        let info = artist_infos.info(artist).cloned();
+        let fetching = artist_infos.is_fetching(artist);
+        let offline = cx.global::<Settings>().network.offline;
+        let source_url = info.as_ref().and_then(|info| info.source_url.clone());
+
+        // Their albums in the order they're listed, each rated from here
+        let library = self.library.read(cx);
+        let mut albums: Vec<(TrackId, SharedString)> = Vec::new();
+        for (_, id) in &self.rows {
+            let Some(track) = library.track(id) else {
+                continue;
+            };
+            let listed = albums.iter().any(|(first, _)| {
+                library
+                    .track(first)
+                    .is_some_and(|first| first.is_same_album(track))
+            });
+            if !track.album().is_empty() && !listed {
+                albums.push((id.clone(), track.album()));
+            }
+        }
+        let albums: Vec<_> = albums
+            .into_iter()
+            .map(|(id, album)| {
+                let rating = library.album_rating(&id);
+                (id, album, rating)
+            })
+            .collect();
+
+        Some(
+            h_stack()
+                .flex_none()
+                .items_start()
+                .gap(px(10.))
+                .px(px(10.))
+                .py(px(8.))
+                .border_b_1()
+                .border_color(rgb(0xC0C0C0))
+                .bg(vertical_linear_gradient(rgb(0xFAFAFA), rgb(0xE8E8E8)))
+                .text_size(px(11.))
+                .children(
+                    info.as_ref()
+                        .and_then(|info| info.image.clone())
+                        .map(|image| {
+                            img(image)
+                                .flex_none()
+                                .size(px(64.))
+                                .rounded(px(4.))
+                                .object_fit(ObjectFit::Cover)
+                        }),
+                )
+                .child(
+                    v_stack()
+                        .flex_1()
+                        .gap(px(2.))
+                        .overflow_hidden()
+                        .child(
+                            div()
+                                .text_size(px(16.))
+                                .font_weight(FontWeight::BOLD)
+                                .child(artist.to_string()),
+                        )
+                        .children(
+                            info.as_ref()
+                                .and_then(|info| info.bio.clone())
+                                .map(|bio| div().max_h(px(44.)).overflow_hidden().child(bio)),
+                        )
+                        .when(!albums.is_empty(), |header| {
+                            header.child(
+                                h_stack().flex_wrap().gap_x(px(12.)).children(
+                                    albums.into_iter().enumerate().map(
+                                        |(ix, (id, album, rating))| {
+                                            h_stack().gap(px(4.)).child(album).children(
+                                                album_stars(rating).map(|(stars, star)| {
+                                                    let id = id.clone();
+                                                    star.id(SharedString::from(format!(
+                                                        "artist-album-rating-{}-{}",
+                                                        ix, stars
+                                                    )))
+                                                    .cursor_pointer()
+                                                    .on_click(cx.listener(move |this, _, cx| {
+                                                        this.rate_album(&id, stars, cx)
+                                                    }))
+                                                }),
+                                            )
+                                        },
+                                    ),
+                                ),
+                            )
+                        })
+                        .child(
+                            h_stack()
+                                .gap(px(10.))
+                                .opacity(0.8)
+                                .when_some(
+                                    info.as_ref()
+                                        .and_then(|info| info.attribution())
+                                        .zip(source_url),
+                                    |links, (attribution, url)| {
+                                        links.child(
+                                            div()
+                                                .id("artist-info-source")
+                                                .cursor_pointer()
+                                                .text_color(rgb(0x3D80DF))
+                                                .child(attribution)
+                                                .on_click(move |_, cx| cx.open_url(&url)),
+                                        )
+                                    },
+                                )
+                                .map(|links| {
+                                    if fetching {
+                                        links.child(div().opacity(0.6).child("Looking up…"))
+                                    } else if offline {
+                                        links.child(div().opacity(0.6).child("Working offline"))
+                                    } else {
+                                        let label =
+                                            if info.is_some() { "Refresh" } else { "Look Up" };
+                                        links.child(
+                                            div()
+                                                .id("refresh-artist-info")
+                                                .cursor_pointer()
+                                                .text_color(rgb(0x3D80DF))
+                                                .child(label)
+                                                .on_click(cx.listener(|this, _, cx| {
+                                                    this.fetch_artist_info(true, cx)
+                                                })),
+                                        )
+                                    }
+                                }),
+                        ),
+                ),
+        )
+    }
+
+    // Columns as they're drawn, with any resize in progress
+    fn columns(&self, cx: &AppContext) -> Vec<Column> {
+        let mut columns = self.library.read(cx).columns().to_vec();
+        if let Some(resize) = &self.column_resize {
+            if let Some(column) = columns.iter_mut().find(|c| c.kind() == &resize.kind) {
+                column.set_width(Some(resize.width));
+            }
+        }
+        columns
+    }
+
+    fn start_column_resize(&mut self, column: &Column, x: Pixels, cx: &mut ViewContext<Self>) {
+        self.column_resize = Some(ColumnResize {
+            kind: column.kind().clone(),
+            start_x: x,
+            start_width: column.width(),
+            width: column.width(),
+        });
+        cx.notify();
+    }
+
+    fn end_column_resize(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(resize) = self.column_resize.take() else {
+            return;
+        };
+        self.library.update(cx, |library, cx| {
+            library.set_column_width(&resize.kind, resize.width);
+            cx.notify();
+        });
+    }
+
+    fn move_column(&mut self, from: usize, to: usize, cx: &mut ViewContext<Self>) {
+        if from == to {
+            return;
+        }
+        self.library.update(cx, |library, cx| {
+            library.move_column(from, to);
+            cx.notify();
+        });
+    }
+
+    // Hiding the column the list is sorted by goes back to the manual order
+    fn toggle_column(&mut self, kind: ColumnKind, cx: &mut ViewContext<Self>) {
+        self.column_menu = None;
+        let enabled = self.library.update(cx, |library, cx| {
+            let enabled = library
+                .columns()
+                .iter()
+                .any(|column| column.kind() == &kind && column.enabled());
+            library.set_column_enabled(&kind, !enabled);
+            cx.notify();
+            enabled
+        });
+        if enabled && self.sort.as_ref() == Some(&kind) {
+            self.set_sort(None, cx);
+        }
+    }
+
+    fn reset_columns(&mut self, cx: &mut ViewContext<Self>) {
+        self.column_menu = None;
+        self.library.update(cx, |library, cx| {
+            library.reset_columns();
+            cx.notify();
+        });
+        let hidden = self.sort.as_ref().is_some_and(|kind| {
+            !self
+                .library
+                .read(cx)
+                .columns()
+                .iter()
+                .any(|column| column.kind() == kind && column.enabled())
+        });
+        if hidden {
+            self.set_sort(None, cx);
+        }
+    }
+
+    fn render_column_menu(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let position = self.column_menu?;
+        // The order number and now-playing columns are always there
+        let items: Vec<_> = self
+            .library
+            .read(cx)
+            .columns()
+            .iter()
+            .filter(|column| !matches!(column.kind(), ColumnKind::Index | ColumnKind::Playing))
+            .enumerate()
+            .map(|(ix, column)| {
+                let kind = column.kind().clone();
+                let label = if column.enabled() {
+                    format!("✓ {}", column.name())
+                } else {
+                    column.name()
+                };
+                if kind == ColumnKind::Title {
+                    return context_menu_label(label).pl(px(28.)).into_any_element();
+                }
+                context_menu_item(("toggle-column", ix), label)
+                    .pl(px(28.))
+                    .on_click(cx.listener(move |this, _, cx| this.toggle_column(kind.clone(), cx)))
+                    .into_any_element()
+            })
+            .collect();
+
+        Some(deferred(
+            anchored().position(position).child(
+                v_stack()
+                    .occlude()
+                    .py(px(4.))
+                    .min_w(px(160.))
+                    .rounded(px(4.))
+                    .border_1()
+                    .border_color(rgb(0xA0A0A0))
+                    .bg(rgb(0xFFFFFF))
+                    .shadow_lg()
+                    .text_size(px(13.))
+                    .on_mouse_down_out(cx.listener(|this, _, cx| {
+                        this.column_menu = None;
+                        cx.notify();
+                    }))
+                    .child(context_menu_label("Show Columns"))
+                    .children(items)
+                    .child(context_menu_separator())
+                    .child(
+                        context_menu_item("reset-columns", "Restore Default Columns")
+                            .on_click(cx.listener(|this, _, cx| this.reset_columns(cx))),
+                    ),
+            ),
+        ))
+    }
+
+    fn render_column_headers(
+        &self,
+        columns: &[Column],
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let sorted_by = match &self.sort {
+            Some(kind) if !self.is_manual_order() => kind.clone(),
+            _ => ColumnKind::Index,
+        };
+
+        h_stack()
+            .flex_none()
+            .min_w_full()
+            .h(px(18.))
+            .overflow_hidden()
+            .border_b_1()
+            .border_color(rgb(0xC0C0C0))
+            .bg(rgb(0xF4F4F4))
+            .text_size(px(11.))
+            .on_mouse_down(
+                MouseButton::Right,
+                cx.listener(|this, event: &MouseDownEvent, cx| {
+                    this.column_menu = Some(event.position);
+                    cx.notify();
+                }),
+            )
+            .children(columns.iter().enumerate().filter(|(_, c)| c.enabled()).map(
+                |(position, column)| {
+                    let kind = column.kind().clone();
+                    let sorted = kind == sorted_by;
+                    let triangle = match self.sort_direction {
+                        SortDirection::Ascending => "▲",
+                        SortDirection::Descending => "▼",
+                    };
+                    let dragged = DraggedColumn {
+                        position,
+                        name: column.name(),
+                    };
+                    let resized = column.clone();
+
+                    h_stack()
+                        .id(("column-header", position))
+                        .relative()
+                        .flex_none()
+                        .w(px(column.width()))
+                        .h_full()
+                        .px(px(3.))
+                        .gap(px(2.))
+                        .overflow_hidden()
+                        .border_r_1()
+                        .border_color(rgb(0xD9D9D9))
+                        .cursor_pointer()
+                        .when(sorted, |header| {
+                            header.bg(rgb(0xD5E1F2)).font_weight(FontWeight::BOLD)
+                        })
+                        .map(|header| match column.alignment() {
+                            ColumnAlignment::Left => header,
+                            ColumnAlignment::Center => header.justify_center(),
+                            ColumnAlignment::Right => header.justify_end(),
+                        })
+                        .child(div().overflow_hidden().child(column.name()))
+                        // The manual order only goes one way
+                        .when(sorted && kind != ColumnKind::Index, |header| {
+                            header.child(div().flex_none().text_size(px(7.)).child(triangle))
+                        })
+                        .on_click(cx.listener(move |this, _, cx| {
+                            this.click_column_header(kind.clone(), cx)
+                        }))
+                        .on_drag(dragged, |dragged, _, cx| cx.new_view(|_| dragged.clone()))
+                        .drag_over::<DraggedColumn>(|style, _, _| style.bg(rgb(0xC5D9F5)))
+                        .on_drop(cx.listener(move |this, dragged: &DraggedColumn, cx| {
+                            this.move_column(dragged.position, position, cx)
+                        }))
+                        // Kept from starting a drag or a click on the header
+                        .child(
+                            div()
+                                .absolute()
+                                .right_0()
+                                .top_0()
+                                .w(px(5.))
+                                .h_full()
+                                .cursor(CursorStyle::ResizeLeftRight)
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(move |this, event: &MouseDownEvent, cx| {
+                                        cx.stop_propagation();
+                                        this.start_column_resize(&resized, event.position.x, cx)
+                                    }),
+                                ),
+                        )
+                },
+            ))
+    }
+
+    // A–Z down the right edge, plus any other scripts the names are in.
+    // Letters nothing is filed under jump on to the next one that is.
+    fn render_index_strip(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        if self.letter_index.is_empty() {
+            return None;
+        }
+
+        let letters: Vec<(SharedString, Option<usize>)> = INDEX_LETTERS
+            .iter()
+            .map(|letter| SharedString::from(*letter))
+            .chain(
+                self.letter_index
+                    .iter()
+                    .map(|(letter, _)| letter.clone())
+                    .filter(|letter| !INDEX_LETTERS.contains(&letter.as_ref())),
+            )
+            .map(|letter| {
+                let row = self
+                    .letter_index
+                    .iter()
+                    .find(|(other, _)| *other == letter)
+                    .map(|(_, row)| *row);
+                (letter, row)
+            })
+            .collect();
+        let mut targets = vec![None; letters.len()];
+        let mut next = None;
+        for (ix, (_, row)) in letters.iter().enumerate().rev() {
+            next = row.or(next);
+            targets[ix] = next;
+        }
+
+        let items: Vec<_> = letters
+            .into_iter()
+            .zip(targets)
+            .enumerate()
+            .map(|(ix, ((letter, row), target))| {
+                div()
+                    .id(("index-letter", ix))
+                    .w_full()
+                    .flex()
+                    .justify_center()
+                    .when(row.is_none(), |item| item.text_color(rgb(0xB0B0B0)))
+                    .when_some(target, |item, target| {
+                        item.cursor_pointer()
+                            .on_click(cx.listener(move |this, _, cx| this.jump_to_row(target, cx)))
+                    })
+                    .child(letter)
+            })
+            .collect();
+
+        Some(
+            v_stack()
+                .absolute()
+                .top_0()
+                .bottom_0()
+                .right(SCROLLBAR_WIDTH)
+                .w(px(14.))
+                .py(px(2.))
+                .justify_between()
+                .bg(rgb(0xFFFFFF))
+                .opacity(0.9)
+                .text_size(px(9.))
+                .font_weight(FontWeight::BOLD)
+                .text_color(rgb(0x3D80DF))
+                .children(items),
+        )
+    }
+
+    fn render_context_menu(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        if self.source == Source::RecentlyDeleted {
+            return None;
+        }
+
+        let menu = self.context_menu.as_ref()?;
+        let library = self.library.read(cx);
+        let track = library.track(&menu.track_id);
+        let is_unplayed = track.is_some_and(|track| track.is_unplayed());
+        let has_artwork = track.is_some_and(|track| track.artwork().is_some());
+        let delete_label = match (&self.source, self.target_tracks().len()) {
+            (Source::Playlist(_), 1) => "Remove from Playlist".to_string(),
+            (Source::Playlist(_), count) => format!("Remove {} Songs from Playlist", count),
+            (_, 1) => "Delete from Library".to_string(),
+            (_, count) => format!("Delete {} Songs from Library", count),
+        };
+        let is_daily_mix = matches!(self.source, Source::DailyMix(_));
+        let artist = track
+            .map(|track| track.artist().to_string())
+            .unwrap_or_default();
+        let following = cx
+            .global::<Settings>()
+            .new_releases
+            .followed_artists
+            .iter()
+            .any(|followed| followed.eq_ignore_ascii_case(&artist));
+        let rating_stars: Vec<_> = album_stars(library.album_rating(&menu.track_id))
+            .map(|(stars, star)| {
+                let track_id = menu.track_id.clone();
+                star.id(("album-rating", stars as usize))
+                    .cursor_pointer()
+                    .on_click(cx.listener(move |this, _, cx| this.rate_album(&track_id, stars, cx)))
+            })
+            .collect();
+        let playlists: Vec<(PlaylistId, SharedString)> = library
+            .playlists_containing(&menu.track_id)
+            .into_iter()
+            .map(|playlist| (playlist.id().clone(), playlist.name()))
+            .collect();
+
+        // Smart playlists pick their own tracks
+        let add_items: Vec<_> =
+            library
+                .playlists()
+                .iter()
+                .filter(|playlist| {
+                    !playlist.is_smart() && self.source != Source::Playlist(playlist.id().clone())
+                })
+                .enumerate()
+                .map(|(ix, playlist)| {
+                    let playlist_id = playlist.id().clone();
+                    context_menu_item(("add-to-playlist", ix), playlist.name())
+                        .pl(px(28.))
+                        .on_click(cx.listener(move |this, _, cx| {
+                            this.add_to_playlist(playlist_id.clone(), cx)
+                        }))
+                })
+                .collect();
+
+        let playlist_items: Vec<_> = playlists
+            .into_iter()
+            .enumerate()
+            .map(|(ix, (playlist_id, name))| {
+                let track_id = menu.track_id.clone();
+                context_menu_item(("show-in-playlist", ix), name)
+                    .pl(px(28.))
+                    .on_click(cx.listener(move |this, _, cx| {
+                        this.show_in_playlist(playlist_id.clone(), track_id.clone(), cx)
+                    }))
+            })
+            .collect();
+
+        let filter_items: Vec<_> = [
+            MetadataField::Artist,
+            MetadataField::Album,
+            MetadataField::Year,
+            MetadataField::Decade,
+            MetadataField::Language,
+            MetadataField::Grouping,
+            MetadataField::Genre,
+            MetadataField::Key,
+        ]
+        .into_iter()
+        .filter_map(|field| Some(TrackFilter::new(field, track?.metadata(field)?)))
+        .filter(|filter| !filter.value.is_empty())
+        .enumerate()
+        .map(|(ix, filter)| {
+            context_menu_item(("add-filter", ix), filter.label())
+                .pl(px(28.))
+                .on_click(cx.listener(move |this, _, cx| this.add_filter(filter.clone(), cx)))
+        })
+        .collect();
+
+        let version = track.and_then(|track| track.version());
+        let version_items: Vec<_> = library
+            .versions(&menu.track_id)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(ix, track_id)| {
+                let other = library.track(&track_id)?;
+                let label = match other.version() {
+                    Some(kind) => format!("{} ({})", other.title(), kind.name()),
+                    None => other.title().to_string(),
+                };
+                Some(
+                    context_menu_item(("show-version", ix), label)
+                        .pl(px(28.))
+                        .on_click(
+                            cx.listener(move |this, _, cx| this.reveal_track(track_id.clone(), cx)),
+                        ),
+                )
+            })
+            .collect();
+        let kind_items: Vec<_> = version
+            .map(|version| {
+                VersionKind::ALL
+                    .into_iter()
+                    .enumerate()
+                    .map(|(ix, kind)| {
+                        let label = if kind == version {
+                            format!("✓ {}", kind.name())
+                        } else {
+                            kind.name().to_string()
+                        };
+                        context_menu_item(("version-kind", ix), label)
+                            .pl(px(28.))
+                            .on_click(
+                                cx.listener(move |this, _, cx| this.set_version_kind(kind, cx)),
+                            )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let candidates = library.version_candidates(&menu.track_id);
+        let link_item = (!candidates.is_empty()).then(|| {
+            let label = match candidates.len() {
+                1 => "Link 1 Other Version".to_string(),
+                count => format!("Link {} Other Versions", count),
+            };
+            let mut track_ids = candidates;
+            track_ids.push(menu.track_id.clone());
+            context_menu_item("link-versions", label)
+                .on_click(cx.listener(move |this, _, cx| this.link_versions(track_ids.clone(), cx)))
+        });
+
+        // Newest first, with a way back from each change
+        let date_format = cx.global::<Settings>().display.date_format;
+        let history: Vec<_> = library
+            .metadata_history(&menu.track_id)
+            .into_iter()
+            .rev()
+            .take(MAX_HISTORY_ITEMS)
+            .flat_map(|change| {
+                let change_id = change.id;
+                let batch = change.batch;
+                let label = format!(
+                    "{} ({}, {})",
+                    change.description(),
+                    change.source.name(),
+                    date_format.format(&date_for_timestamp(change.timestamp))
+                );
+                let revert = context_menu_item(("revert-metadata", change_id as usize), label)
+                    .pl(px(28.))
+                    .on_click(
+                        cx.listener(move |this, _, cx| this.revert_metadata_change(change_id, cx)),
+                    );
+                let revert_pass = (change.source != ChangeSource::User).then(|| {
+                    context_menu_item(
+                        ("revert-metadata-pass", change_id as usize),
+                        "Revert Whole Pass",
+                    )
+                    .pl(px(40.))
+                    .on_click(cx.listener(move |this, _, cx| this.revert_metadata_batch(batch, cx)))
+                });
+                std::iter::once(revert).chain(revert_pass)
+            })
+            .collect();
+
+        Some(deferred(
+            anchored().position(menu.position).child(
+                v_stack()
+                    .occlude()
+                    .py(px(4.))
+                    .min_w(px(160.))
+                    .rounded(px(4.))
+                    .border_1()
+                    .border_color(rgb(0xA0A0A0))
+                    .bg(rgb(0xFFFFFF))
+                    .shadow_lg()
+                    .text_size(px(13.))
+                    .on_mouse_down_out(cx.listener(|this, _, cx| this.dismiss_context_menu(cx)))
+                    .child(
+                        context_menu_item("play-next", "Play Next")
+                            .on_click(cx.listener(|this, _, cx| this.queue_tracks(true, cx))),
+                    )
+                    .child(
+                        context_menu_item("add-to-up-next", "Add to Up Next")
+                            .on_click(cx.listener(|this, _, cx| this.queue_tracks(false, cx))),
+                    )
+                    .child(context_menu_separator())
+                    .map(|menu| {
+                        if is_unplayed {
+                            menu.child(
+                                context_menu_item("mark-as-played", "Mark as Played").on_click(
+                                    cx.listener(|this, _, cx| {
+                                        this.mark_as_played(&MarkAsPlayed, cx)
+                                    }),
+                                ),
+                            )
+                        } else {
+                            menu.child(
+                                context_menu_item("mark-as-unplayed", "Mark as Unplayed").on_click(
+                                    cx.listener(|this, _, cx| {
+                                        this.mark_as_unplayed(&MarkAsUnplayed, cx)
+                                    }),
+                                ),
+                            )
+                        }
+                    })
+                    .child(
+                        context_menu_item("get-info", "Get Info")
+                            .on_click(cx.listener(|this, _, cx| this.get_info(&GetInfo, cx))),
+                    )
+                    .child(
+                        context_menu_item("tag-mood", "Mood…")
+                            .on_click(cx.listener(|this, _, cx| this.tag_mood(&TagMood, cx))),
+                    )
+                    .child(
+                        context_menu_item("analyze-key", "Analyze Key")
+                            .on_click(cx.listener(|this, _, cx| this.analyze_keys(cx))),
+                    )
+                    .child(
+                        context_menu_item("edit-custom-fields", "Custom Fields…")
+                            .on_click(cx.listener(|this, _, cx| this.edit_custom_fields(cx))),
+                    )
+                    .when(has_artwork, |menu| {
+                        menu.child(
+                            context_menu_item("save-artwork-as", "Save Artwork As…")
+                                .on_click(cx.listener(|this, _, cx| this.save_artwork_as(cx))),
+                        )
+                    })
+                    .map(|menu| {
+                        if is_daily_mix {
+                            menu.child(
+                                context_menu_item("exclude-track", "Don't Include in Daily Mixes")
+                                    .on_click(cx.listener(|this, _, cx| {
+                                        this.exclude_from_daily_mixes(false, cx)
+                                    })),
+                            )
+                            .child(
+                                context_menu_item("exclude-artist", "Don't Include This Artist")
+                                    .on_click(cx.listener(|this, _, cx| {
+                                        this.exclude_from_daily_mixes(true, cx)
+                                    })),
+                            )
+                        } else {
+                            menu.child(
+                                context_menu_item("delete", delete_label)
+                                    .on_click(cx.listener(|this, _, cx| this.delete_tracks(cx))),
+                            )
+                        }
+                    })
+                    .when(!artist.is_empty(), |menu| {
+                        let label = if following {
+                            "Unfollow Artist"
+                        } else {
+                            "Follow Artist"
+                        };
+                        menu.child(
+                            context_menu_item("follow-artist", label).on_click(cx.listener(
+                                move |this, _, cx| {
+                                    this.set_following(artist.clone(), !following, cx)
+                                },
+                            )),
+                        )
+                    })
+                    .child(context_menu_separator())
+                    .child(context_menu_label("Album Rating"))
+                    .child(h_stack().px(px(18.)).py(px(2.)).children(rating_stars))
+                    .child(context_menu_separator())
+                    .when(!add_items.is_empty(), |menu| {
+                        menu.child(context_menu_label("Add to Playlist"))
+                            .children(add_items)
+                            .child(context_menu_separator())
+                    })
+                    .child(context_menu_label("Show in Playlist"))
+                    .when(playlist_items.is_empty(), |menu| {
+                        menu.child(context_menu_label("Not in any playlists").pl(px(28.)))
+                    })
+                    .children(playlist_items)
+                    .when(!filter_items.is_empty(), |menu| {
+                        menu.child(context_menu_separator())
+                            .child(context_menu_label("Pin as Filter"))
+                            .children(filter_items)
+                    })
+                    .when(version.is_some() || link_item.is_some(), |menu| {
+                        menu.child(context_menu_separator())
+                            .child(context_menu_label("Versions"))
+                            .children(version_items)
+                            .children(kind_items)
+                            .children(link_item)
+                            .when(version.is_some(), |menu| {
+                                menu.child(
+                                    context_menu_item("unlink-versions", "Unlink Version")
+                                        .on_click(
+                                            cx.listener(|this, _, cx| this.unlink_versions(cx)),
+                                        ),
+                                )
+                            })
+                    })
+                    .when(!history.is_empty(), |menu| {
+                        menu.child(context_menu_separator())
+                            .child(context_menu_label("Metadata History"))
+                            .children(history)
+                    }),
+            ),
+        ))
+    }
+
+    fn render_recently_deleted_menu(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        if self.source != Source::RecentlyDeleted {
+            return None;
+        }
+        let menu = self.context_menu.as_ref()?;
+        let days_left = self
+            .library
+            .read(cx)
+            .recently_deleted()
+            .iter()
+            .find(|deleted| deleted.track().id() == &menu.track_id)?
+            .days_left();
+        let expiry = match days_left {
+            0 => "Removed today".to_string(),
+            1 => "Removed in 1 day".to_string(),
+            days => format!("Removed in {} days", days),
+        };
+
+        Some(deferred(
+            anchored().position(menu.position).child(
+                v_stack()
+                    .occlude()
+                    .py(px(4.))
+                    .min_w(px(160.))
+                    .rounded(px(4.))
+                    .border_1()
+                    .border_color(rgb(0xA0A0A0))
+                    .bg(rgb(0xFFFFFF))
+                    .shadow_lg()
+                    .text_size(px(13.))
+                    .on_mouse_down_out(cx.listener(|this, _, cx| this.dismiss_context_menu(cx)))
+                    .child(
+                        context_menu_item("restore", "Restore")
+                            .on_click(cx.listener(|this, _, cx| this.restore_tracks(cx))),
+                    )
+                    .child(context_menu_separator())
+                    .child(context_menu_label(expiry)),
+            ),
+        ))
+    }
+}
+
+impl Render for LibraryView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let item_count = self.rows.len();
+        let columns = self.columns(cx);
+
+        let list = uniform_list(cx.view().clone(), "library-tracks", item_count, {
+            move |this, range, cx| {
+                let columns = this.columns(cx);
+                let library = this.library.read(cx);
+                let rows: Vec<(usize, Track)> = this.rows[range.clone()]
+                    .iter()
+                    .filter_map(|(index, id)| {
+                        let track = library.track(id).or_else(|| library.deleted_track(id))?;
+                        Some((*index, track.clone()))
+                    })
+                    .collect();
+
+                rows.iter()
+                    .enumerate()
+                    .map(|(ix, (index, track))| {
+                        this.render_row(range.start + ix, *index, track, &columns, cx)
+                    })
+                    .collect()
+            }
+        })
+        .size_full()
+        .track_scroll(self.scroll_handle.clone())
+        .with_sizing_behavior(ListSizingBehavior::Infer)
+        .with_horizontal_sizing_behavior(ListHorizontalSizingBehavior::Unconstrained);
+
+        v_stack()
+            .id("library-view")
+            .key_context("LibraryView")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::mark_as_played))
+            .on_action(cx.listener(Self::select_all))
+            .on_action(cx.listener(Self::select_previous))
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::play_selection))
+            .on_action(cx.listener(Self::delete_selection))
+            .on_action(cx.listener(Self::mark_as_unplayed))
+            .on_action(cx.listener(Self::copy_sort_as_manual_order))
+            .on_action(cx.listener(Self::page_up))
+            .on_action(cx.listener(Self::page_down))
+            .on_action(cx.listener(Self::scroll_to_top))
+            .on_action(cx.listener(Self::scroll_to_bottom))
+            .on_action(cx.listener(Self::tag_mood))
+            .on_action(cx.listener(Self::get_info))
+            .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, cx| {
+                if this.scrollbar.is_dragging() {
+                    this.scrollbar.drag_to(event.position);
+                    cx.notify();
+                }
+                if let Some(resize) = &mut this.column_resize {
+                    let moved = (event.position.x - resize.start_x) / px(1.);
+                    resize.width = (resize.start_width + moved).max(MIN_COLUMN_WIDTH);
+                    cx.notify();
+                }
+            }))
+            .on_mouse_up(
+                MouseButton::Left,
+                cx.listener(|this, _, cx| {
+                    this.end_scrollbar_drag(cx);
+                    this.end_column_resize(cx);
+                }),
+            )
+            .on_mouse_up_out(
+                MouseButton::Left,
+                cx.listener(|this, _, cx| {
+                    this.end_scrollbar_drag(cx);
+                    this.end_column_resize(cx);
+                }),
+            )
+            .flex_1()
+            .size_full()
+            .overflow_hidden()
+            .children(self.render_filter_bar(cx))
+            .children(self.render_artist_header(cx))
+            .child(self.render_column_headers(&columns, cx))
+            .child(
+                div()
+                    .relative()
+                    .size_full()
+                    .child(list)
+                    .children(self.render_index_strip(cx))
+                    .children(self.render_scrollbar(cx))
+                    .children(self.mood_picker.as_ref().map(|(picker, _)| {
+                        div()
+                            .absolute()
+                            .top(px(8.))
+                            .right(px(24.))
+                            .child(picker.clone())
+                    }))
+                    .children(self.custom_fields_editor.as_ref().map(|(editor, _)| {
+                        div()
+                            .absolute()
+                            .top(px(8.))
+                            .right(px(24.))
+                            .child(editor.clone())
+                    }))
+                    // Modal, the list behind it is dimmed and can't be clicked
+                    .children(self.get_info.as_ref().map(|(view, _)| {
+                        div()
+                            .id("get-info-backdrop")
+                            .occlude()
+                            .absolute()
+                            .size_full()
+                            .flex()
+                            .items_center()
+                            .justify_center()
+                            .bg(hsla(0., 0., 0., 0.15))
+                            .child(view.clone())
+                    })),
+            )
+            .children(self.render_context_menu(cx))
+            .children(self.render_recently_deleted_menu(cx))
+            .children(self.render_column_menu(cx))
+    }
+}
+
+impl FocusableView for LibraryView {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+struct StatusBar {
+    window: WeakView<AppWindow>,
+    library: Model<Library>,
+    audition: Model<Audition>,
+    selection: Vec<TrackId>,
+    // Shown in place of the summary for a while
+    toast: Option<String>,
+    _hide_toast: Option<Task<()>>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl StatusBar {
+    pub fn new(
+        window: WeakView<AppWindow>,
+        library: Model<Library>,
+        audition: Model<Audition>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let subscriptions = vec![
+            cx.observe(&library, |_, _, cx| cx.notify()),
+            cx.observe(&audition, |_, _, cx| cx.notify()),
+        ];
+
+        StatusBar {
+            window,
+            library,
+            audition,
+            selection: Vec::new(),
+            toast: None,
+            _hide_toast: None,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    pub fn set_selection(&mut self, selection: Vec<TrackId>, cx: &mut ViewContext<Self>) {
+        self.selection = selection;
+        cx.notify();
+    }
+
+    pub fn show_toast(&mut self, message: String, cx: &mut ViewContext<Self>) {
+        self.toast = Some(message);
+        self._hide_toast = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(TOAST_DURATION).await;
+            this.update(&mut cx, |this, cx| {
+                this.toast = None;
+                cx.notify();
+            })
+            .ok();
+        }));
+        cx.notify();
+    }
+
+    fn render_audition_transport(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let current_track = self.audition.read(cx).current_track()?;
+        let icon = if current_track.is_playing() {
+            Icon::Pause
+        } else {
+            Icon::Play
+        };
+        let label = format!(
+            "Preview: {} — {}  {}",
+            current_track.title(),
+            current_track.artist(),
+            format_playback_time(current_track.current_time())
+        );
+
+        let button = |id: &'static str, icon: Icon| {
+            circle(px(20.))
+                .id(id)
+                .flex()
+                .items_center()
+                .justify_center()
+                .border_1()
+                .border_color(rgb(0x737373))
+                .bg(rgb(0xF0F0F0))
+                .active(|this| this.opacity(0.8))
+                .child(
+                    svg()
+                        .size(px(8.))
+                        .path(icon.path())
+                        .text_color(rgb(0x000000)),
+                )
+        };
+
+        Some(
+            h_stack()
+                .absolute()
+                .left(px(10.))
+                .gap(px(4.))
+                .child(
+                    button("audition-toggle", icon).on_click(cx.listener(|this, _, cx| {
+                        this.audition.update(cx, |audition, cx| {
+                            audition.toggle();
+                            cx.notify();
+                        })
+                    })),
+                )
+                .child(button("audition-stop", Icon::XCircle).on_click(cx.listener(
+                    |this, _, cx| {
+                        this.audition.update(cx, |audition, cx| {
+                            audition.stop();
+                            cx.notify();
+                        })
+                    },
+                )))
+                .child(div().ml(px(2.)).text_size(px(11.)).child(label)),
+        )
+    }
+}
+
+impl Render for StatusBar {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let radio = self
+            .window
+            .upgrade()
+            .and_then(|window| window.read(cx).radio_summary());
+        // A single selected track isn't worth summarizing, show the whole library instead
+        let summary = if let Some(toast) = &self.toast {
+            toast.clone()
+        } else if let Some(radio) = radio {
+            radio
+        } else if let Some((scanned, total)) = library.scan_progress() {
+            // Nothing's been counted while the folder is still being walked
+            match total {
+                0 => "Scanning music folder…".to_string(),
+                total => format!("Scanning music folder… {} of {} files", scanned, total),
+            }
+        } else if self.selection.len() > 1 {
+            library.summarize(&self.selection).format(true)
+        } else {
+            library.summary().format(false)
+        };
+
+        h_stack()
+            .relative()
+            .flex_none()
+            .justify_center()
+            .h(px(36.))
+            .border_t_1()
+            .border_color(rgb(0x414141))
+            .bg(vertical_linear_gradient(rgb(0xC5C5C5), rgb(0x969696)))
+            .child(div().text_size(px(12.)).child(summary))
+            .children(self.render_audition_transport(cx))
+    }
+}
+
+struct PlayingStation {
+    station: RadioStation,
+    status: StreamStatus,
+    // The song the station last said was on
+    title: Option<String>,
+}
+
+pub struct AppWindow {
+    weak_self: WeakView<Self>,
+    sidebar: View<Sidebar>,
+    // For now is just the library, but could
+    // be a slot for any activated view
+    active_view: View<LibraryView>,
+    status_bar: View<StatusBar>,
+    up_next: View<UpNextPanel>,
+    show_up_next: bool,
+    show_volume_options: bool,
+    // The playing track's artwork, over the whole window
+    show_artwork: bool,
+    artwork_drag: FileDragSource,
+    // Shown in place of the track list until a source is picked
+    storage_report: Option<(View<StorageReportView>, Subscription)>,
+    albums_view: Option<(View<AlbumsView>, Subscription)>,
+    new_releases_view: Option<View<NewReleasesView>>,
+    wishlist_view: Option<View<WishlistView>>,
+    imports_view: Option<View<ImportsView>>,
+    playlist_history_view: Option<View<PlaylistHistoryView>>,
+    duplicates_view: Option<View<DuplicatesView>>,
+    verify_view: Option<View<VerifyFilesView>>,
+    moods_view: Option<(View<MoodsView>, Subscription)>,
+    tag_cleanup_view: Option<View<TagCleanupView>>,
+    incomplete_albums_view: Option<(View<IncompleteAlbumsView>, Subscription)>,
+    // Kept while hidden so a recording carries on in the background
+    capture_view: Option<(View<CaptureView>, Subscription)>,
+    show_capture: bool,
+    new_releases: Model<NewReleases>,
+    search: View<SearchField>,
+    library: Model<Library>,
+    // Missing when the saved library couldn't be read
+    store: Option<Arc<Mutex<Box<dyn LibraryStore>>>>,
+    now_playing: Model<NowPlaying>,
+    audition: Model<Audition>,
+    artwork_store: Arc<ArtworkStore>,
+    app_state: Arc<AppState>,
+    remote: Option<RemoteServer>,
+    media_controls: Option<MediaControls>,
+    widget_feed: Option<WidgetFeed>,
+    stream_overlay: StreamOverlay,
+    engine: PlaybackEngine,
+    // Plays whatever is auditioned, on the audition device
+    audition_engine: PlaybackEngine,
+    volume: Volume,
+    volume_slider: Rc<VolumeSlider>,
+    balance_slider: Rc<VolumeSlider>,
+    // The output the volume and effects are set for, so they can be kept
+    // for it when playback moves to another
+    output_device: Option<String>,
+    announcer: Announcer,
+    // Whether the volume was last set held down under an announcement
+    ducked: bool,
+    // The `NowPlaying` generation the engine was last given a track for
+    loaded_generation: Option<u64>,
+    // The track the engine was last told to go on to, and how long to
+    // crossfade into it. `None` when it's been told nothing since loading.
+    lined_up: Option<(Option<PathBuf>, Option<Duration>)>,
+    // The same for the audition engine and `Audition`
+    auditioned_generation: Option<u64>,
+    // A station playing in place of the library
+    radio: Option<PlayingStation>,
+    taskbar_progress: Option<TaskbarProgress>,
+    jobs: Vec<JobProgress>,
+    // Songs whose keys were asked for in low-power mode, analyzed once
+    // it ends
+    deferred_key_analysis: Vec<TrackId>,
+    _subscriptions: Vec<Subscription>,
+    suspend: SuspendDetector,
+    scripts: Scripts,
+    // What the track hooks last saw playing
+    last_track: Option<Track>,
+    _tick: Task<()>,
+    _watch_downloads: Option<Task<()>>,
+    _schedule_serialize: Option<Task<()>>,
+}
+
+impl AppWindow {
+    pub fn new(
+        library: Model<Library>,
+        store: Option<Arc<Mutex<Box<dyn LibraryStore>>>>,
+        app_state: Arc<AppState>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        library.update(cx, |library, _| {
+            library.purge_expired();
+            library.check_roots();
+        });
+        // Watch for changes to the library, update the ui when they occur
+        cx.observe(&library, |_, _, cx| cx.notify()).detach();
+        // cx.subscribe(&library, move |this, _, event, cx| {
+        //     match event {
+        //         Event::LibraryUpdated => {
+        //             // todo!(): something
+        //         }
+        //         _ => {}
+        //     }
+        // });
+
+        // Ensure _something_ always has focus
+        cx.on_focus_lost(|this, cx| {
+            let focus_handle = this.focus_handle(cx);
+            cx.focus(&focus_handle);
+        })
+        .detach();
+
+        let weak_handle = cx.view().downgrade();
+
+        let app_state = Arc::new(AppState::new(cx));
+
+        let now_playing = cx.new_model(|_| NowPlaying::default());
+        let new_releases = cx.new_model(|cx| NewReleases::load(cx));
+        let audition = cx.new_model(|_| Audition::default());
+        let artist_infos = cx.new_model(|cx| ArtistInfos::load(cx));
+
+        let sidebar = cx.new_view(|cx| {
+            Sidebar::new(
+                weak_handle.clone(),
+                library.clone(),
+                now_playing.clone(),
+                cx,
+            )
+        });
+        let library_view = cx.new_view(|cx| {
+            LibraryView::new(
+                weak_handle.clone(),
+                library.clone(),
+                now_playing.clone(),
+                audition.clone(),
+                artist_infos,
+                cx,
+            )
+        });
+        let status_bar = cx.new_view(|cx| {
+            StatusBar::new(weak_handle.clone(), library.clone(), audition.clone(), cx)
+        });
+        let up_next = cx.new_view(|cx| UpNextPanel::new(library.clone(), now_playing.clone(), cx));
+        let session = Session::load(&data_dir(cx));
+        let volume = session.volume;
+        let output_device = session.output_device.clone();
+        Self::restore_session(session, &library, &library_view, &now_playing, cx);
+        let search = cx.new_view(|cx| SearchField::new(library.clone(), cx));
+        let search_changed =
+            cx.subscribe(&search, |this, _, event: &SearchEvent, cx| match event {
+                SearchEvent::Changed(criteria) => {
+                    let criteria = criteria.clone();
+                    this.active_view
+                        .update(cx, |library_view, cx| library_view.set_search(criteria, cx));
+                }
+                SearchEvent::SaveAsSmartPlaylist(name, criteria) => {
+                    this.save_search_as_smart_playlist(name.clone(), criteria.clone(), cx)
+                }
+                SearchEvent::Play(track_id) => this.play_track(track_id, cx),
+            });
+        let record_plays = cx.observe(&now_playing, |this, now_playing, cx| {
+            let plays = now_playing.update(cx, |now_playing, _| now_playing.take_finished_plays());
+            if !plays.is_empty() {
+                this.library.update(cx, |library, cx| {
+                    library.record_plays(&plays);
+                    cx.notify();
+                });
+            }
+        });
+        let track_hooks = cx.observe(&now_playing, |this, _, cx| this.run_track_hooks(cx));
+        let sync_engine = cx.observe(&now_playing, |this, _, cx| this.sync_engine(cx));
+        let sync_audition = cx.observe(&audition, |this, _, cx| this.sync_audition(cx));
+        let autoplay = cx.observe(&now_playing, |this, _, cx| this.fill_autoplay(cx));
+        let settings_changed = cx.observe_global::<Settings>(|this, cx| {
+            Self::update_effects(&this.engine, cx);
+            // The engines only reopen the output when these have changed
+            let settings = cx.global::<Settings>();
+            let (output, stream_cache) = (settings.output.clone(), settings.stream_cache.clone());
+            this.audition_engine.set_output(output.clone());
+            this.engine.set_output(output);
+            this.audition_engine.set_stream_cache(stream_cache.clone());
+            this.engine.set_stream_cache(stream_cache);
+            this.line_up_next(cx);
+            this.sync_remote(cx);
+        });
+        let crash_context = cx.observe(&library, |_, library, cx| {
+            let library = library.read(cx);
+            crash::set_library_size(library.track_order().len(), library.playlists().len());
+        });
+        let window_activation = cx.observe_window_activation(|_, cx| {
+            power::set_window_active(cx.is_window_active(), cx);
+        });
+        let schedule_serialize = cx.observe(&library, |this, _, cx| this.schedule_serialize(cx));
+        let save_session = cx.on_app_quit(|this, cx| {
+            this.save_session(cx);
+            this.save_library(cx);
+            async {}
+        });
+
+        if cx.global::<Settings>().remote.token.is_empty() {
+            cx.update_global::<Settings, _>(|settings, _| {
+                settings.remote.token = RemoteSettings::new_token()
+            });
+            if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+                eprintln!("Failed to save settings: {}", e);
+            }
+        }
+        let remote_settings = cx.global::<Settings>().remote.clone();
+        let remote = if remote_settings.enabled {
+            start_remote(&remote_settings)
+        } else {
+            None
+        };
+        let media_controls = MediaControls::start(cx);
+        let widget_feed = WidgetFeed::start();
+        let settings = cx.global::<Settings>();
+        let engine = PlaybackEngine::spawn(
+            settings.output.clone(),
+            StreamCache::new(
+                paths::data_dir().join("stream-cache"),
+                settings.stream_cache.clone(),
+            ),
+        );
+        Self::update_effects(&engine, cx);
+        engine.set_volume(volume.output_level());
+        engine.set_balance(volume.balance);
+        let settings = cx.global::<Settings>();
+        let audition_engine = PlaybackEngine::spawn_audition(
+            settings.output.clone(),
+            StreamCache::new(
+                paths::data_dir().join("stream-cache"),
+                settings.stream_cache.clone(),
+            ),
+        );
+        let taskbar_progress = TaskbarProgress::new(cx);
+        // The interval is picked again each time round, so a timer that
+        // fires late after a suspend doesn't throw off the ones after it
+        let tick = cx.spawn(|this, mut cx| async move {
+            loop {
+                let interval = power::update_interval(&cx);
+                cx.background_executor().timer(interval).await;
+                if this.update(&mut cx, |this, cx| this.tick(cx)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let artwork_store = Arc::new(ArtworkStore::new(paths::data_dir().join("artwork")));
+        let import_settings = cx.global::<Settings>().import.clone();
+        let watch_downloads = import_settings
+            .watched_downloads_folder
+            .clone()
+            .map(|folder| {
+                Self::watch_downloads(folder, import_settings, artwork_store.clone(), cx)
+            });
+
+        AppWindow {
+            weak_self: weak_handle,
+            sidebar,
+            active_view: library_view,
+            status_bar,
+            up_next,
+            show_up_next: false,
+            show_artwork: false,
+            artwork_drag: FileDragSource::default(),
+            show_volume_options: false,
+            storage_report: None,
+            albums_view: None,
+            new_releases_view: None,
+            wishlist_view: None,
+            imports_view: None,
+            playlist_history_view: None,
+            duplicates_view: None,
+            verify_view: None,
+            moods_view: None,
+            tag_cleanup_view: None,
+            incomplete_albums_view: None,
+            capture_view: None,
+            show_capture: false,
+            new_releases,
+            search,
+            library,
+            store,
+            now_playing,
+            audition,
+            artwork_store,
+            app_state,
+            remote,
+            media_controls,
+            widget_feed,
+            stream_overlay: StreamOverlay::default(),
+            engine,
+            audition_engine,
+            volume,
+            volume_slider: Rc::default(),
+            balance_slider: Rc::default(),
+            output_device,
+            announcer: Announcer::default(),
+            ducked: false,
+            loaded_generation: None,
+            lined_up: None,
+            auditioned_generation: None,
+            radio: None,
+            taskbar_progress,
+            jobs: Vec::new(),
+            deferred_key_analysis: Vec::new(),
+            _subscriptions: vec![
+                search_changed,
+                record_plays,
+                window_activation,
+                crash_context,
+                track_hooks,
+                sync_engine,
+                sync_audition,
+                autoplay,
+                settings_changed,
+                schedule_serialize,
+                save_session,
+            ],
+            suspend: SuspendDetector::default(),
+            scripts: Scripts::load(&config_dir(cx).join("scripts")),
+            last_track: None,
+            _tick: tick,
+            _watch_downloads: watch_downloads,
+            _schedule_serialize: None,
+        }
+    }
+}
+
+impl AppWindow {
+    pub fn app_state(&self) -> &Arc<AppState> {
+        &self.app_state
+    }
+
+    pub fn library(&self) -> &Model<Library> {
+        &self.library
+    }
+
+    // Opens to the source the startup settings ask for, and picks the last
+    // track back up if they want playback resumed
+    fn restore_session(
+        session: Session,
+        library: &Model<Library>,
+        library_view: &View<LibraryView>,
+        now_playing: &Model<NowPlaying>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let startup = cx.global::<Settings>().startup.clone();
+        let library = library.read(cx);
+
+        let source = match &startup.source {
+            StartupSource::Library => None,
+            StartupSource::LastSource => session.source.as_ref().and_then(|saved| match saved {
+                SavedSource::Library => Some(Source::Library),
+                SavedSource::RecentlyDeleted => Some(Source::RecentlyDeleted),
+                SavedSource::Playlist(name) => playlist_named(library, name).map(Source::Playlist),
+            }),
+            StartupSource::Playlist(name) => playlist_named(library, name).map(Source::Playlist),
+        };
+        let resumed = session
+            .track
+            .filter(|_| startup.resume_playback)
+            .and_then(|saved| {
+                let track = library.tracks().find(|track| {
+                    track.title().as_ref() == saved.title
+                        && track.artist().as_ref() == saved.artist
+                        && track.album().as_ref() == saved.album
+                })?;
+                let mut current_track = CurrentTrack::new(track.clone());
+                current_track.set_current_time(saved.current_time);
+                current_track.set_is_playing(true);
+                Some(current_track)
+            });
+
+        if let Some(source) = source {
+            library_view.update(cx, |library_view, cx| library_view.show_source(source, cx));
+        }
+        if let Some(current_track) = resumed {
+            now_playing.update(cx, |now_playing, cx| {
+                now_playing.set_current_track(Some(current_track));
+                cx.notify();
+            });
+        }
+    }
+
+    // Starts the wait over on every change
+    fn schedule_serialize(&mut self, cx: &mut ViewContext<Self>) {
+        self._schedule_serialize = Some(cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(SERIALIZE_DELAY).await;
+            this.update(&mut cx, |this, cx| {
+                this._schedule_serialize = None;
+                let Some(store) = this.store.clone() else {
+                    return;
+                };
+                let (library, changed) = this.library.update(cx, |library, _| {
+                    (library.serializable(), library.take_changed_tracks())
+                });
+                cx.background_executor()
+                    .spawn(async move {
+                        if let Err(e) = store.lock().unwrap().save(&library, &changed) {
+                            eprintln!("Failed to save library: {}", e);
+                        }
+                    })
+                    .detach();
+            })
+            .ok();
+        }));
+    }
+
+    // Saves straight away, for when the app is about to go
+    fn save_library(&mut self, cx: &mut ViewContext<Self>) {
+        self._schedule_serialize = None;
+        let Some(store) = &self.store else {
+            return;
+        };
+        let (library, changed) = self.library.update(cx, |library, _| {
+            (library.serializable(), library.take_changed_tracks())
+        });
+        if let Err(e) = store.lock().unwrap().save(&library, &changed) {
+            eprintln!("Failed to save library: {}", e);
+        }
+    }
+
+    fn save_session(&self, cx: &mut ViewContext<Self>) {
+        let library = self.library.read(cx);
+        let source = match &self.active_view.read(cx).source {
+            Source::Library => Some(SavedSource::Library),
+            Source::RecentlyDeleted => Some(SavedSource::RecentlyDeleted),
+            Source::Playlist(id) => library
+                .playlist(id)
+                .map(|playlist| SavedSource::Playlist(playlist.name().to_string())),
+            // Tomorrow's mixes won't be the same ones
+            Source::DailyMix(_) => None,
+        };
+        let track = self
+            .now_playing
+            .read(cx)
+            .current_track()
+            .filter(|current| !current.track().is_external())
+            .map(|current| SavedTrack {
+                title: current.title().to_string(),
+                artist: current.artist().to_string(),
+                album: current.album().to_string(),
+                current_time: current.current_time(),
+            });
+
+        let session = Session {
+            source,
+            track,
+            volume: self.volume,
+            output_device: self.output_device.clone(),
+        };
+        if let Err(e) = session.save(&data_dir(cx)) {
+            eprintln!("Failed to save session: {}", e);
+        }
+    }
+
+    fn tick(&mut self, cx: &mut ViewContext<Self>) {
+        if self.suspend.check().is_some() {
+            self.resume_from_sleep(cx);
+        }
+        // Also picks up a track restored from the last session, which is
+        // set before anything is observing
+        self.sync_engine(cx);
+        self.poll_engine(cx);
+        self.poll_audition(cx);
+        self.poll_remote(cx);
+        self.poll_media_controls(cx);
+        self.poll_widget(cx);
+        self.poll_opened_links(cx);
+        self.update_stream_overlay(cx);
+        // The music comes back up once the announcement is over
+        if self.ducked && !self.announcer.is_speaking() {
+            self.apply_volume(cx);
+        }
+        self.refresh_daily_mixes(cx);
+        self.new_releases
+            .update(cx, |new_releases, cx| new_releases.check_if_due(cx));
+        self.announce_fulfilled_wishes(cx);
+        self.check_library_drives(cx);
+        self.store_checksums(cx);
+        self.find_missing_artwork(cx);
+        if !self.deferred_key_analysis.is_empty() && !power::is_low_power(cx) {
+            let tracks = std::mem::take(&mut self.deferred_key_analysis);
+            self.analyze_keys(tracks, cx);
+        }
+    }
+
+    // Runs on the selected songs, or else every song that has no key yet
+    fn analyze_all_keys(&mut self, _: &AnalyzeKeys, cx: &mut ViewContext<Self>) {
+        let selection = self.active_view.read(cx).selection.clone();
+        let tracks = if selection.is_empty() {
+            self.library
+                .read(cx)
+                .tracks()
+                .filter(|track| track.key().is_none())
+                .map(|track| track.id().clone())
+                .collect()
+        } else {
+            selection
+        };
+        self.analyze_keys(tracks, cx);
+    }
+
+    // Done off the main thread, since minutes of audio are decoded per song
+    pub fn analyze_keys(&mut self, tracks: Vec<TrackId>, cx: &mut ViewContext<Self>) {
+        let files = self.library.read(cx).key_analysis_files(&tracks);
+        if files.is_empty() {
+            return;
+        }
+        if power::is_low_power(cx) {
+            self.deferred_key_analysis.extend(tracks);
+            self.status_bar.update(cx, |status_bar, cx| {
+                status_bar.show_toast(
+                    "Keys will be analyzed when low-power mode ends".to_string(),
+                    cx,
+                )
+            });
+            return;
+        }
+        let progress = JobProgress::new(files.len());
+        self.track_progress(progress.clone(), cx);
+
+        cx.spawn(|this, mut cx| async move {
+            let keys = cx
+                .background_executor()
+                .spawn(async move {
+                    files
+                        .into_iter()
+                        .filter_map(|(id, path)| {
+                            let key = detect_key(&path);
+                            progress.advance();
+                            match key {
+                                Ok(key) => Some((id, key?)),
+                                Err(e) => {
+                                    eprintln!("Failed to analyze {}: {}", path.display(), e);
+                                    None
+                                }
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.library.update(cx, |library, cx| {
+                    library.set_detected_keys(keys);
+                    cx.notify();
+                })
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // Tracks without artwork of their own get the cover embedded in their
+    // file or sitting next to it, looked up off the main thread. Not while
+    // in low-power mode, they're still waiting when it ends.
+    fn find_missing_artwork(&mut self, cx: &mut ViewContext<Self>) {
+        if power::is_low_power(cx) {
+            return;
+        }
+        let files = self
+            .library
+            .update(cx, |library, _| library.take_missing_artwork());
+        if files.is_empty() {
+            return;
+        }
+
+        let artwork_store = self.artwork_store.clone();
+        cx.spawn(|this, mut cx| async move {
+            let found = cx
+                .background_executor()
+                .spawn(async move { find_artwork(files, &artwork_store) })
+                .await;
+            if found.is_empty() {
+                return;
+            }
+            this.update(&mut cx, |this, cx| {
+                this.library.update(cx, |library, cx| {
+                    library.set_found_artwork(found);
+                    cx.notify();
+                })
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // Done off the main thread after import, since every file is read in
+    // full. Left queued in low-power mode, for the first tick after it ends.
+    fn store_checksums(&mut self, cx: &mut ViewContext<Self>) {
+        if power::is_low_power(cx) {
+            return;
+        }
+        let files = self
+            .library
+            .update(cx, |library, _| library.take_unchecksummed());
+        if files.is_empty() || !cx.global::<Settings>().import.store_checksums {
+            return;
+        }
+
+        cx.spawn(|this, mut cx| async move {
+            let checksums = cx
+                .background_executor()
+                .spawn(async move {
+                    files
+                        .into_iter()
+                        .filter_map(|(id, path)| match file_checksum(&path) {
+                            Ok(checksum) => Some((id, checksum)),
+                            Err(e) => {
+                                eprintln!("Failed to checksum {}: {}", path.display(), e);
+                                None
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.library.update(cx, |library, cx| {
+                    library.set_checksums(checksums);
+                    cx.notify();
+                })
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn check_library_drives(&mut self, cx: &mut ViewContext<Self>) {
+        let changes = self.library.update(cx, |library, cx| {
+            let changes = library.check_roots();
+            if !changes.is_empty() {
+                cx.notify();
+            }
+            changes
+        });
+        for change in changes.iter().filter(|change| change.tracks > 0) {
+            let name = change
+                .root
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| change.root.display().to_string());
+            let message = if change.online {
+                format!("{} is back, {} tracks can play again", name, change.tracks)
+            } else {
+                format!(
+                    "{} is disconnected, {} tracks are unavailable until it's back",
+                    name, change.tracks
+                )
+            };
+            self.status_bar
+                .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+        }
+    }
+
+    fn announce_fulfilled_wishes(&mut self, cx: &mut ViewContext<Self>) {
+        let fulfilled = self
+            .library
+            .update(cx, |library, _| library.take_fulfilled_wishes());
+        let (message, detail) = match fulfilled.as_slice() {
+            [] => return,
+            [wish] => (
+                format!("“{}” by {} is in your library.", wish.album, wish.artist),
+                "It's been marked off your wishlist.".to_string(),
+            ),
+            wishes => (
+                format!(
+                    "{} albums from your wishlist are in your library.",
+                    wishes.len()
+                ),
+                wishes
+                    .iter()
+                    .map(|wish| format!("{} — {}", wish.album, wish.artist))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        };
+        cx.prompt(PromptLevel::Info, &message, Some(&detail), &["OK"])
+            .detach();
+    }
+
+    // Makes the day's mixes once it comes round, or as soon as there's been
+    // enough listening to make any
+    fn refresh_daily_mixes(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = cx.global::<Settings>().daily_mix.clone();
+        let library = self.library.read(cx);
+        if !settings.enabled {
+            if !library.daily_mixes().is_empty() {
+                self.library.update(cx, |library, cx| {
+                    library.clear_daily_mixes();
+                    cx.notify();
+                });
+            }
+            return;
+        }
+
+        let day = settings.day();
+        if library.daily_mix_day() == Some(day.as_str()) {
+            return;
+        }
+        self.library.update(cx, |library, cx| {
+            library.generate_daily_mixes(&day, &settings.exclusions);
+            cx.notify();
+        });
+    }
+
+    // Playback is left paused where it was rather than carrying on by itself
+    // after a wake, possibly out of speakers the listener has since walked
+    // away from. The position stays as it was, as none of the sleep was played.
+    fn resume_from_sleep(&mut self, cx: &mut ViewContext<Self>) {
+        if self.radio.is_some() {
+            self.engine.pause();
+            cx.notify();
+        }
+        self.now_playing.update(cx, |now_playing, cx| {
+            if now_playing
+                .current_track()
+                .is_some_and(|current| current.is_playing())
+            {
+                now_playing.set_is_playing(false);
+                cx.notify();
+            }
+        });
+    }
+
+    // Loads whatever became current and pauses or resumes along with it
+    fn sync_engine(&mut self, cx: &mut ViewContext<Self>) {
+        // A station plays on until a track is started
+        if self.radio.is_some() {
+            let now_playing = self.now_playing.read(cx);
+            if now_playing.current_track().is_none()
+                || self.loaded_generation == Some(now_playing.generation())
+            {
+                return;
+            }
+            self.radio = None;
+        }
+        if self.loaded_generation != Some(self.now_playing.read(cx).generation()) {
+            self.skip_unavailable(cx);
+        }
+        let now_playing = self.now_playing.read(cx);
+        let generation = now_playing.generation();
+        let Some(current_track) = now_playing.current_track() else {
+            if self.loaded_generation.take().is_some() {
+                self.engine.stop();
+                self.lined_up = None;
+            }
+            return;
+        };
+
+        if self.loaded_generation != Some(generation) {
+            self.loaded_generation = Some(generation);
+            self.lined_up = None;
+            match current_track.track().path() {
+                Some(path) => self.engine.load(
+                    path,
+                    Duration::from_secs(current_track.current_time().max(0) as u64),
+                    current_track.is_playing(),
+                ),
+                None => {
+                    eprintln!("No file to play for {}", current_track.title());
+                    self.engine.stop();
+                }
+            }
+        } else if current_track.is_playing() != self.engine.is_playing() {
+            if current_track.is_playing() {
+                self.engine.play();
+            } else {
+                self.engine.pause();
+            }
+        }
+        self.line_up_next(cx);
+    }
+
+    // Lets the engine go straight on to the first track in Up Next,
+    // crossfading into it unless they're parts of one work or the album
+    // plays continuously
+    fn line_up_next(&mut self, cx: &mut ViewContext<Self>) {
+        if self.radio.is_some() || self.loaded_generation.is_none() {
+            return;
+        }
+        let now_playing = self.now_playing.read(cx);
+        let library = self.library.read(cx);
+        let current = now_playing.current_track().map(|current| current.track());
+        let next = now_playing.up_next().first();
+        let crossfade = cx.global::<Settings>().crossfade.duration().filter(|_| {
+            current
+                .zip(next)
+                .is_some_and(|(current, next)| library.should_crossfade(current.id(), next.id()))
+        });
+        let lined_up = (
+            next.and_then(|next| next.path()).map(Path::to_path_buf),
+            crossfade,
+        );
+        if self.lined_up.as_ref() != Some(&lined_up) {
+            self.engine.set_next(lined_up.0.as_deref(), lined_up.1);
+            self.lined_up = Some(lined_up);
+        }
+    }
+
+    // The engine went on to the lined up track by itself. It's already
+    // playing, so it's only loaded again if Up Next changed in the meantime.
+    fn advance(&mut self, path: PathBuf, duration: Option<Duration>, cx: &mut ViewContext<Self>) {
+        self.lined_up = None;
+        let generation = self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.skip_next();
+            cx.notify();
+            let current_track = now_playing
+                .current_track_mut()
+                .filter(|current_track| current_track.track().path() == Some(path.as_path()))?;
+            if let Some(duration) = duration.filter(|_| current_track.duration() == 0) {
+                current_track.set_duration(duration.as_secs() as i32);
+            }
+            Some(now_playing.generation())
+        });
+        if generation.is_some() {
+            self.loaded_generation = generation;
+        }
+    }
+
+    // Like `sync_engine`, for the audition output. Main playback carries on
+    // regardless.
+    fn sync_audition(&mut self, cx: &mut ViewContext<Self>) {
+        let audition = self.audition.read(cx);
+        let generation = audition.generation();
+        // Hover previews play quieter than a track auditioned on purpose
+        self.audition_engine.set_volume(audition.volume());
+        let Some(current_track) = audition.current_track() else {
+            if self.auditioned_generation.take().is_some() {
+                self.audition_engine.stop();
+            }
+            return;
+        };
+
+        if self.auditioned_generation != Some(generation) {
+            self.auditioned_generation = Some(generation);
+            match current_track.track().path() {
+                Some(path) => {
+                    self.audition_engine
+                        .load(path, Duration::ZERO, current_track.is_playing())
+                }
+                None => {
+                    eprintln!("No file to audition for {}", current_track.title());
+                    self.audition_engine.stop();
+                }
+            }
+        } else if current_track.is_playing() != self.audition_engine.is_playing() {
+            if current_track.is_playing() {
+                self.audition_engine.play();
+            } else {
+                self.audition_engine.pause();
+            }
+        }
+    }
+
+    // Tracks on an unplugged drive are passed over rather than failing to
+    // load one after another
+    fn skip_unavailable(&mut self, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        let skipped = self.now_playing.update(cx, |now_playing, cx| {
+            let skipped =
+                now_playing.skip_unavailable(|track| library.read(cx).is_available(track));
+            if skipped > 0 {
+                cx.notify();
+            }
+            skipped
+        });
+        let message = match skipped {
+            0 => return,
+            1 => "Skipped a track on a disconnected drive".to_string(),
+            skipped => format!("Skipped {} tracks on a disconnected drive", skipped),
+        };
+        self.status_bar
+            .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+    }
+
+    // Rebuilt with the output's format by the engine, and again whenever
+    // the chain is edited. The limiter and night mode come after the chain.
+    fn update_effects(engine: &PlaybackEngine, cx: &AppContext) {
+        let settings = cx.global::<Settings>().dsp.clone();
+        let registry = cx.global::<Effects>().0.clone();
+        engine.set_effects(move |sample_rate, channels| {
+            DspChain::build(&settings, &registry, sample_rate, channels)
+        });
+        engine.set_dynamics(cx.global::<Settings>().dynamics.clone());
+    }
+
+    fn poll_engine(&mut self, cx: &mut ViewContext<Self>) {
+        let events: Vec<EngineEvent> = self.engine.events().collect();
+        for event in events {
+            match event {
+                EngineEvent::Loaded {
+                    duration: Some(duration),
+                } => {
+                    self.now_playing.update(cx, |now_playing, cx| {
+                        if let Some(current_track) = now_playing.current_track_mut() {
+                            if current_track.duration() == 0 {
+                                current_track.set_duration(duration.as_secs() as i32);
+                                cx.notify();
+                            }
+                        }
+                    });
+                }
+                EngineEvent::Loaded { duration: None } => {}
+                EngineEvent::StreamStatus(status) => {
+                    if let Some(radio) = &mut self.radio {
+                        radio.status = status;
+                        cx.notify();
+                    }
+                }
+                EngineEvent::StreamTitle(title) => {
+                    if let Some(radio) = &mut self.radio {
+                        radio.title = Some(title);
+                        cx.notify();
+                    }
+                }
+                EngineEvent::Finished => self.skip_next(&SkipNext, cx),
+                EngineEvent::Advanced(path, duration) => self.advance(path, duration, cx),
+                EngineEvent::OutputDevice(device) => self.switch_output_device(device, cx),
+                EngineEvent::RecoveringRepeatedly(recoveries) => {
+                    let message = format!(
+                        "Audio output keeps stalling, restarted {} times in the last minute",
+                        recoveries
+                    );
+                    self.status_bar
+                        .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+                }
+                EngineEvent::Error(e) => {
+                    eprintln!("Playback failed: {}", e);
+                    self.now_playing.update(cx, |now_playing, cx| {
+                        now_playing.set_is_playing(false);
+                        cx.notify();
+                    });
+                }
+            }
+        }
+
+        // Until the engine has the current track its position is for the last one
+        let position = self.engine.position().as_secs() as i32;
+        let loaded_generation = self.loaded_generation;
+        self.now_playing.update(cx, |now_playing, cx| {
+            if loaded_generation != Some(now_playing.generation()) {
+                return;
+            }
+            if let Some(current_track) = now_playing.current_track_mut() {
+                if current_track.current_time() != position {
+                    current_track.set_current_time(position);
+                    cx.notify();
+                }
+            }
+        });
+    }
+
+    // An audition that ends or fails just goes away, there's no queue to
+    // carry on with
+    fn poll_audition(&mut self, cx: &mut ViewContext<Self>) {
+        let events: Vec<EngineEvent> = self.audition_engine.events().collect();
+        for event in events {
+            match event {
+                EngineEvent::Finished => {}
+                EngineEvent::Error(e) => eprintln!("Audition failed: {}", e),
+                _ => continue,
+            }
+            self.audition.update(cx, |audition, cx| {
+                audition.stop();
+                cx.notify();
+            });
+        }
+
+        let position = self.audition_engine.position().as_secs() as i32;
+        let auditioned_generation = self.auditioned_generation;
+        self.audition.update(cx, |audition, cx| {
+            if auditioned_generation != Some(audition.generation()) {
+                return;
+            }
+            if let Some(current_track) = audition.current_track_mut() {
+                if current_track.current_time() != position {
+                    current_track.set_current_time(position);
+                    cx.notify();
+                }
+            }
+        });
+    }
+
+    fn toggle_playback(&mut self, _: &TogglePlayback, cx: &mut ViewContext<Self>) {
+        if let Some(radio) = &self.radio {
+            // Picked up live again, rather than from wherever it was paused
+            if self.engine.is_playing() {
+                self.engine.pause();
+            } else {
+                let station = radio.station.clone();
+                self.play_station(station, cx);
+            }
+            cx.notify();
+            return;
+        }
+        let is_playing = match self.now_playing.read(cx).current_track() {
+            Some(current_track) => current_track.is_playing(),
+            // Nothing current yet, start on whatever is queued
+            None => return self.skip_next(&SkipNext, cx),
+        };
+        self.set_is_playing(!is_playing, cx);
+    }
+
+    fn play(&mut self, _: &Play, cx: &mut ViewContext<Self>) {
+        self.set_is_playing(true, cx);
+    }
+
+    fn pause(&mut self, _: &Pause, cx: &mut ViewContext<Self>) {
+        self.set_is_playing(false, cx);
+    }
+
+    fn set_is_playing(&mut self, is_playing: bool, cx: &mut ViewContext<Self>) {
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.set_is_playing(is_playing);
+            cx.notify();
+        });
+    }
+
+    fn skip_next(&mut self, _: &SkipNext, cx: &mut ViewContext<Self>) {
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.skip_next();
+            cx.notify();
+        });
+    }
+
+    fn skip_prev(&mut self, _: &SkipPrev, cx: &mut ViewContext<Self>) {
+        let current_time = self
+            .now_playing
+            .read(cx)
+            .current_track()
+            .map(|current_track| current_track.current_time());
+        if current_time.is_some_and(|time| time >= SKIP_PREV_RESTART_SECS) {
+            return self.restart(&Restart, cx);
+        }
+        let went_back = self.now_playing.update(cx, |now_playing, cx| {
+            let went_back = now_playing.skip_prev();
+            cx.notify();
+            went_back
+        });
+        if !went_back {
+            self.restart(&Restart, cx);
+        }
+    }
+
+    fn restart(&mut self, _: &Restart, cx: &mut ViewContext<Self>) {
+        self.engine.seek(Duration::ZERO);
+        self.now_playing.update(cx, |now_playing, cx| {
+            if let Some(current_track) = now_playing.current_track_mut() {
+                current_track.set_current_time(0);
+                cx.notify();
+            }
+        });
+    }
+
+    // Started and stopped as it's turned on and off, and handed the token
+    // whenever it changes
+    fn sync_remote(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = cx.global::<Settings>().remote.clone();
+        match (&self.remote, settings.enabled) {
+            (Some(remote), true) => remote.set_token(settings.token),
+            (None, true) => self.remote = start_remote(&settings),
+            (Some(_), false) => self.remote = None,
+            (None, false) => {}
+        }
+    }
+
+    fn poll_remote(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+
+        let commands: Vec<RemoteCommand> = remote.commands().collect();
+        for command in commands {
+            match command {
+                RemoteCommand::TogglePlayback => cx.dispatch_action(Box::new(TogglePlayback)),
+                RemoteCommand::Play => cx.dispatch_action(Box::new(Play)),
+                RemoteCommand::Pause => cx.dispatch_action(Box::new(Pause)),
+                RemoteCommand::SkipNext => cx.dispatch_action(Box::new(SkipNext)),
+                RemoteCommand::SkipPrev => cx.dispatch_action(Box::new(SkipPrev)),
+                RemoteCommand::PlayTrack(id) => {
+                    let track = self.library.read(cx).track(&TrackId::from(id)).cloned();
+                    if let Some(track) = track {
+                        self.now_playing.update(cx, |now_playing, cx| {
+                            let mut current_track = CurrentTrack::new(track);
+                            current_track.set_is_playing(true);
+                            now_playing.set_current_track(Some(current_track));
+                            cx.notify();
+                        });
+                    }
+                }
+                RemoteCommand::Search { query, reply } => {
+                    let query = query.to_lowercase();
+                    let results = self
+                        .library
+                        .read(cx)
+                        .tracks()
+                        .filter(|track| {
+                            track.title().to_lowercase().contains(&query)
+                                || track.artist().to_lowercase().contains(&query)
+                                || track.album().to_lowercase().contains(&query)
+                        })
+                        .take(50)
+                        .map(remote_track)
+                        .collect();
+                    reply.send(results).ok();
+                }
+                RemoteCommand::Stats { reply } => {
+                    match serde_json::to_value(self.library.read(cx).stats()) {
+                        Ok(stats) => {
+                            reply.send(stats).ok();
+                        }
+                        Err(e) => eprintln!("Failed to serialize library statistics: {}", e),
+                    }
+                }
+            }
+        }
+
+        let now_playing = match self.now_playing.read(cx).current_track() {
+            Some(current_track) => RemoteNowPlaying {
+                track: Some(remote_track(current_track.track())),
+                is_playing: current_track.is_playing(),
+                current_time: current_track.current_time(),
+            },
+            None => RemoteNowPlaying::default(),
+        };
+        if let Some(remote) = &self.remote {
+            let queue = self
+                .now_playing
+                .read(cx)
+                .up_next()
+                .iter()
+                .map(remote_track)
+                .collect();
+            remote.publish(RemoteSnapshot {
+                now_playing,
+                queue,
+                volume: self.volume.output_level(),
+            });
+        }
+    }
+
+    fn poll_media_controls(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(media_controls) = &mut self.media_controls else {
+            return;
+        };
+
+        let commands: Vec<MediaCommand> = media_controls.commands().collect();
+        Self::dispatch_media_commands(commands, cx);
+
+        media_controls.update(self.now_playing.read(cx).current_track());
+    }
+
+    fn poll_widget(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(widget_feed) = &mut self.widget_feed else {
+            return;
+        };
+
+        let commands: Vec<MediaCommand> = widget_feed.commands().collect();
+        Self::dispatch_media_commands(commands, cx);
+
+        widget_feed.update(self.now_playing.read(cx).current_track());
+    }
+
+    fn update_stream_overlay(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = cx.global::<Settings>().stream_overlay.clone();
+        if !settings.enabled {
+            return;
+        }
+
+        let now_playing = self.now_playing.read(cx);
+        let (playing, artwork) = if let Some(radio) = &self.radio {
+            let fields = OverlayFields::from_stream(&radio.station.name, radio.title.as_deref());
+            (Some(fields), None)
+        } else if let Some(current_track) = now_playing.current_track() {
+            let track = current_track.track();
+            let fields = OverlayFields::from_track(track, current_track.current_time());
+            (Some(fields), track.artwork().map(Path::to_path_buf))
+        } else {
+            (None, None)
+        };
+        let up_next: Vec<OverlayFields> = now_playing
+            .up_next()
+            .iter()
+            .map(|track| OverlayFields::from_track(track, 0))
+            .collect();
+        self.stream_overlay.update(
+            &settings,
+            &data_dir(cx),
+            playing.as_ref(),
+            artwork.as_deref(),
+            &up_next,
+        );
+    }
+
+    fn dispatch_media_commands(commands: Vec<MediaCommand>, cx: &mut ViewContext<Self>) {
+        for command in commands {
+            match command {
+                MediaCommand::TogglePlayback => cx.dispatch_action(Box::new(TogglePlayback)),
+                MediaCommand::Play => cx.dispatch_action(Box::new(Play)),
+                MediaCommand::Pause => cx.dispatch_action(Box::new(Pause)),
+                MediaCommand::SkipNext => cx.dispatch_action(Box::new(SkipNext)),
+                MediaCommand::SkipPrev => cx.dispatch_action(Box::new(SkipPrev)),
+            }
+        }
+    }
+
+    // Shows a background job on the Dock icon or taskbar button until every
+    // job running alongside it has finished too
+    fn track_progress(&mut self, job: JobProgress, cx: &mut ViewContext<Self>) {
+        if self.taskbar_progress.is_none() {
+            return;
+        }
+        let already_polling = !self.jobs.is_empty();
+        self.jobs.push(job);
+        if already_polling {
+            return;
+        }
+
+        cx.spawn(|this, mut cx| async move {
+            loop {
+                let interval = power::update_interval(&cx);
+                cx.background_executor().timer(interval).await;
+                let Ok(finished) = this.update(&mut cx, |this, _| this.show_progress()) else {
+                    break;
+                };
+                if finished {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    // Returns whether every job is done
+    fn show_progress(&mut self) -> bool {
+        let progress = JobProgress::combined(&self.jobs);
+        if self.jobs.iter().all(JobProgress::is_finished) {
+            self.jobs.clear();
+        }
+        if let Some(taskbar_progress) = &mut self.taskbar_progress {
+            taskbar_progress.set(progress.filter(|_| !self.jobs.is_empty()));
+        }
+        self.jobs.is_empty()
+    }
+
+    fn show_effects(&mut self, _: &ShowEffects, cx: &mut ViewContext<Self>) {
+        cx.open_window(
+            WindowOptions {
+                titlebar: Some(TitlebarOptions {
+                    title: Some("Effects".into()),
+                    ..Default::default()
+                }),
+                window_bounds: Some(WindowBounds::Windowed(Bounds::centered(
+                    None,
+                    size(px(420.), px(360.)),
+                    cx,
+                ))),
+                ..Default::default()
+            },
+            |cx| cx.new_view(|_| EffectsView),
+        )
+        .map_err(|e| eprintln!("Failed to open effects: {}", e))
+        .ok();
+    }
+
+    fn show_diagnostics(&mut self, _: &ShowDiagnostics, cx: &mut ViewContext<Self>) {
+        let heartbeat = self.engine.heartbeat();
+        cx.open_window(
+            WindowOptions {
+                titlebar: Some(TitlebarOptions {
+                    title: Some("Playback Diagnostics".into()),
+                    ..Default::default()
+                }),
+                window_bounds: Some(WindowBounds::Windowed(Bounds::centered(
+                    None,
+                    size(px(380.), px(300.)),
+                    cx,
+                ))),
+                ..Default::default()
+            },
+            |cx| cx.new_view(|cx| DiagnosticsView::new(heartbeat, cx)),
+        )
+        .map_err(|e| eprintln!("Failed to open diagnostics: {}", e))
+        .ok();
+    }
+
+    fn show_stream_cache(&mut self, _: &ShowStreamCache, cx: &mut ViewContext<Self>) {
+        cx.open_window(
+            WindowOptions {
+                titlebar: Some(TitlebarOptions {
+                    title: Some("Network Stream Cache".into()),
+                    ..Default::default()
+                }),
+                window_bounds: Some(WindowBounds::Windowed(Bounds::centered(
+                    None,
+                    size(px(360.), px(220.)),
+                    cx,
+                ))),
+                ..Default::default()
+            },
+            |cx| cx.new_view(|_| StreamCacheView),
+        )
+        .map_err(|e| eprintln!("Failed to open stream cache settings: {}", e))
+        .ok();
+    }
+
+    fn show_remote_settings(&mut self, _: &ShowRemoteSettings, cx: &mut ViewContext<Self>) {
+        cx.open_window(
+            WindowOptions {
+                titlebar: Some(TitlebarOptions {
+                    title: Some("Remote Control".into()),
+                    ..Default::default()
+                }),
+                window_bounds: Some(WindowBounds::Windowed(Bounds::centered(
+                    None,
+                    size(px(460.), px(180.)),
+                    cx,
+                ))),
+                ..Default::default()
+            },
+            |cx| cx.new_view(|_| RemoteSettingsView),
+        )
+        .map_err(|e| eprintln!("Failed to open remote control settings: {}", e))
+        .ok();
+    }
+
+    fn show_import_rules(&mut self, _: &ShowImportRules, cx: &mut ViewContext<Self>) {
+        cx.open_window(
+            WindowOptions {
+                titlebar: Some(TitlebarOptions {
+                    title: Some("Import Rules".into()),
+                    ..Default::default()
+                }),
+                window_bounds: Some(WindowBounds::Windowed(Bounds::centered(
+                    None,
+                    size(px(480.), px(400.)),
+                    cx,
+                ))),
+                ..Default::default()
+            },
+            |cx| cx.new_view(ImportRulesView::new),
+        )
+        .map_err(|e| eprintln!("Failed to open import rules: {}", e))
+        .ok();
+    }
+
+    fn report_last_crash(&mut self, _: &ReportLastCrash, cx: &mut ViewContext<Self>) {
+        match crash::last_report() {
+            Some(report) => cx.open_url(&crash::report_url(&report)),
+            None => {
+                let detail = if cx.global::<Settings>().crash_reports.enabled {
+                    "Crash reports are saved on this computer whenever gpuiTunes crashes."
+                } else {
+                    "Turn on crash reports in settings to have them saved on this computer."
+                };
+                cx.prompt(
+                    PromptLevel::Info,
+                    "No crashes to report.",
+                    Some(detail),
+                    &["OK"],
+                )
+                .detach();
+            }
+        }
+    }
+
+    fn export_library_statistics(
+        &mut self,
+        _: &ExportLibraryStatistics,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let stats = self.library.read(cx).stats();
+        let directory = std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join("Desktop"))
+            .unwrap_or_else(|| PathBuf::from("."));
+        let destination = cx.prompt_for_new_path(&directory);
+
+        cx.spawn(|_, _| async move {
+            let Ok(Ok(Some(mut destination))) = destination.await else {
+                return;
+            };
+            if destination.extension().is_none() {
+                destination.set_extension("json");
+            }
+            if let Err(e) = stats.export(&destination) {
+                eprintln!(
+                    "Failed to export library statistics to {}: {}",
+                    destination.display(),
+                    e
+                );
+            }
+        })
+        .detach();
+    }
+
+    fn export_library_manifest(&mut self, _: &ExportLibraryManifest, cx: &mut ViewContext<Self>) {
+        let manifest = self.library.read(cx).manifest(&machine_name());
+        let directory = std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join("Desktop"))
+            .unwrap_or_else(|| PathBuf::from("."));
+        let destination = cx.prompt_for_new_path(&directory);
+
+        cx.spawn(|_, _| async move {
+            let Ok(Ok(Some(mut destination))) = destination.await else {
+                return;
+            };
+            if destination.extension().is_none() {
+                destination.set_extension("json");
+            }
+            if let Err(e) = manifest.export(&destination) {
+                eprintln!(
+                    "Failed to export library manifest to {}: {}",
+                    destination.display(),
+                    e
+                );
+            }
+        })
+        .detach();
+    }
+
+    // Against a manifest exported on another machine, saving what each
+    // side is missing as a list of files to copy
+    fn compare_library_manifest(&mut self, _: &CompareLibraryManifest, cx: &mut ViewContext<Self>) {
+        let here = self.library.read(cx).manifest(&machine_name());
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+        });
+
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            let diff = cx
+                .background_executor()
+                .spawn(async move { LibraryManifest::load(&path).map(|there| here.diff(&there)) })
+                .await;
+
+            let destination = this.update(&mut cx, |this, cx| {
+                let (message, destination) = match &diff {
+                    Ok(diff) if diff.is_empty() => {
+                        ("Both libraries have the same albums".to_string(), None)
+                    }
+                    Ok(diff) => {
+                        let directory = std::env::var_os("HOME")
+                            .map(|home| PathBuf::from(home).join("Desktop"))
+                            .unwrap_or_else(|| PathBuf::from("."));
+                        (diff.summary(), Some(cx.prompt_for_new_path(&directory)))
+                    }
+                    Err(e) => (format!("Couldn't read the manifest: {}", e), None),
+                };
+                this.status_bar
+                    .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+                destination
+            });
+            let (Ok(Some(destination)), Ok(diff)) = (destination, diff) else {
+                return;
+            };
+            let Ok(Ok(Some(mut destination))) = destination.await else {
+                return;
+            };
+            if destination.extension().is_none() {
+                destination.set_extension("txt");
+            }
+            if let Err(e) = fs::write(&destination, diff.copy_list()) {
+                eprintln!(
+                    "Failed to save the copy list to {}: {}",
+                    destination.display(),
+                    e
+                );
+            }
+        })
+        .detach();
+    }
+
+    fn check_library_integrity(&mut self, _: &CheckLibraryIntegrity, cx: &mut ViewContext<Self>) {
+        let report = self
+            .library
+            .read(cx)
+            .check_integrity(self.now_playing.read(cx), &self.artwork_store);
+        if report.is_clean() {
+            cx.prompt(
+                PromptLevel::Info,
+                "The library has no problems.",
+                None,
+                &["OK"],
+            )
+            .detach();
+            return;
+        }
+
+        let answer = cx.prompt(
+            PromptLevel::Warning,
+            "The library has problems. Repair it?",
+            Some(&report.summary()),
+            &["Repair", "Cancel"],
+        );
+        cx.spawn(|this, mut cx| async move {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update(&mut cx, |this, cx| {
+                // Checked again, in case anything changed while the prompt was up
+                let (library, now_playing) = (this.library.clone(), this.now_playing.clone());
+                let report = library
+                    .read(cx)
+                    .check_integrity(now_playing.read(cx), &this.artwork_store);
+                library.update(cx, |library, cx| {
+                    now_playing.update(cx, |now_playing, cx| {
+                        library.repair(&report, now_playing);
+                        cx.notify();
+                    });
+                    cx.notify();
+                });
+                let remaining = library
+                    .read(cx)
+                    .check_integrity(now_playing.read(cx), &this.artwork_store);
+                let message = if remaining.is_clean() {
+                    "Repaired the library".to_string()
+                } else {
+                    format!("Repaired the library, still left: {}", remaining.summary())
+                };
+                this.status_bar
+                    .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn clean_up_artwork(&mut self, _: &CleanUpArtwork, cx: &mut ViewContext<Self>) {
+        let report = self.artwork_store.report(self.library.read(cx));
+        let detail = format!(
+            "The cache holds {} images, {}.",
+            report.images,
+            format_size(report.total_bytes)
+        );
+        if report.unreferenced.is_empty() {
+            cx.prompt(
+                PromptLevel::Info,
+                "No artwork in the cache is unused.",
+                Some(&detail),
+                &["OK"],
+            )
+            .detach();
+            return;
+        }
+
+        let answer = cx.prompt(
+            PromptLevel::Info,
+            &format!(
+                "Remove {} unused images, {}?",
+                report.unreferenced.len(),
+                format_size(report.unreferenced_bytes)
+            ),
+            Some(&detail),
+            &["Remove", "Cancel"],
+        );
+        cx.spawn(|this, mut cx| async move {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update(&mut cx, |this, cx| {
+                // Taken again, songs added since could be using some of it
+                let report = this.artwork_store.report(this.library.read(cx));
+                let message = match this.artwork_store.clean_up(&report) {
+                    Ok(()) => format!(
+                        "Removed {} unused images, {}",
+                        report.unreferenced.len(),
+                        format_size(report.unreferenced_bytes)
+                    ),
+                    Err(e) => format!("Failed to clean up artwork: {}", e),
+                };
+                this.status_bar
+                    .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn import_archive(&mut self, _: &ImportArchive, cx: &mut ViewContext<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: true,
+        });
+        let settings = cx.global::<Settings>().import.clone();
+        let artwork_store = self.artwork_store.clone();
+
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+
+            let progress = JobProgress::new(paths.len());
+            this.update(&mut cx, |this, cx| {
+                this.track_progress(progress.clone(), cx)
+            })
+            .ok();
+            let imported = cx
+                .background_executor()
+                .spawn(async move {
+                    let music_folder = settings.music_folder();
+                    paths
+                        .into_iter()
+                        .filter_map(|path| {
+                            let extracted = extract_archive(&path, &music_folder);
+                            progress.advance();
+                            match extracted {
+                                Ok(extracted) => {
+                                    if settings.delete_archives_after_import {
+                                        fs::remove_file(&path).ok();
+                                    }
+                                    Some(extracted)
+                                }
+                                Err(e) => {
+                                    eprintln!("Failed to import {}: {}", path.display(), e);
+                                    None
+                                }
+                            }
+                        })
+                        .map(|archive| {
+                            let folder = archive.folder.clone();
+                            let album = archive
+                                .folder
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            let cover = archive.cover();
+                            let mut files: Vec<ImportedFile> = archive
+                                .audio_files
+                                .into_iter()
+                                .map(|path| ImportedFile {
+                                    artwork: cover.clone(),
+                                    ..ImportedFile::from_path(path, "Unknown Artist", &album)
+                                })
+                                .collect();
+                            artwork_store.intern(&mut files);
+                            (folder, files)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+
+            let (folders, imported): (Vec<PathBuf>, Vec<Vec<ImportedFile>>) =
+                imported.into_iter().unzip();
+            this.update(&mut cx, |this, cx| {
+                this.add_import(ImportKind::Archive, imported, folders, cx)
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn watch_downloads(
+        folder: PathBuf,
+        settings: ImportSettings,
+        artwork_store: Arc<ArtworkStore>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<()> {
+        cx.spawn(|this, mut cx| async move {
+            let mut watcher = PurchaseWatcher::default();
+            loop {
+                cx.background_executor()
+                    .timer(DOWNLOADS_POLL_INTERVAL)
+                    .await;
+                // Picked up on the first scan after low-power mode ends
+                if cx.update(|cx| power::is_low_power(cx)).unwrap_or(false) {
+                    continue;
+                }
+
+                let folder = folder.clone();
+                let settings = settings.clone();
+                let artwork_store = artwork_store.clone();
+                let (returned_watcher, imported) = cx
+                    .background_executor()
+                    .spawn(async move {
+                        let music_folder = settings.music_folder();
+                        let imported: Vec<Vec<ImportedFile>> = watcher
+                            .scan(&folder)
+                            .into_iter()
+                            .filter_map(|(path, release)| {
+                                ingest_purchase(
+                                    &path,
+                                    &release,
+                                    &music_folder,
+                                    settings.delete_archives_after_import,
+                                )
+                                .map_err(|e| {
+                                    eprintln!("Failed to ingest {}: {}", path.display(), e)
+                                })
+                                .ok()
+                            })
+                            .map(|mut files| {
+                                artwork_store.intern(&mut files);
+                                files
+                            })
+                            .collect();
+                        (watcher, imported)
+                    })
+                    .await;
+                watcher = returned_watcher;
+
+                if imported.is_empty() {
+                    continue;
+                }
+                // Each purchase is moved into a folder of its own
+                let mut copied: Vec<PathBuf> = imported
+                    .iter()
+                    .flatten()
+                    .filter_map(|file| file.path.parent().map(Path::to_path_buf))
+                    .collect();
+                copied.sort();
+                copied.dedup();
+                let updated = this.update(&mut cx, |this, cx| {
+                    this.add_import(ImportKind::Purchase, imported, copied, cx)
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    // Scans first so the prompt can say what exporting and stripping would do
+    fn export_embedded_artwork(&mut self, _: &ExportEmbeddedArtwork, cx: &mut ViewContext<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Ok(Some(mut paths))) = paths.await else {
+                return;
+            };
+            let Some(folder) = paths.pop() else {
+                return;
+            };
+
+            let dry_run = cx
+                .background_executor()
+                .spawn({
+                    let folder = folder.clone();
+                    async move {
+                        export_embedded_artwork(
+                            &folder,
+                            ArtworkExportOptions {
+                                strip: true,
+                                dry_run: true,
+                            },
+                        )
+                    }
+                })
+                .await;
+            if dry_run.files_with_artwork == 0 {
+                this.update(&mut cx, |_, cx| {
+                    cx.prompt(
+                        PromptLevel::Info,
+                        "No embedded artwork found.",
+                        Some(&format!("Nothing in {} has artwork of its own.", folder.display())),
+                        &["OK"],
+                    )
+                    .detach()
+                })
+                .ok();
+                return;
+            }
+
+            let Ok(answer) = this.update(&mut cx, |_, cx| {
+                cx.prompt(
+                    PromptLevel::Info,
+                    &format!(
+                        "Export artwork from {} files?",
+                        dry_run.files_with_artwork
+                    ),
+                    Some(&format!(
+                        "{} folder images will be written. Stripping the embedded copies would reclaim {}.",
+                        dry_run.exported.len(),
+                        format_size(dry_run.reclaimable_bytes)
+                    )),
+                    &["Export", "Export and Strip", "Cancel"],
+                )
+            }) else {
+                return;
+            };
+            let strip = match answer.await {
+                Ok(0) => false,
+                Ok(1) => true,
+                _ => return,
+            };
+
+            let report = cx
+                .background_executor()
+                .spawn({
+                    let folder = folder.clone();
+                    async move {
+                        export_embedded_artwork(
+                            &folder,
+                            ArtworkExportOptions {
+                                strip,
+                                dry_run: false,
+                            },
+                        )
+                    }
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                if report.stripped > 0 {
+                    this.library
+                        .update(cx, |library, _| library.rechecksum_under(&folder));
+                }
+                let message = format!("Embedded artwork: {}", report.summary());
+                this.status_bar
+                    .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // Scans first so the prompt can say how many files would be given artwork
+    fn embed_folder_artwork(&mut self, _: &EmbedFolderArtwork, cx: &mut ViewContext<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+        let settings = &cx.global::<Settings>().artwork;
+        let options = ArtworkEmbedOptions {
+            max_size: settings.embed_max_size,
+            as_jpeg: settings.embed_as_jpeg,
+            dry_run: true,
+        };
+        let artwork_store = self.artwork_store.clone();
+
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Ok(Some(mut paths))) = paths.await else {
+                return;
+            };
+            let Some(folder) = paths.pop() else {
+                return;
+            };
+
+            let dry_run = cx
+                .background_executor()
+                .spawn({
+                    let folder = folder.clone();
+                    let artwork_store = artwork_store.clone();
+                    async move { embed_folder_artwork(&folder, &artwork_store, options) }
+                })
+                .await;
+            if dry_run.embedded.is_empty() {
+                this.update(&mut cx, |_, cx| {
+                    cx.prompt(
+                        PromptLevel::Info,
+                        "No artwork to embed.",
+                        Some(&format!(
+                            "No tracks in {} are missing artwork with a folder image next to them.",
+                            folder.display()
+                        )),
+                        &["OK"],
+                    )
+                    .detach()
+                })
+                .ok();
+                return;
+            }
+
+            let Ok(answer) = this.update(&mut cx, |_, cx| {
+                cx.prompt(
+                    PromptLevel::Info,
+                    &format!("Embed artwork into {} files?", dry_run.embedded.len()),
+                    Some(&format!(
+                        "Each of {} albums has its folder image embedded into the tracks that have no artwork of their own.",
+                        dry_run.albums
+                    )),
+                    &["Embed", "Cancel"],
+                )
+            }) else {
+                return;
+            };
+            if answer.await != Ok(0) {
+                return;
+            }
+
+            let report = cx
+                .background_executor()
+                .spawn(async move {
+                    embed_folder_artwork(
+                        &folder,
+                        &artwork_store,
+                        ArtworkEmbedOptions {
+                            dry_run: false,
+                            ..options
+                        },
+                    )
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.library.update(cx, |library, cx| {
+                    library.set_embedded_artwork(&report.embedded);
+                    cx.notify();
+                });
+                let message = format!("Folder artwork: {}", report.summary());
+                this.status_bar
+                    .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // Remembered in settings, so it's scanned at every launch after this
+    fn add_library_folder(&mut self, _: &AddLibraryFolder, cx: &mut ViewContext<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            this.update(&mut cx, |this, cx| {
+                for path in paths {
+                    let roots = cx.global::<Settings>().import.library_roots();
+                    if roots.contains(&path) {
+                        continue;
+                    }
+                    cx.update_global::<Settings, _>(|settings, _| {
+                        settings.import.extra_folders.push(path.clone())
+                    });
+                    this.library
+                        .update(cx, |library, cx| library.add_root(path, cx));
+                }
+                if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+                    eprintln!("Failed to save settings: {}", e);
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn play_folder(&mut self, _: &PlayFolder, cx: &mut ViewContext<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: true,
+        });
+
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            this.update(&mut cx, |this, cx| this.play_external(paths, cx))
+                .ok();
+        })
+        .detach();
+    }
+
+    /// Plays files and folders as they are, without adding them to the library.
+    fn play_external(&mut self, paths: Vec<PathBuf>, cx: &mut ViewContext<Self>) {
+        let tracks = cx.background_executor().spawn(async move {
+            paths
+                .into_iter()
+                .flat_map(|path| {
+                    let (folder, files) = if path.is_dir() {
+                        let files = audio_files_in(&path);
+                        (path, files)
+                    } else {
+                        let folder = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+                        let files = is_audio_file(&path).then_some(path).into_iter().collect();
+                        (folder, files)
+                    };
+                    let album = folder
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    files.into_iter().map(move |file| {
+                        Track::external(ImportedFile::from_path(file, "Unknown Artist", &album))
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        cx.spawn(|this, mut cx| async move {
+            let tracks = tracks.await;
+            if tracks.is_empty() {
+                return;
+            }
+            this.update(&mut cx, |this, cx| {
+                this.now_playing.update(cx, |now_playing, cx| {
+                    now_playing.play_tracks(tracks);
+                    cx.notify();
+                });
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // Holding Option while dropping auditions the files instead of importing them
+    fn handle_drop(&mut self, paths: &ExternalPaths, cx: &mut ViewContext<Self>) {
+        let paths = paths.paths().to_vec();
+        if cx.modifiers().alt {
+            self.play_external(paths, cx);
+            return;
+        }
+
+        let files: Vec<ImportedFile> = paths
+            .iter()
+            .flat_map(|path| {
+                if path.is_dir() {
+                    audio_files_in(path)
+                } else if is_audio_file(path) {
+                    vec![path.clone()]
+                } else {
+                    Vec::new()
+                }
+            })
+            .map(|path| {
+                let album = path
+                    .parent()
+                    .and_then(|folder| folder.file_name())
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                ImportedFile::from_path(path, "Unknown Artist", &album)
+            })
+            .collect();
+        if files.is_empty() {
+            return;
+        }
+        self.add_import(ImportKind::Files, vec![files], Vec::new(), cx);
+    }
+
+    fn switch_profile(&mut self, action: &SwitchProfile, cx: &mut ViewContext<Self>) {
+        if cx.global::<Profiles>().current == action.name {
+            return;
+        }
+        // The new profile gets a window of its own, this one goes away
+        self.save_session(cx);
+        self.save_library(cx);
+        let name = action.name.clone();
+        AppContext::defer(cx, move |cx| activate_profile(name, cx));
+    }
+
+    fn new_profile(&mut self, _: &NewProfile, cx: &mut ViewContext<Self>) {
+        let name = cx.global_mut::<Profiles>().add();
+        self.switch_profile(&SwitchProfile { name }, cx);
+    }
+
+    fn set_volume(&mut self, action: &SetVolume, cx: &mut ViewContext<Self>) {
+        self.volume.set_level(action.level);
+        self.apply_volume(cx);
+    }
+
+    fn volume_increase(&mut self, _: &VolumeIncrease, cx: &mut ViewContext<Self>) {
+        self.volume.step(1.);
+        self.apply_volume(cx);
+    }
+
+    fn volume_decrease(&mut self, _: &VolumeDecrease, cx: &mut ViewContext<Self>) {
+        self.volume.step(-1.);
+        self.apply_volume(cx);
+    }
+
+    fn toggle_mute(&mut self, _: &ToggleMute, cx: &mut ViewContext<Self>) {
+        self.volume.muted = !self.volume.muted;
+        self.apply_volume(cx);
+    }
+
+    fn set_balance(&mut self, action: &SetBalance, cx: &mut ViewContext<Self>) {
+        self.volume.set_balance(action.balance);
+        self.apply_volume(cx);
+    }
+
+    fn balance_left(&mut self, _: &BalanceLeft, cx: &mut ViewContext<Self>) {
+        self.volume.nudge_balance(-1.);
+        self.apply_volume(cx);
+    }
+
+    fn balance_right(&mut self, _: &BalanceRight, cx: &mut ViewContext<Self>) {
+        self.volume.nudge_balance(1.);
+        self.apply_volume(cx);
+    }
+
+    fn reset_balance(&mut self, _: &ResetBalance, cx: &mut ViewContext<Self>) {
+        self.volume.set_balance(0.);
+        self.apply_volume(cx);
+    }
+
+    fn toggle_volume_options(&mut self, _: &ToggleVolumeOptions, cx: &mut ViewContext<Self>) {
+        self.show_volume_options = !self.show_volume_options;
+        cx.notify();
+    }
+
+    fn hide_volume_options(&mut self, _: &HideVolumeOptions, cx: &mut ViewContext<Self>) {
+        self.show_volume_options = false;
+        cx.notify();
+    }
+
+    // The engine picks the change up from the settings observer
+    fn update_dynamics(
+        &mut self,
+        cx: &mut ViewContext<Self>,
+        f: impl FnOnce(&mut DynamicsSettings),
+    ) {
+        cx.update_global::<Settings, _>(|settings, _| f(&mut settings.dynamics));
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        cx.notify();
+    }
+
+    fn toggle_limiter(&mut self, _: &ToggleLimiter, cx: &mut ViewContext<Self>) {
+        self.update_dynamics(cx, |dynamics| dynamics.limiter = !dynamics.limiter);
+    }
+
+    fn toggle_night_mode(&mut self, _: &ToggleNightMode, cx: &mut ViewContext<Self>) {
+        self.update_dynamics(cx, |dynamics| dynamics.night_mode = !dynamics.night_mode);
+    }
+
+    fn stop_slider_drags(&mut self) {
+        self.volume_slider.set_dragging(false);
+        self.balance_slider.set_dragging(false);
+    }
+
+    fn apply_volume(&mut self, cx: &mut ViewContext<Self>) {
+        self.ducked = self.announcer.is_speaking();
+        let mut level = self.volume.output_level();
+        if self.ducked {
+            level *= cx
+                .global::<Settings>()
+                .announcements
+                .duck_level
+                .clamp(0., 1.);
+        }
+        self.engine.set_volume(level);
+        self.engine.set_balance(self.volume.balance);
+        cx.notify();
+    }
+
+    fn toggle_private_listening(&mut self, _: &TogglePrivateListening, cx: &mut ViewContext<Self>) {
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.set_private_listening(!now_playing.is_private_listening());
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    /// Stops the current track and tunes in to `station`, recording it when
+    /// it's set to be.
+    pub fn play_station(&mut self, station: RadioStation, cx: &mut ViewContext<Self>) {
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.set_current_track(None);
+            cx.notify();
+        });
+        self.loaded_generation = None;
+        let record_to = station.record.then(|| {
+            cx.global::<Settings>()
+                .radio
+                .recording_folder(&station, &data_dir(cx))
+        });
+        self.engine.load_stream(&station.url, record_to, true);
+        self.radio = Some(PlayingStation {
+            station,
+            status: StreamStatus::Connecting,
+            title: None,
+        });
+        cx.notify();
+    }
+
+    // "Radio Paradise — Artist - Title", or how the connection is doing
+    // while there's nothing to hear
+    pub fn radio_summary(&self) -> Option<String> {
+        let radio = self.radio.as_ref()?;
+        let state = match &radio.status {
+            _ if !self.engine.is_playing() => "Paused".to_string(),
+            StreamStatus::Connecting => "Connecting…".to_string(),
+            StreamStatus::Buffering => "Buffering…".to_string(),
+            StreamStatus::Playing => radio.title.clone().unwrap_or_default(),
+            StreamStatus::Reconnecting { retry_in, .. } => {
+                format!("Connection lost, retrying in {}s…", retry_in.as_secs())
+            }
+        };
+        let recording = if radio.station.record {
+            " (Recording)"
+        } else {
+            ""
+        };
+        Some(if state.is_empty() {
+            format!("{}{}", radio.station.name, recording)
+        } else {
+            format!("{}{} — {}", radio.station.name, recording, state)
+        })
+    }
+
+    fn toggle_offline(&mut self, _: &WorkOffline, cx: &mut ViewContext<Self>) {
+        cx.update_global::<Settings, _>(|settings, _| {
+            settings.network.offline = !settings.network.offline
+        });
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        cx.notify();
+    }
+
+    // Keeps the volume and effects with the device they were set for, and
+    // puts back the ones the new device was last left with. Mute carries
+    // over, it's about the room rather than the device.
+    fn switch_output_device(&mut self, device: String, cx: &mut ViewContext<Self>) {
+        let previous = self.output_device.replace(device.clone());
+        if previous.as_ref() == Some(&device) || !cx.global::<Settings>().output_profiles.enabled {
+            return;
+        }
+        let volume = self.volume;
+        let profile = cx.update_global::<Settings, _>(|settings, _| {
+            if let Some(previous) = previous {
+                let profile = OutputProfile {
+                    volume,
+                    dsp: settings.dsp.clone(),
+                };
+                settings.output_profiles.devices.insert(previous, profile);
+            }
+            let profile = settings.output_profiles.devices.get(&device).cloned();
+            if let Some(profile) = &profile {
+                settings.dsp = profile.dsp.clone();
+            }
+            profile
+        });
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+
+        if let Some(profile) = profile {
+            self.volume = Volume {
+                muted: self.volume.muted,
+                ..profile.volume
+            };
+            self.apply_volume(cx);
+            let message = format!("Using the sound settings for {}", device);
+            self.status_bar
+                .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+        }
+    }
+
+    fn toggle_output_profiles(&mut self, _: &ToggleOutputProfiles, cx: &mut ViewContext<Self>) {
+        cx.update_global::<Settings, _>(|settings, _| {
+            settings.output_profiles.enabled = !settings.output_profiles.enabled
+        });
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        let message = if cx.global::<Settings>().output_profiles.enabled {
+            "Volume and effects will follow the output device"
+        } else {
+            "Volume and effects stay the same on every output device"
+        };
+        self.status_bar.update(cx, |status_bar, cx| {
+            status_bar.show_toast(message.to_string(), cx)
+        });
+    }
+
+    fn toggle_stream_overlay(&mut self, _: &ExportNowPlaying, cx: &mut ViewContext<Self>) {
+        cx.update_global::<Settings, _>(|settings, _| {
+            settings.stream_overlay.enabled = !settings.stream_overlay.enabled
+        });
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        let overlay = &cx.global::<Settings>().stream_overlay;
+        let message = if overlay.enabled {
+            format!(
+                "Writing now playing to {}",
+                overlay.folder(&data_dir(cx)).display()
+            )
+        } else {
+            "Stopped writing now playing".to_string()
+        };
+        self.stream_overlay = StreamOverlay::default();
+        self.status_bar
+            .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+    }
+
+    fn show_artwork(&mut self, _: &ShowArtwork, cx: &mut ViewContext<Self>) {
+        self.show_artwork = !self.show_artwork;
+        cx.notify();
+    }
+
+    // Dragged out as a file like the thumbnail in Up Next, and clicked away
+    fn render_artwork(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        if !self.show_artwork {
+            return None;
+        }
+        let artwork = self
+            .now_playing
+            .read(cx)
+            .current_track()?
+            .track()
+            .artwork()?
+            .to_path_buf();
+
+        Some(
+            div()
+                .id("artwork-view")
+                .absolute()
+                .inset_0()
+                .flex()
+                .items_center()
+                .justify_center()
+                .p(px(40.))
+                .bg(rgba(0x000000E6))
+                .on_click(cx.listener(|this, _, cx| {
+                    this.show_artwork = false;
+                    cx.notify();
+                }))
+                .child(
+                    self.artwork_drag.attach(
+                        div().id("artwork-view-image").size_full().child(
+                            img(artwork.clone())
+                                .size_full()
+                                .object_fit(ObjectFit::Contain),
+                        ),
+                        artwork,
+                    ),
+                ),
+        )
+    }
+
+    fn toggle_up_next(&mut self, _: &ToggleUpNext, cx: &mut ViewContext<Self>) {
+        self.show_up_next = !self.show_up_next;
+        if self.show_up_next {
+            self.up_next.focus_handle(cx).focus(cx);
+        }
+        cx.notify();
+    }
+
+    // The panel handles this itself when focused, this covers the menu item while it's hidden
+    fn clear_up_next(&mut self, action: &ClearUpNext, cx: &mut ViewContext<Self>) {
+        self.up_next
+            .update(cx, |up_next, cx| up_next.clear(action, cx));
+    }
+
+    // Up Next isn't part of the library, so clearing it is undone and
+    // redone here
+    fn undo(&mut self, _: &Undo, cx: &mut ViewContext<Self>) {
+        let command = self.library.update(cx, |library, cx| {
+            let command = library.undo();
+            cx.notify();
+            command
+        });
+        let Some(command) = command else {
+            return;
+        };
+        if let LibraryCommand::ClearQueue(tracks) = &command {
+            self.now_playing.update(cx, |now_playing, cx| {
+                now_playing.queue_tracks(tracks.clone());
+                cx.notify();
+            });
+        }
+        self.status_bar.update(cx, |status_bar, cx| {
+            status_bar.show_toast(format!("Undo {}", command.name()), cx)
+        });
+    }
+
+    fn redo(&mut self, _: &Redo, cx: &mut ViewContext<Self>) {
+        let command = self.library.update(cx, |library, cx| {
+            let command = library.redo();
+            cx.notify();
+            command
+        });
+        let Some(command) = command else {
+            return;
+        };
+        if let LibraryCommand::ClearQueue(_) = &command {
+            self.now_playing.update(cx, |now_playing, cx| {
+                now_playing.clear_queue();
+                cx.notify();
+            });
+        }
+        self.status_bar.update(cx, |status_bar, cx| {
+            status_bar.show_toast(format!("Redo {}", command.name()), cx)
+        });
+    }
+
+    fn save_up_next_as_playlist(&mut self, _: &SaveUpNextAsPlaylist, cx: &mut ViewContext<Self>) {
+        let has_history = !self.now_playing.read(cx).history().is_empty();
+        let date_format = cx.global::<Settings>().display.date_format;
+        let name = format!("Up Next {}", date_format.format(&today()));
+
+        let answers: &[&str] = if has_history {
+            &["Save", "Save with History", "Cancel"]
+        } else {
+            &["Save", "Cancel"]
+        };
+        let answer = cx.prompt(
+            PromptLevel::Info,
+            &format!("Save Up Next as “{}”?", name),
+            has_history.then_some("Songs that have already played can be included at the start."),
+            answers,
+        );
+
+        cx.spawn(|this, mut cx| async move {
+            let include_history = match answer.await {
+                Ok(0) => false,
+                Ok(1) if has_history => true,
+                _ => return,
+            };
+            this.update(&mut cx, |this, cx| {
+                let track_ids = this.now_playing.read(cx).queue_snapshot(include_history);
+                if track_ids.is_empty() {
+                    return;
+                }
+                let playlist_id = this.library.update(cx, |library, cx| {
+                    let playlist_id = library.create_playlist(name);
+                    library.add_to_playlist(&playlist_id, &track_ids);
+                    cx.notify();
+                    playlist_id
+                });
+                this.active_view.update(cx, |library_view, cx| {
+                    library_view.show_source(Source::Playlist(playlist_id), cx)
+                });
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    // Runs when the current track changes. Private listening keeps scripts
+    // out of it too, as they could be scrobbling.
+    fn run_track_hooks(&mut self, cx: &mut ViewContext<Self>) {
+        let now_playing = self.now_playing.read(cx);
+        let current = now_playing
+            .current_track()
+            .map(|current| current.track().clone());
+        let private_listening = now_playing.is_private_listening();
+        if current.as_ref().map(Track::id) == self.last_track.as_ref().map(Track::id) {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.last_track, current.clone());
+        self.announce_track(cx);
+        if private_listening {
+            return;
+        }
+
+        let mut commands = Vec::new();
+        if let Some(previous) = previous {
+            commands.extend(
+                self.scripts
+                    .run_hook(scripting::ON_TRACK_END, vec![track_map(&previous)]),
+            );
+        }
+        if let Some(current) = current {
+            commands.extend(
+                self.scripts
+                    .run_hook(scripting::ON_TRACK_START, vec![track_map(&current)]),
+            );
+        }
+        self.apply_script_commands(commands, cx);
+    }
+
+    // Only a song that starts playing is announced, not one put back paused
+    // from the last session
+    fn announce_track(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = cx.global::<Settings>().announcements.clone();
+        if !settings.enabled {
+            return;
+        }
+        let playing = self
+            .now_playing
+            .read(cx)
+            .current_track()
+            .filter(|current| current.is_playing())
+            .map(|current| OverlayFields::from_track(current.track(), 0));
+        match playing {
+            Some(fields) => self
+                .announcer
+                .announce(&fields.fill(&settings.template), &settings),
+            None => self.announcer.stop(),
+        }
+        self.apply_volume(cx);
+    }
+
+    fn toggle_announcements(&mut self, _: &ToggleAnnouncements, cx: &mut ViewContext<Self>) {
+        cx.update_global::<Settings, _>(|settings, _| {
+            settings.announcements.enabled = !settings.announcements.enabled
+        });
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        let message = if cx.global::<Settings>().announcements.enabled {
+            "Each new song will be announced"
+        } else {
+            self.announcer.stop();
+            self.apply_volume(cx);
+            "Songs will no longer be announced"
+        };
+        self.status_bar.update(cx, |status_bar, cx| {
+            status_bar.show_toast(message.to_string(), cx)
+        });
+    }
+
+    // Tops up an empty queue with tracks like the last few played, so
+    // playback carries on rather than stopping
+    fn fill_autoplay(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = cx.global::<Settings>().autoplay.clone();
+        if !settings.enabled {
+            return;
+        }
+        let now_playing = self.now_playing.read(cx);
+        let Some(current) = now_playing.current_track().map(|current| current.track()) else {
+            return;
+        };
+        if current.is_external() || !now_playing.up_next().is_empty() {
+            return;
+        }
+        let seeds: Vec<TrackId> = std::iter::once(current)
+            .chain(now_playing.history().iter().rev().take(2))
+            .map(|track| track.id().clone())
+            .collect();
+        let played: HashSet<TrackId> = now_playing
+            .history()
+            .iter()
+            .map(|track| track.id().clone())
+            .collect();
+
+        let library = self.library.read(cx);
+        let tracks: Vec<Track> = library
+            .similar_tracks(&seeds, &played, settings.batch_size)
+            .iter()
+            .filter_map(|id| library.track(id).cloned())
+            .collect();
+        if tracks.is_empty() {
+            return;
+        }
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.queue_autoplay(tracks);
+            cx.notify();
+        });
+    }
+
+    fn toggle_autoplay(&mut self, _: &ToggleAutoplay, cx: &mut ViewContext<Self>) {
+        cx.update_global::<Settings, _>(|settings, _| {
+            settings.autoplay.enabled = !settings.autoplay.enabled
+        });
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        let message = if cx.global::<Settings>().autoplay.enabled {
+            self.fill_autoplay(cx);
+            "Similar songs will play when Up Next runs out"
+        } else {
+            "Playback will stop when Up Next runs out"
+        };
+        self.status_bar.update(cx, |status_bar, cx| {
+            status_bar.show_toast(message.to_string(), cx)
+        });
+    }
+
+    fn run_import_hook(&mut self, ids: Vec<TrackId>, cx: &mut ViewContext<Self>) {
+        if ids.is_empty() {
+            return;
+        }
+        let library = self.library.read(cx);
+        let tracks: Array = ids
+            .iter()
+            .filter_map(|id| library.track(id))
+            .map(track_map)
+            .collect();
+        let commands = self
+            .scripts
+            .run_hook(scripting::ON_IMPORT, vec![tracks.into()]);
+        self.apply_script_commands(commands, cx);
+    }
+
+    // Tag edits from one hook are logged as one batch, so they can be
+    // reverted together
+    fn apply_script_commands(&mut self, commands: Vec<ScriptCommand>, cx: &mut ViewContext<Self>) {
+        let mut edits = Vec::new();
+        for command in commands {
+            match command {
+                ScriptCommand::Play(id) => self.play_track(&id, cx),
+                ScriptCommand::Queue(id) => {
+                    let Some(track) = self.library.read(cx).track(&id).cloned() else {
+                        continue;
+                    };
+                    self.now_playing.update(cx, |now_playing, cx| {
+                        now_playing.queue_tracks(vec![track]);
+                        cx.notify();
+                    });
+                }
+                ScriptCommand::SetField(id, field, value) => edits.push((id, field, value)),
+                ScriptCommand::AddToPlaylist(id, name) => {
+                    self.library.update(cx, |library, cx| {
+                        let playlist = playlist_named(library, &name)
+                            .unwrap_or_else(|| library.create_playlist(name));
+                        library.add_to_playlist(&playlist, &[id]);
+                        cx.notify();
+                    });
+                }
+            }
+        }
+
+        if !edits.is_empty() {
+            self.library.update(cx, |library, cx| {
+                library.edit_metadata_batch(edits, ChangeSource::Script);
+                cx.notify();
+            });
+        }
+    }
+
+    fn play_track(&mut self, track_id: &TrackId, cx: &mut ViewContext<Self>) {
+        let Some(track) = self.library.read(cx).track(track_id).cloned() else {
+            return;
+        };
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.play_tracks(vec![track]);
+            cx.notify();
+        });
+    }
+
+    fn save_search_as_smart_playlist(
+        &mut self,
+        name: String,
+        criteria: SmartCriteria,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let playlist_id = self.library.update(cx, |library, cx| {
+            let playlist_id = library.create_smart_playlist(name, criteria);
+            cx.notify();
+            playlist_id
+        });
+        self.show_source(Source::Playlist(playlist_id), cx);
+    }
+
+    fn show_storage_report(&mut self, _: &ShowStorageReport, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        let view = cx.new_view(|cx| StorageReportView::new(library, cx));
+        let subscription = cx.subscribe(&view, |this, _, event: &StorageEvent, cx| match event {
+            StorageEvent::ShowAlbum(album) => this.show_album(&album.artist, &album.album, cx),
+            StorageEvent::MakePlaylist(name, track_ids) => {
+                let playlist_id = this.library.update(cx, |library, cx| {
+                    let playlist_id = library.create_playlist(name.clone());
+                    library.add_to_playlist(&playlist_id, track_ids);
+                    cx.notify();
+                    playlist_id
+                });
+                this.show_source(Source::Playlist(playlist_id), cx);
+            }
+            StorageEvent::Relocate(album) => this.relocate_album(album.clone(), cx),
+            StorageEvent::Transcode(track_ids) => this.transcode_to_flac(track_ids, cx),
+        });
+        self.close_main_views();
+        self.storage_report = Some((view, subscription));
+        cx.notify();
+    }
+
+    // The files of the tracks that have one
+    fn track_files(&self, track_ids: &[TrackId], cx: &AppContext) -> Vec<(TrackId, PathBuf)> {
+        let library = self.library.read(cx);
+        track_ids
+            .iter()
+            .filter_map(|id| {
+                let path = library.track(id)?.path()?;
+                Some((id.clone(), path.to_path_buf()))
+            })
+            .collect()
+    }
+
+    // Hands the tracks their new files, and says how many of them moved
+    fn set_track_files(
+        &mut self,
+        files: Vec<(TrackId, PathBuf)>,
+        total: usize,
+        verb: &str,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let message = if files.len() == total {
+            format!("{} {} songs", verb, files.len())
+        } else {
+            format!(
+                "{} {} of {} songs, the rest couldn't be",
+                verb,
+                files.len(),
+                total
+            )
+        };
+        self.library.update(cx, |library, cx| {
+            library.set_track_files(files);
+            cx.notify();
+        });
+        self.status_bar
+            .update(cx, |status_bar, cx| status_bar.show_toast(message, cx));
+    }
+
+    fn relocate_album(&mut self, album: AlbumSize, cx: &mut ViewContext<Self>) {
+        let files = self.track_files(&album.tracks, cx);
+        if files.is_empty() {
+            return;
+        }
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Ok(Some(mut paths))) = paths.await else {
+                return;
+            };
+            let Some(folder) = paths.pop() else {
+                return;
+            };
+
+            let total = files.len();
+            let progress = JobProgress::new(total);
+            this.update(&mut cx, |this, cx| {
+                this.track_progress(progress.clone(), cx)
+            })
+            .ok();
+            let moved = cx
+                .background_executor()
+                .spawn(async move { relocate_files(files, &folder, &progress) })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.set_track_files(moved, total, "Moved", cx)
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn transcode_to_flac(&mut self, track_ids: &[TrackId], cx: &mut ViewContext<Self>) {
+        let files = self.track_files(track_ids, cx);
+        if files.is_empty() {
+            return;
+        }
+        let answer = cx.prompt(
+            PromptLevel::Warning,
+            &format!("Transcode {} songs to FLAC?", files.len()),
+            Some(
+                "Each WAV or AIFF file is replaced by a FLAC file of the same audio. \
+                 Files with more than 16 bits a sample are left as they are.",
+            ),
+            &["Transcode", "Cancel"],
+        );
+
+        cx.spawn(|this, mut cx| async move {
+            if answer.await != Ok(0) {
+                return;
+            }
+            let total = files.len();
+            let progress = JobProgress::new(total);
+            this.update(&mut cx, |this, cx| {
+                this.track_progress(progress.clone(), cx)
+            })
+            .ok();
+            let transcoded = cx
+                .background_executor()
+                .spawn(async move { transcode_files(files, &progress) })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.set_track_files(transcoded, total, "Transcoded", cx)
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn show_new_releases(&mut self, _: &ShowNewReleases, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        let new_releases = self.new_releases.clone();
+        self.close_main_views();
+        self.new_releases_view =
+            Some(cx.new_view(|cx| NewReleasesView::new(library, new_releases, cx)));
+        cx.notify();
+    }
+
+    fn show_wishlist(&mut self, _: &ShowWishlist, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        self.close_main_views();
+        self.wishlist_view = Some(cx.new_view(|cx| WishlistView::new(library, cx)));
+        cx.notify();
+    }
+
+    fn show_imports(&mut self, _: &ShowImports, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        self.close_main_views();
+        self.imports_view = Some(cx.new_view(|cx| ImportsView::new(library, cx)));
+        cx.notify();
+    }
+
+    fn undo_last_import(&mut self, _: &UndoLastImport, cx: &mut ViewContext<Self>) {
+        let Some(id) = self
+            .library
+            .read(cx)
+            .last_import()
+            .map(|session| session.id)
+        else {
+            return;
+        };
+        confirm_roll_back(self.library.clone(), id, cx);
+    }
+
+    pub fn show_playlist_history(&mut self, playlist_id: PlaylistId, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        self.close_main_views();
+        self.playlist_history_view =
+            Some(cx.new_view(|cx| PlaylistHistoryView::new(library, playlist_id, cx)));
+        cx.notify();
+    }
+
+    fn show_duplicates(&mut self, _: &ShowDuplicates, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        self.close_main_views();
+        self.duplicates_view = Some(cx.new_view(|cx| DuplicatesView::new(library, cx)));
+        cx.notify();
+    }
+
+    fn verify_library_files(&mut self, _: &VerifyLibraryFiles, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        let checks = library.read(cx).file_checks();
+        let progress = JobProgress::new(checks.len());
+        self.track_progress(progress.clone(), cx);
+        self.close_main_views();
+        self.verify_view =
+            Some(cx.new_view(|cx| VerifyFilesView::new(library, checks, progress, cx)));
+        cx.notify();
+    }
+
+    // Drops whatever is showing in place of the track list, returning
+    // whether there was anything
+    fn close_main_views(&mut self) -> bool {
+        let storage_report = self.storage_report.take().is_some();
+        let albums = self.albums_view.take().is_some();
+        let new_releases = self.new_releases_view.take().is_some();
+        let wishlist = self.wishlist_view.take().is_some();
+        let imports = self.imports_view.take().is_some();
+        let playlist_history = self.playlist_history_view.take().is_some();
+        let duplicates = self.duplicates_view.take().is_some();
+        let verify = self.verify_view.take().is_some();
+        let moods = self.moods_view.take().is_some();
+        let tag_cleanup = self.tag_cleanup_view.take().is_some();
+        let incomplete_albums = self.incomplete_albums_view.take().is_some();
+        let capture = std::mem::take(&mut self.show_capture);
+        storage_report
+            || albums
+            || new_releases
+            || wishlist
+            || imports
+            || playlist_history
+            || duplicates
+            || verify
+            || moods
+            || tag_cleanup
+            || incomplete_albums
+            || capture
+    }
+
+    fn show_moods(&mut self, _: &ShowMoods, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        let view = cx.new_view(|cx| MoodsView::new(library, cx));
+        let subscription = cx.subscribe(&view, |this, _, event: &MoodsEvent, cx| match event {
+            MoodsEvent::ShowMood(mood) => this.show_mood(mood, cx),
+        });
+        self.close_main_views();
+        self.moods_view = Some((view, subscription));
+        cx.notify();
+    }
+
+    pub fn show_albums(&mut self, _: &ShowAlbums, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        let audition = self.audition.clone();
+        let artwork_store = self.artwork_store.clone();
+        let view = cx.new_view(|cx| AlbumsView::new(library, audition, artwork_store, cx));
+        let subscription = cx.subscribe(&view, |this, _, event: &AlbumsEvent, cx| match event {
+            AlbumsEvent::ShowAlbum(artist, album) => this.show_album_tracks(artist, album, cx),
+        });
+        self.close_main_views();
+        self.albums_view = Some((view, subscription));
+        cx.notify();
+    }
+
+    pub fn show_incomplete_albums(&mut self, _: &ShowIncompleteAlbums, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        let view = cx.new_view(|cx| IncompleteAlbumsView::new(library, cx));
+        let subscription =
+            cx.subscribe(
+                &view,
+                |this, _, event: &IncompleteAlbumsEvent, cx| match event {
+                    IncompleteAlbumsEvent::ShowAlbum(artist, album) => {
+                        this.show_album(artist, album, cx)
+                    }
+                },
+            );
+        self.close_main_views();
+        self.incomplete_albums_view = Some((view, subscription));
+        cx.notify();
+    }
+
+    // The same view comes back if it's already open, recording or not
+    fn record_from_input(&mut self, _: &RecordFromInput, cx: &mut ViewContext<Self>) {
+        if self.capture_view.is_none() {
+            let view = cx.new_view(CaptureView::new);
+            let subscription =
+                cx.subscribe(&view, |this, _, event: &CaptureEvent, cx| match event {
+                    CaptureEvent::Import(album, tracks) => {
+                        this.import_recording(album, tracks.clone(), cx)
+                    }
+                });
+            self.capture_view = Some((view, subscription));
+        }
+        self.close_main_views();
+        self.show_capture = true;
+        cx.notify();
+    }
+
+    fn import_recording(&mut self, album: &str, tracks: Vec<PathBuf>, cx: &mut ViewContext<Self>) {
+        let files = tracks
+            .iter()
+            .map(|path| ImportedFile::from_path(path.clone(), "Unknown Artist", album))
+            .collect();
+        self.add_import(ImportKind::Recording, vec![files], tracks, cx);
+    }
+
+    // Each import is kept as a session of its own, so it can be rolled back
+    // as a whole. `copied` is whatever it put into the music folder.
+    fn add_import(
+        &mut self,
+        kind: ImportKind,
+        imported: Vec<Vec<ImportedFile>>,
+        copied: Vec<PathBuf>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let rules = cx.global::<Settings>().import.rules.clone();
+        let ids = self.library.update(cx, |library, cx| {
+            let ids: Vec<TrackId> = imported
+                .into_iter()
+                .flat_map(|files| library.add_imported_with_rules(files, &rules))
+                .collect();
+            library.record_import(kind, ids.clone(), copied);
+            cx.notify();
+            ids
+        });
+        self.run_import_hook(ids, cx);
+    }
+
+    fn focus_search(&mut self, _: &FocusSearch, cx: &mut ViewContext<Self>) {
+        self.search.update(cx, |search, cx| search.focus(cx));
+    }
+
+    // Starts out on the selected songs, if there are any
+    fn show_tag_cleanup(&mut self, _: &ShowTagCleanup, cx: &mut ViewContext<Self>) {
+        let library = self.library.clone();
+        let selection = self.active_view.read(cx).selection.clone();
+        self.close_main_views();
+        self.tag_cleanup_view = Some(cx.new_view(|cx| TagCleanupView::new(library, selection, cx)));
+        cx.notify();
+    }
+
+    fn show_mood(&mut self, mood: &str, cx: &mut ViewContext<Self>) {
+        self.show_source(Source::Library, cx);
+        self.active_view.update(cx, |library_view, cx| {
+            library_view.clear_filters(cx);
+            library_view.add_filter(TrackFilter::new(MetadataField::Mood, mood.to_string()), cx);
+        });
+    }
+
+    pub fn set_selection(&mut self, selection: Vec<TrackId>, cx: &mut ViewContext<Self>) {
+        self.status_bar
+            .update(cx, |status_bar, cx| status_bar.set_selection(selection, cx));
+    }
+
+    fn show_album(&mut self, artist: &str, album: &str, cx: &mut ViewContext<Self>) {
+        self.show_source(Source::Library, cx);
+        self.active_view.update(cx, |library_view, cx| {
+            library_view.clear_filters(cx);
+            library_view.add_filter(
+                TrackFilter::new(MetadataField::AlbumArtist, artist.to_string()),
+                cx,
+            );
+            library_view.add_filter(
+                TrackFilter::new(MetadataField::Album, album.to_string()),
+                cx,
+            );
+        });
+    }
+
+    // Sorted by album, which runs in track number order within one
+    fn show_album_tracks(&mut self, artist: &str, album: &str, cx: &mut ViewContext<Self>) {
+        self.show_album(artist, album, cx);
+        self.active_view.update(cx, |library_view, cx| {
+            library_view.set_sort(Some(ColumnKind::Album), cx)
+        });
+    }
+
+    // Nothing is highlighted while a view the sidebar doesn't list is
+    // covering the track list
+    pub fn sidebar_item(&self, cx: &AppContext) -> Option<SidebarItem> {
+        if self.albums_view.is_some() {
+            return Some(SidebarItem::Albums);
+        }
+        if self.new_releases_view.is_some() {
+            return Some(SidebarItem::NewReleases);
+        }
+        if self.incomplete_albums_view.is_some() {
+            return Some(SidebarItem::IncompleteAlbums);
+        }
+        let covered = self.storage_report.is_some()
+            || self.wishlist_view.is_some()
+            || self.imports_view.is_some()
+            || self.playlist_history_view.is_some()
+            || self.duplicates_view.is_some()
+            || self.verify_view.is_some()
+            || self.moods_view.is_some()
+            || self.tag_cleanup_view.is_some()
+            || self.show_capture;
+        (!covered).then(|| SidebarItem::Source(self.active_view.read(cx).source.clone()))
+    }
+
+    // Starts out named "untitled playlist", ready to be renamed in the sidebar
+    fn new_playlist(&mut self, _: &NewPlaylist, cx: &mut ViewContext<Self>) {
+        let playlist_id = self.library.update(cx, |library, cx| {
+            let playlist_id = library.create_playlist("untitled playlist");
+            cx.notify();
+            playlist_id
+        });
+        self.show_source(Source::Playlist(playlist_id.clone()), cx);
+        self.sidebar
+            .update(cx, |sidebar, cx| sidebar.rename_playlist(playlist_id, cx));
+    }
+
+    pub fn delete_playlist(&mut self, playlist_id: PlaylistId, cx: &mut ViewContext<Self>) {
+        let Some(name) = self
+            .library
+            .read(cx)
+            .playlist(&playlist_id)
+            .map(|playlist| playlist.name())
+        else {
+            return;
+        };
+        let answer = cx.prompt(
+            PromptLevel::Warning,
+            &format!("Delete the playlist “{}”?", name),
+            Some("Its songs stay in the library."),
+            &["Delete", "Cancel"],
+        );
+
+        cx.spawn(|this, mut cx| async move {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update(&mut cx, |this, cx| {
+                let showing = Source::Playlist(playlist_id.clone());
+                if this.active_view.read(cx).source == showing {
+                    this.show_source(Source::Library, cx);
+                }
+                this.library.update(cx, |library, cx| {
+                    library.delete_playlist(&playlist_id);
+                    cx.notify();
+                })
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn show_source(&mut self, source: Source, cx: &mut ViewContext<Self>) {
+        if self.close_main_views() {
+            cx.notify();
+        }
+        self.active_view
+            .update(cx, |library_view, cx| library_view.show_source(source, cx));
+        self.set_selection(Vec::new(), cx);
+    }
+
+    fn empty_recently_deleted(&mut self, _: &EmptyRecentlyDeleted, cx: &mut ViewContext<Self>) {
+        let count = self.library.read(cx).recently_deleted().len();
+        if count == 0 {
+            return;
+        }
+        let answer = cx.prompt(
+            PromptLevel::Warning,
+            &format!("Permanently delete {} songs?", count),
+            Some("They can't be restored afterwards."),
+            &["Delete", "Cancel"],
+        );
+
+        cx.spawn(|this, mut cx| async move {
+            if answer.await != Ok(0) {
+                return;
+            }
+            this.update(&mut cx, |this, cx| {
+                this.library.update(cx, |library, cx| {
+                    library.empty_recently_deleted();
+                    cx.notify();
+                })
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn set_shuffle_mode(&mut self, shuffle_mode: ShuffleMode, cx: &mut ViewContext<Self>) {
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.set_shuffle_mode(shuffle_mode);
+            cx.notify();
+        });
+    }
+
+    fn save_artwork_as(&mut self, _: &SaveArtworkAs, cx: &mut ViewContext<Self>) {
+        let track = self
+            .now_playing
+            .read(cx)
+            .current_track()
+            .map(|current| current.track().clone());
+        if let Some(track) = track {
+            save_artwork_as(&track, cx);
+        }
+    }
+
+    // A line for each selected track, each followed by its MusicBrainz page
+    // when that's known
+    fn track_links(&self, cx: &AppContext) -> Option<String> {
+        let library = self.library.read(cx);
+        let links: Vec<String> = self
+            .active_view
+            .read(cx)
+            .selection
+            .iter()
+            .filter_map(|id| library.track(id))
+            .map(|track| {
+                let mut link = DeepLink::for_track(track).to_url();
+                if let Some(url) = musicbrainz_track_url(track) {
+                    link.push('\n');
+                    link.push_str(&url);
+                }
+                link
+            })
+            .collect();
+        (!links.is_empty()).then(|| links.join("\n"))
+    }
+
+    // The same for each album in the selection, once however many of its
+    // tracks are selected
+    fn album_links(&self, cx: &AppContext) -> Option<String> {
+        let library = self.library.read(cx);
+        let mut albums: Vec<DeepLink> = Vec::new();
+        let mut links: Vec<String> = Vec::new();
+        for track in self
+            .active_view
+            .read(cx)
+            .selection
+            .iter()
+            .filter_map(|id| library.track(id))
+        {
+            let album = DeepLink::for_album(track);
+            if albums.contains(&album) {
+                continue;
+            }
+            let mut link = album.to_url();
+            if let Some(url) = musicbrainz_album_url(track) {
+                link.push('\n');
+                link.push_str(&url);
+            }
+            albums.push(album);
+            links.push(link);
+        }
+        (!links.is_empty()).then(|| links.join("\n"))
+    }
+
+    fn copy_link(&mut self, _: &CopyLink, cx: &mut ViewContext<Self>) {
+        if let Some(links) = self.track_links(cx) {
+            cx.write_to_clipboard(ClipboardItem::new_string(links));
+        }
+    }
+
+    fn copy_album_link(&mut self, _: &CopyAlbumLink, cx: &mut ViewContext<Self>) {
+        if let Some(links) = self.album_links(cx) {
+            cx.write_to_clipboard(ClipboardItem::new_string(links));
+        }
+    }
+
+    // Copied instead where there's no share sheet
+    fn share_link(&mut self, _: &ShareLink, cx: &mut ViewContext<Self>) {
+        let Some(links) = self.track_links(cx) else {
+            return;
+        };
+        if !share::share(&links) {
+            cx.write_to_clipboard(ClipboardItem::new_string(links));
+            self.status_bar.update(cx, |status_bar, cx| {
+                status_bar.show_toast("Link copied to the clipboard".to_string(), cx)
+            });
+        }
+    }
+
+    // Links opened from elsewhere show what they point at in the library
+    fn poll_opened_links(&mut self, cx: &mut ViewContext<Self>) {
+        let urls: Vec<String> = cx.global::<OpenedLinks>().0.try_iter().collect();
+        for url in urls {
+            let Some(link) = DeepLink::parse(&url) else {
+                eprintln!("Not a link gpuiTunes can open: {}", url);
+                continue;
+            };
+            let track = self
+                .library
+                .read(cx)
+                .tracks()
+                .find(|track| link.matches(track))
+                .cloned();
+            let Some(track) = track else {
+                self.status_bar.update(cx, |status_bar, cx| {
+                    status_bar.show_toast("The linked music isn't in the library".to_string(), cx)
+                });
+                continue;
+            };
+            match link {
+                DeepLink::Track { .. } => {
+                    let id = track.id().clone();
+                    self.show_source(Source::Library, cx);
+                    self.active_view.update(cx, |library_view, cx| {
+                        library_view.selection = vec![id.clone()];
+                        library_view.reveal_track(id.clone(), cx);
+                    });
+                    self.set_selection(vec![id], cx);
+                }
+                DeepLink::Album { album, .. } => {
+                    self.show_album_tracks(&track.album_artist(), &album, cx)
+                }
+            }
+        }
+    }
+}
+
+// Artwork can only be dragged out of the window on macOS, so this is the way
+// to export it everywhere
+fn save_artwork_as<V: 'static>(track: &Track, cx: &mut ViewContext<V>) {
+    let Some(artwork) = track.artwork().map(|path| path.to_path_buf()) else {
+        return;
+    };
+    let directory = std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join("Desktop"))
+        .unwrap_or_else(|| PathBuf::from("."));
+    let destination = cx.prompt_for_new_path(&directory);
+
+    cx.spawn(|_, _| async move {
+        let Ok(Ok(Some(mut destination))) = destination.await else {
+            return;
+        };
+        if destination.extension().is_none() {
+            if let Some(extension) = artwork.extension() {
+                destination.set_extension(extension);
+            }
+        }
+        if let Err(e) = fs::copy(&artwork, &destination) {
+            eprintln!("Failed to save artwork to {}: {}", destination.display(), e);
+        }
+    })
+    .detach();
+}
+
+// Taken before a playlist is edited, to undo the edit back to
+fn playlist_contents(library: &Library, playlist: &PlaylistId) -> Vec<TrackId> {
+    library
+        .playlist(playlist)
+        .map(|playlist| playlist.tracks().to_vec())
+        .unwrap_or_default()
+}
+
+// What a library manifest says it came from
+fn machine_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| {
+            let output = std::process::Command::new("hostname").output().ok()?;
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "This Computer".to_string())
+}
+
+fn remote_track(track: &Track) -> RemoteTrack {
+    RemoteTrack {
+        id: track.id().clone().into(),
+        title: track.title().to_string(),
+        artist: track.artist().to_string(),
+        album: track.album().to_string(),
+        duration: track.duration(),
+    }
+}
+
+impl FocusableView for AppWindow {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.active_view.focus_handle(cx)
+    }
+}
+
+impl Render for AppWindow {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        // This should be more like 4.0, but later macOS versions have
+        // a higher default window border radius
+        let window_rounding = px(10.0);
+        let state = cx.new_model(|cx| AppState::new(cx));
+
+        let private_listening = self.now_playing.read(cx).is_private_listening();
+        let is_playing = self
+            .now_playing
+            .read(cx)
+            .current_track()
+            .is_some_and(|current_track| current_track.is_playing());
+        let title_bar = cx.new_view(|cx| {
+            TitleBar::new(
+                state.clone(),
+                self.search.clone(),
+                private_listening,
+                is_playing,
+                self.volume,
+                self.volume_slider.clone(),
+                self.balance_slider.clone(),
+                cx.global::<Settings>().dynamics.clone(),
+                self.show_volume_options,
+                cx,
+            )
+        });
+
+        let window = div()
+            .id("gpuitunes-window")
+            .track_focus(&self.focus_handle(cx))
+            .on_action(|_: &Minimize, cx| cx.minimize_window())
+            .on_action(|_: &Maximize, cx| cx.zoom_window())
+            .on_action(|_: &FullScreen, cx| cx.toggle_fullscreen())
+            .on_action(cx.listener(Self::show_artwork))
+            .on_action(cx.listener(Self::toggle_playback))
+            .on_action(cx.listener(Self::play))
+            .on_action(cx.listener(Self::pause))
+            .on_action(cx.listener(Self::skip_next))
+            .on_action(cx.listener(Self::skip_prev))
+            .on_action(cx.listener(Self::restart))
+            .on_action(cx.listener(Self::copy_link))
+            .on_action(cx.listener(Self::copy_album_link))
+            .on_action(cx.listener(Self::share_link))
+            .on_action(cx.listener(Self::check_library_integrity))
+            .on_action(cx.listener(Self::import_archive))
+            .on_action(cx.listener(Self::report_last_crash))
+            .on_action(cx.listener(Self::show_effects))
+            .on_action(cx.listener(Self::show_diagnostics))
+            .on_action(cx.listener(Self::show_stream_cache))
+            .on_action(cx.listener(Self::show_remote_settings))
+            .on_action(cx.listener(Self::show_import_rules))
+            .on_action(cx.listener(Self::clean_up_artwork))
+            .on_action(cx.listener(Self::export_embedded_artwork))
+            .on_action(cx.listener(Self::embed_folder_artwork))
+            .on_action(cx.listener(Self::export_library_statistics))
+            .on_action(cx.listener(Self::export_library_manifest))
+            .on_action(cx.listener(Self::compare_library_manifest))
+            .on_action(cx.listener(Self::show_storage_report))
+            .on_action(cx.listener(Self::show_moods))
+            .on_action(cx.listener(Self::show_incomplete_albums))
+            .on_action(cx.listener(Self::show_albums))
+            .on_action(cx.listener(Self::toggle_offline))
+            .on_action(cx.listener(Self::toggle_stream_overlay))
+            .on_action(cx.listener(Self::toggle_output_profiles))
+            .on_action(cx.listener(Self::toggle_announcements))
+            .on_action(cx.listener(Self::toggle_autoplay))
+            .on_action(cx.listener(Self::undo))
+            .on_action(cx.listener(Self::redo))
+            .on_action(cx.listener(Self::record_from_input))
+            .on_action(cx.listener(Self::show_tag_cleanup))
+            .on_action(cx.listener(Self::analyze_all_keys))
+            .on_action(cx.listener(Self::new_playlist))
+            .on_action(cx.listener(Self::focus_search))
+            .on_action(cx.listener(Self::show_new_releases))
+            .on_action(cx.listener(Self::show_wishlist))
+            .on_action(cx.listener(Self::show_imports))
+            .on_action(cx.listener(Self::undo_last_import))
+            .on_action(cx.listener(Self::show_duplicates))
+            .on_action(cx.listener(Self::verify_library_files))
+            .on_action(cx.listener(Self::add_library_folder))
+            .on_action(cx.listener(Self::toggle_private_listening))
+            .on_action(cx.listener(Self::switch_profile))
+            .on_action(cx.listener(Self::new_profile))
+            .on_action(cx.listener(Self::save_artwork_as))
+            .on_action(cx.listener(Self::play_folder))
+            .on_action(cx.listener(Self::toggle_up_next))
+            .on_action(cx.listener(Self::clear_up_next))
+            .on_action(cx.listener(Self::save_up_next_as_playlist))
+            .on_action(cx.listener(Self::empty_recently_deleted))
+            .on_action(
+                cx.listener(|this, _: &ShowLibrary, cx| this.show_source(Source::Library, cx)),
+            )
+            .on_action(cx.listener(|this, _: &ShowRecentlyDeleted, cx| {
+                this.show_source(Source::RecentlyDeleted, cx)
+            }))
+            .on_action(
+                cx.listener(|this, _: &ShuffleOff, cx| this.set_shuffle_mode(ShuffleMode::Off, cx)),
+            )
+            .on_action(cx.listener(|this, _: &ShuffleSongs, cx| {
+                this.set_shuffle_mode(ShuffleMode::Songs, cx)
+            }))
+            .on_action(cx.listener(|this, _: &ShuffleByAlbum, cx| {
+                this.set_shuffle_mode(ShuffleMode::Albums, cx)
+            }))
+            .on_action(cx.listener(|this, _: &ShuffleByGrouping, cx| {
+                this.set_shuffle_mode(ShuffleMode::Groupings, cx)
+            }))
+            .on_action(cx.listener(Self::set_volume))
+            .on_action(cx.listener(Self::volume_increase))
+            .on_action(cx.listener(Self::volume_decrease))
+            .on_action(cx.listener(Self::toggle_mute))
+            .on_action(cx.listener(Self::set_balance))
+            .on_action(cx.listener(Self::balance_left))
+            .on_action(cx.listener(Self::balance_right))
+            .on_action(cx.listener(Self::reset_balance))
+            .on_action(cx.listener(Self::toggle_volume_options))
+            .on_action(cx.listener(Self::hide_volume_options))
+            .on_action(cx.listener(Self::toggle_limiter))
+            .on_action(cx.listener(Self::toggle_night_mode))
+            // The volume and balance thumbs follow the pointer anywhere in
+            // the window once they're grabbed
+            .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, cx| {
+                if this.volume_slider.is_dragging() {
+                    let level = this.volume_slider.level_at(event.position);
+                    this.set_volume(&SetVolume { level }, cx);
+                }
+                if this.balance_slider.is_dragging() {
+                    let balance = this.balance_slider.level_at(event.position) * 2. - 1.;
+                    this.set_balance(&SetBalance { balance }, cx);
+                }
+            }))
+            .on_mouse_up(
+                MouseButton::Left,
+                cx.listener(|this, _, _| this.stop_slider_drags()),
+            )
+            .on_mouse_up_out(
+                MouseButton::Left,
+                cx.listener(|this, _, _| this.stop_slider_drags()),
+            )
+            .on_drop(cx.listener(Self::handle_drop))
+            .flex()
+            .flex_col()
+            .rounded(window_rounding)
+            .relative()
+            .bg(rgb(0xFEFFFF))
+            .size_full()
+            .font_family("Helvetica")
+            .line_height(px(14.))
+            .text_color(rgb(0x0F1219))
+            .text_size(px(14.))
+            .child(title_bar)
+            .child(
+                h_stack()
+                    .w_full()
+                    .flex_1()
+                    .overflow_hidden()
+                    .child(self.sidebar.clone())
+                    .map(|stack| {
+                        if let Some((storage_report, _)) = &self.storage_report {
+                            stack.child(storage_report.clone())
+                        } else if let Some((albums, _)) = &self.albums_view {
+                            stack.child(albums.clone())
+                        } else if let Some(new_releases) = &self.new_releases_view {
+                            stack.child(new_releases.clone())
+                        } else if let Some(wishlist) = &self.wishlist_view {
+                            stack.child(wishlist.clone())
+                        } else if let Some(imports) = &self.imports_view {
+                            stack.child(imports.clone())
+                        } else if let Some(playlist_history) = &self.playlist_history_view {
+                            stack.child(playlist_history.clone())
+                        } else if let Some(duplicates) = &self.duplicates_view {
+                            stack.child(duplicates.clone())
+                        } else if let Some(verify) = &self.verify_view {
+                            stack.child(verify.clone())
+                        } else if let Some((moods, _)) = &self.moods_view {
+                            stack.child(moods.clone())
+                        } else if let Some(tag_cleanup) = &self.tag_cleanup_view {
+                            stack.child(tag_cleanup.clone())
+                        } else if let Some((incomplete_albums, _)) = &self.incomplete_albums_view {
+                            stack.child(incomplete_albums.clone())
+                        } else if let Some((capture, _)) =
+                            self.capture_view.as_ref().filter(|_| self.show_capture)
+                        {
+                            stack.child(capture.clone())
+                        } else {
+                            stack.child(self.active_view.clone())
+                        }
+                    })
+                    .when(self.show_up_next, |stack| stack.child(self.up_next.clone())),
+            )
+            .child(self.status_bar.clone())
+            .children(self.render_artwork(cx));
+
+        client_side_decorations(window, cx)
+    }
+}
+
+fn start_remote(settings: &RemoteSettings) -> Option<RemoteServer> {
+    RemoteServer::start(("0.0.0.0", settings.port), settings.token.clone())
+        .map_err(|e| eprintln!("Failed to start remote server: {}", e))
+        .ok()
+}
+
+fn playlist_named(library: &Library, name: &str) -> Option<PlaylistId> {
+    library
+        .playlists()
+        .iter()
+        .find(|playlist| playlist.name().as_ref() == name)
+        .map(|playlist| playlist.id().clone())
 }
 
 impl EventEmitter<Event> for AppWindow {}
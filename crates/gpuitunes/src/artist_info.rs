@@ -0,0 +1,90 @@
+use gpui::*;
+use library::{fetch_artist_info, ArtistInfo, ArtistInfoCache};
+use std::time::Duration;
+
+use crate::{profile::data_dir, settings::Settings};
+
+// MusicBrainz allows each client one request a second
+const REQUEST_SPACING: Duration = Duration::from_millis(1100);
+
+/// Photos and bios of artists, shown over their songs. Each is looked up
+/// once and kept, artists are fetched one after another so MusicBrainz
+/// isn't asked too often.
+pub struct ArtistInfos {
+    cache: ArtistInfoCache,
+    // Waiting to be fetched, the first is under way
+    queue: Vec<String>,
+}
+
+impl ArtistInfos {
+    pub fn load(cx: &AppContext) -> Self {
+        ArtistInfos {
+            cache: ArtistInfoCache::load(&data_dir(cx)),
+            queue: Vec::new(),
+        }
+    }
+
+    pub fn info(&self, artist: &str) -> Option<&ArtistInfo> {
+        self.cache.get(artist)
+    }
+
+    pub fn is_fetching(&self, artist: &str) -> bool {
+        self.queue
+            .iter()
+            .any(|queued| queued.eq_ignore_ascii_case(artist))
+    }
+
+    /// Looks the artist up unless they already have been, or `refresh` asks
+    /// for it again. Nothing is fetched while working offline.
+    pub fn fetch(&mut self, artist: &str, refresh: bool, cx: &mut ModelContext<Self>) {
+        if cx.global::<Settings>().network.offline
+            || artist.trim().is_empty()
+            || self.is_fetching(artist)
+            || (!refresh && self.info(artist).is_some())
+        {
+            return;
+        }
+        self.queue.push(artist.to_string());
+        if self.queue.len() == 1 {
+            self.fetch_next(cx);
+        }
+        cx.notify();
+    }
+
+    fn fetch_next(&mut self, cx: &mut ModelContext<Self>) {
+        let Some(artist) = self.queue.first().cloned() else {
+            return;
+        };
+        let image_dir = ArtistInfoCache::image_dir(&data_dir(cx));
+
+        cx.spawn(|this, mut cx| async move {
+            let fetched = artist.clone();
+            let result = cx
+                .background_executor()
+                .spawn(async move { fetch_artist_info(&fetched, &image_dir) })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                match result {
+                    Ok(info) => {
+                        this.cache.insert(info);
+                        if let Err(e) = this.cache.save(&data_dir(cx)) {
+                            eprintln!("Failed to save artist info: {}", e);
+                        }
+                    }
+                    Err(e) => eprintln!("Failed to look up {}: {}", artist, e),
+                }
+                cx.notify();
+            })
+            .ok();
+
+            cx.background_executor().timer(REQUEST_SPACING).await;
+            this.update(&mut cx, |this, cx| {
+                this.queue.remove(0);
+                this.fetch_next(cx);
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+}
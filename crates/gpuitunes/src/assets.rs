@@ -36,10 +36,17 @@ impl AssetSource for Assets {
 #[strum(serialize_all = "snake_case")]
 pub enum Icon {
     Eye,
+    Gear,
+    Library,
     MagnifyingGlass,
+    MusicNote,
     Next,
     Pause,
+    Play,
+    Podcast,
     Previous,
+    Radio,
+    Trash,
     VolumeHigh,
     VolumeLow,
     XCircle,
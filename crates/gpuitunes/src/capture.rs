@@ -0,0 +1,336 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{format_playback_time, today};
+use playback::{input_devices, Capture, CaptureFormat, CaptureSettings};
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    element::*,
+    profile::{config_dir, data_dir},
+    settings::Settings,
+};
+
+// How often the meters redraw while recording
+const METER_INTERVAL: Duration = Duration::from_millis(50);
+// The bottom of the meters, in dBFS
+const METER_FLOOR_DB: f32 = -60.;
+// How far a meter falls back each redraw, in dB
+const METER_FALLOFF_DB: f32 = 1.5;
+const METER_WIDTH: f32 = 240.;
+
+pub enum CaptureEvent {
+    // The tracks of a finished recording, to add to the library as an album
+    Import(String, Vec<PathBuf>),
+}
+
+/// Records a record or a tape from an input, splitting it into tracks at
+/// the gaps between songs and adding them to the library when it's
+/// stopped.
+pub struct CaptureView {
+    devices: Vec<String>,
+    recording: Option<(Capture, String)>,
+    // Per channel, in dBFS, falling back slowly like a real meter
+    levels: Vec<f32>,
+    message: Option<String>,
+}
+
+impl EventEmitter<CaptureEvent> for CaptureView {}
+
+impl CaptureView {
+    pub fn new(_cx: &mut ViewContext<Self>) -> Self {
+        CaptureView {
+            devices: input_devices(),
+            recording: None,
+            levels: Vec::new(),
+            message: None,
+        }
+    }
+
+    fn update_settings(
+        &mut self,
+        cx: &mut ViewContext<Self>,
+        f: impl FnOnce(&mut CaptureSettings),
+    ) {
+        cx.update_global::<Settings, _>(|settings, _| f(&mut settings.capture));
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        cx.notify();
+    }
+
+    // "Recording 2026-10-16", numbered when there's already one that day
+    fn next_album(cx: &AppContext) -> (String, PathBuf) {
+        let folder = data_dir(cx).join("Recordings");
+        let base = format!("Recording {}", today());
+        let mut album = base.clone();
+        let mut n = 2;
+        while folder.join(&album).exists() {
+            album = format!("{} {}", base, n);
+            n += 1;
+        }
+        let path = folder.join(&album);
+        (album, path)
+    }
+
+    fn start(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = cx.global::<Settings>().capture.clone();
+        let (album, folder) = Self::next_album(cx);
+        match Capture::start(&folder, &settings) {
+            Ok(capture) => {
+                self.recording = Some((capture, album));
+                self.message = None;
+                self.poll_levels(cx);
+            }
+            Err(e) => self.message = Some(format!("Couldn't start recording: {}", e)),
+        }
+        cx.notify();
+    }
+
+    pub fn stop(&mut self, cx: &mut ViewContext<Self>) {
+        let Some((capture, album)) = self.recording.take() else {
+            return;
+        };
+        self.levels.clear();
+        match capture.stop() {
+            Ok(tracks) if tracks.is_empty() => {
+                self.message = Some("Nothing was recorded, the input stayed silent.".to_string())
+            }
+            Ok(tracks) => {
+                self.message = Some(format!(
+                    "Added {} {} to the library as “{}”.",
+                    tracks.len(),
+                    if tracks.len() == 1 { "track" } else { "tracks" },
+                    album
+                ));
+                cx.emit(CaptureEvent::Import(album, tracks));
+            }
+            Err(e) => self.message = Some(format!("The recording failed: {}", e)),
+        }
+        cx.notify();
+    }
+
+    fn poll_levels(&mut self, cx: &mut ViewContext<Self>) {
+        cx.spawn(|this, mut cx| async move {
+            loop {
+                cx.background_executor().timer(METER_INTERVAL).await;
+                let recording = this
+                    .update(&mut cx, |this, cx| {
+                        let Some((capture, _)) = &this.recording else {
+                            return false;
+                        };
+                        let peaks = capture.levels();
+                        this.levels.resize(peaks.len(), METER_FLOOR_DB);
+                        for (level, peak) in this.levels.iter_mut().zip(peaks) {
+                            let db = 20. * peak.max(1e-6).log10();
+                            *level = db.max(*level - METER_FALLOFF_DB).max(METER_FLOOR_DB);
+                        }
+                        cx.notify();
+                        true
+                    })
+                    .unwrap_or(false);
+                if !recording {
+                    break;
+                }
+            }
+        })
+        .detach();
+    }
+
+    fn render_option(
+        id: impl Into<ElementId>,
+        label: impl Into<SharedString>,
+        selected: bool,
+        enabled: bool,
+    ) -> Stateful<Div> {
+        div()
+            .id(id)
+            .py(px(1.))
+            .when(selected, |option| option.font_weight(FontWeight::BOLD))
+            .when(enabled, |option| {
+                option
+                    .cursor_pointer()
+                    .hover(|option| option.text_color(rgb(0x3D80DF)))
+            })
+            .when(!enabled, |option| option.opacity(0.5))
+            .child(label.into())
+    }
+
+    fn render_meters(&self) -> Div {
+        v_stack()
+            .gap(px(4.))
+            .py(px(8.))
+            .children(self.levels.iter().map(|level| {
+                let fraction = (level - METER_FLOOR_DB) / -METER_FLOOR_DB;
+                let clipping = *level > -0.1;
+                h_stack()
+                    .gap(px(8.))
+                    .child(
+                        div()
+                            .w(px(METER_WIDTH))
+                            .h(px(8.))
+                            .rounded(px(4.))
+                            .bg(rgb(0xE2E6EC))
+                            .child(
+                                div()
+                                    .h_full()
+                                    .rounded(px(4.))
+                                    .w(px(METER_WIDTH * fraction.clamp(0., 1.)))
+                                    .bg(if clipping {
+                                        vertical_linear_gradient(rgb(0xF08A7E), rgb(0xD8402F))
+                                    } else {
+                                        vertical_linear_gradient(rgb(0x8FD27F), rgb(0x45A935))
+                                    }),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .w(px(50.))
+                            .opacity(0.6)
+                            .child(format!("{:.0} dB", level)),
+                    )
+            }))
+    }
+
+    fn render_status(&self) -> Option<String> {
+        let (capture, album) = self.recording.as_ref()?;
+        let elapsed = format_playback_time(capture.elapsed().as_secs() as i32);
+        let state = match capture.tracks() {
+            0 => "waiting for sound".to_string(),
+            _ if capture.is_silent() => "between tracks".to_string(),
+            n => format!("track {}", n),
+        };
+        let mut status = format!(
+            "{} — {}, {} ({})",
+            album,
+            elapsed,
+            state,
+            capture.describe()
+        );
+        let dropped = capture.dropped();
+        if !dropped.is_zero() {
+            status.push_str(&format!(", {:.1}s lost", dropped.as_secs_f32()));
+        }
+        Some(status)
+    }
+}
+
+impl Render for CaptureView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let settings = cx.global::<Settings>().capture.clone();
+        let recording = self.recording.is_some();
+
+        v_stack()
+            .id("capture")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Record from Input"),
+            )
+            .child(
+                div()
+                    .opacity(0.6)
+                    .pb(px(8.))
+                    .child("Play a record or a tape into an input and each song is saved as a track of its own."),
+            )
+            .child(div().font_weight(FontWeight::BOLD).pb(px(2.)).child("Input"))
+            .child(
+                Self::render_option(
+                    "input-default",
+                    "System Default",
+                    settings.device.is_none(),
+                    !recording,
+                )
+                .when(!recording, |option| {
+                    option.on_click(cx.listener(|this, _, cx| {
+                        this.update_settings(cx, |capture| capture.device = None)
+                    }))
+                }),
+            )
+            .children(self.devices.iter().enumerate().map(|(ix, device)| {
+                let name = device.clone();
+                Self::render_option(
+                    ("input", ix),
+                    device.clone(),
+                    settings.device.as_ref() == Some(device),
+                    !recording,
+                )
+                .when(!recording, |option| {
+                    option.on_click(cx.listener(move |this, _, cx| {
+                        let name = name.clone();
+                        this.update_settings(cx, |capture| capture.device = Some(name))
+                    }))
+                })
+            }))
+            .child(
+                div()
+                    .font_weight(FontWeight::BOLD)
+                    .pt(px(8.))
+                    .pb(px(2.))
+                    .child("Format"),
+            )
+            .child(h_stack().gap(px(12.)).children(
+                [CaptureFormat::Flac, CaptureFormat::Aiff].map(|format| {
+                    Self::render_option(
+                        SharedString::from(format!("format-{}", format.extension())),
+                        format.name(),
+                        settings.format == format,
+                        !recording,
+                    )
+                    .when(!recording, |option| {
+                        option.on_click(cx.listener(move |this, _, cx| {
+                            this.update_settings(cx, |capture| capture.format = format)
+                        }))
+                    })
+                }),
+            ))
+            .child(
+                Self::render_option(
+                    "split-on-silence",
+                    if settings.split_on_silence {
+                        "✓ Split into tracks at silence"
+                    } else {
+                        "Split into tracks at silence"
+                    },
+                    false,
+                    !recording,
+                )
+                .mt(px(8.))
+                .when(!recording, |option| {
+                    option.on_click(cx.listener(|this, _, cx| {
+                        this.update_settings(cx, |capture| {
+                            capture.split_on_silence = !capture.split_on_silence
+                        })
+                    }))
+                }),
+            )
+            .when(recording, |view| view.child(self.render_meters()))
+            .child(
+                h_stack()
+                    .gap(px(8.))
+                    .pt(px(8.))
+                    .child(
+                        small_button("record", if recording { "Stop" } else { "Record" })
+                            .on_click(cx.listener(move |this, _, cx| {
+                                if recording {
+                                    this.stop(cx)
+                                } else {
+                                    this.start(cx)
+                                }
+                            })),
+                    )
+                    .children(self.render_status().map(|status| div().child(status))),
+            )
+            .children(
+                self.message
+                    .clone()
+                    .map(|message| div().pt(px(8.)).opacity(0.6).child(message)),
+            )
+    }
+}
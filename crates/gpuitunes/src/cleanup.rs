@@ -0,0 +1,225 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{CleanupEdit, CleanupPreset, Library, TrackId};
+use std::collections::HashSet;
+
+use crate::element::*;
+
+// A whole library can have thousands of changes, only the first are listed
+const MAX_SHOWN: usize = 500;
+
+/// Runs a cleanup preset over the selected songs or the whole library.
+/// Every change is listed first, and any of them can be left out before
+/// they're made. They're made as one batch, so they can be rolled back
+/// together from a track's change history.
+pub struct TagCleanupView {
+    library: Model<Library>,
+    selection: Vec<TrackId>,
+    whole_library: bool,
+    preset: CleanupPreset,
+    edits: Vec<CleanupEdit>,
+    // Positions in `edits` that were unticked
+    skipped: HashSet<usize>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl TagCleanupView {
+    pub fn new(
+        library: Model<Library>,
+        selection: Vec<TrackId>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let subscriptions = vec![cx.observe(&library, |this, _, cx| this.update_preview(cx))];
+
+        let mut this = TagCleanupView {
+            library,
+            whole_library: selection.is_empty(),
+            selection,
+            preset: CleanupPreset::TitleCase,
+            edits: Vec::new(),
+            skipped: HashSet::new(),
+            _subscriptions: subscriptions,
+        };
+        this.update_preview(cx);
+        this
+    }
+
+    fn update_preview(&mut self, cx: &mut ViewContext<Self>) {
+        let library = self.library.read(cx);
+        let ids = if self.whole_library {
+            library.track_order()
+        } else {
+            self.selection.as_slice()
+        };
+        self.edits = library.preview_cleanup(ids, self.preset);
+        self.skipped.clear();
+        cx.notify();
+    }
+
+    fn set_preset(&mut self, preset: CleanupPreset, cx: &mut ViewContext<Self>) {
+        self.preset = preset;
+        self.update_preview(cx);
+    }
+
+    fn set_whole_library(&mut self, whole_library: bool, cx: &mut ViewContext<Self>) {
+        self.whole_library = whole_library;
+        self.update_preview(cx);
+    }
+
+    fn toggle_edit(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        if !self.skipped.remove(&ix) {
+            self.skipped.insert(ix);
+        }
+        cx.notify();
+    }
+
+    fn apply(&mut self, cx: &mut ViewContext<Self>) {
+        let edits: Vec<CleanupEdit> = self
+            .edits
+            .iter()
+            .enumerate()
+            .filter(|(ix, _)| !self.skipped.contains(ix))
+            .map(|(_, edit)| edit.clone())
+            .collect();
+        if edits.is_empty() {
+            return;
+        }
+        self.library.update(cx, |library, cx| {
+            library.apply_cleanup(edits);
+            cx.notify();
+        });
+    }
+
+    fn render_edit(
+        &self,
+        ix: usize,
+        edit: &CleanupEdit,
+        cx: &mut ViewContext<Self>,
+    ) -> Stateful<Div> {
+        let title = self
+            .library
+            .read(cx)
+            .track(&edit.track_id)
+            .map(|track| track.title())
+            .unwrap_or_default();
+        let included = !self.skipped.contains(&ix);
+
+        h_stack()
+            .id(("cleanup-edit", ix))
+            .gap(px(8.))
+            .py(px(2.))
+            .border_b_1()
+            .border_color(rgb(0xE4E4E4))
+            .cursor_pointer()
+            .on_click(cx.listener(move |this, _, cx| this.toggle_edit(ix, cx)))
+            .when(!included, |row| row.opacity(0.4))
+            .child(div().w(px(12.)).child(if included { "✓" } else { "" }))
+            .child(
+                v_stack()
+                    .flex_1()
+                    .overflow_hidden()
+                    .child(format!(
+                        "{}: {} → {}",
+                        edit.field.name(),
+                        edit.old_value,
+                        edit.new_value
+                    ))
+                    .child(div().opacity(0.6).child(title)),
+            )
+    }
+}
+
+fn choice(id: impl Into<ElementId>, label: impl Into<SharedString>, chosen: bool) -> Stateful<Div> {
+    small_button(id, label).when(chosen, |button| {
+        button
+            .bg(rgb(0x3D80DF))
+            .border_color(rgb(0x2A64B8))
+            .text_color(rgb(0xFFFFFF))
+    })
+}
+
+impl Render for TagCleanupView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let included = self.edits.len() - self.skipped.len();
+        let hidden = self.edits.len().saturating_sub(MAX_SHOWN);
+        let edits: Vec<_> = self
+            .edits
+            .iter()
+            .take(MAX_SHOWN)
+            .enumerate()
+            .map(|(ix, edit)| self.render_edit(ix, edit, cx))
+            .collect();
+
+        v_stack()
+            .id("tag-cleanup")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .gap(px(6.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .child("Clean Up Tags"),
+            )
+            .child(
+                h_stack().flex_wrap().gap(px(4.)).children(
+                    CleanupPreset::ALL
+                        .into_iter()
+                        .enumerate()
+                        .map(|(ix, preset)| {
+                            choice(("cleanup-preset", ix), preset.name(), preset == self.preset)
+                                .on_click(
+                                    cx.listener(move |this, _, cx| this.set_preset(preset, cx)),
+                                )
+                        }),
+                ),
+            )
+            .child(
+                h_stack()
+                    .gap(px(4.))
+                    .child(
+                        choice(
+                            "cleanup-selection",
+                            format!("Selected Songs ({})", self.selection.len()),
+                            !self.whole_library,
+                        )
+                        .when(self.selection.is_empty(), |button| button.opacity(0.5))
+                        .when(!self.selection.is_empty(), |button| {
+                            button.on_click(
+                                cx.listener(|this, _, cx| this.set_whole_library(false, cx)),
+                            )
+                        }),
+                    )
+                    .child(
+                        choice("cleanup-library", "Entire Library", self.whole_library)
+                            .on_click(cx.listener(|this, _, cx| this.set_whole_library(true, cx))),
+                    ),
+            )
+            .map(|view| {
+                if self.edits.is_empty() {
+                    view.child(div().opacity(0.6).child("Nothing to change."))
+                } else {
+                    view.child(
+                        h_stack()
+                            .gap(px(8.))
+                            .child(div().flex_1().child(format!(
+                                "{} of {} changes ticked",
+                                included,
+                                self.edits.len()
+                            )))
+                            .child(
+                                small_button("apply-cleanup", "Make Changes")
+                                    .on_click(cx.listener(|this, _, cx| this.apply(cx))),
+                            ),
+                    )
+                }
+            })
+            .child(v_stack().children(edits))
+            .when(hidden > 0, |view| {
+                view.child(div().opacity(0.6).child(format!("And {} more", hidden)))
+            })
+    }
+}
@@ -0,0 +1,92 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{IncompleteAlbum, Library};
+
+use crate::element::*;
+
+pub enum IncompleteAlbumsEvent {
+    ShowAlbum(SharedString, SharedString),
+}
+
+/// Albums with gaps in their track numbers, the closest to complete first,
+/// so it's easy to see what's left to rip again. Clicking one shows its
+/// tracks.
+pub struct IncompleteAlbumsView {
+    albums: Vec<IncompleteAlbum>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl EventEmitter<IncompleteAlbumsEvent> for IncompleteAlbumsView {}
+
+impl IncompleteAlbumsView {
+    pub fn new(library: Model<Library>, cx: &mut ViewContext<Self>) -> Self {
+        let subscriptions = vec![cx.observe(&library, |this, library, cx| {
+            this.albums = library.read(cx).incomplete_albums();
+            cx.notify();
+        })];
+
+        IncompleteAlbumsView {
+            albums: library.read(cx).incomplete_albums(),
+            _subscriptions: subscriptions,
+        }
+    }
+
+    // "Missing 3, 7 and 11"
+    fn missing_label(album: &IncompleteAlbum) -> String {
+        let numbers: Vec<String> = album.missing.iter().map(u32::to_string).collect();
+        match numbers.split_last() {
+            Some((last, [])) => format!("Missing {}", last),
+            Some((last, rest)) => format!("Missing {} and {}", rest.join(", "), last),
+            None => String::new(),
+        }
+    }
+}
+
+impl Render for IncompleteAlbumsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_stack()
+            .id("incomplete-albums")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Incomplete Albums"),
+            )
+            .when(self.albums.is_empty(), |view| {
+                view.child(
+                    div()
+                        .opacity(0.6)
+                        .child("Every album has all of its tracks, going by their track numbers."),
+                )
+            })
+            .children(self.albums.iter().enumerate().map(|(ix, album)| {
+                let (artist, title) = (album.artist.clone(), album.album.clone());
+                h_stack()
+                    .id(("incomplete-album", ix))
+                    .gap(px(8.))
+                    .py(px(1.))
+                    .cursor_pointer()
+                    .hover(|row| row.text_color(rgb(0x3D80DF)))
+                    .on_click(cx.listener(move |_, _, cx| {
+                        cx.emit(IncompleteAlbumsEvent::ShowAlbum(
+                            artist.clone(),
+                            title.clone(),
+                        ))
+                    }))
+                    .child(
+                        div()
+                            .w(px(280.))
+                            .overflow_hidden()
+                            .child(format!("{} — {}", album.album, album.artist)),
+                    )
+                    .child(div().w(px(60.)).child(album.summary()))
+                    .child(div().opacity(0.6).child(Self::missing_label(album)))
+            }))
+    }
+}
@@ -0,0 +1,183 @@
+use gpui::AppContext;
+use library::url_encode;
+use std::{
+    backtrace::Backtrace,
+    env, fs, io,
+    panic::{self, PanicHookInfo},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{paths, settings::Settings};
+
+const ISSUE_URL: &str = "https://github.com/iamnbutler/gpuitunes/issues/new";
+// Older reports are deleted once there are more than this
+const MAX_REPORTS: usize = 10;
+// Browsers and GitHub both give up on very long URLs
+const MAX_REPORT_LENGTH: usize = 6000;
+
+// What a report says about the app, kept up to date as it runs so the panic
+// hook has it to hand. Nothing here names a track, a playlist or a file.
+struct CrashContext {
+    tracks: usize,
+    playlists: usize,
+    last_action: Option<String>,
+}
+
+static CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    tracks: 0,
+    playlists: 0,
+    last_action: None,
+});
+
+fn reports_dir() -> PathBuf {
+    paths::data_dir().join("crashes")
+}
+
+/// Saves a report for any panic from here on, if crash reports are turned on.
+pub fn init(cx: &mut AppContext) {
+    if !cx.global::<Settings>().crash_reports.enabled {
+        return;
+    }
+
+    let dir = reports_dir();
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Err(e) = save_report(&dir, info) {
+            eprintln!("Failed to save crash report: {}", e);
+        }
+        previous_hook(info);
+    }));
+
+    // Only actions from key bindings come through here, menu items don't
+    cx.observe_keystrokes(|event, _| {
+        if let Some(action) = &event.action {
+            record_action(action.name());
+        }
+    })
+    .detach();
+}
+
+pub fn record_action(name: &str) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.last_action = Some(name.to_string());
+    }
+}
+
+pub fn set_library_size(tracks: usize, playlists: usize) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.tracks = tracks;
+        context.playlists = playlists;
+    }
+}
+
+// The home directory shows up in paths and names whoever's crash it is
+fn redact(text: &str) -> String {
+    match env::var("HOME").or_else(|_| env::var("USERPROFILE")) {
+        Ok(home) if !home.is_empty() => text.replace(&home, "~"),
+        _ => text.to_string(),
+    }
+}
+
+fn save_report(dir: &Path, info: &PanicHookInfo) -> io::Result<()> {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic".to_string());
+    let location = info
+        .location()
+        .map(|location| format!("{}:{}", location.file(), location.line()))
+        .unwrap_or_default();
+    // Never wait on the lock here, whatever holds it may be what panicked
+    let (tracks, playlists, last_action) = match CONTEXT.try_lock() {
+        Ok(context) => (
+            context.tracks,
+            context.playlists,
+            context.last_action.clone(),
+        ),
+        Err(_) => (0, 0, None),
+    };
+
+    let report = format!(
+        "Panic: {}\nAt: {}\nVersion: {}\nPlatform: {} {}\nLibrary: {} songs, {} playlists\nLast action: {}\n\n{}",
+        message,
+        location,
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH,
+        tracks,
+        playlists,
+        last_action.as_deref().unwrap_or("None"),
+        Backtrace::force_capture(),
+    );
+
+    fs::create_dir_all(dir)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    fs::write(
+        dir.join(format!("crash-{}.txt", timestamp)),
+        redact(&report),
+    )?;
+
+    let mut reports = saved_reports(dir);
+    while reports.len() > MAX_REPORTS {
+        fs::remove_file(reports.remove(0)).ok();
+    }
+    Ok(())
+}
+
+// Oldest first, the timestamps in the names sort the same way
+fn saved_reports(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("crash-") && name.ends_with(".txt"))
+        })
+        .collect();
+    reports.sort();
+    reports
+}
+
+pub fn last_report() -> Option<String> {
+    let path = saved_reports(&reports_dir()).pop()?;
+    fs::read_to_string(path).ok()
+}
+
+/// A new issue on GitHub, filled in with the report. Nothing is sent until
+/// whoever's filing it reads it over and submits it.
+pub fn report_url(report: &str) -> String {
+    let summary = report
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("Panic: "))
+        .unwrap_or("Crash");
+    let mut shown = report;
+    if shown.len() > MAX_REPORT_LENGTH {
+        let mut end = MAX_REPORT_LENGTH;
+        while !shown.is_char_boundary(end) {
+            end -= 1;
+        }
+        shown = &shown[..end];
+    }
+    let body = format!(
+        "**What were you doing when it crashed?**\n\n\n**Crash report**\n\n```\n{}\n```\n",
+        shown
+    );
+    format!(
+        "{}?title={}&labels=crash&body={}",
+        ISSUE_URL,
+        url_encode(&format!("Crash: {}", summary)),
+        url_encode(&body)
+    )
+}
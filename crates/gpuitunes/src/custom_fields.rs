@@ -0,0 +1,178 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{Library, TrackId};
+
+use crate::element::*;
+
+pub enum CustomFieldsEvent {
+    Dismissed,
+}
+
+/// Edits the custom fields of tracks, opened from the track context menu.
+/// Fields the tracks have are listed to take off, and one is set by typing
+/// "Key: 8A".
+pub struct CustomFieldsEditor {
+    library: Model<Library>,
+    tracks: Vec<TrackId>,
+    focus_handle: FocusHandle,
+    draft: String,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl EventEmitter<CustomFieldsEvent> for CustomFieldsEditor {}
+
+impl CustomFieldsEditor {
+    pub fn new(library: Model<Library>, tracks: Vec<TrackId>, cx: &mut ViewContext<Self>) -> Self {
+        let subscriptions = vec![cx.observe(&library, |_, _, cx| cx.notify())];
+
+        CustomFieldsEditor {
+            library,
+            tracks,
+            focus_handle: cx.focus_handle(),
+            draft: String::new(),
+            _subscriptions: subscriptions,
+        }
+    }
+
+    pub fn focus_handle(&self) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.platform || keystroke.modifiers.control {
+            return;
+        }
+        match keystroke.key.as_str() {
+            "enter" => self.set_draft(cx),
+            "backspace" => {
+                self.draft.pop();
+            }
+            "escape" => cx.emit(CustomFieldsEvent::Dismissed),
+            _ => match &keystroke.key_char {
+                Some(text) if !text.chars().any(char::is_control) => self.draft.push_str(text),
+                _ => return,
+            },
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    // A draft without a value is left to be finished
+    fn set_draft(&mut self, cx: &mut ViewContext<Self>) {
+        let Some((name, value)) = self.draft.split_once(':') else {
+            return;
+        };
+        let (name, value) = (name.trim().to_string(), value.trim().to_string());
+        if name.is_empty() || value.is_empty() {
+            return;
+        }
+        self.draft.clear();
+        self.set_field(&name, Some(&value), cx);
+    }
+
+    fn set_field(&mut self, name: &str, value: Option<&str>, cx: &mut ViewContext<Self>) {
+        let tracks = self.tracks.clone();
+        self.library.update(cx, |library, cx| {
+            library.set_custom_field(&tracks, name, value);
+            cx.notify();
+        });
+    }
+
+    // Each field any of the tracks has, with its value when they all share it
+    fn fields(&self, cx: &AppContext) -> Vec<(String, Option<String>)> {
+        let library = self.library.read(cx);
+        let tracks: Vec<_> = self
+            .tracks
+            .iter()
+            .filter_map(|id| library.track(id))
+            .collect();
+        library
+            .custom_field_names()
+            .into_iter()
+            .filter_map(|name| {
+                let mut values = tracks.iter().map(|track| track.custom_field(&name));
+                let first = values.clone().flatten().next()?.to_string();
+                let shared = values.all(|value| value == Some(first.as_str()));
+                Some((name, shared.then_some(first)))
+            })
+            .collect()
+    }
+}
+
+impl Render for CustomFieldsEditor {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let title = match self.tracks.as_slice() {
+            [id] => library
+                .track(id)
+                .map(|track| format!("Custom Fields for {}", track.title()))
+                .unwrap_or_default(),
+            tracks => format!("Custom Fields for {} Songs", tracks.len()),
+        };
+        let fields = self.fields(cx);
+
+        v_stack()
+            .id("custom-fields")
+            .occlude()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .on_mouse_down_out(cx.listener(|_, _, cx| cx.emit(CustomFieldsEvent::Dismissed)))
+            .w(px(260.))
+            .p(px(8.))
+            .gap(px(6.))
+            .rounded(px(4.))
+            .border_1()
+            .border_color(rgb(0xA0A0A0))
+            .bg(rgb(0xFFFFFF))
+            .shadow_lg()
+            .text_size(px(12.))
+            .text_color(rgb(0x0F1219))
+            .child(div().font_weight(FontWeight::BOLD).child(title))
+            .when(!fields.is_empty(), |editor| {
+                editor.child(v_stack().children(fields.into_iter().enumerate().map(
+                    |(ix, (name, value))| {
+                        h_stack()
+                            .gap(px(6.))
+                            .py(px(1.))
+                            .child(div().font_weight(FontWeight::BOLD).child(name.clone()))
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .overflow_hidden()
+                                    .when(value.is_none(), |value| value.opacity(0.6))
+                                    .child(value.unwrap_or_else(|| "Mixed".to_string())),
+                            )
+                            .child(
+                                div()
+                                    .id(("remove-custom-field", ix))
+                                    .px(px(4.))
+                                    .cursor_pointer()
+                                    .opacity(0.6)
+                                    .hover(|remove| remove.opacity(1.))
+                                    .on_click(cx.listener(move |this, _, cx| {
+                                        this.set_field(&name, None, cx)
+                                    }))
+                                    .child("×"),
+                            )
+                    },
+                )))
+            })
+            .child(
+                h_stack()
+                    .px(px(4.))
+                    .h(px(20.))
+                    .border_1()
+                    .border_color(rgb(0x9A9A9A))
+                    .overflow_hidden()
+                    .map(|field| {
+                        if self.draft.is_empty() {
+                            field
+                                .text_color(rgb(0x808080))
+                                .child("Type “Name: value” and press Return")
+                        } else {
+                            field.child(self.draft.clone())
+                        }
+                    }),
+            )
+    }
+}
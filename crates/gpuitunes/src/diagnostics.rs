@@ -0,0 +1,154 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::format_size;
+use playback::{
+    decode_memory, output_format, reset_peak_decode_memory, BufferSize, Heartbeat,
+    BUFFER_SIZE_PRESETS,
+};
+use std::{sync::Arc, time::Duration};
+
+use crate::{element::*, profile::config_dir, settings::Settings};
+
+// The stats are read from the engine's counters, so they're only as fresh
+// as the last redraw
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What playback is using and how it's coping, for tracking down stutters
+/// and memory growth.
+pub struct DiagnosticsView {
+    // The main engine's, which counts its underruns
+    heartbeat: Arc<Heartbeat>,
+    _refresh: Task<()>,
+}
+
+impl DiagnosticsView {
+    pub fn new(heartbeat: Arc<Heartbeat>, cx: &mut ViewContext<Self>) -> Self {
+        let refresh = cx.spawn(|this, mut cx| async move {
+            loop {
+                cx.background_executor().timer(REFRESH_INTERVAL).await;
+                if this.update(&mut cx, |_, cx| cx.notify()).is_err() {
+                    break;
+                }
+            }
+        });
+        DiagnosticsView {
+            heartbeat,
+            _refresh: refresh,
+        }
+    }
+
+    // Both engines reopen their output with it straight away
+    fn set_buffer_size(&mut self, buffer_size: BufferSize, cx: &mut ViewContext<Self>) {
+        cx.update_global::<Settings, _>(|settings, _| settings.output.buffer_size = buffer_size);
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        cx.notify();
+    }
+
+    fn render_buffer_sizes(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let current = cx.global::<Settings>().output.buffer_size;
+        let choices = std::iter::once(BufferSize::DeviceDefault).chain(
+            BUFFER_SIZE_PRESETS
+                .iter()
+                .map(|frames| BufferSize::Frames(*frames)),
+        );
+
+        h_stack()
+            .flex_wrap()
+            .gap(px(4.))
+            .pl(px(148.))
+            .children(choices.enumerate().map(|(ix, buffer_size)| {
+                let label = match buffer_size {
+                    BufferSize::DeviceDefault => "Default".to_string(),
+                    BufferSize::Frames(frames) => frames.to_string(),
+                };
+                small_button(("buffer-size", ix), label)
+                    .when(buffer_size == current, |button| {
+                        button.font_weight(FontWeight::BOLD)
+                    })
+                    .on_click(cx.listener(move |this, _, cx| this.set_buffer_size(buffer_size, cx)))
+            }))
+    }
+
+    fn render_row(label: &str, value: String) -> Div {
+        h_stack()
+            .gap(px(8.))
+            .child(
+                div()
+                    .w(px(140.))
+                    .flex()
+                    .justify_end()
+                    .text_color(rgb(0x606060))
+                    .child(label.to_string()),
+            )
+            .child(value)
+    }
+}
+
+impl Render for DiagnosticsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let memory = decode_memory();
+        let format = output_format();
+        let underruns = self.heartbeat.underruns();
+
+        v_stack()
+            .id("diagnostics")
+            .size_full()
+            .overflow_y_scroll()
+            .p(px(12.))
+            .gap(px(4.))
+            .bg(rgb(0xF0F0F0))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(13.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Playback Diagnostics"),
+            )
+            .child(Self::render_row(
+                "Output",
+                format
+                    .as_ref()
+                    .map(|format| format!("{}, {}", format.device_name, format.describe()))
+                    .unwrap_or_else(|| "Not open".to_string()),
+            ))
+            .child(Self::render_row(
+                "Buffer",
+                format
+                    .as_ref()
+                    .map(|format| format.describe_latency())
+                    .unwrap_or_else(|| "—".to_string()),
+            ))
+            .child(self.render_buffer_sizes(cx))
+            .child(
+                Self::render_row(
+                    "Underruns",
+                    format!(
+                        "{} ({} frames of silence)",
+                        underruns.count, underruns.silent_frames
+                    ),
+                )
+                .child(small_button("reset-underruns", "Reset").on_click(
+                    cx.listener(|this, _, cx| {
+                        this.heartbeat.reset_underruns();
+                        cx.notify();
+                    }),
+                )),
+            )
+            .child(Self::render_row(
+                "Decode memory",
+                format_size(memory.current as u64),
+            ))
+            .child(
+                Self::render_row("Peak decode memory", format_size(memory.peak as u64)).child(
+                    small_button("reset-peak-decode-memory", "Reset").on_click(cx.listener(
+                        |_, _, cx| {
+                            reset_peak_decode_memory();
+                            cx.notify();
+                        },
+                    )),
+                ),
+            )
+    }
+}
@@ -0,0 +1,133 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{AlbumCopy, DuplicateAlbum, Library};
+use std::path::PathBuf;
+
+use crate::element::*;
+
+/// Albums that are in more than one library folder, such as on the
+/// internal drive and an archive drive. The better copy is picked as
+/// they're found, and another can be picked here instead.
+pub struct DuplicatesView {
+    library: Model<Library>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl DuplicatesView {
+    pub fn new(library: Model<Library>, cx: &mut ViewContext<Self>) -> Self {
+        let subscriptions = vec![cx.observe(&library, |_, _, cx| cx.notify())];
+
+        DuplicatesView {
+            library,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn use_copies_in(&mut self, folder: PathBuf, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            library.use_copies_in(&folder);
+            cx.notify();
+        });
+    }
+
+    fn render_copy(
+        &self,
+        id: ElementId,
+        copy: &AlbumCopy,
+        roots: &[PathBuf],
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let quality = match (copy.lossless, copy.bitrate) {
+            (true, _) => "Lossless".to_string(),
+            (false, Some(bitrate)) => format!("{} kbps", bitrate),
+            (false, None) => "Unknown quality".to_string(),
+        };
+        let location = match copy.root.and_then(|root| roots.get(root)) {
+            Some(root) => format!("in {}", root.display()),
+            None => "outside the library folders".to_string(),
+        };
+        let folder = copy.folder.clone();
+
+        h_stack()
+            .gap(px(8.))
+            .py(px(2.))
+            .pl(px(12.))
+            .child(
+                v_stack()
+                    .flex_1()
+                    .overflow_hidden()
+                    .child(copy.folder.display().to_string())
+                    .child(div().opacity(0.6).child(format!(
+                        "{} · {} tracks · {}",
+                        quality, copy.tracks, location
+                    ))),
+            )
+            .map(|row| {
+                if copy.in_use {
+                    row.child(div().opacity(0.6).child("In Use"))
+                } else {
+                    row.child(small_button(id, "Use This Copy").on_click(
+                        cx.listener(move |this, _, cx| this.use_copies_in(folder.clone(), cx)),
+                    ))
+                }
+            })
+    }
+
+    fn render_album(
+        &self,
+        ix: usize,
+        album: &DuplicateAlbum,
+        roots: &[PathBuf],
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        v_stack()
+            .py(px(6.))
+            .border_b_1()
+            .border_color(rgb(0xD0D0D0))
+            .child(
+                div()
+                    .font_weight(FontWeight::BOLD)
+                    .child(format!("{} — {}", album.album, album.artist)),
+            )
+            .children(album.copies.iter().enumerate().map(|(copy_ix, copy)| {
+                let id = ElementId::Name(format!("use-copy-{}-{}", ix, copy_ix).into());
+                self.render_copy(id, copy, roots, cx)
+            }))
+    }
+}
+
+impl Render for DuplicatesView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let albums = library.duplicate_albums();
+        let roots = library.roots().to_vec();
+
+        v_stack()
+            .id("duplicates")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Duplicates"),
+            )
+            .when(albums.is_empty(), |view| {
+                view.child(
+                    div()
+                        .opacity(0.6)
+                        .child("No album is in more than one library folder."),
+                )
+            })
+            .children(
+                albums
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, album)| self.render_album(ix, album, &roots, cx)),
+            )
+    }
+}
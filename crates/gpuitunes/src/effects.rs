@@ -0,0 +1,186 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use playback::{DspRegistry, DspSettings, DspSlot, ParameterDescriptor};
+
+use crate::{element::*, profile::config_dir, settings::Settings};
+
+/// The plugins the chain can be built from. Anything registering its own
+/// does so before the main window opens.
+#[derive(Default)]
+pub struct Effects(pub DspRegistry);
+
+impl Global for Effects {}
+
+/// Edits the effects chain in settings: which plugins run, in what order,
+/// and with what parameters.
+pub struct EffectsView;
+
+impl EffectsView {
+    fn update_chain(&mut self, cx: &mut ViewContext<Self>, f: impl FnOnce(&mut DspSettings)) {
+        cx.update_global::<Settings, _>(|settings, _| f(&mut settings.dsp));
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        cx.notify();
+    }
+
+    fn render_parameter(
+        &self,
+        ix: usize,
+        descriptor: &'static ParameterDescriptor,
+        value: f32,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let step = descriptor.step();
+        h_stack()
+            .gap(px(6.))
+            .pl(px(16.))
+            .child(div().w(px(80.)).child(descriptor.name))
+            .child(
+                small_button(
+                    SharedString::from(format!("lower-{}-{}", ix, descriptor.id)),
+                    "−",
+                )
+                .on_click(cx.listener(move |this, _, cx| {
+                    this.update_chain(cx, |chain| {
+                        chain.set_parameter(ix, descriptor, value - step)
+                    })
+                })),
+            )
+            .child(
+                div()
+                    .w(px(60.))
+                    .text_center()
+                    .child(format!("{:.1} {}", value, descriptor.unit)),
+            )
+            .child(
+                small_button(
+                    SharedString::from(format!("raise-{}-{}", ix, descriptor.id)),
+                    "+",
+                )
+                .on_click(cx.listener(move |this, _, cx| {
+                    this.update_chain(cx, |chain| {
+                        chain.set_parameter(ix, descriptor, value + step)
+                    })
+                })),
+            )
+    }
+
+    fn render_slot(
+        &self,
+        ix: usize,
+        slot: DspSlot,
+        last: usize,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let registry = &cx.global::<Effects>().0;
+        let info = registry.plugin(&slot.plugin);
+        let name = info.map(|info| info.name).unwrap_or("Unknown Effect");
+        let parameters: Vec<(&'static ParameterDescriptor, f32)> = info
+            .map(|info| {
+                info.parameters
+                    .iter()
+                    .map(|descriptor| (descriptor, slot.parameter(descriptor)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        v_stack()
+            .gap(px(4.))
+            .py(px(6.))
+            .border_b_1()
+            .border_color(rgb(0xD0D0D0))
+            .child(
+                h_stack()
+                    .gap(px(6.))
+                    .child(
+                        div()
+                            .flex_1()
+                            .font_weight(FontWeight::BOLD)
+                            .when(slot.bypassed, |name| name.opacity(0.5))
+                            .child(name),
+                    )
+                    .child(
+                        small_button(
+                            ("bypass", ix),
+                            if slot.bypassed { "Enable" } else { "Bypass" },
+                        )
+                        .on_click(cx.listener(move |this, _, cx| {
+                            this.update_chain(cx, |chain| chain.toggle_bypass(ix))
+                        })),
+                    )
+                    .when(ix > 0, |row| {
+                        row.child(small_button(("move-up", ix), "↑").on_click(cx.listener(
+                            move |this, _, cx| {
+                                this.update_chain(cx, |chain| chain.move_slot(ix, -1))
+                            },
+                        )))
+                    })
+                    .when(ix < last, |row| {
+                        row.child(small_button(("move-down", ix), "↓").on_click(cx.listener(
+                            move |this, _, cx| {
+                                this.update_chain(cx, |chain| chain.move_slot(ix, 1))
+                            },
+                        )))
+                    })
+                    .child(small_button(("remove", ix), "✕").on_click(cx.listener(
+                        move |this, _, cx| this.update_chain(cx, |chain| chain.remove(ix)),
+                    ))),
+            )
+            .children(
+                parameters
+                    .into_iter()
+                    .map(|(descriptor, value)| self.render_parameter(ix, descriptor, value, cx)),
+            )
+    }
+}
+
+impl Render for EffectsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let chain = cx.global::<Settings>().dsp.chain.clone();
+        let last = chain.len().saturating_sub(1);
+        let plugins: Vec<(&'static str, &'static str)> = cx
+            .global::<Effects>()
+            .0
+            .plugins()
+            .iter()
+            .map(|plugin| (plugin.id, plugin.name))
+            .collect();
+
+        v_stack()
+            .id("effects")
+            .size_full()
+            .overflow_y_scroll()
+            .p(px(12.))
+            .bg(rgb(0xF0F0F0))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(13.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Effects"),
+            )
+            .children(
+                chain
+                    .into_iter()
+                    .enumerate()
+                    .map(|(ix, slot)| self.render_slot(ix, slot, last, cx)),
+            )
+            .child(
+                h_stack()
+                    .pt(px(10.))
+                    .gap(px(6.))
+                    .child(div().opacity(0.6).child("Add:"))
+                    .children(plugins.into_iter().map(|(id, name)| {
+                        div()
+                            .id(SharedString::from(format!("add-{}", id)))
+                            .cursor_pointer()
+                            .text_color(rgb(0x3D80DF))
+                            .child(name)
+                            .on_click(cx.listener(move |this, _, cx| {
+                                this.update_chain(cx, |chain| chain.add(id))
+                            }))
+                    })),
+            )
+    }
+}
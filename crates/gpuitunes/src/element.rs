@@ -1,6 +1,8 @@
 use gpui::prelude::FluentBuilder as _;
 use gpui::*;
+use library::{AlbumRating, MAX_RATING};
 use smallvec::{smallvec, SmallVec};
+use std::sync::Arc;
 
 use crate::assets::Icon;
 
@@ -81,6 +83,14 @@ pub fn vertical_linear_gradient(start: impl Into<Hsla>, stop: impl Into<Hsla>) -
     gpui::linear_gradient(180.0, start, end)
 }
 
+// Gives every digit the same width so numbers line up down a column
+pub fn tabular_figures(family: impl Into<SharedString>) -> Font {
+    Font {
+        features: FontFeatures(Arc::new(vec![("tnum".into(), 1)])),
+        ..font(family)
+    }
+}
+
 pub fn large_icon(icon: Icon) -> Svg {
     svg()
         .size(px(16.))
@@ -96,3 +106,63 @@ pub fn small_icon(icon: Icon) -> Svg {
         .path(icon.path())
         .text_color(rgb(0x000000))
 }
+
+// The small bevelled buttons in utility windows like Effects
+pub fn small_button(id: impl Into<ElementId>, label: impl Into<SharedString>) -> Stateful<Div> {
+    h_stack()
+        .id(id.into())
+        .justify_center()
+        .min_w(px(18.))
+        .h(px(16.))
+        .px(px(4.))
+        .rounded(px(3.))
+        .border_1()
+        .border_color(rgb(0x9A9A9A))
+        .bg(vertical_linear_gradient(rgb(0xFAFAFA), rgb(0xDCDCDC)))
+        .child(label.into())
+}
+
+pub fn context_menu_item(
+    id: impl Into<ElementId>,
+    label: impl Into<SharedString>,
+) -> Stateful<Div> {
+    div()
+        .id(id)
+        .px(px(18.))
+        .py(px(2.))
+        .hover(|item| item.bg(rgb(0x3D80DF)).text_color(rgb(0xFFFFFF)))
+        .child(label.into())
+}
+
+pub fn context_menu_label(label: impl Into<SharedString>) -> Div {
+    div()
+        .px(px(18.))
+        .py(px(2.))
+        .text_color(rgb(0x808080))
+        .child(label.into())
+}
+
+pub fn context_menu_separator() -> Div {
+    div().my(px(4.)).h(px(1.)).bg(rgb(0xD9D9D9))
+}
+
+// A hollow star stands in for a rating that was worked out rather than set
+pub fn star(filled: bool, hollow: bool) -> Div {
+    let glyph = match (filled, hollow) {
+        (false, _) => "·",
+        (true, false) => "★",
+        (true, true) => "☆",
+    };
+    div().w(px(10.)).flex_none().child(glyph)
+}
+
+pub fn stars(rating: u8, hollow: bool) -> Div {
+    h_stack().children((1..=MAX_RATING).map(|stars| star(stars <= rating, hollow)))
+}
+
+// Paired with the rating clicking each one gives the album
+pub fn album_stars(rating: Option<AlbumRating>) -> impl Iterator<Item = (u8, Div)> {
+    let hollow = rating.is_some_and(|rating| rating.is_computed());
+    let current = rating.map_or(0, |rating| rating.stars());
+    (1..=MAX_RATING).map(move |stars| (stars, star(stars <= current, hollow)))
+}
@@ -0,0 +1,334 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{write_tags, ChangeSource, Library, MetadataField, TrackId};
+
+use crate::{element::*, profile::config_dir, settings::Settings};
+
+// In the order they're laid out, and tabbed through
+const FIELDS: [MetadataField; 6] = [
+    MetadataField::Title,
+    MetadataField::Artist,
+    MetadataField::Album,
+    MetadataField::TrackNumber,
+    MetadataField::Genre,
+    MetadataField::Year,
+];
+
+pub enum GetInfoEvent {
+    Dismissed,
+}
+
+struct InfoField {
+    field: MetadataField,
+    // What every track has, `None` when they differ
+    shared: Option<String>,
+    value: String,
+    edited: bool,
+}
+
+/// Edits the tags of the selected tracks, opened with Get Info. Only the
+/// fields typed into are changed, so a field the tracks differ on is left
+/// as each has it unless it's given a value for them all.
+pub struct GetInfoView {
+    library: Model<Library>,
+    tracks: Vec<TrackId>,
+    focus_handle: FocusHandle,
+    fields: Vec<InfoField>,
+    // The field being typed into
+    active: usize,
+    // Whether the album plays straight through, when the tracks are all
+    // from one. `None` inside leaves it to be detected.
+    gapless: Option<Option<bool>>,
+    gapless_edited: bool,
+}
+
+impl EventEmitter<GetInfoEvent> for GetInfoView {}
+
+impl GetInfoView {
+    pub fn new(library: Model<Library>, tracks: Vec<TrackId>, cx: &mut ViewContext<Self>) -> Self {
+        let (fields, gapless) = {
+            let library = library.read(cx);
+            let tracks: Vec<_> = tracks.iter().filter_map(|id| library.track(id)).collect();
+            let gapless = tracks
+                .first()
+                .filter(|first| tracks.iter().all(|track| track.is_same_album(first)))
+                .map(|first| {
+                    library
+                        .album_tracks(first.id())
+                        .iter()
+                        .find_map(|track| track.gapless())
+                });
+            let fields = FIELDS
+                .into_iter()
+                .map(|field| {
+                    let mut values = tracks
+                        .iter()
+                        .map(|track| track.metadata(field).unwrap_or_default());
+                    let first = values.next().unwrap_or_default();
+                    let shared = values.all(|value| value == first).then_some(first);
+                    InfoField {
+                        field,
+                        value: shared.clone().unwrap_or_default(),
+                        shared,
+                        edited: false,
+                    }
+                })
+                .collect();
+            (fields, gapless)
+        };
+
+        GetInfoView {
+            library,
+            tracks,
+            focus_handle: cx.focus_handle(),
+            fields,
+            active: 0,
+            gapless,
+            gapless_edited: false,
+        }
+    }
+
+    pub fn focus_handle(&self) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.platform || keystroke.modifiers.control {
+            return;
+        }
+        match keystroke.key.as_str() {
+            "enter" => self.save(cx),
+            "escape" => cx.emit(GetInfoEvent::Dismissed),
+            "tab" if keystroke.modifiers.shift => {
+                self.active = (self.active + self.fields.len() - 1) % self.fields.len()
+            }
+            "tab" => self.active = (self.active + 1) % self.fields.len(),
+            "backspace" => {
+                let field = &mut self.fields[self.active];
+                field.value.pop();
+                field.edited = true;
+            }
+            _ => match &keystroke.key_char {
+                Some(text) if !text.chars().any(char::is_control) => {
+                    let field = &mut self.fields[self.active];
+                    field.value.push_str(text);
+                    field.edited = true;
+                }
+                _ => return,
+            },
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    fn toggle_write_tags(&mut self, cx: &mut ViewContext<Self>) {
+        cx.update_global::<Settings, _>(|settings, _| {
+            settings.import.write_edited_tags = !settings.import.write_edited_tags
+        });
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        cx.notify();
+    }
+
+    // Detected, then on, then off
+    fn cycle_gapless(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(gapless) = &mut self.gapless {
+            *gapless = match gapless {
+                None => Some(true),
+                Some(true) => Some(false),
+                Some(false) => None,
+            };
+            self.gapless_edited = true;
+            cx.notify();
+        }
+    }
+
+    // Logged as one change, so it can be rolled back together
+    fn save(&mut self, cx: &mut ViewContext<Self>) {
+        let edits: Vec<(TrackId, MetadataField, Option<String>)> = self
+            .fields
+            .iter()
+            .filter(|field| field.edited && field.shared.as_ref() != Some(&field.value))
+            .flat_map(|field| {
+                let value = Some(field.value.trim().to_string()).filter(|value| !value.is_empty());
+                self.tracks
+                    .iter()
+                    .map(move |id| (id.clone(), field.field, value.clone()))
+            })
+            .collect();
+        if let (Some(gapless), true, Some(id)) =
+            (self.gapless, self.gapless_edited, self.tracks.first())
+        {
+            self.library.update(cx, |library, cx| {
+                library.set_album_gapless(id, gapless);
+                cx.notify();
+            });
+        }
+        if !edits.is_empty() {
+            self.library.update(cx, |library, cx| {
+                library.edit_metadata_batch(edits, ChangeSource::User);
+                cx.notify();
+            });
+            if cx.global::<Settings>().import.write_edited_tags {
+                self.write_tags(cx);
+            }
+        }
+        cx.emit(GetInfoEvent::Dismissed);
+    }
+
+    // Done off the main thread, and the files rewritten are checksummed
+    // again so Verify Library Files doesn't flag them
+    fn write_tags(&mut self, cx: &mut ViewContext<Self>) {
+        let library = self.library.read(cx);
+        let files: Vec<_> = self
+            .tracks
+            .iter()
+            .filter_map(|id| {
+                let track = library.track(id)?;
+                Some((id.clone(), track.path()?.to_path_buf(), track.clone()))
+            })
+            .collect();
+
+        let library = self.library.clone();
+        cx.spawn(|_, mut cx| async move {
+            let written = cx
+                .background_executor()
+                .spawn(async move {
+                    files
+                        .into_iter()
+                        .filter_map(|(id, path, track)| match write_tags(&path, &track) {
+                            Ok(written) => written.then_some(id),
+                            Err(e) => {
+                                eprintln!("Failed to write tags to {}: {}", path.display(), e);
+                                None
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            library
+                .update(&mut cx, |library, _| library.rechecksum(&written))
+                .ok();
+        })
+        .detach();
+    }
+
+    fn render_field(&self, ix: usize, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let field = &self.fields[ix];
+        let active = ix == self.active;
+        let mixed = field.shared.is_none() && !field.edited;
+
+        h_stack()
+            .gap(px(8.))
+            .child(
+                div()
+                    .w(px(90.))
+                    .flex()
+                    .justify_end()
+                    .text_color(rgb(0x606060))
+                    .child(field.field.name()),
+            )
+            .child(
+                h_stack()
+                    .id(("info-field", ix))
+                    .flex_1()
+                    .px(px(4.))
+                    .h(px(20.))
+                    .border_1()
+                    .border_color(if active { rgb(0x3D80DF) } else { rgb(0x9A9A9A) })
+                    .overflow_hidden()
+                    .cursor_text()
+                    .on_click(cx.listener(move |this, _, cx| {
+                        this.active = ix;
+                        cx.notify();
+                    }))
+                    .map(|input| {
+                        if mixed {
+                            input.text_color(rgb(0x808080)).child("Mixed")
+                        } else {
+                            input.child(field.value.clone())
+                        }
+                    }),
+            )
+    }
+}
+
+impl Render for GetInfoView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let title = match self.tracks.as_slice() {
+            [id] => library
+                .track(id)
+                .map(|track| format!("Info for {}", track.title()))
+                .unwrap_or_default(),
+            tracks => format!("Info for {} Songs", tracks.len()),
+        };
+        let write_tags = cx.global::<Settings>().import.write_edited_tags;
+
+        v_stack()
+            .id("get-info")
+            .occlude()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .w(px(380.))
+            .p(px(12.))
+            .gap(px(6.))
+            .rounded(px(4.))
+            .border_1()
+            .border_color(rgb(0xA0A0A0))
+            .bg(rgb(0xFFFFFF))
+            .shadow_lg()
+            .text_size(px(12.))
+            .text_color(rgb(0x0F1219))
+            .child(
+                div()
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(4.))
+                    .overflow_hidden()
+                    .child(title),
+            )
+            .children((0..self.fields.len()).map(|ix| self.render_field(ix, cx)))
+            .child(
+                div()
+                    .id("write-edited-tags")
+                    .pl(px(98.))
+                    .pt(px(4.))
+                    .cursor_pointer()
+                    .on_click(cx.listener(|this, _, cx| this.toggle_write_tags(cx)))
+                    .child(if write_tags {
+                        "✓ Write tags to MP3 files"
+                    } else {
+                        "Write tags to MP3 files"
+                    }),
+            )
+            .when_some(self.gapless, |view, gapless| {
+                view.child(
+                    div()
+                        .id("album-gapless")
+                        .pl(px(98.))
+                        .cursor_pointer()
+                        .on_click(cx.listener(|this, _, cx| this.cycle_gapless(cx)))
+                        .child(match gapless {
+                            None => "Gapless album: Detect",
+                            Some(true) => "Gapless album: On",
+                            Some(false) => "Gapless album: Off",
+                        }),
+                )
+            })
+            .child(
+                h_stack()
+                    .justify_end()
+                    .gap(px(8.))
+                    .pt(px(6.))
+                    .child(
+                        small_button("get-info-cancel", "Cancel")
+                            .on_click(cx.listener(|_, _, cx| cx.emit(GetInfoEvent::Dismissed))),
+                    )
+                    .child(
+                        small_button("get-info-ok", "OK")
+                            .on_click(cx.listener(|this, _, cx| this.save(cx))),
+                    ),
+            )
+    }
+}
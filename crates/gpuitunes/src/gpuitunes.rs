@@ -1,51 +1,396 @@
 #![allow(dead_code)]
 
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex};
 
 use app::{AppState, AppWindow};
 use assets::Assets;
+use effects::Effects;
 use gpui::*;
-use library::Library;
+use library::{open_library_store, Library, LibraryBackend};
+use platform::WindowChrome;
+use profile::{config_dir, data_dir, Profiles};
+use serde::Deserialize;
+use settings::Settings;
+use share::OpenedLinks;
+use title_bar::{
+    BalanceLeft, BalanceRight, ResetBalance, SkipNext, SkipPrev, ToggleLimiter, ToggleMute,
+    ToggleNightMode, TogglePlayback, VolumeDecrease, VolumeIncrease,
+};
 
+mod albums;
+mod announce;
 mod app;
+mod artist_info;
 mod assets;
+mod capture;
+mod cleanup;
+mod completeness;
+mod crash;
+mod custom_fields;
+mod diagnostics;
+mod duplicates;
+mod effects;
 mod element;
+mod get_info;
+mod import_rules;
+mod imports;
+mod media_controls;
+mod moods;
+mod new_releases;
+mod overlay;
+mod paths;
+mod platform;
+mod playlist_history;
+mod power;
+mod profile;
+mod progress;
+mod remote_settings;
+mod scripting;
+mod scrollbar;
+mod search;
+mod session;
+mod settings;
+mod share;
+mod storage;
+mod stream_cache;
 mod title_bar;
+mod up_next;
+mod verify;
+mod volume;
+mod widget;
+mod wishlist;
 
-actions!(gpuitunes, [Quit, Minimize, FullScreen]);
+actions!(
+    gpuitunes,
+    [
+        Quit,
+        Minimize,
+        Maximize,
+        FullScreen,
+        CopyLink,
+        CopyAlbumLink,
+        ShareLink,
+        FocusSearch,
+        CheckLibraryIntegrity,
+        VerifyLibraryFiles,
+        ImportArchive,
+        MarkAsPlayed,
+        MarkAsUnplayed,
+        SaveArtworkAs,
+        ShowArtwork,
+        PlayFolder,
+        CopySortAsManualOrder,
+        ShuffleOff,
+        ShuffleSongs,
+        ShuffleByAlbum,
+        ShuffleByGrouping,
+        CleanUpArtwork,
+        ExportEmbeddedArtwork,
+        EmbedFolderArtwork,
+        ExportLibraryStatistics,
+        ExportLibraryManifest,
+        CompareLibraryManifest,
+        ToggleUpNext,
+        MoveQueuedUp,
+        MoveQueuedDown,
+        RemoveQueued,
+        ClearUpNext,
+        SaveUpNextAsPlaylist,
+        ShowLibrary,
+        ShowRecentlyDeleted,
+        ShowStorageReport,
+        ShowNewReleases,
+        ShowWishlist,
+        ShowDuplicates,
+        ShowMoods,
+        ShowIncompleteAlbums,
+        ShowTagCleanup,
+        AnalyzeKeys,
+        WorkOffline,
+        TagMood,
+        AddLibraryFolder,
+        NewPlaylist,
+        TogglePrivateListening,
+        EmptyRecentlyDeleted,
+        PageUp,
+        PageDown,
+        ScrollToTop,
+        ScrollToBottom,
+        NewProfile,
+        ReportLastCrash,
+        ShowEffects,
+        ShowDiagnostics,
+        ShowStreamCache,
+        ShowRemoteSettings,
+        ShowImportRules,
+        RecordFromInput,
+        ExportNowPlaying,
+        SelectAll,
+        DeleteSelection,
+        SelectPrevious,
+        SelectNext,
+        PlaySelection,
+        ToggleOutputProfiles,
+        ToggleAnnouncements,
+        GetInfo,
+        ToggleAutoplay,
+        Undo,
+        Redo,
+        ShowImports,
+        UndoLastImport,
+        ShowAlbums
+    ]
+);
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct SwitchProfile {
+    pub name: String,
+}
+
+impl_actions!(gpuitunes, [SwitchProfile]);
 
 fn main() {
-    App::new().with_assets(Assets).run(|cx: &mut AppContext| {
+    let app = App::new().with_assets(Assets);
+    let (opened_link, opened_links) = mpsc::channel();
+    app.on_open_urls(move |urls| {
+        for url in urls {
+            opened_link.send(url).ok();
+        }
+    });
+    app.run(|cx: &mut AppContext| {
+        cx.set_global(OpenedLinks(opened_links));
+        cx.set_global(Profiles::load());
+        cx.set_global(Settings::load(&config_dir(cx)));
+        power::init(cx);
+        crash::init(cx);
+        cx.set_global(Effects::default());
         cx.activate(true);
         cx.on_action(|_: &Quit, cx| cx.quit());
-        cx.bind_keys([KeyBinding::new("cmd-q", Quit, None)]);
-        cx.set_menus(vec![Menu {
-            name: "gpuiTunes".into(),
-            items: vec![MenuItem::action("Quit", Quit)],
-        }]);
-
-        cx.open_window(
-            WindowOptions {
-                titlebar: None,
-                window_bounds: Some(gpui::WindowBounds::Windowed(Bounds {
-                    origin: point(px(0.), px(0.)),
-                    size: Size {
-                        width: px(1018.),
-                        height: px(708.),
-                    },
-                })),
-                ..Default::default()
-            },
-            |cx| {
-                let state = Arc::new(AppState::new(cx));
-
-                let library = Library::default();
-
-                let library = cx.new_model(|_| library);
-
-                cx.new_view(|cx| AppWindow::new(library, state.clone(), cx))
-            },
-        )
-        .unwrap();
+        cx.bind_keys([
+            KeyBinding::new("cmd-q", Quit, None),
+            KeyBinding::new("cmd-up", VolumeIncrease, None),
+            KeyBinding::new("cmd-down", VolumeDecrease, None),
+            KeyBinding::new("cmd-alt-down", ToggleMute, None),
+            KeyBinding::new("cmd-alt-left", BalanceLeft, None),
+            KeyBinding::new("cmd-alt-right", BalanceRight, None),
+            KeyBinding::new("cmd-alt-up", ResetBalance, None),
+            KeyBinding::new("cmd-up", MoveQueuedUp, Some("UpNext")),
+            KeyBinding::new("cmd-down", MoveQueuedDown, Some("UpNext")),
+            KeyBinding::new("backspace", RemoveQueued, Some("UpNext")),
+            KeyBinding::new("delete", RemoveQueued, Some("UpNext")),
+            KeyBinding::new("pageup", PageUp, Some("LibraryView")),
+            KeyBinding::new("pagedown", PageDown, Some("LibraryView")),
+            KeyBinding::new("home", ScrollToTop, Some("LibraryView")),
+            KeyBinding::new("end", ScrollToBottom, Some("LibraryView")),
+            KeyBinding::new("t", TagMood, Some("LibraryView")),
+            KeyBinding::new("cmd-a", SelectAll, Some("LibraryView")),
+            KeyBinding::new("cmd-i", GetInfo, Some("LibraryView")),
+            KeyBinding::new("up", SelectPrevious, Some("LibraryView")),
+            KeyBinding::new("down", SelectNext, Some("LibraryView")),
+            KeyBinding::new("enter", PlaySelection, Some("LibraryView")),
+            KeyBinding::new("space", TogglePlayback, Some("LibraryView")),
+            KeyBinding::new("backspace", DeleteSelection, Some("LibraryView")),
+            KeyBinding::new("delete", DeleteSelection, Some("LibraryView")),
+            KeyBinding::new("cmd-z", Undo, None),
+            KeyBinding::new("cmd-shift-z", Redo, None),
+            KeyBinding::new("cmd-f", FocusSearch, None),
+            KeyBinding::new("cmd-n", NewPlaylist, None),
+        ]);
+        cx.set_menus(app_menus(cx.global::<Profiles>()));
+
+        open_main_window(cx);
     });
 }
+
+// The app menu doubles as the profile switcher, with the current one checked
+fn app_menus(profiles: &Profiles) -> Vec<Menu> {
+    let mut profile_items: Vec<MenuItem> = profiles
+        .names
+        .iter()
+        .map(|name| {
+            let label = if *name == profiles.current {
+                format!("✓ {}", name)
+            } else {
+                name.clone()
+            };
+            MenuItem::action(label, SwitchProfile { name: name.clone() })
+        })
+        .collect();
+    profile_items.push(MenuItem::separator());
+    profile_items.push(MenuItem::action("New Profile", NewProfile));
+    profile_items.push(MenuItem::separator());
+    profile_items.push(MenuItem::action("Quit", Quit));
+
+    vec![
+        Menu {
+            name: "gpuiTunes".into(),
+            items: profile_items,
+        },
+        Menu {
+            name: "File".into(),
+            items: vec![
+                MenuItem::action("New Playlist", NewPlaylist),
+                MenuItem::separator(),
+                MenuItem::action("Import Archive…", ImportArchive),
+                MenuItem::action("Import Rules…", ShowImportRules),
+                MenuItem::action("Play Folder…", PlayFolder),
+                MenuItem::action("Add Library Folder…", AddLibraryFolder),
+                MenuItem::action("Record from Input…", RecordFromInput),
+                MenuItem::action("Undo Last Import…", UndoLastImport),
+                MenuItem::separator(),
+                MenuItem::action("Get Info", GetInfo),
+                MenuItem::action("Save Artwork As…", SaveArtworkAs),
+                MenuItem::action("Export Embedded Artwork…", ExportEmbeddedArtwork),
+                MenuItem::action("Embed Folder Artwork…", EmbedFolderArtwork),
+                MenuItem::action("Export Library Statistics…", ExportLibraryStatistics),
+                MenuItem::action("Export Library Manifest…", ExportLibraryManifest),
+                MenuItem::action("Compare with Library Manifest…", CompareLibraryManifest),
+            ],
+        },
+        Menu {
+            name: "Edit".into(),
+            items: vec![
+                MenuItem::action("Undo", Undo),
+                MenuItem::action("Redo", Redo),
+                MenuItem::separator(),
+                MenuItem::action("Copy Link", CopyLink),
+                MenuItem::action("Copy Album Link", CopyAlbumLink),
+                MenuItem::action("Share To…", ShareLink),
+                MenuItem::separator(),
+                MenuItem::action("Select All", SelectAll),
+                MenuItem::action("Find", FocusSearch),
+                MenuItem::separator(),
+                MenuItem::action("Copy Sort as Manual Order", CopySortAsManualOrder),
+                MenuItem::separator(),
+                MenuItem::action("Empty Recently Deleted…", EmptyRecentlyDeleted),
+            ],
+        },
+        Menu {
+            name: "View".into(),
+            items: vec![
+                MenuItem::action("Library", ShowLibrary),
+                MenuItem::action("Albums", ShowAlbums),
+                MenuItem::action("Recently Deleted", ShowRecentlyDeleted),
+                MenuItem::separator(),
+                MenuItem::action("Storage Report", ShowStorageReport),
+                MenuItem::action("New Releases", ShowNewReleases),
+                MenuItem::action("Wishlist", ShowWishlist),
+                MenuItem::action("Duplicates", ShowDuplicates),
+                MenuItem::action("Moods", ShowMoods),
+                MenuItem::action("Incomplete Albums", ShowIncompleteAlbums),
+                MenuItem::action("Imports", ShowImports),
+            ],
+        },
+        Menu {
+            name: "Controls".into(),
+            items: vec![
+                MenuItem::action("Play", TogglePlayback),
+                MenuItem::action("Next", SkipNext),
+                MenuItem::action("Previous", SkipPrev),
+                MenuItem::separator(),
+                MenuItem::action("Increase Volume", VolumeIncrease),
+                MenuItem::action("Decrease Volume", VolumeDecrease),
+                MenuItem::action("Mute", ToggleMute),
+                MenuItem::action("Balance Left", BalanceLeft),
+                MenuItem::action("Balance Right", BalanceRight),
+                MenuItem::action("Center Balance", ResetBalance),
+                MenuItem::action("Limiter", ToggleLimiter),
+                MenuItem::action("Night Mode", ToggleNightMode),
+                MenuItem::action("Remember Sound per Output", ToggleOutputProfiles),
+                MenuItem::action("Announce Tracks", ToggleAnnouncements),
+                MenuItem::action("Autoplay", ToggleAutoplay),
+                MenuItem::separator(),
+                MenuItem::action("Shuffle Off", ShuffleOff),
+                MenuItem::action("Shuffle Songs", ShuffleSongs),
+                MenuItem::action("Shuffle by Album", ShuffleByAlbum),
+                MenuItem::action("Shuffle by Grouping", ShuffleByGrouping),
+                MenuItem::separator(),
+                MenuItem::action("Private Listening", TogglePrivateListening),
+                MenuItem::action("Effects…", ShowEffects),
+                MenuItem::separator(),
+                MenuItem::action("Show Artwork", ShowArtwork),
+                MenuItem::action("Show Up Next", ToggleUpNext),
+                MenuItem::action("Clear Up Next", ClearUpNext),
+                MenuItem::action("Save Up Next as Playlist…", SaveUpNextAsPlaylist),
+            ],
+        },
+        Menu {
+            name: "Advanced".into(),
+            items: vec![
+                MenuItem::action("Check Library Integrity", CheckLibraryIntegrity),
+                MenuItem::action("Verify Library Files", VerifyLibraryFiles),
+                MenuItem::action("Clean Up Tags…", ShowTagCleanup),
+                MenuItem::action("Analyze Musical Keys", AnalyzeKeys),
+                MenuItem::action("Clean Up Artwork Cache", CleanUpArtwork),
+                MenuItem::action("Playback Diagnostics", ShowDiagnostics),
+                MenuItem::action("Network Stream Cache…", ShowStreamCache),
+                MenuItem::action("Remote Control…", ShowRemoteSettings),
+                MenuItem::separator(),
+                MenuItem::action("Work Offline", WorkOffline),
+                MenuItem::action("Export Now Playing for Streaming", ExportNowPlaying),
+            ],
+        },
+        Menu {
+            name: "Help".into(),
+            items: vec![MenuItem::action("Report Last Crash…", ReportLastCrash)],
+        },
+    ]
+}
+
+fn open_main_window(cx: &mut AppContext) {
+    let chrome = WindowChrome::current();
+    cx.open_window(
+        WindowOptions {
+            titlebar: chrome.titlebar(),
+            window_decorations: chrome.decorations(),
+            window_bounds: Some(gpui::WindowBounds::Windowed(Bounds {
+                origin: point(px(0.), px(0.)),
+                size: Size {
+                    width: px(1018.),
+                    height: px(708.),
+                },
+            })),
+            ..Default::default()
+        },
+        |cx| {
+            let state = Arc::new(AppState::new(cx));
+
+            let data_dir = data_dir(cx);
+            let backend = cx.global::<Settings>().library_store.backend;
+            let mut store = open_library_store(&data_dir, backend).unwrap_or_else(|e| {
+                eprintln!("Failed to open library store: {}", e);
+                open_library_store(&data_dir, LibraryBackend::Json).unwrap()
+            });
+            // A library that couldn't be read is left alone rather than
+            // saved over
+            let (saved, store) = match store.load() {
+                Ok(saved) => (saved, Some(Arc::new(Mutex::new(store)))),
+                Err(e) => {
+                    eprintln!("Failed to load library: {}", e);
+                    (None, None)
+                }
+            };
+
+            let roots = cx.global::<Settings>().import.library_roots();
+            let library = Library::new(cx, saved, roots);
+
+            cx.new_view(|cx| AppWindow::new(library, store, state.clone(), cx))
+        },
+    )
+    .unwrap();
+}
+
+/// Makes `name` the current profile and reopens the app with its data.
+pub fn activate_profile(name: String, cx: &mut AppContext) {
+    cx.update_global::<Profiles, _>(|profiles, _| profiles.current = name);
+    if let Err(e) = cx.global::<Profiles>().save() {
+        eprintln!("Failed to save profiles: {}", e);
+    }
+    cx.set_global(Settings::load(&config_dir(cx)));
+    cx.set_menus(app_menus(cx.global::<Profiles>()));
+
+    for window in cx.windows() {
+        window.update(cx, |_, cx| cx.remove_window()).ok();
+    }
+    open_main_window(cx);
+}
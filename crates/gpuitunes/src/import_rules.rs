@@ -0,0 +1,289 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{audio_files_in, import_actions, ImportAction, ImportRule, ImportedFile};
+use std::path::PathBuf;
+
+use crate::{element::*, profile::config_dir, settings::Settings};
+
+// Enough to see what the rules do without listing a whole library
+const MAX_PREVIEW_FILES: usize = 200;
+
+/// Edits the rules every import runs through. New rules are typed the way
+/// they're listed, and a dry run shows what they'd do to a folder without
+/// importing anything.
+pub struct ImportRulesView {
+    focus_handle: FocusHandle,
+    draft: String,
+    error: Option<String>,
+    // Each file from the last dry run, with what the rules would do to it
+    preview: Option<Vec<(PathBuf, Vec<ImportAction>)>>,
+}
+
+impl ImportRulesView {
+    pub fn new(cx: &mut ViewContext<Self>) -> Self {
+        ImportRulesView {
+            focus_handle: cx.focus_handle(),
+            draft: String::new(),
+            error: None,
+            preview: None,
+        }
+    }
+
+    fn update_rules(&mut self, cx: &mut ViewContext<Self>, f: impl FnOnce(&mut Vec<ImportRule>)) {
+        cx.update_global::<Settings, _>(|settings, _| f(&mut settings.import.rules));
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        // Whatever was previewed was for the old rules
+        self.preview = None;
+        cx.notify();
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.platform || keystroke.modifiers.control {
+            return;
+        }
+        match keystroke.key.as_str() {
+            "enter" => self.add_draft(cx),
+            "backspace" => {
+                self.draft.pop();
+            }
+            "escape" => self.draft.clear(),
+            _ => match &keystroke.key_char {
+                Some(text) if !text.chars().any(char::is_control) => self.draft.push_str(text),
+                _ => return,
+            },
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    fn add_draft(&mut self, cx: &mut ViewContext<Self>) {
+        match ImportRule::parse(&self.draft) {
+            Ok(rule) => {
+                self.draft.clear();
+                self.error = None;
+                self.update_rules(cx, |rules| rules.push(rule));
+            }
+            Err(error) => {
+                self.error = Some(error);
+                cx.notify();
+            }
+        }
+    }
+
+    fn move_rule(&mut self, ix: usize, offset: isize, cx: &mut ViewContext<Self>) {
+        self.update_rules(cx, |rules| {
+            let target = ix as isize + offset;
+            if ix < rules.len() && target >= 0 && (target as usize) < rules.len() {
+                rules.swap(ix, target as usize);
+            }
+        });
+    }
+
+    // Reads the folder the same way dropping it on the window would, but
+    // only reports what the rules would do
+    fn preview_folder(&mut self, cx: &mut ViewContext<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+        });
+        let rules = cx.global::<Settings>().import.rules.clone();
+
+        cx.spawn(|this, mut cx| async move {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let preview = cx
+                .background_executor()
+                .spawn(async move {
+                    paths
+                        .iter()
+                        .flat_map(|folder| audio_files_in(folder))
+                        .take(MAX_PREVIEW_FILES)
+                        .map(|path| {
+                            let album = path
+                                .parent()
+                                .and_then(|folder| folder.file_name())
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default();
+                            let file =
+                                ImportedFile::from_path(path.clone(), "Unknown Artist", &album);
+                            (path, import_actions(&rules, &file))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.preview = Some(preview);
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn render_rule(
+        &self,
+        ix: usize,
+        rule: &ImportRule,
+        last: usize,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        h_stack()
+            .gap(px(6.))
+            .py(px(4.))
+            .border_b_1()
+            .border_color(rgb(0xD0D0D0))
+            .child(
+                div()
+                    .flex_1()
+                    .overflow_hidden()
+                    .when(!rule.enabled, |description| description.opacity(0.5))
+                    .child(rule.description()),
+            )
+            .child(
+                small_button(
+                    ("enable", ix),
+                    if rule.enabled { "Disable" } else { "Enable" },
+                )
+                .on_click(cx.listener(move |this, _, cx| {
+                    this.update_rules(cx, |rules| {
+                        if let Some(rule) = rules.get_mut(ix) {
+                            rule.enabled = !rule.enabled;
+                        }
+                    })
+                })),
+            )
+            .when(ix > 0, |row| {
+                row.child(
+                    small_button(("move-up", ix), "↑")
+                        .on_click(cx.listener(move |this, _, cx| this.move_rule(ix, -1, cx))),
+                )
+            })
+            .when(ix < last, |row| {
+                row.child(
+                    small_button(("move-down", ix), "↓")
+                        .on_click(cx.listener(move |this, _, cx| this.move_rule(ix, 1, cx))),
+                )
+            })
+            .child(
+                small_button(("remove", ix), "✕").on_click(cx.listener(move |this, _, cx| {
+                    this.update_rules(cx, |rules| {
+                        if ix < rules.len() {
+                            rules.remove(ix);
+                        }
+                    })
+                })),
+            )
+    }
+
+    fn render_preview(&self) -> Option<impl IntoElement> {
+        let preview = self.preview.as_ref()?;
+        let matched: Vec<_> = preview
+            .iter()
+            .filter(|(_, actions)| !actions.is_empty())
+            .collect();
+        let summary = format!(
+            "Dry run: {} of {} files would be changed",
+            matched.len(),
+            preview.len()
+        );
+
+        Some(
+            v_stack()
+                .pt(px(10.))
+                .gap(px(4.))
+                .child(div().font_weight(FontWeight::BOLD).child(summary))
+                .children(matched.into_iter().map(|(path, actions)| {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let actions: Vec<String> =
+                        actions.iter().map(ImportAction::description).collect();
+                    v_stack()
+                        .child(div().overflow_hidden().child(name))
+                        .child(div().pl(px(12.)).opacity(0.6).child(actions.join(", ")))
+                })),
+        )
+    }
+}
+
+impl Render for ImportRulesView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let rules = cx.global::<Settings>().import.rules.clone();
+        let last = rules.len().saturating_sub(1);
+
+        v_stack()
+            .id("import-rules")
+            .size_full()
+            .overflow_y_scroll()
+            .p(px(12.))
+            .bg(rgb(0xF0F0F0))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(13.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Import Rules"),
+            )
+            .when(rules.is_empty(), |view| {
+                view.child(div().opacity(0.6).child("No rules yet."))
+            })
+            .children(
+                rules
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, rule)| self.render_rule(ix, rule, last, cx)),
+            )
+            .child(
+                h_stack()
+                    .id("draft")
+                    .track_focus(&self.focus_handle)
+                    .on_key_down(cx.listener(Self::key_down))
+                    .on_click(cx.listener(|this, _, cx| cx.focus(&this.focus_handle)))
+                    .mt(px(10.))
+                    .px(px(4.))
+                    .h(px(20.))
+                    .border_1()
+                    .border_color(if self.error.is_some() {
+                        rgb(0xC4312B)
+                    } else {
+                        rgb(0x9A9A9A)
+                    })
+                    .bg(rgb(0xFFFFFF))
+                    .overflow_hidden()
+                    .map(|field| {
+                        if self.draft.is_empty() {
+                            field
+                                .text_color(rgb(0x808080))
+                                .child("path contains /Singles/ -> playlist Singles")
+                        } else {
+                            field.child(self.draft.clone())
+                        }
+                    }),
+            )
+            .children(
+                self.error
+                    .clone()
+                    .map(|error| div().text_color(rgb(0xC4312B)).child(error)),
+            )
+            .child(
+                h_stack()
+                    .pt(px(6.))
+                    .gap(px(6.))
+                    .child(
+                        small_button("add-rule", "Add Rule")
+                            .on_click(cx.listener(|this, _, cx| this.add_draft(cx))),
+                    )
+                    .child(
+                        small_button("preview", "Dry Run…")
+                            .on_click(cx.listener(|this, _, cx| this.preview_folder(cx))),
+                    ),
+            )
+            .children(self.render_preview())
+    }
+}
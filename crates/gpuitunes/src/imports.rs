@@ -0,0 +1,166 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{date_for_timestamp, remove_imported_files, ImportSession, Library, TrackId};
+
+use crate::{element::*, settings::Settings};
+
+/// Every import, newest first, with how much of it is still in the
+/// library. Any of them can be rolled back as a whole.
+pub struct ImportsView {
+    library: Model<Library>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl ImportsView {
+    pub fn new(library: Model<Library>, cx: &mut ViewContext<Self>) -> Self {
+        let subscriptions = vec![cx.observe(&library, |_, _, cx| cx.notify())];
+
+        ImportsView {
+            library,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn describe_album(id: &TrackId, library: &Library) -> Option<String> {
+        let track = library.track(id)?;
+        Some(format!("{} — {}", track.album(), track.artist()))
+    }
+
+    fn render_session(
+        &self,
+        session: &ImportSession,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let id = session.id;
+        let library = self.library.read(cx);
+        let remaining = library.import_tracks_remaining(session);
+        // Named after the album its first song is on
+        let album = session
+            .tracks
+            .iter()
+            .find_map(|id| Self::describe_album(id, library));
+        let date_format = cx.global::<Settings>().display.date_format;
+
+        let status = if session.rolled_back {
+            "Rolled back".to_string()
+        } else if remaining == session.tracks.len() {
+            format!("{} songs", remaining)
+        } else {
+            format!(
+                "{} of {} songs still in the library",
+                remaining,
+                session.tracks.len()
+            )
+        };
+
+        h_stack()
+            .py(px(4.))
+            .gap(px(8.))
+            .border_b_1()
+            .border_color(rgb(0xD0D0D0))
+            .child(
+                v_stack()
+                    .flex_1()
+                    .overflow_hidden()
+                    .child(format!(
+                        "{} · {}",
+                        date_format.format(&date_for_timestamp(session.imported_at)),
+                        session.kind.name()
+                    ))
+                    .children(album)
+                    .child(div().opacity(0.6).child(status)),
+            )
+            .when(!session.rolled_back, |row| {
+                row.child(
+                    small_button(("roll-back-import", id as usize), "Roll Back").on_click(
+                        cx.listener(move |this, _, cx| {
+                            confirm_roll_back(this.library.clone(), id, cx)
+                        }),
+                    ),
+                )
+            })
+    }
+}
+
+/// Asks before taking an import's songs out of the library, offering to
+/// delete what it put into the music folder as well.
+pub fn confirm_roll_back<V: 'static>(library: Model<Library>, id: u64, cx: &mut ViewContext<V>) {
+    let Some(session) = library
+        .read(cx)
+        .import_sessions()
+        .iter()
+        .find(|session| session.id == id && !session.rolled_back)
+    else {
+        return;
+    };
+    let remaining = library.read(cx).import_tracks_remaining(session);
+    let has_files = !session.files.is_empty();
+    let answers: &[&str] = if has_files {
+        &["Remove Songs", "Remove Songs and Files", "Cancel"]
+    } else {
+        &["Remove Songs", "Cancel"]
+    };
+    let answer = cx.prompt(
+        PromptLevel::Warning,
+        &format!("Roll back the import of {} songs?", remaining),
+        Some("They're taken out of the library and every playlist, without going to Recently Deleted."),
+        answers,
+    );
+
+    cx.spawn(|_, mut cx| async move {
+        let delete_files = match answer.await {
+            Ok(0) => false,
+            Ok(1) if has_files => true,
+            _ => return,
+        };
+        let Ok(Some(files)) = library.update(&mut cx, |library, cx| {
+            let files = library.roll_back_import(id);
+            cx.notify();
+            files
+        }) else {
+            return;
+        };
+        if delete_files {
+            cx.background_executor()
+                .spawn(async move { remove_imported_files(&files) })
+                .await;
+        }
+    })
+    .detach();
+}
+
+impl Render for ImportsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let sessions: Vec<ImportSession> = self
+            .library
+            .read(cx)
+            .import_sessions()
+            .iter()
+            .rev()
+            .cloned()
+            .collect();
+
+        v_stack()
+            .id("imports")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Imports"),
+            )
+            .when(sessions.is_empty(), |view| {
+                view.child(div().opacity(0.6).child("Nothing has been imported yet."))
+            })
+            .children(
+                sessions
+                    .iter()
+                    .map(|session| self.render_session(session, cx)),
+            )
+    }
+}
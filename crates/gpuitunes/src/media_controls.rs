@@ -0,0 +1,145 @@
+// Nothing starts the controls off Windows yet
+#![cfg_attr(not(target_os = "windows"), allow(dead_code))]
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaCommand {
+    TogglePlayback,
+    Play,
+    Pause,
+    SkipNext,
+    SkipPrev,
+}
+
+#[cfg(target_os = "windows")]
+pub use self::windows::MediaControls;
+
+// The System Media Transport Controls, which media keys, the volume flyout
+// and Bluetooth headsets all go through. Like the remote server, events
+// arrive on another thread and are queued for the app to drain.
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::MediaCommand;
+    use gpui::WindowContext;
+    use library::{CurrentTrack, TrackId};
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use souvlaki::{
+        MediaControlEvent, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig,
+    };
+    use std::{
+        ffi::c_void,
+        sync::mpsc::{self, Receiver},
+        time::Duration,
+    };
+
+    pub struct MediaControls {
+        controls: souvlaki::MediaControls,
+        commands: Receiver<MediaCommand>,
+        // What the system was last told, so it's only updated on changes
+        shown_track: Option<TrackId>,
+        shown_playback: Option<(bool, i32)>,
+    }
+
+    impl MediaControls {
+        pub fn start(cx: &mut WindowContext) -> Option<Self> {
+            // The controls belong to a window, so they need its HWND
+            let hwnd = match HasWindowHandle::window_handle(cx).ok()?.as_raw() {
+                RawWindowHandle::Win32(handle) => handle.hwnd.get() as *mut c_void,
+                _ => return None,
+            };
+            let mut controls = souvlaki::MediaControls::new(PlatformConfig {
+                dbus_name: "gpuitunes",
+                display_name: "gpuiTunes",
+                hwnd: Some(hwnd),
+            })
+            .map_err(|e| eprintln!("Failed to start media controls: {:?}", e))
+            .ok()?;
+
+            let (sender, commands) = mpsc::channel();
+            controls
+                .attach(move |event| {
+                    let command = match event {
+                        MediaControlEvent::Toggle => MediaCommand::TogglePlayback,
+                        MediaControlEvent::Play => MediaCommand::Play,
+                        MediaControlEvent::Pause | MediaControlEvent::Stop => MediaCommand::Pause,
+                        MediaControlEvent::Next => MediaCommand::SkipNext,
+                        MediaControlEvent::Previous => MediaCommand::SkipPrev,
+                        _ => return,
+                    };
+                    sender.send(command).ok();
+                })
+                .map_err(|e| eprintln!("Failed to start media controls: {:?}", e))
+                .ok()?;
+
+            Some(MediaControls {
+                controls,
+                commands,
+                shown_track: None,
+                shown_playback: None,
+            })
+        }
+
+        pub fn commands(&self) -> impl Iterator<Item = MediaCommand> + '_ {
+            self.commands.try_iter()
+        }
+
+        pub fn update(&mut self, current_track: Option<&CurrentTrack>) {
+            let Some(current_track) = current_track else {
+                if self.shown_track.take().is_some() {
+                    self.shown_playback = None;
+                    self.controls.set_playback(MediaPlayback::Stopped).ok();
+                }
+                return;
+            };
+
+            let track = current_track.track();
+            if self.shown_track.as_ref() != Some(track.id()) {
+                let (title, artist, album) = (track.title(), track.artist(), track.album());
+                let cover_url = track
+                    .artwork()
+                    .map(|path| format!("file://{}", path.display()));
+                let metadata = MediaMetadata {
+                    title: Some(&title),
+                    artist: Some(&artist),
+                    album: Some(&album),
+                    cover_url: cover_url.as_deref(),
+                    duration: Some(Duration::from_secs(track.duration().max(0) as u64)),
+                };
+                if let Err(e) = self.controls.set_metadata(metadata) {
+                    eprintln!("Failed to update media controls: {:?}", e);
+                }
+                self.shown_track = Some(track.id().clone());
+            }
+
+            let playback = (current_track.is_playing(), current_track.current_time());
+            if self.shown_playback != Some(playback) {
+                let progress = Some(MediaPosition(Duration::from_secs(
+                    current_track.current_time().max(0) as u64,
+                )));
+                let playback_state = if current_track.is_playing() {
+                    MediaPlayback::Playing { progress }
+                } else {
+                    MediaPlayback::Paused { progress }
+                };
+                self.controls.set_playback(playback_state).ok();
+                self.shown_playback = Some(playback);
+            }
+        }
+    }
+}
+
+// Elsewhere the window's menus and key bindings are all there is for now
+#[cfg(not(target_os = "windows"))]
+pub struct MediaControls;
+
+#[cfg(not(target_os = "windows"))]
+impl MediaControls {
+    pub fn start(_: &mut gpui::WindowContext) -> Option<Self> {
+        None
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = MediaCommand> + '_ {
+        std::iter::empty()
+    }
+
+    pub fn update(&mut self, _: Option<&library::CurrentTrack>) {}
+}
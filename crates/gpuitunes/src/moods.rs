@@ -0,0 +1,296 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{parse_moods, write_mood_comment, Library, TrackId};
+
+use crate::{element::*, settings::Settings};
+
+// Mood names in the cloud are sized between these, by how many tracks have them
+const MIN_CLOUD_SIZE: f32 = 11.;
+const MAX_CLOUD_SIZE: f32 = 26.;
+
+pub enum MoodPickerEvent {
+    Dismissed,
+}
+
+/// Tags tracks with moods, opened by pressing T over the track list. The
+/// moods already in use are listed to toggle, and new ones are typed in.
+pub struct MoodPicker {
+    library: Model<Library>,
+    tracks: Vec<TrackId>,
+    focus_handle: FocusHandle,
+    draft: String,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl EventEmitter<MoodPickerEvent> for MoodPicker {}
+
+impl MoodPicker {
+    pub fn new(library: Model<Library>, tracks: Vec<TrackId>, cx: &mut ViewContext<Self>) -> Self {
+        let subscriptions = vec![cx.observe(&library, |_, _, cx| cx.notify())];
+
+        MoodPicker {
+            library,
+            tracks,
+            focus_handle: cx.focus_handle(),
+            draft: String::new(),
+            _subscriptions: subscriptions,
+        }
+    }
+
+    pub fn focus_handle(&self) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.platform || keystroke.modifiers.control {
+            return;
+        }
+        match keystroke.key.as_str() {
+            "enter" => self.add_draft(cx),
+            "backspace" => {
+                self.draft.pop();
+            }
+            "escape" => cx.emit(MoodPickerEvent::Dismissed),
+            _ => match &keystroke.key_char {
+                Some(text) if !text.chars().any(char::is_control) => self.draft.push_str(text),
+                _ => return,
+            },
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    fn add_draft(&mut self, cx: &mut ViewContext<Self>) {
+        let draft = std::mem::take(&mut self.draft);
+        for mood in parse_moods(&draft) {
+            self.set_mood(&mood, true, cx);
+        }
+    }
+
+    // Tags every track when any of them is missing the mood, otherwise takes
+    // it off them all
+    fn toggle_mood(&mut self, mood: &str, cx: &mut ViewContext<Self>) {
+        let tagged = !self.all_tagged(mood, cx);
+        self.set_mood(mood, tagged, cx);
+    }
+
+    fn all_tagged(&self, mood: &str, cx: &AppContext) -> bool {
+        let library = self.library.read(cx);
+        self.tracks
+            .iter()
+            .filter_map(|id| library.track(id))
+            .all(|track| track.has_mood(mood))
+    }
+
+    fn set_mood(&mut self, mood: &str, tagged: bool, cx: &mut ViewContext<Self>) {
+        let tracks = self.tracks.clone();
+        let changed = self.library.update(cx, |library, cx| {
+            let changed = library.set_mood(&tracks, mood, tagged);
+            if !changed.is_empty() {
+                cx.notify();
+            }
+            changed
+        });
+        if !changed.is_empty() && cx.global::<Settings>().import.moods_in_comments {
+            self.write_comments(changed, cx);
+        }
+    }
+
+    // Done off the main thread, and the files rewritten are checksummed
+    // again so Verify Library Files doesn't flag them
+    fn write_comments(&mut self, changed: Vec<TrackId>, cx: &mut ViewContext<Self>) {
+        let library = self.library.read(cx);
+        let files: Vec<_> = changed
+            .into_iter()
+            .filter_map(|id| {
+                let track = library.track(&id)?;
+                let path = track.path()?.to_path_buf();
+                Some((id, path, track.moods().to_vec()))
+            })
+            .collect();
+
+        let library = self.library.clone();
+        cx.spawn(|_, mut cx| async move {
+            let written = cx
+                .background_executor()
+                .spawn(async move {
+                    files
+                        .into_iter()
+                        .filter_map(
+                            |(id, path, moods)| match write_mood_comment(&path, &moods) {
+                                Ok(written) => written.then_some(id),
+                                Err(e) => {
+                                    eprintln!("Failed to write moods to {}: {}", path.display(), e);
+                                    None
+                                }
+                            },
+                        )
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            library
+                .update(&mut cx, |library, _| library.rechecksum(&written))
+                .ok();
+        })
+        .detach();
+    }
+}
+
+impl Render for MoodPicker {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let title = match self.tracks.as_slice() {
+            [id] => library
+                .track(id)
+                .map(|track| format!("Moods for {}", track.title()))
+                .unwrap_or_default(),
+            tracks => format!("Moods for {} Songs", tracks.len()),
+        };
+        let moods: Vec<_> = library
+            .moods()
+            .into_iter()
+            .map(|(mood, _)| {
+                let tagged = self.all_tagged(&mood, cx);
+                (mood, tagged)
+            })
+            .collect();
+
+        v_stack()
+            .id("mood-picker")
+            .occlude()
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .on_mouse_down_out(cx.listener(|_, _, cx| cx.emit(MoodPickerEvent::Dismissed)))
+            .w(px(260.))
+            .p(px(8.))
+            .gap(px(6.))
+            .rounded(px(4.))
+            .border_1()
+            .border_color(rgb(0xA0A0A0))
+            .bg(rgb(0xFFFFFF))
+            .shadow_lg()
+            .text_size(px(12.))
+            .text_color(rgb(0x0F1219))
+            .child(div().font_weight(FontWeight::BOLD).child(title))
+            .when(!moods.is_empty(), |picker| {
+                picker.child(h_stack().flex_wrap().gap(px(4.)).children(
+                    moods.into_iter().enumerate().map(|(ix, (mood, tagged))| {
+                        let label = if tagged {
+                            format!("✓ {}", mood)
+                        } else {
+                            mood.clone()
+                        };
+                        div()
+                            .id(("mood", ix))
+                            .px(px(6.))
+                            .py(px(1.))
+                            .rounded(px(8.))
+                            .border_1()
+                            .cursor_pointer()
+                            .map(|chip| {
+                                if tagged {
+                                    chip.bg(rgb(0x3D80DF))
+                                        .border_color(rgb(0x3D80DF))
+                                        .text_color(rgb(0xFFFFFF))
+                                } else {
+                                    chip.border_color(rgb(0xB0B0B0))
+                                }
+                            })
+                            .on_click(cx.listener(move |this, _, cx| this.toggle_mood(&mood, cx)))
+                            .child(label)
+                    }),
+                ))
+            })
+            .child(
+                h_stack()
+                    .px(px(4.))
+                    .h(px(20.))
+                    .border_1()
+                    .border_color(rgb(0x9A9A9A))
+                    .overflow_hidden()
+                    .map(|field| {
+                        if self.draft.is_empty() {
+                            field
+                                .text_color(rgb(0x808080))
+                                .child("Type a new mood and press Return")
+                        } else {
+                            field.child(self.draft.clone())
+                        }
+                    }),
+            )
+    }
+}
+
+pub enum MoodsEvent {
+    ShowMood(String),
+}
+
+/// Every mood in the library as a cloud, the more tracks a mood has the
+/// larger it's shown. Clicking one shows its tracks.
+pub struct MoodsView {
+    library: Model<Library>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl EventEmitter<MoodsEvent> for MoodsView {}
+
+impl MoodsView {
+    pub fn new(library: Model<Library>, cx: &mut ViewContext<Self>) -> Self {
+        let subscriptions = vec![cx.observe(&library, |_, _, cx| cx.notify())];
+
+        MoodsView {
+            library,
+            _subscriptions: subscriptions,
+        }
+    }
+}
+
+impl Render for MoodsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let moods = self.library.read(cx).moods();
+        let most = moods.first().map_or(1, |(_, count)| *count);
+
+        v_stack()
+            .id("moods")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Moods"),
+            )
+            .when(moods.is_empty(), |view| {
+                view.child(
+                    div()
+                        .opacity(0.6)
+                        .child("No songs have a mood yet. Select some and press T to tag them."),
+                )
+            })
+            .child(h_stack().flex_wrap().items_end().gap(px(10.)).children(
+                moods.into_iter().enumerate().map(|(ix, (mood, count))| {
+                    let size = MIN_CLOUD_SIZE
+                        + (MAX_CLOUD_SIZE - MIN_CLOUD_SIZE) * count as f32 / most as f32;
+                    let shown = mood.clone();
+                    h_stack()
+                        .id(("mood-cloud", ix))
+                        .items_end()
+                        .gap(px(2.))
+                        .cursor_pointer()
+                        .hover(|mood| mood.text_color(rgb(0x3D80DF)))
+                        .on_click(
+                            cx.listener(move |_, _, cx| {
+                                cx.emit(MoodsEvent::ShowMood(shown.clone()))
+                            }),
+                        )
+                        .child(div().text_size(px(size)).child(mood))
+                        .child(div().opacity(0.5).child(format!("{}", count)))
+                }),
+            ))
+    }
+}
@@ -0,0 +1,328 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{
+    date_for_timestamp, fetch_new_releases, unix_time, Library, NewRelease, NewReleaseFeed,
+};
+use std::{thread, time::Duration};
+
+use crate::{
+    element::*,
+    profile::{config_dir, data_dir},
+    settings::Settings,
+};
+
+// MusicBrainz allows each client one request a second
+const REQUEST_SPACING: Duration = Duration::from_millis(1100);
+
+/// The followed artists' releases, checked against MusicBrainz every so
+/// often in the background.
+pub struct NewReleases {
+    feed: NewReleaseFeed,
+    checking: bool,
+}
+
+impl NewReleases {
+    pub fn load(cx: &AppContext) -> Self {
+        NewReleases {
+            feed: NewReleaseFeed::load(&data_dir(cx)),
+            checking: false,
+        }
+    }
+
+    pub fn releases(&self) -> &[NewRelease] {
+        &self.feed.releases
+    }
+
+    pub fn is_checking(&self) -> bool {
+        self.checking
+    }
+
+    pub fn check_if_due(&mut self, cx: &mut ModelContext<Self>) {
+        let interval = cx.global::<Settings>().new_releases.check_every_hours as u64 * 3600;
+        let due = self
+            .feed
+            .last_checked
+            .is_none_or(|checked| unix_time().saturating_sub(checked) >= interval);
+        if due {
+            self.check(cx);
+        }
+    }
+
+    /// Asks MusicBrainz about every followed artist, unless that's already
+    /// under way.
+    pub fn check(&mut self, cx: &mut ModelContext<Self>) {
+        let settings = cx.global::<Settings>().new_releases.clone();
+        if self.checking
+            || settings.followed_artists.is_empty()
+            || cx.global::<Settings>().network.offline
+        {
+            return;
+        }
+        let since =
+            date_for_timestamp(unix_time().saturating_sub(settings.lookback_days as u64 * 86_400));
+        self.checking = true;
+        cx.notify();
+
+        cx.spawn(|this, mut cx| async move {
+            let artists = settings.followed_artists.clone();
+            let found = cx
+                .background_executor()
+                .spawn(async move {
+                    let mut found = Vec::new();
+                    for (ix, artist) in artists.iter().enumerate() {
+                        if ix > 0 {
+                            thread::sleep(REQUEST_SPACING);
+                        }
+                        match fetch_new_releases(artist, &since) {
+                            Ok(releases) => found.extend(releases),
+                            Err(e) => {
+                                eprintln!("Failed to check {} for new releases: {}", artist, e)
+                            }
+                        }
+                    }
+                    found
+                })
+                .await;
+
+            this.update(&mut cx, |this, cx| {
+                this.checking = false;
+                this.feed.merge(found);
+                this.feed.retain_artists(&settings.followed_artists);
+                this.feed.last_checked = Some(unix_time());
+                if let Err(e) = this.feed.save(&data_dir(cx)) {
+                    eprintln!("Failed to save new releases: {}", e);
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+}
+
+/// Starts or stops following `artist`, checking their releases straight away
+/// when they're new.
+pub fn set_following(
+    artist: &str,
+    follow: bool,
+    new_releases: &Model<NewReleases>,
+    cx: &mut WindowContext,
+) {
+    cx.update_global::<Settings, _>(|settings, _| {
+        let followed = &mut settings.new_releases.followed_artists;
+        followed.retain(|other| !other.eq_ignore_ascii_case(artist));
+        if follow {
+            followed.push(artist.to_string());
+            followed.sort_by_key(|artist| artist.to_lowercase());
+        }
+    });
+    if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+        eprintln!("Failed to save settings: {}", e);
+    }
+
+    new_releases.update(cx, |new_releases, cx| {
+        if follow {
+            // Only the one artist is new, but checking them all keeps one
+            // code path and MusicBrainz's rate limit in one place
+            new_releases.feed.last_checked = None;
+            new_releases.check(cx);
+        } else {
+            let followed = cx
+                .global::<Settings>()
+                .new_releases
+                .followed_artists
+                .clone();
+            new_releases.feed.retain_artists(&followed);
+            if let Err(e) = new_releases.feed.save(&data_dir(cx)) {
+                eprintln!("Failed to save new releases: {}", e);
+            }
+        }
+        cx.notify();
+    });
+}
+
+/// Lists what the followed artists have put out lately, leaving out what's
+/// already in the library.
+pub struct NewReleasesView {
+    library: Model<Library>,
+    new_releases: Model<NewReleases>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl NewReleasesView {
+    pub fn new(
+        library: Model<Library>,
+        new_releases: Model<NewReleases>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let subscriptions = vec![
+            cx.observe(&library, |_, _, cx| cx.notify()),
+            cx.observe(&new_releases, |_, _, cx| cx.notify()),
+            cx.observe_global::<Settings>(|_, cx| cx.notify()),
+        ];
+
+        NewReleasesView {
+            library,
+            new_releases,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn add_to_wishlist(&mut self, release: NewRelease, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            library.add_to_wishlist(&release);
+            cx.notify();
+        });
+    }
+
+    fn render_followed(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let followed = cx
+            .global::<Settings>()
+            .new_releases
+            .followed_artists
+            .clone();
+
+        v_stack()
+            .child(
+                div()
+                    .pt(px(14.))
+                    .pb(px(4.))
+                    .font_weight(FontWeight::BOLD)
+                    .text_size(px(12.))
+                    .child("Following"),
+            )
+            .when(followed.is_empty(), |section| {
+                section.child(div().opacity(0.6).child(
+                    "Follow an artist from a song's context menu to see their new releases here.",
+                ))
+            })
+            .child(h_stack().flex_wrap().gap(px(6.)).children(
+                followed.into_iter().enumerate().map(|(ix, artist)| {
+                    let unfollowed = artist.clone();
+                    h_stack().gap(px(4.)).child(artist).child(
+                        small_button(("unfollow", ix), "✕").on_click(cx.listener(
+                            move |this, _, cx| {
+                                set_following(&unfollowed, false, &this.new_releases, cx)
+                            },
+                        )),
+                    )
+                }),
+            ))
+    }
+
+    fn render_release(
+        &self,
+        ix: usize,
+        release: &NewRelease,
+        wished_for: bool,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let url = release.url();
+        let wished = release.clone();
+
+        h_stack()
+            .gap(px(8.))
+            .py(px(2.))
+            .child(div().w(px(80.)).opacity(0.6).child(release.date.clone()))
+            .child(
+                div()
+                    .w(px(260.))
+                    .overflow_hidden()
+                    .child(format!("{} — {}", release.title, release.artist)),
+            )
+            .child(div().w(px(60.)).opacity(0.6).child(release.kind.clone()))
+            .child(
+                div()
+                    .id(("open-release", ix))
+                    .cursor_pointer()
+                    .text_color(rgb(0x3D80DF))
+                    .child("MusicBrainz")
+                    .on_click(move |_, cx| cx.open_url(&url)),
+            )
+            .map(|row| {
+                if wished_for {
+                    row.child(div().opacity(0.6).child("On Wishlist"))
+                } else {
+                    row.child(
+                        div()
+                            .id(("wish-release", ix))
+                            .cursor_pointer()
+                            .text_color(rgb(0x3D80DF))
+                            .child("Add to Wishlist")
+                            .on_click(cx.listener(move |this, _, cx| {
+                                this.add_to_wishlist(wished.clone(), cx)
+                            })),
+                    )
+                }
+            })
+    }
+}
+
+impl Render for NewReleasesView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let new_releases = self.new_releases.read(cx);
+        let checking = new_releases.is_checking();
+        let releases: Vec<(NewRelease, bool)> = new_releases
+            .releases()
+            .iter()
+            .filter(|release| !library.owns_release(release))
+            .map(|release| {
+                (
+                    release.clone(),
+                    library.is_wished_for(&release.artist, &release.title),
+                )
+            })
+            .collect();
+
+        div()
+            .id("new-releases")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                h_stack()
+                    .gap(px(10.))
+                    .child(
+                        div()
+                            .text_size(px(16.))
+                            .font_weight(FontWeight::BOLD)
+                            .child("New Releases"),
+                    )
+                    .map(|header| {
+                        if checking {
+                            header.child(div().opacity(0.6).child("Checking MusicBrainz…"))
+                        } else {
+                            header.child(small_button("check-new-releases", "Check Now").on_click(
+                                cx.listener(|this, _, cx| {
+                                    this.new_releases
+                                        .update(cx, |new_releases, cx| new_releases.check(cx))
+                                }),
+                            ))
+                        }
+                    }),
+            )
+            .child(self.render_followed(cx))
+            .child(
+                div()
+                    .pt(px(14.))
+                    .pb(px(4.))
+                    .font_weight(FontWeight::BOLD)
+                    .text_size(px(12.))
+                    .child("Latest"),
+            )
+            .when(releases.is_empty(), |view| {
+                view.child(div().opacity(0.6).child("Nothing new yet."))
+            })
+            .children(
+                releases
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, (release, wished_for))| {
+                        self.render_release(ix, release, *wished_for, cx)
+                    }),
+            )
+    }
+}
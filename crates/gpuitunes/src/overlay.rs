@@ -0,0 +1,149 @@
+use library::{format_playback_time, Track};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::settings::StreamOverlaySettings;
+
+/// What a template can show of a song.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OverlayFields {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    pub year: Option<u32>,
+    // In seconds, both zero for songs that aren't playing
+    pub elapsed: i32,
+    pub duration: i32,
+}
+
+impl OverlayFields {
+    pub fn from_track(track: &Track, elapsed: i32) -> Self {
+        OverlayFields {
+            title: track.title().to_string(),
+            artist: track.artist().to_string(),
+            album: track.album().to_string(),
+            album_artist: track.album_artist().to_string(),
+            year: track.year(),
+            elapsed,
+            duration: track.duration(),
+        }
+    }
+
+    // Stations usually send "Artist - Title"
+    pub fn from_stream(station: &str, stream_title: Option<&str>) -> Self {
+        let stream_title = stream_title.unwrap_or_default();
+        let (artist, title) = stream_title.split_once(" - ").unwrap_or(("", stream_title));
+        OverlayFields {
+            title: title.trim().to_string(),
+            artist: artist.trim().to_string(),
+            album: station.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn fill(&self, template: &str) -> String {
+        let time = |seconds: i32| {
+            if self.duration > 0 {
+                format_playback_time(seconds.max(0))
+            } else {
+                String::new()
+            }
+        };
+        template
+            .replace("{title}", &self.title)
+            .replace("{artist}", &self.artist)
+            .replace("{album}", &self.album)
+            .replace("{album_artist}", &self.album_artist)
+            .replace(
+                "{year}",
+                &self.year.map(|year| year.to_string()).unwrap_or_default(),
+            )
+            .replace("{elapsed}", &time(self.elapsed))
+            .replace("{duration}", &time(self.duration))
+            .replace("{remaining}", &time(self.duration - self.elapsed))
+    }
+}
+
+/// Writes what's playing to text files, and its artwork to an image, for
+/// streaming software to pick up as sources. Nothing is rewritten unless
+/// it changed, and with nothing playing the files are left empty.
+#[derive(Default)]
+pub struct StreamOverlay {
+    // What each file was last given
+    written: HashMap<PathBuf, String>,
+    artwork: Option<Option<PathBuf>>,
+}
+
+impl StreamOverlay {
+    pub fn update(
+        &mut self,
+        settings: &StreamOverlaySettings,
+        data_dir: &Path,
+        playing: Option<&OverlayFields>,
+        artwork: Option<&Path>,
+        up_next: &[OverlayFields],
+    ) {
+        let folder = settings.folder(data_dir);
+        if let Err(e) = fs::create_dir_all(&folder) {
+            eprintln!("Failed to create {}: {}", folder.display(), e);
+            return;
+        }
+
+        for file in &settings.files {
+            let text = playing
+                .map(|fields| fields.fill(&file.template))
+                .unwrap_or_default();
+            self.write(&folder.join(&file.path), text);
+        }
+        if let Some(file) = &settings.up_next {
+            let lines: Vec<String> = up_next
+                .iter()
+                .take(settings.up_next_count)
+                .map(|fields| fields.fill(&file.template))
+                .collect();
+            self.write(&folder.join(&file.path), lines.join("\n"));
+        }
+        if let Some(path) = &settings.artwork {
+            let artwork = artwork.map(Path::to_path_buf);
+            if self.artwork.as_ref() != Some(&artwork) {
+                let target = folder.join(path);
+                let copied = artwork
+                    .as_ref()
+                    .is_some_and(|source| copy_over(source, &target).is_ok());
+                if !copied {
+                    fs::remove_file(&target).ok();
+                }
+                self.artwork = Some(artwork);
+            }
+        }
+    }
+
+    fn write(&mut self, path: &Path, text: String) {
+        if self.written.get(path) == Some(&text) {
+            return;
+        }
+        match write_over(path, &text) {
+            Ok(()) => {
+                self.written.insert(path.to_path_buf(), text);
+            }
+            Err(e) => eprintln!("Failed to write {}: {}", path.display(), e),
+        }
+    }
+}
+
+// Written alongside and renamed over, so a half-written file is never shown
+fn write_over(path: &Path, text: &str) -> io::Result<()> {
+    let partial = path.with_extension("partial");
+    fs::write(&partial, text)?;
+    fs::rename(partial, path)
+}
+
+fn copy_over(source: &Path, path: &Path) -> io::Result<()> {
+    let partial = path.with_extension("partial");
+    fs::copy(source, &partial)?;
+    fs::rename(partial, path)
+}
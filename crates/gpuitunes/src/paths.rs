@@ -0,0 +1,138 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+const APP_NAME: &str = "gpuiTunes";
+// Keeps everything next to the executable, for running from a USB stick
+const PORTABLE_FLAG: &str = "--portable";
+const PORTABLE_DIR: &str = "gpuiTunes Data";
+// Files that belong with the configuration rather than the data
+const CONFIG_FILES: &[&str] = &["settings.json", "profiles.json"];
+
+struct Paths {
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+}
+
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+fn paths() -> &'static Paths {
+    PATHS.get_or_init(|| {
+        let paths = if env::args().any(|arg| arg == PORTABLE_FLAG) {
+            portable_paths()
+        } else {
+            platform_paths()
+        };
+        if let Err(e) = migrate_legacy_data(&paths) {
+            eprintln!("Failed to move data to {}: {}", paths.data_dir.display(), e);
+        }
+        paths
+    })
+}
+
+/// Where settings and profiles are kept.
+pub fn config_dir() -> PathBuf {
+    paths().config_dir.clone()
+}
+
+/// Where sessions, artwork and imported music are kept.
+pub fn data_dir() -> PathBuf {
+    paths().data_dir.clone()
+}
+
+fn home_dir() -> PathBuf {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn portable_paths() -> Paths {
+    let dir = env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(PORTABLE_DIR)))
+        .unwrap_or_else(|| PathBuf::from(PORTABLE_DIR));
+    Paths {
+        config_dir: dir.clone(),
+        data_dir: dir,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn platform_paths() -> Paths {
+    let dir = home_dir()
+        .join("Library/Application Support")
+        .join(APP_NAME);
+    Paths {
+        config_dir: dir.clone(),
+        data_dir: dir,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn platform_paths() -> Paths {
+    let dir = env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join("AppData/Roaming"))
+        .join(APP_NAME);
+    Paths {
+        config_dir: dir.clone(),
+        data_dir: dir,
+    }
+}
+
+// Follows the XDG base directories, falling back to their usual defaults
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn platform_paths() -> Paths {
+    let xdg_dir = |var: &str, fallback: &str| {
+        env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .unwrap_or_else(|| home_dir().join(fallback))
+            .join(APP_NAME.to_lowercase())
+    };
+    Paths {
+        config_dir: xdg_dir("XDG_CONFIG_HOME", ".config"),
+        data_dir: xdg_dir("XDG_DATA_HOME", ".local/share"),
+    }
+}
+
+// Data used to live in the source tree. It's copied over the first time the
+// new directories are used, leaving anything already there alone. The old
+// directory is checked in, so it stays where it is.
+fn migrate_legacy_data(paths: &Paths) -> io::Result<()> {
+    let legacy = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data");
+    if !legacy.is_dir() || paths.data_dir.exists() || paths.config_dir.exists() {
+        return Ok(());
+    }
+    copy_tree(&legacy, &legacy, paths)
+}
+
+fn copy_tree(root: &Path, dir: &Path, paths: &Paths) -> io::Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            copy_tree(root, &path, paths)?;
+            continue;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let is_config = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| CONFIG_FILES.contains(&name));
+        let destination = if is_config {
+            paths.config_dir.join(relative)
+        } else {
+            paths.data_dir.join(relative)
+        };
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&path, &destination)?;
+    }
+    Ok(())
+}
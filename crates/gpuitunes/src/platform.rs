@@ -0,0 +1,305 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use std::{cell::Cell, path::PathBuf, rc::Rc};
+
+use crate::{element::*, Maximize, Minimize, Quit};
+
+// The shadow drawn around the window when it decorates itself
+const CLIENT_INSET: Pixels = px(10.);
+// How far into the shadow a press still starts resizing
+const RESIZE_EDGE: Pixels = px(10.);
+const CAPTION_BUTTON_WIDTH: Pixels = px(36.);
+const CAPTION_BUTTON_HEIGHT: Pixels = px(21.);
+
+/// How the window's frame and controls are drawn on each platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowChrome {
+    // Traffic lights on the left, drawn by the title bar itself
+    Mac,
+    // Caption buttons on the right, over the area Windows hit-tests as the
+    // caption so hovering maximize still brings up snap layouts
+    Windows,
+    // Client-side decorations, with the window drawing its own shadow,
+    // resize edges and buttons
+    Linux,
+}
+
+impl WindowChrome {
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            WindowChrome::Mac
+        } else if cfg!(target_os = "windows") {
+            WindowChrome::Windows
+        } else {
+            WindowChrome::Linux
+        }
+    }
+
+    pub fn titlebar(&self) -> Option<TitlebarOptions> {
+        match self {
+            WindowChrome::Mac | WindowChrome::Linux => None,
+            WindowChrome::Windows => Some(TitlebarOptions {
+                title: Some("gpuiTunes".into()),
+                appears_transparent: true,
+                traffic_light_position: None,
+            }),
+        }
+    }
+
+    pub fn decorations(&self) -> Option<WindowDecorations> {
+        match self {
+            WindowChrome::Linux => Some(WindowDecorations::Client),
+            WindowChrome::Mac | WindowChrome::Windows => None,
+        }
+    }
+
+    pub fn has_controls_on_left(&self) -> bool {
+        *self == WindowChrome::Mac
+    }
+}
+
+/// Makes an element move the window when dragged, and maximize it when
+/// double-clicked. Only needed where the platform doesn't do it for us.
+pub fn window_drag_area(element: Stateful<Div>) -> Stateful<Div> {
+    if WindowChrome::current() != WindowChrome::Linux {
+        return element;
+    }
+    element
+        .on_mouse_down(MouseButton::Left, |event, cx| {
+            if event.click_count == 2 {
+                cx.zoom_window();
+            } else {
+                cx.start_window_move();
+            }
+        })
+        .on_mouse_down(MouseButton::Right, |event, cx| {
+            cx.show_window_menu(event.position)
+        })
+}
+
+fn caption_button(
+    id: &'static str,
+    glyph: &'static str,
+    hover: Hsla,
+    on_click: impl Fn(&mut WindowContext) + 'static,
+) -> impl IntoElement {
+    h_stack()
+        .id(id)
+        .justify_center()
+        .w(CAPTION_BUTTON_WIDTH)
+        .h(CAPTION_BUTTON_HEIGHT)
+        .font_family("Segoe Fluent Icons")
+        .text_size(px(10.))
+        .hover(move |button| button.bg(hover))
+        .child(glyph)
+        .on_click(move |_, cx| on_click(cx))
+}
+
+fn client_button(
+    id: &'static str,
+    glyph: &'static str,
+    on_click: impl Fn(&mut WindowContext) + 'static,
+) -> impl IntoElement {
+    circle(px(16.))
+        .id(id)
+        .flex()
+        .items_center()
+        .justify_center()
+        .bg(vertical_linear_gradient(rgb(0xE4E4E4), rgb(0xB8B8B8)))
+        .border_1()
+        .border_color(rgb(0x7C7C7C))
+        .text_size(px(9.))
+        .text_color(rgb(0x333333))
+        .hover(|button| button.bg(rgb(0xF5F5F5)))
+        .child(glyph)
+        .on_click(move |_, cx| on_click(cx))
+}
+
+/// Minimize, maximize and close for the platforms that put them on the
+/// right. `None` on macOS, where the title bar draws traffic lights.
+pub fn render_window_controls(cx: &mut WindowContext) -> Option<AnyElement> {
+    match WindowChrome::current() {
+        WindowChrome::Mac => None,
+        WindowChrome::Windows => {
+            let maximize_glyph = if cx.is_maximized() {
+                "\u{e923}"
+            } else {
+                "\u{e922}"
+            };
+            Some(
+                h_stack()
+                    .absolute()
+                    .top_0()
+                    .right_0()
+                    .occlude()
+                    .child(caption_button(
+                        "minimize",
+                        "\u{e921}",
+                        black().opacity(0.1),
+                        |cx| cx.dispatch_action(Box::new(Minimize)),
+                    ))
+                    .child(caption_button(
+                        "maximize",
+                        maximize_glyph,
+                        black().opacity(0.1),
+                        |cx| cx.dispatch_action(Box::new(Maximize)),
+                    ))
+                    .child(caption_button(
+                        "close",
+                        "\u{e8bb}",
+                        rgb(0xE81123).into(),
+                        |cx| cx.dispatch_action(Box::new(Quit)),
+                    ))
+                    .into_any_element(),
+            )
+        }
+        WindowChrome::Linux => Some(
+            h_stack()
+                .absolute()
+                .top(px(3.))
+                .right(px(8.))
+                .gap(px(6.))
+                .occlude()
+                .child(client_button("minimize", "–", |cx| {
+                    cx.dispatch_action(Box::new(Minimize))
+                }))
+                .child(client_button("maximize", "□", |cx| {
+                    cx.dispatch_action(Box::new(Maximize))
+                }))
+                .child(client_button("close", "✕", |cx| {
+                    cx.dispatch_action(Box::new(Quit))
+                }))
+                .into_any_element(),
+        ),
+    }
+}
+
+fn resize_edge(position: Point<Pixels>, size: Size<Pixels>) -> Option<ResizeEdge> {
+    let left = position.x < RESIZE_EDGE;
+    let right = position.x > size.width - RESIZE_EDGE;
+    let top = position.y < RESIZE_EDGE;
+    let bottom = position.y > size.height - RESIZE_EDGE;
+    match (top, bottom, left, right) {
+        (true, _, true, _) => Some(ResizeEdge::TopLeft),
+        (true, _, _, true) => Some(ResizeEdge::TopRight),
+        (_, true, true, _) => Some(ResizeEdge::BottomLeft),
+        (_, true, _, true) => Some(ResizeEdge::BottomRight),
+        (true, ..) => Some(ResizeEdge::Top),
+        (_, true, ..) => Some(ResizeEdge::Bottom),
+        (_, _, true, _) => Some(ResizeEdge::Left),
+        (_, _, _, true) => Some(ResizeEdge::Right),
+        _ => None,
+    }
+}
+
+/// Wraps the window's contents in the shadow and resize edges a window
+/// needs when it decorates itself. Anywhere else it's passed through as is.
+pub fn client_side_decorations(content: impl IntoElement, cx: &mut WindowContext) -> Div {
+    let Decorations::Client { tiling } = cx.window_decorations() else {
+        return div().size_full().child(content);
+    };
+    // Tiled edges sit flush against the screen or other windows
+    let inset = if tiling.is_tiled() {
+        px(0.)
+    } else {
+        CLIENT_INSET
+    };
+    cx.set_client_inset(inset);
+
+    div()
+        .size_full()
+        .when(!tiling.top, |frame| frame.pt(inset))
+        .when(!tiling.bottom, |frame| frame.pb(inset))
+        .when(!tiling.left, |frame| frame.pl(inset))
+        .when(!tiling.right, |frame| frame.pr(inset))
+        .on_mouse_down(MouseButton::Left, |event, cx| {
+            let size = cx.window_bounds().get_bounds().size;
+            if let Some(edge) = resize_edge(event.position, size) {
+                cx.start_window_resize(edge);
+            }
+        })
+        .child(
+            div()
+                .size_full()
+                .when(inset > px(0.), |window| window.rounded(px(10.)).shadow_lg())
+                .on_mouse_down(MouseButton::Left, |_, cx| cx.stop_propagation())
+                .child(content),
+        )
+}
+
+/// Lets elements be dragged out of the window as a file, such as artwork
+/// onto the desktop or into another app. Only macOS can drag files out, so
+/// elsewhere elements are left as they are.
+#[derive(Debug, Default)]
+pub struct FileDragSource {
+    // Whether the button went down on the element, so a drag started
+    // elsewhere that passes over it doesn't count
+    pressed: Rc<Cell<bool>>,
+}
+
+impl FileDragSource {
+    pub fn attach<E: InteractiveElement>(&self, element: E, path: PathBuf) -> E {
+        if !cfg!(target_os = "macos") {
+            return element;
+        }
+        let (pressed, dragged) = (self.pressed.clone(), self.pressed.clone());
+        element
+            .on_mouse_down(MouseButton::Left, move |_, _| pressed.set(true))
+            .on_mouse_move(move |event, cx| {
+                if !dragged.replace(false) || event.pressed_button != Some(MouseButton::Left) {
+                    return;
+                }
+                let path = path.clone();
+                // AppKit runs the drag in an event loop of its own, which
+                // can't start while the window is still handling this move
+                cx.spawn(|_| async move { drag_file_out(&path) }).detach();
+            })
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn drag_file_out(path: &std::path::Path) {
+    use cocoa::{
+        appkit::{NSApp, NSEvent, NSEventType},
+        base::{id, nil, BOOL, YES},
+        foundation::{NSPoint, NSRect, NSSize, NSString},
+    };
+    use objc::{msg_send, sel, sel_impl};
+
+    // The image AppKit drags along, centered on the pointer
+    const ICON_SIZE: f64 = 32.;
+
+    unsafe {
+        let window: id = msg_send![NSApp(), keyWindow];
+        let event: id = msg_send![NSApp(), currentEvent];
+        if window == nil || event == nil {
+            return;
+        }
+        // Anything but the press or drag it started from, and AppKit throws
+        if !matches!(
+            event.eventType(),
+            NSEventType::NSLeftMouseDown | NSEventType::NSLeftMouseDragged
+        ) {
+            return;
+        }
+        let view: id = msg_send![window, contentView];
+        let location: NSPoint = msg_send![event, locationInWindow];
+        let location: NSPoint = msg_send![view, convertPoint: location fromView: nil];
+        let from = NSRect::new(
+            NSPoint::new(location.x - ICON_SIZE / 2., location.y - ICON_SIZE / 2.),
+            NSSize::new(ICON_SIZE, ICON_SIZE),
+        );
+
+        let path = NSString::alloc(nil).init_str(&path.to_string_lossy());
+        let _: BOOL = msg_send![
+            view,
+            dragFile: path
+            fromRect: from
+            slideBack: YES
+            event: event
+        ];
+        let () = msg_send![path, release];
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn drag_file_out(_path: &std::path::Path) {}
@@ -0,0 +1,173 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{date_for_timestamp, Library, PlaylistId, PlaylistVersion, TrackId};
+
+use crate::{element::*, settings::Settings};
+
+/// Earlier versions of a playlist, newest first, each with what's been
+/// added and removed since. Any of them can be put back, and what the
+/// playlist held until then becomes a version in turn.
+pub struct PlaylistHistoryView {
+    library: Model<Library>,
+    playlist_id: PlaylistId,
+    // The version whose changes are listed out
+    expanded: Option<u64>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl PlaylistHistoryView {
+    pub fn new(
+        library: Model<Library>,
+        playlist_id: PlaylistId,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let subscriptions = vec![cx.observe(&library, |_, _, cx| cx.notify())];
+
+        PlaylistHistoryView {
+            library,
+            playlist_id,
+            expanded: None,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn toggle_expanded(&mut self, taken: u64, cx: &mut ViewContext<Self>) {
+        if self.expanded == Some(taken) {
+            self.expanded = None;
+        } else {
+            self.expanded = Some(taken);
+        }
+        cx.notify();
+    }
+
+    fn restore(&mut self, taken: u64, cx: &mut ViewContext<Self>) {
+        let playlist_id = self.playlist_id.clone();
+        self.library.update(cx, |library, cx| {
+            if library.restore_playlist_version(&playlist_id, taken) {
+                cx.notify();
+            }
+        });
+        self.expanded = None;
+    }
+
+    fn describe_change(sign: &str, id: &TrackId, library: &Library) -> String {
+        match library.track(id) {
+            Some(track) => format!("{} {} — {}", sign, track.title(), track.artist()),
+            None => format!("{} A track no longer in the library", sign),
+        }
+    }
+
+    fn render_version(
+        &self,
+        ix: usize,
+        version: &PlaylistVersion,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let taken = version.taken;
+        let expanded = self.expanded == Some(taken);
+        let library = self.library.read(cx);
+        let diff = library.playlist_diff(&self.playlist_id, version);
+        let changes: Vec<_> = if expanded {
+            let removed = diff
+                .removed
+                .iter()
+                .map(|id| Self::describe_change("−", id, library));
+            let added = diff
+                .added
+                .iter()
+                .map(|id| Self::describe_change("+", id, library));
+            removed.chain(added).collect()
+        } else {
+            Vec::new()
+        };
+        let date_format = cx.global::<Settings>().display.date_format;
+
+        let summary = if diff.is_empty() {
+            "Same as now".to_string()
+        } else {
+            format!(
+                "{} added since, {} removed since",
+                diff.added.len(),
+                diff.removed.len()
+            )
+        };
+
+        v_stack()
+            .py(px(4.))
+            .border_b_1()
+            .border_color(rgb(0xD0D0D0))
+            .child(
+                h_stack()
+                    .id(("playlist-version", ix))
+                    .gap(px(8.))
+                    .cursor_pointer()
+                    .on_click(cx.listener(move |this, _, cx| this.toggle_expanded(taken, cx)))
+                    .child(
+                        v_stack()
+                            .flex_1()
+                            .overflow_hidden()
+                            .child(date_format.format(&date_for_timestamp(taken)))
+                            .child(div().opacity(0.6).child(format!(
+                                "{} tracks · {}",
+                                version.tracks.len(),
+                                summary
+                            ))),
+                    )
+                    .when(!diff.is_empty(), |row| {
+                        row.child(
+                            small_button(("restore-playlist-version", ix), "Restore")
+                                .on_click(cx.listener(move |this, _, cx| this.restore(taken, cx))),
+                        )
+                    }),
+            )
+            .children(
+                changes
+                    .into_iter()
+                    .map(|change| div().pl(px(12.)).opacity(0.8).child(change)),
+            )
+    }
+}
+
+impl Render for PlaylistHistoryView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let name = library
+            .playlist(&self.playlist_id)
+            .map(|playlist| playlist.name())
+            .unwrap_or_default();
+        let versions: Vec<_> = library
+            .playlist_versions(&self.playlist_id)
+            .iter()
+            .rev()
+            .cloned()
+            .collect();
+
+        v_stack()
+            .id("playlist-history")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child(format!("Version History of {}", name)),
+            )
+            .when(versions.is_empty(), |view| {
+                view.child(
+                    div()
+                        .opacity(0.6)
+                        .child("Versions are kept from the first time the playlist is changed."),
+                )
+            })
+            .children(
+                versions
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, version)| self.render_version(ix, version, cx)),
+            )
+    }
+}
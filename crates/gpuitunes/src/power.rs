@@ -0,0 +1,88 @@
+use gpui::{AppContext, AsyncAppContext, Global};
+use std::time::Duration;
+
+use crate::settings::{LowPowerMode, Settings};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+const LOW_POWER_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+// Unplugging doesn't need noticing to the second
+const BATTERY_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What low-power mode is decided from, besides the setting.
+pub struct Power {
+    on_battery: bool,
+    // gpui doesn't report occlusion, so a window in the background counts
+    window_active: bool,
+}
+
+impl Global for Power {}
+
+pub fn init(cx: &mut AppContext) {
+    cx.set_global(Power {
+        on_battery: on_battery(),
+        window_active: true,
+    });
+    cx.spawn(|cx| async move {
+        loop {
+            cx.background_executor().timer(BATTERY_POLL_INTERVAL).await;
+            let on_battery = cx.background_executor().spawn(async { on_battery() }).await;
+            let updated = cx.update(|cx| cx.global_mut::<Power>().on_battery = on_battery);
+            if updated.is_err() {
+                break;
+            }
+        }
+    })
+    .detach();
+}
+
+pub fn set_window_active(active: bool, cx: &mut AppContext) {
+    cx.global_mut::<Power>().window_active = active;
+}
+
+pub fn is_low_power(cx: &AppContext) -> bool {
+    match cx.global::<Settings>().power.low_power {
+        LowPowerMode::Automatic => {
+            let power = cx.global::<Power>();
+            power.on_battery || !power.window_active
+        }
+        LowPowerMode::Always => true,
+        LowPowerMode::Never => false,
+    }
+}
+
+/// How often playback time and the remote are brought up to date.
+pub fn update_interval(cx: &AsyncAppContext) -> Duration {
+    match cx.update(|cx| is_low_power(cx)) {
+        Ok(true) => LOW_POWER_UPDATE_INTERVAL,
+        _ => UPDATE_INTERVAL,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn on_battery() -> bool {
+    std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("'Battery Power'"))
+}
+
+#[cfg(target_os = "windows")]
+fn on_battery() -> bool {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    // 0 is offline, 255 is unknown, which desktops without a battery report
+    unsafe { GetSystemPowerStatus(&mut status) }.is_ok() && status.ACLineStatus == 0
+}
+
+// Any battery discharging means nothing's plugged in
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn on_battery() -> bool {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    supplies.flatten().any(|supply| {
+        std::fs::read_to_string(supply.path().join("status"))
+            .is_ok_and(|status| status.trim() == "Discharging")
+    })
+}
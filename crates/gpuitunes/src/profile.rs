@@ -0,0 +1,100 @@
+use gpui::{AppContext, Global};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::paths;
+
+const DEFAULT_PROFILE: &str = "Default";
+
+/// Everyone using the app on this machine. Profiles share the media files
+/// and artwork, everything else lives in each profile's own directories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    pub names: Vec<String>,
+    pub current: String,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles {
+            names: vec![DEFAULT_PROFILE.to_string()],
+            current: DEFAULT_PROFILE.to_string(),
+        }
+    }
+}
+
+impl Global for Profiles {}
+
+impl Profiles {
+    fn path() -> PathBuf {
+        paths::config_dir().join("profiles.json")
+    }
+
+    pub fn load() -> Self {
+        let Ok(json) = fs::read_to_string(Self::path()) else {
+            return Profiles::default();
+        };
+
+        let mut profiles: Profiles = serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("Failed to parse profiles: {}", e);
+            Profiles::default()
+        });
+        if !profiles.names.contains(&profiles.current) {
+            profiles.current = profiles
+                .names
+                .first()
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        }
+        profiles
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // The default profile keeps the directories from before there were
+    // profiles, so nothing has to move
+    fn profile_dir(&self, root: &Path) -> PathBuf {
+        if self.current == DEFAULT_PROFILE {
+            root.to_path_buf()
+        } else {
+            root.join("profiles").join(&self.current)
+        }
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.profile_dir(&paths::config_dir())
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.profile_dir(&paths::data_dir())
+    }
+
+    /// Adds a profile named after the next free number, returning its name.
+    pub fn add(&mut self) -> String {
+        let name = (2..)
+            .map(|n| format!("Profile {}", n))
+            .find(|name| !self.names.contains(name))
+            .unwrap_or_default();
+        self.names.push(name.clone());
+        name
+    }
+}
+
+pub fn config_dir(cx: &AppContext) -> PathBuf {
+    cx.global::<Profiles>().config_dir()
+}
+
+pub fn data_dir(cx: &AppContext) -> PathBuf {
+    cx.global::<Profiles>().data_dir()
+}
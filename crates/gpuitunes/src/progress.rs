@@ -0,0 +1,208 @@
+// Nothing shows progress on Linux yet
+#![cfg_attr(not(any(target_os = "macos", target_os = "windows")), allow(dead_code))]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// How far along a long-running job is, shared with whichever background
+/// thread is doing it.
+#[derive(Debug, Clone, Default)]
+pub struct JobProgress {
+    done: Arc<AtomicUsize>,
+    total: usize,
+}
+
+impl JobProgress {
+    pub fn new(total: usize) -> Self {
+        JobProgress {
+            done: Arc::default(),
+            total,
+        }
+    }
+
+    pub fn advance(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts whatever's left as done, for a job that's been called off.
+    pub fn finish(&self) {
+        self.done.fetch_max(self.total, Ordering::Relaxed);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done.load(Ordering::Relaxed) >= self.total
+    }
+
+    /// All the jobs' progress as one fraction, `None` when there's nothing
+    /// left to show.
+    pub fn combined(jobs: &[JobProgress]) -> Option<f32> {
+        let total: usize = jobs.iter().map(|job| job.total).sum();
+        if total == 0 {
+            return None;
+        }
+        let done: usize = jobs
+            .iter()
+            .map(|job| job.done.load(Ordering::Relaxed).min(job.total))
+            .sum();
+        Some(done as f32 / total as f32)
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub use self::macos::TaskbarProgress;
+#[cfg(target_os = "windows")]
+pub use self::windows::TaskbarProgress;
+
+// A bar drawn over the app icon in the Dock. The Dock only redraws a custom
+// tile when asked, so every change ends with `display`.
+#[cfg(target_os = "macos")]
+mod macos {
+    use cocoa::{
+        appkit::NSApp,
+        base::{id, nil, NO},
+        foundation::{NSPoint, NSRect, NSSize},
+    };
+    use gpui::WindowContext;
+    use objc::{class, msg_send, sel, sel_impl};
+
+    // NSProgressIndicatorStyleBar
+    const BAR_STYLE: u64 = 0;
+
+    pub struct TaskbarProgress {
+        dock_tile: id,
+        icon_view: id,
+        indicator: id,
+        shown: bool,
+    }
+
+    impl TaskbarProgress {
+        pub fn new(_: &mut WindowContext) -> Option<Self> {
+            unsafe {
+                let dock_tile: id = msg_send![NSApp(), dockTile];
+                let size: NSSize = msg_send![dock_tile, size];
+
+                let icon_view: id = msg_send![class!(NSImageView), alloc];
+                let icon_view: id = msg_send![
+                    icon_view,
+                    initWithFrame: NSRect::new(NSPoint::new(0., 0.), size)
+                ];
+                let icon: id = msg_send![NSApp(), applicationIconImage];
+                let () = msg_send![icon_view, setImage: icon];
+
+                let indicator: id = msg_send![class!(NSProgressIndicator), alloc];
+                let indicator: id = msg_send![
+                    indicator,
+                    initWithFrame: NSRect::new(
+                        NSPoint::new(size.width * 0.1, size.height * 0.08),
+                        NSSize::new(size.width * 0.8, 14.),
+                    )
+                ];
+                let () = msg_send![indicator, setStyle: BAR_STYLE];
+                let () = msg_send![indicator, setIndeterminate: NO];
+                let () = msg_send![indicator, setMinValue: 0f64];
+                let () = msg_send![indicator, setMaxValue: 1f64];
+                let () = msg_send![icon_view, addSubview: indicator];
+
+                Some(TaskbarProgress {
+                    dock_tile,
+                    icon_view,
+                    indicator,
+                    shown: false,
+                })
+            }
+        }
+
+        pub fn set(&mut self, progress: Option<f32>) {
+            unsafe {
+                match progress {
+                    Some(progress) => {
+                        if !self.shown {
+                            let () = msg_send![self.dock_tile, setContentView: self.icon_view];
+                        }
+                        let () = msg_send![self.indicator, setDoubleValue: progress as f64];
+                    }
+                    // Without a content view the Dock goes back to the plain icon
+                    None if self.shown => {
+                        let () = msg_send![self.dock_tile, setContentView: nil];
+                    }
+                    None => return,
+                }
+                let () = msg_send![self.dock_tile, display];
+            }
+            self.shown = progress.is_some();
+        }
+    }
+}
+
+// The taskbar button's progress bar, through ITaskbarList3
+#[cfg(target_os = "windows")]
+mod windows {
+    use ::windows::Win32::{
+        Foundation::HWND,
+        System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER},
+        UI::Shell::{ITaskbarList3, TaskbarList, TBPF_NOPROGRESS, TBPF_NORMAL},
+    };
+    use gpui::WindowContext;
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use std::ffi::c_void;
+
+    // The taskbar takes whole numbers, this is plenty for a bar that small
+    const STEPS: u64 = 1000;
+
+    pub struct TaskbarProgress {
+        taskbar: ITaskbarList3,
+        hwnd: HWND,
+    }
+
+    impl TaskbarProgress {
+        pub fn new(cx: &mut WindowContext) -> Option<Self> {
+            let hwnd = match HasWindowHandle::window_handle(cx).ok()?.as_raw() {
+                RawWindowHandle::Win32(handle) => HWND(handle.hwnd.get() as *mut c_void),
+                _ => return None,
+            };
+            let taskbar: ITaskbarList3 =
+                unsafe { CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER) }
+                    .map_err(|e| eprintln!("Failed to reach the taskbar: {}", e))
+                    .ok()?;
+            unsafe { taskbar.HrInit() }
+                .map_err(|e| eprintln!("Failed to reach the taskbar: {}", e))
+                .ok()?;
+            Some(TaskbarProgress { taskbar, hwnd })
+        }
+
+        pub fn set(&mut self, progress: Option<f32>) {
+            let result = unsafe {
+                match progress {
+                    Some(progress) => self
+                        .taskbar
+                        .SetProgressState(self.hwnd, TBPF_NORMAL)
+                        .and_then(|_| {
+                            self.taskbar.SetProgressValue(
+                                self.hwnd,
+                                (progress * STEPS as f32) as u64,
+                                STEPS,
+                            )
+                        }),
+                    None => self.taskbar.SetProgressState(self.hwnd, TBPF_NOPROGRESS),
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("Failed to show progress on the taskbar: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub struct TaskbarProgress;
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+impl TaskbarProgress {
+    pub fn new(_: &mut gpui::WindowContext) -> Option<Self> {
+        None
+    }
+
+    pub fn set(&mut self, _: Option<f32>) {}
+}
@@ -0,0 +1,98 @@
+use gpui::*;
+
+use crate::{
+    element::*,
+    profile::config_dir,
+    settings::{RemoteSettings, Settings},
+};
+
+/// Turns the remote on and off, and shows the address to pair a phone
+/// with. A new token unpairs every device paired before.
+pub struct RemoteSettingsView;
+
+impl RemoteSettingsView {
+    fn update_settings(&mut self, cx: &mut ViewContext<Self>, f: impl FnOnce(&mut RemoteSettings)) {
+        cx.update_global::<Settings, _>(|settings, _| f(&mut settings.remote));
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        cx.notify();
+    }
+
+    fn render_row(label: &str) -> Div {
+        h_stack().gap(px(8.)).child(
+            div()
+                .w(px(100.))
+                .flex_none()
+                .flex()
+                .justify_end()
+                .text_color(rgb(0x606060))
+                .child(label.to_string()),
+        )
+    }
+}
+
+impl Render for RemoteSettingsView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let settings = cx.global::<Settings>().remote.clone();
+        let address = settings.pairing_address();
+        let events_address = settings.events_address();
+
+        v_stack()
+            .id("remote-settings")
+            .size_full()
+            .p(px(12.))
+            .gap(px(4.))
+            .bg(rgb(0xF0F0F0))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(13.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Remote Control"),
+            )
+            .child(
+                Self::render_row("Remote control").child(
+                    small_button("toggle-remote", if settings.enabled { "On" } else { "Off" })
+                        .on_click(cx.listener(|this, _, cx| {
+                            this.update_settings(cx, |settings| {
+                                settings.enabled = !settings.enabled
+                            })
+                        })),
+                ),
+            )
+            .child(Self::render_row("Port").child(settings.port.to_string()))
+            .child(
+                Self::render_row("Pairing token")
+                    .child(settings.token.clone())
+                    .child(small_button("new-token", "New Token").on_click(cx.listener(
+                        |this, _, cx| {
+                            this.update_settings(cx, |settings| {
+                                settings.token = RemoteSettings::new_token()
+                            })
+                        },
+                    ))),
+            )
+            .child(
+                Self::render_row("Pairing address")
+                    .child(div().overflow_hidden().child(address.clone()))
+                    .child(small_button("copy-address", "Copy").on_click(move |_, cx| {
+                        cx.write_to_clipboard(ClipboardItem::new_string(address.clone()))
+                    })),
+            )
+            .child(
+                Self::render_row("Events")
+                    .child(div().overflow_hidden().child(events_address.clone()))
+                    .child(small_button("copy-events", "Copy").on_click(move |_, cx| {
+                        cx.write_to_clipboard(ClipboardItem::new_string(events_address.clone()))
+                    })),
+            )
+            .child(
+                div()
+                    .pt(px(6.))
+                    .text_color(rgb(0x808080))
+                    .child("Open the pairing address in a browser on the same network."),
+            )
+    }
+}
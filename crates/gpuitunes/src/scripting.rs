@@ -0,0 +1,174 @@
+use library::{MetadataField, Track, TrackId};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use std::{cell::RefCell, fs, path::Path, process::Command, rc::Rc};
+
+// Enough for any reasonable hook, a runaway loop gives up well before the
+// app stops responding
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+pub const ON_TRACK_START: &str = "on_track_start";
+pub const ON_TRACK_END: &str = "on_track_end";
+pub const ON_IMPORT: &str = "on_import";
+
+/// What a script asked for. Scripts never touch app state directly, the
+/// window applies these once the hook returns.
+#[derive(Debug, Clone)]
+pub enum ScriptCommand {
+    Play(TrackId),
+    Queue(TrackId),
+    SetField(TrackId, MetadataField, Option<String>),
+    AddToPlaylist(TrackId, String),
+}
+
+/// The `.rhai` files in the scripts folder, each defining whichever hooks
+/// it needs as plain functions.
+pub struct Scripts {
+    engine: Engine,
+    scripts: Vec<(String, AST)>,
+    commands: Rc<RefCell<Vec<ScriptCommand>>>,
+}
+
+impl Scripts {
+    pub fn load(dir: &Path) -> Self {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| eprintln!("Script: {}", text));
+        register_api(&mut engine, &commands);
+
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "rhai")
+            })
+            .collect();
+        // Scripts run in name order, so a numeric prefix decides which goes first
+        paths.sort();
+        let scripts = paths
+            .into_iter()
+            .filter_map(|path| {
+                let name = path.file_name()?.to_string_lossy().to_string();
+                engine
+                    .compile_file(path)
+                    .map(|ast| (name.clone(), ast))
+                    .map_err(|e| eprintln!("Failed to load script {}: {}", name, e))
+                    .ok()
+            })
+            .collect();
+
+        Scripts {
+            engine,
+            scripts,
+            commands,
+        }
+    }
+
+    /// Calls `hook` in every script that defines it, returning what they
+    /// asked for along the way.
+    pub fn run_hook(&self, hook: &str, args: Vec<Dynamic>) -> Vec<ScriptCommand> {
+        for (name, ast) in &self.scripts {
+            if !ast.iter_functions().any(|function| function.name == hook) {
+                continue;
+            }
+            let result = self
+                .engine
+                .call_fn::<Dynamic>(&mut Scope::new(), ast, hook, args.clone());
+            if let Err(e) = result {
+                eprintln!("Script {} failed in {}: {}", name, hook, e);
+            }
+        }
+        self.commands.borrow_mut().drain(..).collect()
+    }
+}
+
+/// A track as scripts see it: a map of its tags, keyed the same way as
+/// `set_field`.
+pub fn track_map(track: &Track) -> Dynamic {
+    let mut map = Map::new();
+    let id: String = track.id().clone().into();
+    map.insert("id".into(), id.into());
+    for (key, field) in FIELDS {
+        let value = track
+            .metadata(*field)
+            .map(Dynamic::from)
+            .unwrap_or(Dynamic::UNIT);
+        map.insert((*key).into(), value);
+    }
+    map.insert("duration".into(), (track.duration() as i64).into());
+    map.insert("plays".into(), (track.plays() as i64).into());
+    map.insert("kind".into(), track.kind().to_string().into());
+    map.into()
+}
+
+const FIELDS: &[(&str, MetadataField)] = &[
+    ("title", MetadataField::Title),
+    ("artist", MetadataField::Artist),
+    ("album", MetadataField::Album),
+    ("album_artist", MetadataField::AlbumArtist),
+    ("grouping", MetadataField::Grouping),
+    ("genre", MetadataField::Genre),
+    ("year", MetadataField::Year),
+    ("release_date", MetadataField::ReleaseDate),
+    ("original_release_date", MetadataField::OriginalReleaseDate),
+    ("decade", MetadataField::Decade),
+    ("language", MetadataField::Language),
+    ("mood", MetadataField::Mood),
+    ("key", MetadataField::Key),
+    ("track_number", MetadataField::TrackNumber),
+    ("rating", MetadataField::Rating),
+];
+
+fn register_api(engine: &mut Engine, commands: &Rc<RefCell<Vec<ScriptCommand>>>) {
+    let queued = commands.clone();
+    engine.register_fn("play", move |id: &str| {
+        queued
+            .borrow_mut()
+            .push(ScriptCommand::Play(TrackId::from(id.to_string())));
+    });
+
+    let queued = commands.clone();
+    engine.register_fn("queue", move |id: &str| {
+        queued
+            .borrow_mut()
+            .push(ScriptCommand::Queue(TrackId::from(id.to_string())));
+    });
+
+    // Passing () clears the field
+    let queued = commands.clone();
+    engine.register_fn(
+        "set_field",
+        move |id: &str, key: &str, value: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            let Some((_, field)) = FIELDS.iter().find(|(name, _)| *name == key) else {
+                return Err(format!("Unknown field {}", key).into());
+            };
+            let value = (!value.is_unit()).then(|| value.to_string());
+            queued.borrow_mut().push(ScriptCommand::SetField(
+                TrackId::from(id.to_string()),
+                *field,
+                value,
+            ));
+            Ok(())
+        },
+    );
+
+    let queued = commands.clone();
+    engine.register_fn("add_to_playlist", move |id: &str, playlist: &str| {
+        queued.borrow_mut().push(ScriptCommand::AddToPlaylist(
+            TrackId::from(id.to_string()),
+            playlist.to_string(),
+        ));
+    });
+
+    // Left running in the background, for things like posting to a
+    // scrobbler with curl
+    engine.register_fn("run", |program: &str, args: Array| {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        if let Err(e) = Command::new(program).args(&args).spawn() {
+            eprintln!("Failed to run {}: {}", program, e);
+        }
+    });
+}
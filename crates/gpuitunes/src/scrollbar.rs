@@ -0,0 +1,135 @@
+use gpui::*;
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(16);
+// How much of the remaining distance each frame covers while easing
+const EASING: f32 = 0.25;
+pub const SCROLLBAR_WIDTH: Pixels = px(15.);
+const MIN_THUMB_HEIGHT: Pixels = px(24.);
+
+/// Scroll position and thumb dragging for a uniform list, with the easing
+/// used whenever the app scrolls the list itself. Trackpad momentum comes
+/// through as scroll events the list already follows.
+pub struct ScrollbarState {
+    handle: UniformListScrollHandle,
+    // Where the pointer grabbed the thumb, from the thumb's top
+    drag_offset: Option<Pixels>,
+    _animation: Option<Task<()>>,
+}
+
+impl ScrollbarState {
+    pub fn new(handle: UniformListScrollHandle) -> Self {
+        ScrollbarState {
+            handle,
+            drag_offset: None,
+            _animation: None,
+        }
+    }
+
+    pub fn viewport(&self) -> Bounds<Pixels> {
+        self.handle.0.borrow().base_handle.bounds()
+    }
+
+    fn content_height(&self) -> Pixels {
+        self.handle
+            .0
+            .borrow()
+            .last_item_size
+            .map(|size| size.contents.height)
+            .unwrap_or_default()
+    }
+
+    fn max_offset(&self) -> Pixels {
+        (self.content_height() - self.viewport().size.height).max(px(0.))
+    }
+
+    /// How far down the list is scrolled, from 0 at the top.
+    pub fn offset(&self) -> Pixels {
+        -self.handle.0.borrow().base_handle.offset().y
+    }
+
+    pub fn scroll_to(&mut self, offset: Pixels) {
+        let offset = offset.clamp(px(0.), self.max_offset());
+        let base_handle = &self.handle.0.borrow().base_handle;
+        base_handle.set_offset(point(base_handle.offset().x, -offset));
+    }
+
+    pub fn animate_to<V: 'static>(&mut self, target: Pixels, cx: &mut ViewContext<V>) {
+        let target = target.clamp(px(0.), self.max_offset());
+        let handle = self.handle.clone();
+        self._animation = Some(cx.spawn(|this, mut cx| async move {
+            loop {
+                cx.background_executor().timer(FRAME).await;
+                let base_handle = handle.0.borrow().base_handle.clone();
+                let current = -base_handle.offset().y;
+                let remaining = target - current;
+                let next = if remaining.abs() < px(1.) {
+                    target
+                } else {
+                    current + remaining * EASING
+                };
+                base_handle.set_offset(point(base_handle.offset().x, -next));
+                if this.update(&mut cx, |_, cx| cx.notify()).is_err() || next == target {
+                    break;
+                }
+            }
+        }));
+    }
+
+    // Leaves a little of the previous page showing, as the Finder does
+    pub fn page<V: 'static>(&mut self, pages: f32, overlap: Pixels, cx: &mut ViewContext<V>) {
+        let page = (self.viewport().size.height - overlap).max(px(0.));
+        self.animate_to(self.offset() + page * pages, cx);
+    }
+
+    /// The thumb's top and height, measured down the track. `None` when
+    /// everything fits and there's nothing to scroll.
+    pub fn thumb(&self) -> Option<(Pixels, Pixels)> {
+        let track = self.viewport().size.height;
+        let content = self.content_height();
+        if content <= track || track <= px(0.) {
+            return None;
+        }
+
+        let height = (track * (track / content)).max(MIN_THUMB_HEIGHT).min(track);
+        let top = (track - height) * (self.offset() / self.max_offset());
+        Some((top, height))
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag_offset.is_some()
+    }
+
+    /// Starts dragging the thumb, or pages toward the pointer when the track
+    /// outside it is pressed.
+    pub fn mouse_down<V: 'static>(&mut self, position: Point<Pixels>, cx: &mut ViewContext<V>) {
+        let Some((top, height)) = self.thumb() else {
+            return;
+        };
+        let y = position.y - self.viewport().origin.y;
+        if y < top {
+            self.page(-1., px(0.), cx);
+        } else if y > top + height {
+            self.page(1., px(0.), cx);
+        } else {
+            self._animation = None;
+            self.drag_offset = Some(y - top);
+        }
+    }
+
+    pub fn drag_to(&mut self, position: Point<Pixels>) {
+        let (Some(drag_offset), Some((_, height))) = (self.drag_offset, self.thumb()) else {
+            return;
+        };
+        let travel = self.viewport().size.height - height;
+        if travel <= px(0.) {
+            return;
+        }
+        let top = position.y - self.viewport().origin.y - drag_offset;
+        self.scroll_to(self.max_offset() * (top / travel));
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag_offset = None;
+    }
+}
@@ -0,0 +1,340 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{parse_query, Library, QueryError, SmartCriteria, Suggestion, Suggestions, TrackId};
+
+use crate::{assets::Icon, element::*};
+
+// Of each kind, artists, albums and songs
+const SUGGESTIONS_PER_KIND: usize = 4;
+
+pub struct SearchField {
+    library: Model<Library>,
+    query: String,
+    focus_handle: FocusHandle,
+    suggestions: Suggestions,
+    // Moved with the arrow keys, an index into `Suggestions::iter`
+    highlighted: Option<usize>,
+}
+
+pub enum SearchEvent {
+    // `None` when the query is empty
+    Changed(Option<SmartCriteria>),
+    SaveAsSmartPlaylist(String, SmartCriteria),
+    Play(TrackId),
+}
+
+impl EventEmitter<SearchEvent> for SearchField {}
+
+impl SearchField {
+    pub fn new(library: Model<Library>, cx: &mut ViewContext<Self>) -> Self {
+        SearchField {
+            library,
+            query: String::new(),
+            focus_handle: cx.focus_handle(),
+            suggestions: Suggestions::default(),
+            highlighted: None,
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn focus(&self, cx: &mut ViewContext<Self>) {
+        cx.focus(&self.focus_handle);
+    }
+
+    fn parsed(&self) -> Option<Result<SmartCriteria, QueryError>> {
+        (!self.query.trim().is_empty()).then(|| parse_query(&self.query))
+    }
+
+    fn set_query(&mut self, query: String, cx: &mut ViewContext<Self>) {
+        self.query = query;
+        // Field queries are already precise, suggestions are for plain words
+        self.suggestions = if self.query.contains(':') {
+            Suggestions::default()
+        } else {
+            self.library
+                .read(cx)
+                .suggestions(&self.query, SUGGESTIONS_PER_KIND)
+        };
+        self.highlighted = None;
+        // A malformed query leaves the last results showing while it's fixed
+        match self.parsed() {
+            None => cx.emit(SearchEvent::Changed(None)),
+            Some(Ok(criteria)) => cx.emit(SearchEvent::Changed(Some(criteria))),
+            Some(Err(_)) => {}
+        }
+        cx.notify();
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.platform || keystroke.modifiers.control {
+            return;
+        }
+
+        let suggestion_count = self.suggestions.iter().count();
+        let mut query = self.query.clone();
+        match keystroke.key.as_str() {
+            "down" if suggestion_count > 0 => {
+                self.highlighted = Some(
+                    self.highlighted
+                        .map_or(0, |ix| (ix + 1).min(suggestion_count - 1)),
+                );
+                cx.stop_propagation();
+                cx.notify();
+                return;
+            }
+            "up" if suggestion_count > 0 => {
+                self.highlighted = self.highlighted.and_then(|ix| ix.checked_sub(1));
+                cx.stop_propagation();
+                cx.notify();
+                return;
+            }
+            "enter" => {
+                if let Some(ix) = self.highlighted {
+                    self.choose(ix, cx);
+                }
+                cx.stop_propagation();
+                return;
+            }
+            // The first escape only closes the suggestions
+            "escape" if !self.suggestions.is_empty() => {
+                self.dismiss_suggestions(cx);
+                cx.stop_propagation();
+                return;
+            }
+            "backspace" => {
+                query.pop();
+            }
+            "escape" => query.clear(),
+            _ => match &keystroke.key_char {
+                Some(text) if !text.chars().any(char::is_control) => query.push_str(text),
+                _ => return,
+            },
+        }
+        cx.stop_propagation();
+        self.set_query(query, cx);
+    }
+
+    fn clear(&mut self, cx: &mut ViewContext<Self>) {
+        self.set_query(String::new(), cx);
+        self.dismiss_suggestions(cx);
+    }
+
+    fn dismiss_suggestions(&mut self, cx: &mut ViewContext<Self>) {
+        self.suggestions = Suggestions::default();
+        self.highlighted = None;
+        cx.notify();
+    }
+
+    // Artists and albums filter to themselves, songs start playing
+    fn choose(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let Some(suggestion) = self.suggestions.iter().nth(ix).cloned() else {
+            return;
+        };
+        match suggestion {
+            Suggestion::Artist(artist) => {
+                self.set_query(format!("artist:\"{}\"", artist), cx);
+                self.dismiss_suggestions(cx);
+            }
+            Suggestion::Album { album, .. } => {
+                self.set_query(format!("album:\"{}\"", album), cx);
+                self.dismiss_suggestions(cx);
+            }
+            Suggestion::Song(track_id) => {
+                self.dismiss_suggestions(cx);
+                cx.emit(SearchEvent::Play(track_id));
+            }
+        }
+    }
+
+    fn render_suggestions(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        if self.suggestions.is_empty() {
+            return None;
+        }
+        let library = self.library.read(cx);
+
+        let mut ix = 0;
+        let mut groups = Vec::new();
+        for (heading, suggestions) in [
+            ("Artists", &self.suggestions.artists),
+            ("Albums", &self.suggestions.albums),
+            ("Songs", &self.suggestions.songs),
+        ] {
+            if suggestions.is_empty() {
+                continue;
+            }
+            groups.push(context_menu_label(heading).into_any_element());
+            for suggestion in suggestions {
+                let item_ix = ix;
+                ix += 1;
+                let (name, detail) = match suggestion {
+                    Suggestion::Artist(artist) => (artist.clone(), None),
+                    Suggestion::Album { album, artist } => (album.clone(), Some(artist.clone())),
+                    Suggestion::Song(track_id) => match library.track(track_id) {
+                        Some(track) => (track.title(), Some(track.artist())),
+                        None => continue,
+                    },
+                };
+                groups.push(
+                    h_stack()
+                        .id(("suggestion", item_ix))
+                        .px(px(12.))
+                        .py(px(1.))
+                        .gap(px(6.))
+                        .cursor_pointer()
+                        .when(self.highlighted == Some(item_ix), |item| {
+                            item.bg(rgb(0x3D80DF)).text_color(rgb(0xFFFFFF))
+                        })
+                        .hover(|style| style.bg(rgb(0xC5D9F5)))
+                        .child(div().flex_1().overflow_hidden().child(name))
+                        .children(detail.map(|detail| {
+                            div()
+                                .flex_none()
+                                .max_w(px(100.))
+                                .overflow_hidden()
+                                .opacity(0.6)
+                                .child(detail)
+                        }))
+                        .on_click(cx.listener(move |this, _, cx| this.choose(item_ix, cx)))
+                        .into_any_element(),
+                );
+            }
+        }
+
+        Some(deferred(
+            anchored().child(
+                v_stack()
+                    .occlude()
+                    .mt(px(22.))
+                    .py(px(4.))
+                    .w(px(240.))
+                    .rounded(px(4.))
+                    .border_1()
+                    .border_color(rgb(0xA0A0A0))
+                    .bg(rgb(0xFFFFFF))
+                    .shadow_lg()
+                    .text_size(px(12.))
+                    .text_color(rgb(0x0F1219))
+                    .on_mouse_down_out(cx.listener(|this, _, cx| this.dismiss_suggestions(cx)))
+                    .children(groups),
+            ),
+        ))
+    }
+
+    fn save_as_smart_playlist(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(Ok(criteria)) = self.parsed() {
+            cx.emit(SearchEvent::SaveAsSmartPlaylist(
+                self.query.trim().to_string(),
+                criteria,
+            ));
+        }
+    }
+
+    // The malformed part is underlined in red, with the reason below the field
+    fn render_query(&self, error: Option<&QueryError>) -> Div {
+        let Some(error) = error else {
+            return h_stack().child(self.query.clone());
+        };
+        let start = error.range.start.min(self.query.len());
+        let end = error.range.end.min(self.query.len()).max(start);
+
+        h_stack()
+            .child(self.query[..start].to_string())
+            .child(
+                div()
+                    .text_color(rgb(0xC4312B))
+                    .border_b_1()
+                    .border_color(rgb(0xC4312B))
+                    .child(self.query[start..end].to_string()),
+            )
+            .child(self.query[end..].to_string())
+    }
+}
+
+impl Render for SearchField {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let parsed = self.parsed();
+        let error = parsed.as_ref().and_then(|parsed| parsed.as_ref().err());
+        let can_save = matches!(parsed, Some(Ok(_)));
+        let input_width: f32 = 134.;
+        let input_height: f32 = 20.;
+
+        h_stack()
+            .id("search")
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(Self::key_down))
+            .on_click(cx.listener(|this, _, cx| cx.focus(&this.focus_handle)))
+            .mr(px(20.))
+            .flex_none()
+            .rounded_full()
+            .w(px(input_width))
+            .h(px(input_height))
+            .bg(vertical_linear_gradient(rgb(0xC5C5C5), rgb(0x969696)))
+            .when(error.is_some(), |field| field.bg(rgb(0xC4312B)))
+            .child(
+                h_stack()
+                    .flex_none()
+                    .rounded_full()
+                    .gap(px(4.))
+                    .px(px(3.))
+                    .w(px(input_width - 2.))
+                    .h(px(input_height - 2.))
+                    .bg(rgb(0xFFFFFF))
+                    .overflow_hidden()
+                    .child(small_icon(Icon::MagnifyingGlass))
+                    .child(
+                        h_stack()
+                            .flex_1()
+                            .overflow_hidden()
+                            .text_size(px(11.))
+                            .line_height(px(11.))
+                            .map(|query| {
+                                if self.query.is_empty() {
+                                    query.child("Search...")
+                                } else {
+                                    query.child(self.render_query(error))
+                                }
+                            }),
+                    )
+                    .when(can_save, |field| {
+                        field.child(
+                            div()
+                                .id("save-search")
+                                .cursor_pointer()
+                                .child(small_icon(Icon::Gear).text_color(rgb(0x808080)))
+                                .on_click(
+                                    cx.listener(|this, _, cx| this.save_as_smart_playlist(cx)),
+                                ),
+                        )
+                    })
+                    .when(!self.query.is_empty(), |field| {
+                        field.child(
+                            div()
+                                .id("clear-search")
+                                .cursor_pointer()
+                                .child(small_icon(Icon::XCircle).text_color(rgb(0xB3B3B3)))
+                                .on_click(cx.listener(|this, _, cx| {
+                                    cx.stop_propagation();
+                                    this.clear(cx)
+                                })),
+                        )
+                    }),
+            )
+            .when_some(error, |field, error| {
+                field.relative().child(
+                    div()
+                        .absolute()
+                        .top(px(input_height + 1.))
+                        .left(px(8.))
+                        .w(px(input_width))
+                        .overflow_hidden()
+                        .text_size(px(9.))
+                        .text_color(rgb(0xC4312B))
+                        .child(error.message.clone()),
+                )
+            })
+            .children(self.render_suggestions(cx))
+    }
+}
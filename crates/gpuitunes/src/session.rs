@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::volume::Volume;
+
+/// A source as it can be found again after a restart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SavedSource {
+    Library,
+    Playlist(String),
+    RecentlyDeleted,
+}
+
+// Track ids are made fresh on every load, so the track is found by its tags
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedTrack {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub current_time: i32,
+}
+
+/// Where things were left at quit, used to pick back up at the next launch.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub source: Option<SavedSource>,
+    pub track: Option<SavedTrack>,
+    pub volume: Volume,
+    // The output the volume was set for
+    pub output_device: Option<String>,
+}
+
+impl Session {
+    fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join("session.json")
+    }
+
+    pub fn load(data_dir: &Path) -> Self {
+        let Ok(json) = fs::read_to_string(Self::path(data_dir)) else {
+            return Session::default();
+        };
+
+        serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("Failed to parse session: {}", e);
+            Session::default()
+        })
+    }
+
+    pub fn save(&self, data_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::path(data_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+use gpui::*;
+use library::{
+    date_for_timestamp, unix_time, DailyMixExclusions, DateFormat, ImportRule, LibraryBackend,
+};
+use playback::{
+    CaptureSettings, CrossfadeSettings, DspSettings, DynamicsSettings, OutputSettings,
+    StreamCacheSettings,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::UdpSocket,
+    path::{Path, PathBuf},
+};
+
+use crate::{paths, volume::Volume};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteSettings {
+    pub enabled: bool,
+    pub port: u16,
+    // Wanted by every request to the remote's API, so only devices opened
+    // from the pairing address can use it. Made up when the server first
+    // starts.
+    pub token: String,
+}
+
+impl Default for RemoteSettings {
+    fn default() -> Self {
+        RemoteSettings {
+            enabled: false,
+            port: 8018,
+            token: String::new(),
+        }
+    }
+}
+
+impl RemoteSettings {
+    pub fn new_token() -> String {
+        uuid::Uuid::new_v4().simple().to_string()
+    }
+
+    /// Where to point a phone's browser to pair it, with the token in the
+    /// address.
+    pub fn pairing_address(&self) -> String {
+        format!(
+            "http://{}:{}/?token={}",
+            local_host(),
+            self.port,
+            self.token
+        )
+    }
+
+    /// Where integrations such as overlays subscribe to playback events.
+    pub fn events_address(&self) -> String {
+        format!(
+            "ws://{}:{}/api/events?token={}",
+            local_host(),
+            remote::events_port(self.port),
+            self.token
+        )
+    }
+}
+
+// Connecting a UDP socket sends nothing, it only picks the interface the
+// machine would reach the network through
+fn local_host() -> String {
+    UdpSocket::bind(("0.0.0.0", 0))
+        .and_then(|socket| {
+            socket.connect(("192.0.2.1", 80))?;
+            socket.local_addr()
+        })
+        .map(|address| address.ip().to_string())
+        .unwrap_or_else(|_| "localhost".to_string())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportSettings {
+    pub music_folder: Option<PathBuf>,
+    // Scanned after the music folder, such as an archive drive. When a track
+    // is in more than one, the better copy is played, or on a tie the one in
+    // the earlier folder
+    pub extra_folders: Vec<PathBuf>,
+    pub delete_archives_after_import: bool,
+    // Every imported file is read through once to take its checksum, for
+    // Verify Library Files to check against later
+    pub store_checksums: bool,
+    // Moods are also written into MP3 comments, for other players to see
+    pub moods_in_comments: bool,
+    // Edits made in Get Info are also written into MP3 tags
+    pub write_edited_tags: bool,
+    // Bandcamp and Beatport purchases landing here are imported automatically
+    pub watched_downloads_folder: Option<PathBuf>,
+    // Run over every file as it's imported, in order
+    pub rules: Vec<ImportRule>,
+}
+
+impl ImportSettings {
+    pub fn music_folder(&self) -> PathBuf {
+        self.music_folder
+            .clone()
+            .unwrap_or_else(|| paths::data_dir().join("music"))
+    }
+
+    /// Every folder scanned into the library, in order of preference.
+    pub fn library_roots(&self) -> Vec<PathBuf> {
+        let mut roots = vec![self.music_folder()];
+        roots.extend(self.extra_folders.iter().cloned());
+        roots
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibraryStoreSettings {
+    // Read at launch. Switching to SQLite moves library.json into the
+    // database the first time
+    pub backend: LibraryBackend,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub date_format: DateFormat,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtworkSettings {
+    // When folder images are embedded, larger ones are scaled down to fit
+    pub embed_max_size: Option<u32>,
+    pub embed_as_jpeg: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyMixSettings {
+    pub enabled: bool,
+    // Hour of the day, in UTC, the mixes are made anew
+    pub new_day_hour: u32,
+    pub exclusions: DailyMixExclusions,
+}
+
+impl Default for DailyMixSettings {
+    fn default() -> Self {
+        DailyMixSettings {
+            enabled: true,
+            new_day_hour: 6,
+            exclusions: DailyMixExclusions::default(),
+        }
+    }
+}
+
+impl DailyMixSettings {
+    // Still yesterday until the new day hour comes round
+    pub fn day(&self) -> String {
+        date_for_timestamp(unix_time().saturating_sub(self.new_day_hour.min(23) as u64 * 3600))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NewReleaseSettings {
+    // By name, as they're credited on MusicBrainz
+    pub followed_artists: Vec<String>,
+    pub check_every_hours: u32,
+    // How far back a release still counts as new
+    pub lookback_days: u32,
+}
+
+impl Default for NewReleaseSettings {
+    fn default() -> Self {
+        NewReleaseSettings {
+            followed_artists: Vec::new(),
+            check_every_hours: 24,
+            lookback_days: 90,
+        }
+    }
+}
+
+/// An internet radio station, listed in the sidebar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RadioStation {
+    pub name: String,
+    pub url: String,
+    // Saved as it plays, a file per song
+    #[serde(default)]
+    pub record: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RadioSettings {
+    pub stations: Vec<RadioStation>,
+    // Each station records into a folder of its own in here, "Radio
+    // Recordings" in the data folder when unset
+    pub recordings_folder: Option<PathBuf>,
+}
+
+impl RadioSettings {
+    pub fn recording_folder(&self, station: &RadioStation, data_dir: &Path) -> PathBuf {
+        let folder = self
+            .recordings_folder
+            .clone()
+            .unwrap_or_else(|| data_dir.join("Radio Recordings"));
+        let name: String = station
+            .name
+            .chars()
+            .map(|c| if "/\\:*?\"<>|".contains(c) { '-' } else { c })
+            .collect();
+        folder.join(name.trim())
+    }
+}
+
+/// A text file kept up to date with what's playing, for streaming software
+/// to show without a plugin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverlayFile {
+    // Relative to the overlay folder, unless absolute
+    pub path: PathBuf,
+    // Fields in braces, like "{artist} — {title}"
+    pub template: String,
+}
+
+impl OverlayFile {
+    fn new(path: &str, template: &str) -> Self {
+        OverlayFile {
+            path: PathBuf::from(path),
+            template: template.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamOverlaySettings {
+    pub enabled: bool,
+    // "Now Playing" in the data folder when unset
+    pub folder: Option<PathBuf>,
+    pub files: Vec<OverlayFile>,
+    // The song's artwork is copied here, and removed when it has none
+    pub artwork: Option<PathBuf>,
+    // A line per upcoming song, each filled in from the template
+    pub up_next: Option<OverlayFile>,
+    pub up_next_count: usize,
+}
+
+impl Default for StreamOverlaySettings {
+    fn default() -> Self {
+        StreamOverlaySettings {
+            enabled: false,
+            folder: None,
+            files: vec![
+                OverlayFile::new("now-playing.txt", "{artist} — {title}"),
+                OverlayFile::new("title.txt", "{title}"),
+                OverlayFile::new("artist.txt", "{artist}"),
+                OverlayFile::new("album.txt", "{album}"),
+            ],
+            // Image sources go by what's in the file, not its extension
+            artwork: Some(PathBuf::from("artwork.jpg")),
+            up_next: Some(OverlayFile::new("up-next.txt", "{artist} — {title}")),
+            up_next_count: 3,
+        }
+    }
+}
+
+impl StreamOverlaySettings {
+    pub fn folder(&self, data_dir: &Path) -> PathBuf {
+        self.folder
+            .clone()
+            .unwrap_or_else(|| data_dir.join("Now Playing"))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnnouncementSettings {
+    pub enabled: bool,
+    // Filled in like the stream overlay's, "{artist} — {title}" and so on
+    pub template: String,
+    // The system's default voice when unset
+    pub voice: Option<String>,
+    // How far the music is held down while it's spoken over, as a fraction
+    // of the volume
+    pub duck_level: f32,
+}
+
+impl Default for AnnouncementSettings {
+    fn default() -> Self {
+        AnnouncementSettings {
+            enabled: false,
+            template: "{artist} — {title}".to_string(),
+            voice: None,
+            duck_level: 0.4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoplaySettings {
+    pub enabled: bool,
+    // How many similar tracks are queued each time Up Next runs out
+    pub batch_size: usize,
+}
+
+impl Default for AutoplaySettings {
+    fn default() -> Self {
+        AutoplaySettings {
+            enabled: false,
+            batch_size: 10,
+        }
+    }
+}
+
+/// How an output device was last left, put back when playback moves to it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputProfile {
+    pub volume: Volume,
+    pub dsp: DspSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputProfileSettings {
+    pub enabled: bool,
+    // By device name, as the system reports it
+    pub devices: BTreeMap<String, OutputProfile>,
+}
+
+impl Default for OutputProfileSettings {
+    fn default() -> Self {
+        OutputProfileSettings {
+            enabled: true,
+            devices: BTreeMap::new(),
+        }
+    }
+}
+
+// Offline leaves MusicBrainz, Wikidata and Wikipedia alone, new releases
+// and artist info included
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub offline: bool,
+}
+
+/// What the window opens to at launch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupSource {
+    #[default]
+    Library,
+    // Whatever was showing at quit
+    LastSource,
+    // By name, as playlist ids don't survive a restart
+    Playlist(String),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupSettings {
+    pub source: StartupSource,
+    // Pick the track that was playing at quit back up where it left off
+    pub resume_playback: bool,
+}
+
+// Off unless asked for, reports only ever stay on this machine
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashReportSettings {
+    pub enabled: bool,
+}
+
+/// When to cut back on background work to save battery.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LowPowerMode {
+    // On battery, or while the window is in the background
+    #[default]
+    Automatic,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerSettings {
+    pub low_power: LowPowerMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub stream_cache: StreamCacheSettings,
+    pub output: OutputSettings,
+    pub crossfade: CrossfadeSettings,
+    pub dsp: DspSettings,
+    pub dynamics: DynamicsSettings,
+    pub output_profiles: OutputProfileSettings,
+    pub announcements: AnnouncementSettings,
+    pub autoplay: AutoplaySettings,
+    pub remote: RemoteSettings,
+    pub import: ImportSettings,
+    pub library_store: LibraryStoreSettings,
+    pub display: DisplaySettings,
+    pub artwork: ArtworkSettings,
+    pub daily_mix: DailyMixSettings,
+    pub new_releases: NewReleaseSettings,
+    pub network: NetworkSettings,
+    pub radio: RadioSettings,
+    pub capture: CaptureSettings,
+    pub stream_overlay: StreamOverlaySettings,
+    pub startup: StartupSettings,
+    pub power: PowerSettings,
+    pub crash_reports: CrashReportSettings,
+}
+
+impl Global for Settings {}
+
+impl Settings {
+    fn path(config_dir: &Path) -> PathBuf {
+        config_dir.join("settings.json")
+    }
+
+    pub fn load(config_dir: &Path) -> Self {
+        let Ok(json) = fs::read_to_string(Self::path(config_dir)) else {
+            return Settings::default();
+        };
+
+        serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("Failed to parse settings: {}", e);
+            Settings::default()
+        })
+    }
+
+    pub fn save(&self, config_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::path(config_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
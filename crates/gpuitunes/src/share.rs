@@ -0,0 +1,55 @@
+use gpui::Global;
+use std::sync::mpsc::Receiver;
+
+/// `gpuitunes://` links the system asked the app to open, waiting for the
+/// main window to pick them up. They can arrive before it's open.
+pub struct OpenedLinks(pub Receiver<String>);
+
+impl Global for OpenedLinks {}
+
+/// Offers `text` to the system share sheet, over the middle of the key
+/// window. Returns false where there's no share sheet to offer it to.
+#[cfg(target_os = "macos")]
+pub fn share(text: &str) -> bool {
+    use cocoa::{
+        appkit::NSApp,
+        base::{id, nil},
+        foundation::{NSArray, NSPoint, NSRect, NSSize, NSString},
+    };
+    use objc::{class, msg_send, sel, sel_impl};
+
+    // NSMinYEdge
+    const PREFERRED_EDGE: u64 = 1;
+
+    unsafe {
+        let window: id = msg_send![NSApp(), keyWindow];
+        if window == nil {
+            return false;
+        }
+        let view: id = msg_send![window, contentView];
+        let bounds: NSRect = msg_send![view, bounds];
+        let anchor = NSRect::new(
+            NSPoint::new(bounds.size.width / 2., bounds.size.height / 2.),
+            NSSize::new(1., 1.),
+        );
+
+        let item = NSString::alloc(nil).init_str(text);
+        let items = NSArray::arrayWithObject(nil, item);
+        // Not released, AppKit doesn't keep the picker alive while it's up
+        let picker: id = msg_send![class!(NSSharingServicePicker), alloc];
+        let picker: id = msg_send![picker, initWithItems: items];
+        let () = msg_send![
+            picker,
+            showRelativeToRect: anchor
+            ofView: view
+            preferredEdge: PREFERRED_EDGE
+        ];
+        let () = msg_send![item, release];
+    }
+    true
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn share(_text: &str) -> bool {
+    false
+}
@@ -0,0 +1,295 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{format_size, AlbumSize, Library, Share, StorageReport, TrackId};
+use playback::{transcode, CaptureFormat};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{element::*, progress::JobProgress};
+
+// Rows shown per breakdown, the rest are summed into "Other"
+const SHARES_SHOWN: usize = 6;
+const BAR_WIDTH: f32 = 160.;
+
+pub struct StorageReportView {
+    library: Model<Library>,
+    report: StorageReport,
+    _subscriptions: Vec<Subscription>,
+}
+
+pub enum StorageEvent {
+    ShowAlbum(AlbumSize),
+    MakePlaylist(String, Vec<TrackId>),
+    // Moves the album's files to a folder that's asked for
+    Relocate(AlbumSize),
+    // Replaces the WAV and AIFF files with FLAC ones
+    Transcode(Vec<TrackId>),
+}
+
+/// Moves the files into `folder`, keeping their names, and returns where
+/// each ended up. Files that are in the way are left alone.
+pub fn relocate_files(
+    files: Vec<(TrackId, PathBuf)>,
+    folder: &Path,
+    progress: &JobProgress,
+) -> Vec<(TrackId, PathBuf)> {
+    files
+        .into_iter()
+        .filter_map(|(id, path)| {
+            let destination = folder.join(path.file_name()?);
+            let result = move_file(&path, &destination);
+            progress.advance();
+            match result {
+                Ok(()) => Some((id, destination)),
+                Err(e) => {
+                    eprintln!("Failed to move {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if to.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is in the way", to.display()),
+        ));
+    }
+    // Renaming can't cross volumes, copying can
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+/// Transcodes the files to FLAC next to them, removing the originals once
+/// that's worked, and returns the new files.
+pub fn transcode_files(
+    files: Vec<(TrackId, PathBuf)>,
+    progress: &JobProgress,
+) -> Vec<(TrackId, PathBuf)> {
+    files
+        .into_iter()
+        .filter_map(|(id, path)| {
+            let destination = path.with_extension(CaptureFormat::Flac.extension());
+            let result = if destination.exists() {
+                Err(anyhow::anyhow!("{} is in the way", destination.display()))
+            } else {
+                transcode(&path, &destination, CaptureFormat::Flac)
+            };
+            progress.advance();
+            match result {
+                Ok(destination) => {
+                    if let Err(e) = fs::remove_file(&path) {
+                        eprintln!("Failed to remove {}: {}", path.display(), e);
+                    }
+                    Some((id, destination))
+                }
+                Err(e) => {
+                    eprintln!("Failed to transcode {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+impl EventEmitter<StorageEvent> for StorageReportView {}
+
+impl StorageReportView {
+    pub fn new(library: Model<Library>, cx: &mut ViewContext<Self>) -> Self {
+        let subscriptions = vec![cx.observe(&library, |this, library, cx| {
+            this.report = library.read(cx).storage_report();
+            cx.notify();
+        })];
+
+        StorageReportView {
+            report: library.read(cx).storage_report(),
+            library,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn render_heading(title: &str) -> Div {
+        div()
+            .pt(px(14.))
+            .pb(px(4.))
+            .font_weight(FontWeight::BOLD)
+            .text_size(px(12.))
+            .child(title.to_string())
+    }
+
+    fn render_shares(&self, title: &str, shares: &[Share]) -> Div {
+        let mut shown: Vec<(String, u64)> = shares
+            .iter()
+            .take(SHARES_SHOWN)
+            .map(|share| (share.label.clone(), share.size))
+            .collect();
+        let other: u64 = shares
+            .iter()
+            .skip(SHARES_SHOWN)
+            .map(|share| share.size)
+            .sum();
+        if other > 0 {
+            shown.push(("Other".to_string(), other));
+        }
+        let total = self.report.size.max(1) as f32;
+
+        v_stack()
+            .child(Self::render_heading(title))
+            .children(shown.into_iter().map(|(label, size)| {
+                h_stack()
+                    .gap(px(8.))
+                    .py(px(1.))
+                    .child(div().w(px(140.)).overflow_hidden().child(label))
+                    .child(
+                        div()
+                            .w(px(BAR_WIDTH))
+                            .h(px(8.))
+                            .rounded(px(4.))
+                            .bg(rgb(0xE2E6EC))
+                            .child(
+                                div()
+                                    .h_full()
+                                    .rounded(px(4.))
+                                    .w(px(BAR_WIDTH * size as f32 / total))
+                                    .bg(vertical_linear_gradient(rgb(0x7FA6DD), rgb(0x3D80DF))),
+                            ),
+                    )
+                    .child(div().opacity(0.6).child(format_size(size)))
+            }))
+    }
+
+    fn render_biggest_albums(&self, cx: &mut ViewContext<Self>) -> Div {
+        v_stack()
+            .child(Self::render_heading("Biggest Albums"))
+            .children(
+                self.report
+                    .biggest_albums
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, album)| {
+                        let shown = album.clone();
+                        let relocated = album.clone();
+                        h_stack()
+                            .gap(px(8.))
+                            .py(px(1.))
+                            .child(
+                                div()
+                                    .w(px(220.))
+                                    .overflow_hidden()
+                                    .child(format!("{} — {}", album.album, album.artist)),
+                            )
+                            .child(div().w(px(80.)).opacity(0.6).child(format_size(album.size)))
+                            .child(
+                                div()
+                                    .id(("show-album", ix))
+                                    .cursor_pointer()
+                                    .text_color(rgb(0x3D80DF))
+                                    .child("Show in Library")
+                                    .on_click(cx.listener(move |_, _, cx| {
+                                        cx.emit(StorageEvent::ShowAlbum(shown.clone()))
+                                    })),
+                            )
+                            .child(
+                                div()
+                                    .id(("relocate-album", ix))
+                                    .cursor_pointer()
+                                    .text_color(rgb(0x3D80DF))
+                                    .child("Move To…")
+                                    .on_click(cx.listener(move |_, _, cx| {
+                                        cx.emit(StorageEvent::Relocate(relocated.clone()))
+                                    })),
+                            )
+                    }),
+            )
+    }
+
+    fn render_lossless(&self, cx: &mut ViewContext<Self>) -> Div {
+        let count = self.report.lossless.len();
+        let uncompressed = self.report.uncompressed.len();
+        v_stack()
+            .child(Self::render_heading("Lossless Files"))
+            .child(if count == 0 {
+                div()
+                    .opacity(0.6)
+                    .child("No lossless files in the library.")
+            } else {
+                div().child(format!(
+                    "{} songs take up {}. Transcoding them to 256 kbps AAC would free about {}.",
+                    count,
+                    format_size(self.report.lossless_size),
+                    format_size(self.report.transcode_savings),
+                ))
+            })
+            .when(count > 0, |section| {
+                section.child(
+                    div()
+                        .id("lossless-playlist")
+                        .pt(px(4.))
+                        .cursor_pointer()
+                        .text_color(rgb(0x3D80DF))
+                        .child("Make a Playlist of Them")
+                        .on_click(cx.listener(|this, _, cx| {
+                            cx.emit(StorageEvent::MakePlaylist(
+                                "Lossless Files".to_string(),
+                                this.report.lossless.clone(),
+                            ))
+                        })),
+                )
+            })
+            .when(uncompressed > 0, |section| {
+                section
+                    .child(div().pt(px(8.)).child(format!(
+                        "{} of them are WAV or AIFF files, which would take less room as FLAC.",
+                        uncompressed
+                    )))
+                    .child(
+                        div()
+                            .id("transcode-uncompressed")
+                            .pt(px(4.))
+                            .cursor_pointer()
+                            .text_color(rgb(0x3D80DF))
+                            .child("Transcode Them to FLAC")
+                            .on_click(cx.listener(|this, _, cx| {
+                                cx.emit(StorageEvent::Transcode(this.report.uncompressed.clone()))
+                            })),
+                    )
+            })
+    }
+}
+
+impl Render for StorageReportView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let count = self.library.read(cx).track_order().len();
+
+        div()
+            .id("storage-report")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .child("Storage"),
+            )
+            .child(div().pt(px(4.)).opacity(0.6).child(format!(
+                "{} songs — {}",
+                count,
+                format_size(self.report.size)
+            )))
+            .child(self.render_shares("By Format", &self.report.by_kind))
+            .child(self.render_shares("By Bitrate", &self.report.by_bitrate))
+            .child(self.render_shares("By Folder", &self.report.by_folder))
+            .child(self.render_biggest_albums(cx))
+            .child(self.render_lossless(cx))
+    }
+}
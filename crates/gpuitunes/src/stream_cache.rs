@@ -0,0 +1,156 @@
+use gpui::*;
+use library::format_size;
+use playback::{StreamCache, StreamCacheSettings};
+
+use crate::{element::*, paths, profile::config_dir, settings::Settings};
+
+const MIN_READ_AHEAD_KB: u64 = 256;
+const MAX_READ_AHEAD_KB: u64 = 64 * 1024;
+const CACHE_SIZE_STEP_MB: u64 = 256;
+
+/// Edits how tracks on network drives are copied locally while they play.
+/// Both engines pick the changes up for the next track they open.
+pub struct StreamCacheView;
+
+impl StreamCacheView {
+    fn update_settings(
+        &mut self,
+        cx: &mut ViewContext<Self>,
+        f: impl FnOnce(&mut StreamCacheSettings),
+    ) {
+        cx.update_global::<Settings, _>(|settings, _| f(&mut settings.stream_cache));
+        if let Err(e) = cx.global::<Settings>().save(&config_dir(cx)) {
+            eprintln!("Failed to save settings: {}", e);
+        }
+        cx.notify();
+    }
+
+    // Only for its size and clearing, the engines own the ones they read from
+    fn cache(cx: &ViewContext<Self>) -> StreamCache {
+        StreamCache::new(
+            paths::data_dir().join("stream-cache"),
+            cx.global::<Settings>().stream_cache.clone(),
+        )
+    }
+
+    fn render_row(label: &str) -> Div {
+        h_stack().gap(px(8.)).child(
+            div()
+                .w(px(140.))
+                .flex()
+                .justify_end()
+                .text_color(rgb(0x606060))
+                .child(label.to_string()),
+        )
+    }
+
+    fn render_toggle(
+        &self,
+        id: &'static str,
+        label: &str,
+        on: bool,
+        f: fn(&mut StreamCacheSettings),
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        Self::render_row(label).child(
+            small_button(id, if on { "On" } else { "Off" })
+                .on_click(cx.listener(move |this, _, cx| this.update_settings(cx, f))),
+        )
+    }
+}
+
+impl Render for StreamCacheView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let settings = cx.global::<Settings>().stream_cache.clone();
+        let size = Self::cache(cx).size();
+
+        v_stack()
+            .id("stream-cache")
+            .size_full()
+            .overflow_y_scroll()
+            .p(px(12.))
+            .gap(px(4.))
+            .bg(rgb(0xF0F0F0))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(13.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Network Stream Cache"),
+            )
+            .child(self.render_toggle(
+                "toggle-enabled",
+                "Cache network tracks",
+                settings.enabled,
+                |settings| settings.enabled = !settings.enabled,
+                cx,
+            ))
+            .child(self.render_toggle(
+                "toggle-whole-track",
+                "Fetch whole track",
+                settings.cache_whole_track,
+                |settings| settings.cache_whole_track = !settings.cache_whole_track,
+                cx,
+            ))
+            .child(
+                Self::render_row("Read ahead")
+                    .child(small_button("lower-read-ahead", "−").on_click(cx.listener(
+                        |this, _, cx| {
+                            this.update_settings(cx, |settings| {
+                                settings.read_ahead_kb =
+                                    (settings.read_ahead_kb / 2).max(MIN_READ_AHEAD_KB)
+                            })
+                        },
+                    )))
+                    .child(
+                        div()
+                            .w(px(60.))
+                            .text_center()
+                            .child(format_size(settings.read_ahead_kb * 1024)),
+                    )
+                    .child(small_button("raise-read-ahead", "+").on_click(cx.listener(
+                        |this, _, cx| {
+                            this.update_settings(cx, |settings| {
+                                settings.read_ahead_kb =
+                                    (settings.read_ahead_kb * 2).min(MAX_READ_AHEAD_KB)
+                            })
+                        },
+                    ))),
+            )
+            .child(
+                Self::render_row("Largest size")
+                    .child(small_button("lower-cache-size", "−").on_click(cx.listener(
+                        |this, _, cx| {
+                            this.update_settings(cx, |settings| {
+                                settings.max_cache_size_mb = settings
+                                    .max_cache_size_mb
+                                    .saturating_sub(CACHE_SIZE_STEP_MB)
+                                    .max(CACHE_SIZE_STEP_MB)
+                            })
+                        },
+                    )))
+                    .child(
+                        div()
+                            .w(px(60.))
+                            .text_center()
+                            .child(format_size(settings.max_cache_size_mb * 1024 * 1024)),
+                    )
+                    .child(small_button("raise-cache-size", "+").on_click(cx.listener(
+                        |this, _, cx| {
+                            this.update_settings(cx, |settings| {
+                                settings.max_cache_size_mb += CACHE_SIZE_STEP_MB
+                            })
+                        },
+                    ))),
+            )
+            .child(Self::render_row("In use").child(format_size(size)).child(
+                small_button("clear-stream-cache", "Clear").on_click(cx.listener(|_, _, cx| {
+                    if let Err(e) = Self::cache(cx).clear() {
+                        eprintln!("Failed to clear stream cache: {}", e);
+                    }
+                    cx.notify();
+                })),
+            ))
+    }
+}
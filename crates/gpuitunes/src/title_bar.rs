@@ -1,7 +1,12 @@
-use crate::{assets::Icon, AppState};
-use crate::{element::*, FullScreen, Minimize, Quit};
-use gpui::*;
+use crate::platform::{render_window_controls, window_drag_area, WindowChrome};
+use crate::volume::{Volume, VolumeSlider};
+use crate::{assets::Icon, search::SearchField, AppState};
+use crate::{element::*, FullScreen, Minimize, Quit, TogglePrivateListening};
+use gpui::{prelude::FluentBuilder as _, *};
+use playback::DynamicsSettings;
+use serde::Deserialize;
 use smallvec::smallvec;
+use std::rc::Rc;
 
 // TODO: Move to playback
 actions!(
@@ -14,10 +19,30 @@ actions!(
         Pause,
         Restart,
         VolumeIncrease,
-        VolumeDecrease
+        VolumeDecrease,
+        ToggleMute,
+        ToggleVolumeOptions,
+        HideVolumeOptions,
+        ToggleLimiter,
+        ToggleNightMode,
+        BalanceLeft,
+        BalanceRight,
+        ResetBalance
     ]
 );
 
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct SetVolume {
+    pub level: f32,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct SetBalance {
+    pub balance: f32,
+}
+
+impl_actions!(playback, [SetVolume, SetBalance]);
+
 #[derive(Clone, Copy, Debug)]
 enum WindowButtonType {
     Close,
@@ -104,10 +129,29 @@ impl RenderOnce for TrafficLight {
 
 pub struct TitleBar {
     state: Model<AppState>,
+    search: View<SearchField>,
+    private_listening: bool,
+    is_playing: bool,
+    volume: Volume,
+    volume_slider: Rc<VolumeSlider>,
+    balance_slider: Rc<VolumeSlider>,
+    dynamics: DynamicsSettings,
+    show_volume_options: bool,
 }
 
 impl TitleBar {
-    pub fn new(state: Model<AppState>, _cx: &mut ViewContext<Self>) -> Self {
+    pub fn new(
+        state: Model<AppState>,
+        search: View<SearchField>,
+        private_listening: bool,
+        is_playing: bool,
+        volume: Volume,
+        volume_slider: Rc<VolumeSlider>,
+        balance_slider: Rc<VolumeSlider>,
+        dynamics: DynamicsSettings,
+        show_volume_options: bool,
+        _cx: &mut ViewContext<Self>,
+    ) -> Self {
         // cx.subscribe(
         //     &state,
         //     |_this, _model, _event: &CurrentTimeChangedEvent, cx| {
@@ -118,6 +162,14 @@ impl TitleBar {
 
         TitleBar {
             state: state.clone(),
+            search,
+            private_listening,
+            is_playing,
+            volume,
+            volume_slider,
+            balance_slider,
+            dynamics,
+            show_volume_options,
         }
     }
 }
@@ -140,14 +192,15 @@ impl TitleBar {
 
     fn render_playback_button(
         &self,
+        id: &'static str,
         size: impl Into<Pixels>,
         icon: Icon,
-        cx: &mut ViewContext<Self>,
+        action: impl Action,
     ) -> impl IntoElement {
         let size = size.into();
 
         div()
-            .id("some-playback-button")
+            .id(id)
             .relative()
             .flex_none()
             .w(size)
@@ -170,35 +223,58 @@ impl TitleBar {
                     })),
             )
             .active(|this| this.opacity(0.8))
-            .on_click(cx.listener(move |_, event, cx| {
-                println!("{:?}", event);
-                cx.notify();
-            }))
+            .on_click(move |_, cx| cx.dispatch_action(action.boxed_clone()))
     }
 
-    fn render_playback_buttons(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+    fn render_playback_buttons(&self) -> impl IntoElement {
         h_stack()
             .top(px(5.))
             .gap(px(4.))
             .items_center()
-            .child(self.render_playback_button(px(31.), Icon::Previous, cx))
-            .child(self.render_playback_button(px(37.), Icon::Pause, cx))
-            .child(self.render_playback_button(px(31.), Icon::Next, cx))
+            .child(self.render_playback_button("skip-prev", px(31.), Icon::Previous, SkipPrev))
+            .child(self.render_playback_button(
+                "toggle-playback",
+                px(37.),
+                if self.is_playing {
+                    Icon::Pause
+                } else {
+                    Icon::Play
+                },
+                TogglePlayback,
+            ))
+            .child(self.render_playback_button("skip-next", px(31.), Icon::Next, SkipNext))
     }
 
+    // Pressing anywhere along the track jumps the thumb there and starts a
+    // drag, which the window follows until the button comes up
     fn render_volume_controls(&self) -> impl IntoElement {
-        let current_volume: f32 = 0.7;
         let width: f32 = 75.0;
         let thumb_width: f32 = 12.0;
-        let thumb_position = current_volume * width - (thumb_width / 2.0);
+        let thumb_position = self.volume.level * width - (thumb_width / 2.0);
+        let slider = self.volume_slider.clone();
 
         h_stack()
             .ml(px(10.))
             .gap_1()
-            .child(small_icon(Icon::VolumeLow))
+            .child(
+                div()
+                    .id("toggle-mute")
+                    .when(self.volume.muted, |icon| icon.opacity(0.4))
+                    .on_click(|_, cx| cx.dispatch_action(Box::new(ToggleMute)))
+                    .child(small_icon(Icon::VolumeLow)),
+            )
             .child(
                 h_stack()
+                    .id("volume-slider")
                     .relative()
+                    .h(px(thumb_width))
+                    .when(self.volume.muted, |slider| slider.opacity(0.5))
+                    .on_mouse_down(MouseButton::Left, move |event, cx| {
+                        slider.set_dragging(true);
+                        let level = slider.level_at(event.position);
+                        cx.dispatch_action(Box::new(SetVolume { level }));
+                        cx.stop_propagation();
+                    })
                     .child(
                         div()
                             .w(px(75.))
@@ -206,7 +282,12 @@ impl TitleBar {
                             .rounded_full()
                             .border_1()
                             .border_color(rgb(0x444444))
-                            .bg(vertical_linear_gradient(rgb(0x666666), rgb(0x838383))),
+                            .bg(vertical_linear_gradient(rgb(0x666666), rgb(0x838383)))
+                            .child({
+                                let slider = self.volume_slider.clone();
+                                canvas(move |bounds, _| slider.set_track(bounds), |_, _, _| {})
+                                    .size_full()
+                            }),
                     )
                     .child(
                         circle(px(thumb_width))
@@ -224,7 +305,145 @@ impl TitleBar {
                             ),
                     ),
             )
-            .child(small_icon(Icon::VolumeHigh))
+            .child(
+                div()
+                    .id("volume-options")
+                    .relative()
+                    .when(!self.show_volume_options, |icon| {
+                        icon.on_click(|_, cx| cx.dispatch_action(Box::new(ToggleVolumeOptions)))
+                    })
+                    .child(small_icon(Icon::VolumeHigh))
+                    .when(self.show_volume_options, |icon| {
+                        icon.child(self.render_volume_options())
+                    }),
+            )
+    }
+
+    // Dropped down from the loud end of the volume slider
+    fn render_volume_options(&self) -> impl IntoElement {
+        let option = |id: &'static str, label: &'static str, on: bool, action: Box<dyn Action>| {
+            context_menu_item(
+                id,
+                if on {
+                    format!("✓ {}", label)
+                } else {
+                    label.to_string()
+                },
+            )
+            .on_click(move |_, cx| cx.dispatch_action(action.boxed_clone()))
+        };
+
+        deferred(
+            anchored().child(
+                v_stack()
+                    .occlude()
+                    .mt(px(16.))
+                    .py(px(4.))
+                    .min_w(px(140.))
+                    .rounded(px(4.))
+                    .border_1()
+                    .border_color(rgb(0xA0A0A0))
+                    .bg(rgb(0xFFFFFF))
+                    .shadow_lg()
+                    .text_size(px(13.))
+                    .on_mouse_down_out(|_, cx| cx.dispatch_action(Box::new(HideVolumeOptions)))
+                    .child(self.render_balance())
+                    .child(option(
+                        "toggle-limiter",
+                        "Limiter",
+                        self.dynamics.limiter,
+                        Box::new(ToggleLimiter),
+                    ))
+                    .child(option(
+                        "toggle-night-mode",
+                        "Night Mode",
+                        self.dynamics.night_mode,
+                        Box::new(ToggleNightMode),
+                    )),
+            ),
+        )
+    }
+
+    // Centered in the track, L to R. It drags like the volume slider, and a
+    // double click puts it back in the middle
+    fn render_balance(&self) -> impl IntoElement {
+        let width: f32 = 100.0;
+        let thumb_width: f32 = 10.0;
+        let thumb_position = (self.volume.balance + 1.) / 2. * width - (thumb_width / 2.0);
+        let slider = self.balance_slider.clone();
+
+        v_stack()
+            .px(px(12.))
+            .pt(px(2.))
+            .pb(px(6.))
+            .gap(px(2.))
+            .child(
+                div()
+                    .text_size(px(11.))
+                    .opacity(0.6)
+                    .child(match self.volume.balance {
+                        balance if balance < 0. => format!("Balance: {:.0}% L", -balance * 100.),
+                        balance if balance > 0. => format!("Balance: {:.0}% R", balance * 100.),
+                        _ => "Balance: Center".to_string(),
+                    }),
+            )
+            .child(
+                h_stack()
+                    .gap(px(6.))
+                    .text_size(px(10.))
+                    .child("L")
+                    .child(
+                        h_stack()
+                            .id("balance-slider")
+                            .relative()
+                            .h(px(thumb_width))
+                            .on_mouse_down(MouseButton::Left, move |event, cx| {
+                                if event.click_count >= 2 {
+                                    slider.set_dragging(false);
+                                    cx.dispatch_action(Box::new(ResetBalance));
+                                } else {
+                                    slider.set_dragging(true);
+                                    let balance = slider.level_at(event.position) * 2. - 1.;
+                                    cx.dispatch_action(Box::new(SetBalance { balance }));
+                                }
+                                cx.stop_propagation();
+                            })
+                            .child(
+                                div()
+                                    .w(px(width))
+                                    .h(px(4.))
+                                    .rounded_full()
+                                    .border_1()
+                                    .border_color(rgb(0x999999))
+                                    .bg(vertical_linear_gradient(rgb(0xBBBBBB), rgb(0xD6D6D6)))
+                                    .child({
+                                        let slider = self.balance_slider.clone();
+                                        canvas(
+                                            move |bounds, _| slider.set_track(bounds),
+                                            |_, _, _| {},
+                                        )
+                                        .size_full()
+                                    }),
+                            )
+                            .child(
+                                div()
+                                    .absolute()
+                                    .left(px(width / 2.))
+                                    .w(px(1.))
+                                    .h_full()
+                                    .bg(rgb(0x999999)),
+                            )
+                            .child(
+                                circle(px(thumb_width))
+                                    .absolute()
+                                    .left(px(thumb_position))
+                                    .bg(rgb(0xFEFEFE))
+                                    .border_1()
+                                    .border_color(rgb(0x7C7C7C)),
+                            ),
+                    )
+                    .child("R"),
+            )
     }
 
     fn render_now_playing(&self, _cx: &ViewContext<Self>) -> impl IntoElement {
@@ -332,36 +551,34 @@ impl TitleBar {
         div()
     }
 
-    fn render_search(&self) -> impl IntoElement {
-        let input_width: f32 = 134.;
-        let input_height: f32 = 20.;
-
+    // Stays in view the whole time so nobody forgets their plays aren't counting
+    fn render_private_listening(&self) -> impl IntoElement {
+        // Kept clear of the window controls, whichever side they're on
+        let chrome = WindowChrome::current();
         h_stack()
-            .mr(px(20.))
-            .flex_none()
+            .id("private-listening")
+            .absolute()
+            .top(px(3.))
+            .when(chrome.has_controls_on_left(), |pill| pill.right(px(8.)))
+            .when(!chrome.has_controls_on_left(), |pill| pill.left(px(8.)))
+            .occlude()
+            .h(px(15.))
+            .px(px(7.))
+            .gap(px(4.))
             .rounded_full()
-            .w(px(input_width))
-            .h(px(input_height))
-            .bg(vertical_linear_gradient(rgb(0xC5C5C5), rgb(0x969696)))
-            .child(
-                h_stack()
-                    .flex_none()
-                    .rounded_full()
-                    .gap(px(4.))
-                    .px(px(3.))
-                    .w(px(input_width - 2.))
-                    .h(px(input_height - 2.))
-                    .bg(rgb(0xFFFFFF))
-                    .child(small_icon(Icon::MagnifyingGlass))
-                    .child(
-                        h_stack()
-                            .flex_1()
-                            .text_size(px(11.))
-                            .line_height(px(11.))
-                            .child("Search..."),
-                    )
-                    .child(small_icon(Icon::XCircle).text_color(rgb(0xB3B3B3))),
-            )
+            .bg(vertical_linear_gradient(rgb(0x5E5E5E), rgb(0x414141)))
+            .border_1()
+            .border_color(rgb(0x2E2E2E))
+            .text_size(px(10.))
+            .text_color(rgb(0xFFFFFF))
+            .cursor_pointer()
+            .child(small_icon(Icon::Eye).text_color(rgb(0xFFFFFF)))
+            .child("Private Listening")
+            .on_click(|_, cx| cx.dispatch_action(Box::new(TogglePrivateListening)))
+    }
+
+    fn render_search(&self) -> impl IntoElement {
+        self.search.clone()
     }
 
     fn render_browse(&self) -> impl IntoElement {
@@ -452,8 +669,11 @@ impl Render for TitleBar {
             .border_b_1()
             .border_color(rgb(0x414141))
             // TODO: Should be able to drag the app from the whole title bar
-            .child(self.render_traffic_lights(cx))
-            .child(
+            .when(
+                WindowChrome::current().has_controls_on_left(),
+                |title_bar| title_bar.child(self.render_traffic_lights(cx)),
+            )
+            .child(window_drag_area(
                 h_stack()
                     .id("title-bar")
                     .h(px(21.))
@@ -473,8 +693,12 @@ impl Render for TitleBar {
                             .child(div().child("iTunes")),
                     )
                     .child(div().flex_1())
-                    .justify_between(),
-            )
+                    .justify_between()
+                    .when(self.private_listening, |title| {
+                        title.child(self.render_private_listening())
+                    })
+                    .children(render_window_controls(cx)),
+            ))
             .child(
                 div()
                     .flex()
@@ -486,7 +710,7 @@ impl Render for TitleBar {
                             .flex_none()
                             .justify_start()
                             .child(spacer().width(px(28.)))
-                            .child(self.render_playback_buttons(cx))
+                            .child(self.render_playback_buttons())
                             .child(self.render_volume_controls()),
                     )
                     .child(
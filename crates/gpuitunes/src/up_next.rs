@@ -0,0 +1,379 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{format_playback_time, Library, LibraryCommand, NowPlaying, Track};
+
+use crate::{
+    element::*, platform::FileDragSource, ClearUpNext, MoveQueuedDown, MoveQueuedUp, RemoveQueued,
+    ShowArtwork,
+};
+
+const ARTWORK_SIZE: f32 = 40.;
+
+pub struct UpNextPanel {
+    library: Model<Library>,
+    now_playing: Model<NowPlaying>,
+    focus_handle: FocusHandle,
+    // Positions in the queue, kept in step as entries move
+    selection: Vec<usize>,
+    // The last change, spelled out for screen readers and anyone not watching the list
+    announcement: Option<SharedString>,
+    artwork_drag: FileDragSource,
+    _subscriptions: Vec<Subscription>,
+}
+
+#[derive(Clone)]
+struct DraggedQueueEntry {
+    index: usize,
+    title: SharedString,
+}
+
+impl Render for DraggedQueueEntry {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        div()
+            .px(px(6.))
+            .py(px(1.))
+            .rounded(px(3.))
+            .bg(rgb(0x3D80DF))
+            .opacity(0.8)
+            .text_color(rgb(0xFFFFFF))
+            .text_size(px(12.))
+            .child(self.title.clone())
+    }
+}
+
+impl UpNextPanel {
+    pub fn new(
+        library: Model<Library>,
+        now_playing: Model<NowPlaying>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let subscriptions = vec![cx.observe(&now_playing, |this, now_playing, cx| {
+            let len = now_playing.read(cx).up_next().len();
+            this.selection.retain(|ix| *ix < len);
+            cx.notify();
+        })];
+
+        UpNextPanel {
+            library,
+            now_playing,
+            focus_handle: cx.focus_handle(),
+            selection: Vec::new(),
+            announcement: None,
+            artwork_drag: FileDragSource::default(),
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn announce(&mut self, announcement: impl Into<SharedString>, cx: &mut ViewContext<Self>) {
+        self.announcement = Some(announcement.into());
+        cx.notify();
+    }
+
+    fn select(&mut self, index: usize, event: &MouseDownEvent, cx: &mut ViewContext<Self>) {
+        if event.modifiers.platform {
+            if let Some(position) = self.selection.iter().position(|ix| *ix == index) {
+                self.selection.remove(position);
+            } else {
+                self.selection.push(index);
+            }
+        } else {
+            self.selection = vec![index];
+        }
+        cx.focus(&self.focus_handle);
+        cx.notify();
+    }
+
+    fn selection_label(&self, cx: &AppContext) -> String {
+        match self.selection.as_slice() {
+            [index] => self
+                .now_playing
+                .read(cx)
+                .up_next()
+                .get(*index)
+                .map(|track| format!("“{}”", track.title()))
+                .unwrap_or_default(),
+            selection => format!("{} songs", selection.len()),
+        }
+    }
+
+    fn move_up(&mut self, _: &MoveQueuedUp, cx: &mut ViewContext<Self>) {
+        let label = self.selection_label(cx);
+        let moved = self.now_playing.update(cx, |now_playing, cx| {
+            let moved = now_playing.move_queued_up(&self.selection);
+            cx.notify();
+            moved
+        });
+        self.announce_move(label, moved, cx);
+    }
+
+    fn move_down(&mut self, _: &MoveQueuedDown, cx: &mut ViewContext<Self>) {
+        let label = self.selection_label(cx);
+        let moved = self.now_playing.update(cx, |now_playing, cx| {
+            let moved = now_playing.move_queued_down(&self.selection);
+            cx.notify();
+            moved
+        });
+        self.announce_move(label, moved, cx);
+    }
+
+    fn announce_move(
+        &mut self,
+        label: String,
+        moved: Option<Vec<usize>>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(moved) = moved else {
+            return;
+        };
+        let position = moved.first().map(|ix| ix + 1).unwrap_or_default();
+        self.selection = moved;
+        self.announce(format!("Moved {} to position {}", label, position), cx);
+    }
+
+    fn move_entry(&mut self, from: usize, to: usize, cx: &mut ViewContext<Self>) {
+        if from == to {
+            return;
+        }
+        let title = self.now_playing.update(cx, |now_playing, cx| {
+            let title = now_playing.up_next().get(from).map(|track| track.title());
+            now_playing.move_queued(from, to);
+            cx.notify();
+            title
+        });
+        self.selection = vec![to];
+        if let Some(title) = title {
+            self.announce(format!("Moved “{}” to position {}", title, to + 1), cx);
+        }
+    }
+
+    fn remove(&mut self, _: &RemoveQueued, cx: &mut ViewContext<Self>) {
+        if self.selection.is_empty() {
+            return;
+        }
+        let label = self.selection_label(cx);
+        let selection = std::mem::take(&mut self.selection);
+        self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.remove_queued(&selection);
+            cx.notify();
+        });
+        self.announce(format!("Removed {} from Up Next", label), cx);
+    }
+
+    fn pin(&mut self, index: usize, cx: &mut ViewContext<Self>) {
+        let title = self.now_playing.update(cx, |now_playing, cx| {
+            now_playing.pin_queued(&[index]);
+            cx.notify();
+            now_playing.up_next().get(index).map(|track| track.title())
+        });
+        if let Some(title) = title {
+            self.announce(format!("Kept “{}” in Up Next", title), cx);
+        }
+    }
+
+    pub fn clear(&mut self, _: &ClearUpNext, cx: &mut ViewContext<Self>) {
+        let cleared = self.now_playing.update(cx, |now_playing, cx| {
+            let cleared = now_playing.up_next().to_vec();
+            now_playing.clear_queue();
+            cx.notify();
+            cleared
+        });
+        self.selection.clear();
+        if !cleared.is_empty() {
+            self.announce(format!("Cleared {} songs from Up Next", cleared.len()), cx);
+            self.library.update(cx, |library, _| {
+                library.record_undo(LibraryCommand::ClearQueue(cleared))
+            });
+        }
+    }
+
+    fn render_entry(
+        &self,
+        index: usize,
+        track: &Track,
+        auto_added: bool,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        let selected = self.selection.contains(&index);
+        let dragged = DraggedQueueEntry {
+            index,
+            title: track.title(),
+        };
+
+        h_stack()
+            .id(("up-next", index))
+            .h(px(32.))
+            .px(px(8.))
+            .gap(px(6.))
+            .text_size(px(11.))
+            .when(index % 2 != 0, |row| row.bg(rgb(0xF0F0F0)))
+            .when(auto_added, |row| row.text_color(rgb(0x707070)))
+            .when(selected, |row| {
+                row.bg(rgb(0x3D80DF)).text_color(rgb(0xFFFFFF))
+            })
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, event: &MouseDownEvent, cx| this.select(index, event, cx)),
+            )
+            .on_drag(dragged, |dragged, _, cx| cx.new_view(|_| dragged.clone()))
+            .drag_over::<DraggedQueueEntry>(|style, _, _| style.bg(rgb(0xC5D9F5)))
+            .on_drop(cx.listener(move |this, dragged: &DraggedQueueEntry, cx| {
+                this.move_entry(dragged.index, index, cx)
+            }))
+            .child(
+                v_stack()
+                    .flex_1()
+                    .overflow_hidden()
+                    .child(div().font_weight(FontWeight::MEDIUM).child(track.title()))
+                    .child(div().child(track.artist())),
+            )
+            .when(auto_added, |row| {
+                row.child(
+                    div()
+                        .id(("pin-up-next", index))
+                        .flex_none()
+                        .text_color(if selected {
+                            rgb(0xFFFFFF)
+                        } else {
+                            rgb(0x3D80DF)
+                        })
+                        .child("Pin")
+                        .on_click(cx.listener(move |this, _, cx| this.pin(index, cx))),
+                )
+            })
+            .child(
+                div()
+                    .flex_none()
+                    .child(format_playback_time(track.duration())),
+            )
+    }
+}
+
+impl Render for UpNextPanel {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let now_playing = self.now_playing.read(cx);
+        let current = now_playing.current_track().map(|current| {
+            let artwork = current.track().artwork().map(|path| path.to_path_buf());
+            (current.title(), current.artist(), artwork)
+        });
+        let up_next: Vec<(Track, bool)> = now_playing
+            .up_next()
+            .iter()
+            .map(|track| (track.clone(), now_playing.is_auto_added(track.id())))
+            .collect();
+        // Autoplay's picks always come after everything queued by hand
+        let first_auto_added = up_next.iter().position(|(_, auto_added)| *auto_added);
+
+        v_stack()
+            .id("up-next-panel")
+            .key_context("UpNext")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::move_up))
+            .on_action(cx.listener(Self::move_down))
+            .on_action(cx.listener(Self::remove))
+            .on_action(cx.listener(Self::clear))
+            .flex_none()
+            .w(px(240.))
+            .h_full()
+            .border_l_1()
+            .border_color(rgb(0x969696))
+            .child(
+                h_stack()
+                    .flex_none()
+                    .h(px(24.))
+                    .px(px(8.))
+                    .justify_between()
+                    .border_b_1()
+                    .border_color(rgb(0xC0C0C0))
+                    .bg(rgb(0xF0F0F0))
+                    .text_size(px(11.))
+                    .child(div().font_weight(FontWeight::BOLD).child("Up Next"))
+                    .when(!up_next.is_empty(), |header| {
+                        header.child(
+                            div()
+                                .id("clear-up-next")
+                                .text_color(rgb(0x3D80DF))
+                                .child("Clear")
+                                .on_click(cx.listener(|this, _, cx| this.clear(&ClearUpNext, cx))),
+                        )
+                    }),
+            )
+            .when_some(current, |panel, (title, artist, artwork)| {
+                panel.child(
+                    h_stack()
+                        .flex_none()
+                        .gap(px(8.))
+                        .px(px(8.))
+                        .py(px(4.))
+                        .text_size(px(11.))
+                        .border_b_1()
+                        .border_color(rgb(0xC0C0C0))
+                        // Dragged out as a file, or clicked to see it whole
+                        .children(artwork.map(|artwork| {
+                            self.artwork_drag.attach(
+                                div()
+                                    .id("now-playing-artwork")
+                                    .flex_none()
+                                    .size(px(ARTWORK_SIZE))
+                                    .rounded(px(3.))
+                                    .overflow_hidden()
+                                    .cursor_pointer()
+                                    .child(
+                                        img(artwork.clone())
+                                            .size_full()
+                                            .object_fit(ObjectFit::Cover),
+                                    )
+                                    .on_click(|_, cx| cx.dispatch_action(Box::new(ShowArtwork))),
+                                artwork,
+                            )
+                        }))
+                        .child(
+                            v_stack()
+                                .overflow_hidden()
+                                .child(div().text_color(rgb(0x808080)).child("Now Playing"))
+                                .child(div().font_weight(FontWeight::MEDIUM).child(title))
+                                .child(div().child(artist)),
+                        ),
+                )
+            })
+            .child(
+                v_stack()
+                    .id("up-next-entries")
+                    .flex_1()
+                    .overflow_y_scroll()
+                    .children(up_next.iter().enumerate().flat_map(
+                        |(index, (track, auto_added))| {
+                            let label = (Some(index) == first_auto_added).then(|| {
+                                div()
+                                    .px(px(8.))
+                                    .pt(px(6.))
+                                    .pb(px(2.))
+                                    .text_size(px(10.))
+                                    .text_color(rgb(0x808080))
+                                    .child("Autoplay")
+                                    .into_any_element()
+                            });
+                            label.into_iter().chain(Some(
+                                self.render_entry(index, track, *auto_added, cx)
+                                    .into_any_element(),
+                            ))
+                        },
+                    )),
+            )
+            .children(self.announcement.clone().map(|announcement| {
+                div()
+                    .flex_none()
+                    .px(px(8.))
+                    .py(px(2.))
+                    .border_t_1()
+                    .border_color(rgb(0xC0C0C0))
+                    .text_size(px(10.))
+                    .text_color(rgb(0x808080))
+                    .child(announcement)
+            }))
+    }
+}
+
+impl FocusableView for UpNextPanel {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
@@ -0,0 +1,221 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{FileCheck, FileProblem, Library, TrackId};
+use std::path::PathBuf;
+
+use crate::{element::*, progress::JobProgress};
+
+// Files read between each update, so problems show up as they're found
+const VERIFY_BATCH: usize = 20;
+
+struct FileIssue {
+    track: TrackId,
+    title: SharedString,
+    artist: SharedString,
+    path: PathBuf,
+    problem: FileProblem,
+    // What the file hashes to now, when it could still be read
+    current: Option<String>,
+}
+
+/// Reads every file in the library again and compares it with the checksum
+/// taken at import, listing the ones that have gone bad so they can be
+/// ripped again or restored from a backup. Files without a checksum get one.
+pub struct VerifyFilesView {
+    library: Model<Library>,
+    progress: JobProgress,
+    checked: usize,
+    total: usize,
+    // Files that had no checksum before this run
+    recorded: usize,
+    issues: Vec<FileIssue>,
+    _verify: Task<()>,
+}
+
+impl VerifyFilesView {
+    pub fn new(
+        library: Model<Library>,
+        checks: Vec<FileCheck>,
+        progress: JobProgress,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let total = checks.len();
+        let batches: Vec<Vec<FileCheck>> = checks
+            .chunks(VERIFY_BATCH)
+            .map(|batch| batch.to_vec())
+            .collect();
+        let verify = cx.spawn({
+            let progress = progress.clone();
+            |this, mut cx| async move {
+                for batch in batches {
+                    let progress = progress.clone();
+                    let results = cx
+                        .background_executor()
+                        .spawn(async move {
+                            batch
+                                .into_iter()
+                                .map(|check| {
+                                    let result = check.run();
+                                    progress.advance();
+                                    (check, result)
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .await;
+                    if this
+                        .update(&mut cx, |this, cx| this.record(results, cx))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        });
+
+        VerifyFilesView {
+            library,
+            progress,
+            checked: 0,
+            total,
+            recorded: 0,
+            issues: Vec::new(),
+            _verify: verify,
+        }
+    }
+
+    fn record(
+        &mut self,
+        results: Vec<(FileCheck, Result<String, (FileProblem, Option<String>)>)>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.checked += results.len();
+        let library = self.library.read(cx);
+        let mut checksums = Vec::new();
+        for (check, result) in results {
+            match result {
+                Ok(checksum) => {
+                    if check.expected.is_none() {
+                        checksums.push((check.track, checksum));
+                    }
+                }
+                Err((problem, current)) => {
+                    let Some(track) = library.track(&check.track) else {
+                        continue;
+                    };
+                    self.issues.push(FileIssue {
+                        track: check.track,
+                        title: track.title(),
+                        artist: track.artist(),
+                        path: check.path,
+                        problem,
+                        current,
+                    });
+                }
+            }
+        }
+
+        if !checksums.is_empty() {
+            self.recorded += checksums.len();
+            self.library.update(cx, |library, cx| {
+                library.set_checksums(checksums);
+                cx.notify();
+            });
+        }
+        cx.notify();
+    }
+
+    // The file was replaced on purpose, its checksum is taken as it is now
+    fn keep_file(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        if ix >= self.issues.len() {
+            return;
+        }
+        let issue = self.issues.remove(ix);
+        if let Some(checksum) = issue.current {
+            self.library.update(cx, |library, cx| {
+                library.set_checksums(vec![(issue.track, checksum)]);
+                cx.notify();
+            });
+        }
+        cx.notify();
+    }
+
+    fn render_issue(
+        &self,
+        ix: usize,
+        issue: &FileIssue,
+        cx: &mut ViewContext<Self>,
+    ) -> impl IntoElement {
+        h_stack()
+            .gap(px(8.))
+            .py(px(4.))
+            .border_b_1()
+            .border_color(rgb(0xD0D0D0))
+            .child(
+                v_stack()
+                    .flex_1()
+                    .overflow_hidden()
+                    .child(format!("{} — {}", issue.title, issue.artist))
+                    .child(div().opacity(0.6).child(issue.path.display().to_string()))
+                    .child(
+                        div()
+                            .text_color(rgb(0xC4312B))
+                            .child(issue.problem.describe()),
+                    ),
+            )
+            .when(issue.current.is_some(), |row| {
+                row.child(
+                    small_button(("keep-file", ix), "Keep This File")
+                        .on_click(cx.listener(move |this, _, cx| this.keep_file(ix, cx))),
+                )
+            })
+    }
+}
+
+impl Drop for VerifyFilesView {
+    // Closed part way through, the rest of the files are left unchecked
+    fn drop(&mut self) {
+        self.progress.finish();
+    }
+}
+
+impl Render for VerifyFilesView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let status = if self.checked < self.total {
+            format!("Checking {} of {} files…", self.checked, self.total)
+        } else {
+            match self.issues.len() {
+                0 => format!("Checked {} files, all of them are intact.", self.total),
+                1 => format!("Checked {} files, 1 has a problem.", self.total),
+                issues => format!("Checked {} files, {} have problems.", self.total, issues),
+            }
+        };
+
+        v_stack()
+            .id("verify-files")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Verify Library Files"),
+            )
+            .child(div().pb(px(2.)).child(status))
+            .when(self.recorded > 0, |view| {
+                view.child(div().opacity(0.6).pb(px(2.)).child(format!(
+                    "{} files had no checksum yet and were given one to check against next time.",
+                    self.recorded
+                )))
+            })
+            .children(
+                self.issues
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, issue)| self.render_issue(ix, issue, cx)),
+            )
+    }
+}
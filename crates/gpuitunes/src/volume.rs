@@ -0,0 +1,97 @@
+use gpui::{px, Bounds, Pixels, Point};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+// How far the volume keys move the slider
+const VOLUME_STEP: f32 = 0.1;
+// How far the balance keys move it, out of the 1 from center to one side
+const BALANCE_STEP: f32 = 0.1;
+// Close enough to center to snap there, since it's hard to hit by hand
+const BALANCE_DETENT: f32 = 0.04;
+
+/// The output level as the slider and volume keys leave it, kept between
+/// launches.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Volume {
+    // From 0 to 1, along the slider
+    pub level: f32,
+    pub muted: bool,
+    // From -1, left only, to 1, right only
+    pub balance: f32,
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Volume {
+            level: 1.,
+            muted: false,
+            balance: 0.,
+        }
+    }
+}
+
+impl Volume {
+    /// What the engine should play at, 0 while muted.
+    pub fn output_level(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.level
+        }
+    }
+
+    // Moving the slider while muted unmutes, as it does on a Mac
+    pub fn set_level(&mut self, level: f32) {
+        self.level = level.clamp(0., 1.);
+        self.muted = false;
+    }
+
+    pub fn step(&mut self, steps: f32) {
+        self.set_level(self.level + steps * VOLUME_STEP);
+    }
+
+    pub fn set_balance(&mut self, balance: f32) {
+        self.balance = if balance.abs() < BALANCE_DETENT {
+            0.
+        } else {
+            balance.clamp(-1., 1.)
+        };
+    }
+
+    pub fn nudge_balance(&mut self, steps: f32) {
+        // Rounded so nudging back and forth lands on center again
+        let balance = ((self.balance + steps * BALANCE_STEP) / BALANCE_STEP).round();
+        self.set_balance(balance * BALANCE_STEP);
+    }
+}
+
+/// Where the volume or balance slider's track was last drawn, so a press or
+/// drag anywhere along it can be turned into a level.
+#[derive(Default)]
+pub struct VolumeSlider {
+    track: Cell<Bounds<Pixels>>,
+    dragging: Cell<bool>,
+}
+
+impl VolumeSlider {
+    pub fn set_track(&self, bounds: Bounds<Pixels>) {
+        self.track.set(bounds);
+    }
+
+    pub fn level_at(&self, position: Point<Pixels>) -> f32 {
+        let track = self.track.get();
+        if track.size.width <= px(0.) {
+            return 0.;
+        }
+        ((position.x - track.origin.x) / track.size.width).clamp(0., 1.)
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging.get()
+    }
+
+    pub fn set_dragging(&self, dragging: bool) {
+        self.dragging.set(dragging);
+    }
+}
@@ -0,0 +1,186 @@
+// Only macOS has a widget to feed
+#![cfg_attr(not(target_os = "macos"), allow(dead_code))]
+
+use crate::media_controls::MediaCommand;
+
+#[cfg(target_os = "macos")]
+pub use self::macos::WidgetFeed;
+
+// Feeds the Now Playing widget through the app group container it shares
+// with the app. The widget reads `now-playing.json`, and the `artwork` file
+// beside it, and counts the position on from `updated_at` while playing, so
+// the snapshot is only rewritten when something changes. Its buttons drop
+// an empty file into `commands/`, named `<timestamp>-<command>` where the
+// command is one of `toggle`, `play`, `pause`, `next` or `previous`.
+#[cfg(target_os = "macos")]
+mod macos {
+    use super::MediaCommand;
+    use library::{CurrentTrack, TrackId};
+    use serde::Serialize;
+    use std::{
+        env, fs, io,
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    // The widget extension has to be signed into the same group
+    const APP_GROUP: &str = "group.gpuitunes";
+    const SNAPSHOT_FILE: &str = "now-playing.json";
+    const ARTWORK_FILE: &str = "artwork";
+    const COMMANDS_DIR: &str = "commands";
+    // A seek moves the position further than the widget's own counting would
+    const MAX_DRIFT_SECS: i64 = 2;
+
+    #[derive(Serialize)]
+    struct Snapshot {
+        title: String,
+        artist: String,
+        album: String,
+        is_playing: bool,
+        current_time: i32,
+        duration: i32,
+        has_artwork: bool,
+        // Seconds since the Unix epoch
+        updated_at: u64,
+    }
+
+    pub struct WidgetFeed {
+        dir: PathBuf,
+        // What the widget was last told, so it's only updated on changes
+        shown_track: Option<TrackId>,
+        shown_playback: Option<(bool, i32, u64)>,
+    }
+
+    impl WidgetFeed {
+        pub fn start() -> Option<Self> {
+            let dir = PathBuf::from(env::var_os("HOME")?)
+                .join("Library/Group Containers")
+                .join(APP_GROUP);
+            // Only there once the widget extension has been installed
+            if !dir.is_dir() {
+                return None;
+            }
+            fs::create_dir_all(dir.join(COMMANDS_DIR))
+                .map_err(|e| eprintln!("Failed to start the widget feed: {}", e))
+                .ok()?;
+
+            Some(WidgetFeed {
+                dir,
+                shown_track: None,
+                shown_playback: None,
+            })
+        }
+
+        pub fn commands(&mut self) -> impl Iterator<Item = MediaCommand> {
+            let Ok(entries) = fs::read_dir(self.dir.join(COMMANDS_DIR)) else {
+                return Vec::new().into_iter();
+            };
+            let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+            // Oldest first, the timestamps sort the same way
+            paths.sort();
+
+            let commands: Vec<MediaCommand> = paths
+                .into_iter()
+                .filter_map(|path| {
+                    fs::remove_file(&path).ok()?;
+                    let name = path.file_name()?.to_str()?;
+                    match name.rsplit('-').next()? {
+                        "toggle" => Some(MediaCommand::TogglePlayback),
+                        "play" => Some(MediaCommand::Play),
+                        "pause" => Some(MediaCommand::Pause),
+                        "next" => Some(MediaCommand::SkipNext),
+                        "previous" => Some(MediaCommand::SkipPrev),
+                        _ => None,
+                    }
+                })
+                .collect();
+            commands.into_iter()
+        }
+
+        pub fn update(&mut self, current_track: Option<&CurrentTrack>) {
+            let Some(current_track) = current_track else {
+                if self.shown_track.take().is_some() {
+                    self.shown_playback = None;
+                    fs::remove_file(self.dir.join(SNAPSHOT_FILE)).ok();
+                    fs::remove_file(self.dir.join(ARTWORK_FILE)).ok();
+                }
+                return;
+            };
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let track = current_track.track();
+            let is_playing = current_track.is_playing();
+            let current_time = current_track.current_time();
+            let track_changed = self.shown_track.as_ref() != Some(track.id());
+            let playback_changed = match self.shown_playback {
+                Some((was_playing, time, at)) => {
+                    let expected = if was_playing {
+                        time as i64 + now.saturating_sub(at) as i64
+                    } else {
+                        time as i64
+                    };
+                    was_playing != is_playing
+                        || (current_time as i64 - expected).abs() > MAX_DRIFT_SECS
+                }
+                None => true,
+            };
+            if !track_changed && !playback_changed {
+                return;
+            }
+
+            if track_changed {
+                self.shown_track = Some(track.id().clone());
+                let artwork = self.dir.join(ARTWORK_FILE);
+                let copied = track
+                    .artwork()
+                    .is_some_and(|source| fs::copy(source, &artwork).is_ok());
+                if !copied {
+                    fs::remove_file(&artwork).ok();
+                }
+            }
+
+            let snapshot = Snapshot {
+                title: track.title().to_string(),
+                artist: track.artist().to_string(),
+                album: track.album().to_string(),
+                is_playing,
+                current_time,
+                duration: track.duration(),
+                has_artwork: self.dir.join(ARTWORK_FILE).exists(),
+                updated_at: now,
+            };
+            if let Err(e) = write_snapshot(&self.dir, &snapshot) {
+                eprintln!("Failed to update the widget: {}", e);
+            }
+            self.shown_playback = Some((is_playing, current_time, now));
+        }
+    }
+
+    // Written alongside and renamed over, so the widget never reads half a file
+    fn write_snapshot(dir: &Path, snapshot: &Snapshot) -> io::Result<()> {
+        let json = serde_json::to_string(snapshot)?;
+        let partial = dir.join(format!("{}.partial", SNAPSHOT_FILE));
+        fs::write(&partial, json)?;
+        fs::rename(partial, dir.join(SNAPSHOT_FILE))
+    }
+}
+
+// Elsewhere there's no widget to feed
+#[cfg(not(target_os = "macos"))]
+pub struct WidgetFeed;
+
+#[cfg(not(target_os = "macos"))]
+impl WidgetFeed {
+    pub fn start() -> Option<Self> {
+        None
+    }
+
+    pub fn commands(&mut self) -> impl Iterator<Item = MediaCommand> {
+        std::iter::empty()
+    }
+
+    pub fn update(&mut self, _: Option<&library::CurrentTrack>) {}
+}
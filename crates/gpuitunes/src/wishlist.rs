@@ -0,0 +1,179 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use library::{Library, Wish};
+
+use crate::element::*;
+
+/// The albums wanted but not yet in the library. Wishes are typed in here
+/// or added from New Releases, and are marked off once matching files are
+/// imported.
+pub struct WishlistView {
+    library: Model<Library>,
+    focus_handle: FocusHandle,
+    draft: String,
+    error: Option<String>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl WishlistView {
+    pub fn new(library: Model<Library>, cx: &mut ViewContext<Self>) -> Self {
+        let subscriptions = vec![cx.observe(&library, |_, _, cx| cx.notify())];
+
+        WishlistView {
+            library,
+            focus_handle: cx.focus_handle(),
+            draft: String::new(),
+            error: None,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    fn key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.platform || keystroke.modifiers.control {
+            return;
+        }
+        match keystroke.key.as_str() {
+            "enter" => self.add_draft(cx),
+            "backspace" => {
+                self.draft.pop();
+            }
+            "escape" => self.draft.clear(),
+            _ => match &keystroke.key_char {
+                Some(text) if !text.chars().any(char::is_control) => self.draft.push_str(text),
+                _ => return,
+            },
+        }
+        cx.stop_propagation();
+        cx.notify();
+    }
+
+    fn add_draft(&mut self, cx: &mut ViewContext<Self>) {
+        let added = Wish::parse(&self.draft).and_then(|wish| {
+            let description = format!("{} by {}", wish.album, wish.artist);
+            let added = self.library.update(cx, |library, cx| {
+                let added = library.add_wish(wish, None);
+                cx.notify();
+                added
+            });
+            if added {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} is already in the library or on the wishlist",
+                    description
+                ))
+            }
+        });
+        match added {
+            Ok(()) => {
+                self.draft.clear();
+                self.error = None;
+            }
+            Err(error) => self.error = Some(error),
+        }
+        cx.notify();
+    }
+
+    fn remove_wish(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        self.library.update(cx, |library, cx| {
+            library.remove_wish(ix);
+            cx.notify();
+        });
+    }
+
+    fn render_wish(&self, ix: usize, wish: &Wish, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let status = match &wish.fulfilled {
+            Some(day) => format!("In library since {}", day),
+            None => format!("Wanted since {}", wish.added),
+        };
+
+        h_stack()
+            .gap(px(8.))
+            .py(px(4.))
+            .border_b_1()
+            .border_color(rgb(0xD0D0D0))
+            .child(
+                div()
+                    .flex_1()
+                    .overflow_hidden()
+                    .when(wish.is_fulfilled(), |description| description.opacity(0.5))
+                    .child(format!("{} — {}", wish.album, wish.artist)),
+            )
+            .child(div().opacity(0.6).child(status))
+            .child(
+                small_button(("remove-wish", ix), "✕")
+                    .on_click(cx.listener(move |this, _, cx| this.remove_wish(ix, cx))),
+            )
+    }
+}
+
+impl Render for WishlistView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let wishlist = self.library.read(cx).wishlist().to_vec();
+
+        v_stack()
+            .id("wishlist")
+            .flex_1()
+            .h_full()
+            .overflow_y_scroll()
+            .px(px(20.))
+            .py(px(12.))
+            .text_size(px(11.))
+            .child(
+                div()
+                    .text_size(px(16.))
+                    .font_weight(FontWeight::BOLD)
+                    .pb(px(6.))
+                    .child("Wishlist"),
+            )
+            .when(wishlist.is_empty(), |view| {
+                view.child(
+                    div()
+                        .opacity(0.6)
+                        .child("Nothing wished for yet. Add albums below, or from New Releases."),
+                )
+            })
+            .children(
+                wishlist
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, wish)| self.render_wish(ix, wish, cx)),
+            )
+            .child(
+                h_stack()
+                    .id("wish-draft")
+                    .track_focus(&self.focus_handle)
+                    .on_key_down(cx.listener(Self::key_down))
+                    .on_click(cx.listener(|this, _, cx| cx.focus(&this.focus_handle)))
+                    .mt(px(10.))
+                    .px(px(4.))
+                    .h(px(20.))
+                    .border_1()
+                    .border_color(if self.error.is_some() {
+                        rgb(0xC4312B)
+                    } else {
+                        rgb(0x9A9A9A)
+                    })
+                    .bg(rgb(0xFFFFFF))
+                    .overflow_hidden()
+                    .map(|field| {
+                        if self.draft.is_empty() {
+                            field.text_color(rgb(0x808080)).child("Artist - Album")
+                        } else {
+                            field.child(self.draft.clone())
+                        }
+                    }),
+            )
+            .children(
+                self.error
+                    .clone()
+                    .map(|error| div().text_color(rgb(0xC4312B)).child(error)),
+            )
+            .child(
+                h_stack().pt(px(6.)).child(
+                    small_button("add-wish", "Add to Wishlist")
+                        .on_click(cx.listener(|this, _, cx| this.add_draft(cx))),
+                ),
+            )
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{Library, Track, TrackId};
+use gpui::SharedString;
+use std::{collections::HashMap, path::PathBuf};
+
+/// An album as the Albums grid shows it.
+#[derive(Debug, Clone)]
+pub struct Album {
+    pub album: SharedString,
+    // The album artist, so compilations stay together
+    pub artist: SharedString,
+    // In track number order
+    pub tracks: Vec<TrackId>,
+    // The first of its tracks' artwork
+    pub artwork: Option<PathBuf>,
+}
+
+impl Library {
+    /// Every named album, by artist and then title.
+    pub fn albums(&self) -> Vec<Album> {
+        let mut albums: HashMap<(SharedString, SharedString), Vec<&Track>> = HashMap::new();
+        for track in self.tracks() {
+            albums
+                .entry((track.album_artist(), track.album()))
+                .or_default()
+                .push(track);
+        }
+
+        let mut albums: Vec<Album> = albums
+            .into_iter()
+            .filter(|((_, album), _)| !album.is_empty())
+            .map(|((artist, album), mut tracks)| {
+                tracks.sort_by_key(|track| track.album_position());
+                Album {
+                    album,
+                    artist,
+                    artwork: tracks
+                        .iter()
+                        .find_map(|track| track.artwork())
+                        .map(|artwork| artwork.to_path_buf()),
+                    tracks: tracks.iter().map(|track| track.id().clone()).collect(),
+                }
+            })
+            .collect();
+        albums.sort_by(|a, b| {
+            a.artist
+                .to_lowercase()
+                .cmp(&b.artist.to_lowercase())
+                .then_with(|| a.album.to_lowercase().cmp(&b.album.to_lowercase()))
+        });
+        albums
+    }
+}
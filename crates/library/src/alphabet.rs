@@ -0,0 +1,117 @@
+use crate::{ColumnKind, Library, Track, TrackId};
+use gpui::SharedString;
+
+pub const INDEX_LETTERS: [&str; 26] = [
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S",
+    "T", "U", "V", "W", "X", "Y", "Z",
+];
+
+// Names in other scripts are filed under one bucket per script, shown after
+// the Latin letters in this order
+const SCRIPT_BUCKETS: &[(char, char, &str)] = &[
+    ('\u{0370}', '\u{03FF}', "Α"),
+    ('\u{0400}', '\u{04FF}', "А"),
+    ('\u{0590}', '\u{05FF}', "א"),
+    ('\u{0600}', '\u{06FF}', "ا"),
+    ('\u{3040}', '\u{30FF}', "あ"),
+    ('\u{AC00}', '\u{D7AF}', "가"),
+    ('\u{4E00}', '\u{9FFF}', "漢"),
+];
+
+impl ColumnKind {
+    // Sorted by name, so the list reads as an alphabet
+    pub fn is_alphabetical(&self) -> bool {
+        matches!(
+            self,
+            ColumnKind::Title | ColumnKind::Artist | ColumnKind::Album | ColumnKind::Kind
+        )
+    }
+}
+
+impl Track {
+    /// The text a track is sorted by for an alphabetical column.
+    pub fn sort_text(&self, kind: &ColumnKind) -> Option<SharedString> {
+        match kind {
+            ColumnKind::Title => Some(self.title()),
+            ColumnKind::Artist => Some(self.artist()),
+            ColumnKind::Album => Some(self.album()),
+            ColumnKind::Kind => Some(self.kind().to_string().into()),
+            _ => None,
+        }
+    }
+}
+
+/// The letter a name is filed under. Accented Latin letters go with their
+/// base letter, other scripts get a bucket of their own and anything else,
+/// such as digits, goes under "#".
+pub fn index_letter(name: &str) -> SharedString {
+    let Some(c) = name.trim_start().chars().next() else {
+        return "#".into();
+    };
+    if let Some(letter) = latin_letter(c) {
+        return letter.to_string().into();
+    }
+    SCRIPT_BUCKETS
+        .iter()
+        .find(|(start, end, _)| (*start..=*end).contains(&c))
+        .map(|(_, _, bucket)| SharedString::from(*bucket))
+        .unwrap_or_else(|| "#".into())
+}
+
+fn latin_letter(c: char) -> Option<char> {
+    if c.is_ascii_alphabetic() {
+        return Some(c.to_ascii_uppercase());
+    }
+    let base = match c.to_uppercase().next()? {
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => 'A',
+        'Ç' | 'Ć' | 'Č' => 'C',
+        'Ď' | 'Đ' => 'D',
+        'È'..='Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => 'E',
+        'Ğ' => 'G',
+        'Ì'..='Ï' | 'Ī' | 'İ' => 'I',
+        'Ł' => 'L',
+        'Ñ' | 'Ń' | 'Ň' => 'N',
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ő' => 'O',
+        'Ř' => 'R',
+        'Ś' | 'Š' | 'Ş' => 'S',
+        'Ť' => 'T',
+        'Ù'..='Ü' | 'Ū' | 'Ů' | 'Ű' => 'U',
+        'Ý' | 'Ÿ' => 'Y',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        _ => return None,
+    };
+    Some(base)
+}
+
+// Latin letters first, then the other scripts, then "#"
+fn bucket_rank(letter: &str) -> usize {
+    INDEX_LETTERS
+        .iter()
+        .position(|other| *other == letter)
+        .or_else(|| {
+            SCRIPT_BUCKETS
+                .iter()
+                .position(|(_, _, bucket)| *bucket == letter)
+                .map(|ix| INDEX_LETTERS.len() + ix)
+        })
+        .unwrap_or(usize::MAX)
+}
+
+impl Library {
+    /// Where each letter first appears in `ids`, in index strip order.
+    /// Letters no track is filed under are left out.
+    pub fn letter_index(&self, ids: &[TrackId], kind: &ColumnKind) -> Vec<(SharedString, usize)> {
+        let mut index: Vec<(SharedString, usize)> = Vec::new();
+        for (ix, id) in ids.iter().enumerate() {
+            let Some(text) = self.track(id).and_then(|track| track.sort_text(kind)) else {
+                continue;
+            };
+            let letter = index_letter(&text);
+            if !index.iter().any(|(other, _)| *other == letter) {
+                index.push((letter, ix));
+            }
+        }
+        index.sort_by_key(|(letter, _)| bucket_rank(letter));
+        index
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{unix_time, url_encode, MUSICBRAINZ_API, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read as _,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+const WIKIDATA_ENTITY: &str = "https://www.wikidata.org/wiki/Special:EntityData";
+const WIKIPEDIA_SUMMARY: &str = "https://en.wikipedia.org/api/rest_v1/page/summary";
+// MusicBrainz allows each client one request a second
+const MUSICBRAINZ_SPACING: Duration = Duration::from_millis(1100);
+// Anything bigger isn't a thumbnail
+const MAX_IMAGE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// A photo and short bio of an artist, with where they came from so they
+/// can be credited. Artists nothing was found for are kept too, so they
+/// aren't looked up again until asked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtistInfo {
+    // As the library spells it
+    pub name: String,
+    // The opening paragraph of their Wikipedia article
+    pub bio: Option<String>,
+    // Saved alongside the cache
+    pub image: Option<PathBuf>,
+    // The article the bio and image are from
+    pub source_url: Option<String>,
+    // Seconds since the Unix epoch
+    pub fetched: u64,
+}
+
+impl ArtistInfo {
+    pub fn is_empty(&self) -> bool {
+        self.bio.is_none() && self.image.is_none()
+    }
+
+    // Wikipedia text is CC BY-SA, which asks for a credit and a link
+    pub fn attribution(&self) -> Option<&'static str> {
+        self.source_url.as_ref().map(|_| "From Wikipedia")
+    }
+}
+
+#[derive(Deserialize)]
+struct ArtistSearchResponse {
+    #[serde(default)]
+    artists: Vec<ArtistMatch>,
+}
+
+#[derive(Deserialize)]
+struct ArtistMatch {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ArtistLookup {
+    #[serde(default)]
+    relations: Vec<Relation>,
+}
+
+#[derive(Deserialize)]
+struct Relation {
+    #[serde(rename = "type")]
+    kind: String,
+    url: Option<RelationUrl>,
+}
+
+#[derive(Deserialize)]
+struct RelationUrl {
+    resource: String,
+}
+
+#[derive(Deserialize)]
+struct WikidataResponse {
+    entities: HashMap<String, WikidataEntity>,
+}
+
+#[derive(Deserialize)]
+struct WikidataEntity {
+    #[serde(default)]
+    sitelinks: HashMap<String, Sitelink>,
+}
+
+#[derive(Deserialize)]
+struct Sitelink {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct WikipediaSummary {
+    extract: Option<String>,
+    thumbnail: Option<WikipediaImage>,
+    content_urls: Option<ContentUrls>,
+}
+
+#[derive(Deserialize)]
+struct WikipediaImage {
+    source: String,
+}
+
+#[derive(Deserialize)]
+struct ContentUrls {
+    desktop: PageUrls,
+}
+
+#[derive(Deserialize)]
+struct PageUrls {
+    page: String,
+}
+
+fn get_json<T: serde::de::DeserializeOwned>(url: &str) -> anyhow::Result<T> {
+    Ok(ureq::get(url)
+        .set("User-Agent", USER_AGENT)
+        .call()?
+        .into_json()?)
+}
+
+// MusicBrainz knows which Wikidata item is the artist, which knows their
+// Wikipedia article, so a band named like something else isn't mixed up
+fn wikipedia_title(artist: &str) -> anyhow::Result<Option<String>> {
+    let query = format!("artist:\"{}\"", artist.replace('"', ""));
+    let url = format!(
+        "{}/artist?query={}&fmt=json&limit=5",
+        MUSICBRAINZ_API,
+        url_encode(&query)
+    );
+    let search: ArtistSearchResponse = get_json(&url)?;
+    let Some(found) = search
+        .artists
+        .into_iter()
+        .find(|found| found.name.eq_ignore_ascii_case(artist))
+    else {
+        return Ok(None);
+    };
+
+    thread::sleep(MUSICBRAINZ_SPACING);
+    let url = format!(
+        "{}/artist/{}?inc=url-rels&fmt=json",
+        MUSICBRAINZ_API, found.id
+    );
+    let lookup: ArtistLookup = get_json(&url)?;
+    let Some(item) = lookup
+        .relations
+        .into_iter()
+        .filter(|relation| relation.kind == "wikidata")
+        .find_map(|relation| {
+            let resource = relation.url?.resource;
+            resource.rsplit('/').next().map(str::to_string)
+        })
+    else {
+        return Ok(None);
+    };
+
+    let url = format!("{}/{}.json", WIKIDATA_ENTITY, url_encode(&item));
+    let wikidata: WikidataResponse = get_json(&url)?;
+    Ok(wikidata.entities.into_values().find_map(|entity| {
+        entity
+            .sitelinks
+            .get("enwiki")
+            .map(|link| link.title.clone())
+    }))
+}
+
+fn save_image(url: &str, artist: &str, image_dir: &Path) -> anyhow::Result<PathBuf> {
+    let mut bytes = Vec::new();
+    ureq::get(url)
+        .set("User-Agent", USER_AGENT)
+        .call()?
+        .into_reader()
+        .take(MAX_IMAGE_BYTES)
+        .read_to_end(&mut bytes)?;
+
+    let extension = Path::new(url)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("jpg")
+        .to_ascii_lowercase();
+    let name: String = artist
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    fs::create_dir_all(image_dir)?;
+    let path = image_dir.join(format!("{}.{}", name.to_lowercase(), extension));
+    fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+/// Looks the artist up on MusicBrainz, Wikidata and Wikipedia, saving
+/// their photo into `image_dir`. Blocks until they all answer.
+pub fn fetch_artist_info(artist: &str, image_dir: &Path) -> anyhow::Result<ArtistInfo> {
+    let mut info = ArtistInfo {
+        name: artist.to_string(),
+        fetched: unix_time(),
+        ..Default::default()
+    };
+    let Some(title) = wikipedia_title(artist)? else {
+        return Ok(info);
+    };
+
+    let url = format!(
+        "{}/{}",
+        WIKIPEDIA_SUMMARY,
+        url_encode(&title.replace(' ', "_"))
+    );
+    let summary: WikipediaSummary = get_json(&url)?;
+    info.bio = summary.extract.filter(|extract| !extract.trim().is_empty());
+    info.source_url = summary.content_urls.map(|urls| urls.desktop.page);
+    // The article is still worth crediting when only the photo fails
+    info.image = summary.thumbnail.and_then(|thumbnail| {
+        save_image(&thumbnail.source, artist, image_dir)
+            .map_err(|e| eprintln!("Failed to save a photo of {}: {}", artist, e))
+            .ok()
+    });
+    Ok(info)
+}
+
+/// Artist info fetched so far, kept between launches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtistInfoCache {
+    pub artists: Vec<ArtistInfo>,
+}
+
+impl ArtistInfoCache {
+    fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join("artist-info.json")
+    }
+
+    pub fn image_dir(data_dir: &Path) -> PathBuf {
+        data_dir.join("artist-images")
+    }
+
+    pub fn load(data_dir: &Path) -> Self {
+        let Ok(json) = fs::read_to_string(Self::path(data_dir)) else {
+            return ArtistInfoCache::default();
+        };
+
+        serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("Failed to parse artist info: {}", e);
+            ArtistInfoCache::default()
+        })
+    }
+
+    pub fn save(&self, data_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::path(data_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, artist: &str) -> Option<&ArtistInfo> {
+        self.artists
+            .iter()
+            .find(|info| info.name.eq_ignore_ascii_case(artist))
+    }
+
+    /// Replaces what was known about the artist, removing a photo that's
+    /// no longer used.
+    pub fn insert(&mut self, info: ArtistInfo) {
+        if let Some(old) = self.get(&info.name) {
+            if let Some(image) = old
+                .image
+                .as_ref()
+                .filter(|image| info.image.as_ref() != Some(image))
+            {
+                fs::remove_file(image).ok();
+            }
+        }
+        self.artists
+            .retain(|other| !other.name.eq_ignore_ascii_case(&info.name));
+        self.artists.push(info);
+    }
+}
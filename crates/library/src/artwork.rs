@@ -0,0 +1,194 @@
+use crate::{is_artwork_file, ImportedFile, Library, Track};
+use image::ImageFormat;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// Big enough for a grid cell on a Retina display
+const THUMBNAIL_SIZE: u32 = 320;
+const THUMBNAIL_DIR: &str = "albums";
+
+/// Keeps one copy of each distinct image, named by the hash of its bytes,
+/// so every track of an album shares the same file.
+pub struct ArtworkStore {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArtworkReport {
+    pub images: usize,
+    pub total_bytes: u64,
+    pub unreferenced: Vec<PathBuf>,
+    pub unreferenced_bytes: u64,
+}
+
+impl ArtworkStore {
+    pub fn new(dir: PathBuf) -> Self {
+        ArtworkStore { dir }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.dir)
+    }
+
+    /// Copies an image into the store, returning the path of the shared copy.
+    pub fn store(&self, image: &Path) -> io::Result<PathBuf> {
+        if self.contains(image) {
+            return Ok(image.to_path_buf());
+        }
+
+        let bytes = fs::read(image)?;
+        let extension = image
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        self.store_bytes(&bytes, extension.as_deref())
+    }
+
+    /// Like `store`, for an image that isn't in a file of its own.
+    pub fn store_bytes(&self, bytes: &[u8], extension: Option<&str>) -> io::Result<PathBuf> {
+        let hash = Sha256::digest(bytes);
+        let mut name: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+        if let Some(extension) = extension {
+            name.push('.');
+            name.push_str(extension);
+        }
+
+        let path = self.dir.join(name);
+        if !path.exists() {
+            fs::create_dir_all(&self.dir)?;
+            fs::write(&path, bytes)?;
+        }
+        Ok(path)
+    }
+
+    // Moves the artwork of freshly imported files into the store
+    pub fn intern(&self, files: &mut [ImportedFile]) {
+        let mut stored = HashMap::new();
+        for file in files {
+            let Some(artwork) = file.artwork.take() else {
+                continue;
+            };
+            if !stored.contains_key(&artwork) {
+                match self.store(&artwork) {
+                    Ok(path) => {
+                        stored.insert(artwork.clone(), path);
+                    }
+                    Err(e) => eprintln!("Failed to store artwork {}: {}", artwork.display(), e),
+                }
+            }
+            file.artwork = stored.get(&artwork).cloned();
+        }
+    }
+
+    pub fn report(&self, library: &Library) -> ArtworkReport {
+        let references = library.artwork_references();
+        let mut report = ArtworkReport::default();
+
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return report;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if !is_artwork_file(&path) {
+                continue;
+            }
+            let len = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            report.images += 1;
+            report.total_bytes += len;
+            if !references.contains_key(&path) {
+                report.unreferenced.push(path);
+                report.unreferenced_bytes += len;
+            }
+        }
+        report
+    }
+
+    /// Deletes every image no track refers to any more.
+    pub fn clean_up(&self, report: &ArtworkReport) -> io::Result<()> {
+        for path in &report.unreferenced {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// A small copy of the cover of the track's album, decoded and scaled
+    /// once and then kept, for showing many covers at a time. Made again
+    /// whenever the album's artwork is newer than it.
+    pub fn album_thumbnail(&self, track: &Track) -> anyhow::Result<Option<PathBuf>> {
+        let Some(artwork) = track.artwork() else {
+            return Ok(None);
+        };
+        let thumbnail = self
+            .dir
+            .join(THUMBNAIL_DIR)
+            .join(format!("{}.jpg", album_key(track)));
+        if is_newer(&thumbnail, artwork) {
+            return Ok(Some(thumbnail));
+        }
+
+        let image = image::open(artwork)?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+        fs::create_dir_all(self.dir.join(THUMBNAIL_DIR))?;
+        // JPEG has no alpha channel
+        image
+            .to_rgb8()
+            .save_with_format(&thumbnail, ImageFormat::Jpeg)?;
+        Ok(Some(thumbnail))
+    }
+}
+
+impl Library {
+    /// How many tracks use each artwork file.
+    pub fn artwork_references(&self) -> HashMap<PathBuf, usize> {
+        let mut references = HashMap::new();
+        for track in self.tracks.values() {
+            if let Some(artwork) = track.artwork() {
+                *references.entry(artwork.to_path_buf()).or_default() += 1;
+            }
+        }
+        references
+    }
+
+    /// Gives the tracks at these files the artwork just embedded into them,
+    /// where they had none. Being rewritten, they're checksummed again.
+    pub fn set_embedded_artwork(&mut self, embedded: &[(PathBuf, PathBuf)]) {
+        let artwork: HashMap<&Path, &PathBuf> = embedded
+            .iter()
+            .map(|(file, artwork)| (file.as_path(), artwork))
+            .collect();
+        let mut rewritten = Vec::new();
+        for track in self.tracks.values_mut() {
+            let Some(artwork) = track.path().and_then(|path| artwork.get(path)) else {
+                continue;
+            };
+            if track.artwork.is_none() {
+                track.artwork = Some(artwork.to_path_buf());
+                self.changed_tracks.insert(track.id.clone());
+            }
+            rewritten.push(track.id.clone());
+        }
+        self.rechecksum(&rewritten);
+    }
+}
+
+// The same however the album's name is capitalised
+fn album_key(track: &Track) -> String {
+    let hash = Sha256::digest(format!(
+        "{}\0{}",
+        track.album_artist().to_lowercase(),
+        track.album().to_lowercase()
+    ));
+    hash[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn is_newer(path: &Path, than: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(path), modified(than)) {
+        (Some(modified), Some(than)) => modified >= than,
+        _ => false,
+    }
+}
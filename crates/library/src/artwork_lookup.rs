@@ -0,0 +1,71 @@
+use crate::{existing_cover, read_embedded_artwork, ArtworkStore, Library, TrackId};
+use std::{collections::HashMap, path::PathBuf};
+
+impl Library {
+    /// Files of the tracks loaded or imported since this was last called
+    /// that have no artwork. Ones on a drive that isn't plugged in are
+    /// left until the next launch.
+    pub fn take_missing_artwork(&mut self) -> Vec<(TrackId, PathBuf)> {
+        std::mem::take(&mut self.pending_artwork)
+            .into_iter()
+            .filter_map(|id| {
+                let track = self.tracks.get(&id)?;
+                if track.artwork.is_some() || !self.is_available(track) {
+                    return None;
+                }
+                Some((id, track.path.clone()?))
+            })
+            .collect()
+    }
+
+    /// Gives tracks the artwork `find_artwork` turned up, where they still
+    /// have none.
+    pub fn set_found_artwork(&mut self, found: Vec<(TrackId, PathBuf)>) {
+        for (id, artwork) in found {
+            if let Some(track) = self.track_mut(&id) {
+                track.artwork.get_or_insert(artwork);
+            }
+        }
+    }
+}
+
+/// Looks for each file's cover, first in its tags and failing that in an
+/// image such as folder.jpg next to it, and keeps what it finds in `store`.
+/// Reads every file, so it's meant for off the main thread.
+pub fn find_artwork(
+    files: Vec<(TrackId, PathBuf)>,
+    store: &ArtworkStore,
+) -> Vec<(TrackId, PathBuf)> {
+    // Tracks of an album mostly share a folder, and so its cover
+    let mut folder_covers: HashMap<PathBuf, Option<PathBuf>> = HashMap::new();
+    files
+        .into_iter()
+        .filter_map(|(id, path)| {
+            let embedded = match read_embedded_artwork(&path) {
+                Ok(images) => images.into_iter().next(),
+                Err(e) => {
+                    eprintln!("Failed to read artwork from {}: {}", path.display(), e);
+                    None
+                }
+            };
+            let artwork = match embedded {
+                Some(image) => store.store_bytes(&image.data, Some(image.extension())),
+                None => {
+                    let folder = path.parent()?;
+                    folder_covers
+                        .entry(folder.to_path_buf())
+                        .or_insert_with(|| existing_cover(folder))
+                        .as_deref()
+                        .map(|cover| store.store(cover))?
+                }
+            };
+            match artwork {
+                Ok(artwork) => Some((id, artwork)),
+                Err(e) => {
+                    eprintln!("Failed to store artwork for {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
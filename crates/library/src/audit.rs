@@ -0,0 +1,375 @@
+use crate::{
+    language_name, parse_moods, parse_release_date, unix_time, Library, LibraryCommand,
+    MetadataEdit, MusicalKey, Track, TrackId, MAX_RATING,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Grouping,
+    Genre,
+    Year,
+    ReleaseDate,
+    OriginalReleaseDate,
+    // Worked out from the dates, so it can be filtered on but not set
+    Decade,
+    Language,
+    // Several at once, written as "chill, focus"
+    Mood,
+    // In Camelot notation, as "8A"
+    Key,
+    TrackNumber,
+    Rating,
+    AlbumRating,
+    Gapless,
+}
+
+impl MetadataField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetadataField::Title => "Name",
+            MetadataField::Artist => "Artist",
+            MetadataField::Album => "Album",
+            MetadataField::AlbumArtist => "Album Artist",
+            MetadataField::Grouping => "Grouping",
+            MetadataField::Genre => "Genre",
+            MetadataField::Year => "Year",
+            MetadataField::ReleaseDate => "Release Date",
+            MetadataField::OriginalReleaseDate => "Original Release Date",
+            MetadataField::Decade => "Decade",
+            MetadataField::Language => "Language",
+            MetadataField::Mood => "Mood",
+            MetadataField::Key => "Key",
+            MetadataField::TrackNumber => "Track Number",
+            MetadataField::Rating => "Rating",
+            MetadataField::AlbumRating => "Album Rating",
+            MetadataField::Gapless => "Gapless Album",
+        }
+    }
+
+    // The names rules and settings use, matching the serialized form
+    pub fn key(&self) -> &'static str {
+        match self {
+            MetadataField::Title => "title",
+            MetadataField::Artist => "artist",
+            MetadataField::Album => "album",
+            MetadataField::AlbumArtist => "album_artist",
+            MetadataField::Grouping => "grouping",
+            MetadataField::Genre => "genre",
+            MetadataField::Year => "year",
+            MetadataField::ReleaseDate => "release_date",
+            MetadataField::OriginalReleaseDate => "original_release_date",
+            MetadataField::Decade => "decade",
+            MetadataField::Language => "language",
+            MetadataField::Mood => "mood",
+            MetadataField::Key => "key",
+            MetadataField::TrackNumber => "track_number",
+            MetadataField::Rating => "rating",
+            MetadataField::AlbumRating => "album_rating",
+            MetadataField::Gapless => "gapless",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        [
+            MetadataField::Title,
+            MetadataField::Artist,
+            MetadataField::Album,
+            MetadataField::AlbumArtist,
+            MetadataField::Grouping,
+            MetadataField::Genre,
+            MetadataField::Year,
+            MetadataField::ReleaseDate,
+            MetadataField::OriginalReleaseDate,
+            MetadataField::Decade,
+            MetadataField::Language,
+            MetadataField::Mood,
+            MetadataField::Key,
+            MetadataField::TrackNumber,
+            MetadataField::Rating,
+            MetadataField::AlbumRating,
+            MetadataField::Gapless,
+        ]
+        .into_iter()
+        .find(|field| field.key() == key.to_lowercase())
+    }
+}
+
+/// Who made a change, so an automated pass can be told apart from edits
+/// made by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeSource {
+    User,
+    AutoFix,
+    OnlineLookup,
+    Script,
+    ImportRule,
+}
+
+impl ChangeSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeSource::User => "Edited",
+            ChangeSource::AutoFix => "Auto-fix",
+            ChangeSource::OnlineLookup => "Online lookup",
+            ChangeSource::Script => "Script",
+            ChangeSource::ImportRule => "Import rule",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataChange {
+    pub id: u64,
+    pub track_id: TrackId,
+    pub field: MetadataField,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    // Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub source: ChangeSource,
+    // Changes made together, such as one tagging pass, share a batch
+    pub batch: u64,
+}
+
+impl MetadataChange {
+    // "Year: 1959 → 1960"
+    pub fn description(&self) -> String {
+        let value = |value: &Option<String>| value.clone().unwrap_or_else(|| "—".to_string());
+        format!(
+            "{}: {} → {}",
+            self.field.name(),
+            value(&self.old_value),
+            value(&self.new_value)
+        )
+    }
+}
+
+impl Track {
+    pub fn metadata(&self, field: MetadataField) -> Option<String> {
+        match field {
+            MetadataField::Title => Some(self.title.to_string()),
+            MetadataField::Artist => Some(self.artist.to_string()),
+            MetadataField::Album => Some(self.album.to_string()),
+            MetadataField::AlbumArtist => self.album_artist.as_ref().map(|a| a.to_string()),
+            MetadataField::Grouping => self.grouping.as_ref().map(|g| g.to_string()),
+            MetadataField::Genre => self.genre.clone(),
+            MetadataField::Year => self.year.map(|year| year.to_string()),
+            MetadataField::ReleaseDate => self.release_date.clone(),
+            MetadataField::OriginalReleaseDate => self.original_release_date.clone(),
+            MetadataField::Decade => self.decade().map(|decade| format!("{}s", decade)),
+            MetadataField::Language => self.language.clone(),
+            MetadataField::Mood => (!self.moods.is_empty()).then(|| self.moods.join(", ")),
+            MetadataField::Key => self.key.map(|key| key.camelot()),
+            MetadataField::TrackNumber => {
+                (self.track_number > 0).then(|| self.track_number.to_string())
+            }
+            MetadataField::Rating => self.rating.map(|stars| stars.to_string()),
+            MetadataField::AlbumRating => self.album_rating.map(|stars| stars.to_string()),
+            MetadataField::Gapless => self.gapless.map(|gapless| gapless.to_string()),
+        }
+    }
+
+    // Values that don't parse for the field leave it as it was
+    fn set_metadata(&mut self, field: MetadataField, value: Option<&str>) {
+        let value = value.map(str::trim).filter(|value| !value.is_empty());
+        match field {
+            MetadataField::Title => self.title = value.unwrap_or_default().to_string().into(),
+            MetadataField::Artist => self.artist = value.unwrap_or_default().to_string().into(),
+            MetadataField::Album => self.album = value.unwrap_or_default().to_string().into(),
+            MetadataField::AlbumArtist => {
+                self.album_artist = value.map(|value| value.to_string().into())
+            }
+            MetadataField::Grouping => self.grouping = value.map(|value| value.to_string().into()),
+            MetadataField::Genre => self.genre = value.map(str::to_string),
+            MetadataField::Year => {
+                if let Some(year) = parse(value) {
+                    self.year = year;
+                }
+            }
+            MetadataField::ReleaseDate => {
+                if let Some(date) = parse_date(value) {
+                    self.release_date = date;
+                }
+            }
+            MetadataField::OriginalReleaseDate => {
+                if let Some(date) = parse_date(value) {
+                    self.original_release_date = date;
+                }
+            }
+            MetadataField::Decade => {}
+            MetadataField::Language => self.language = value.and_then(language_name),
+            MetadataField::Mood => self.moods = value.map(parse_moods).unwrap_or_default(),
+            MetadataField::Key => match value {
+                Some(value) => {
+                    if let Some(key) = MusicalKey::parse(value) {
+                        self.key = Some(key);
+                    }
+                }
+                None => self.key = None,
+            },
+            MetadataField::TrackNumber => {
+                if let Some(track_number) = parse(value) {
+                    self.track_number = track_number.unwrap_or(0);
+                }
+            }
+            MetadataField::Rating => {
+                if let Some(rating) = parse::<u8>(value) {
+                    self.rating = rating.map(|stars| stars.min(MAX_RATING));
+                }
+            }
+            MetadataField::AlbumRating => {
+                if let Some(rating) = parse::<u8>(value) {
+                    self.album_rating = rating.map(|stars| stars.min(MAX_RATING));
+                }
+            }
+            MetadataField::Gapless => {
+                if let Some(gapless) = parse(value) {
+                    self.gapless = gapless;
+                }
+            }
+        }
+    }
+}
+
+// `None` when the value is there but isn't valid, `Some(None)` when it's cleared
+fn parse<T: std::str::FromStr>(value: Option<&str>) -> Option<Option<T>> {
+    match value {
+        Some(value) => value.parse().ok().map(Some),
+        None => Some(None),
+    }
+}
+
+fn parse_date(value: Option<&str>) -> Option<Option<String>> {
+    match value {
+        Some(value) => parse_release_date(value).map(Some),
+        None => Some(None),
+    }
+}
+
+impl Library {
+    /// Sets a field on each track, logging every value that actually changes.
+    /// Returns the batch the changes were logged under.
+    pub fn edit_metadata(
+        &mut self,
+        ids: &[TrackId],
+        field: MetadataField,
+        value: Option<String>,
+        source: ChangeSource,
+    ) -> u64 {
+        self.edit_metadata_batch(
+            ids.iter()
+                .map(|id| (id.clone(), field, value.clone()))
+                .collect(),
+            source,
+        )
+    }
+
+    /// Applies many edits as one batch, the way a tagging pass would, so they
+    /// can be rolled back together.
+    pub fn edit_metadata_batch(
+        &mut self,
+        edits: Vec<(TrackId, MetadataField, Option<String>)>,
+        source: ChangeSource,
+    ) -> u64 {
+        self.next_change_batch += 1;
+        let batch = self.next_change_batch;
+        let timestamp = unix_time();
+
+        for (track_id, field, value) in edits {
+            let Some(track) = self.track_mut(&track_id) else {
+                continue;
+            };
+            let old_value = track.metadata(field);
+            track.set_metadata(field, value.as_deref());
+            let new_value = track.metadata(field);
+            if new_value == old_value {
+                continue;
+            }
+
+            self.metadata_log.push(MetadataChange {
+                id: self.metadata_log.len() as u64,
+                track_id,
+                field,
+                old_value,
+                new_value,
+                timestamp,
+                source,
+                batch,
+            });
+        }
+
+        // Edits made by hand can be undone, automated passes are rolled back
+        // from their history instead
+        if source == ChangeSource::User {
+            let edits: Vec<MetadataEdit> = self
+                .metadata_log
+                .iter()
+                .rev()
+                .take_while(|change| change.batch == batch)
+                .map(|change| MetadataEdit {
+                    track: change.track_id.clone(),
+                    field: change.field,
+                    old_value: change.old_value.clone(),
+                    new_value: change.new_value.clone(),
+                })
+                .collect();
+            if !edits.is_empty() {
+                self.record_undo(LibraryCommand::EditMetadata(edits));
+            }
+        }
+        batch
+    }
+
+    /// Every change made to a track, oldest first.
+    pub fn metadata_history(&self, id: &TrackId) -> Vec<&MetadataChange> {
+        self.metadata_log
+            .iter()
+            .filter(|change| &change.track_id == id)
+            .collect()
+    }
+
+    pub fn revert_metadata_change(&mut self, change_id: u64) -> usize {
+        self.revert_metadata(|change| change.id == change_id)
+    }
+
+    /// Rolls back a whole pass, such as an automated tagging run gone wrong.
+    pub fn revert_metadata_batch(&mut self, batch: u64) -> usize {
+        self.revert_metadata(|change| change.batch == batch)
+    }
+
+    // Fields that have been changed again since are left alone, the later
+    // edit wins. The rollback is logged like any other edit.
+    fn revert_metadata(&mut self, matches: impl Fn(&MetadataChange) -> bool) -> usize {
+        let edits: Vec<(TrackId, MetadataField, Option<String>)> = self
+            .metadata_log
+            .iter()
+            .rev()
+            .filter(|change| matches(change))
+            .filter(|change| {
+                self.tracks
+                    .get(&change.track_id)
+                    .is_some_and(|track| track.metadata(change.field) == change.new_value)
+            })
+            .map(|change| {
+                (
+                    change.track_id.clone(),
+                    change.field,
+                    change.old_value.clone(),
+                )
+            })
+            .collect();
+
+        let reverted = edits.len();
+        if reverted > 0 {
+            self.edit_metadata_batch(edits, ChangeSource::User);
+        }
+        reverted
+    }
+}
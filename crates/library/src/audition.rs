@@ -0,0 +1,75 @@
+use crate::{CurrentTrack, Track};
+
+// Hover previews sit well under whatever is playing on the main output
+const PREVIEW_VOLUME: f32 = 0.3;
+
+/// A track being pre-listened on the audition output, independent of
+/// whatever is playing on the main output.
+#[derive(Default)]
+pub struct Audition {
+    current_track: Option<CurrentTrack>,
+    // Started by hovering, so it stops again when the pointer moves away
+    hover_preview: bool,
+    // Bumped whenever a track is started, even the same one again
+    generation: u64,
+}
+
+impl Audition {
+    pub fn current_track(&self) -> Option<&CurrentTrack> {
+        self.current_track.as_ref()
+    }
+
+    // For following the audition output along
+    pub fn current_track_mut(&mut self) -> Option<&mut CurrentTrack> {
+        self.current_track.as_mut()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn start(&mut self, track: Track) {
+        let mut current_track = CurrentTrack::new(track);
+        current_track.set_is_playing(true);
+        self.current_track = Some(current_track);
+        self.hover_preview = false;
+        self.generation += 1;
+    }
+
+    /// Like `start`, but quieter, and only until `stop_preview`.
+    pub fn start_preview(&mut self, track: Track) {
+        self.start(track);
+        self.hover_preview = true;
+    }
+
+    pub fn is_preview(&self) -> bool {
+        self.current_track.is_some() && self.hover_preview
+    }
+
+    pub fn volume(&self) -> f32 {
+        if self.hover_preview {
+            PREVIEW_VOLUME
+        } else {
+            1.
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        if let Some(current_track) = &mut self.current_track {
+            current_track.set_is_playing(!current_track.is_playing());
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.current_track = None;
+        self.hover_preview = false;
+    }
+
+    /// Stops a hover preview, leaving anything auditioned on purpose
+    /// playing.
+    pub fn stop_preview(&mut self) {
+        if self.hover_preview {
+            self.stop();
+        }
+    }
+}
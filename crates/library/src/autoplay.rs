@@ -0,0 +1,98 @@
+use crate::{Library, Track, TrackId};
+use gpui::SharedString;
+use std::collections::{HashMap, HashSet};
+
+// So a batch isn't one artist's back catalogue
+const MAX_PER_ARTIST: usize = 2;
+// Released this close together counts as the same era
+const ERA_YEARS: u32 = 5;
+
+impl Library {
+    /// Up to `limit` tracks to carry on with after `seeds`, for when the
+    /// queue runs out. A track scores for sharing an artist, a genre, moods
+    /// or an era with a seed, and for its artist turning up on albums and
+    /// playlists with a seed's. The best are picked with some chance to it,
+    /// leaning towards ones played more, and one-star tracks are never
+    /// picked.
+    pub fn similar_tracks(
+        &self,
+        seeds: &[TrackId],
+        exclude: &HashSet<TrackId>,
+        limit: usize,
+    ) -> Vec<TrackId> {
+        let seeds: Vec<&Track> = seeds.iter().filter_map(|id| self.track(id)).collect();
+        if seeds.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+        let tracks: Vec<&Track> = self.tracks().collect();
+        let affinity = self.artist_affinity(&tracks);
+
+        let mut scored: Vec<(f64, &Track)> = tracks
+            .into_iter()
+            .filter(|track| {
+                !exclude.contains(track.id())
+                    && track.rating() != Some(1)
+                    && !seeds.iter().any(|seed| seed.id() == track.id())
+            })
+            .filter_map(|track| {
+                let score: u32 = seeds
+                    .iter()
+                    .map(|seed| similarity(seed, track, &affinity))
+                    .sum();
+                if score == 0 {
+                    return None;
+                }
+                let roll = 0.5 + rand::random::<f64>() / 2.;
+                let plays = (track.plays().max(0) as f64).ln_1p();
+                Some((score as f64 * (1. + plays / 4.) * roll, track))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut per_artist: HashMap<SharedString, usize> = HashMap::new();
+        scored
+            .into_iter()
+            .filter(|(_, track)| {
+                let count = per_artist.entry(track.artist()).or_default();
+                *count += 1;
+                *count <= MAX_PER_ARTIST
+            })
+            .take(limit)
+            .map(|(_, track)| track.id().clone())
+            .collect()
+    }
+}
+
+fn similarity(
+    seed: &Track,
+    track: &Track,
+    affinity: &HashMap<SharedString, HashMap<SharedString, u32>>,
+) -> u32 {
+    let mut score = 0;
+    if seed.artist() == track.artist() {
+        score += 3;
+    } else if let Some(count) = affinity
+        .get(&seed.artist())
+        .and_then(|artists| artists.get(&track.artist()))
+    {
+        score += (*count).min(3);
+    }
+    if seed
+        .genre()
+        .zip(track.genre())
+        .is_some_and(|(a, b)| a.eq_ignore_ascii_case(b))
+    {
+        score += 2;
+    }
+    score += seed
+        .moods()
+        .iter()
+        .filter(|mood| track.has_mood(mood))
+        .count() as u32;
+    if let (Some(a), Some(b)) = (seed.year(), track.year()) {
+        if a.abs_diff(b) <= ERA_YEARS {
+            score += 1;
+        }
+    }
+    score
+}
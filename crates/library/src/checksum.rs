@@ -0,0 +1,148 @@
+use crate::{Library, Track, TrackId};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The SHA-256 of a file's contents, in hex.
+pub fn file_checksum(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// What's wrong with a track's file, found by reading it again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileProblem {
+    Missing,
+    // Shorter than when it was imported, most likely cut off mid-copy
+    Truncated { size: u64, expected: u64 },
+    // The same length but not the same contents, such as from a failing disk
+    Changed,
+    Unreadable(String),
+}
+
+impl FileProblem {
+    pub fn describe(&self) -> String {
+        match self {
+            FileProblem::Missing => "File is missing".to_string(),
+            FileProblem::Truncated { size, expected } => {
+                format!("Truncated, {} of {} bytes left", size, expected)
+            }
+            FileProblem::Changed => "Contents have changed".to_string(),
+            FileProblem::Unreadable(error) => format!("Couldn't be read: {}", error),
+        }
+    }
+}
+
+/// A track's file to read again, with what it was when imported.
+#[derive(Debug, Clone)]
+pub struct FileCheck {
+    pub track: TrackId,
+    pub path: PathBuf,
+    pub size: u64,
+    // `None` for files that have yet to be checksummed
+    pub expected: Option<String>,
+}
+
+impl FileCheck {
+    /// Re-hashes the file. A file with nothing to compare against passes,
+    /// and its checksum is what should be kept from now on.
+    pub fn run(&self) -> Result<String, (FileProblem, Option<String>)> {
+        let metadata = fs::metadata(&self.path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => (FileProblem::Missing, None),
+            _ => (FileProblem::Unreadable(e.to_string()), None),
+        })?;
+        let checksum = file_checksum(&self.path)
+            .map_err(|e| (FileProblem::Unreadable(e.to_string()), None))?;
+        match &self.expected {
+            Some(expected) if *expected != checksum => {
+                // Sizes weren't kept by older libraries, which leaves 0
+                let problem = if self.size > 0 && metadata.len() < self.size {
+                    FileProblem::Truncated {
+                        size: metadata.len(),
+                        expected: self.size,
+                    }
+                } else {
+                    FileProblem::Changed
+                };
+                Err((problem, Some(checksum)))
+            }
+            _ => Ok(checksum),
+        }
+    }
+}
+
+impl Track {
+    /// Taken when the file was imported, if checksums were being kept.
+    pub fn checksum(&self) -> Option<&str> {
+        self.checksum.as_deref()
+    }
+}
+
+impl Library {
+    /// Every file that can be read right now, to be checked against its
+    /// checksum, or checksummed for the first time.
+    pub fn file_checks(&self) -> Vec<FileCheck> {
+        self.tracks()
+            .filter(|track| self.is_available(track))
+            .filter_map(|track| {
+                Some(FileCheck {
+                    track: track.id.clone(),
+                    path: track.path.clone()?,
+                    size: track.size,
+                    expected: track.checksum.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Files imported since this was last called that have no checksum.
+    pub fn take_unchecksummed(&mut self) -> Vec<(TrackId, PathBuf)> {
+        std::mem::take(&mut self.pending_checksums)
+            .into_iter()
+            .filter_map(|id| {
+                let track = self.tracks.get(&id)?;
+                if track.checksum.is_some() {
+                    return None;
+                }
+                Some((id, track.path.clone()?))
+            })
+            .collect()
+    }
+
+    pub fn set_checksums(&mut self, checksums: Vec<(TrackId, String)>) {
+        for (id, checksum) in checksums {
+            if let Some(track) = self.track_mut(&id) {
+                track.checksum = Some(checksum);
+            }
+        }
+    }
+
+    /// The tracks' files were rewritten on purpose, such as to tag them, so
+    /// the ones that had a checksum are checksummed again.
+    pub fn rechecksum(&mut self, ids: &[TrackId]) {
+        for id in ids {
+            if let Some(track) = self.track_mut(id) {
+                if track.checksum.take().is_some() {
+                    self.pending_checksums.push(id.clone());
+                }
+            }
+        }
+    }
+
+    /// The files under `folder` were rewritten on purpose, such as to strip
+    /// their artwork, so they're checksummed again rather than flagged.
+    pub fn rechecksum_under(&mut self, folder: &Path) {
+        for track in self.tracks.values_mut() {
+            if track.checksum.is_some() && track.path().is_some_and(|path| path.starts_with(folder))
+            {
+                track.checksum = None;
+                self.pending_checksums.push(track.id.clone());
+                self.changed_tracks.insert(track.id.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{ChangeSource, Library, MetadataField, TrackId};
+
+// Left lowercase in title case, unless they start or end the title
+const SMALL_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "nor", "of", "on", "or", "the",
+    "to", "vs", "vs.", "via", "with",
+];
+
+// Ways "featuring" gets written, all made "feat."
+const FEATURING: &[&str] = &["ft", "ft.", "feat", "feat.", "featuring"];
+
+// Store and streaming exports add these, and they say nothing about the song
+const EXPLICIT_TAGS: &[&str] = &[
+    "[explicit]",
+    "(explicit)",
+    "[explicit version]",
+    "(explicit version)",
+];
+
+/// A tidy-up that can be run over the tags of many tracks at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CleanupPreset {
+    TitleCase,
+    FixAllCaps,
+    StripExplicit,
+    NormalizeFeaturing,
+    TrimWhitespace,
+}
+
+impl CleanupPreset {
+    pub const ALL: [CleanupPreset; 5] = [
+        CleanupPreset::TitleCase,
+        CleanupPreset::FixAllCaps,
+        CleanupPreset::StripExplicit,
+        CleanupPreset::NormalizeFeaturing,
+        CleanupPreset::TrimWhitespace,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CleanupPreset::TitleCase => "Title Case",
+            CleanupPreset::FixAllCaps => "Fix ALL CAPS",
+            CleanupPreset::StripExplicit => "Remove “[Explicit]”",
+            CleanupPreset::NormalizeFeaturing => "Write “feat.” the Same Way",
+            CleanupPreset::TrimWhitespace => "Trim Whitespace",
+        }
+    }
+
+    // Artists are left out of title casing, their names are often cased
+    // on purpose
+    fn fields(&self) -> &'static [MetadataField] {
+        match self {
+            CleanupPreset::TitleCase => &[MetadataField::Title, MetadataField::Album],
+            CleanupPreset::StripExplicit => &[MetadataField::Title, MetadataField::Album],
+            CleanupPreset::NormalizeFeaturing => &[
+                MetadataField::Title,
+                MetadataField::Artist,
+                MetadataField::AlbumArtist,
+            ],
+            CleanupPreset::FixAllCaps | CleanupPreset::TrimWhitespace => &[
+                MetadataField::Title,
+                MetadataField::Artist,
+                MetadataField::Album,
+                MetadataField::AlbumArtist,
+                MetadataField::Grouping,
+            ],
+        }
+    }
+
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            CleanupPreset::TitleCase => title_case(value),
+            CleanupPreset::FixAllCaps => fix_all_caps(value),
+            CleanupPreset::StripExplicit => strip_explicit(value),
+            CleanupPreset::NormalizeFeaturing => normalize_featuring(value),
+            CleanupPreset::TrimWhitespace => trim_whitespace(value),
+        }
+    }
+}
+
+/// A value a cleanup would change, shown before anything is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CleanupEdit {
+    pub track_id: TrackId,
+    pub field: MetadataField,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+impl Library {
+    /// What running the preset over the tracks would change.
+    pub fn preview_cleanup(&self, ids: &[TrackId], preset: CleanupPreset) -> Vec<CleanupEdit> {
+        let mut edits = Vec::new();
+        for id in ids {
+            let Some(track) = self.track(id) else {
+                continue;
+            };
+            for &field in preset.fields() {
+                let Some(old_value) = track.metadata(field) else {
+                    continue;
+                };
+                let new_value = preset.apply(&old_value);
+                if new_value != old_value && !new_value.is_empty() {
+                    edits.push(CleanupEdit {
+                        track_id: id.clone(),
+                        field,
+                        old_value,
+                        new_value,
+                    });
+                }
+            }
+        }
+        edits
+    }
+
+    /// Makes the edits as one batch, so the whole cleanup can be rolled back
+    /// together. Returns the batch.
+    pub fn apply_cleanup(&mut self, edits: Vec<CleanupEdit>) -> u64 {
+        self.edit_metadata_batch(
+            edits
+                .into_iter()
+                .map(|edit| (edit.track_id, edit.field, Some(edit.new_value)))
+                .collect(),
+            ChangeSource::AutoFix,
+        )
+    }
+}
+
+fn trim_whitespace(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Only lowercase words are capitalized, so "McCartney", "iPod" and "DJ"
+// keep the casing they were given
+fn title_case(value: &str) -> String {
+    let words: Vec<&str> = value.split_whitespace().collect();
+    let last = words.len().saturating_sub(1);
+    let mut starts_phrase = true;
+    let mut cased = Vec::with_capacity(words.len());
+    for (ix, word) in words.iter().enumerate() {
+        let bare = word.trim_start_matches(['(', '[', '"', '“']);
+        let is_lowercase = !bare.chars().any(char::is_uppercase);
+        let is_small = SMALL_WORDS.contains(&bare);
+        if is_lowercase && !(is_small && !starts_phrase && ix != last && bare.len() == word.len()) {
+            cased.push(capitalize(word));
+        } else {
+            cased.push(word.to_string());
+        }
+        starts_phrase = word.ends_with([':', '-', '–', '—']) || *word == "-";
+    }
+    cased.join(" ")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut capitalized = String::with_capacity(word.len());
+    let mut done = false;
+    for c in word.chars() {
+        if !done && c.is_alphabetic() {
+            capitalized.extend(c.to_uppercase());
+            done = true;
+        } else {
+            capitalized.push(c);
+        }
+    }
+    capitalized
+}
+
+// A single all-caps word is more likely a name like "ABBA" than a mistake
+fn fix_all_caps(value: &str) -> String {
+    let has_lowercase = value.chars().any(char::is_lowercase);
+    let has_uppercase = value.chars().any(char::is_uppercase);
+    if has_lowercase || !has_uppercase || value.split_whitespace().count() < 2 {
+        return value.to_string();
+    }
+    title_case(&value.to_lowercase())
+}
+
+fn strip_explicit(value: &str) -> String {
+    let mut stripped = value.to_string();
+    for tag in EXPLICIT_TAGS {
+        while let Some(start) = stripped.to_ascii_lowercase().find(tag) {
+            stripped.replace_range(start..start + tag.len(), "");
+        }
+    }
+    trim_whitespace(&stripped)
+}
+
+fn normalize_featuring(value: &str) -> String {
+    value
+        .split_whitespace()
+        .map(|word| {
+            let bare = word.trim_start_matches(['(', '[']);
+            let opening = &word[..word.len() - bare.len()];
+            if FEATURING.contains(&bare.to_lowercase().as_str()) {
+                format!("{}feat.", opening)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
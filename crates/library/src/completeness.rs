@@ -0,0 +1,80 @@
+use crate::{Library, Track, TrackId};
+use gpui::SharedString;
+use std::collections::{HashMap, HashSet};
+
+/// An album with gaps in its track numbers, such as 9 of 12 tracks, going
+/// by the total its tracks are tagged with.
+#[derive(Debug, Clone)]
+pub struct IncompleteAlbum {
+    pub album: SharedString,
+    pub artist: SharedString,
+    pub total: u32,
+    // The track numbers that aren't in the library, in order
+    pub missing: Vec<u32>,
+    pub tracks: Vec<TrackId>,
+}
+
+impl IncompleteAlbum {
+    pub fn present(&self) -> u32 {
+        self.total - self.missing.len() as u32
+    }
+
+    // "9 of 12"
+    pub fn summary(&self) -> String {
+        format!("{} of {}", self.present(), self.total)
+    }
+}
+
+// A track number that shows up twice is most likely a second disc, which
+// the tags don't tell apart, so there's no saying what's missing
+fn missing_tracks(tracks: &[&Track]) -> Option<(u32, Vec<u32>)> {
+    let total = tracks.iter().map(|track| track.total_tracks()).max()?;
+    let mut numbers = HashSet::new();
+    for track in tracks {
+        let number = track.track_number();
+        if number > total || (number > 0 && !numbers.insert(number)) {
+            return None;
+        }
+    }
+    let missing: Vec<u32> = (1..=total)
+        .filter(|number| !numbers.contains(number))
+        .collect();
+    (!missing.is_empty() && !numbers.is_empty()).then_some((total, missing))
+}
+
+impl Library {
+    /// Every album missing some of its tracks, the closest to complete
+    /// first.
+    pub fn incomplete_albums(&self) -> Vec<IncompleteAlbum> {
+        let mut albums: HashMap<(SharedString, SharedString), Vec<&Track>> = HashMap::new();
+        for track in self.tracks().filter(|track| track.path().is_some()) {
+            albums
+                .entry((track.album_artist(), track.album()))
+                .or_default()
+                .push(track);
+        }
+
+        let mut incomplete: Vec<IncompleteAlbum> = albums
+            .into_iter()
+            .filter(|((_, album), _)| !album.is_empty())
+            .filter_map(|((artist, album), tracks)| {
+                let (total, missing) = missing_tracks(&tracks)?;
+                Some(IncompleteAlbum {
+                    album,
+                    artist,
+                    total,
+                    missing,
+                    tracks: tracks.iter().map(|track| track.id().clone()).collect(),
+                })
+            })
+            .collect();
+        incomplete.sort_by(|a, b| {
+            a.missing
+                .len()
+                .cmp(&b.missing.len())
+                .then_with(|| a.artist.to_lowercase().cmp(&b.artist.to_lowercase()))
+                .then_with(|| a.album.to_lowercase().cmp(&b.album.to_lowercase()))
+        });
+        incomplete
+    }
+}
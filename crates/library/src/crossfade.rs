@@ -0,0 +1,66 @@
+use crate::{ChangeSource, Library, MetadataField, Track, TrackId};
+
+// Tracks with less silence than this between them run into each other
+const CONTINUOUS_GAP_MS: u32 = 250;
+
+impl Library {
+    /// Every track on the same album as `id`, in track number order.
+    pub fn album_tracks(&self, id: &TrackId) -> Vec<&Track> {
+        let Some(track) = self.track(id) else {
+            return Vec::new();
+        };
+        let mut tracks: Vec<&Track> = self
+            .tracks()
+            .filter(|other| track.is_same_album(other))
+            .collect();
+        tracks.sort_by_key(|track| track.album_position());
+        tracks
+    }
+
+    /// Live and DJ-mixed albums are meant to play straight through. A gapless
+    /// flag on any track decides it, otherwise the measured silence between
+    /// tracks does.
+    pub fn is_continuous_album(&self, id: &TrackId) -> bool {
+        let tracks = self.album_tracks(id);
+        if let Some(gapless) = tracks.iter().find_map(|track| track.gapless()) {
+            return gapless;
+        }
+
+        let gaps: Vec<u32> = tracks
+            .windows(2)
+            .filter_map(|pair| {
+                let trailing = pair[0].trailing_silence_ms()?;
+                let leading = pair[1].leading_silence_ms()?;
+                Some(trailing + leading)
+            })
+            .collect();
+        !gaps.is_empty() && gaps.iter().all(|gap| *gap < CONTINUOUS_GAP_MS)
+    }
+
+    /// Whether moving from `from` to `to` should crossfade, assuming
+    /// crossfading is turned on at all.
+    pub fn should_crossfade(&self, from: &TrackId, to: &TrackId) -> bool {
+        let (Some(from_track), Some(to_track)) = (self.track(from), self.track(to)) else {
+            return true;
+        };
+        if from_track.is_same_work(to_track) {
+            return false;
+        }
+        !(from_track.is_same_album(to_track) && self.is_continuous_album(from))
+    }
+
+    // The per-album override from Get Info, `None` goes back to detecting it
+    pub fn set_album_gapless(&mut self, id: &TrackId, gapless: Option<bool>) {
+        let ids: Vec<TrackId> = self
+            .album_tracks(id)
+            .into_iter()
+            .map(|track| track.id().clone())
+            .collect();
+        self.edit_metadata(
+            &ids,
+            MetadataField::Gapless,
+            gapless.map(|gapless| gapless.to_string()),
+            ChangeSource::User,
+        );
+    }
+}
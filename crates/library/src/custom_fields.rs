@@ -0,0 +1,74 @@
+use crate::{Column, ColumnKind, Library, Track, TrackId};
+use std::collections::BTreeMap;
+
+// Names are told apart ignoring case, and a query's "key_signature" finds
+// a field named "Key Signature"
+fn same_name(a: &str, b: &str) -> bool {
+    a.replace('_', " ")
+        .eq_ignore_ascii_case(&b.replace('_', " "))
+}
+
+impl Track {
+    /// Fields made up for the library, such as "Key" or "Source", by name.
+    pub fn custom_fields(&self) -> &BTreeMap<String, String> {
+        &self.custom_fields
+    }
+
+    pub fn custom_field(&self, name: &str) -> Option<&str> {
+        self.custom_fields
+            .iter()
+            .find(|(other, _)| same_name(other, name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Library {
+    /// Every custom field some track has, alphabetically.
+    pub fn custom_field_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for name in self.tracks().flat_map(|track| track.custom_fields().keys()) {
+            if !names.iter().any(|other| same_name(other, name)) {
+                names.push(name.clone());
+            }
+        }
+        names.sort_by_key(|name| name.to_lowercase());
+        names
+    }
+
+    /// Sets a custom field on each track, `None` or an empty value takes it
+    /// off. A field new to the library gets a column, hidden until it's
+    /// picked from the column menu.
+    pub fn set_custom_field(&mut self, ids: &[TrackId], name: &str, value: Option<&str>) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        // Spelled the way it was first used
+        let name = self
+            .custom_field_names()
+            .into_iter()
+            .find(|other| same_name(other, name))
+            .unwrap_or_else(|| name.to_string());
+        let value = value.map(str::trim).filter(|value| !value.is_empty());
+
+        for id in ids {
+            let Some(track) = self.track_mut(id) else {
+                continue;
+            };
+            track
+                .custom_fields
+                .retain(|other, _| !same_name(other, &name));
+            if let Some(value) = value {
+                track.custom_fields.insert(name.clone(), value.to_string());
+            }
+        }
+
+        let kind = ColumnKind::Custom(name);
+        if value.is_some() && !self.columns.iter().any(|column| column.kind() == &kind) {
+            self.columns.push(Column {
+                enabled: false,
+                ..Column::new(kind)
+            });
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{Library, Track, TrackId};
+use gpui::SharedString;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+const MAX_MIXES: usize = 6;
+const MAX_ARTISTS_PER_MIX: usize = 6;
+const MIX_LENGTH: usize = 50;
+// Fewer than this and it's an album, not a mix
+const MIN_MIX_LENGTH: usize = 10;
+
+/// Kept out of every Daily Mix.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailyMixExclusions {
+    pub artists: Vec<String>,
+    // By track id
+    pub tracks: Vec<String>,
+}
+
+impl DailyMixExclusions {
+    pub fn excludes(&self, track: &Track) -> bool {
+        let id: String = track.id().clone().into();
+        self.tracks.contains(&id)
+            || self
+                .artists
+                .iter()
+                .any(|artist| artist.eq_ignore_ascii_case(&track.artist()))
+    }
+}
+
+/// A day's mix built around one of the most played artists, along with
+/// the artists that tend to turn up with them.
+#[derive(Debug, Clone)]
+pub struct DailyMix {
+    name: SharedString,
+    artists: Vec<SharedString>,
+    tracks: Vec<TrackId>,
+}
+
+impl DailyMix {
+    pub fn name(&self) -> SharedString {
+        self.name.clone()
+    }
+
+    pub fn artists(&self) -> &[SharedString] {
+        &self.artists
+    }
+
+    pub fn tracks(&self) -> &[TrackId] {
+        &self.tracks
+    }
+
+    // "Miles Davis, John Coltrane and more"
+    pub fn description(&self) -> String {
+        match self.artists.as_slice() {
+            [] => String::new(),
+            [artist] => artist.to_string(),
+            [first, second] => format!("{} and {}", first, second),
+            [first, second, ..] => format!("{}, {} and more", first, second),
+        }
+    }
+}
+
+// The same for a track all day, and different the next
+fn day_key(day: &str, id: &TrackId) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    day.hash(&mut hasher);
+    id.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Library {
+    /// How often each artist turns up alongside each other one. Artists
+    /// sharing a playlist or an album among `tracks` are taken to go
+    /// together.
+    pub(crate) fn artist_affinity(
+        &self,
+        tracks: &[&Track],
+    ) -> HashMap<SharedString, HashMap<SharedString, u32>> {
+        let mut albums: HashMap<(SharedString, SharedString), HashSet<SharedString>> =
+            HashMap::new();
+        for track in tracks {
+            albums
+                .entry((track.album(), track.album_artist()))
+                .or_default()
+                .insert(track.artist());
+        }
+        let playlists = self
+            .playlists()
+            .iter()
+            .filter(|playlist| !playlist.is_smart())
+            .map(|playlist| {
+                playlist
+                    .tracks()
+                    .iter()
+                    .filter_map(|id| self.track(id))
+                    .map(|track| track.artist())
+                    .collect::<HashSet<_>>()
+            });
+        let mut affinity: HashMap<SharedString, HashMap<SharedString, u32>> = HashMap::new();
+        for artists in albums.into_values().chain(playlists) {
+            for artist in &artists {
+                for other in &artists {
+                    if artist != other {
+                        *affinity
+                            .entry(artist.clone())
+                            .or_default()
+                            .entry(other.clone())
+                            .or_default() += 1;
+                    }
+                }
+            }
+        }
+        affinity
+    }
+
+    pub fn daily_mixes(&self) -> &[DailyMix] {
+        &self.daily_mixes
+    }
+
+    pub fn daily_mix(&self, ix: usize) -> Option<&DailyMix> {
+        self.daily_mixes.get(ix)
+    }
+
+    /// The day the current mixes were made for, as "2005-05-09". `None`
+    /// until there's been enough play history to make any.
+    pub fn daily_mix_day(&self) -> Option<&str> {
+        self.daily_mix_day.as_deref()
+    }
+
+    pub fn clear_daily_mixes(&mut self) {
+        self.daily_mixes.clear();
+        self.daily_mix_day = None;
+    }
+
+    /// Replaces the mixes with ones for `day`. Each is seeded from one of
+    /// the most played artists, joined by the artists that share playlists
+    /// and albums with them, and no artist is in more than one mix. Which
+    /// of their tracks make it in rotates from day to day, favouring the
+    /// ones played most.
+    pub fn generate_daily_mixes(&mut self, day: &str, exclusions: &DailyMixExclusions) {
+        let eligible: Vec<&Track> = self
+            .tracks()
+            .filter(|track| !exclusions.excludes(track))
+            .collect();
+
+        let mut plays: HashMap<SharedString, i64> = HashMap::new();
+        for track in &eligible {
+            *plays.entry(track.artist()).or_default() += track.plays().max(0) as i64;
+        }
+        let mut seeds: Vec<(SharedString, i64)> = plays
+            .iter()
+            .filter(|(_, plays)| **plays > 0)
+            .map(|(artist, plays)| (artist.clone(), *plays))
+            .collect();
+        seeds.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        if seeds.is_empty() {
+            self.clear_daily_mixes();
+            return;
+        }
+
+        let affinity = self.artist_affinity(&eligible);
+
+        let mut taken: HashSet<SharedString> = HashSet::new();
+        let mut mixes = Vec::new();
+        for (seed, _) in &seeds {
+            if mixes.len() == MAX_MIXES {
+                break;
+            }
+            if taken.contains(seed) {
+                continue;
+            }
+
+            let mut neighbours: Vec<(&SharedString, u32)> = affinity
+                .get(seed)
+                .into_iter()
+                .flatten()
+                .filter(|(artist, _)| !taken.contains(*artist) && plays.contains_key(*artist))
+                .map(|(artist, count)| (artist, *count))
+                .collect();
+            neighbours.sort_by(|a, b| {
+                b.1.cmp(&a.1)
+                    .then_with(|| plays[b.0].cmp(&plays[a.0]))
+                    .then_with(|| a.0.cmp(b.0))
+            });
+            let artists: Vec<SharedString> = std::iter::once(seed.clone())
+                .chain(
+                    neighbours
+                        .into_iter()
+                        .take(MAX_ARTISTS_PER_MIX - 1)
+                        .map(|(artist, _)| artist.clone()),
+                )
+                .collect();
+            taken.extend(artists.iter().cloned());
+
+            let mut tracks: Vec<(f64, &Track)> = eligible
+                .iter()
+                .filter(|track| artists.contains(&track.artist()))
+                .map(|track| {
+                    let roll = (day_key(day, track.id()) % 1000) as f64 / 1000.;
+                    (roll * (1. + (track.plays().max(0) as f64).ln_1p()), *track)
+                })
+                .collect();
+            if tracks.len() < MIN_MIX_LENGTH {
+                continue;
+            }
+            tracks.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+            mixes.push(DailyMix {
+                name: format!("Daily Mix {}", mixes.len() + 1).into(),
+                artists,
+                tracks: tracks
+                    .into_iter()
+                    .take(MIX_LENGTH)
+                    .map(|(_, track)| track.id().clone())
+                    .collect(),
+            });
+        }
+
+        // Nothing made counts as not done, so it's tried again as plays come in
+        self.daily_mix_day = (!mixes.is_empty()).then(|| day.to_string());
+        self.daily_mixes = mixes;
+    }
+}
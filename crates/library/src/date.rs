@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// How dates such as Date Added are shown. They're always stored as
+/// "2005-05-09".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DateFormat {
+    // 2005-05-09
+    #[default]
+    Iso,
+    // 5/9/05
+    Short,
+    // 9/5/05
+    ShortDayFirst,
+    // May 9, 2005
+    Long,
+}
+
+impl DateFormat {
+    pub fn format(&self, date: &str) -> String {
+        let Some((year, month, day)) = parse_date(date) else {
+            return date.to_string();
+        };
+
+        match self {
+            DateFormat::Iso => date.to_string(),
+            DateFormat::Short => format!("{}/{}/{:02}", month, day, year % 100),
+            DateFormat::ShortDayFirst => format!("{}/{}/{:02}", day, month, year % 100),
+            DateFormat::Long => format!("{} {}, {}", MONTHS[month as usize - 1], day, year),
+        }
+    }
+}
+
+fn parse_date(date: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = date.splitn(3, '-').map(|part| part.parse::<u32>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((year, month, day))
+}
+
+/// Reads a date the way tags have it, as "2005", "2005-05" or "2005-05-09",
+/// dropping any time of day after it. `None` if it doesn't start with a year.
+pub fn parse_release_date(value: &str) -> Option<String> {
+    let mut parts = value.trim().splitn(3, '-');
+    let year = parts.next()?;
+    if year.len() != 4 || !year.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut date = year.to_string();
+    for (part, valid) in parts.zip([1..=12, 1..=31]) {
+        let Some(number) = part
+            .get(..2)
+            .filter(|part| part.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|part| part.parse::<u32>().ok())
+            .filter(|number| valid.contains(number))
+        else {
+            break;
+        };
+        date.push_str(&format!("-{:02}", number));
+    }
+    Some(date)
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn today() -> String {
+    date_for_timestamp(unix_time())
+}
+
+// As "2005-05-09", in UTC
+pub fn date_for_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
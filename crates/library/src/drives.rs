@@ -0,0 +1,74 @@
+use crate::{Library, Track};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A library folder that's gone away or come back since it was last
+/// checked, such as when an external drive is unplugged.
+#[derive(Debug, Clone)]
+pub struct RootChange {
+    pub root: PathBuf,
+    pub online: bool,
+    // Tracks with files under the folder
+    pub tracks: usize,
+}
+
+// A mount point is left behind as an empty folder when its drive goes, so
+// an empty root counts as missing too
+fn is_reachable(root: &Path) -> bool {
+    fs::read_dir(root).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+impl Library {
+    /// Library folders that can't be reached right now. Their tracks stay
+    /// in the library, and play again as soon as the folder is back.
+    pub fn offline_roots(&self) -> &[PathBuf] {
+        &self.offline_roots
+    }
+
+    /// False for tracks whose file is under an offline library folder.
+    pub fn is_available(&self, track: &Track) -> bool {
+        track
+            .path()
+            .is_none_or(|path| !self.offline_roots.iter().any(|root| path.starts_with(root)))
+    }
+
+    fn tracks_under(&self, root: &Path) -> usize {
+        self.tracks()
+            .filter(|track| track.path().is_some_and(|path| path.starts_with(root)))
+            .count()
+    }
+
+    /// Looks for library folders that have gone or come back. A folder with
+    /// no tracks in it is never taken as offline, so one that's yet to be
+    /// made, like a fresh music folder, doesn't count.
+    pub fn check_roots(&mut self) -> Vec<RootChange> {
+        let mut changes = Vec::new();
+        for root in &self.roots {
+            let online = is_reachable(root);
+            let was_online = !self.offline_roots.contains(root);
+            if online == was_online {
+                continue;
+            }
+            let tracks = self.tracks_under(root);
+            if !online && tracks == 0 {
+                continue;
+            }
+            changes.push(RootChange {
+                root: root.clone(),
+                online,
+                tracks,
+            });
+        }
+
+        for change in &changes {
+            if change.online {
+                self.offline_roots.retain(|root| root != &change.root);
+            } else {
+                self.offline_roots.push(change.root.clone());
+            }
+        }
+        changes
+    }
+}
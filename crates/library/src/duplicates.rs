@@ -0,0 +1,240 @@
+use crate::{Library, Track, TrackId};
+use gpui::SharedString;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Another file of a track that's already in the library, such as the same
+/// album on an archive drive. The track plays whichever copy is preferred,
+/// and the rest are kept here so the choice can be changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlternateCopy {
+    pub track: TrackId,
+    pub path: PathBuf,
+    pub kind: String,
+    pub size: u64,
+    pub duration: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+/// One folder's copy of an album that's in more than one place.
+#[derive(Debug, Clone)]
+pub struct AlbumCopy {
+    pub folder: PathBuf,
+    // Position among the library roots, `None` when it's in none of them
+    pub root: Option<usize>,
+    pub tracks: usize,
+    pub lossless: bool,
+    pub bitrate: Option<u32>,
+    pub in_use: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateAlbum {
+    pub artist: SharedString,
+    pub album: SharedString,
+    // The copy in use first, then the rest as they'd be preferred
+    pub copies: Vec<AlbumCopy>,
+}
+
+// Better copies compare greater: lossless first, then the higher bitrate,
+// then whichever root comes first
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Preference {
+    lossless: bool,
+    bitrate: u32,
+    root: Reverse<usize>,
+}
+
+// Two files are the same track when they agree on everything but the file
+pub(crate) type CopyKey = (String, String, u32, String);
+
+pub(crate) fn copy_key(track: &Track) -> CopyKey {
+    (
+        track.album_artist().to_lowercase(),
+        track.album.to_lowercase(),
+        track.track_number,
+        track.title.to_lowercase(),
+    )
+}
+
+fn folder(path: Option<&Path>) -> Option<&Path> {
+    path.and_then(Path::parent)
+}
+
+fn swap_file(track: &mut Track, copy: &mut AlternateCopy) {
+    let Some(path) = track.path.as_mut() else {
+        return;
+    };
+    std::mem::swap(path, &mut copy.path);
+    std::mem::swap(&mut track.kind, &mut copy.kind);
+    std::mem::swap(&mut track.size, &mut copy.size);
+    std::mem::swap(&mut track.duration, &mut copy.duration);
+    std::mem::swap(&mut track.checksum, &mut copy.checksum);
+}
+
+impl Library {
+    /// The folders scanned into the library, in order of preference.
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub fn alternate_copies(&self) -> &[AlternateCopy] {
+        &self.alternate_copies
+    }
+
+    fn root_index(&self, path: &Path) -> Option<usize> {
+        self.roots.iter().position(|root| path.starts_with(root))
+    }
+
+    fn preference(&self, track: &Track) -> Preference {
+        Preference {
+            lossless: track.is_lossless(),
+            bitrate: track.estimated_bitrate().unwrap_or(0),
+            root: Reverse(
+                track
+                    .path()
+                    .and_then(|path| self.root_index(path))
+                    .unwrap_or(usize::MAX),
+            ),
+        }
+    }
+
+    fn copy_preference(&self, track: &Track, copy: &AlternateCopy) -> Preference {
+        let mut track = track.clone();
+        swap_file(&mut track, &mut copy.clone());
+        self.preference(&track)
+    }
+
+    // Tracks with files, by what they'd be matched on, for spotting a
+    // second copy as it's imported
+    pub(crate) fn copy_index(&self) -> HashMap<CopyKey, TrackId> {
+        self.tracks()
+            .filter(|track| track.path.is_some() && !track.is_placeholder())
+            .map(|track| (copy_key(track), track.id.clone()))
+            .collect()
+    }
+
+    /// Takes `track` as another copy of one already in the library when
+    /// there is one in a different folder, keeping the better file in use.
+    /// Returns the existing track's id if so.
+    pub(crate) fn add_as_copy(
+        &mut self,
+        track: &Track,
+        index: &HashMap<CopyKey, TrackId>,
+    ) -> Option<TrackId> {
+        let existing_id = index.get(&copy_key(track))?;
+        let existing = self.tracks.get(existing_id)?;
+        if folder(existing.path()) == folder(track.path()) {
+            return None;
+        }
+
+        let mut copy = AlternateCopy {
+            track: existing_id.clone(),
+            path: track.path.clone()?,
+            kind: track.kind.clone(),
+            size: track.size,
+            duration: track.duration,
+            checksum: None,
+        };
+        if self.preference(track) > self.preference(existing) {
+            if let Some(existing) = self.track_mut(existing_id) {
+                swap_file(existing, &mut copy);
+            }
+            self.pending_checksums.push(existing_id.clone());
+        }
+        self.alternate_copies.push(copy);
+        Some(existing_id.clone())
+    }
+
+    /// Albums with files in more than one folder, by artist and album.
+    pub fn duplicate_albums(&self) -> Vec<DuplicateAlbum> {
+        let mut albums: HashMap<(String, String), DuplicateAlbum> = HashMap::new();
+        let mut seen: HashMap<(String, String), Vec<TrackId>> = HashMap::new();
+        for copy in &self.alternate_copies {
+            let Some(track) = self.tracks.get(&copy.track) else {
+                continue;
+            };
+            let key = (
+                track.album_artist().to_lowercase(),
+                track.album.to_lowercase(),
+            );
+            let album = albums.entry(key.clone()).or_insert_with(|| DuplicateAlbum {
+                artist: track.album_artist(),
+                album: track.album(),
+                copies: Vec::new(),
+            });
+
+            let tracks = seen.entry(key).or_default();
+            if !tracks.contains(&track.id) {
+                tracks.push(track.id.clone());
+                self.count_copy(album, track.path(), self.preference(track), true);
+            }
+            let preference = self.copy_preference(track, copy);
+            self.count_copy(album, Some(&copy.path), preference, false);
+        }
+
+        let mut albums: Vec<DuplicateAlbum> = albums.into_values().collect();
+        for album in &mut albums {
+            album.copies.sort_by(|a, b| {
+                b.in_use
+                    .cmp(&a.in_use)
+                    .then(b.lossless.cmp(&a.lossless))
+                    .then(b.bitrate.cmp(&a.bitrate))
+                    .then(
+                        a.root
+                            .unwrap_or(usize::MAX)
+                            .cmp(&b.root.unwrap_or(usize::MAX)),
+                    )
+            });
+        }
+        albums.sort_by(|a, b| a.artist.cmp(&b.artist).then(a.album.cmp(&b.album)));
+        albums
+    }
+
+    fn count_copy(
+        &self,
+        album: &mut DuplicateAlbum,
+        path: Option<&Path>,
+        preference: Preference,
+        in_use: bool,
+    ) {
+        let Some(folder) = folder(path) else {
+            return;
+        };
+        let bitrate = (preference.bitrate > 0).then_some(preference.bitrate);
+        match album.copies.iter_mut().find(|copy| copy.folder == folder) {
+            Some(copy) => {
+                copy.tracks += 1;
+                copy.in_use |= in_use;
+                copy.lossless &= preference.lossless;
+                copy.bitrate = copy.bitrate.min(bitrate);
+            }
+            None => album.copies.push(AlbumCopy {
+                folder: folder.to_path_buf(),
+                root: self.root_index(folder),
+                tracks: 1,
+                lossless: preference.lossless,
+                bitrate,
+                in_use,
+            }),
+        }
+    }
+
+    /// Plays the files in `folder` for every track that has a copy there.
+    pub fn use_copies_in(&mut self, folder: &Path) {
+        for copy in &mut self.alternate_copies {
+            if copy.path.parent() != Some(folder) {
+                continue;
+            }
+            if let Some(track) = self.tracks.get_mut(&copy.track) {
+                swap_file(track, copy);
+                self.changed_tracks.insert(copy.track.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use crate::{audio_files_in, cover_artwork, format_size, is_artwork_file, ArtworkStore};
+use id3::TagLike;
+use image::{imageops::FilterType, ImageFormat};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Cursor, Read, Write},
+    path::{Path, PathBuf},
+};
+
+const FLAC_PICTURE: u8 = 6;
+const FRONT_COVER: u32 = 3;
+
+/// A picture stored in an audio file's tags.
+#[derive(Debug, Clone)]
+pub struct EmbeddedImage {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl EmbeddedImage {
+    pub fn extension(&self) -> &'static str {
+        if self.mime_type.eq_ignore_ascii_case("image/png") {
+            "png"
+        } else {
+            "jpg"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArtworkExportOptions {
+    // Remove the images from the files once their folder has a cover on disk
+    pub strip: bool,
+    // Work out what would happen without touching anything
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArtworkExportReport {
+    pub files_scanned: usize,
+    pub files_with_artwork: usize,
+    pub exported: Vec<PathBuf>,
+    pub stripped: usize,
+    pub reclaimable_bytes: u64,
+    pub failed: usize,
+}
+
+impl ArtworkExportReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} of {} files have embedded artwork, {} folder images exported, {} files stripped, {} reclaimable",
+            self.files_with_artwork,
+            self.files_scanned,
+            self.exported.len(),
+            self.stripped,
+            format_size(self.reclaimable_bytes),
+        )
+    }
+}
+
+/// Writes the embedded cover of each album folder under `folder` out to a
+/// folder.jpg next to its tracks, optionally stripping the embedded copies.
+///
+/// Folders that already have a cover keep it. Files are only stripped once
+/// their folder has a cover, so no artwork is ever lost.
+pub fn export_embedded_artwork(
+    folder: &Path,
+    options: ArtworkExportOptions,
+) -> ArtworkExportReport {
+    let mut report = ArtworkExportReport::default();
+
+    let mut album_folders: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for path in audio_files_in(folder) {
+        let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        album_folders.entry(parent).or_default().push(path);
+    }
+
+    for (album_folder, files) in album_folders {
+        let mut cover = existing_cover(&album_folder);
+
+        for path in files {
+            report.files_scanned += 1;
+            let images = match read_embedded_artwork(&path) {
+                Ok(images) => images,
+                Err(e) => {
+                    eprintln!("Failed to read artwork from {}: {}", path.display(), e);
+                    report.failed += 1;
+                    continue;
+                }
+            };
+            let Some(front) = images.first() else {
+                continue;
+            };
+            report.files_with_artwork += 1;
+
+            if cover.is_none() {
+                let destination = album_folder.join(format!("folder.{}", front.extension()));
+                if !options.dry_run {
+                    if let Err(e) = fs::write(&destination, &front.data) {
+                        eprintln!("Failed to write {}: {}", destination.display(), e);
+                        report.failed += 1;
+                        continue;
+                    }
+                }
+                report.exported.push(destination.clone());
+                cover = Some(destination);
+            }
+
+            report.reclaimable_bytes += images
+                .iter()
+                .map(|image| image.data.len() as u64)
+                .sum::<u64>();
+            if options.strip {
+                if !options.dry_run {
+                    if let Err(e) = strip_embedded_artwork(&path) {
+                        eprintln!("Failed to strip artwork from {}: {}", path.display(), e);
+                        report.failed += 1;
+                        continue;
+                    }
+                }
+                report.stripped += 1;
+            }
+        }
+    }
+
+    report
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ArtworkEmbedOptions {
+    // Covers larger than this on either side are scaled down to fit
+    pub max_size: Option<u32>,
+    // Covers in any other format are converted to JPEG
+    pub as_jpeg: bool,
+    // Work out what would happen without touching anything
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArtworkEmbedReport {
+    pub files_scanned: usize,
+    pub albums: usize,
+    // Each file embedded into, with its artwork's shared copy in the store
+    pub embedded: Vec<(PathBuf, PathBuf)>,
+    pub failed: usize,
+}
+
+impl ArtworkEmbedReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} of {} files given their folder's cover, across {} albums, {} failed",
+            self.embedded.len(),
+            self.files_scanned,
+            self.albums,
+            self.failed,
+        )
+    }
+}
+
+/// Embeds the cover image of each album folder under `folder`, such as a
+/// cover.jpg or folder.png next to its tracks, into the MP3 and FLAC files
+/// that have no embedded artwork yet. The cover is also kept in `store`.
+pub fn embed_folder_artwork(
+    folder: &Path,
+    store: &ArtworkStore,
+    options: ArtworkEmbedOptions,
+) -> ArtworkEmbedReport {
+    let mut report = ArtworkEmbedReport::default();
+
+    let mut album_folders: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for path in audio_files_in(folder) {
+        let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        album_folders.entry(parent).or_default().push(path);
+    }
+
+    for (album_folder, files) in album_folders {
+        let Some(cover_path) = existing_cover(&album_folder) else {
+            continue;
+        };
+
+        let mut bare = Vec::new();
+        for path in files {
+            report.files_scanned += 1;
+            if !matches!(extension(&path).as_str(), "mp3" | "flac") {
+                continue;
+            }
+            match read_embedded_artwork(&path) {
+                Ok(images) if images.is_empty() => bare.push(path),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Failed to read artwork from {}: {}", path.display(), e);
+                    report.failed += 1;
+                }
+            }
+        }
+        if bare.is_empty() {
+            continue;
+        }
+
+        let cover = match prepare_cover(&cover_path, options) {
+            Ok(cover) => cover,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", cover_path.display(), e);
+                report.failed += bare.len();
+                continue;
+            }
+        };
+        let stored = if options.dry_run {
+            cover_path.clone()
+        } else {
+            match store.store_bytes(&cover.image.data, Some(cover.image.extension())) {
+                Ok(stored) => stored,
+                Err(e) => {
+                    eprintln!("Failed to store artwork {}: {}", cover_path.display(), e);
+                    report.failed += bare.len();
+                    continue;
+                }
+            }
+        };
+
+        report.albums += 1;
+        for path in bare {
+            if !options.dry_run {
+                if let Err(e) = embed_artwork(&path, &cover) {
+                    eprintln!("Failed to embed artwork into {}: {}", path.display(), e);
+                    report.failed += 1;
+                    continue;
+                }
+            }
+            report.embedded.push((path, stored.clone()));
+        }
+    }
+
+    report
+}
+
+// A cover ready to embed, with the dimensions FLAC wants alongside it
+struct Cover {
+    image: EmbeddedImage,
+    width: u32,
+    height: u32,
+}
+
+// Left byte for byte as it is unless it has to be scaled or converted
+fn prepare_cover(path: &Path, options: ArtworkEmbedOptions) -> anyhow::Result<Cover> {
+    let data = fs::read(path)?;
+    let format = image::guess_format(&data)?;
+    let mut image = image::load_from_memory_with_format(&data, format)?;
+
+    let too_large = options
+        .max_size
+        .is_some_and(|max_size| image.width().max(image.height()) > max_size);
+    let convert = options.as_jpeg && format != ImageFormat::Jpeg;
+    if !too_large && !convert {
+        let mime_type = match format {
+            ImageFormat::Png => "image/png",
+            _ => "image/jpeg",
+        };
+        return Ok(Cover {
+            width: image.width(),
+            height: image.height(),
+            image: EmbeddedImage {
+                mime_type: mime_type.to_string(),
+                data,
+            },
+        });
+    }
+
+    if let Some(max_size) = options.max_size.filter(|_| too_large) {
+        image = image.resize(max_size, max_size, FilterType::Lanczos3);
+    }
+    let (format, mime_type) = if options.as_jpeg || format == ImageFormat::Jpeg {
+        // JPEG has no alpha channel
+        image = image.to_rgb8().into();
+        (ImageFormat::Jpeg, "image/jpeg")
+    } else {
+        (ImageFormat::Png, "image/png")
+    };
+    let mut data = Cursor::new(Vec::new());
+    image.write_to(&mut data, format)?;
+
+    Ok(Cover {
+        width: image.width(),
+        height: image.height(),
+        image: EmbeddedImage {
+            mime_type: mime_type.to_string(),
+            data: data.into_inner(),
+        },
+    })
+}
+
+fn embed_artwork(path: &Path, cover: &Cover) -> anyhow::Result<()> {
+    match extension(path).as_str() {
+        "mp3" => {
+            let existing = id3::no_tag_ok(id3::Tag::read_from_path(path))?;
+            let version = existing
+                .as_ref()
+                .map_or(id3::Version::Id3v24, |tag| tag.version());
+            let mut tag = existing.unwrap_or_default();
+            tag.add_frame(id3::frame::Picture {
+                mime_type: cover.image.mime_type.clone(),
+                picture_type: id3::frame::PictureType::CoverFront,
+                description: String::new(),
+                data: cover.image.data.clone(),
+            });
+            tag.write_to_path(path, version)?;
+        }
+        "flac" => rewrite_flac_blocks(path, |blocks| {
+            blocks.push(FlacBlock {
+                kind: FLAC_PICTURE,
+                data: flac_picture(cover),
+            })
+        })?,
+        _ => {}
+    }
+    Ok(())
+}
+
+pub(crate) fn existing_cover(folder: &Path) -> Option<PathBuf> {
+    let artwork_files: Vec<PathBuf> = fs::read_dir(folder)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_artwork_file(path))
+        .collect();
+    cover_artwork(&artwork_files)
+}
+
+/// Every picture embedded in an MP3 or FLAC file, front cover first. Other
+/// formats have no embedded artwork as far as this is concerned.
+pub fn read_embedded_artwork(path: &Path) -> anyhow::Result<Vec<EmbeddedImage>> {
+    let mut pictures = match extension(path).as_str() {
+        "mp3" => {
+            let Some(tag) = id3::no_tag_ok(id3::Tag::read_from_path(path))? else {
+                return Ok(Vec::new());
+            };
+            tag.pictures()
+                .map(|picture| {
+                    let picture_type: u8 = picture.picture_type.into();
+                    (
+                        picture_type as u32,
+                        EmbeddedImage {
+                            mime_type: picture.mime_type.clone(),
+                            data: picture.data.clone(),
+                        },
+                    )
+                })
+                .collect()
+        }
+        "flac" => {
+            let mut reader = BufReader::new(File::open(path)?);
+            read_flac_blocks(&mut reader)?
+                .iter()
+                .filter(|block| block.kind == FLAC_PICTURE)
+                .filter_map(|block| parse_flac_picture(&block.data))
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    pictures.sort_by_key(|(picture_type, _)| *picture_type != FRONT_COVER);
+    Ok(pictures.into_iter().map(|(_, image)| image).collect())
+}
+
+pub fn strip_embedded_artwork(path: &Path) -> anyhow::Result<()> {
+    match extension(path).as_str() {
+        "mp3" => {
+            let Some(mut tag) = id3::no_tag_ok(id3::Tag::read_from_path(path))? else {
+                return Ok(());
+            };
+            tag.remove_all_pictures();
+            tag.write_to_path(path, tag.version())?;
+        }
+        "flac" => strip_flac_pictures(path)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+struct FlacBlock {
+    kind: u8,
+    data: Vec<u8>,
+}
+
+// Leaves `reader` at the first audio frame
+fn read_flac_blocks(reader: &mut impl Read) -> anyhow::Result<Vec<FlacBlock>> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    anyhow::ensure!(&magic == b"fLaC", "not a FLAC file");
+
+    let mut blocks = Vec::new();
+    loop {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let mut data = vec![0; len];
+        reader.read_exact(&mut data)?;
+        blocks.push(FlacBlock {
+            kind: header[0] & 0x7f,
+            data,
+        });
+        if header[0] & 0x80 != 0 {
+            return Ok(blocks);
+        }
+    }
+}
+
+fn parse_flac_picture(data: &[u8]) -> Option<(u32, EmbeddedImage)> {
+    fn take<'a>(cursor: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        if cursor.len() < len {
+            return None;
+        }
+        let (head, tail) = cursor.split_at(len);
+        *cursor = tail;
+        Some(head)
+    }
+    fn take_u32(cursor: &mut &[u8]) -> Option<u32> {
+        Some(u32::from_be_bytes(take(cursor, 4)?.try_into().ok()?))
+    }
+
+    let mut cursor = data;
+    let picture_type = take_u32(&mut cursor)?;
+    let mime_len = take_u32(&mut cursor)? as usize;
+    let mime_type = String::from_utf8_lossy(take(&mut cursor, mime_len)?).to_string();
+    let description_len = take_u32(&mut cursor)? as usize;
+    take(&mut cursor, description_len)?;
+    // Width, height, color depth and palette size
+    take(&mut cursor, 16)?;
+    let data_len = take_u32(&mut cursor)? as usize;
+    let data = take(&mut cursor, data_len)?.to_vec();
+
+    Some((picture_type, EmbeddedImage { mime_type, data }))
+}
+
+fn flac_picture(cover: &Cover) -> Vec<u8> {
+    let mime_type = cover.image.mime_type.as_bytes();
+    let mut data = Vec::new();
+    data.extend_from_slice(&FRONT_COVER.to_be_bytes());
+    data.extend_from_slice(&(mime_type.len() as u32).to_be_bytes());
+    data.extend_from_slice(mime_type);
+    // No description
+    data.extend_from_slice(&0u32.to_be_bytes());
+    data.extend_from_slice(&cover.width.to_be_bytes());
+    data.extend_from_slice(&cover.height.to_be_bytes());
+    // Color depth, and no palette
+    data.extend_from_slice(&24u32.to_be_bytes());
+    data.extend_from_slice(&0u32.to_be_bytes());
+    data.extend_from_slice(&(cover.image.data.len() as u32).to_be_bytes());
+    data.extend_from_slice(&cover.image.data);
+    data
+}
+
+fn strip_flac_pictures(path: &Path) -> anyhow::Result<()> {
+    rewrite_flac_blocks(path, |blocks| {
+        blocks.retain(|block| block.kind != FLAC_PICTURE)
+    })
+}
+
+// Rewrites the metadata blocks in front of the audio, leaving the audio be
+fn rewrite_flac_blocks(path: &Path, edit: impl FnOnce(&mut Vec<FlacBlock>)) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut blocks = read_flac_blocks(&mut reader)?;
+    edit(&mut blocks);
+
+    // Write alongside and swap in, so a failure never leaves a half-written file
+    let temp_path = path.with_extension("flac.tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    writer.write_all(b"fLaC")?;
+    for (ix, block) in blocks.iter().enumerate() {
+        let last = if ix + 1 == blocks.len() { 0x80 } else { 0 };
+        let len = (block.data.len() as u32).to_be_bytes();
+        writer.write_all(&[block.kind | last, len[1], len[2], len[3]])?;
+        writer.write_all(&block.data)?;
+    }
+    io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    drop(writer);
+
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use crate::{MetadataField, MusicalKey, Track};
+
+/// A browser selection pinned above the track list, such as Artist = Miles
+/// Davis. It stays applied across sources until it's cleared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackFilter {
+    pub field: MetadataField,
+    pub value: String,
+}
+
+impl TrackFilter {
+    pub fn new(field: MetadataField, value: impl Into<String>) -> Self {
+        TrackFilter {
+            field,
+            value: value.into(),
+        }
+    }
+
+    pub fn matches(&self, track: &Track) -> bool {
+        match self.field {
+            // A track with several moods is under each of them
+            MetadataField::Mood => track.has_mood(&self.value),
+            // Takes in every key that mixes with it, for harmonic mixing
+            MetadataField::Key => {
+                let key = MusicalKey::parse(&self.value);
+                track
+                    .key()
+                    .zip(key)
+                    .is_some_and(|(track_key, key)| track_key.is_compatible(&key))
+            }
+            field => track.metadata(field).as_deref() == Some(self.value.as_str()),
+        }
+    }
+
+    // "Artist: Miles Davis", or "Mixes with 8A"
+    pub fn label(&self) -> String {
+        match self.field {
+            MetadataField::Key => format!("Mixes with {}", self.value),
+            field => format!("{}: {}", field.name(), self.value),
+        }
+    }
+}
+
+/// Filters on the same field widen the selection, like picking several
+/// entries in one browser column. Filters on different fields narrow it.
+pub fn matches_filters(track: &Track, filters: &[TrackFilter]) -> bool {
+    filters.iter().all(|filter| {
+        filters
+            .iter()
+            .filter(|other| other.field == filter.field)
+            .any(|other| other.matches(track))
+    })
+}
@@ -0,0 +1,348 @@
+use crate::{copy_key, today, Library, MusicalKey, SerializableTrack, Track, TrackId};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "m4a", "aac", "flac", "alac", "wav", "aiff", "aif", "ogg", "opus",
+];
+pub const ARTWORK_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+
+pub fn is_audio_file(path: &Path) -> bool {
+    has_extension(path, AUDIO_EXTENSIONS)
+}
+
+pub fn is_artwork_file(path: &Path) -> bool {
+    has_extension(path, ARTWORK_EXTENSIONS)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| extensions.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub fn kind_for_path(path: &Path) -> String {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "mp3" => "MPEG audio file",
+        "m4a" | "aac" | "alac" => "AAC audio file",
+        "flac" => "FLAC audio file",
+        "wav" => "WAV audio file",
+        "aiff" | "aif" => "AIFF audio file",
+        "ogg" | "opus" => "Ogg audio file",
+        _ => "Audio file",
+    }
+    .to_string()
+}
+
+/// Works out the kind from the start of the file, so a FLAC saved as
+/// ".mp3" still shows as FLAC. Falls back to the extension when the file
+/// can't be read or isn't recognised.
+pub fn kind_for_file(path: &Path) -> String {
+    let mut header = [0u8; 12];
+    let read = File::open(path).and_then(|mut file| file.read_exact(&mut header));
+    if read.is_err() {
+        return kind_for_path(path);
+    }
+
+    let kind = match header {
+        [b'f', b'L', b'a', b'C', ..] => "FLAC audio file",
+        [b'O', b'g', b'g', b'S', ..] => "Ogg audio file",
+        [b'I', b'D', b'3', ..] => "MPEG audio file",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E'] => "WAV audio file",
+        [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', b'F' | b'C'] => "AIFF audio file",
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => "AAC audio file",
+        // A frame sync, where a layer of 0 means an ADTS AAC stream
+        [0xFF, second, ..] if second & 0xE0 == 0xE0 => {
+            if second & 0x06 == 0 {
+                "AAC audio file"
+            } else {
+                "MPEG audio file"
+            }
+        }
+        _ => return kind_for_path(path),
+    };
+    kind.to_string()
+}
+
+/// A file that is ready to be added to the library, with whatever tags
+/// could be worked out before its metadata is read.
+#[derive(Debug, Clone)]
+pub struct ImportedFile {
+    pub path: PathBuf,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+    pub release_date: Option<String>,
+    pub original_release_date: Option<String>,
+    pub language: Option<String>,
+    pub moods: Vec<String>,
+    pub key: Option<MusicalKey>,
+    pub track_number: u32,
+    // As tagged, otherwise worked out from the files imported with it
+    pub total_tracks: Option<u32>,
+    pub disc_number: u32,
+    // In seconds, 0 until the file has been read
+    pub duration: i32,
+    pub artwork: Option<PathBuf>,
+    // Leading and trailing, in milliseconds, once the file has been listened to
+    pub silence: Option<(u32, u32)>,
+}
+
+impl ImportedFile {
+    // Guesses tags from names like "03 Song Title.flac" inside an album folder
+    pub fn from_path(path: PathBuf, artist: &str, album: &str) -> Self {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (track_number, title) = split_track_number(&stem);
+
+        ImportedFile {
+            path,
+            title: title.to_string(),
+            artist: artist.to_string(),
+            album: album.to_string(),
+            album_artist: None,
+            genre: None,
+            year: None,
+            release_date: None,
+            original_release_date: None,
+            language: None,
+            moods: Vec::new(),
+            key: None,
+            track_number,
+            total_tracks: None,
+            disc_number: 0,
+            duration: 0,
+            artwork: None,
+            silence: None,
+        }
+    }
+}
+
+pub fn split_track_number(stem: &str) -> (u32, &str) {
+    let digits = stem.chars().take_while(|c| c.is_ascii_digit()).count();
+    let Ok(track_number) = stem[..digits].parse() else {
+        return (0, stem.trim());
+    };
+
+    let title = stem[digits..].trim_start_matches([' ', '-', '.', '_']);
+    if title.is_empty() {
+        (track_number, stem.trim())
+    } else {
+        (track_number, title.trim())
+    }
+}
+
+pub struct ExtractedArchive {
+    pub folder: PathBuf,
+    pub audio_files: Vec<PathBuf>,
+    pub artwork_files: Vec<PathBuf>,
+}
+
+impl ExtractedArchive {
+    pub fn cover(&self) -> Option<PathBuf> {
+        cover_artwork(&self.artwork_files)
+    }
+}
+
+/// Picks the front cover out of a release's images, preferring the usual
+/// "cover" and "folder" names over whatever else came with it.
+pub fn cover_artwork(artwork_files: &[PathBuf]) -> Option<PathBuf> {
+    const COVER_NAMES: &[&str] = &["cover", "folder", "front"];
+
+    artwork_files
+        .iter()
+        .find(|path| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_lowercase())
+                .is_some_and(|stem| COVER_NAMES.contains(&stem.as_str()))
+        })
+        .or_else(|| artwork_files.first())
+        .cloned()
+}
+
+/// Unpacks the music and artwork in a zip (such as a Bandcamp download) into
+/// its own folder under `music_folder`, skipping everything else.
+pub fn extract_archive(
+    archive_path: &Path,
+    music_folder: &Path,
+) -> anyhow::Result<ExtractedArchive> {
+    let name = archive_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Imported".to_string());
+    let folder = unique_folder(&music_folder.join(name));
+
+    let mut archive = zip::ZipArchive::new(File::open(archive_path)?)?;
+    let mut audio_files = Vec::new();
+    let mut artwork_files = Vec::new();
+
+    for ix in 0..archive.len() {
+        let mut entry = archive.by_index(ix)?;
+        // Rejects absolute paths and `..` so an archive can't write outside the folder
+        let Some(relative_path) = entry.enclosed_name() else {
+            continue;
+        };
+        if entry.is_dir() || relative_path.starts_with("__MACOSX") {
+            continue;
+        }
+
+        let is_audio = is_audio_file(&relative_path);
+        if !is_audio && !is_artwork_file(&relative_path) {
+            continue;
+        }
+
+        let destination = folder.join(&relative_path);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut entry, &mut File::create(&destination)?)?;
+
+        if is_audio {
+            audio_files.push(destination);
+        } else {
+            artwork_files.push(destination);
+        }
+    }
+
+    audio_files.sort();
+    Ok(ExtractedArchive {
+        folder,
+        audio_files,
+        artwork_files,
+    })
+}
+
+pub(crate) fn unique_folder(folder: &Path) -> PathBuf {
+    let mut candidate = folder.to_path_buf();
+    let mut suffix = 2;
+    while candidate.exists() {
+        candidate = PathBuf::from(format!("{} {}", folder.display(), suffix));
+        suffix += 1;
+    }
+    candidate
+}
+
+/// Every audio file under `folder`, in path order.
+pub fn audio_files_in(folder: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut folders = vec![folder.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        let Ok(entries) = fs::read_dir(&folder) else {
+            continue;
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                folders.push(path);
+            } else if is_audio_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+impl ImportedFile {
+    pub(crate) fn into_track(self, date_added: String, total_tracks: u32) -> Track {
+        Track::from(SerializableTrack {
+            id: None,
+            kind: kind_for_file(&self.path),
+            size: fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0),
+            title: self.title,
+            artist: self.artist,
+            album: self.album,
+            duration: self.duration,
+            date_added,
+            plays: 0,
+            track_number: self.track_number,
+            total_tracks: self.total_tracks.unwrap_or(total_tracks),
+            disc_number: self.disc_number,
+            grouping: None,
+            genre: self.genre,
+            album_artist: self.album_artist,
+            year: self.year,
+            release_date: self.release_date,
+            original_release_date: self.original_release_date,
+            language: self.language,
+            moods: self.moods,
+            key: self.key.map(|key| key.camelot()),
+            custom_fields: BTreeMap::new(),
+            musicbrainz_recording_id: None,
+            musicbrainz_release_id: None,
+            last_played: None,
+            artwork: self.artwork,
+            gapless: None,
+            leading_silence_ms: self.silence.map(|(leading, _)| leading),
+            trailing_silence_ms: self.silence.map(|(_, trailing)| trailing),
+            rating: None,
+            album_rating: None,
+            version_group: None,
+            version: None,
+            path: Some(self.path),
+            checksum: None,
+        })
+    }
+}
+
+impl Track {
+    /// A track that plays straight from disk without being added to the
+    /// library, for auditioning files before importing them.
+    pub fn external(file: ImportedFile) -> Self {
+        let mut track = file.into_track(today(), 0);
+        track.external = true;
+        track
+    }
+}
+
+impl Library {
+    pub fn add_imported(&mut self, files: Vec<ImportedFile>) -> Vec<TrackId> {
+        // Files without a tagged total are counted up by album, as a scan
+        // reads several albums at once
+        let mut album_sizes: HashMap<(String, String), u32> = HashMap::new();
+        for file in &files {
+            *album_sizes
+                .entry((file.artist.clone(), file.album.clone()))
+                .or_default() += 1;
+        }
+        let date_added = today();
+
+        let mut copies = self.copy_index();
+        let ids: Vec<TrackId> = files
+            .into_iter()
+            .filter_map(|file| {
+                let total_tracks = album_sizes[&(file.artist.clone(), file.album.clone())];
+                let track = file.into_track(date_added.clone(), total_tracks);
+                // Another copy of a track that's already here isn't added
+                // again, the library keeps the better of the two in use
+                if self.add_as_copy(&track, &copies).is_some() {
+                    return None;
+                }
+                let id = track.id.clone();
+                copies.insert(copy_key(&track), id.clone());
+                self.tracks.insert(id.clone(), track);
+                self.track_order.push(id.clone());
+                Some(id)
+            })
+            .collect();
+        self.fulfill_wishes(&ids);
+        self.pending_checksums.extend(ids.iter().cloned());
+        self.pending_artwork.extend(ids.iter().cloned());
+        ids
+    }
+}
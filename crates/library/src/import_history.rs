@@ -0,0 +1,139 @@
+use crate::{unix_time, Library, TrackId};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const MAX_IMPORT_SESSIONS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportKind {
+    Archive,
+    Purchase,
+    // Dropped on the window, and added from where they are
+    Files,
+    Recording,
+}
+
+impl ImportKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportKind::Archive => "Archive",
+            ImportKind::Purchase => "Purchase",
+            ImportKind::Files => "Added Files",
+            ImportKind::Recording => "Recording",
+        }
+    }
+}
+
+/// Everything one import added, so it can be taken out again as a whole.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSession {
+    pub id: u64,
+    pub kind: ImportKind,
+    // Seconds since the Unix epoch
+    pub imported_at: u64,
+    pub tracks: Vec<TrackId>,
+    // What the import put into the music folder, as opposed to files added
+    // where they already were. Folders for archives and purchases, files
+    // for recordings.
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    #[serde(default)]
+    pub rolled_back: bool,
+}
+
+impl Library {
+    /// Oldest first.
+    pub fn import_sessions(&self) -> &[ImportSession] {
+        &self.import_sessions
+    }
+
+    /// The most recent import that hasn't been rolled back.
+    pub fn last_import(&self) -> Option<&ImportSession> {
+        self.import_sessions
+            .iter()
+            .rev()
+            .find(|session| !session.rolled_back)
+    }
+
+    /// How many of the session's tracks are still in the library.
+    pub fn import_tracks_remaining(&self, session: &ImportSession) -> usize {
+        session
+            .tracks
+            .iter()
+            .filter(|id| self.tracks.contains_key(*id))
+            .count()
+    }
+
+    pub fn record_import(&mut self, kind: ImportKind, tracks: Vec<TrackId>, files: Vec<PathBuf>) {
+        if tracks.is_empty() {
+            return;
+        }
+        let id = self
+            .import_sessions
+            .last()
+            .map_or(0, |session| session.id + 1);
+        self.import_sessions.push(ImportSession {
+            id,
+            kind,
+            imported_at: unix_time(),
+            tracks,
+            files,
+            rolled_back: false,
+        });
+        if self.import_sessions.len() > MAX_IMPORT_SESSIONS {
+            self.import_sessions
+                .drain(..self.import_sessions.len() - MAX_IMPORT_SESSIONS);
+        }
+    }
+
+    /// Takes every track the import added out of the library and its
+    /// playlists for good, skipping Recently Deleted. Gives back what the
+    /// import put into the music folder, for deleting with
+    /// `remove_imported_files` if wanted.
+    pub fn roll_back_import(&mut self, id: u64) -> Option<Vec<PathBuf>> {
+        let session = self
+            .import_sessions
+            .iter_mut()
+            .find(|session| session.id == id && !session.rolled_back)?;
+        session.rolled_back = true;
+        let tracks = session.tracks.clone();
+        let files = session.files.clone();
+
+        self.delete_tracks(&tracks);
+        self.recently_deleted
+            .retain(|deleted| !tracks.contains(deleted.track().id()));
+        self.pending_checksums.retain(|id| !tracks.contains(id));
+        Some(files)
+    }
+}
+
+/// Deletes files and folders an import put into the music folder, giving
+/// back how many couldn't be.
+pub fn remove_imported_files(files: &[PathBuf]) -> usize {
+    files
+        .iter()
+        .filter(|path| {
+            let removed = remove_path(path);
+            if let Err(e) = &removed {
+                eprintln!("Failed to remove {}: {}", path.display(), e);
+            }
+            removed.is_err()
+        })
+        .count()
+}
+
+// Already gone counts as removed
+fn remove_path(path: &Path) -> io::Result<()> {
+    let result = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
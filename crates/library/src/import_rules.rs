@@ -0,0 +1,348 @@
+use crate::{ChangeSource, ImportedFile, Library, MetadataField, TrackId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What an import rule can look at. Rules run before a file becomes a
+/// track, so they see its path as well as its tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportRuleField {
+    Path,
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Year,
+}
+
+impl ImportRuleField {
+    const ALL: [ImportRuleField; 6] = [
+        ImportRuleField::Path,
+        ImportRuleField::Title,
+        ImportRuleField::Artist,
+        ImportRuleField::Album,
+        ImportRuleField::AlbumArtist,
+        ImportRuleField::Year,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            ImportRuleField::Path => "path",
+            ImportRuleField::Title => "title",
+            ImportRuleField::Artist => "artist",
+            ImportRuleField::Album => "album",
+            ImportRuleField::AlbumArtist => "album_artist",
+            ImportRuleField::Year => "year",
+        }
+    }
+
+    fn value(&self, file: &ImportedFile) -> String {
+        match self {
+            // Forward slashes on every platform, so one rule works everywhere
+            ImportRuleField::Path => file.path.to_string_lossy().replace('\\', "/"),
+            ImportRuleField::Title => file.title.clone(),
+            ImportRuleField::Artist => file.artist.clone(),
+            ImportRuleField::Album => file.album.clone(),
+            ImportRuleField::AlbumArtist => file.album_artist.clone().unwrap_or_default(),
+            ImportRuleField::Year => file.year.map(|year| year.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextMatch {
+    Is,
+    Contains,
+    StartsWith,
+}
+
+impl TextMatch {
+    fn name(&self) -> &'static str {
+        match self {
+            TextMatch::Is => "is",
+            TextMatch::Contains => "contains",
+            TextMatch::StartsWith => "starts with",
+        }
+    }
+}
+
+/// One test against a file, ignoring case.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportCondition {
+    pub field: ImportRuleField,
+    pub matching: TextMatch,
+    pub text: String,
+}
+
+impl ImportCondition {
+    pub fn matches(&self, file: &ImportedFile) -> bool {
+        let value = self.field.value(file).to_lowercase();
+        let text = self.text.to_lowercase();
+        match self.matching {
+            TextMatch::Is => value == text,
+            TextMatch::Contains => value.contains(&text),
+            TextMatch::StartsWith => value.starts_with(&text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ImportAction {
+    // `None` clears the field
+    SetField {
+        field: MetadataField,
+        value: Option<String>,
+    },
+    // Created if there's no playlist by that name yet
+    AddToPlaylist {
+        playlist: String,
+    },
+}
+
+impl ImportAction {
+    // "Set Grouping to “Live”"
+    pub fn description(&self) -> String {
+        match self {
+            ImportAction::SetField {
+                field,
+                value: Some(value),
+            } => format!("Set {} to “{}”", field.name(), value),
+            ImportAction::SetField { field, value: None } => format!("Clear {}", field.name()),
+            ImportAction::AddToPlaylist { playlist } => format!("Add to “{}”", playlist),
+        }
+    }
+}
+
+/// Carried out on every imported file matching all of its conditions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportRule {
+    pub enabled: bool,
+    pub conditions: Vec<ImportCondition>,
+    pub actions: Vec<ImportAction>,
+}
+
+impl Default for ImportRule {
+    fn default() -> Self {
+        ImportRule {
+            enabled: true,
+            conditions: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+}
+
+impl ImportRule {
+    // A rule without conditions would apply to everything, which is never
+    // what a half-written rule means
+    pub fn matches(&self, file: &ImportedFile) -> bool {
+        self.enabled
+            && !self.conditions.is_empty()
+            && self
+                .conditions
+                .iter()
+                .all(|condition| condition.matches(file))
+    }
+
+    /// Parses a rule written the way `description` shows it, such as
+    /// `path contains /Singles/ -> playlist Singles` or
+    /// `artist is "Various Artists" and album contains live -> set grouping Live`.
+    pub fn parse(text: &str) -> Result<ImportRule, String> {
+        let Some((conditions, actions)) = text.split_once("->") else {
+            return Err("Expected -> between the conditions and the actions".to_string());
+        };
+        let conditions = split_clauses(conditions)
+            .into_iter()
+            .map(|words| parse_condition(&words))
+            .collect::<Result<Vec<_>, _>>()?;
+        let actions = split_clauses(actions)
+            .into_iter()
+            .map(|words| parse_action(&words))
+            .collect::<Result<Vec<_>, _>>()?;
+        if conditions.is_empty() || actions.is_empty() {
+            return Err("A rule needs at least one condition and one action".to_string());
+        }
+        Ok(ImportRule {
+            enabled: true,
+            conditions,
+            actions,
+        })
+    }
+
+    pub fn description(&self) -> String {
+        let conditions: Vec<String> = self
+            .conditions
+            .iter()
+            .map(|condition| {
+                format!(
+                    "{} {} {}",
+                    condition.field.key(),
+                    condition.matching.name(),
+                    quoted(&condition.text)
+                )
+            })
+            .collect();
+        let actions: Vec<String> = self
+            .actions
+            .iter()
+            .map(|action| match action {
+                ImportAction::SetField {
+                    field,
+                    value: Some(value),
+                } => format!("set {} {}", field.key(), quoted(value)),
+                ImportAction::SetField { field, value: None } => format!("clear {}", field.key()),
+                ImportAction::AddToPlaylist { playlist } => {
+                    format!("playlist {}", quoted(playlist))
+                }
+            })
+            .collect();
+        format!("{} -> {}", conditions.join(" and "), actions.join(" and "))
+    }
+}
+
+fn quoted(text: &str) -> String {
+    if text.is_empty() || text.contains(char::is_whitespace) || text.eq_ignore_ascii_case("and") {
+        format!("\"{}\"", text)
+    } else {
+        text.to_string()
+    }
+}
+
+// Words split on whitespace outside quotes, grouped into clauses at each `and`
+fn split_clauses(text: &str) -> Vec<Vec<String>> {
+    let mut clauses = vec![Vec::new()];
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    for c in text.chars().chain([' ']) {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                was_quoted = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !was_quoted && word.eq_ignore_ascii_case("and") {
+                    clauses.push(Vec::new());
+                } else if !word.is_empty() || was_quoted {
+                    clauses.last_mut().unwrap().push(word.clone());
+                }
+                word.clear();
+                was_quoted = false;
+            }
+            c => word.push(c),
+        }
+    }
+    clauses.retain(|clause| !clause.is_empty());
+    clauses
+}
+
+fn parse_condition(words: &[String]) -> Result<ImportCondition, String> {
+    let Some((key, rest)) = words.split_first() else {
+        return Err("Expected a condition".to_string());
+    };
+    let Some(field) = ImportRuleField::ALL
+        .into_iter()
+        .find(|field| field.key() == key.to_lowercase())
+    else {
+        return Err(format!("Rules can't look at {}", key));
+    };
+    let (matching, text) = match rest {
+        [is, text] if is == "is" => (TextMatch::Is, text),
+        [contains, text] if contains == "contains" => (TextMatch::Contains, text),
+        [starts, with, text] if starts == "starts" && with == "with" => {
+            (TextMatch::StartsWith, text)
+        }
+        _ => {
+            return Err(format!(
+                "Expected is, contains or starts with and some text after {}",
+                key
+            ))
+        }
+    };
+    Ok(ImportCondition {
+        field,
+        matching,
+        text: text.clone(),
+    })
+}
+
+fn parse_action(words: &[String]) -> Result<ImportAction, String> {
+    let field =
+        |key: &str| MetadataField::from_key(key).ok_or_else(|| format!("Rules can't set {}", key));
+    match words {
+        [set, key, value] if set == "set" => Ok(ImportAction::SetField {
+            field: field(key)?,
+            value: Some(value.clone()),
+        }),
+        [clear, key] if clear == "clear" => Ok(ImportAction::SetField {
+            field: field(key)?,
+            value: None,
+        }),
+        [playlist, name] if playlist == "playlist" => Ok(ImportAction::AddToPlaylist {
+            playlist: name.clone(),
+        }),
+        _ => Err(format!(
+            "Expected set, clear or playlist, not {}",
+            words.join(" ")
+        )),
+    }
+}
+
+/// Everything the enabled rules would do to `file`, in rule order.
+pub fn import_actions(rules: &[ImportRule], file: &ImportedFile) -> Vec<ImportAction> {
+    rules
+        .iter()
+        .filter(|rule| rule.matches(file))
+        .flat_map(|rule| rule.actions.iter().cloned())
+        .collect()
+}
+
+impl Library {
+    /// Imports `files` like `add_imported`, then carries out whatever the
+    /// rules ask for. Tag changes are logged as one batch, so a rule that
+    /// got it wrong can be reverted.
+    pub fn add_imported_with_rules(
+        &mut self,
+        files: Vec<ImportedFile>,
+        rules: &[ImportRule],
+    ) -> Vec<TrackId> {
+        let actions: Vec<Vec<ImportAction>> = files
+            .iter()
+            .map(|file| import_actions(rules, file))
+            .collect();
+        let ids = self.add_imported(files);
+
+        let mut edits = Vec::new();
+        let mut playlists: BTreeMap<String, Vec<TrackId>> = BTreeMap::new();
+        for (id, actions) in ids.iter().zip(actions) {
+            for action in actions {
+                match action {
+                    ImportAction::SetField { field, value } => {
+                        edits.push((id.clone(), field, value))
+                    }
+                    ImportAction::AddToPlaylist { playlist } => {
+                        playlists.entry(playlist).or_default().push(id.clone())
+                    }
+                }
+            }
+        }
+
+        if !edits.is_empty() {
+            self.edit_metadata_batch(edits, ChangeSource::ImportRule);
+        }
+        for (name, track_ids) in playlists {
+            let existing = self
+                .playlists()
+                .iter()
+                .find(|playlist| playlist.name().as_ref() == name)
+                .map(|playlist| playlist.id().clone());
+            let playlist = existing.unwrap_or_else(|| self.create_playlist(name));
+            self.add_to_playlist(&playlist, &track_ids);
+        }
+        // Rules can change the album or artist a wish is matched on
+        self.fulfill_wishes(&ids);
+        ids
+    }
+}
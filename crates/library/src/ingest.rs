@@ -0,0 +1,274 @@
+use crate::{
+    cover_artwork, extract_archive, import::unique_folder, is_artwork_file, is_audio_file,
+    split_track_number, ImportedFile,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    Bandcamp,
+    Beatport,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PurchaseRelease {
+    pub store: Store,
+    pub artist: String,
+    pub album: String,
+    pub year: Option<u32>,
+}
+
+impl PurchaseRelease {
+    /// Recognizes the names stores give their downloads: Bandcamp uses
+    /// "Artist - Album" (sometimes with a "(2021)" suffix), Beatport bundles
+    /// are named "beatport_tracks_2024-01".
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+
+        if let Some(rest) = name.to_lowercase().strip_prefix("beatport_tracks") {
+            return Some(PurchaseRelease {
+                store: Store::Beatport,
+                artist: "Various Artists".to_string(),
+                album: "Beatport Purchases".to_string(),
+                year: trailing_year(rest.trim_start_matches(['_', '-'])),
+            });
+        }
+
+        let (name, year) = strip_year(name);
+        let (artist, album) = name.split_once(" - ")?;
+        let (artist, album) = (artist.trim(), album.trim());
+        if artist.is_empty() || album.is_empty() {
+            return None;
+        }
+
+        Some(PurchaseRelease {
+            store: Store::Bandcamp,
+            artist: artist.to_string(),
+            album: album.to_string(),
+            year,
+        })
+    }
+
+    pub fn imported_file(&self, path: PathBuf) -> ImportedFile {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let (artist, track_number, title) = match self.store {
+            // "Artist - Album - 01 Title"
+            Store::Bandcamp => {
+                let prefix = format!("{} - {} - ", self.artist, self.album);
+                let rest = stem.strip_prefix(&prefix).unwrap_or(&stem);
+                let (track_number, title) = split_track_number(rest);
+                (self.artist.clone(), track_number, title.to_string())
+            }
+            // "12345678_Title_(Original_Mix)", with the artist only in the tags
+            Store::Beatport => {
+                let rest = stem.trim_start_matches(|c: char| c.is_ascii_digit());
+                let title = rest.trim_start_matches('_').replace('_', " ");
+                ("Unknown Artist".to_string(), 0, title)
+            }
+        };
+
+        ImportedFile {
+            path,
+            title,
+            artist,
+            album: self.album.clone(),
+            album_artist: Some(self.artist.clone()),
+            genre: None,
+            year: self.year,
+            release_date: None,
+            original_release_date: None,
+            language: None,
+            moods: Vec::new(),
+            key: None,
+            track_number,
+            total_tracks: None,
+            disc_number: 0,
+            duration: 0,
+            artwork: None,
+            silence: None,
+        }
+    }
+}
+
+fn strip_year(name: &str) -> (&str, Option<u32>) {
+    for (open, close) in [('(', ')'), ('[', ']')] {
+        if let Some(inner) = name.strip_suffix(close) {
+            if let Some((rest, year)) = inner.rsplit_once(open) {
+                if let Some(year) = parse_year(year) {
+                    return (rest.trim_end(), Some(year));
+                }
+            }
+        }
+    }
+    (name, None)
+}
+
+fn trailing_year(value: &str) -> Option<u32> {
+    value.get(..4).and_then(parse_year)
+}
+
+fn parse_year(value: &str) -> Option<u32> {
+    let year: u32 = value.trim().parse().ok()?;
+    (1900..=2100).contains(&year).then_some(year)
+}
+
+/// Moves a purchase into the music folder, unpacking it first if it's a zip.
+pub fn ingest_purchase(
+    path: &Path,
+    release: &PurchaseRelease,
+    music_folder: &Path,
+    delete_archive: bool,
+) -> anyhow::Result<Vec<ImportedFile>> {
+    let (mut audio_files, artwork_files) = if path.is_dir() {
+        let destination = unique_folder(&music_folder.join(&release.artist).join(&release.album));
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        move_folder(path, &destination)?;
+
+        let files: Vec<PathBuf> = fs::read_dir(&destination)?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        files.into_iter().partition(|path| is_audio_file(path))
+    } else {
+        let extracted = extract_archive(path, music_folder)?;
+        // Kept if nothing came out of it, it may not have been a purchase
+        if delete_archive && !extracted.audio_files.is_empty() {
+            fs::remove_file(path)?;
+        }
+        (extracted.audio_files, extracted.artwork_files)
+    };
+
+    let artwork_files: Vec<PathBuf> = artwork_files
+        .into_iter()
+        .filter(|path| is_artwork_file(path))
+        .collect();
+    let cover = cover_artwork(&artwork_files);
+
+    audio_files.sort();
+    Ok(audio_files
+        .into_iter()
+        .map(|path| ImportedFile {
+            artwork: cover.clone(),
+            ..release.imported_file(path)
+        })
+        .collect())
+}
+
+/// Polls a downloads folder for new purchases. Anything is only reported
+/// once its size has stopped changing between two scans, so half-finished
+/// downloads are left alone.
+#[derive(Default)]
+pub struct PurchaseWatcher {
+    pending: HashMap<PathBuf, u64>,
+    ingested: Vec<PathBuf>,
+}
+
+impl PurchaseWatcher {
+    pub fn scan(&mut self, folder: &Path) -> Vec<(PathBuf, PurchaseRelease)> {
+        let Ok(entries) = fs::read_dir(folder) else {
+            return Vec::new();
+        };
+
+        let mut ready = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if self.ingested.contains(&path) {
+                continue;
+            }
+
+            let is_zip = path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("zip"));
+            if !is_zip && !path.is_dir() {
+                continue;
+            }
+
+            let Some(release) = path
+                .file_stem()
+                .and_then(|stem| PurchaseRelease::parse(&stem.to_string_lossy()))
+            else {
+                continue;
+            };
+
+            let size = size_of(&path);
+            if size == 0 {
+                continue;
+            }
+            if self.pending.insert(path.clone(), size) == Some(size) {
+                self.pending.remove(&path);
+                self.ingested.push(path.clone());
+                // Any other "X - Y.zip" that turns up in downloads is left be
+                if is_zip && !zip_has_audio(&path) {
+                    continue;
+                }
+                ready.push((path, release));
+            }
+        }
+        ready
+    }
+}
+
+fn zip_has_audio(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    let Ok(archive) = zip::ZipArchive::new(file) else {
+        return false;
+    };
+    let has_audio = archive
+        .file_names()
+        .any(|name| !name.starts_with("__MACOSX") && is_audio_file(Path::new(name)));
+    has_audio
+}
+
+// Renaming fails across volumes, such as downloads on one drive and music
+// on another, so it's copied over and the original removed instead
+fn move_folder(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_folder(from, to)?;
+    fs::remove_dir_all(from)
+}
+
+fn copy_folder(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)?.flatten() {
+        let path = entry.path();
+        let destination = to.join(entry.file_name());
+        if path.is_dir() {
+            copy_folder(&path, &destination)?;
+        } else {
+            fs::copy(&path, &destination)?;
+        }
+    }
+    Ok(())
+}
+
+fn size_of(path: &Path) -> u64 {
+    if path.is_dir() {
+        fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| is_audio_file(path))
+                    .map(|path| size_of(&path))
+                    .sum()
+            })
+            .unwrap_or(0)
+    } else {
+        fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{ArtworkStore, Library, NowPlaying, PlaylistId, Track, TrackId};
+use std::{collections::HashSet, fs, path::PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    // An entry in the track order with no track behind it
+    MissingTrack(TrackId),
+    // A track that can never be shown because nothing orders it
+    UnorderedTrack(TrackId),
+    DuplicateId(TrackId),
+    // A playlist entry for a track that's no longer in the library
+    DanglingPlaylistEntry(PlaylistId, TrackId),
+    // Queued in Up Next, or played this session, and since gone from the
+    // library
+    DanglingQueueEntry(TrackId),
+    DanglingHistoryEntry(TrackId),
+    // An image in the artwork cache that no track refers to
+    OrphanedArtwork(PathBuf),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.is_clean() {
+            return "No problems found".to_string();
+        }
+
+        let count = |matches: fn(&IntegrityIssue) -> bool| {
+            self.issues.iter().filter(|issue| matches(issue)).count()
+        };
+        format!(
+            "{} missing tracks, {} unordered tracks, {} duplicate ids, \
+             {} dangling playlist entries, {} dangling Up Next entries, \
+             {} dangling history entries, {} orphaned artwork images",
+            count(|issue| matches!(issue, IntegrityIssue::MissingTrack(_))),
+            count(|issue| matches!(issue, IntegrityIssue::UnorderedTrack(_))),
+            count(|issue| matches!(issue, IntegrityIssue::DuplicateId(_))),
+            count(|issue| matches!(issue, IntegrityIssue::DanglingPlaylistEntry(..))),
+            count(|issue| matches!(issue, IntegrityIssue::DanglingQueueEntry(_))),
+            count(|issue| matches!(issue, IntegrityIssue::DanglingHistoryEntry(_))),
+            count(|issue| matches!(issue, IntegrityIssue::OrphanedArtwork(_))),
+        )
+    }
+}
+
+impl Library {
+    /// Looks for entries pointing at tracks that are gone, tracks nothing
+    /// points at, and artwork nothing uses. Files played without being
+    /// added to the library don't count as gone.
+    pub fn check_integrity(
+        &self,
+        now_playing: &NowPlaying,
+        artwork_store: &ArtworkStore,
+    ) -> IntegrityReport {
+        let mut issues = Vec::new();
+        let mut seen = HashSet::new();
+
+        for id in &self.track_order {
+            if !seen.insert(id) {
+                issues.push(IntegrityIssue::DuplicateId(id.clone()));
+            } else if !self.tracks.contains_key(id) {
+                issues.push(IntegrityIssue::MissingTrack(id.clone()));
+            }
+        }
+
+        for id in self.tracks.keys() {
+            if !seen.contains(id) {
+                issues.push(IntegrityIssue::UnorderedTrack(id.clone()));
+            }
+        }
+
+        // Smart playlists are worked out afresh each time, nothing to go stale
+        for playlist in self
+            .playlists
+            .iter()
+            .filter(|playlist| !playlist.is_smart())
+        {
+            for id in playlist.tracks() {
+                if !self.tracks.contains_key(id) {
+                    issues.push(IntegrityIssue::DanglingPlaylistEntry(
+                        playlist.id().clone(),
+                        id.clone(),
+                    ));
+                }
+            }
+        }
+
+        let is_gone =
+            |track: &&Track| !track.is_external() && !self.tracks.contains_key(track.id());
+        for track in now_playing.up_next.iter().filter(is_gone) {
+            issues.push(IntegrityIssue::DanglingQueueEntry(track.id().clone()));
+        }
+        for track in now_playing.history.iter().filter(is_gone) {
+            issues.push(IntegrityIssue::DanglingHistoryEntry(track.id().clone()));
+        }
+
+        for path in artwork_store.report(self).unreferenced {
+            issues.push(IntegrityIssue::OrphanedArtwork(path));
+        }
+
+        IntegrityReport { issues }
+    }
+
+    pub fn repair(&mut self, report: &IntegrityReport, now_playing: &mut NowPlaying) {
+        for issue in &report.issues {
+            match issue {
+                IntegrityIssue::MissingTrack(id) => self.track_order.retain(|other| other != id),
+                IntegrityIssue::UnorderedTrack(id) => self.track_order.push(id.clone()),
+                IntegrityIssue::DuplicateId(_) => {
+                    let mut seen = HashSet::new();
+                    self.track_order.retain(|id| seen.insert(id.clone()));
+                }
+                // Not kept in the playlist's history, there's nothing to go back to
+                IntegrityIssue::DanglingPlaylistEntry(playlist_id, id) => {
+                    if let Some(playlist) = self.playlist_mut(playlist_id) {
+                        playlist.retain_tracks(|other| other != id);
+                    }
+                    self.playlist_index.remove(id);
+                }
+                IntegrityIssue::DanglingQueueEntry(id) => {
+                    now_playing.up_next.retain(|track| track.id() != id);
+                    now_playing.auto_added.remove(id);
+                }
+                IntegrityIssue::DanglingHistoryEntry(id) => {
+                    now_playing.history.retain(|track| track.id() != id)
+                }
+                IntegrityIssue::OrphanedArtwork(path) => {
+                    if let Err(e) = fs::remove_file(path) {
+                        eprintln!("Failed to remove {}: {}", path.display(), e);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{Library, Track, TrackId};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{f32::consts::PI, fs::File, path::Path};
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError,
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+// Audio is averaged down to about this rate before it's analyzed, which
+// still leaves every note up to the top of the piano's middle range
+const ANALYSIS_RATE: u32 = 11025;
+// Enough of a track to hear its key, without decoding all of it
+const ANALYZED_SECONDS: u32 = 120;
+// About a third of a second at the analysis rate, fine enough to tell
+// neighbouring notes apart in the lowest octave listened to
+const FRAME_LEN: usize = 4096;
+// C3 to B6, as MIDI notes. Bass notes below this blur together, and
+// harmonics above it say more about the timbre than the key.
+const LOWEST_NOTE: u8 = 48;
+const HIGHEST_NOTE: u8 = 95;
+
+// How strongly each note of the scale, from the tonic up, is felt to
+// belong to a major or minor key (Krumhansl and Kessler)
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+const MAJOR_NAMES: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+const MINOR_NAMES: [&str; 12] = [
+    "Cm", "C#m", "Dm", "Ebm", "Em", "Fm", "F#m", "Gm", "G#m", "Am", "Bbm", "Bm",
+];
+
+/// The key a track is in, as its tonic (C is 0, C# is 1, and so on up to
+/// B at 11) and whether it's minor. Saved in Camelot notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct MusicalKey {
+    tonic: u8,
+    minor: bool,
+}
+
+impl MusicalKey {
+    pub fn new(tonic: u8, minor: bool) -> Self {
+        MusicalKey {
+            tonic: tonic % 12,
+            minor,
+        }
+    }
+
+    /// Reads a key as tagged or typed, either in Camelot notation ("8A")
+    /// or by name ("Am", "A minor", "F#", "Bb major").
+    pub fn parse(value: &str) -> Option<Self> {
+        let value: String = value.split_whitespace().collect();
+        Self::parse_camelot(&value).or_else(|| Self::parse_name(&value))
+    }
+
+    fn parse_camelot(value: &str) -> Option<Self> {
+        let letter = value.chars().last()?;
+        let number: u8 = value[..value.len() - letter.len_utf8()].parse().ok()?;
+        if !(1..=12).contains(&number) {
+            return None;
+        }
+        // Seven semitones, a fifth, separate each step around the wheel
+        let major = (number + 4) % 12 * 7 % 12;
+        match letter.to_ascii_uppercase() {
+            'A' => Some(MusicalKey::new(major + 9, true)),
+            'B' => Some(MusicalKey::new(major, false)),
+            _ => None,
+        }
+    }
+
+    fn parse_name(value: &str) -> Option<Self> {
+        let mut chars = value.chars();
+        let natural = match chars.next()?.to_ascii_uppercase() {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => return None,
+        };
+        let rest = chars.as_str();
+        let (tonic, rest) = if let Some(rest) = rest.strip_prefix(['#', '♯']) {
+            (natural + 1, rest)
+        } else if let Some(rest) = rest.strip_prefix(['b', '♭']) {
+            (natural + 11, rest)
+        } else {
+            (natural, rest)
+        };
+        let minor = match rest.to_lowercase().as_str() {
+            "" | "maj" | "major" => false,
+            "m" | "min" | "minor" => true,
+            _ => return None,
+        };
+        Some(MusicalKey::new(tonic, minor))
+    }
+
+    pub fn is_minor(&self) -> bool {
+        self.minor
+    }
+
+    /// Where the key sits on the Camelot wheel, from 1 to 12. A minor key
+    /// shares its number with its relative major.
+    pub fn camelot_number(&self) -> u8 {
+        let major = if self.minor {
+            (self.tonic + 3) % 12
+        } else {
+            self.tonic
+        };
+        (major * 7 % 12 + 7) % 12 + 1
+    }
+
+    // "8A"
+    pub fn camelot(&self) -> String {
+        format!(
+            "{}{}",
+            self.camelot_number(),
+            if self.minor { 'A' } else { 'B' }
+        )
+    }
+
+    // "Am"
+    pub fn name(&self) -> &'static str {
+        if self.minor {
+            MINOR_NAMES[self.tonic as usize]
+        } else {
+            MAJOR_NAMES[self.tonic as usize]
+        }
+    }
+
+    /// Whether two tracks can be mixed without the keys clashing: the same
+    /// key, a step either way around the Camelot wheel, or its relative
+    /// major or minor.
+    pub fn is_compatible(&self, other: &MusicalKey) -> bool {
+        let (a, b) = (self.camelot_number(), other.camelot_number());
+        if self.minor != other.minor {
+            return a == b;
+        }
+        let distance = (a + 12 - b) % 12;
+        matches!(distance, 0 | 1 | 11)
+    }
+}
+
+impl From<MusicalKey> for String {
+    fn from(key: MusicalKey) -> Self {
+        key.camelot()
+    }
+}
+
+impl TryFrom<String> for MusicalKey {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        MusicalKey::parse(&value).ok_or_else(|| format!("Unknown key “{}”", value))
+    }
+}
+
+/// Listens to the start of the file and works out which key it's in, or
+/// `None` when nothing tonal could be heard, such as in silence or speech.
+pub fn detect_key(path: &Path) -> anyhow::Result<Option<MusicalKey>> {
+    let (samples, rate) = decode_mono(path)?;
+    Ok(best_key(&chromagram(&samples, rate)))
+}
+
+// The file's samples as one channel, averaged down to about
+// `ANALYSIS_RATE`, along with the rate they ended up at
+fn decode_mono(path: &Path) -> anyhow::Result<(Vec<f32>, u32)> {
+    let stream = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| anyhow!("No audio track"))?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| anyhow!("Unknown sample rate"))?;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let step = (sample_rate / ANALYSIS_RATE).max(1) as usize;
+    let rate = sample_rate / step as u32;
+    let limit = (rate * ANALYZED_SECONDS) as usize;
+    let mut samples = Vec::with_capacity(limit);
+    let (mut sum, mut summed) = (0.0, 0);
+
+    while samples.len() < limit {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged packet is skipped rather than giving up on the file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        for frame in buffer.samples().chunks(channels) {
+            sum += frame.iter().sum::<f32>() / channels as f32;
+            summed += 1;
+            if summed == step {
+                samples.push(sum / step as f32);
+                (sum, summed) = (0.0, 0);
+            }
+        }
+    }
+
+    Ok((samples, rate))
+}
+
+// How much of each of the 12 notes is heard over the whole of the audio,
+// whatever octave it's in
+fn chromagram(samples: &[f32], rate: u32) -> [f32; 12] {
+    let window: Vec<f32> = (0..FRAME_LEN)
+        .map(|ix| 0.5 - 0.5 * (2. * PI * ix as f32 / (FRAME_LEN - 1) as f32).cos())
+        .collect();
+    let notes: Vec<(usize, f32)> = (LOWEST_NOTE..=HIGHEST_NOTE)
+        .map(|note| {
+            let frequency = 440. * 2f32.powf((note as f32 - 69.) / 12.);
+            (note as usize % 12, frequency)
+        })
+        .filter(|(_, frequency)| *frequency < rate as f32 / 2.)
+        .collect();
+
+    let mut chroma = [0.; 12];
+    let mut frame = vec![0.; FRAME_LEN];
+    for chunk in samples.chunks_exact(FRAME_LEN) {
+        for (sample, (value, weight)) in frame.iter_mut().zip(chunk.iter().zip(&window)) {
+            *sample = value * weight;
+        }
+        let mut frame_chroma = [0.; 12];
+        for (pitch_class, frequency) in &notes {
+            frame_chroma[*pitch_class] += goertzel(&frame, *frequency, rate as f32);
+        }
+        // Each frame counts the same, so loud passages don't drown out
+        // the rest of the track
+        let loudest = frame_chroma.iter().cloned().fold(0., f32::max);
+        if loudest > f32::EPSILON {
+            for (total, value) in chroma.iter_mut().zip(frame_chroma) {
+                *total += value / loudest;
+            }
+        }
+    }
+    chroma
+}
+
+// The strength of a single frequency in the frame
+fn goertzel(frame: &[f32], frequency: f32, rate: f32) -> f32 {
+    let coefficient = 2. * (2. * PI * frequency / rate).cos();
+    let (mut previous, mut before) = (0., 0.);
+    for sample in frame {
+        let current = sample + coefficient * previous - before;
+        before = previous;
+        previous = current;
+    }
+    (previous * previous + before * before - coefficient * previous * before)
+        .max(0.)
+        .sqrt()
+}
+
+// The key whose profile lines up best with what was heard
+fn best_key(chroma: &[f32; 12]) -> Option<MusicalKey> {
+    if chroma.iter().all(|value| *value <= f32::EPSILON) {
+        return None;
+    }
+    (0..12)
+        .flat_map(|tonic| {
+            [
+                (MusicalKey::new(tonic, false), &MAJOR_PROFILE),
+                (MusicalKey::new(tonic, true), &MINOR_PROFILE),
+            ]
+        })
+        .map(|(key, profile)| {
+            let rotated: Vec<f32> = (0..12)
+                .map(|ix| profile[(ix + 12 - key.tonic as usize) % 12])
+                .collect();
+            (key, correlation(chroma, &rotated))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(key, _)| key)
+}
+
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut covariance, mut variance_a, mut variance_b) = (0., 0., 0.);
+    for (a, b) in a.iter().zip(b) {
+        covariance += (a - mean_a) * (b - mean_b);
+        variance_a += (a - mean_a).powi(2);
+        variance_b += (b - mean_b).powi(2);
+    }
+    covariance / (variance_a * variance_b).sqrt().max(f32::EPSILON)
+}
+
+impl Track {
+    pub fn key(&self) -> Option<MusicalKey> {
+        self.key
+    }
+}
+
+impl Library {
+    /// The files of the tracks that can be listened to for their key.
+    pub fn key_analysis_files(&self, ids: &[TrackId]) -> Vec<(TrackId, std::path::PathBuf)> {
+        ids.iter()
+            .filter_map(|id| self.track(id))
+            .filter(|track| self.is_available(track))
+            .filter_map(|track| Some((track.id().clone(), track.path()?.to_path_buf())))
+            .collect()
+    }
+
+    /// Keys worked out by listening, which replace any that were tagged.
+    pub fn set_detected_keys(&mut self, keys: Vec<(TrackId, MusicalKey)>) {
+        for (id, key) in keys {
+            if let Some(track) = self.track_mut(&id) {
+                track.key = Some(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn camelot(value: &str) -> MusicalKey {
+        MusicalKey::parse_camelot(value).unwrap()
+    }
+
+    #[test]
+    fn every_key_reads_back_from_its_camelot_notation() {
+        for tonic in 0..12 {
+            for minor in [false, true] {
+                let key = MusicalKey::new(tonic, minor);
+                assert_eq!(MusicalKey::parse(&key.camelot()), Some(key));
+                assert_eq!(MusicalKey::parse(key.name()), Some(key));
+            }
+        }
+    }
+
+    #[test]
+    fn camelot_numbers_match_the_wheel() {
+        assert_eq!(camelot("8A"), MusicalKey::new(9, true));
+        assert_eq!(camelot("8B"), MusicalKey::new(0, false));
+        assert_eq!(camelot("1A"), MusicalKey::new(8, true));
+        assert_eq!(camelot("1B"), MusicalKey::new(11, false));
+        assert_eq!(camelot("12B"), MusicalKey::new(4, false));
+        assert_eq!(MusicalKey::new(9, true).camelot_number(), 8);
+        assert_eq!(MusicalKey::new(0, false).camelot_number(), 8);
+        assert_eq!(MusicalKey::new(7, false).camelot_number(), 9);
+        assert_eq!(MusicalKey::new(4, true).camelot_number(), 9);
+    }
+
+    #[test]
+    fn camelot_notation_is_checked() {
+        assert_eq!(MusicalKey::parse_camelot("8a"), Some(camelot("8A")));
+        assert_eq!(MusicalKey::parse_camelot("0A"), None);
+        assert_eq!(MusicalKey::parse_camelot("13B"), None);
+        assert_eq!(MusicalKey::parse_camelot("8C"), None);
+        assert_eq!(MusicalKey::parse_camelot("A"), None);
+    }
+
+    #[test]
+    fn names_are_read_in_any_spelling() {
+        assert_eq!(MusicalKey::parse("A minor"), Some(camelot("8A")));
+        assert_eq!(MusicalKey::parse("Bb major"), Some(camelot("6B")));
+        assert_eq!(MusicalKey::parse("F♯m"), Some(camelot("11A")));
+        assert_eq!(MusicalKey::parse("H"), None);
+    }
+
+    #[test]
+    fn compatible_keys_wrap_around_the_wheel() {
+        assert!(camelot("12B").is_compatible(&camelot("1B")));
+        assert!(camelot("1A").is_compatible(&camelot("12A")));
+        assert!(camelot("12A").is_compatible(&camelot("12B")));
+        assert!(!camelot("12B").is_compatible(&camelot("2B")));
+        assert!(!camelot("12A").is_compatible(&camelot("1B")));
+    }
+}
@@ -0,0 +1,75 @@
+// ISO 639-1 and 639-2 codes, as ID3 and Vorbis comments have them
+const LANGUAGES: &[(&[&str], &str)] = &[
+    (&["en", "eng"], "English"),
+    (&["ja", "jpn"], "Japanese"),
+    (&["ko", "kor"], "Korean"),
+    (&["zh", "chi", "zho"], "Chinese"),
+    (&["fr", "fre", "fra"], "French"),
+    (&["de", "ger", "deu"], "German"),
+    (&["es", "spa"], "Spanish"),
+    (&["it", "ita"], "Italian"),
+    (&["pt", "por"], "Portuguese"),
+    (&["nl", "dut", "nld"], "Dutch"),
+    (&["sv", "swe"], "Swedish"),
+    (&["ru", "rus"], "Russian"),
+    (&["el", "gre", "ell"], "Greek"),
+    (&["he", "heb"], "Hebrew"),
+    (&["ar", "ara"], "Arabic"),
+    (&["hi", "hin"], "Hindi"),
+    (&["th", "tha"], "Thai"),
+];
+
+/// The language as it's shown, from a tag or from what's typed in. Codes
+/// become names, and anything else is kept as it was written. `None` for
+/// "und" and "zxx", which tag a language as unknown or an instrumental.
+pub fn language_name(value: &str) -> Option<String> {
+    let value = value.trim();
+    let lowercase = value.to_lowercase();
+    if value.is_empty() || lowercase == "und" || lowercase == "zxx" {
+        return None;
+    }
+    let name = LANGUAGES
+        .iter()
+        .find(|(codes, name)| {
+            codes.contains(&lowercase.as_str()) || name.to_lowercase() == lowercase
+        })
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| value.to_string());
+    Some(name)
+}
+
+/// Guesses the language of lyrics from the script most of their letters
+/// are in. Only scripts that mostly mean one language are counted, so
+/// lyrics in Latin or Cyrillic letters are left unknown.
+pub fn detect_language(lyrics: &str) -> Option<&'static str> {
+    let (mut letters, mut kana, mut hangul, mut han, mut greek, mut hebrew, mut thai) =
+        (0, 0, 0, 0, 0, 0, 0);
+    for c in lyrics.chars().filter(|c| c.is_alphabetic()) {
+        letters += 1;
+        match c as u32 {
+            0x3040..=0x30FF => kana += 1,
+            0x1100..=0x11FF | 0xAC00..=0xD7AF => hangul += 1,
+            0x4E00..=0x9FFF => han += 1,
+            0x0370..=0x03FF => greek += 1,
+            0x0590..=0x05FF => hebrew += 1,
+            0x0E00..=0x0E7F => thai += 1,
+            _ => {}
+        }
+    }
+
+    // Japanese mixes kana in with its kanji, Chinese is kanji alone
+    let (count, language) = if kana > 0 {
+        (kana + han, "Japanese")
+    } else {
+        [
+            (hangul, "Korean"),
+            (han, "Chinese"),
+            (greek, "Greek"),
+            (hebrew, "Hebrew"),
+            (thai, "Thai"),
+        ]
+        .into_iter()
+        .max_by_key(|(count, _)| *count)?
+    };
+    (count > 0 && count * 2 > letters).then_some(language)
+}
@@ -1,6 +1,110 @@
 use gpui::*;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+mod albums;
+mod alphabet;
+mod artist_info;
+mod artwork;
+mod artwork_lookup;
+mod audit;
+mod audition;
+mod autoplay;
+mod checksum;
+mod cleanup;
+mod completeness;
+mod crossfade;
+mod custom_fields;
+mod daily_mix;
+mod date;
+mod drives;
+mod duplicates;
+mod embedded_artwork;
+mod filter;
+mod import;
+mod import_history;
+mod import_rules;
+mod ingest;
+mod integrity;
+mod key;
+mod language;
+mod links;
+mod manifest;
+mod moods;
+mod new_releases;
+mod persist;
+mod playlist;
+mod playlist_history;
+mod query;
+mod rating;
+mod scan;
+mod shuffle;
+mod silence;
+mod smart;
+mod sort;
+mod stats;
+mod storage;
+mod store;
+mod suggest;
+mod summary;
+mod tags;
+mod trash;
+mod undo;
+mod up_next;
+mod versions;
+mod wishlist;
+
+pub use albums::*;
+pub use alphabet::*;
+pub use artist_info::*;
+pub use artwork::*;
+pub use artwork_lookup::*;
+pub use audit::*;
+pub use audition::*;
+pub use autoplay::*;
+pub use checksum::*;
+pub use cleanup::*;
+pub use completeness::*;
+pub use custom_fields::*;
+pub use daily_mix::*;
+pub use date::*;
+pub use drives::*;
+pub use duplicates::*;
+pub use embedded_artwork::*;
+pub use filter::*;
+pub use import::*;
+pub use import_history::*;
+pub use import_rules::*;
+pub use ingest::*;
+pub use integrity::*;
+pub use key::*;
+pub use language::*;
+pub use links::*;
+pub use manifest::*;
+pub use moods::*;
+pub use new_releases::*;
+pub use playlist::*;
+pub use playlist_history::*;
+pub use query::*;
+pub use rating::*;
+pub use scan::*;
+pub use shuffle::*;
+pub use silence::*;
+pub use smart::*;
+pub use sort::*;
+pub use stats::*;
+pub use storage::*;
+pub use store::*;
+pub use suggest::*;
+pub use summary::*;
+pub use tags::*;
+pub use trash::*;
+pub use undo::*;
+pub use versions::*;
+pub use wishlist::*;
 
 pub fn format_playback_time(seconds: i32) -> String {
     let minutes = seconds / 60;
@@ -8,7 +112,7 @@ pub fn format_playback_time(seconds: i32) -> String {
     format!("{:02}:{:02}", minutes, seconds)
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TrackId(String);
 
 impl Into<String> for TrackId {
@@ -17,6 +121,12 @@ impl Into<String> for TrackId {
     }
 }
 
+impl From<String> for TrackId {
+    fn from(id: String) -> Self {
+        TrackId(id)
+    }
+}
+
 fn track_id(title: String, artist: String, album: String) -> TrackId {
     let uuid = uuid::Uuid::new_v4();
     let id = format!("{}-{}-{}-{}", title, artist, album, uuid);
@@ -25,29 +135,124 @@ fn track_id(title: String, artist: String, album: String) -> TrackId {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableTrack {
+    // Missing from libraries saved before ids were kept, which get new ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
     title: String,
     artist: String,
     album: String,
     duration: i32,
+    // Left out by libraries saved before kinds were detected, see `Track::from`
+    #[serde(default)]
     kind: String,
     date_added: String,
     plays: i32,
     track_number: u32,
     total_tracks: u32,
+    // 0 when the tags don't say
+    #[serde(default)]
+    disc_number: u32,
+    #[serde(default)]
+    size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    grouping: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    genre: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    album_artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    year: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    release_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    original_release_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    moods: Vec<String>,
+    // In Camelot notation, as "8A"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    custom_fields: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    musicbrainz_recording_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    musicbrainz_release_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_played: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    artwork: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gapless: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    leading_silence_ms: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trailing_silence_ms: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rating: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    album_rating: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version_group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<VersionKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Track {
-    _id: TrackId,
+    id: TrackId,
     title: SharedString,
     artist: SharedString,
     album: SharedString,
     duration: i32,
-    _kind: String,
-    _date_added: String,
+    kind: String,
+    date_added: String,
     plays: i32,
     track_number: u32,
     total_tracks: u32,
+    disc_number: u32,
+    size: u64,
+    grouping: Option<SharedString>,
+    genre: Option<String>,
+    album_artist: Option<SharedString>,
+    year: Option<u32>,
+    // As "2005", "2005-05" or "2005-05-09", however much the tags give
+    release_date: Option<String>,
+    // When the recording first came out, if this is a remaster or reissue
+    original_release_date: Option<String>,
+    // Named, as "Japanese", from the tags or else guessed from the lyrics
+    language: Option<String>,
+    // Free-form and lowercase, as "chill" or "focus"
+    moods: Vec<String>,
+    // Tagged, or worked out by listening to the track
+    key: Option<MusicalKey>,
+    // Made up for the library, as "Key: 8A" or "Source: Vinyl rip"
+    custom_fields: BTreeMap<String, String>,
+    musicbrainz_recording_id: Option<String>,
+    musicbrainz_release_id: Option<String>,
+    last_played: Option<String>,
+    artwork: Option<PathBuf>,
+    // Set from the gapless tag or by hand, `None` leaves it to detection
+    gapless: Option<bool>,
+    leading_silence_ms: Option<u32>,
+    trailing_silence_ms: Option<u32>,
+    // In stars, from 0 to `MAX_RATING`
+    rating: Option<u8>,
+    album_rating: Option<u8>,
+    // Shared by every linked version of the same song
+    version_group: Option<String>,
+    version: Option<VersionKind>,
+    // The audio file, missing for tracks known only by their tags
+    path: Option<PathBuf>,
+    // SHA-256 of the file as imported, for spotting it going bad later
+    checksum: Option<String>,
+    // Played from outside the library, never saved with it
+    external: bool,
 }
 
 impl From<SerializableTrack> for Track {
@@ -55,30 +260,244 @@ impl From<SerializableTrack> for Track {
         let title = track.title.clone();
         let artist = track.artist.clone();
         let album = track.album.clone();
+        // Older libraries have neither a path nor a kind for some tracks,
+        // which still load and are filled in when there's a file to look at
+        let kind = match (track.kind.is_empty(), &track.path) {
+            (false, _) => track.kind,
+            (true, Some(path)) => kind_for_file(path),
+            (true, None) => "Audio file".to_string(),
+        };
 
         Track {
-            _id: track_id(title.clone(), artist.clone(), album.clone()),
+            id: track
+                .id
+                .map(TrackId)
+                .unwrap_or_else(|| track_id(title.clone(), artist.clone(), album.clone())),
             title: track.title.into(),
             artist: track.artist.into(),
             album: track.album.into(),
             duration: track.duration,
-            _kind: track.kind,
-            _date_added: track.date_added,
+            kind,
+            date_added: track.date_added,
             plays: track.plays,
             track_number: track.track_number,
             total_tracks: track.total_tracks,
+            disc_number: track.disc_number,
+            size: track.size,
+            grouping: track.grouping.map(Into::into),
+            genre: track.genre,
+            album_artist: track.album_artist.map(Into::into),
+            year: track.year,
+            release_date: track.release_date,
+            original_release_date: track.original_release_date,
+            language: track.language,
+            moods: track.moods,
+            key: track.key.as_deref().and_then(MusicalKey::parse),
+            custom_fields: track.custom_fields,
+            musicbrainz_recording_id: track.musicbrainz_recording_id,
+            musicbrainz_release_id: track.musicbrainz_release_id,
+            last_played: track.last_played,
+            artwork: track.artwork,
+            gapless: track.gapless,
+            leading_silence_ms: track.leading_silence_ms,
+            trailing_silence_ms: track.trailing_silence_ms,
+            rating: track.rating,
+            album_rating: track.album_rating,
+            version_group: track.version_group,
+            version: track.version,
+            path: track.path,
+            checksum: track.checksum,
+            external: false,
         }
     }
 }
 
+impl Track {
+    pub fn id(&self) -> &TrackId {
+        &self.id
+    }
+
+    pub fn title(&self) -> SharedString {
+        self.title.clone()
+    }
+
+    pub fn artist(&self) -> SharedString {
+        self.artist.clone()
+    }
+
+    pub fn album(&self) -> SharedString {
+        self.album.clone()
+    }
+
+    // Falls back to the track artist, as most files don't tag it separately
+    pub fn album_artist(&self) -> SharedString {
+        self.album_artist
+            .clone()
+            .unwrap_or_else(|| self.artist.clone())
+    }
+
+    pub fn year(&self) -> Option<u32> {
+        self.year
+    }
+
+    pub fn release_date(&self) -> Option<&str> {
+        self.release_date.as_deref()
+    }
+
+    pub fn original_release_date(&self) -> Option<&str> {
+        self.original_release_date.as_deref()
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// The year the recording first came out, so remasters and reissues
+    /// are placed in their own era. Falls back to the release itself.
+    pub fn original_year(&self) -> Option<u32> {
+        self.original_release_date
+            .as_deref()
+            .or(self.release_date.as_deref())
+            .and_then(|date| date.get(..4)?.parse().ok())
+            .or(self.year)
+    }
+
+    /// As 1960 for the 1960s, from the original year.
+    pub fn decade(&self) -> Option<u32> {
+        self.original_year().map(|year| year / 10 * 10)
+    }
+
+    pub fn duration(&self) -> i32 {
+        self.duration
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn track_number(&self) -> u32 {
+        self.track_number
+    }
+
+    pub fn total_tracks(&self) -> u32 {
+        self.total_tracks
+    }
+
+    pub fn disc_number(&self) -> u32 {
+        self.disc_number
+    }
+
+    // Where the track comes on its album, across every disc
+    pub fn album_position(&self) -> (u32, u32) {
+        (self.disc_number, self.track_number)
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn date_added(&self) -> &str {
+        &self.date_added
+    }
+
+    pub fn plays(&self) -> i32 {
+        self.plays
+    }
+
+    pub fn last_played(&self) -> Option<&str> {
+        self.last_played.as_deref()
+    }
+
+    pub fn is_unplayed(&self) -> bool {
+        self.plays == 0
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.external
+    }
+
+    pub fn artwork(&self) -> Option<&Path> {
+        self.artwork.as_deref()
+    }
+
+    pub fn grouping(&self) -> Option<SharedString> {
+        self.grouping.clone()
+    }
+
+    pub fn genre(&self) -> Option<&str> {
+        self.genre.as_deref()
+    }
+
+    pub fn musicbrainz_recording_id(&self) -> Option<&str> {
+        self.musicbrainz_recording_id.as_deref()
+    }
+
+    pub fn musicbrainz_release_id(&self) -> Option<&str> {
+        self.musicbrainz_release_id.as_deref()
+    }
+
+    pub fn gapless(&self) -> Option<bool> {
+        self.gapless
+    }
+
+    pub fn leading_silence_ms(&self) -> Option<u32> {
+        self.leading_silence_ms
+    }
+
+    pub fn trailing_silence_ms(&self) -> Option<u32> {
+        self.trailing_silence_ms
+    }
+
+    pub fn rating(&self) -> Option<u8> {
+        self.rating
+    }
+
+    pub fn album_rating(&self) -> Option<u8> {
+        self.album_rating
+    }
+
+    pub fn is_same_album(&self, other: &Track) -> bool {
+        self.album == other.album && self.album_artist() == other.album_artist()
+    }
+
+    // Parts of the same work are meant to run together without a crossfade
+    pub fn is_same_work(&self, other: &Track) -> bool {
+        self.grouping.is_some() && self.grouping == other.grouping && self.album == other.album
+    }
+}
+
 pub struct NowPlaying {
     current_track: Option<CurrentTrack>,
+    up_next: Vec<Track>,
+    // Queued by autoplay rather than by hand, until they're pinned
+    auto_added: HashSet<TrackId>,
+    // Tracks that have been playing this session, oldest first
+    history: Vec<Track>,
+    // Played far enough to count, waiting to be recorded in the library
+    finished_plays: Vec<TrackId>,
+    // Nothing played while this is on counts as a play or goes into history
+    private_listening: bool,
+    shuffle_mode: ShuffleMode,
+    // Bumped whenever the current track is replaced, even by itself, so
+    // playing a track again starts it over
+    generation: u64,
 }
 
 impl Default for NowPlaying {
     fn default() -> Self {
         NowPlaying {
             current_track: None,
+            up_next: Vec::new(),
+            auto_added: HashSet::new(),
+            history: Vec::new(),
+            finished_plays: Vec::new(),
+            private_listening: false,
+            shuffle_mode: ShuffleMode::Off,
+            generation: 0,
         }
     }
 }
@@ -88,8 +507,85 @@ impl NowPlaying {
         self.current_track.as_ref()
     }
 
+    // For following playback along, replacing the track goes through
+    // `set_current_track`
+    pub fn current_track_mut(&mut self) -> Option<&mut CurrentTrack> {
+        self.current_track.as_mut()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     pub fn set_current_track(&mut self, current_track: Option<CurrentTrack>) {
-        self.current_track = current_track;
+        self.generation += 1;
+        let Some(previous) = std::mem::replace(&mut self.current_track, current_track) else {
+            return;
+        };
+        if self.private_listening {
+            return;
+        }
+        if previous.counts_as_play() {
+            self.finished_plays.push(previous.track().id().clone());
+        }
+        self.history.push(previous.track().clone());
+    }
+
+    /// Pauses or resumes the current track where it is, without it counting
+    /// as a change of track.
+    pub fn set_is_playing(&mut self, is_playing: bool) {
+        if let Some(current_track) = &mut self.current_track {
+            current_track.set_is_playing(is_playing);
+        }
+    }
+
+    pub fn take_finished_plays(&mut self) -> Vec<TrackId> {
+        std::mem::take(&mut self.finished_plays)
+    }
+
+    pub fn is_private_listening(&self) -> bool {
+        self.private_listening
+    }
+
+    pub fn set_private_listening(&mut self, private_listening: bool) {
+        self.private_listening = private_listening;
+    }
+
+    pub fn up_next(&self) -> &[Track] {
+        &self.up_next
+    }
+
+    pub fn history(&self) -> &[Track] {
+        &self.history
+    }
+
+    /// Starts playing the first track, queueing the rest behind it.
+    pub fn play_tracks(&mut self, tracks: Vec<Track>) {
+        let mut tracks = tracks.into_iter();
+        self.set_current_track(tracks.next().map(|track| {
+            let mut current_track = CurrentTrack::new(track);
+            current_track.set_is_playing(true);
+            current_track
+        }));
+        self.up_next = shuffle_queue(
+            self.current_track.as_ref().map(|current| current.track()),
+            tracks.collect(),
+            self.shuffle_mode,
+        );
+        self.auto_added.clear();
+    }
+
+    pub fn shuffle_mode(&self) -> ShuffleMode {
+        self.shuffle_mode
+    }
+
+    pub fn set_shuffle_mode(&mut self, shuffle_mode: ShuffleMode) {
+        self.shuffle_mode = shuffle_mode;
+        self.up_next = shuffle_queue(
+            self.current_track.as_ref().map(|current| current.track()),
+            std::mem::take(&mut self.up_next),
+            shuffle_mode,
+        );
     }
 }
 
@@ -125,10 +621,20 @@ impl CurrentTrack {
         self.track.duration
     }
 
+    // For files imported without a known length, once playback finds it
+    pub fn set_duration(&mut self, duration: i32) {
+        self.track.duration = duration;
+    }
+
     pub fn progress(&self) -> f32 {
         (self.current_time as f32 / self.duration() as f32).clamp(0., 1.)
     }
 
+    // Skipping before the halfway point doesn't count
+    pub fn counts_as_play(&self) -> bool {
+        self.duration() > 0 && self.current_time * 2 >= self.duration()
+    }
+
     pub fn time_remaining(&self) -> i32 {
         self.duration() - self.current_time()
     }
@@ -174,10 +680,26 @@ impl CurrentTrack {
 pub struct SerializableLibrary {
     tracks: Vec<SerializableTrack>,
     columns: Vec<Column>,
+    #[serde(default)]
+    playlists: Vec<SerializablePlaylist>,
+    #[serde(default)]
+    wishlist: Vec<Wish>,
+    #[serde(default)]
+    alternate_copies: Vec<AlternateCopy>,
+    #[serde(default)]
+    playlist_history: Vec<SerializablePlaylistHistory>,
+    #[serde(default)]
+    import_sessions: Vec<ImportSession>,
+    #[serde(default)]
+    recently_deleted: Vec<SerializableDeletedTrack>,
+    #[serde(default)]
+    metadata_log: Vec<MetadataChange>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ColumnKind {
+    // Position in the manual order
+    Index,
     Playing,
     Title,
     Artist,
@@ -186,6 +708,39 @@ pub enum ColumnKind {
     TrackNumber,
     Kind,
     DateAdded,
+    Plays,
+    // Out of five stars, unrated sorts as none
+    Rating,
+    Size,
+    // The original release date, or the release date for anything that
+    // isn't a reissue
+    OriginalReleaseDate,
+    Language,
+    // The musical key, in Camelot notation
+    Key,
+    // A custom field, by name
+    Custom(String),
+}
+
+impl ColumnKind {
+    // Numbers line up on their right edge, with every digit the same width
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            ColumnKind::Index
+                | ColumnKind::Duration
+                | ColumnKind::TrackNumber
+                | ColumnKind::Plays
+                | ColumnKind::Size
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColumnAlignment {
+    Left,
+    Center,
+    Right,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -194,10 +749,16 @@ pub struct Column {
     #[serde(skip_serializing_if = "Option::is_none")]
     width: Option<f32>,
     enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alignment: Option<ColumnAlignment>,
 }
 
-fn _default_columns() -> Vec<Column> {
+/// Narrower than this and a column's name can't be made out at all.
+pub const MIN_COLUMN_WIDTH: f32 = 20.0;
+
+fn default_columns() -> Vec<Column> {
     vec![
+        Column::new(ColumnKind::Index),
         Column::new(ColumnKind::Playing),
         Column::new(ColumnKind::Title),
         Column::new(ColumnKind::Artist),
@@ -206,6 +767,18 @@ fn _default_columns() -> Vec<Column> {
         Column::new(ColumnKind::TrackNumber),
         Column::new(ColumnKind::Kind),
         Column::new(ColumnKind::DateAdded),
+        Column::new(ColumnKind::Plays),
+        Column::new(ColumnKind::Rating),
+        Column::new(ColumnKind::Size),
+        Column::new(ColumnKind::OriginalReleaseDate),
+        Column {
+            enabled: false,
+            ..Column::new(ColumnKind::Language)
+        },
+        Column {
+            enabled: false,
+            ..Column::new(ColumnKind::Key)
+        },
     ]
 }
 
@@ -215,11 +788,17 @@ impl Column {
             kind,
             width: None,
             enabled: true,
+            alignment: None,
         }
     }
 
+    pub fn kind(&self) -> &ColumnKind {
+        &self.kind
+    }
+
     pub fn name(&self) -> String {
-        match self.kind {
+        match &self.kind {
+            ColumnKind::Index => "#".to_string(),
             ColumnKind::Playing => "".to_string(),
             ColumnKind::Title => "Name".to_string(),
             ColumnKind::Artist => "Artist".to_string(),
@@ -228,11 +807,19 @@ impl Column {
             ColumnKind::TrackNumber => "Track Number".to_string(),
             ColumnKind::Kind => "Kind".to_string(),
             ColumnKind::DateAdded => "Date Added".to_string(),
+            ColumnKind::Plays => "Plays".to_string(),
+            ColumnKind::Rating => "Rating".to_string(),
+            ColumnKind::Size => "Size".to_string(),
+            ColumnKind::OriginalReleaseDate => "Released".to_string(),
+            ColumnKind::Language => "Language".to_string(),
+            ColumnKind::Key => "Key".to_string(),
+            ColumnKind::Custom(name) => name.clone(),
         }
     }
 
     pub fn width(&self) -> f32 {
         self.width.unwrap_or(match self.kind {
+            ColumnKind::Index => 30.0,
             ColumnKind::Playing => 17.0,
             ColumnKind::Title => 300.0,
             ColumnKind::Artist => 150.0,
@@ -241,9 +828,29 @@ impl Column {
             ColumnKind::TrackNumber => 50.0,
             ColumnKind::Kind => 100.0,
             ColumnKind::DateAdded => 150.0,
+            ColumnKind::Plays => 50.0,
+            ColumnKind::Rating => 62.0,
+            ColumnKind::Size => 70.0,
+            ColumnKind::OriginalReleaseDate => 90.0,
+            ColumnKind::Language => 90.0,
+            ColumnKind::Key => 40.0,
+            ColumnKind::Custom(_) => 100.0,
         })
     }
 
+    pub fn alignment(&self) -> ColumnAlignment {
+        self.alignment.unwrap_or(match &self.kind {
+            ColumnKind::Playing => ColumnAlignment::Center,
+            kind if kind.is_numeric() => ColumnAlignment::Right,
+            _ => ColumnAlignment::Left,
+        })
+    }
+
+    /// Overrides the column's natural alignment, `None` goes back to it.
+    pub fn set_alignment(&mut self, alignment: Option<ColumnAlignment>) {
+        self.alignment = alignment;
+    }
+
     pub fn set_width(&mut self, width: Option<f32>) {
         self.width = width;
     }
@@ -264,39 +871,218 @@ pub fn test_library_path() -> PathBuf {
 }
 
 pub struct Library {
-    _source: Option<PathBuf>,
-    _tracks: HashMap<TrackId, Track>,
-    _track_order: Vec<TrackId>,
-    _columns: Vec<Column>,
+    // In order of preference, when the same track is in more than one
+    roots: Vec<PathBuf>,
+    // Roots that couldn't be reached when last checked
+    offline_roots: Vec<PathBuf>,
+    tracks: HashMap<TrackId, Track>,
+    track_order: Vec<TrackId>,
+    columns: Vec<Column>,
+    playlists: Vec<Playlist>,
+    // Which playlists each track appears in, kept in step with `playlists`
+    playlist_index: HashMap<TrackId, Vec<PlaylistId>>,
+    // Earlier contents of each playlist, oldest first
+    playlist_history: HashMap<PlaylistId, Vec<PlaylistVersion>>,
+    recently_deleted: Vec<DeletedTrack>,
+    metadata_log: Vec<MetadataChange>,
+    next_change_batch: u64,
+    // Not saved, changes can only be undone in the session they were made
+    undo_stack: UndoStack,
+    daily_mixes: Vec<DailyMix>,
+    daily_mix_day: Option<String>,
+    wishlist: Vec<Wish>,
+    // Files of tracks that are also elsewhere, besides the one in use
+    alternate_copies: Vec<AlternateCopy>,
+    // Fulfilled by imports but not yet shown to the user
+    fulfilled_wishes: Vec<Wish>,
+    // Imported tracks whose files are yet to be checksummed
+    pending_checksums: Vec<TrackId>,
+    // Tracks loaded or imported since they were last looked for artwork
+    pending_artwork: Vec<TrackId>,
+    // Oldest first
+    import_sessions: Vec<ImportSession>,
+    // Tracks edited since the store was last given them. Added and removed
+    // tracks, and moves, the store works out for itself.
+    changed_tracks: HashSet<TrackId>,
+    // Files read and found, while `_scanning_task` is running
+    scan_progress: Option<(usize, usize)>,
     _scanning_task: Option<Task<()>>,
 }
 
 impl Default for Library {
     fn default() -> Self {
         Library {
-            _source: None,
-            _tracks: HashMap::new(),
-            _track_order: Vec::new(),
-            _columns: Vec::new(),
+            roots: Vec::new(),
+            offline_roots: Vec::new(),
+            tracks: HashMap::new(),
+            track_order: Vec::new(),
+            columns: default_columns(),
+            playlists: Vec::new(),
+            playlist_index: HashMap::new(),
+            playlist_history: HashMap::new(),
+            recently_deleted: Vec::new(),
+            metadata_log: Vec::new(),
+            next_change_batch: 0,
+            undo_stack: UndoStack::default(),
+            daily_mixes: Vec::new(),
+            daily_mix_day: None,
+            wishlist: Vec::new(),
+            alternate_copies: Vec::new(),
+            fulfilled_wishes: Vec::new(),
+            pending_checksums: Vec::new(),
+            pending_artwork: Vec::new(),
+            import_sessions: Vec::new(),
+            changed_tracks: HashSet::new(),
+            scan_progress: None,
             _scanning_task: None,
         }
     }
 }
 
 impl Library {
-    pub fn new(cx: &mut WindowContext, path: PathBuf) -> Model<Self> {
-        // check and load dir
-
-        cx.new_model(|_cx| Library {
-            _source: Some(path),
-            _tracks: HashMap::new(),
-            _track_order: Vec::new(),
-            _columns: Vec::new(),
-            _scanning_task: None,
+    /// Starts out with the library as it was saved, then adds any new audio
+    /// files under `roots`, emitting `Event::ScanProgress` as it goes. A
+    /// track found under more than one root plays the better copy.
+    pub fn new(
+        cx: &mut WindowContext,
+        saved: Option<SerializableLibrary>,
+        roots: Vec<PathBuf>,
+    ) -> Model<Self> {
+        cx.new_model(|cx| {
+            let mut library = Library {
+                roots: roots.clone(),
+                scan_progress: Some((0, 0)),
+                _scanning_task: Some(Library::scan(roots, cx)),
+                ..Library::default()
+            };
+            if let Some(saved) = saved {
+                library.restore(saved);
+            }
+            library
         })
     }
+
+    pub fn track(&self, id: &TrackId) -> Option<&Track> {
+        self.tracks.get(id)
+    }
+
+    pub fn track_order(&self) -> &[TrackId] {
+        &self.track_order
+    }
+
+    pub fn set_column_width(&mut self, kind: &ColumnKind, width: f32) {
+        if let Some(column) = self.columns.iter_mut().find(|column| &column.kind == kind) {
+            column.set_width(Some(width.max(MIN_COLUMN_WIDTH)));
+        }
+    }
+
+    /// Moves a column, by its place among all of them, hidden ones included.
+    pub fn move_column(&mut self, from: usize, to: usize) {
+        move_item(&mut self.columns, from, to);
+    }
+
+    /// Shows or hides a column. The ones hidden keep their place and width.
+    /// The title is always shown, so there's something to tell tracks apart.
+    pub fn set_column_enabled(&mut self, kind: &ColumnKind, enabled: bool) {
+        if kind == &ColumnKind::Title && !enabled {
+            return;
+        }
+        if let Some(column) = self.columns.iter_mut().find(|column| &column.kind == kind) {
+            column.set_enabled(enabled);
+        }
+    }
+
+    /// Puts every column back where it started, at its first width. Custom
+    /// field columns go to the end, hidden.
+    pub fn reset_columns(&mut self) {
+        let custom: Vec<Column> = self
+            .columns
+            .drain(..)
+            .filter(|column| matches!(column.kind, ColumnKind::Custom(_)))
+            .map(|column| Column {
+                enabled: false,
+                ..Column::new(column.kind)
+            })
+            .collect();
+        self.columns = default_columns();
+        self.columns.extend(custom);
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    // Counts as a play without touching tracks that were already played
+    pub fn mark_played(&mut self, ids: &[TrackId]) {
+        let today = today();
+        for id in ids {
+            if let Some(track) = self.track_mut(id) {
+                track.plays = track.plays.max(1);
+                track.last_played = Some(today.clone());
+            }
+        }
+    }
+
+    pub fn record_plays(&mut self, ids: &[TrackId]) {
+        let today = today();
+        for id in ids {
+            if let Some(track) = self.track_mut(id) {
+                track.plays += 1;
+                track.last_played = Some(today.clone());
+            }
+        }
+    }
+
+    pub fn mark_unplayed(&mut self, ids: &[TrackId]) {
+        for id in ids {
+            if let Some(track) = self.track_mut(id) {
+                track.plays = 0;
+                track.last_played = None;
+            }
+        }
+    }
+
+    // For editing a track, noting it for the next save
+    pub(crate) fn track_mut(&mut self, id: &TrackId) -> Option<&mut Track> {
+        let track = self.tracks.get_mut(id)?;
+        self.changed_tracks.insert(id.clone());
+        Some(track)
+    }
+
+    /// The tracks edited since this was last called, for the store to write.
+    pub fn take_changed_tracks(&mut self) -> HashSet<TrackId> {
+        std::mem::take(&mut self.changed_tracks)
+    }
+
+    pub fn tracks(&self) -> impl Iterator<Item = &Track> {
+        self.track_order.iter().filter_map(|id| self.tracks.get(id))
+    }
+
+    /// Every part of the work `id` belongs to, in track number order. A track
+    /// without a grouping is a work of its own.
+    pub fn work(&self, id: &TrackId) -> Vec<TrackId> {
+        let Some(track) = self.track(id) else {
+            return Vec::new();
+        };
+        if track.grouping.is_none() {
+            return vec![id.clone()];
+        }
+
+        let mut parts: Vec<&Track> = self
+            .track_order
+            .iter()
+            .filter_map(|id| self.tracks.get(id))
+            .filter(|other| track.is_same_work(other))
+            .collect();
+        parts.sort_by_key(|part| part.track_number);
+        parts.into_iter().map(|part| part.id.clone()).collect()
+    }
 }
 
 impl EventEmitter<Event> for Library {}
 
-pub enum Event {}
+pub enum Event {
+    // Files read so far, out of all those found
+    ScanProgress { scanned: usize, total: usize },
+    ScanFinished,
+}
@@ -0,0 +1,156 @@
+use crate::Track;
+
+const SCHEME: &str = "gpuitunes://";
+
+// Track ids are regenerated on every load, so links identify tracks by their
+// tags instead and are resolved by matching against the library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    Track {
+        artist: String,
+        album: String,
+        title: String,
+    },
+    Album {
+        artist: String,
+        album: String,
+    },
+}
+
+impl DeepLink {
+    pub fn for_track(track: &Track) -> Self {
+        DeepLink::Track {
+            artist: track.artist().to_string(),
+            album: track.album().to_string(),
+            title: track.title().to_string(),
+        }
+    }
+
+    pub fn for_album(track: &Track) -> Self {
+        DeepLink::Album {
+            artist: track.artist().to_string(),
+            album: track.album().to_string(),
+        }
+    }
+
+    pub fn to_url(&self) -> String {
+        match self {
+            DeepLink::Track {
+                artist,
+                album,
+                title,
+            } => format!(
+                "{}track?artist={}&album={}&title={}",
+                SCHEME,
+                url_encode(artist),
+                url_encode(album),
+                url_encode(title)
+            ),
+            DeepLink::Album { artist, album } => format!(
+                "{}album?artist={}&album={}",
+                SCHEME,
+                url_encode(artist),
+                url_encode(album)
+            ),
+        }
+    }
+
+    pub fn parse(url: &str) -> Option<Self> {
+        let rest = url.strip_prefix(SCHEME)?;
+        let (kind, query) = rest.split_once('?')?;
+
+        let mut artist = None;
+        let mut album = None;
+        let mut title = None;
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=')?;
+            let value = decode(value)?;
+            match key {
+                "artist" => artist = Some(value),
+                "album" => album = Some(value),
+                "title" => title = Some(value),
+                _ => {}
+            }
+        }
+
+        match kind {
+            "track" => Some(DeepLink::Track {
+                artist: artist?,
+                album: album?,
+                title: title?,
+            }),
+            "album" => Some(DeepLink::Album {
+                artist: artist?,
+                album: album?,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, track: &Track) -> bool {
+        match self {
+            DeepLink::Track {
+                artist,
+                album,
+                title,
+            } => {
+                track.artist().as_ref() == artist
+                    && track.album().as_ref() == album
+                    && track.title().as_ref() == title
+            }
+            DeepLink::Album { artist, album } => {
+                track.artist().as_ref() == artist && track.album().as_ref() == album
+            }
+        }
+    }
+}
+
+pub fn musicbrainz_track_url(track: &Track) -> Option<String> {
+    track
+        .musicbrainz_recording_id()
+        .map(|id| format!("https://musicbrainz.org/recording/{}", id))
+}
+
+pub fn musicbrainz_album_url(track: &Track) -> Option<String> {
+    track
+        .musicbrainz_release_id()
+        .map(|id| format!("https://musicbrainz.org/release/{}", id))
+}
+
+/// Percent-encodes everything but the characters URLs leave unreserved.
+pub fn url_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut ix = 0;
+    while ix < bytes.len() {
+        match bytes[ix] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(ix + 1..ix + 3)?).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                ix += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                ix += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                ix += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
@@ -0,0 +1,204 @@
+use crate::{today, Library, Track};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write as _,
+    fs,
+    path::Path,
+};
+
+/// One track as a manifest lists it, enough to recognise the same file on
+/// another machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    #[serde(default)]
+    pub track_number: u32,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl ManifestEntry {
+    fn from_track(track: &Track) -> Self {
+        ManifestEntry {
+            id: track.id().clone().into(),
+            checksum: track.checksum().map(str::to_string),
+            title: track.title().to_string(),
+            artist: track.artist().to_string(),
+            album: track.album().to_string(),
+            album_artist: track.album_artist().to_string(),
+            track_number: track.track_number(),
+            size: track.size(),
+            path: track.path().map(|path| path.display().to_string()),
+        }
+    }
+
+    // For files that were never checksummed, or were retagged since
+    fn tag_key(&self) -> (String, String, String) {
+        (
+            self.artist.to_lowercase(),
+            self.album.to_lowercase(),
+            self.title.to_lowercase(),
+        )
+    }
+
+    fn album_key(&self) -> (String, String) {
+        (self.album_artist.to_lowercase(), self.album.to_lowercase())
+    }
+}
+
+/// Every track in a library, written out compactly to be compared against
+/// a library on another machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryManifest {
+    pub machine: String,
+    pub generated: String,
+    pub tracks: Vec<ManifestEntry>,
+}
+
+impl LibraryManifest {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    // Not pretty printed, a large library's manifest is big enough as it is
+    pub fn export(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Albums with tracks here that `other` is missing, and the other way
+    /// around. A track counts as on both when the checksums, the ids or
+    /// the artist, album and title match.
+    pub fn diff(&self, other: &LibraryManifest) -> ManifestDiff {
+        ManifestDiff {
+            here: self.machine.clone(),
+            there: other.machine.clone(),
+            only_here: missing_albums(self, other),
+            only_there: missing_albums(other, self),
+        }
+    }
+}
+
+/// An album, and the tracks of it one machine has that the other doesn't.
+#[derive(Debug, Clone)]
+pub struct ManifestAlbum {
+    pub album: String,
+    pub artist: String,
+    pub missing: Vec<ManifestEntry>,
+    // Some of it is on the other machine already
+    pub partial: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ManifestDiff {
+    pub here: String,
+    pub there: String,
+    pub only_here: Vec<ManifestAlbum>,
+    pub only_there: Vec<ManifestAlbum>,
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_here.is_empty() && self.only_there.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} albums only on {}, {} only on {}",
+            self.only_here.len(),
+            self.here,
+            self.only_there.len(),
+            self.there
+        )
+    }
+
+    /// What to copy each way to even the two libraries up, album by album,
+    /// with the path of every file on the machine that has it.
+    pub fn copy_list(&self) -> String {
+        let mut list = String::new();
+        for (from, to, albums) in [
+            (&self.here, &self.there, &self.only_here),
+            (&self.there, &self.here, &self.only_there),
+        ] {
+            if albums.is_empty() {
+                continue;
+            }
+            writeln!(list, "# Copy from {} to {}", from, to).ok();
+            for album in albums {
+                let partial = if album.partial { " (partly there)" } else { "" };
+                writeln!(list, "\n## {} — {}{}", album.artist, album.album, partial).ok();
+                for entry in &album.missing {
+                    match &entry.path {
+                        Some(path) => writeln!(list, "{}", path).ok(),
+                        None => writeln!(list, "{} (no file)", entry.title).ok(),
+                    };
+                }
+            }
+            list.push('\n');
+        }
+        list
+    }
+}
+
+fn missing_albums(from: &LibraryManifest, to: &LibraryManifest) -> Vec<ManifestAlbum> {
+    let checksums: HashSet<&str> = to
+        .tracks
+        .iter()
+        .filter_map(|entry| entry.checksum.as_deref())
+        .collect();
+    let ids: HashSet<&str> = to.tracks.iter().map(|entry| entry.id.as_str()).collect();
+    let tags: HashSet<_> = to.tracks.iter().map(ManifestEntry::tag_key).collect();
+    let is_there = |entry: &ManifestEntry| {
+        entry
+            .checksum
+            .as_deref()
+            .is_some_and(|checksum| checksums.contains(checksum))
+            || ids.contains(entry.id.as_str())
+            || tags.contains(&entry.tag_key())
+    };
+
+    let mut albums: BTreeMap<(String, String), ManifestAlbum> = BTreeMap::new();
+    for entry in &from.tracks {
+        let album = albums
+            .entry(entry.album_key())
+            .or_insert_with(|| ManifestAlbum {
+                album: entry.album.clone(),
+                artist: entry.album_artist.clone(),
+                missing: Vec::new(),
+                partial: false,
+            });
+        if is_there(entry) {
+            album.partial = true;
+        } else {
+            album.missing.push(entry.clone());
+        }
+    }
+    albums
+        .into_values()
+        .filter(|album| !album.missing.is_empty())
+        .map(|mut album| {
+            album.missing.sort_by_key(|entry| entry.track_number);
+            album
+        })
+        .collect()
+}
+
+impl Library {
+    /// Named after `machine`, so a diff can say which side is which.
+    pub fn manifest(&self, machine: &str) -> LibraryManifest {
+        LibraryManifest {
+            machine: machine.to_string(),
+            generated: today(),
+            tracks: self.tracks().map(ManifestEntry::from_track).collect(),
+        }
+    }
+}
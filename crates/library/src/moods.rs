@@ -0,0 +1,106 @@
+use crate::{ChangeSource, Library, MetadataField, Track, TrackId};
+use id3::TagLike;
+use std::path::Path;
+
+// Written as the comment's description, so it can be told apart from
+// anything else in the comments
+const MOOD_COMMENT: &str = "Moods";
+
+/// Reads moods typed or tagged as "chill, focus". They're kept lowercase,
+/// so "Chill" and "chill" are the same mood.
+pub fn parse_moods(value: &str) -> Vec<String> {
+    let mut moods: Vec<String> = Vec::new();
+    for mood in value.split([',', ';', '/']) {
+        let mood = mood.trim().to_lowercase();
+        if !mood.is_empty() && !moods.contains(&mood) {
+            moods.push(mood);
+        }
+    }
+    moods
+}
+
+/// Puts the moods into an MP3's comments, replacing any written before,
+/// so other players can see them. Other formats are left alone, and give
+/// back `false`.
+pub fn write_mood_comment(path: &Path, moods: &[String]) -> anyhow::Result<bool> {
+    let is_mp3 = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("mp3"));
+    if !is_mp3 {
+        return Ok(false);
+    }
+
+    let existing = id3::no_tag_ok(id3::Tag::read_from_path(path))?;
+    let version = existing
+        .as_ref()
+        .map_or(id3::Version::Id3v24, |tag| tag.version());
+    let mut tag = existing.unwrap_or_default();
+    tag.remove_comment(Some(MOOD_COMMENT), None);
+    if !moods.is_empty() {
+        tag.add_frame(id3::frame::Comment {
+            lang: "eng".to_string(),
+            description: MOOD_COMMENT.to_string(),
+            text: moods.join(", "),
+        });
+    }
+    tag.write_to_path(path, version)?;
+    Ok(true)
+}
+
+impl Track {
+    pub fn moods(&self) -> &[String] {
+        &self.moods
+    }
+
+    pub fn has_mood(&self, mood: &str) -> bool {
+        self.moods
+            .iter()
+            .any(|other| other.eq_ignore_ascii_case(mood))
+    }
+}
+
+impl Library {
+    /// Every mood tracks are tagged with and how many tracks have it, most
+    /// used first.
+    pub fn moods(&self) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for mood in self.tracks().flat_map(|track| track.moods()) {
+            match counts.iter_mut().find(|(other, _)| other == mood) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((mood.clone(), 1)),
+            }
+        }
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        counts
+    }
+
+    /// Tags each track with the mood, or takes it off each of them, logged
+    /// as one change. Returns the tracks that changed.
+    pub fn set_mood(&mut self, ids: &[TrackId], mood: &str, tagged: bool) -> Vec<TrackId> {
+        let mood = mood.trim().to_lowercase();
+        if mood.is_empty() {
+            return Vec::new();
+        }
+
+        let edits: Vec<_> = ids
+            .iter()
+            .filter_map(|id| {
+                let track = self.track(id)?;
+                if track.has_mood(&mood) == tagged {
+                    return None;
+                }
+                let mut moods = track.moods().to_vec();
+                if tagged {
+                    moods.push(mood.clone());
+                } else {
+                    moods.retain(|other| other != &mood);
+                }
+                let value = (!moods.is_empty()).then(|| moods.join(", "));
+                Some((id.clone(), MetadataField::Mood, value))
+            })
+            .collect();
+        let changed = edits.iter().map(|(id, _, _)| id.clone()).collect();
+        self.edit_metadata_batch(edits, ChangeSource::User);
+        changed
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{today, url_encode, Library, Track, Wish};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub(crate) const MUSICBRAINZ_API: &str = "https://musicbrainz.org/ws/2";
+// MusicBrainz turns away clients that don't say who they are
+pub(crate) const USER_AGENT: &str = concat!(
+    "gpuiTunes/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/iamnbutler/gpuitunes )"
+);
+
+pub const PLACEHOLDER_KIND: &str = "Placeholder";
+
+/// A release group MusicBrainz lists for a followed artist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewRelease {
+    // The MusicBrainz release group id
+    pub id: String,
+    pub artist: String,
+    pub title: String,
+    // "Album", "EP", "Single" and so on
+    pub kind: String,
+    // "2005-05-09", or just the year or month when that's all that's known
+    pub date: String,
+}
+
+impl NewRelease {
+    pub fn url(&self) -> String {
+        format!("https://musicbrainz.org/release-group/{}", self.id)
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(rename = "release-groups", default)]
+    release_groups: Vec<ReleaseGroup>,
+}
+
+#[derive(Deserialize)]
+struct ReleaseGroup {
+    id: String,
+    title: String,
+    #[serde(rename = "primary-type")]
+    primary_type: Option<String>,
+    #[serde(rename = "first-release-date", default)]
+    first_release_date: String,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+}
+
+#[derive(Deserialize)]
+struct ArtistCredit {
+    name: String,
+}
+
+/// Release groups credited to `artist` that first came out on or after
+/// `since`, newest first. Blocks until MusicBrainz answers.
+pub fn fetch_new_releases(artist: &str, since: &str) -> anyhow::Result<Vec<NewRelease>> {
+    let query = format!(
+        "artist:\"{}\" AND firstreleasedate:[{} TO *]",
+        artist.replace('"', ""),
+        since
+    );
+    let url = format!(
+        "{}/release-group?query={}&fmt=json&limit=50",
+        MUSICBRAINZ_API,
+        url_encode(&query)
+    );
+    let response: SearchResponse = ureq::get(&url)
+        .set("User-Agent", USER_AGENT)
+        .call()?
+        .into_json()?;
+
+    let mut releases: Vec<NewRelease> = response
+        .release_groups
+        .into_iter()
+        // Search matches loosely, so only the artist's own releases are kept
+        .filter(|group| {
+            group
+                .artist_credit
+                .iter()
+                .any(|credit| credit.name.eq_ignore_ascii_case(artist))
+        })
+        .filter(|group| {
+            !group.first_release_date.is_empty() && group.first_release_date.as_str() >= since
+        })
+        .map(|group| NewRelease {
+            id: group.id,
+            artist: artist.to_string(),
+            title: group.title,
+            kind: group.primary_type.unwrap_or_else(|| "Release".to_string()),
+            date: group.first_release_date,
+        })
+        .collect();
+    releases.sort_by(|a, b| b.date.cmp(&a.date));
+    Ok(releases)
+}
+
+/// Everything found for the followed artists so far, kept between checks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NewReleaseFeed {
+    // Newest first
+    pub releases: Vec<NewRelease>,
+    // Seconds since the Unix epoch
+    pub last_checked: Option<u64>,
+}
+
+impl NewReleaseFeed {
+    fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join("new-releases.json")
+    }
+
+    pub fn load(data_dir: &Path) -> Self {
+        let Ok(json) = fs::read_to_string(Self::path(data_dir)) else {
+            return NewReleaseFeed::default();
+        };
+
+        serde_json::from_str(&json).unwrap_or_else(|e| {
+            eprintln!("Failed to parse new releases: {}", e);
+            NewReleaseFeed::default()
+        })
+    }
+
+    pub fn save(&self, data_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::path(data_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds the releases that weren't in the feed yet, returning how many.
+    pub fn merge(&mut self, releases: Vec<NewRelease>) -> usize {
+        let before = self.releases.len();
+        for release in releases {
+            if !self.releases.iter().any(|other| other.id == release.id) {
+                self.releases.push(release);
+            }
+        }
+        self.releases.sort_by(|a, b| b.date.cmp(&a.date));
+        self.releases.len() - before
+    }
+
+    // Releases by artists that have since been unfollowed go with them
+    pub fn retain_artists(&mut self, artists: &[String]) {
+        self.releases.retain(|release| {
+            artists
+                .iter()
+                .any(|artist| artist.eq_ignore_ascii_case(&release.artist))
+        });
+    }
+}
+
+impl Track {
+    /// Stands in for music that isn't in the library yet, such as a
+    /// release added to the wishlist.
+    pub fn is_placeholder(&self) -> bool {
+        self.path().is_none() && self.kind() == PLACEHOLDER_KIND
+    }
+}
+
+impl Library {
+    /// Whether there's already an album by that name from the artist.
+    pub fn owns_release(&self, release: &NewRelease) -> bool {
+        self.tracks().any(|track| {
+            !track.is_placeholder()
+                && track.album().eq_ignore_ascii_case(&release.title)
+                && (track.artist().eq_ignore_ascii_case(&release.artist)
+                    || track.album_artist().eq_ignore_ascii_case(&release.artist))
+        })
+    }
+
+    pub fn add_to_wishlist(&mut self, release: &NewRelease) -> bool {
+        let wish = Wish {
+            artist: release.artist.clone(),
+            album: release.title.clone(),
+            added: today(),
+            fulfilled: None,
+        };
+        self.add_wish(
+            wish,
+            release.date.get(..4).and_then(|year| year.parse().ok()),
+        )
+    }
+}
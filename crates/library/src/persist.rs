@@ -0,0 +1,175 @@
+use crate::{
+    default_columns, DeletedTrack, Library, Playlist, SerializableDeletedTrack,
+    SerializableLibrary, SerializablePlaylist, SerializableTrack, Track, UndoStack,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+impl From<&Track> for SerializableTrack {
+    fn from(track: &Track) -> Self {
+        SerializableTrack {
+            id: Some(track.id.0.clone()),
+            title: track.title.to_string(),
+            artist: track.artist.to_string(),
+            album: track.album.to_string(),
+            duration: track.duration,
+            kind: track.kind.clone(),
+            date_added: track.date_added.clone(),
+            plays: track.plays,
+            track_number: track.track_number,
+            total_tracks: track.total_tracks,
+            disc_number: track.disc_number,
+            size: track.size,
+            grouping: track.grouping.as_ref().map(|grouping| grouping.to_string()),
+            genre: track.genre.clone(),
+            album_artist: track.album_artist.as_ref().map(|artist| artist.to_string()),
+            year: track.year,
+            release_date: track.release_date.clone(),
+            original_release_date: track.original_release_date.clone(),
+            language: track.language.clone(),
+            moods: track.moods.clone(),
+            key: track.key.map(|key| key.camelot()),
+            custom_fields: track.custom_fields.clone(),
+            musicbrainz_recording_id: track.musicbrainz_recording_id.clone(),
+            musicbrainz_release_id: track.musicbrainz_release_id.clone(),
+            last_played: track.last_played.clone(),
+            artwork: track.artwork.clone(),
+            gapless: track.gapless,
+            leading_silence_ms: track.leading_silence_ms,
+            trailing_silence_ms: track.trailing_silence_ms,
+            rating: track.rating,
+            album_rating: track.album_rating,
+            version_group: track.version_group.clone(),
+            version: track.version,
+            path: track.path.clone(),
+            checksum: track.checksum.clone(),
+        }
+    }
+}
+
+impl SerializableLibrary {
+    pub(crate) fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join("library.json")
+    }
+
+    /// The library saved in `data_dir`, if there is one.
+    pub fn load(data_dir: &Path) -> Option<Self> {
+        let path = Self::path(data_dir);
+        let json = fs::read_to_string(&path).ok()?;
+
+        match serde_json::from_str(&json) {
+            Ok(library) => Some(library),
+            Err(e) => {
+                eprintln!("Failed to parse library: {}", e);
+                // Kept out of the way rather than saved over, so nothing is
+                // lost if it can be put right by hand
+                if let Err(e) = fs::rename(&path, path.with_extension("json.unreadable")) {
+                    eprintln!("Failed to set aside unreadable library: {}", e);
+                }
+                None
+            }
+        }
+    }
+
+    // Written alongside and then moved into place, so quitting part way
+    // through leaves the last save as it was
+    pub fn save(&self, data_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::path(data_dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, serde_json::to_string(self)?)?;
+        fs::rename(partial, path)?;
+        Ok(())
+    }
+}
+
+impl Library {
+    /// Everything that's kept between launches, ready to be saved off the
+    /// main thread.
+    pub fn serializable(&self) -> SerializableLibrary {
+        SerializableLibrary {
+            tracks: self
+                .track_order
+                .iter()
+                .filter_map(|id| self.tracks.get(id))
+                .filter(|track| !track.external)
+                .map(SerializableTrack::from)
+                .collect(),
+            columns: self.columns.clone(),
+            playlists: self
+                .playlists
+                .iter()
+                .map(SerializablePlaylist::from)
+                .collect(),
+            wishlist: self.wishlist.clone(),
+            alternate_copies: self.alternate_copies.clone(),
+            playlist_history: self.serializable_playlist_history(),
+            import_sessions: self.import_sessions.clone(),
+            recently_deleted: self
+                .recently_deleted
+                .iter()
+                .map(SerializableDeletedTrack::from)
+                .collect(),
+            metadata_log: self.metadata_log.clone(),
+        }
+    }
+
+    pub(crate) fn restore(&mut self, saved: SerializableLibrary) {
+        self.undo_stack = UndoStack::default();
+        self.changed_tracks.clear();
+        self.tracks.clear();
+        self.track_order.clear();
+        for track in saved.tracks {
+            let track = Track::from(track);
+            self.track_order.push(track.id.clone());
+            self.pending_artwork.push(track.id.clone());
+            self.tracks.insert(track.id.clone(), track);
+        }
+        if !saved.columns.is_empty() {
+            self.columns = saved.columns;
+            // Columns added since the library was saved go on the end
+            for column in default_columns() {
+                if !self.columns.iter().any(|other| other.kind == column.kind) {
+                    self.columns.push(column);
+                }
+            }
+        }
+
+        // Tracks that went missing from the save leave their playlists too
+        let playlists = saved
+            .playlists
+            .into_iter()
+            .map(Playlist::from)
+            .map(|mut playlist| {
+                playlist.retain_tracks(|id| self.tracks.contains_key(id));
+                playlist
+            })
+            .collect();
+        self.set_playlists(playlists);
+        self.restore_playlist_history(saved.playlist_history);
+        self.wishlist = saved.wishlist;
+        self.import_sessions = saved.import_sessions;
+        self.alternate_copies = saved.alternate_copies;
+        self.alternate_copies
+            .retain(|copy| self.tracks.contains_key(&copy.track));
+        self.recently_deleted = saved
+            .recently_deleted
+            .into_iter()
+            .map(DeletedTrack::from)
+            .collect();
+        self.purge_expired();
+        // New changes carry on from the saved ones rather than joining
+        // their batches
+        self.next_change_batch = saved
+            .metadata_log
+            .iter()
+            .map(|change| change.batch)
+            .max()
+            .unwrap_or(0);
+        self.metadata_log = saved.metadata_log;
+    }
+}
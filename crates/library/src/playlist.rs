@@ -0,0 +1,301 @@
+use crate::{sort::move_item, ColumnKind, Library, SmartCriteria, SortDirection, TrackId};
+use gpui::SharedString;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistId(String);
+
+impl PlaylistId {
+    fn new() -> Self {
+        PlaylistId(uuid::Uuid::new_v4().to_string())
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Shown in place of the playlist's usual icon in the sidebar.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum PlaylistIcon {
+    Emoji(String),
+    // One of the app's own icons, by name
+    Symbol(String),
+}
+
+/// A playlist as it's saved in library.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerializablePlaylist {
+    id: String,
+    name: String,
+    tracks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<PlaylistIcon>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    criteria: Option<SmartCriteria>,
+}
+
+impl From<&Playlist> for SerializablePlaylist {
+    fn from(playlist: &Playlist) -> Self {
+        SerializablePlaylist {
+            id: playlist.id.0.clone(),
+            name: playlist.name.to_string(),
+            tracks: playlist.tracks.iter().map(|id| id.0.clone()).collect(),
+            icon: playlist.icon.clone(),
+            criteria: playlist.criteria.clone(),
+        }
+    }
+}
+
+impl From<SerializablePlaylist> for Playlist {
+    fn from(playlist: SerializablePlaylist) -> Self {
+        Playlist {
+            id: PlaylistId(playlist.id),
+            name: playlist.name.into(),
+            tracks: playlist.tracks.into_iter().map(TrackId::from).collect(),
+            icon: playlist.icon,
+            criteria: playlist.criteria,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    id: PlaylistId,
+    name: SharedString,
+    tracks: Vec<TrackId>,
+    icon: Option<PlaylistIcon>,
+    // Set for smart playlists, whose tracks come from their rules instead
+    criteria: Option<SmartCriteria>,
+}
+
+impl Playlist {
+    pub fn id(&self) -> &PlaylistId {
+        &self.id
+    }
+
+    pub fn name(&self) -> SharedString {
+        self.name.clone()
+    }
+
+    pub fn tracks(&self) -> &[TrackId] {
+        &self.tracks
+    }
+
+    pub fn icon(&self) -> Option<&PlaylistIcon> {
+        self.icon.as_ref()
+    }
+
+    pub fn criteria(&self) -> Option<&SmartCriteria> {
+        self.criteria.as_ref()
+    }
+
+    pub fn is_smart(&self) -> bool {
+        self.criteria.is_some()
+    }
+
+    pub fn contains(&self, track_id: &TrackId) -> bool {
+        self.tracks.contains(track_id)
+    }
+
+    pub fn position(&self, track_id: &TrackId) -> Option<usize> {
+        self.tracks.iter().position(|id| id == track_id)
+    }
+
+    pub(crate) fn retain_tracks(&mut self, f: impl FnMut(&TrackId) -> bool) {
+        self.tracks.retain(f);
+    }
+
+    pub(crate) fn set_tracks(&mut self, tracks: Vec<TrackId>) {
+        self.tracks = tracks;
+    }
+}
+
+impl Library {
+    pub fn playlists(&self) -> &[Playlist] {
+        &self.playlists
+    }
+
+    pub fn playlist(&self, id: &PlaylistId) -> Option<&Playlist> {
+        self.playlists.iter().find(|playlist| &playlist.id == id)
+    }
+
+    pub(crate) fn playlist_mut(&mut self, id: &PlaylistId) -> Option<&mut Playlist> {
+        self.playlists
+            .iter_mut()
+            .find(|playlist| &playlist.id == id)
+    }
+
+    pub fn create_playlist(&mut self, name: impl Into<SharedString>) -> PlaylistId {
+        let id = PlaylistId::new();
+        self.playlists.push(Playlist {
+            id: id.clone(),
+            name: name.into(),
+            tracks: Vec::new(),
+            icon: None,
+            criteria: None,
+        });
+        id
+    }
+
+    pub fn create_smart_playlist(
+        &mut self,
+        name: impl Into<SharedString>,
+        criteria: SmartCriteria,
+    ) -> PlaylistId {
+        let id = PlaylistId::new();
+        self.playlists.push(Playlist {
+            id: id.clone(),
+            name: name.into(),
+            tracks: Vec::new(),
+            icon: None,
+            criteria: Some(criteria),
+        });
+        id
+    }
+
+    /// The playlist's tracks, evaluating its rules if it's a smart playlist.
+    pub fn playlist_tracks(&self, id: &PlaylistId) -> Vec<TrackId> {
+        match self.playlist(id) {
+            Some(Playlist {
+                criteria: Some(criteria),
+                ..
+            }) => self.smart_tracks(criteria),
+            Some(playlist) => playlist.tracks.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Blank names are ignored, the playlist keeps the one it had.
+    pub fn rename_playlist(&mut self, id: &PlaylistId, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        if let Some(playlist) = self.playlist_mut(id) {
+            playlist.name = name.to_string().into();
+        }
+    }
+
+    /// Removes the playlist along with its version history. Its tracks stay
+    /// in the library.
+    pub fn delete_playlist(&mut self, id: &PlaylistId) {
+        self.playlists.retain(|playlist| &playlist.id != id);
+        self.playlist_history.remove(id);
+        self.playlist_index.retain(|_, containing| {
+            containing.retain(|other| other != id);
+            !containing.is_empty()
+        });
+    }
+
+    /// `None` goes back to the icon for the kind of playlist.
+    pub fn set_playlist_icon(&mut self, id: &PlaylistId, icon: Option<PlaylistIcon>) {
+        if let Some(playlist) = self.playlist_mut(id) {
+            playlist.icon = icon;
+        }
+    }
+
+    pub fn add_to_playlist(&mut self, id: &PlaylistId, track_ids: &[TrackId]) {
+        self.snapshot_playlist(id, false);
+        let Some(playlist) = self.playlist_mut(id) else {
+            return;
+        };
+        playlist.tracks.extend(track_ids.iter().cloned());
+
+        for track_id in track_ids {
+            let containing = self.playlist_index.entry(track_id.clone()).or_default();
+            if !containing.contains(id) {
+                containing.push(id.clone());
+            }
+        }
+    }
+
+    pub fn remove_from_playlist(&mut self, id: &PlaylistId, track_ids: &[TrackId]) {
+        self.snapshot_playlist(id, track_ids.len() > 1);
+        let Some(playlist) = self.playlist_mut(id) else {
+            return;
+        };
+        playlist
+            .tracks
+            .retain(|track_id| !track_ids.contains(track_id));
+
+        for track_id in track_ids {
+            if let Some(containing) = self.playlist_index.get_mut(track_id) {
+                containing.retain(|other| other != id);
+                if containing.is_empty() {
+                    self.playlist_index.remove(track_id);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn insert_into_playlist(
+        &mut self,
+        id: &PlaylistId,
+        position: usize,
+        track_id: TrackId,
+    ) {
+        self.snapshot_playlist(id, false);
+        let Some(playlist) = self.playlist_mut(id) else {
+            return;
+        };
+        playlist
+            .tracks
+            .insert(position.min(playlist.tracks.len()), track_id.clone());
+
+        let containing = self.playlist_index.entry(track_id).or_default();
+        if !containing.contains(id) {
+            containing.push(id.clone());
+        }
+    }
+
+    pub fn move_in_playlist(&mut self, id: &PlaylistId, from: usize, to: usize) {
+        self.snapshot_playlist(id, false);
+        if let Some(playlist) = self.playlist_mut(id) {
+            move_item(&mut playlist.tracks, from, to);
+        }
+    }
+
+    // "Copy current sort as manual order"
+    pub fn sort_playlist_by_column(
+        &mut self,
+        id: &PlaylistId,
+        kind: &ColumnKind,
+        direction: SortDirection,
+    ) {
+        self.snapshot_playlist(id, false);
+        let Some(playlist) = self.playlist(id) else {
+            return;
+        };
+        let sorted = self.sorted(&playlist.tracks, kind, direction);
+        if let Some(playlist) = self.playlist_mut(id) {
+            playlist.tracks = sorted;
+        }
+    }
+
+    // Rebuilds the index after the playlists have been replaced wholesale
+    pub(crate) fn set_playlists(&mut self, playlists: Vec<Playlist>) {
+        self.playlist_index.clear();
+        for playlist in &playlists {
+            for track_id in &playlist.tracks {
+                let containing = self.playlist_index.entry(track_id.clone()).or_default();
+                if !containing.contains(&playlist.id) {
+                    containing.push(playlist.id.clone());
+                }
+            }
+        }
+        self.playlists = playlists;
+    }
+
+    /// Every playlist the track appears in, in sidebar order.
+    pub fn playlists_containing(&self, track_id: &TrackId) -> Vec<&Playlist> {
+        let Some(containing) = self.playlist_index.get(track_id) else {
+            return Vec::new();
+        };
+        self.playlists
+            .iter()
+            .filter(|playlist| containing.contains(&playlist.id))
+            .collect()
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{unix_time, Library, PlaylistId, TrackId};
+use serde::{Deserialize, Serialize};
+
+// Edits closer together than this share the snapshot taken before the first
+const SNAPSHOT_INTERVAL: u64 = 10 * 60;
+const MAX_VERSIONS: usize = 100;
+
+/// A playlist's tracks as they were just before it was edited.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistVersion {
+    pub taken: u64,
+    pub tracks: Vec<TrackId>,
+}
+
+/// A playlist's versions as they're saved in library.json.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerializablePlaylistHistory {
+    playlist: String,
+    versions: Vec<PlaylistVersion>,
+}
+
+/// What's changed in a playlist since one of its versions.
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistDiff {
+    pub added: Vec<TrackId>,
+    pub removed: Vec<TrackId>,
+}
+
+impl PlaylistDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl Library {
+    /// Oldest first. Smart playlists have none, since their rules decide
+    /// what's in them.
+    pub fn playlist_versions(&self, id: &PlaylistId) -> &[PlaylistVersion] {
+        self.playlist_history
+            .get(id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn playlist_diff(&self, id: &PlaylistId, version: &PlaylistVersion) -> PlaylistDiff {
+        let Some(playlist) = self.playlist(id) else {
+            return PlaylistDiff::default();
+        };
+        PlaylistDiff {
+            added: playlist
+                .tracks()
+                .iter()
+                .filter(|id| !version.tracks.contains(id))
+                .cloned()
+                .collect(),
+            removed: version
+                .tracks
+                .iter()
+                .filter(|id| !playlist.contains(id))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Puts the playlist back as it was when `taken`, keeping what it holds
+    /// now as a version of its own. Tracks since removed from the library
+    /// stay out.
+    pub fn restore_playlist_version(&mut self, id: &PlaylistId, taken: u64) -> bool {
+        let Some(version) = self
+            .playlist_versions(id)
+            .iter()
+            .find(|version| version.taken == taken)
+            .cloned()
+        else {
+            return false;
+        };
+        self.snapshot_playlist(id, true);
+
+        let tracks = version
+            .tracks
+            .into_iter()
+            .filter(|track_id| self.tracks.contains_key(track_id))
+            .collect();
+        let Some(playlist) = self.playlist_mut(id) else {
+            return false;
+        };
+        playlist.set_tracks(tracks);
+        let playlists = std::mem::take(&mut self.playlists);
+        self.set_playlists(playlists);
+        true
+    }
+
+    // Called before each edit. `force` is for edits worth a version of their
+    // own, like taking out several tracks at once.
+    pub(crate) fn snapshot_playlist(&mut self, id: &PlaylistId, force: bool) {
+        let Some(playlist) = self.playlist(id).filter(|playlist| !playlist.is_smart()) else {
+            return;
+        };
+        let tracks = playlist.tracks().to_vec();
+        let now = unix_time();
+
+        let versions = self.playlist_history.entry(id.clone()).or_default();
+        if let Some(last) = versions.last() {
+            if last.tracks == tracks
+                || (!force && now.saturating_sub(last.taken) < SNAPSHOT_INTERVAL)
+            {
+                return;
+            }
+        }
+        versions.push(PlaylistVersion { taken: now, tracks });
+        if versions.len() > MAX_VERSIONS {
+            versions.drain(..versions.len() - MAX_VERSIONS);
+        }
+    }
+
+    pub(crate) fn serializable_playlist_history(&self) -> Vec<SerializablePlaylistHistory> {
+        self.playlists
+            .iter()
+            .filter_map(|playlist| {
+                let versions = self.playlist_history.get(playlist.id())?;
+                Some(SerializablePlaylistHistory {
+                    playlist: playlist.id().as_str().to_string(),
+                    versions: versions.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn restore_playlist_history(&mut self, saved: Vec<SerializablePlaylistHistory>) {
+        self.playlist_history.clear();
+        for history in saved {
+            let Some(playlist) = self
+                .playlists
+                .iter()
+                .find(|playlist| playlist.id().as_str() == history.playlist)
+            else {
+                continue;
+            };
+            self.playlist_history
+                .insert(playlist.id().clone(), history.versions);
+        }
+    }
+}
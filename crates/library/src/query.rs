@@ -0,0 +1,288 @@
+use crate::{Comparison, MusicalKey, RuleField, SmartCriteria, SmartMatch, SmartRule};
+use std::{fmt, ops::Range};
+
+/// Where a search query stopped making sense, as a byte range into it so
+/// the search field can underline it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+impl QueryError {
+    fn new(range: Range<usize>, message: impl Into<String>) -> Self {
+        QueryError {
+            range,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Parses a search such as `artist:"Miles Davis" year:1959..1965 rating:>=4`
+/// into the rules smart playlists use. Every term has to match. Words
+/// without a field are looked for in the title, artist, album and kind.
+/// Custom fields are looked at as `custom.source:vinyl`, and `key:~8A`
+/// finds the keys that mix with 8A.
+pub fn parse_query(query: &str) -> Result<SmartCriteria, QueryError> {
+    let rules = split_terms(query)?
+        .into_iter()
+        .map(|range| parse_term(query, range))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(SmartCriteria::new(SmartMatch::All, rules))
+}
+
+// Terms are separated by spaces, except inside quotes
+fn split_terms(query: &str) -> Result<Vec<Range<usize>>, QueryError> {
+    let mut terms = Vec::new();
+    let mut chars = query.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut end = query.len();
+        let mut open_quote = None;
+        while let Some(&(ix, c)) = chars.peek() {
+            if c == '"' {
+                open_quote = match open_quote {
+                    Some(_) => None,
+                    None => Some(ix),
+                };
+            } else if c.is_whitespace() && open_quote.is_none() {
+                end = ix;
+                break;
+            }
+            chars.next();
+        }
+        if let Some(quote) = open_quote {
+            return Err(QueryError::new(quote..query.len(), "Missing closing quote"));
+        }
+        terms.push(start..end);
+    }
+
+    Ok(terms)
+}
+
+fn parse_term(query: &str, range: Range<usize>) -> Result<SmartRule, QueryError> {
+    let term = &query[range.clone()];
+    let Some(colon) = term.find(':').filter(|colon| !term[..*colon].contains('"')) else {
+        return Ok(SmartRule::Text {
+            text: unquote(term).to_string(),
+        });
+    };
+
+    let key = &term[..colon];
+    let key_range = range.start..range.start + colon;
+    let value = unquote(&term[colon + 1..]);
+    let value_range = range.start + colon + 1..range.end;
+
+    if key.eq_ignore_ascii_case("is") {
+        return match value.to_lowercase().as_str() {
+            "played" => Ok(SmartRule::Played),
+            "unplayed" => Ok(SmartRule::Unplayed),
+            _ => Err(QueryError::new(value_range, "Expected played or unplayed")),
+        };
+    }
+
+    let custom = key
+        .split_once('.')
+        .filter(|(prefix, name)| prefix.eq_ignore_ascii_case("custom") && !name.is_empty());
+    if let Some((_, name)) = custom {
+        if value.is_empty() {
+            return Err(QueryError::new(
+                range,
+                format!("Missing a value for {}", name),
+            ));
+        }
+        return Ok(SmartRule::CustomField {
+            name: name.to_string(),
+            text: value.to_string(),
+        });
+    }
+
+    if key.eq_ignore_ascii_case("key") {
+        let (compatible, name) = match value.strip_prefix('~') {
+            Some(name) => (true, name),
+            None => (false, value),
+        };
+        let key = MusicalKey::parse(name)
+            .ok_or_else(|| QueryError::new(value_range, "Expected a key such as 8A or Am"))?;
+        return Ok(SmartRule::Key { key, compatible });
+    }
+
+    let field = RuleField::from_key(key)
+        .ok_or_else(|| QueryError::new(key_range, format!("Unknown field “{}”", key)))?;
+    if value.is_empty() {
+        return Err(QueryError::new(
+            range,
+            format!("Missing a value for {}", key),
+        ));
+    }
+    if !field.is_numeric() {
+        return Ok(SmartRule::Contains {
+            field,
+            text: value.to_string(),
+        });
+    }
+
+    if let Some((min, max)) = value.split_once("..") {
+        let (Some(min), Some(max)) = (number(min), number(max)) else {
+            return Err(QueryError::new(
+                value_range,
+                "Expected a range such as 1959..1965",
+            ));
+        };
+        if min > max {
+            return Err(QueryError::new(
+                value_range,
+                "The range ends before it starts",
+            ));
+        }
+        return Ok(SmartRule::Between { field, min, max });
+    }
+
+    let (comparison, rest) = [
+        (">=", Comparison::GreaterOrEqual),
+        ("<=", Comparison::LessOrEqual),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ]
+    .into_iter()
+    .find_map(|(prefix, comparison)| Some((comparison, value.strip_prefix(prefix)?)))
+    .unwrap_or((Comparison::Equal, value));
+
+    // Decades read naturally as "1960s"
+    let rest = match field {
+        RuleField::Decade => rest.trim_end_matches('s'),
+        _ => rest,
+    };
+    let value = number(rest).ok_or_else(|| QueryError::new(value_range, "Expected a number"))?;
+    Ok(SmartRule::Compare {
+        field,
+        comparison,
+        value,
+    })
+}
+
+fn number(text: &str) -> Option<u32> {
+    text.trim().parse().ok()
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(query: &str) -> Vec<SmartRule> {
+        parse_query(query).unwrap().rules
+    }
+
+    #[test]
+    fn quoted_values_keep_their_spaces() {
+        assert_eq!(
+            rules(r#"artist:"Miles Davis" "kind of blue""#),
+            vec![
+                SmartRule::Contains {
+                    field: RuleField::Artist,
+                    text: "Miles Davis".to_string(),
+                },
+                SmartRule::Text {
+                    text: "kind of blue".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges_are_inclusive_and_in_order() {
+        assert_eq!(
+            rules("year:1959..1965"),
+            vec![SmartRule::Between {
+                field: RuleField::Year,
+                min: 1959,
+                max: 1965,
+            }]
+        );
+        let error = parse_query("year:1965..1959").unwrap_err();
+        assert_eq!(error.range, 5..15);
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(
+            rules("rating:>=4 plays:<3 decade:1960s"),
+            vec![
+                SmartRule::Compare {
+                    field: RuleField::Rating,
+                    comparison: Comparison::GreaterOrEqual,
+                    value: 4,
+                },
+                SmartRule::Compare {
+                    field: RuleField::Plays,
+                    comparison: Comparison::Less,
+                    value: 3,
+                },
+                SmartRule::Compare {
+                    field: RuleField::Decade,
+                    comparison: Comparison::Equal,
+                    value: 1960,
+                },
+            ]
+        );
+        let error = parse_query("rating:>=four").unwrap_err();
+        assert_eq!(error.range, 7..13);
+    }
+
+    #[test]
+    fn unknown_fields_are_underlined() {
+        let error = parse_query("jazz genre:bebop").unwrap_err();
+        assert_eq!(error.range, 5..10);
+        assert_eq!(error.message, "Unknown field “genre”");
+    }
+
+    #[test]
+    fn unclosed_quotes_run_to_the_end() {
+        let query = r#"year:1959 artist:"Miles"#;
+        let error = parse_query(query).unwrap_err();
+        assert_eq!(&query[error.range.clone()], "\"Miles");
+        assert_eq!(error.message, "Missing closing quote");
+    }
+
+    #[test]
+    fn non_ascii_ranges_are_in_bytes() {
+        assert_eq!(
+            rules("artist:Björk Ágætis"),
+            vec![
+                SmartRule::Contains {
+                    field: RuleField::Artist,
+                    text: "Björk".to_string(),
+                },
+                SmartRule::Text {
+                    text: "Ágætis".to_string(),
+                },
+            ]
+        );
+
+        let query = "über:x";
+        let error = parse_query(query).unwrap_err();
+        assert_eq!(&query[error.range], "über");
+
+        let query = r#"Sigur "Rós"#;
+        let error = parse_query(query).unwrap_err();
+        assert_eq!(&query[error.range], "\"Rós");
+    }
+}
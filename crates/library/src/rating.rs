@@ -0,0 +1,81 @@
+use crate::{Album, ChangeSource, Library, MetadataField, Track, TrackId};
+
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlbumRating {
+    Explicit(u8),
+    // Averaged from the album's rated tracks, drawn as hollow stars
+    Computed(u8),
+}
+
+impl AlbumRating {
+    pub fn stars(&self) -> u8 {
+        match self {
+            AlbumRating::Explicit(stars) | AlbumRating::Computed(stars) => *stars,
+        }
+    }
+
+    pub fn is_computed(&self) -> bool {
+        matches!(self, AlbumRating::Computed(_))
+    }
+}
+
+impl Library {
+    pub fn set_rating(&mut self, ids: &[TrackId], rating: Option<u8>) {
+        let rating = rating.map(|stars| stars.min(MAX_RATING).to_string());
+        self.edit_metadata(ids, MetadataField::Rating, rating, ChangeSource::User);
+    }
+
+    pub fn album_rating(&self, id: &TrackId) -> Option<AlbumRating> {
+        rating_of(&self.album_tracks(id))
+    }
+
+    /// The same as `album_rating`, for an album already gathered up.
+    pub fn rating_of_album(&self, album: &Album) -> Option<AlbumRating> {
+        let tracks: Vec<&Track> = album
+            .tracks
+            .iter()
+            .filter_map(|id| self.track(id))
+            .collect();
+        rating_of(&tracks)
+    }
+
+    // Stored on every track of the album, the way iTunes keeps it
+    pub fn set_album_rating(&mut self, id: &TrackId, rating: Option<u8>) {
+        let rating = rating.map(|stars| stars.min(MAX_RATING).to_string());
+        let ids: Vec<TrackId> = self
+            .album_tracks(id)
+            .into_iter()
+            .map(|track| track.id().clone())
+            .collect();
+        self.edit_metadata(&ids, MetadataField::AlbumRating, rating, ChangeSource::User);
+    }
+
+    /// Rates the album `id` is on. Picking its current rating again
+    /// clears it, falling back to the one worked out from its tracks.
+    pub fn rate_album(&mut self, id: &TrackId, stars: u8) {
+        let rating = match self.album_rating(id) {
+            Some(AlbumRating::Explicit(current)) if current == stars => None,
+            _ => Some(stars),
+        };
+        self.set_album_rating(id, rating);
+    }
+}
+
+fn rating_of(tracks: &[&Track]) -> Option<AlbumRating> {
+    if let Some(stars) = tracks.iter().find_map(|track| track.album_rating()) {
+        return Some(AlbumRating::Explicit(stars));
+    }
+
+    let ratings: Vec<u32> = tracks
+        .iter()
+        .filter_map(|track| track.rating())
+        .map(u32::from)
+        .collect();
+    if ratings.is_empty() {
+        return None;
+    }
+    let average = ratings.iter().sum::<u32>() as f32 / ratings.len() as f32;
+    Some(AlbumRating::Computed(average.round() as u8))
+}
@@ -0,0 +1,221 @@
+use crate::{
+    audio_files_in, detect_language, language_name, measure_silence, parse_moods,
+    parse_release_date, Event, ImportedFile, Library, MusicalKey,
+};
+use gpui::{ModelContext, Task};
+use std::{
+    collections::HashSet,
+    fs::File,
+    path::{Path, PathBuf},
+};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+    probe::Hint,
+};
+
+// Files read between each update, so tracks show up while the rest are read
+const SCAN_BATCH: usize = 50;
+
+// Where the key is kept, by the ID3 frame or the Vorbis comment field
+const KEY_TAGS: &[&str] = &["TKEY", "INITIALKEY", "KEY"];
+
+/// Reads a file's tags and length, and how much silence it starts and ends
+/// with. Anything the tags leave out is guessed from an
+/// "Artist/Album/01 Title.mp3" layout.
+pub fn read_tags(path: &Path) -> ImportedFile {
+    let folder_name = |levels: usize| {
+        path.ancestors()
+            .nth(levels)
+            .and_then(|folder| folder.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+    };
+    let album = folder_name(1).unwrap_or_default();
+    let artist = folder_name(2).unwrap_or_else(|| "Unknown Artist".to_string());
+
+    let mut file = ImportedFile::from_path(path.to_path_buf(), &artist, &album);
+    if let Err(e) = apply_tags(&mut file) {
+        eprintln!("Failed to read tags from {}: {}", path.display(), e);
+    }
+    file.silence = measure_silence(path)
+        .map_err(|e| eprintln!("Failed to measure silence in {}: {}", path.display(), e))
+        .ok();
+    file
+}
+
+fn apply_tags(file: &mut ImportedFile) -> symphonia::core::errors::Result<()> {
+    let stream = MediaSourceStream::new(Box::new(File::open(&file.path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = file.path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut probed = symphonia::default::get_probe().format(
+        &hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+
+    if let Some(track) = probed.format.default_track() {
+        let params = &track.codec_params;
+        if let (Some(frames), Some(sample_rate)) = (params.n_frames, params.sample_rate) {
+            file.duration = (frames / sample_rate.max(1) as u64) as i32;
+        }
+    }
+
+    // Tags ahead of the container, such as ID3, then the container's own
+    let mut revisions: Vec<MetadataRevision> = Vec::new();
+    if let Some(metadata) = probed.metadata.get() {
+        revisions.extend(metadata.current().cloned());
+    }
+    revisions.extend(probed.format.metadata().current().cloned());
+
+    let mut lyrics = None;
+    for tag in revisions.iter().flat_map(|revision| revision.tags()) {
+        let value = tag.value.to_string();
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => file.title = value.to_string(),
+            Some(StandardTagKey::Artist) => file.artist = value.to_string(),
+            Some(StandardTagKey::Album) => file.album = value.to_string(),
+            Some(StandardTagKey::AlbumArtist) => file.album_artist = Some(value.to_string()),
+            Some(StandardTagKey::Genre) => file.genre = Some(value.to_string()),
+            // "2005", "2005-05-09" and the like
+            Some(StandardTagKey::Date) => {
+                file.year = value
+                    .get(..4)
+                    .and_then(|year| year.parse().ok())
+                    .or(file.year);
+                if file.release_date.is_none() {
+                    file.release_date = parse_release_date(value);
+                }
+            }
+            // Set apart from the date when a file has both, which is taken
+            // to be when this edition came out
+            Some(StandardTagKey::ReleaseDate) => {
+                file.release_date = parse_release_date(value).or(file.release_date.take())
+            }
+            // When the recording first came out, for remasters and reissues
+            Some(StandardTagKey::OriginalDate) => {
+                file.original_release_date = parse_release_date(value)
+            }
+            Some(StandardTagKey::Language) => file.language = language_name(value),
+            Some(StandardTagKey::Lyrics) => lyrics = Some(value.to_string()),
+            Some(StandardTagKey::Mood) => file.moods = parse_moods(value),
+            // "3" or "3/12"
+            Some(StandardTagKey::TrackNumber) => {
+                let mut parts = value.split('/').map(|n| n.trim().parse().ok());
+                if let Some(number) = parts.next().flatten() {
+                    file.track_number = number;
+                }
+                if let Some(total) = parts.next().flatten() {
+                    file.total_tracks = Some(total);
+                }
+            }
+            // "2" or "2/3"
+            Some(StandardTagKey::DiscNumber) => {
+                if let Some(number) = value.split('/').next().and_then(|n| n.trim().parse().ok()) {
+                    file.disc_number = number;
+                }
+            }
+            Some(StandardTagKey::TrackTotal) => {
+                if let Ok(total) = value.parse() {
+                    file.total_tracks = Some(total);
+                }
+            }
+            _ if KEY_TAGS.iter().any(|key| tag.key.eq_ignore_ascii_case(key)) => {
+                file.key = MusicalKey::parse(value).or(file.key)
+            }
+            _ => {}
+        }
+    }
+    if file.language.is_none() {
+        file.language = lyrics
+            .as_deref()
+            .and_then(detect_language)
+            .map(str::to_string);
+    }
+    Ok(())
+}
+
+impl Library {
+    /// Files read and found so far, while a scan is running.
+    pub fn scan_progress(&self) -> Option<(usize, usize)> {
+        self.scan_progress
+    }
+
+    /// Adds another folder to the library after the others, scanning it in.
+    pub fn add_root(&mut self, path: PathBuf, cx: &mut ModelContext<Self>) {
+        if self.roots.contains(&path) {
+            return;
+        }
+        self.roots.push(path);
+        // Starting over is cheap, anything already read is skipped
+        self.scan_progress = Some((0, 0));
+        self._scanning_task = Some(Library::scan(self.roots.clone(), cx));
+        cx.notify();
+    }
+
+    // Walks the folders and reads each file's tags in the background, adding
+    // tracks a batch at a time. Files already in the library are left alone.
+    pub(crate) fn scan(roots: Vec<PathBuf>, cx: &mut ModelContext<Self>) -> Task<()> {
+        cx.spawn(|this, mut cx| async move {
+            let mut files = cx
+                .background_executor()
+                .spawn(async move {
+                    roots
+                        .iter()
+                        .flat_map(|root| audio_files_in(root))
+                        .collect::<Vec<_>>()
+                })
+                .await;
+            let Ok(known) = this.update(&mut cx, |library, _| {
+                library
+                    .tracks()
+                    .filter_map(|track| track.path().map(Path::to_path_buf))
+                    .chain(
+                        library
+                            .alternate_copies()
+                            .iter()
+                            .map(|copy| copy.path.clone()),
+                    )
+                    .collect::<HashSet<_>>()
+            }) else {
+                return;
+            };
+            files.retain(|file| !known.contains(file));
+            let total = files.len();
+
+            let mut scanned = 0;
+            for batch in files.chunks(SCAN_BATCH) {
+                let batch = batch.to_vec();
+                let imported: Vec<ImportedFile> = cx
+                    .background_executor()
+                    .spawn(async move { batch.iter().map(|path| read_tags(path)).collect() })
+                    .await;
+                scanned += imported.len();
+
+                let updated = this.update(&mut cx, |library, cx| {
+                    library.add_imported(imported);
+                    library.scan_progress = Some((scanned, total));
+                    cx.emit(Event::ScanProgress { scanned, total });
+                    cx.notify();
+                });
+                if updated.is_err() {
+                    return;
+                }
+            }
+
+            this.update(&mut cx, |library, cx| {
+                library.scan_progress = None;
+                cx.emit(Event::ScanFinished);
+                cx.notify();
+            })
+            .ok();
+        })
+    }
+}
@@ -0,0 +1,95 @@
+use crate::Track;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShuffleMode {
+    #[default]
+    Off,
+    Songs,
+    // Whole albums (or works) play in order, only which one comes next is random
+    Albums,
+    Groupings,
+}
+
+impl ShuffleMode {
+    fn same_unit(&self, a: &Track, b: &Track) -> bool {
+        match self {
+            ShuffleMode::Albums => a.is_same_album(b),
+            ShuffleMode::Groupings => a.id() == b.id() || a.is_same_work(b),
+            ShuffleMode::Off | ShuffleMode::Songs => a.id() == b.id(),
+        }
+    }
+}
+
+// Versions of the same song are moved apart so they don't play back to back,
+// as far as the rest of the queue allows
+fn spread_versions(tracks: &mut [Track]) {
+    for ix in 1..tracks.len() {
+        if !tracks[ix].is_version_of(&tracks[ix - 1]) {
+            continue;
+        }
+        if let Some(other) =
+            (ix + 1..tracks.len()).find(|other| !tracks[*other].is_version_of(&tracks[ix - 1]))
+        {
+            tracks.swap(ix, other);
+        }
+    }
+}
+
+/// Orders the tracks queued behind `current`. In album and grouping modes the
+/// rest of the current album or work keeps playing before anything else.
+pub fn shuffle_queue(current: Option<&Track>, tracks: Vec<Track>, mode: ShuffleMode) -> Vec<Track> {
+    let mut rng = rand::thread_rng();
+    match mode {
+        ShuffleMode::Off => tracks,
+        ShuffleMode::Songs => {
+            let mut tracks = tracks;
+            tracks.shuffle(&mut rng);
+            spread_versions(&mut tracks);
+            tracks
+        }
+        ShuffleMode::Albums | ShuffleMode::Groupings => {
+            let mut units: Vec<Vec<Track>> = Vec::new();
+            for track in tracks {
+                match units
+                    .iter_mut()
+                    .find(|unit| mode.same_unit(&unit[0], &track))
+                {
+                    Some(unit) => unit.push(track),
+                    None => units.push(vec![track]),
+                }
+            }
+            for unit in &mut units {
+                unit.sort_by_key(|track| track.album_position());
+            }
+
+            // The rest of the current album carries on from it. Anything on it
+            // that comes earlier, such as the start of the first disc, plays
+            // once everything else has. Without track numbers there's no
+            // telling, so the queue's own order is kept.
+            let mut playing = Vec::new();
+            let mut earlier = Vec::new();
+            if let Some(current) = current {
+                if let Some(ix) = units
+                    .iter()
+                    .position(|unit| mode.same_unit(&unit[0], current))
+                {
+                    playing = units.remove(ix);
+                    if current.track_number() > 0 {
+                        (playing, earlier) = playing
+                            .into_iter()
+                            .partition(|track| track.album_position() > current.album_position());
+                    }
+                }
+            }
+
+            units.shuffle(&mut rng);
+            playing
+                .into_iter()
+                .chain(units.into_iter().flatten())
+                .chain(earlier)
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::anyhow;
+use std::{fs::File, path::Path};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder, DecoderOptions},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    units::Time,
+};
+
+// About -60 dBFS, quieter than any recording's noise floor
+const SILENCE_THRESHOLD: f32 = 0.001;
+// How much of each end is listened to. Silence running on past this is
+// counted as this long, which is already more than any gapless album has.
+const LISTENED_SECONDS: u64 = 10;
+
+/// How long a file is silent for at its start and at its end, in
+/// milliseconds. Only the ends are decoded, not the whole file.
+pub fn measure_silence(path: &Path) -> anyhow::Result<(u32, u32)> {
+    let stream = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| anyhow!("No audio track"))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+    let sample_rate = params
+        .sample_rate
+        .ok_or_else(|| anyhow!("Unknown sample rate"))? as u64;
+    let mut decoder = symphonia::default::get_codecs().make(&params, &DecoderOptions::default())?;
+    let listened = LISTENED_SECONDS * sample_rate;
+
+    // Frames before the first one that isn't silent
+    let mut leading = None;
+    let mut frames = 0;
+    while leading.is_none() && frames < listened {
+        let Some(peaks) = next_peaks(&mut *format, &mut *decoder, track_id)? else {
+            break;
+        };
+        match peaks.iter().position(|peak| *peak > SILENCE_THRESHOLD) {
+            Some(loud) => leading = Some(frames + loud as u64),
+            None => frames += peaks.len() as u64,
+        }
+    }
+    let leading = leading.unwrap_or(frames.min(listened));
+
+    // Frames after the last one that isn't silent, from a little before
+    // the end when the length is known, or else on from where the start
+    // left off
+    if let Some(total) = params.n_frames {
+        let start = total.saturating_sub(listened);
+        format.seek(
+            SeekMode::Coarse,
+            SeekTo::Time {
+                time: Time::new(
+                    start / sample_rate,
+                    (start % sample_rate) as f64 / sample_rate as f64,
+                ),
+                track_id: Some(track_id),
+            },
+        )?;
+        decoder.reset();
+    }
+    let mut trailing = 0;
+    while let Some(peaks) = next_peaks(&mut *format, &mut *decoder, track_id)? {
+        match peaks.iter().rposition(|peak| *peak > SILENCE_THRESHOLD) {
+            Some(loud) => trailing = (peaks.len() - loud - 1) as u64,
+            None => trailing += peaks.len() as u64,
+        }
+    }
+    let trailing = trailing.min(listened);
+
+    let ms = |frames: u64| (frames * 1000 / sample_rate) as u32;
+    Ok((ms(leading), ms(trailing)))
+}
+
+// The loudest sample of each frame of the next packet, or `None` at the end
+fn next_peaks(
+    format: &mut dyn FormatReader,
+    decoder: &mut dyn Decoder,
+    track_id: u32,
+) -> anyhow::Result<Option<Vec<f32>>> {
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged packet is skipped rather than giving up on the file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        return Ok(Some(
+            buffer
+                .samples()
+                .chunks(channels)
+                .map(|frame| {
+                    frame
+                        .iter()
+                        .fold(0f32, |peak, sample| peak.max(sample.abs()))
+                })
+                .collect(),
+        ));
+    }
+}
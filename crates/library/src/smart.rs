@@ -0,0 +1,216 @@
+use crate::{Library, MusicalKey, Track, TrackId};
+use gpui::SharedString;
+use serde::{Deserialize, Serialize};
+
+/// The fields rules can look at, by the names used in search queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleField {
+    Title,
+    Artist,
+    Album,
+    AlbumArtist,
+    Grouping,
+    Kind,
+    Language,
+    Mood,
+    Year,
+    // Worked out from the original year, as 1960 for the 1960s
+    Decade,
+    Rating,
+    Plays,
+    TrackNumber,
+}
+
+impl RuleField {
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key.to_lowercase().as_str() {
+            "title" | "name" | "song" => Some(RuleField::Title),
+            "artist" => Some(RuleField::Artist),
+            "album" => Some(RuleField::Album),
+            "albumartist" | "album_artist" => Some(RuleField::AlbumArtist),
+            "grouping" | "work" => Some(RuleField::Grouping),
+            "kind" => Some(RuleField::Kind),
+            "language" | "lang" => Some(RuleField::Language),
+            "mood" => Some(RuleField::Mood),
+            "year" => Some(RuleField::Year),
+            "decade" => Some(RuleField::Decade),
+            "rating" | "stars" => Some(RuleField::Rating),
+            "plays" => Some(RuleField::Plays),
+            "track" => Some(RuleField::TrackNumber),
+            _ => None,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            RuleField::Year
+                | RuleField::Decade
+                | RuleField::Rating
+                | RuleField::Plays
+                | RuleField::TrackNumber
+        )
+    }
+
+    fn text(&self, track: &Track) -> Option<SharedString> {
+        match self {
+            RuleField::Title => Some(track.title()),
+            RuleField::Artist => Some(track.artist()),
+            RuleField::Album => Some(track.album()),
+            RuleField::AlbumArtist => Some(track.album_artist()),
+            RuleField::Grouping => track.grouping(),
+            RuleField::Kind => Some(track.kind().to_string().into()),
+            RuleField::Language => track.language().map(|language| language.to_string().into()),
+            RuleField::Mood => Some(track.moods().join(", ").into()),
+            _ => None,
+        }
+    }
+
+    fn number(&self, track: &Track) -> Option<u32> {
+        match self {
+            RuleField::Year => track.year(),
+            RuleField::Decade => track.decade(),
+            RuleField::Rating => Some(track.rating().unwrap_or(0) as u32),
+            RuleField::Plays => Some(track.plays().max(0) as u32),
+            RuleField::TrackNumber => Some(track.track_number()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Equal,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl Comparison {
+    fn holds(&self, value: u32, other: u32) -> bool {
+        match self {
+            Comparison::Equal => value == other,
+            Comparison::Greater => value > other,
+            Comparison::GreaterOrEqual => value >= other,
+            Comparison::Less => value < other,
+            Comparison::LessOrEqual => value <= other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "criterion", rename_all = "snake_case")]
+pub enum SmartRule {
+    Unplayed,
+    Played,
+    // Looked for in the title, artist, album and kind, ignoring case
+    Text {
+        text: String,
+    },
+    Contains {
+        field: RuleField,
+        text: String,
+    },
+    // A custom field, such as "Source", containing the text
+    CustomField {
+        name: String,
+        text: String,
+    },
+    // The key, or with `compatible` any key that mixes with it
+    Key {
+        key: MusicalKey,
+        compatible: bool,
+    },
+    Compare {
+        field: RuleField,
+        comparison: Comparison,
+        value: u32,
+    },
+    // Inclusive at both ends, like 1959..1965
+    Between {
+        field: RuleField,
+        min: u32,
+        max: u32,
+    },
+}
+
+impl SmartRule {
+    pub fn matches(&self, track: &Track) -> bool {
+        match self {
+            SmartRule::Unplayed => track.is_unplayed(),
+            SmartRule::Played => !track.is_unplayed(),
+            SmartRule::Text { text } => {
+                let text = text.to_lowercase();
+                [track.title(), track.artist(), track.album()]
+                    .iter()
+                    .any(|value| value.to_lowercase().contains(&text))
+                    || track.kind().to_string().to_lowercase().contains(&text)
+            }
+            SmartRule::Contains { field, text } => field
+                .text(track)
+                .is_some_and(|value| value.to_lowercase().contains(&text.to_lowercase())),
+            SmartRule::CustomField { name, text } => track
+                .custom_field(name)
+                .is_some_and(|value| value.to_lowercase().contains(&text.to_lowercase())),
+            SmartRule::Key { key, compatible } => track.key().is_some_and(|track_key| {
+                if *compatible {
+                    track_key.is_compatible(key)
+                } else {
+                    track_key == *key
+                }
+            }),
+            SmartRule::Compare {
+                field,
+                comparison,
+                value,
+            } => field
+                .number(track)
+                .is_some_and(|number| comparison.holds(number, *value)),
+            SmartRule::Between { field, min, max } => field
+                .number(track)
+                .is_some_and(|number| (*min..=*max).contains(&number)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartMatch {
+    #[default]
+    All,
+    Any,
+}
+
+/// The rules a smart playlist is built from, re-evaluated whenever the
+/// library changes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SmartCriteria {
+    #[serde(default)]
+    pub match_kind: SmartMatch,
+    pub rules: Vec<SmartRule>,
+}
+
+impl SmartCriteria {
+    pub fn new(match_kind: SmartMatch, rules: Vec<SmartRule>) -> Self {
+        SmartCriteria { match_kind, rules }
+    }
+
+    pub fn matches(&self, track: &Track) -> bool {
+        match self.match_kind {
+            SmartMatch::All => self.rules.iter().all(|rule| rule.matches(track)),
+            SmartMatch::Any => self.rules.iter().any(|rule| rule.matches(track)),
+        }
+    }
+}
+
+impl Library {
+    pub fn smart_tracks(&self, criteria: &SmartCriteria) -> Vec<TrackId> {
+        self.tracks()
+            .filter(|track| criteria.matches(track))
+            .map(|track| track.id().clone())
+            .collect()
+    }
+}
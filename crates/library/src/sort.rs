@@ -0,0 +1,116 @@
+use crate::{ColumnKind, Library, Track, TrackId};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// Which way a column sorts. Clicking its header again flips it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub fn reversed(self) -> Self {
+        match self {
+            SortDirection::Ascending => SortDirection::Descending,
+            SortDirection::Descending => SortDirection::Ascending,
+        }
+    }
+}
+
+fn compare(a: &Track, b: &Track, kind: &ColumnKind) -> Ordering {
+    let by_album = |a: &Track, b: &Track| {
+        a.album()
+            .to_lowercase()
+            .cmp(&b.album().to_lowercase())
+            .then(a.album_position().cmp(&b.album_position()))
+    };
+
+    match kind {
+        ColumnKind::Index => Ordering::Equal,
+        ColumnKind::Playing => b.is_unplayed().cmp(&a.is_unplayed()),
+        ColumnKind::Title => a.title().to_lowercase().cmp(&b.title().to_lowercase()),
+        ColumnKind::Artist => a
+            .artist()
+            .to_lowercase()
+            .cmp(&b.artist().to_lowercase())
+            .then_with(|| by_album(a, b)),
+        ColumnKind::Album => by_album(a, b),
+        ColumnKind::Duration => a.duration().cmp(&b.duration()),
+        ColumnKind::TrackNumber => a.track_number().cmp(&b.track_number()),
+        ColumnKind::Kind => a.kind().cmp(b.kind()),
+        ColumnKind::DateAdded => a.date_added().cmp(b.date_added()),
+        ColumnKind::Plays => a.plays().cmp(&b.plays()),
+        ColumnKind::Rating => a
+            .rating()
+            .unwrap_or(0)
+            .cmp(&b.rating().unwrap_or(0))
+            .then_with(|| by_album(a, b)),
+        ColumnKind::Size => a.size().cmp(&b.size()),
+        // By era first, so a 2011 remaster of a 1959 album sorts with 1959
+        ColumnKind::OriginalReleaseDate => a
+            .original_year()
+            .cmp(&b.original_year())
+            .then_with(|| release(a).cmp(&release(b)))
+            .then_with(|| by_album(a, b)),
+        ColumnKind::Language => a
+            .language()
+            .map(str::to_lowercase)
+            .cmp(&b.language().map(str::to_lowercase))
+            .then_with(|| by_album(a, b)),
+        // Around the Camelot wheel, so keys that mix well sit together
+        ColumnKind::Key => a
+            .key()
+            .map(|key| (key.camelot_number(), !key.is_minor()))
+            .cmp(&b.key().map(|key| (key.camelot_number(), !key.is_minor())))
+            .then_with(|| by_album(a, b)),
+        ColumnKind::Custom(name) => a
+            .custom_field(name)
+            .map(str::to_lowercase)
+            .cmp(&b.custom_field(name).map(str::to_lowercase))
+            .then_with(|| by_album(a, b)),
+    }
+}
+
+fn release(track: &Track) -> Option<&str> {
+    track
+        .original_release_date()
+        .or_else(|| track.release_date())
+}
+
+impl Library {
+    /// `ids` ordered by a column. The sort is stable either way, so sorting
+    /// by `ColumnKind::Index` leaves the manual order as it is and ties keep
+    /// their manual order.
+    pub fn sorted(
+        &self,
+        ids: &[TrackId],
+        kind: &ColumnKind,
+        direction: SortDirection,
+    ) -> Vec<TrackId> {
+        let mut tracks: Vec<&Track> = ids.iter().filter_map(|id| self.track(id)).collect();
+        tracks.sort_by(|a, b| match direction {
+            SortDirection::Ascending => compare(a, b, kind),
+            SortDirection::Descending => compare(b, a, kind),
+        });
+        tracks.into_iter().map(|track| track.id().clone()).collect()
+    }
+
+    /// Makes a column's order the library's manual order.
+    pub fn sort_by_column(&mut self, kind: ColumnKind, direction: SortDirection) {
+        self.track_order = self.sorted(&self.track_order, &kind, direction);
+    }
+
+    pub fn move_track(&mut self, from: usize, to: usize) {
+        move_item(&mut self.track_order, from, to);
+    }
+}
+
+pub(crate) fn move_item<T>(items: &mut Vec<T>, from: usize, to: usize) {
+    if from >= items.len() {
+        return;
+    }
+    let item = items.remove(from);
+    items.insert(to.min(items.len()), item);
+}
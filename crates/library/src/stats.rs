@@ -0,0 +1,181 @@
+use crate::{today, Library, Track};
+use serde::Serialize;
+use std::{collections::HashMap, fs, path::Path};
+
+const TOP_ARTISTS: usize = 25;
+const RECENT_PLAYS: usize = 50;
+// Estimated bitrates are rounded into the tiers encoders actually use
+const BITRATE_TIERS: [u32; 6] = [96, 128, 192, 256, 320, 1411];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtistStats {
+    pub artist: String,
+    pub plays: i64,
+    pub tracks: usize,
+    pub duration: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayedTrack {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub plays: i32,
+    pub last_played: String,
+}
+
+/// One slice of the library, such as every AAC file or every track from
+/// the 1970s.
+#[derive(Debug, Clone, Serialize)]
+pub struct Share {
+    pub label: String,
+    pub tracks: usize,
+    pub duration: i64,
+    pub size: u64,
+}
+
+/// Listening and library statistics, in a shape meant to be fed straight
+/// into someone's own charts.
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryStats {
+    pub generated: String,
+    pub tracks: usize,
+    pub duration: i64,
+    pub size: u64,
+    pub plays: i64,
+    pub unplayed: usize,
+    pub top_artists: Vec<ArtistStats>,
+    // Most recent first. Only the last play of each track is known.
+    pub recently_played: Vec<PlayedTrack>,
+    pub by_kind: Vec<Share>,
+    pub by_bitrate: Vec<Share>,
+    pub by_decade: Vec<Share>,
+}
+
+impl LibraryStats {
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn export(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+impl Track {
+    /// Worked out from the file size, so it's the average over the whole
+    /// file, artwork and all.
+    pub fn estimated_bitrate(&self) -> Option<u32> {
+        if self.duration <= 0 || self.size == 0 {
+            return None;
+        }
+        Some((self.size * 8 / 1000 / self.duration as u64) as u32)
+    }
+}
+
+pub(crate) fn bitrate_label(track: &Track) -> String {
+    let Some(bitrate) = track.estimated_bitrate() else {
+        return "Unknown".to_string();
+    };
+    let tier = BITRATE_TIERS
+        .iter()
+        .min_by_key(|tier| tier.abs_diff(bitrate))
+        .copied()
+        .unwrap_or(bitrate);
+    format!("{} kbps", tier)
+}
+
+fn decade_label(track: &Track) -> String {
+    match track.decade() {
+        Some(decade) => format!("{}s", decade),
+        None => "Unknown".to_string(),
+    }
+}
+
+// Largest share first
+pub(crate) fn shares<'a>(
+    tracks: impl Iterator<Item = &'a Track>,
+    label: impl Fn(&Track) -> String,
+) -> Vec<Share> {
+    let mut shares: Vec<Share> = Vec::new();
+    for track in tracks {
+        let label = label(track);
+        let ix = match shares.iter().position(|share| share.label == label) {
+            Some(ix) => ix,
+            None => {
+                shares.push(Share {
+                    label,
+                    tracks: 0,
+                    duration: 0,
+                    size: 0,
+                });
+                shares.len() - 1
+            }
+        };
+        let share = &mut shares[ix];
+        share.tracks += 1;
+        share.duration += track.duration() as i64;
+        share.size += track.size();
+    }
+    shares.sort_by(|a, b| b.tracks.cmp(&a.tracks).then_with(|| a.label.cmp(&b.label)));
+    shares
+}
+
+impl Library {
+    pub fn stats(&self) -> LibraryStats {
+        let summary = self.summary();
+
+        let mut artists: HashMap<String, ArtistStats> = HashMap::new();
+        for track in self.tracks() {
+            let artist = track.album_artist().to_string();
+            let stats = artists.entry(artist.clone()).or_insert(ArtistStats {
+                artist,
+                plays: 0,
+                tracks: 0,
+                duration: 0,
+            });
+            stats.plays += track.plays() as i64;
+            stats.tracks += 1;
+            stats.duration += track.duration() as i64;
+        }
+        let mut top_artists: Vec<ArtistStats> = artists
+            .into_values()
+            .filter(|artist| artist.plays > 0)
+            .collect();
+        top_artists.sort_by(|a, b| b.plays.cmp(&a.plays).then_with(|| a.artist.cmp(&b.artist)));
+        top_artists.truncate(TOP_ARTISTS);
+
+        let mut recently_played: Vec<PlayedTrack> = self
+            .tracks()
+            .filter_map(|track| {
+                Some(PlayedTrack {
+                    id: track.id().clone().into(),
+                    title: track.title().to_string(),
+                    artist: track.artist().to_string(),
+                    album: track.album().to_string(),
+                    plays: track.plays(),
+                    last_played: track.last_played()?.to_string(),
+                })
+            })
+            .collect();
+        // Dates are stored as "2005-05-09", so they sort as text
+        recently_played.sort_by(|a, b| b.last_played.cmp(&a.last_played));
+        recently_played.truncate(RECENT_PLAYS);
+
+        LibraryStats {
+            generated: today(),
+            tracks: summary.count,
+            duration: summary.duration,
+            size: summary.size,
+            plays: self.tracks().map(|track| track.plays() as i64).sum(),
+            unplayed: self.tracks().filter(|track| track.is_unplayed()).count(),
+            top_artists,
+            recently_played,
+            by_kind: shares(self.tracks(), |track| track.kind().to_string()),
+            by_bitrate: shares(self.tracks(), bitrate_label),
+            by_decade: shares(self.tracks(), decade_label),
+        }
+    }
+}
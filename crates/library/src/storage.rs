@@ -0,0 +1,122 @@
+use crate::{bitrate_label, kind_for_file, shares, Library, Share, Track, TrackId};
+use gpui::SharedString;
+use std::{cmp::Reverse, fs, path::PathBuf};
+
+const BIGGEST_ALBUMS: usize = 10;
+const LOSSLESS_KINDS: &[&str] = &["FLAC audio file", "WAV audio file", "AIFF audio file"];
+// Lossless with no compression, so they'd take less room as FLAC
+const UNCOMPRESSED_KINDS: &[&str] = &["WAV audio file", "AIFF audio file"];
+// ALAC files share the AAC kind, so anything this dense is taken as lossless
+const LOSSLESS_BITRATE: u32 = 700;
+// What a lossless track would take up once transcoded to 256 kbps AAC
+const TRANSCODED_BITRATE: u64 = 256;
+
+#[derive(Debug, Clone)]
+pub struct AlbumSize {
+    pub album: SharedString,
+    pub artist: SharedString,
+    pub tracks: Vec<TrackId>,
+    pub size: u64,
+}
+
+/// Where the library's disk space goes, largest first in every breakdown.
+#[derive(Debug, Clone, Default)]
+pub struct StorageReport {
+    pub size: u64,
+    pub by_kind: Vec<Share>,
+    pub by_bitrate: Vec<Share>,
+    // Folders follow the iTunes Media layout, one per album artist
+    pub by_folder: Vec<Share>,
+    pub biggest_albums: Vec<AlbumSize>,
+    pub lossless: Vec<TrackId>,
+    pub lossless_size: u64,
+    // Roughly what transcoding every lossless track would free up
+    pub transcode_savings: u64,
+    // The lossless tracks in WAV or AIFF files
+    pub uncompressed: Vec<TrackId>,
+}
+
+impl Track {
+    pub fn is_lossless(&self) -> bool {
+        LOSSLESS_KINDS.contains(&self.kind())
+            || self
+                .estimated_bitrate()
+                .is_some_and(|bitrate| bitrate >= LOSSLESS_BITRATE)
+    }
+
+    fn transcoded_size(&self) -> u64 {
+        TRANSCODED_BITRATE * 1000 / 8 * self.duration().max(0) as u64
+    }
+}
+
+fn by_size(mut shares: Vec<Share>) -> Vec<Share> {
+    shares.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.label.cmp(&b.label)));
+    shares
+}
+
+impl Library {
+    pub fn storage_report(&self) -> StorageReport {
+        let mut albums: Vec<AlbumSize> = Vec::new();
+        for track in self.tracks() {
+            match albums
+                .iter_mut()
+                .find(|album| album.album == track.album() && album.artist == track.album_artist())
+            {
+                Some(album) => {
+                    album.tracks.push(track.id().clone());
+                    album.size += track.size();
+                }
+                None => albums.push(AlbumSize {
+                    album: track.album(),
+                    artist: track.album_artist(),
+                    tracks: vec![track.id().clone()],
+                    size: track.size(),
+                }),
+            }
+        }
+        albums.sort_by_key(|album| Reverse(album.size));
+        albums.truncate(BIGGEST_ALBUMS);
+
+        let lossless: Vec<&Track> = self.tracks().filter(|track| track.is_lossless()).collect();
+        let lossless_size = lossless.iter().map(|track| track.size()).sum();
+        let transcode_savings = lossless
+            .iter()
+            .map(|track| track.size().saturating_sub(track.transcoded_size()))
+            .sum();
+
+        StorageReport {
+            size: self.summary().size,
+            by_kind: by_size(shares(self.tracks(), |track| track.kind().to_string())),
+            by_bitrate: by_size(shares(self.tracks(), bitrate_label)),
+            by_folder: by_size(shares(self.tracks(), |track| {
+                track.album_artist().to_string()
+            })),
+            biggest_albums: albums,
+            uncompressed: lossless
+                .iter()
+                .filter(|track| UNCOMPRESSED_KINDS.contains(&track.kind()))
+                .map(|track| track.id().clone())
+                .collect(),
+            lossless: lossless.iter().map(|track| track.id().clone()).collect(),
+            lossless_size,
+            transcode_savings,
+        }
+    }
+
+    /// The tracks' files were moved or transcoded to these paths. Being new
+    /// files, they're checksummed again.
+    pub fn set_track_files(&mut self, files: Vec<(TrackId, PathBuf)>) {
+        for (id, path) in files {
+            let Some(track) = self.track_mut(&id) else {
+                continue;
+            };
+            track.kind = kind_for_file(&path);
+            track.size = fs::metadata(&path)
+                .map(|metadata| metadata.len())
+                .unwrap_or(track.size);
+            track.path = Some(path);
+            track.checksum = None;
+            self.pending_checksums.push(id);
+        }
+    }
+}
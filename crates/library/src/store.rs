@@ -0,0 +1,436 @@
+use crate::{Library, SerializableLibrary, SerializableTrack, SortDirection, TrackId};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS tracks (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        artist TEXT NOT NULL,
+        album TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        date_added TEXT NOT NULL,
+        plays INTEGER NOT NULL,
+        duration INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tracks_position ON tracks (position);
+    CREATE INDEX IF NOT EXISTS tracks_title ON tracks (title COLLATE NOCASE);
+    CREATE INDEX IF NOT EXISTS tracks_artist ON tracks (artist COLLATE NOCASE);
+    CREATE INDEX IF NOT EXISTS tracks_album ON tracks (album COLLATE NOCASE);
+    CREATE INDEX IF NOT EXISTS tracks_date_added ON tracks (date_added);
+    CREATE INDEX IF NOT EXISTS tracks_plays ON tracks (plays);
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// Where the library is kept between launches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibraryBackend {
+    // The whole library in library.json, rewritten on every save
+    #[default]
+    Json,
+    // library.sqlite, where a save only writes what changed
+    Sqlite,
+}
+
+/// The columns a store can pick out and sort tracks by, without the whole
+/// library being loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreColumn {
+    Title,
+    Artist,
+    Album,
+    DateAdded,
+    Plays,
+}
+
+impl StoreColumn {
+    fn name(self) -> &'static str {
+        match self {
+            StoreColumn::Title => "title",
+            StoreColumn::Artist => "artist",
+            StoreColumn::Album => "album",
+            StoreColumn::DateAdded => "date_added",
+            StoreColumn::Plays => "plays",
+        }
+    }
+
+    // Names are matched and sorted ignoring case, as the indexes are
+    fn collation(self) -> &'static str {
+        match self {
+            StoreColumn::Title | StoreColumn::Artist | StoreColumn::Album => " COLLATE NOCASE",
+            StoreColumn::DateAdded | StoreColumn::Plays => "",
+        }
+    }
+
+    fn matches(self, track: &SerializableTrack, value: &str) -> bool {
+        match self {
+            StoreColumn::Title => track.title.eq_ignore_ascii_case(value),
+            StoreColumn::Artist => track.artist.eq_ignore_ascii_case(value),
+            StoreColumn::Album => track.album.eq_ignore_ascii_case(value),
+            StoreColumn::DateAdded => track.date_added == value,
+            StoreColumn::Plays => track.plays.to_string() == value,
+        }
+    }
+
+    fn compare(self, a: &SerializableTrack, b: &SerializableTrack) -> Ordering {
+        let ignoring_case = |a: &str, b: &str| a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase());
+        match self {
+            StoreColumn::Title => ignoring_case(&a.title, &b.title),
+            StoreColumn::Artist => ignoring_case(&a.artist, &b.artist),
+            StoreColumn::Album => ignoring_case(&a.album, &b.album),
+            StoreColumn::DateAdded => a.date_added.cmp(&b.date_added),
+            StoreColumn::Plays => a.plays.cmp(&b.plays),
+        }
+    }
+}
+
+/// Which saved tracks to look up, and in what order.
+#[derive(Debug, Clone, Default)]
+pub struct StoreQuery {
+    // Only tracks with this value in the column
+    pub filter: Option<(StoreColumn, String)>,
+    // Library order when not given
+    pub sort: Option<(StoreColumn, SortDirection)>,
+    pub limit: Option<usize>,
+}
+
+impl StoreQuery {
+    // For stores that only have the tracks in memory
+    fn apply(&self, tracks: Vec<SerializableTrack>) -> Vec<TrackId> {
+        let mut tracks: Vec<_> = tracks
+            .into_iter()
+            .filter(|track| {
+                self.filter
+                    .as_ref()
+                    .is_none_or(|(column, value)| column.matches(track, value))
+            })
+            .collect();
+        if let Some((column, direction)) = self.sort {
+            // Stable, so ties stay in library order
+            tracks.sort_by(|a, b| match direction {
+                SortDirection::Ascending => column.compare(a, b),
+                SortDirection::Descending => column.compare(b, a),
+            });
+        }
+        tracks
+            .into_iter()
+            .filter_map(|track| track.id.map(TrackId::from))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// Loads and saves the library. Saves are handed the whole library along
+/// with the tracks edited since the last one, and it's up to the store how
+/// much of it to write.
+pub trait LibraryStore: Send {
+    fn load(&mut self) -> anyhow::Result<Option<SerializableLibrary>>;
+
+    /// `changed` is from `Library::take_changed_tracks`.
+    fn save(
+        &mut self,
+        library: &SerializableLibrary,
+        changed: &HashSet<TrackId>,
+    ) -> anyhow::Result<()>;
+
+    /// The ids of the saved tracks `query` picks out, in its order.
+    fn query(&mut self, query: &StoreQuery) -> anyhow::Result<Vec<TrackId>>;
+}
+
+/// Opens the store for `backend`. Switching to SQLite moves an existing
+/// library.json into the database the first time.
+pub fn open_library_store(
+    data_dir: &Path,
+    backend: LibraryBackend,
+) -> anyhow::Result<Box<dyn LibraryStore>> {
+    match backend {
+        LibraryBackend::Json => Ok(Box::new(JsonStore {
+            data_dir: data_dir.to_path_buf(),
+        })),
+        LibraryBackend::Sqlite => {
+            fs::create_dir_all(data_dir)?;
+            let mut store = SqliteStore::open(&data_dir.join("library.sqlite"))?;
+            store.migrate_from_json(data_dir)?;
+            Ok(Box::new(store))
+        }
+    }
+}
+
+pub struct JsonStore {
+    data_dir: PathBuf,
+}
+
+impl LibraryStore for JsonStore {
+    fn load(&mut self) -> anyhow::Result<Option<SerializableLibrary>> {
+        Ok(SerializableLibrary::load(&self.data_dir))
+    }
+
+    fn save(
+        &mut self,
+        library: &SerializableLibrary,
+        _changed: &HashSet<TrackId>,
+    ) -> anyhow::Result<()> {
+        library.save(&self.data_dir)
+    }
+
+    // There's nothing for it but to read the whole file
+    fn query(&mut self, query: &StoreQuery) -> anyhow::Result<Vec<TrackId>> {
+        Ok(SerializableLibrary::load(&self.data_dir)
+            .map(|library| query.apply(library.tracks))
+            .unwrap_or_default())
+    }
+}
+
+pub struct SqliteStore {
+    connection: Connection,
+    // Where each track's row was last written, so only tracks that are new,
+    // moved or edited are written again
+    written_positions: HashMap<String, i64>,
+    // Edited tracks yet to be written, kept through a save that fails
+    unwritten: HashSet<String>,
+    // A hash of what each meta value was last written as
+    written_meta: HashMap<&'static str, u64>,
+}
+
+fn hash_of(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        let mut store = SqliteStore {
+            connection,
+            written_positions: HashMap::new(),
+            unwritten: HashSet::new(),
+            written_meta: HashMap::new(),
+        };
+        store.written_positions = store.read_positions()?;
+        Ok(store)
+    }
+
+    fn read_positions(&self) -> anyhow::Result<HashMap<String, i64>> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT id, position FROM tracks")?;
+        let positions = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(positions)
+    }
+
+    // Done once, and noted in `meta` so a library emptied since isn't
+    // migrated again. The JSON file is kept under another name in case it's
+    // wanted back, but only once the database has everything in it.
+    fn migrate_from_json(&mut self, data_dir: &Path) -> anyhow::Result<()> {
+        if self.load_meta::<bool>("migrated_from_json")? {
+            return Ok(());
+        }
+        if let Some(saved) = SerializableLibrary::load(data_dir) {
+            // Rows are keyed by id, and tracks saved before they had one
+            // are given one on the way through
+            let mut library = Library::default();
+            library.restore(saved);
+            self.save(&library.serializable(), &HashSet::new())?;
+            let json = SerializableLibrary::path(data_dir);
+            fs::rename(&json, json.with_extension("json.migrated"))?;
+        }
+        self.save_meta("migrated_from_json", &true)
+    }
+
+    fn load_meta<T: DeserializeOwned + Default>(&mut self, key: &'static str) -> anyhow::Result<T> {
+        let value: Option<String> = self
+            .connection
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        let Some(value) = value else {
+            return Ok(T::default());
+        };
+        self.written_meta.insert(key, hash_of(&value));
+        Ok(serde_json::from_str(&value)?)
+    }
+
+    fn save_meta(&mut self, key: &'static str, value: &impl Serialize) -> anyhow::Result<()> {
+        let value = serde_json::to_string(value)?;
+        let hash = hash_of(&value);
+        if self.written_meta.get(key) == Some(&hash) {
+            return Ok(());
+        }
+        self.connection.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        self.written_meta.insert(key, hash);
+        Ok(())
+    }
+
+    fn write(&mut self, library: &SerializableLibrary) -> anyhow::Result<()> {
+        let transaction = self.connection.transaction()?;
+        let mut kept = HashSet::new();
+        {
+            let mut upsert = transaction.prepare_cached(
+                "INSERT OR REPLACE INTO tracks
+                    (id, position, title, artist, album, kind, date_added, plays, duration, data)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            for (position, track) in library.tracks.iter().enumerate() {
+                let Some(id) = &track.id else {
+                    continue;
+                };
+                kept.insert(id.as_str());
+                let position = position as i64;
+                if self.written_positions.get(id) == Some(&position) && !self.unwritten.contains(id)
+                {
+                    continue;
+                }
+                let data = serde_json::to_string(track)?;
+                upsert.execute(params![
+                    id,
+                    position,
+                    track.title,
+                    track.artist,
+                    track.album,
+                    track.kind,
+                    track.date_added,
+                    track.plays,
+                    track.duration,
+                    data,
+                ])?;
+                self.written_positions.insert(id.clone(), position);
+            }
+
+            let removed: Vec<String> = self
+                .written_positions
+                .keys()
+                .filter(|id| !kept.contains(id.as_str()))
+                .cloned()
+                .collect();
+            let mut delete = transaction.prepare_cached("DELETE FROM tracks WHERE id = ?1")?;
+            for id in removed {
+                delete.execute([&id])?;
+                self.written_positions.remove(&id);
+            }
+        }
+        transaction.commit()?;
+        self.unwritten.clear();
+
+        self.save_meta("columns", &library.columns)?;
+        self.save_meta("playlists", &library.playlists)?;
+        self.save_meta("wishlist", &library.wishlist)?;
+        self.save_meta("alternate_copies", &library.alternate_copies)?;
+        self.save_meta("playlist_history", &library.playlist_history)?;
+        self.save_meta("import_sessions", &library.import_sessions)?;
+        self.save_meta("recently_deleted", &library.recently_deleted)?;
+        self.save_meta("metadata_log", &library.metadata_log)?;
+        Ok(())
+    }
+}
+
+impl LibraryStore for SqliteStore {
+    fn load(&mut self) -> anyhow::Result<Option<SerializableLibrary>> {
+        let rows: Vec<(String, i64, String)> = {
+            let mut statement = self
+                .connection
+                .prepare("SELECT id, position, data FROM tracks ORDER BY position")?;
+            let rows = statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<_, _>>()?;
+            rows
+        };
+        let columns: Vec<_> = self.load_meta("columns")?;
+        if rows.is_empty() && columns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut tracks = Vec::with_capacity(rows.len());
+        self.unwritten.clear();
+        for (id, position, data) in rows {
+            self.written_positions.insert(id.clone(), position);
+            let mut track: SerializableTrack = serde_json::from_str(&data)?;
+            track.id = Some(id);
+            tracks.push(track);
+        }
+
+        Ok(Some(SerializableLibrary {
+            tracks,
+            columns,
+            playlists: self.load_meta("playlists")?,
+            wishlist: self.load_meta("wishlist")?,
+            alternate_copies: self.load_meta("alternate_copies")?,
+            playlist_history: self.load_meta("playlist_history")?,
+            import_sessions: self.load_meta("import_sessions")?,
+            recently_deleted: self.load_meta("recently_deleted")?,
+            metadata_log: self.load_meta("metadata_log")?,
+        }))
+    }
+
+    fn save(
+        &mut self,
+        library: &SerializableLibrary,
+        changed: &HashSet<TrackId>,
+    ) -> anyhow::Result<()> {
+        self.unwritten
+            .extend(changed.iter().map(|id| id.clone().into()));
+        let result = self.write(library);
+        // The transaction was rolled back, so whatever was half written is
+        // written again next time
+        if result.is_err() {
+            self.written_positions = self.read_positions().unwrap_or_default();
+            self.written_meta.clear();
+        }
+        result
+    }
+
+    fn query(&mut self, query: &StoreQuery) -> anyhow::Result<Vec<TrackId>> {
+        let mut sql = "SELECT id FROM tracks".to_string();
+        let mut values = Vec::new();
+        if let Some((column, value)) = &query.filter {
+            sql += &format!(" WHERE {} = ?1{}", column.name(), column.collation());
+            values.push(value.as_str());
+        }
+        if let Some((column, direction)) = query.sort {
+            let direction = match direction {
+                SortDirection::Ascending => "ASC",
+                SortDirection::Descending => "DESC",
+            };
+            sql += &format!(
+                " ORDER BY {}{} {}, position",
+                column.name(),
+                column.collation(),
+                direction
+            );
+        } else {
+            sql += " ORDER BY position";
+        }
+        if let Some(limit) = query.limit {
+            sql += &format!(" LIMIT {}", limit);
+        }
+
+        let mut statement = self.connection.prepare_cached(&sql)?;
+        let ids = statement
+            .query_map(params_from_iter(values), |row| {
+                row.get::<_, String>(0).map(TrackId::from)
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(ids)
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{Library, Track, TrackId};
+use gpui::SharedString;
+
+/// Something to jump to while typing a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Suggestion {
+    Artist(SharedString),
+    Album {
+        album: SharedString,
+        artist: SharedString,
+    },
+    Song(TrackId),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Suggestions {
+    pub artists: Vec<Suggestion>,
+    pub albums: Vec<Suggestion>,
+    pub songs: Vec<Suggestion>,
+}
+
+impl Suggestions {
+    pub fn is_empty(&self) -> bool {
+        self.artists.is_empty() && self.albums.is_empty() && self.songs.is_empty()
+    }
+
+    /// Artists, then albums, then songs, the order they're listed in.
+    pub fn iter(&self) -> impl Iterator<Item = &Suggestion> {
+        self.artists.iter().chain(&self.albums).chain(&self.songs)
+    }
+}
+
+// Names starting with the text come before ones that just contain it
+fn rank(name: &str, text: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    if name.starts_with(text) {
+        Some(0)
+    } else if name.contains(text) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+impl Library {
+    /// The best matches for `text` of each kind, at most `limit` of each.
+    pub fn suggestions(&self, text: &str, limit: usize) -> Suggestions {
+        let text = text.trim().to_lowercase();
+        if text.is_empty() {
+            return Suggestions::default();
+        }
+
+        let mut artists: Vec<(usize, SharedString)> = Vec::new();
+        let mut albums: Vec<(usize, SharedString, SharedString)> = Vec::new();
+        let mut songs: Vec<(usize, &Track)> = Vec::new();
+        for track in self.tracks() {
+            if let Some(rank) = rank(&track.artist(), &text) {
+                if !artists.iter().any(|(_, artist)| *artist == track.artist()) {
+                    artists.push((rank, track.artist()));
+                }
+            }
+            if let Some(rank) = rank(&track.album(), &text) {
+                if !albums.iter().any(|(_, album, artist)| {
+                    *album == track.album() && *artist == track.album_artist()
+                }) {
+                    albums.push((rank, track.album(), track.album_artist()));
+                }
+            }
+            if let Some(rank) = rank(&track.title(), &text) {
+                songs.push((rank, track));
+            }
+        }
+        artists.sort_by_key(|(rank, artist)| (*rank, artist.to_lowercase()));
+        albums.sort_by_key(|(rank, album, _)| (*rank, album.to_lowercase()));
+        songs.sort_by_key(|(rank, track)| (*rank, track.title().to_lowercase()));
+
+        Suggestions {
+            artists: artists
+                .into_iter()
+                .take(limit)
+                .map(|(_, artist)| Suggestion::Artist(artist))
+                .collect(),
+            albums: albums
+                .into_iter()
+                .take(limit)
+                .map(|(_, album, artist)| Suggestion::Album { album, artist })
+                .collect(),
+            songs: songs
+                .into_iter()
+                .take(limit)
+                .map(|(_, track)| Suggestion::Song(track.id().clone()))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{Library, TrackId};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrackSummary {
+    pub count: usize,
+    pub duration: i64,
+    pub size: u64,
+}
+
+impl TrackSummary {
+    // "14 songs — 52 minutes — 487 MB", the way the iTunes status bar shows it
+    pub fn format(&self, selected: bool) -> String {
+        let songs = match (self.count, selected) {
+            (1, false) => "1 song".to_string(),
+            (1, true) => "1 song selected".to_string(),
+            (count, false) => format!("{} songs", count),
+            (count, true) => format!("{} songs selected", count),
+        };
+
+        format!(
+            "{} — {} — {}",
+            songs,
+            format_total_duration(self.duration),
+            format_size(self.size)
+        )
+    }
+}
+
+pub fn format_total_duration(seconds: i64) -> String {
+    let minutes = seconds as f64 / 60.;
+    let hours = minutes / 60.;
+    let days = hours / 24.;
+
+    if days >= 1. {
+        format!("{:.1} days", days)
+    } else if hours >= 1. {
+        format!("{:.1} hours", hours)
+    } else if minutes.round() == 1. {
+        "1 minute".to_string()
+    } else {
+        format!("{} minutes", minutes.round() as i64)
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const KB: f64 = 1024.;
+    const MB: f64 = KB * 1024.;
+    const GB: f64 = MB * 1024.;
+
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{:.2} GB", bytes / GB)
+    } else if bytes >= MB {
+        format!("{:.0} MB", bytes / MB)
+    } else {
+        format!("{:.0} KB", bytes / KB)
+    }
+}
+
+impl Library {
+    pub fn summarize<'a>(&self, ids: impl IntoIterator<Item = &'a TrackId>) -> TrackSummary {
+        ids.into_iter().filter_map(|id| self.track(id)).fold(
+            TrackSummary::default(),
+            |summary, track| TrackSummary {
+                count: summary.count + 1,
+                duration: summary.duration + track.duration() as i64,
+                size: summary.size + track.size(),
+            },
+        )
+    }
+
+    pub fn summary(&self) -> TrackSummary {
+        self.summarize(&self.track_order)
+    }
+}
@@ -0,0 +1,43 @@
+use crate::Track;
+use id3::TagLike;
+use std::path::Path;
+
+/// Writes the track's title, artist, album, album artist, genre, year and
+/// track number into its MP3's tags, so other players see the same. Other
+/// formats are left alone, and give back `false`.
+pub fn write_tags(path: &Path, track: &Track) -> anyhow::Result<bool> {
+    let is_mp3 = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("mp3"));
+    if !is_mp3 {
+        return Ok(false);
+    }
+
+    let existing = id3::no_tag_ok(id3::Tag::read_from_path(path))?;
+    let version = existing
+        .as_ref()
+        .map_or(id3::Version::Id3v24, |tag| tag.version());
+    let mut tag = existing.unwrap_or_default();
+    tag.set_title(track.title().to_string());
+    tag.set_artist(track.artist().to_string());
+    tag.set_album(track.album().to_string());
+    // Not the artist it falls back to, only what was actually set
+    match &track.album_artist {
+        Some(album_artist) => tag.set_album_artist(album_artist.to_string()),
+        None => tag.remove_album_artist(),
+    }
+    match track.genre() {
+        Some(genre) => tag.set_genre(genre),
+        None => tag.remove_genre(),
+    }
+    match track.year() {
+        Some(year) => tag.set_year(year as i32),
+        None => tag.remove_year(),
+    }
+    match track.track_number() {
+        0 => tag.remove_track(),
+        number => tag.set_track(number),
+    }
+    tag.write_to_path(path, version)?;
+    Ok(true)
+}
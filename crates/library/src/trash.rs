@@ -0,0 +1,142 @@
+use crate::{unix_time, Library, PlaylistId, SerializableTrack, Track, TrackId};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long deleted tracks can still be restored.
+pub const RECENTLY_DELETED_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// A track that has been deleted but not yet purged, along with where it
+/// was so restoring puts it back in place.
+#[derive(Debug, Clone)]
+pub struct DeletedTrack {
+    track: Track,
+    // Seconds since the Unix epoch
+    deleted_at: u64,
+    position: usize,
+    playlists: Vec<(PlaylistId, usize)>,
+}
+
+// How a deleted track is saved, so it can still be restored after a relaunch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerializableDeletedTrack {
+    track: SerializableTrack,
+    deleted_at: u64,
+    position: usize,
+    playlists: Vec<(PlaylistId, usize)>,
+}
+
+impl From<&DeletedTrack> for SerializableDeletedTrack {
+    fn from(deleted: &DeletedTrack) -> Self {
+        SerializableDeletedTrack {
+            track: SerializableTrack::from(&deleted.track),
+            deleted_at: deleted.deleted_at,
+            position: deleted.position,
+            playlists: deleted.playlists.clone(),
+        }
+    }
+}
+
+impl From<SerializableDeletedTrack> for DeletedTrack {
+    fn from(deleted: SerializableDeletedTrack) -> Self {
+        DeletedTrack {
+            track: Track::from(deleted.track),
+            deleted_at: deleted.deleted_at,
+            position: deleted.position,
+            playlists: deleted.playlists,
+        }
+    }
+}
+
+impl DeletedTrack {
+    pub fn track(&self) -> &Track {
+        &self.track
+    }
+
+    pub fn days_left(&self) -> u64 {
+        let expires_at = self.deleted_at + RECENTLY_DELETED_RETENTION.as_secs();
+        expires_at
+            .saturating_sub(unix_time())
+            .div_ceil(24 * 60 * 60)
+    }
+}
+
+impl Library {
+    /// Moves tracks out of the library and every playlist into Recently
+    /// Deleted, where they stay restorable until they expire.
+    pub fn delete_tracks(&mut self, ids: &[TrackId]) {
+        self.purge_expired();
+
+        let deleted_at = unix_time();
+        for id in ids {
+            let Some(position) = self.track_order.iter().position(|other| other == id) else {
+                continue;
+            };
+            let Some(track) = self.tracks.remove(id) else {
+                continue;
+            };
+            self.track_order.remove(position);
+
+            let playlists: Vec<(PlaylistId, usize)> = self
+                .playlists_containing(id)
+                .into_iter()
+                .filter_map(|playlist| Some((playlist.id().clone(), playlist.position(id)?)))
+                .collect();
+            for (playlist_id, _) in &playlists {
+                self.remove_from_playlist(playlist_id, std::slice::from_ref(id));
+            }
+
+            self.recently_deleted.push(DeletedTrack {
+                track,
+                deleted_at,
+                position,
+                playlists,
+            });
+        }
+    }
+
+    /// Most recently deleted last.
+    pub fn recently_deleted(&self) -> &[DeletedTrack] {
+        &self.recently_deleted
+    }
+
+    pub fn deleted_track(&self, id: &TrackId) -> Option<&Track> {
+        self.recently_deleted
+            .iter()
+            .map(|deleted| &deleted.track)
+            .find(|track| track.id() == id)
+    }
+
+    pub fn restore_tracks(&mut self, ids: &[TrackId]) {
+        let mut restored = Vec::new();
+        self.recently_deleted.retain(|deleted| {
+            let restore = ids.contains(deleted.track.id());
+            if restore {
+                restored.push(deleted.clone());
+            }
+            !restore
+        });
+
+        // Undo the deletions in reverse so each position means what it did at the time
+        for deleted in restored.into_iter().rev() {
+            let id = deleted.track.id().clone();
+            self.track_order
+                .insert(deleted.position.min(self.track_order.len()), id.clone());
+            self.tracks.insert(id.clone(), deleted.track);
+            for (playlist_id, position) in deleted.playlists {
+                self.insert_into_playlist(&playlist_id, position, id.clone());
+            }
+        }
+    }
+
+    pub fn purge_expired(&mut self) -> usize {
+        let cutoff = unix_time().saturating_sub(RECENTLY_DELETED_RETENTION.as_secs());
+        let before = self.recently_deleted.len();
+        self.recently_deleted
+            .retain(|deleted| deleted.deleted_at > cutoff);
+        before - self.recently_deleted.len()
+    }
+
+    pub fn empty_recently_deleted(&mut self) {
+        self.recently_deleted.clear();
+    }
+}
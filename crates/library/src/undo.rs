@@ -0,0 +1,179 @@
+use crate::{ChangeSource, Library, MetadataField, PlaylistId, Track, TrackId};
+use std::collections::HashSet;
+
+// Older changes drop off the bottom of the stack
+const MAX_UNDO: usize = 100;
+
+/// One field of one track, as it was and as it was changed to.
+#[derive(Debug, Clone)]
+pub struct MetadataEdit {
+    pub track: TrackId,
+    pub field: MetadataField,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+/// A change that can be taken back with Undo and made again with Redo.
+#[derive(Debug, Clone)]
+pub enum LibraryCommand {
+    // Moved into Recently Deleted, so they're restored from there
+    DeleteTracks(Vec<TrackId>),
+    EditMetadata(Vec<MetadataEdit>),
+    // Reordered, sorted or had tracks taken out
+    PlaylistChange {
+        playlist: PlaylistId,
+        before: Vec<TrackId>,
+        after: Vec<TrackId>,
+    },
+    LibraryOrder {
+        before: Vec<TrackId>,
+        after: Vec<TrackId>,
+    },
+    // Up Next lives outside the library, so whoever undoes this puts the
+    // tracks back in the queue
+    ClearQueue(Vec<Track>),
+}
+
+impl LibraryCommand {
+    // For "Undo Delete" and the like
+    pub fn name(&self) -> &'static str {
+        match self {
+            LibraryCommand::DeleteTracks(_) => "Delete",
+            LibraryCommand::EditMetadata(_) => "Edit",
+            LibraryCommand::PlaylistChange { .. } => "Playlist Change",
+            LibraryCommand::LibraryOrder { .. } => "Reorder",
+            LibraryCommand::ClearQueue(_) => "Clear Up Next",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    done: Vec<LibraryCommand>,
+    undone: Vec<LibraryCommand>,
+}
+
+impl Library {
+    /// Adds a change to the undo stack. Anything that had been undone can
+    /// no longer be redone.
+    pub fn record_undo(&mut self, command: LibraryCommand) {
+        let stack = &mut self.undo_stack;
+        stack.undone.clear();
+        stack.done.push(command);
+        if stack.done.len() > MAX_UNDO {
+            stack.done.remove(0);
+        }
+    }
+
+    /// Records a playlist's change from `before` to what it holds now.
+    pub fn record_playlist_change(&mut self, playlist: &PlaylistId, before: Vec<TrackId>) {
+        let Some(after) = self
+            .playlist(playlist)
+            .map(|playlist| playlist.tracks().to_vec())
+        else {
+            return;
+        };
+        if after != before {
+            self.record_undo(LibraryCommand::PlaylistChange {
+                playlist: playlist.clone(),
+                before,
+                after,
+            });
+        }
+    }
+
+    pub fn record_library_order(&mut self, before: Vec<TrackId>) {
+        if self.track_order != before {
+            self.record_undo(LibraryCommand::LibraryOrder {
+                before,
+                after: self.track_order.clone(),
+            });
+        }
+    }
+
+    pub fn undo_name(&self) -> Option<&'static str> {
+        self.undo_stack.done.last().map(LibraryCommand::name)
+    }
+
+    pub fn redo_name(&self) -> Option<&'static str> {
+        self.undo_stack.undone.last().map(LibraryCommand::name)
+    }
+
+    /// Takes back the last change, giving back what was undone.
+    pub fn undo(&mut self) -> Option<LibraryCommand> {
+        let command = self.undo_stack.done.pop()?;
+        self.apply_command(&command, true);
+        self.undo_stack.undone.push(command.clone());
+        Some(command)
+    }
+
+    pub fn redo(&mut self) -> Option<LibraryCommand> {
+        let command = self.undo_stack.undone.pop()?;
+        self.apply_command(&command, false);
+        self.undo_stack.done.push(command.clone());
+        Some(command)
+    }
+
+    // Undoing goes through the same edits as making the change did, which
+    // would record it all over again, so the stack is set aside meanwhile
+    fn apply_command(&mut self, command: &LibraryCommand, undo: bool) {
+        let stack = std::mem::take(&mut self.undo_stack);
+        match command {
+            LibraryCommand::DeleteTracks(ids) if undo => self.restore_tracks(ids),
+            LibraryCommand::DeleteTracks(ids) => self.delete_tracks(ids),
+            // Fields changed again since are left alone, the later edit wins
+            LibraryCommand::EditMetadata(edits) => {
+                let edits = edits
+                    .iter()
+                    .filter_map(|edit| {
+                        let (from, to) = if undo {
+                            (&edit.new_value, &edit.old_value)
+                        } else {
+                            (&edit.old_value, &edit.new_value)
+                        };
+                        let current = self.track(&edit.track)?.metadata(edit.field);
+                        (current == *from).then(|| (edit.track.clone(), edit.field, to.clone()))
+                    })
+                    .collect();
+                self.edit_metadata_batch(edits, ChangeSource::User);
+            }
+            LibraryCommand::PlaylistChange {
+                playlist,
+                before,
+                after,
+            } => {
+                self.snapshot_playlist(playlist, true);
+                let tracks = if undo { before } else { after };
+                let tracks = tracks
+                    .iter()
+                    .filter(|id| self.tracks.contains_key(*id))
+                    .cloned()
+                    .collect();
+                if let Some(playlist) = self.playlist_mut(playlist) {
+                    playlist.set_tracks(tracks);
+                }
+                let playlists = std::mem::take(&mut self.playlists);
+                self.set_playlists(playlists);
+            }
+            // Tracks added since go on the end
+            LibraryCommand::LibraryOrder { before, after } => {
+                let order = if undo { before } else { after };
+                let ordered: HashSet<&TrackId> = order.iter().collect();
+                let mut track_order: Vec<TrackId> = order
+                    .iter()
+                    .filter(|id| self.tracks.contains_key(*id))
+                    .cloned()
+                    .collect();
+                track_order.extend(
+                    self.track_order
+                        .iter()
+                        .filter(|id| !ordered.contains(id))
+                        .cloned(),
+                );
+                self.track_order = track_order;
+            }
+            LibraryCommand::ClearQueue(_) => {}
+        }
+        self.undo_stack = stack;
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{sort::move_item, CurrentTrack, NowPlaying, Track, TrackId};
+use std::collections::HashSet;
+
+impl NowPlaying {
+    pub fn move_queued(&mut self, from: usize, to: usize) {
+        move_item(&mut self.up_next, from, to);
+    }
+
+    /// Moves the queued tracks at `indices` one place earlier, keeping their
+    /// spacing. Returns where they ended up, or `None` if one is already first.
+    pub fn move_queued_up(&mut self, indices: &[usize]) -> Option<Vec<usize>> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        let len = self.up_next.len();
+        if indices.first().is_none_or(|ix| *ix == 0) || indices.last().is_none_or(|ix| *ix >= len) {
+            return None;
+        }
+
+        for ix in &mut indices {
+            self.up_next.swap(*ix, *ix - 1);
+            *ix -= 1;
+        }
+        Some(indices)
+    }
+
+    pub fn move_queued_down(&mut self, indices: &[usize]) -> Option<Vec<usize>> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.last().is_none_or(|ix| ix + 1 >= self.up_next.len()) {
+            return None;
+        }
+
+        for ix in indices.iter_mut().rev() {
+            self.up_next.swap(*ix, *ix + 1);
+            *ix += 1;
+        }
+        Some(indices)
+    }
+
+    pub fn remove_queued(&mut self, indices: &[usize]) -> Vec<Track> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        indices.retain(|ix| *ix < self.up_next.len());
+        let removed = indices
+            .into_iter()
+            .rev()
+            .map(|ix| self.up_next.remove(ix))
+            .collect();
+        self.forget_auto_added();
+        removed
+    }
+
+    /// The current track and everything after it, optionally preceded by
+    /// what has already played. Files played from outside the library are
+    /// left out since a playlist can't refer to them.
+    pub fn queue_snapshot(&self, include_history: bool) -> Vec<TrackId> {
+        let history = include_history
+            .then_some(&self.history)
+            .into_iter()
+            .flatten();
+        let current = self.current_track.iter().map(|current| current.track());
+
+        history
+            .chain(current)
+            .chain(&self.up_next)
+            .filter(|track| !track.is_external())
+            .map(|track| track.id().clone())
+            .collect()
+    }
+
+    /// Adds tracks to the end of the queue, ahead of anything autoplay
+    /// added.
+    pub fn queue_tracks(&mut self, tracks: Vec<Track>) {
+        let end = self
+            .up_next
+            .iter()
+            .position(|track| self.auto_added.contains(track.id()))
+            .unwrap_or(self.up_next.len());
+        self.up_next.splice(end..end, tracks);
+    }
+
+    /// Adds tracks autoplay picked to the end of the queue, marked so they
+    /// can be told apart from ones queued by hand.
+    pub fn queue_autoplay(&mut self, tracks: Vec<Track>) {
+        self.auto_added
+            .extend(tracks.iter().map(|track| track.id().clone()));
+        self.up_next.extend(tracks);
+    }
+
+    pub fn is_auto_added(&self, id: &TrackId) -> bool {
+        self.auto_added.contains(id)
+    }
+
+    /// Keeps the queued tracks at `indices` as if they'd been queued by
+    /// hand, so anything queued later goes after them.
+    pub fn pin_queued(&mut self, indices: &[usize]) {
+        for ix in indices {
+            if let Some(track) = self.up_next.get(*ix) {
+                self.auto_added.remove(track.id());
+            }
+        }
+    }
+
+    // Once a track leaves the queue it's no longer autoplay's
+    fn forget_auto_added(&mut self) {
+        let queued: HashSet<&TrackId> = self.up_next.iter().map(|track| track.id()).collect();
+        self.auto_added.retain(|id| queued.contains(id));
+    }
+
+    /// Puts tracks at the front of the queue, in order, to play once the
+    /// current one's done.
+    pub fn play_next(&mut self, tracks: Vec<Track>) {
+        self.up_next.splice(0..0, tracks);
+    }
+
+    /// Moves on to the next queued track, carrying on playing or staying
+    /// paused as the current one was. Stops once the queue runs out.
+    pub fn skip_next(&mut self) {
+        let is_playing = self
+            .current_track
+            .as_ref()
+            .is_none_or(|current| current.is_playing());
+        let next = (!self.up_next.is_empty()).then(|| self.up_next.remove(0));
+        self.set_current_track(next.map(|track| {
+            let mut current_track = CurrentTrack::new(track);
+            current_track.set_is_playing(is_playing);
+            current_track
+        }));
+        self.forget_auto_added();
+    }
+
+    /// Goes back to the last track played, putting the current one back at
+    /// the front of the queue. Returns false when there's nothing to go
+    /// back to.
+    pub fn skip_prev(&mut self) -> bool {
+        let Some(previous) = self.history.pop() else {
+            return false;
+        };
+        let is_playing = self
+            .current_track
+            .as_ref()
+            .is_none_or(|current| current.is_playing());
+        let mut current_track = CurrentTrack::new(previous);
+        current_track.set_is_playing(is_playing);
+        // Not through `set_current_track`, going back shouldn't count the
+        // track being left as played or put it in history again
+        if let Some(skipped) = self.current_track.replace(current_track) {
+            self.up_next.insert(0, skipped.track().clone());
+        }
+        self.generation += 1;
+        true
+    }
+
+    /// Moves past the current track, and any queued straight after it, when
+    /// `is_available` turns them down, such as tracks on an unplugged
+    /// drive. They're dropped without counting as played or going into
+    /// history. Returns how many were skipped.
+    pub fn skip_unavailable(&mut self, is_available: impl Fn(&Track) -> bool) -> usize {
+        let Some(current) = &self.current_track else {
+            return 0;
+        };
+        if is_available(current.track()) {
+            return 0;
+        }
+
+        let is_playing = current.is_playing();
+        let mut skipped = 1;
+        while self
+            .up_next
+            .first()
+            .is_some_and(|track| !is_available(track))
+        {
+            self.up_next.remove(0);
+            skipped += 1;
+        }
+        let next = (!self.up_next.is_empty()).then(|| self.up_next.remove(0));
+        self.current_track = next.map(|track| {
+            let mut current_track = CurrentTrack::new(track);
+            current_track.set_is_playing(is_playing);
+            current_track
+        });
+        self.generation += 1;
+        skipped
+    }
+
+    // Everything but the current track
+    pub fn clear_queue(&mut self) -> usize {
+        self.auto_added.clear();
+        std::mem::take(&mut self.up_next).len()
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{Library, Track, TrackId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VersionKind {
+    Studio,
+    Live,
+    Remix,
+    Alternate,
+}
+
+impl VersionKind {
+    pub const ALL: [VersionKind; 4] = [
+        VersionKind::Studio,
+        VersionKind::Live,
+        VersionKind::Remix,
+        VersionKind::Alternate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VersionKind::Studio => "Studio",
+            VersionKind::Live => "Live",
+            VersionKind::Remix => "Remix",
+            VersionKind::Alternate => "Alternate",
+        }
+    }
+
+    // Guessed from what's usually tacked onto the title, such as
+    // "Song (Live at Wembley)" or "Song - Extended Mix"
+    fn from_title(title: &str) -> Self {
+        let (_, suffix) = split_title(title);
+        let title = suffix.to_lowercase();
+        if title.contains("live") {
+            VersionKind::Live
+        } else if title.contains("remix") || title.contains(" mix") || title.contains("edit") {
+            VersionKind::Remix
+        } else if ["demo", "acoustic", "alternate", "take", "version"]
+            .iter()
+            .any(|word| title.contains(word))
+        {
+            VersionKind::Alternate
+        } else {
+            VersionKind::Studio
+        }
+    }
+}
+
+// Splits off any parenthesized or dashed suffix, so "Song (Live)" and
+// "Song - 2009 Remaster" both start with "Song"
+fn split_title(title: &str) -> (&str, &str) {
+    let end = [" (", " [", " - "]
+        .iter()
+        .filter_map(|separator| title.find(separator))
+        .min()
+        .unwrap_or(title.len());
+    title.split_at(end)
+}
+
+fn base_title(title: &str) -> String {
+    split_title(title).0.trim().to_lowercase()
+}
+
+impl Track {
+    pub fn version(&self) -> Option<VersionKind> {
+        self.version_group.as_ref().map(|_| {
+            self.version
+                .unwrap_or_else(|| VersionKind::from_title(&self.title))
+        })
+    }
+
+    /// Whether both tracks are linked versions of the same song.
+    pub fn is_version_of(&self, other: &Track) -> bool {
+        self.version_group.is_some() && self.version_group == other.version_group
+    }
+}
+
+impl Library {
+    /// The other versions `id` is linked with, in library order.
+    pub fn versions(&self, id: &TrackId) -> Vec<TrackId> {
+        let Some(track) = self.track(id) else {
+            return Vec::new();
+        };
+        self.tracks()
+            .filter(|other| other.id() != id && track.is_version_of(other))
+            .map(|other| other.id().clone())
+            .collect()
+    }
+
+    /// Unlinked tracks by the same artist that look like another version of
+    /// `id`, going by their titles.
+    pub fn version_candidates(&self, id: &TrackId) -> Vec<TrackId> {
+        let Some(track) = self.track(id) else {
+            return Vec::new();
+        };
+        let title = base_title(&track.title);
+        self.tracks()
+            .filter(|other| {
+                other.id() != id
+                    && !track.is_version_of(other)
+                    && other.artist == track.artist
+                    && base_title(&other.title) == title
+            })
+            .map(|other| other.id().clone())
+            .collect()
+    }
+
+    /// Links the tracks as versions of one song. Any groups they were
+    /// already part of are merged into one.
+    pub fn link_versions(&mut self, ids: &[TrackId]) {
+        let existing: Vec<String> = ids
+            .iter()
+            .filter_map(|id| self.tracks.get(id)?.version_group.clone())
+            .collect();
+        let group = existing
+            .first()
+            .cloned()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+        for track in self.tracks.values_mut() {
+            let merged = track
+                .version_group
+                .as_ref()
+                .is_some_and(|other| existing.contains(other));
+            if merged || ids.contains(&track.id) {
+                track.version_group = Some(group.clone());
+                self.changed_tracks.insert(track.id.clone());
+            }
+        }
+    }
+
+    pub fn unlink_versions(&mut self, ids: &[TrackId]) {
+        let mut groups = Vec::new();
+        for id in ids {
+            if let Some(track) = self.track_mut(id) {
+                groups.extend(track.version_group.take());
+                track.version = None;
+            }
+        }
+
+        // A version on its own isn't linked to anything anymore
+        for group in groups {
+            let remaining: Vec<TrackId> = self
+                .tracks
+                .values()
+                .filter(|track| track.version_group.as_ref() == Some(&group))
+                .map(|track| track.id.clone())
+                .collect();
+            if let [id] = remaining.as_slice() {
+                if let Some(track) = self.track_mut(id) {
+                    track.version_group = None;
+                    track.version = None;
+                }
+            }
+        }
+    }
+
+    pub fn set_version_kind(&mut self, ids: &[TrackId], kind: VersionKind) {
+        for id in ids {
+            if let Some(track) = self.track_mut(id) {
+                if track.version_group.is_some() {
+                    track.version = Some(kind);
+                }
+            }
+        }
+    }
+}
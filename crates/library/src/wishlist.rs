@@ -0,0 +1,152 @@
+use crate::{today, ImportedFile, Library, Track, TrackId, PLACEHOLDER_KIND};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub const WISHLIST_PLAYLIST: &str = "Wishlist";
+
+/// An album that's wanted but not in the library yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wish {
+    pub artist: String,
+    pub album: String,
+    pub added: String,
+    // The day matching files were imported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fulfilled: Option<String>,
+}
+
+impl Wish {
+    /// Reads "Artist - Album", the way wishes are typed in.
+    pub fn parse(draft: &str) -> Result<Wish, String> {
+        let Some((artist, album)) = draft.split_once(" - ") else {
+            return Err("Type the artist and album as \"Artist - Album\"".to_string());
+        };
+        let (artist, album) = (artist.trim(), album.trim());
+        if artist.is_empty() || album.is_empty() {
+            return Err("Both an artist and an album are needed".to_string());
+        }
+        Ok(Wish {
+            artist: artist.to_string(),
+            album: album.to_string(),
+            added: today(),
+            fulfilled: None,
+        })
+    }
+
+    pub fn is_fulfilled(&self) -> bool {
+        self.fulfilled.is_some()
+    }
+
+    pub fn is_for(&self, artist: &str, album: &str) -> bool {
+        self.artist.eq_ignore_ascii_case(artist) && self.album.eq_ignore_ascii_case(album)
+    }
+
+    fn matches(&self, track: &Track) -> bool {
+        track.album().eq_ignore_ascii_case(&self.album)
+            && (track.artist().eq_ignore_ascii_case(&self.artist)
+                || track.album_artist().eq_ignore_ascii_case(&self.artist))
+    }
+}
+
+impl Library {
+    /// Oldest first, fulfilled wishes included until they're removed.
+    pub fn wishlist(&self) -> &[Wish] {
+        &self.wishlist
+    }
+
+    pub fn is_wished_for(&self, artist: &str, album: &str) -> bool {
+        self.wishlist
+            .iter()
+            .any(|wish| !wish.is_fulfilled() && wish.is_for(artist, album))
+    }
+
+    /// Adds the wish along with a placeholder in the Wishlist playlist,
+    /// unless it's already wished for or the album is in the library.
+    pub fn add_wish(&mut self, wish: Wish, year: Option<u32>) -> bool {
+        let owned = self
+            .tracks()
+            .any(|track| !track.is_placeholder() && wish.matches(track));
+        if owned || self.is_wished_for(&wish.artist, &wish.album) {
+            return false;
+        }
+
+        let mut track = ImportedFile {
+            title: wish.album.clone(),
+            album_artist: Some(wish.artist.clone()),
+            year,
+            ..ImportedFile::from_path(PathBuf::new(), &wish.artist, &wish.album)
+        }
+        .into_track(wish.added.clone(), 0);
+        track.kind = PLACEHOLDER_KIND.to_string();
+        track.path = None;
+
+        let id = track.id.clone();
+        self.tracks.insert(id.clone(), track);
+        self.track_order.push(id.clone());
+
+        let existing = self
+            .playlists()
+            .iter()
+            .find(|playlist| playlist.name().as_ref() == WISHLIST_PLAYLIST)
+            .map(|playlist| playlist.id().clone());
+        let playlist = existing.unwrap_or_else(|| self.create_playlist(WISHLIST_PLAYLIST));
+        self.add_to_playlist(&playlist, std::slice::from_ref(&id));
+
+        self.wishlist.push(wish);
+        true
+    }
+
+    pub fn remove_wish(&mut self, ix: usize) {
+        if ix < self.wishlist.len() {
+            let wish = self.wishlist.remove(ix);
+            self.remove_placeholders(&wish);
+        }
+    }
+
+    /// Wishes fulfilled since this was last called, for telling the user.
+    pub fn take_fulfilled_wishes(&mut self) -> Vec<Wish> {
+        std::mem::take(&mut self.fulfilled_wishes)
+    }
+
+    // Marks off wishes the newly imported tracks match, swapping their
+    // placeholders out
+    pub(crate) fn fulfill_wishes(&mut self, ids: &[TrackId]) {
+        let today = today();
+        let mut fulfilled = Vec::new();
+        for wish in self.wishlist.iter_mut().filter(|wish| !wish.is_fulfilled()) {
+            let matched = ids
+                .iter()
+                .filter_map(|id| self.tracks.get(id))
+                .any(|track| !track.is_placeholder() && wish.matches(track));
+            if matched {
+                wish.fulfilled = Some(today.clone());
+                fulfilled.push(wish.clone());
+            }
+        }
+
+        for wish in &fulfilled {
+            self.remove_placeholders(wish);
+        }
+        self.fulfilled_wishes.extend(fulfilled);
+    }
+
+    fn remove_placeholders(&mut self, wish: &Wish) {
+        let ids: Vec<TrackId> = self
+            .tracks()
+            .filter(|track| track.is_placeholder() && wish.matches(track))
+            .map(|track| track.id().clone())
+            .collect();
+        for id in &ids {
+            let playlists: Vec<_> = self
+                .playlists_containing(id)
+                .into_iter()
+                .map(|playlist| playlist.id().clone())
+                .collect();
+            for playlist in playlists {
+                self.remove_from_playlist(&playlist, std::slice::from_ref(id));
+            }
+            self.tracks.remove(id);
+        }
+        self.track_order.retain(|id| !ids.contains(id));
+    }
+}
@@ -0,0 +1,332 @@
+use anyhow::{anyhow, Context as _};
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{CaptureFormat, TrackFile};
+
+// Blocks of input waiting for the writer, a few seconds at most
+const CHUNK_QUEUE: usize = 512;
+// Shorter than this is a click or a needle drop, not a song
+const MIN_TRACK: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureSettings {
+    // `None` records from the system default input
+    pub device: Option<String>,
+    pub format: CaptureFormat,
+    // Start a new track at each gap between songs
+    pub split_on_silence: bool,
+    // Quieter than this counts as a gap, in dBFS
+    pub silence_threshold_db: f32,
+    // How long a gap has to last, in seconds
+    pub min_silence_secs: f32,
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        CaptureSettings {
+            device: None,
+            format: CaptureFormat::Flac,
+            split_on_silence: true,
+            silence_threshold_db: -50.,
+            min_silence_secs: 2.,
+        }
+    }
+}
+
+/// Names of the inputs that can be recorded from.
+pub fn input_devices() -> Vec<String> {
+    let Ok(devices) = cpal::default_host().input_devices() else {
+        return Vec::new();
+    };
+    devices.filter_map(|device| device.name().ok()).collect()
+}
+
+// Shared between the input callback, the writer and the app
+#[derive(Default)]
+struct CaptureShared {
+    // Loudest sample per channel since the meters last looked
+    peaks: Mutex<Vec<f32>>,
+    frames: AtomicU64,
+    tracks: AtomicUsize,
+    silent: AtomicBool,
+    // Input the writer couldn't keep up with
+    dropped: AtomicU64,
+}
+
+/// Records an input to disk, one file per track. Leading and trailing
+/// silence are left out, and with splitting on, each gap long enough
+/// ends the track and the next sound starts another.
+pub struct Capture {
+    stream: cpal::Stream,
+    shared: Arc<CaptureShared>,
+    writer: JoinHandle<anyhow::Result<Vec<PathBuf>>>,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl Capture {
+    /// Starts recording into `folder`, which is created if it's missing.
+    pub fn start(folder: &Path, settings: &CaptureSettings) -> anyhow::Result<Capture> {
+        let host = cpal::default_host();
+        let device = match &settings.device {
+            Some(name) => host
+                .input_devices()?
+                .find(|device| device.name().is_ok_and(|found| &found == name)),
+            None => host.default_input_device(),
+        }
+        .ok_or_else(|| anyhow!("No audio input device"))?;
+        let supported = device
+            .default_input_config()
+            .context("Couldn't read the input's format")?;
+        let config = supported.config();
+        fs::create_dir_all(folder)?;
+
+        let shared = Arc::new(CaptureShared {
+            peaks: Mutex::new(vec![0.; config.channels as usize]),
+            ..Default::default()
+        });
+        let (chunks_tx, chunks_rx) = mpsc::sync_channel(CHUNK_QUEUE);
+        let stream = match supported.sample_format() {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, chunks_tx, &shared),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, chunks_tx, &shared),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, chunks_tx, &shared),
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, chunks_tx, &shared),
+            other => return Err(anyhow!("Unsupported input format {}", other)),
+        }?;
+
+        let writer = Writer {
+            folder: folder.to_path_buf(),
+            format: settings.format,
+            split: settings.split_on_silence,
+            threshold: 10f32.powf(settings.silence_threshold_db / 20.),
+            min_silence: (settings.min_silence_secs.max(0.) * config.sample_rate.0 as f32) as u64,
+            min_track: MIN_TRACK.as_secs() * config.sample_rate.0 as u64,
+            sample_rate: config.sample_rate.0,
+            channels: config.channels,
+            shared: shared.clone(),
+            current: None,
+            held: Vec::new(),
+            held_frames: 0,
+            finished: Vec::new(),
+        };
+        let writer = thread::Builder::new()
+            .name("capture".into())
+            .spawn(move || writer.run(chunks_rx))?;
+        stream.play().context("Couldn't start recording")?;
+
+        Ok(Capture {
+            stream,
+            shared,
+            writer,
+            sample_rate: config.sample_rate.0,
+            channels: config.channels,
+        })
+    }
+
+    /// The loudest each channel has been since last asked, from 0 to 1.
+    pub fn levels(&self) -> Vec<f32> {
+        let mut peaks = self.shared.peaks.lock().unwrap();
+        let levels = peaks.clone();
+        peaks.iter_mut().for_each(|peak| *peak = 0.);
+        levels
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        let frames = self.shared.frames.load(Ordering::Relaxed);
+        Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+    }
+
+    /// Tracks started so far, including the one being recorded.
+    pub fn tracks(&self) -> usize {
+        self.shared.tracks.load(Ordering::Relaxed)
+    }
+
+    /// Whether the input is quiet, between songs or before the first.
+    pub fn is_silent(&self) -> bool {
+        self.shared.silent.load(Ordering::Relaxed)
+    }
+
+    /// How much input was lost because the disk couldn't keep up.
+    pub fn dropped(&self) -> Duration {
+        let samples = self.shared.dropped.load(Ordering::Relaxed);
+        let frames = samples / self.channels.max(1) as u64;
+        Duration::from_secs_f64(frames as f64 / self.sample_rate.max(1) as f64)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} kHz, {} ch",
+            self.sample_rate as f32 / 1000.,
+            self.channels
+        )
+    }
+
+    /// Stops recording and finishes the last track, returning every track
+    /// written in order.
+    pub fn stop(self) -> anyhow::Result<Vec<PathBuf>> {
+        // Dropping the stream drops the sender, which lets the writer finish
+        drop(self.stream);
+        self.writer
+            .join()
+            .map_err(|_| anyhow!("The recording stopped unexpectedly"))?
+    }
+}
+
+fn build_stream<T: SizedSample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    chunks: SyncSender<Vec<f32>>,
+    shared: &Arc<CaptureShared>,
+) -> anyhow::Result<cpal::Stream>
+where
+    f32: FromSample<T>,
+{
+    let shared = shared.clone();
+    let stream = device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                let chunk = data
+                    .iter()
+                    .map(|sample| sample.to_sample::<f32>())
+                    .collect();
+                if chunks.try_send(chunk).is_err() {
+                    shared
+                        .dropped
+                        .fetch_add(data.len() as u64, Ordering::Relaxed);
+                }
+            },
+            |e| eprintln!("Audio input failed: {}", e),
+            None,
+        )
+        .context("Couldn't open the input")?;
+    Ok(stream)
+}
+
+// Meters the input and writes it out, deciding where tracks begin and end
+struct Writer {
+    folder: PathBuf,
+    format: CaptureFormat,
+    split: bool,
+    threshold: f32,
+    // In frames
+    min_silence: u64,
+    min_track: u64,
+    sample_rate: u32,
+    channels: u16,
+    shared: Arc<CaptureShared>,
+    // The track being written and how many frames it has
+    current: Option<(TrackFile, u64)>,
+    // Quiet held back until it's clear whether the track goes on
+    held: Vec<i32>,
+    held_frames: u64,
+    finished: Vec<PathBuf>,
+}
+
+impl Writer {
+    fn run(mut self, chunks: Receiver<Vec<f32>>) -> anyhow::Result<Vec<PathBuf>> {
+        while let Ok(chunk) = chunks.recv() {
+            self.process(&chunk)?;
+        }
+        self.end_track()?;
+        Ok(self.finished)
+    }
+
+    fn process(&mut self, chunk: &[f32]) -> anyhow::Result<()> {
+        let channels = self.channels as usize;
+        let frames = (chunk.len() / channels) as u64;
+        self.shared.frames.fetch_add(frames, Ordering::Relaxed);
+
+        let mut loudest = 0f32;
+        {
+            let mut peaks = self.shared.peaks.lock().unwrap();
+            for frame in chunk.chunks(channels) {
+                for (peak, sample) in peaks.iter_mut().zip(frame) {
+                    *peak = peak.max(sample.abs());
+                    loudest = loudest.max(sample.abs());
+                }
+            }
+        }
+        let samples = chunk
+            .iter()
+            .map(|sample| (sample.clamp(-1., 1.) * i16::MAX as f32).round() as i32);
+
+        let silent = loudest < self.threshold;
+        self.shared.silent.store(silent, Ordering::Relaxed);
+        if silent {
+            if self.current.is_none() {
+                return Ok(());
+            }
+            self.held.extend(samples);
+            self.held_frames += frames;
+            if self.split && self.held_frames >= self.min_silence {
+                self.end_track()?;
+            }
+            return Ok(());
+        }
+
+        if self.current.is_none() {
+            self.start_track()?;
+        }
+        let (file, len) = self.current.as_mut().unwrap();
+        if !self.held.is_empty() {
+            file.write(&self.held)?;
+            *len += self.held_frames;
+        }
+        self.held.clear();
+        self.held_frames = 0;
+        file.write(&samples.collect::<Vec<_>>())?;
+        *len += frames;
+        Ok(())
+    }
+
+    fn start_track(&mut self) -> anyhow::Result<()> {
+        let number = self.finished.len() + 1;
+        let path = self.folder.join(format!(
+            "{:02} Track {}.{}",
+            number,
+            number,
+            self.format.extension()
+        ));
+        let file = TrackFile::create(&path, self.format, self.sample_rate, self.channels)
+            .with_context(|| format!("Couldn't create {}", path.display()))?;
+        self.current = Some((file, 0));
+        self.shared.tracks.store(number, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // Trailing silence is dropped, as are tracks too short to be songs
+    fn end_track(&mut self) -> anyhow::Result<()> {
+        self.held.clear();
+        self.held_frames = 0;
+        let Some((file, len)) = self.current.take() else {
+            return Ok(());
+        };
+        let path = file.finish()?;
+        if len < self.min_track {
+            fs::remove_file(&path).ok();
+        } else {
+            self.finished.push(path);
+        }
+        self.shared
+            .tracks
+            .store(self.finished.len(), Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
+
+// No tick or poll is ever this late unless the machine was asleep
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// The playback position as the audio device sees it, counted in frames it
+/// has actually played rather than time passed, so it can't run ahead of
+/// the audio while the system is asleep or the output is stalled.
+pub struct PlaybackClock {
+    frames: AtomicU64,
+    sample_rate: AtomicU32,
+}
+
+impl PlaybackClock {
+    pub fn new(sample_rate: u32) -> Self {
+        PlaybackClock {
+            frames: AtomicU64::new(0),
+            sample_rate: AtomicU32::new(sample_rate.max(1)),
+        }
+    }
+
+    /// Called from the audio callback with the frames it just handed over.
+    pub fn advance(&self, frames: u64) {
+        self.frames.fetch_add(frames, Ordering::Relaxed);
+    }
+
+    pub fn seek(&self, position: Duration) {
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed) as f64;
+        self.frames.store(
+            (position.as_secs_f64() * sample_rate) as u64,
+            Ordering::Relaxed,
+        );
+    }
+
+    // A rebuilt stream may open at a different rate, the position carries over
+    pub fn set_sample_rate(&self, sample_rate: u32) {
+        let position = self.position();
+        self.sample_rate
+            .store(sample_rate.max(1), Ordering::Relaxed);
+        self.seek(position);
+    }
+
+    pub fn position(&self) -> Duration {
+        let frames = self.frames.load(Ordering::Relaxed);
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed) as u64;
+        Duration::from_secs(frames / sample_rate)
+            + Duration::from_nanos(frames % sample_rate * 1_000_000_000 / sample_rate)
+    }
+}
+
+/// Notices the system having slept between two checks. Monotonic time stops
+/// during suspend on some platforms and not others, so this goes by the wall
+/// clock instead.
+#[derive(Debug)]
+pub struct SuspendDetector {
+    last_check: SystemTime,
+}
+
+impl Default for SuspendDetector {
+    fn default() -> Self {
+        SuspendDetector {
+            last_check: SystemTime::now(),
+        }
+    }
+}
+
+impl SuspendDetector {
+    /// How long the system was away, if it looks like it slept since the
+    /// last check.
+    pub fn check(&mut self) -> Option<Duration> {
+        let now = SystemTime::now();
+        // The wall clock going backwards is a clock change, not a suspend
+        let elapsed = now.duration_since(self.last_check).unwrap_or_default();
+        self.last_check = now;
+        (elapsed >= SUSPEND_THRESHOLD).then_some(elapsed)
+    }
+}
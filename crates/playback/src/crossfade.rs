@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrossfadeSettings {
+    pub enabled: bool,
+    pub duration_secs: f32,
+}
+
+impl Default for CrossfadeSettings {
+    fn default() -> Self {
+        CrossfadeSettings {
+            enabled: false,
+            duration_secs: 6.,
+        }
+    }
+}
+
+impl CrossfadeSettings {
+    pub fn duration(&self) -> Option<Duration> {
+        (self.enabled && self.duration_secs > 0.)
+            .then(|| Duration::from_secs_f32(self.duration_secs))
+    }
+}
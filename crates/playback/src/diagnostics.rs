@@ -0,0 +1,67 @@
+use crate::OutputFormat;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+static DECODE_MEMORY: AtomicUsize = AtomicUsize::new(0);
+static PEAK_DECODE_MEMORY: AtomicUsize = AtomicUsize::new(0);
+static OUTPUT_FORMAT: Mutex<Option<OutputFormat>> = Mutex::new(None);
+
+// Published by the engine whenever the output stream is (re)built
+pub fn set_output_format(format: Option<OutputFormat>) {
+    *OUTPUT_FORMAT.lock().unwrap() = format;
+}
+
+pub fn output_format() -> Option<OutputFormat> {
+    OUTPUT_FORMAT.lock().unwrap().clone()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DecodeMemoryStats {
+    pub current: usize,
+    pub peak: usize,
+}
+
+pub fn decode_memory() -> DecodeMemoryStats {
+    DecodeMemoryStats {
+        current: DECODE_MEMORY.load(Ordering::Relaxed),
+        peak: PEAK_DECODE_MEMORY.load(Ordering::Relaxed),
+    }
+}
+
+pub fn reset_peak_decode_memory() {
+    PEAK_DECODE_MEMORY.store(DECODE_MEMORY.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// Counts a decoder buffer towards the decode memory stats for as long as
+/// it is alive.
+#[derive(Debug)]
+pub struct DecodeAllocation {
+    bytes: usize,
+}
+
+impl DecodeAllocation {
+    pub fn new(bytes: usize) -> Self {
+        let current = DECODE_MEMORY.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        PEAK_DECODE_MEMORY.fetch_max(current, Ordering::Relaxed);
+        DecodeAllocation { bytes }
+    }
+
+    pub fn resize(&mut self, bytes: usize) {
+        if bytes > self.bytes {
+            let grown = bytes - self.bytes;
+            let current = DECODE_MEMORY.fetch_add(grown, Ordering::Relaxed) + grown;
+            PEAK_DECODE_MEMORY.fetch_max(current, Ordering::Relaxed);
+        } else {
+            DECODE_MEMORY.fetch_sub(self.bytes - bytes, Ordering::Relaxed);
+        }
+        self.bytes = bytes;
+    }
+}
+
+impl Drop for DecodeAllocation {
+    fn drop(&mut self) {
+        DECODE_MEMORY.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// One of a plugin's settings, described well enough for a generic editor
+/// to offer it without knowing the plugin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterDescriptor {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub unit: &'static str,
+}
+
+impl ParameterDescriptor {
+    // Twenty steps from one end to the other, for stepping buttons
+    pub fn step(&self) -> f32 {
+        (self.max - self.min) / 20.
+    }
+
+    pub fn clamp(&self, value: f32) -> f32 {
+        value.clamp(self.min, self.max)
+    }
+}
+
+/// An effect in the playback pipeline. `process` runs on the audio thread,
+/// so it must not block or allocate.
+pub trait DspPlugin: Send {
+    fn parameters(&self) -> &'static [ParameterDescriptor];
+
+    fn set_parameter(&mut self, id: &str, value: f32);
+
+    // Called before the first buffer and again whenever the stream is rebuilt
+    fn prepare(&mut self, _sample_rate: u32, _channels: u16) {}
+
+    /// Processes interleaved frames in place.
+    fn process(&mut self, samples: &mut [f32], channels: u16);
+}
+
+#[derive(Clone)]
+pub struct PluginInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub parameters: &'static [ParameterDescriptor],
+    create: fn() -> Box<dyn DspPlugin>,
+}
+
+/// Every plugin that can be put in the chain. Other crates add theirs with
+/// `register` before the chain is built.
+#[derive(Clone)]
+pub struct DspRegistry {
+    plugins: Vec<PluginInfo>,
+}
+
+impl Default for DspRegistry {
+    fn default() -> Self {
+        let mut registry = DspRegistry {
+            plugins: Vec::new(),
+        };
+        registry.register("gain", "Gain", || Box::new(Gain::default()));
+        registry.register("stereo_width", "Stereo Width", || {
+            Box::new(StereoWidth::default())
+        });
+        registry
+    }
+}
+
+impl DspRegistry {
+    // A later registration under the same id replaces the earlier one
+    pub fn register(
+        &mut self,
+        id: &'static str,
+        name: &'static str,
+        create: fn() -> Box<dyn DspPlugin>,
+    ) {
+        let parameters = create().parameters();
+        self.plugins.retain(|plugin| plugin.id != id);
+        self.plugins.push(PluginInfo {
+            id,
+            name,
+            parameters,
+            create,
+        });
+    }
+
+    pub fn plugins(&self) -> &[PluginInfo] {
+        &self.plugins
+    }
+
+    pub fn plugin(&self, id: &str) -> Option<&PluginInfo> {
+        self.plugins.iter().find(|plugin| plugin.id == id)
+    }
+}
+
+/// A plugin's place in the chain, as saved in settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DspSlot {
+    pub plugin: String,
+    pub bypassed: bool,
+    // Anything missing is left at its default
+    pub parameters: BTreeMap<String, f32>,
+}
+
+impl DspSlot {
+    pub fn parameter(&self, descriptor: &ParameterDescriptor) -> f32 {
+        self.parameters
+            .get(descriptor.id)
+            .copied()
+            .unwrap_or(descriptor.default)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DspSettings {
+    // Processed in order, first to last
+    pub chain: Vec<DspSlot>,
+}
+
+impl DspSettings {
+    pub fn add(&mut self, plugin: &str) {
+        self.chain.push(DspSlot {
+            plugin: plugin.to_string(),
+            ..Default::default()
+        });
+    }
+
+    pub fn remove(&mut self, ix: usize) {
+        if ix < self.chain.len() {
+            self.chain.remove(ix);
+        }
+    }
+
+    /// Moves a slot up (negative) or down the chain.
+    pub fn move_slot(&mut self, ix: usize, offset: isize) {
+        let target = ix as isize + offset;
+        if ix < self.chain.len() && target >= 0 && (target as usize) < self.chain.len() {
+            let slot = self.chain.remove(ix);
+            self.chain.insert(target as usize, slot);
+        }
+    }
+
+    pub fn toggle_bypass(&mut self, ix: usize) {
+        if let Some(slot) = self.chain.get_mut(ix) {
+            slot.bypassed = !slot.bypassed;
+        }
+    }
+
+    pub fn set_parameter(&mut self, ix: usize, descriptor: &ParameterDescriptor, value: f32) {
+        if let Some(slot) = self.chain.get_mut(ix) {
+            slot.parameters
+                .insert(descriptor.id.to_string(), descriptor.clamp(value));
+        }
+    }
+}
+
+/// The plugins from settings, ready to run on the audio thread. Rebuilt
+/// whenever the chain is edited.
+#[derive(Default)]
+pub struct DspChain {
+    plugins: Vec<Box<dyn DspPlugin>>,
+}
+
+impl DspChain {
+    pub fn build(
+        settings: &DspSettings,
+        registry: &DspRegistry,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Self {
+        let plugins = settings
+            .chain
+            .iter()
+            .filter(|slot| !slot.bypassed)
+            .filter_map(|slot| {
+                let Some(info) = registry.plugin(&slot.plugin) else {
+                    eprintln!("Skipping unknown effect {}", slot.plugin);
+                    return None;
+                };
+                let mut plugin = (info.create)();
+                for descriptor in info.parameters {
+                    plugin.set_parameter(descriptor.id, slot.parameter(descriptor));
+                }
+                plugin.prepare(sample_rate, channels);
+                Some(plugin)
+            })
+            .collect();
+        DspChain { plugins }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    pub fn process(&mut self, samples: &mut [f32], channels: u16) {
+        for plugin in &mut self.plugins {
+            plugin.process(samples, channels);
+        }
+    }
+}
+
+const GAIN_PARAMETERS: &[ParameterDescriptor] = &[ParameterDescriptor {
+    id: "gain",
+    name: "Gain",
+    min: -24.,
+    max: 12.,
+    default: 0.,
+    unit: "dB",
+}];
+
+#[derive(Default)]
+struct Gain {
+    factor: f32,
+}
+
+impl DspPlugin for Gain {
+    fn parameters(&self) -> &'static [ParameterDescriptor] {
+        GAIN_PARAMETERS
+    }
+
+    fn set_parameter(&mut self, id: &str, value: f32) {
+        if id == "gain" {
+            self.factor = 10f32.powf(value / 20.);
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32], _channels: u16) {
+        for sample in samples {
+            *sample *= self.factor;
+        }
+    }
+}
+
+const WIDTH_PARAMETERS: &[ParameterDescriptor] = &[ParameterDescriptor {
+    id: "width",
+    name: "Width",
+    min: 0.,
+    max: 200.,
+    default: 100.,
+    unit: "%",
+}];
+
+// Scales the side signal against the mid, 0% folds down to mono
+#[derive(Default)]
+struct StereoWidth {
+    width: f32,
+}
+
+impl DspPlugin for StereoWidth {
+    fn parameters(&self) -> &'static [ParameterDescriptor] {
+        WIDTH_PARAMETERS
+    }
+
+    fn set_parameter(&mut self, id: &str, value: f32) {
+        if id == "width" {
+            self.width = value / 100.;
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32], channels: u16) {
+        if channels != 2 {
+            return;
+        }
+        for frame in samples.chunks_exact_mut(2) {
+            let mid = (frame[0] + frame[1]) / 2.;
+            let side = (frame[0] - frame[1]) / 2. * self.width;
+            frame[0] = mid + side;
+            frame[1] = mid - side;
+        }
+    }
+}
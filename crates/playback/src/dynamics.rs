@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+// How quickly night mode clamps down on a loud passage, and lets go after
+const NIGHT_MODE_ATTACK_SECS: f32 = 0.01;
+const NIGHT_MODE_RELEASE_SECS: f32 = 0.3;
+// How quickly the limiter recovers once a peak has passed
+const LIMITER_RELEASE_SECS: f32 = 0.1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DynamicsSettings {
+    // Holds peaks under the ceiling, so boosts from effects never clip
+    pub limiter: bool,
+    pub limiter_ceiling_db: f32,
+    // Evens out loud and quiet passages for listening late at night
+    pub night_mode: bool,
+    pub night_mode_threshold_db: f32,
+    pub night_mode_ratio: f32,
+    // Brings the quiet passages back up once the loud ones are down
+    pub night_mode_makeup_db: f32,
+}
+
+impl Default for DynamicsSettings {
+    fn default() -> Self {
+        DynamicsSettings {
+            limiter: false,
+            limiter_ceiling_db: -1.,
+            night_mode: false,
+            night_mode_threshold_db: -30.,
+            night_mode_ratio: 4.,
+            night_mode_makeup_db: 8.,
+        }
+    }
+}
+
+/// The compressor and limiter from settings, run after the effects chain.
+/// Both follow the loudest channel so the stereo image doesn't wander.
+pub struct Dynamics {
+    settings: DynamicsSettings,
+    attack: f32,
+    release: f32,
+    limiter_release: f32,
+    // Night mode's current gain reduction, in dB
+    reduction_db: f32,
+    limiter_gain: f32,
+}
+
+impl Default for Dynamics {
+    fn default() -> Self {
+        Dynamics::new(DynamicsSettings::default())
+    }
+}
+
+impl Dynamics {
+    pub fn new(settings: DynamicsSettings) -> Self {
+        let mut dynamics = Dynamics {
+            settings,
+            attack: 0.,
+            release: 0.,
+            limiter_release: 0.,
+            reduction_db: 0.,
+            limiter_gain: 1.,
+        };
+        dynamics.prepare(44_100);
+        dynamics
+    }
+
+    pub fn prepare(&mut self, sample_rate: u32) {
+        let coefficient = |secs: f32| (-1. / (secs * sample_rate.max(1) as f32)).exp();
+        self.attack = coefficient(NIGHT_MODE_ATTACK_SECS);
+        self.release = coefficient(NIGHT_MODE_RELEASE_SECS);
+        self.limiter_release = coefficient(LIMITER_RELEASE_SECS);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.settings.limiter || self.settings.night_mode
+    }
+
+    /// Processes interleaved frames in place.
+    pub fn process(&mut self, samples: &mut [f32], channels: u16) {
+        if !self.is_active() {
+            return;
+        }
+        let settings = &self.settings;
+        let ratio = settings.night_mode_ratio.max(1.);
+        let ceiling = db_to_gain(settings.limiter_ceiling_db.min(0.));
+
+        for frame in samples.chunks_exact_mut(channels.max(1) as usize) {
+            let peak = frame
+                .iter()
+                .fold(0f32, |peak, sample| peak.max(sample.abs()));
+
+            let mut gain = 1.;
+            if settings.night_mode {
+                let level_db = 20. * peak.max(1e-9).log10();
+                let over = level_db - settings.night_mode_threshold_db;
+                let target = if over > 0. {
+                    over * (1. - 1. / ratio)
+                } else {
+                    0.
+                };
+                let coefficient = if target > self.reduction_db {
+                    self.attack
+                } else {
+                    self.release
+                };
+                self.reduction_db = target + (self.reduction_db - target) * coefficient;
+                gain = db_to_gain(settings.night_mode_makeup_db - self.reduction_db);
+            }
+
+            // Night mode's makeup gain could clip a sudden peak before it
+            // catches up, so it's always limited too
+            if settings.limiter || settings.night_mode {
+                // Clamps down at once on a peak, then eases back off
+                let needed = if peak * gain > ceiling {
+                    ceiling / (peak * gain)
+                } else {
+                    1.
+                };
+                self.limiter_gain =
+                    (1. + (self.limiter_gain - 1.) * self.limiter_release).min(needed);
+                gain *= self.limiter_gain;
+            }
+
+            for sample in frame {
+                *sample *= gain;
+            }
+        }
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.)
+}
@@ -0,0 +1,515 @@
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError,
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+// Samples are written as 16-bit, plenty for a record or a tape
+const BITS_PER_SAMPLE: u32 = 16;
+const FLAC_BLOCK_SIZE: usize = 4096;
+const FLAC_MAX_FIXED_ORDER: usize = 4;
+const FLAC_MAX_RICE_PARAMETER: u32 = 14;
+// Where STREAMINFO starts, after "fLaC" and its block header
+const FLAC_STREAMINFO_OFFSET: u64 = 8;
+const AIFF_HEADER_LEN: u64 = 54;
+
+/// What recordings are saved as. Both are lossless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureFormat {
+    #[default]
+    Flac,
+    Aiff,
+}
+
+impl CaptureFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptureFormat::Flac => "flac",
+            CaptureFormat::Aiff => "aiff",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaptureFormat::Flac => "FLAC",
+            CaptureFormat::Aiff => "AIFF",
+        }
+    }
+}
+
+/// A file being recorded into, fed interleaved 16-bit samples.
+pub enum TrackFile {
+    Flac(FlacWriter),
+    Aiff(AiffWriter),
+}
+
+impl TrackFile {
+    pub fn create(
+        path: &Path,
+        format: CaptureFormat,
+        sample_rate: u32,
+        channels: u16,
+    ) -> io::Result<Self> {
+        Ok(match format {
+            CaptureFormat::Flac => {
+                TrackFile::Flac(FlacWriter::create(path, sample_rate, channels)?)
+            }
+            CaptureFormat::Aiff => {
+                TrackFile::Aiff(AiffWriter::create(path, sample_rate, channels)?)
+            }
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            TrackFile::Flac(writer) => &writer.path,
+            TrackFile::Aiff(writer) => &writer.path,
+        }
+    }
+
+    pub fn write(&mut self, samples: &[i32]) -> io::Result<()> {
+        match self {
+            TrackFile::Flac(writer) => writer.write(samples),
+            TrackFile::Aiff(writer) => writer.write(samples),
+        }
+    }
+
+    /// Fills in the lengths the header was written without.
+    pub fn finish(self) -> io::Result<PathBuf> {
+        match self {
+            TrackFile::Flac(writer) => writer.finish(),
+            TrackFile::Aiff(writer) => writer.finish(),
+        }
+    }
+}
+
+/// Decodes `source` and writes it out again as `destination`, such as to
+/// shrink a WAV file into FLAC. Files with more than 16 bits a sample are
+/// refused, as they'd lose some of it.
+pub fn transcode(
+    source: &Path,
+    destination: &Path,
+    format: CaptureFormat,
+) -> anyhow::Result<PathBuf> {
+    let result = write_transcoded(source, destination, format);
+    // Nothing half written is left behind
+    if result.is_err() {
+        fs::remove_file(destination).ok();
+    }
+    result
+}
+
+fn write_transcoded(
+    source: &Path,
+    destination: &Path,
+    format: CaptureFormat,
+) -> anyhow::Result<PathBuf> {
+    let stream = MediaSourceStream::new(Box::new(File::open(source)?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = source.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut reader = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+    let track = reader
+        .default_track()
+        .ok_or_else(|| anyhow!("No audio track"))?;
+    if let Some(bits) = track
+        .codec_params
+        .bits_per_sample
+        .filter(|&bits| bits > BITS_PER_SAMPLE)
+    {
+        bail!("{}-bit samples would lose detail", bits);
+    }
+    let track_id = track.id;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    // Created once the first packet says what the audio is
+    let mut file: Option<TrackFile> = None;
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = decoder.decode(&packet)?;
+        let spec = *decoded.spec();
+        let mut buffer = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+
+        let file = match &mut file {
+            Some(file) => file,
+            None => file.insert(TrackFile::create(
+                destination,
+                format,
+                spec.rate,
+                spec.channels.count() as u16,
+            )?),
+        };
+        let samples: Vec<i32> = buffer
+            .samples()
+            .iter()
+            .map(|&sample| sample as i32)
+            .collect();
+        file.write(&samples)?;
+    }
+
+    let file = file.ok_or_else(|| anyhow!("No audio in {}", source.display()))?;
+    Ok(file.finish()?)
+}
+
+pub struct AiffWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    sample_rate: u32,
+    channels: u16,
+    frames: u32,
+}
+
+impl AiffWriter {
+    fn create(path: &Path, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let mut writer = AiffWriter {
+            path: path.to_path_buf(),
+            file: BufWriter::new(File::create(path)?),
+            sample_rate,
+            channels,
+            frames: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let data_len = self.frames * self.channels as u32 * 2;
+        let file = &mut self.file;
+        file.write_all(b"FORM")?;
+        file.write_all(&(AIFF_HEADER_LEN as u32 - 8 + data_len).to_be_bytes())?;
+        file.write_all(b"AIFF")?;
+        file.write_all(b"COMM")?;
+        file.write_all(&18u32.to_be_bytes())?;
+        file.write_all(&(self.channels as i16).to_be_bytes())?;
+        file.write_all(&self.frames.to_be_bytes())?;
+        file.write_all(&(BITS_PER_SAMPLE as i16).to_be_bytes())?;
+        file.write_all(&extended(self.sample_rate))?;
+        file.write_all(b"SSND")?;
+        file.write_all(&(8 + data_len).to_be_bytes())?;
+        // Offset and block size, neither used
+        file.write_all(&[0; 8])
+    }
+
+    fn write(&mut self, samples: &[i32]) -> io::Result<()> {
+        for sample in samples {
+            self.file.write_all(&(*sample as i16).to_be_bytes())?;
+        }
+        self.frames += (samples.len() / self.channels as usize) as u32;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<PathBuf> {
+        self.file.seek(SeekFrom::Start(0))?;
+        self.write_header()?;
+        self.file.flush()?;
+        Ok(self.path)
+    }
+}
+
+// AIFF gives the sample rate as an 80-bit float
+fn extended(value: u32) -> [u8; 10] {
+    let mut bytes = [0; 10];
+    if value == 0 {
+        return bytes;
+    }
+    let shift = 31 - value.leading_zeros();
+    let exponent = 16383 + shift as u16;
+    let mantissa = (value as u64) << (63 - shift);
+    bytes[..2].copy_from_slice(&exponent.to_be_bytes());
+    bytes[2..].copy_from_slice(&mantissa.to_be_bytes());
+    bytes
+}
+
+/// Encodes FLAC with the format's fixed predictors, which gets most of
+/// the way to what the reference encoder manages for far less work.
+pub struct FlacWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    sample_rate: u32,
+    channels: u16,
+    // Waiting to fill a block
+    pending: Vec<i32>,
+    frames: u64,
+    frame_number: u32,
+}
+
+impl FlacWriter {
+    fn create(path: &Path, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let mut writer = FlacWriter {
+            path: path.to_path_buf(),
+            file: BufWriter::new(File::create(path)?),
+            sample_rate,
+            channels,
+            pending: Vec::new(),
+            frames: 0,
+            frame_number: 0,
+        };
+        writer.file.write_all(b"fLaC")?;
+        // The last metadata block, STREAMINFO, 34 bytes long
+        writer.file.write_all(&[0x80, 0, 0, 34])?;
+        let streaminfo = writer.streaminfo();
+        writer.file.write_all(&streaminfo)?;
+        Ok(writer)
+    }
+
+    fn streaminfo(&self) -> [u8; 34] {
+        let mut info = [0; 34];
+        info[0..2].copy_from_slice(&(FLAC_BLOCK_SIZE as u16).to_be_bytes());
+        info[2..4].copy_from_slice(&(FLAC_BLOCK_SIZE as u16).to_be_bytes());
+        // Frame sizes are left unknown, as is the MD5
+        let packed = (self.sample_rate as u64) << 44
+            | ((self.channels as u64 - 1) & 0x7) << 41
+            | (BITS_PER_SAMPLE as u64 - 1) << 36
+            | self.frames & 0xF_FFFF_FFFF;
+        info[10..18].copy_from_slice(&packed.to_be_bytes());
+        info
+    }
+
+    fn write(&mut self, samples: &[i32]) -> io::Result<()> {
+        self.pending.extend_from_slice(samples);
+        let block = FLAC_BLOCK_SIZE * self.channels as usize;
+        while self.pending.len() >= block {
+            let rest = self.pending.split_off(block);
+            let samples = std::mem::replace(&mut self.pending, rest);
+            self.write_frame(&samples)?;
+        }
+        Ok(())
+    }
+
+    fn write_frame(&mut self, samples: &[i32]) -> io::Result<()> {
+        let channels = self.channels as usize;
+        let block_size = samples.len() / channels;
+        if block_size == 0 {
+            return Ok(());
+        }
+
+        let mut bits = BitWriter::default();
+        // Sync code, then a fixed block size
+        bits.write(0b11_1111_1111_1110, 14);
+        bits.write(0, 2);
+        // The block size follows the header as 16 bits, the sample rate
+        // comes from STREAMINFO
+        bits.write(0b0111, 4);
+        bits.write(0b0000, 4);
+        bits.write(channels as u64 - 1, 4);
+        bits.write(0b100, 3);
+        bits.write(0, 1);
+        bits.write_utf8(self.frame_number);
+        bits.write(block_size as u64 - 1, 16);
+        let crc = crc8(&bits.bytes);
+        bits.write(crc as u64, 8);
+
+        let mut channel = Vec::with_capacity(block_size);
+        for ix in 0..channels {
+            channel.clear();
+            channel.extend(samples.iter().skip(ix).step_by(channels).map(|s| *s as i64));
+            write_subframe(&mut bits, &channel);
+        }
+        bits.align();
+        let crc = crc16(&bits.bytes);
+        bits.write(crc as u64, 16);
+
+        self.file.write_all(&bits.bytes)?;
+        self.frames += block_size as u64;
+        self.frame_number += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<PathBuf> {
+        let samples = std::mem::take(&mut self.pending);
+        self.write_frame(&samples)?;
+        self.file.seek(SeekFrom::Start(FLAC_STREAMINFO_OFFSET))?;
+        let streaminfo = self.streaminfo();
+        self.file.write_all(&streaminfo)?;
+        self.file.flush()?;
+        Ok(self.path)
+    }
+}
+
+// Whichever fixed predictor leaves the smallest residual, or the samples
+// as they are when none of them help
+fn write_subframe(bits: &mut BitWriter, samples: &[i64]) {
+    let verbatim_bits = samples.len() as u64 * BITS_PER_SAMPLE as u64;
+    let best = (0..=FLAC_MAX_FIXED_ORDER.min(samples.len().saturating_sub(1)))
+        .map(|order| {
+            let residual = fixed_residual(samples, order);
+            let (parameter, cost) = rice_parameter(&residual);
+            let cost = cost + (order as u64 * BITS_PER_SAMPLE as u64) + 10;
+            (order, residual, parameter, cost)
+        })
+        .min_by_key(|(_, _, _, cost)| *cost)
+        .filter(|(_, _, _, cost)| *cost < verbatim_bits);
+
+    // Padding bit, then the type, then no wasted bits
+    bits.write(0, 1);
+    let Some((order, residual, parameter, _)) = best else {
+        bits.write(0b000001, 6);
+        bits.write(0, 1);
+        for sample in samples {
+            bits.write_signed(*sample, BITS_PER_SAMPLE);
+        }
+        return;
+    };
+    bits.write(0b001000 | order as u64, 6);
+    bits.write(0, 1);
+    for sample in &samples[..order] {
+        bits.write_signed(*sample, BITS_PER_SAMPLE);
+    }
+    // Rice coding with four bit parameters, in a single partition
+    bits.write(0b00, 2);
+    bits.write(0, 4);
+    bits.write(parameter as u64, 4);
+    for value in residual {
+        let value = zigzag(value);
+        bits.write_unary(value >> parameter);
+        bits.write(value, parameter);
+    }
+}
+
+fn fixed_residual(samples: &[i64], order: usize) -> Vec<i64> {
+    (order..samples.len())
+        .map(|i| {
+            let x = |back: usize| samples[i - back];
+            match order {
+                0 => x(0),
+                1 => x(0) - x(1),
+                2 => x(0) - 2 * x(1) + x(2),
+                3 => x(0) - 3 * x(1) + 3 * x(2) - x(3),
+                _ => x(0) - 4 * x(1) + 6 * x(2) - 4 * x(3) + x(4),
+            }
+        })
+        .collect()
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+// The parameter that codes the residual in the fewest bits, with that count
+fn rice_parameter(residual: &[i64]) -> (u32, u64) {
+    let values: Vec<u64> = residual.iter().map(|value| zigzag(*value)).collect();
+    (0..=FLAC_MAX_RICE_PARAMETER)
+        .map(|parameter| {
+            let cost = values
+                .iter()
+                .map(|value| (value >> parameter) + 1 + parameter as u64)
+                .sum();
+            (parameter, cost)
+        })
+        .min_by_key(|(_, cost)| *cost)
+        .unwrap_or((0, 0))
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    // Bits not yet making up a whole byte, in the low end
+    pending: u64,
+    pending_bits: u32,
+}
+
+impl BitWriter {
+    // Up to 32 bits at a time, most significant first
+    fn write(&mut self, value: u64, bits: u32) {
+        let mask = (1u64 << bits) - 1;
+        self.pending = (self.pending << bits) | (value & mask);
+        self.pending_bits += bits;
+        while self.pending_bits >= 8 {
+            self.pending_bits -= 8;
+            self.bytes.push((self.pending >> self.pending_bits) as u8);
+        }
+        self.pending &= (1u64 << self.pending_bits) - 1;
+    }
+
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64, bits);
+    }
+
+    fn write_unary(&mut self, mut zeros: u64) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros as u32 + 1);
+    }
+
+    // Frame numbers are coded the way UTF-8 codes characters
+    fn write_utf8(&mut self, value: u32) {
+        if value < 0x80 {
+            self.write(value as u64, 8);
+            return;
+        }
+        let len = match value {
+            0..=0x7FF => 2,
+            0x800..=0xFFFF => 3,
+            0x1_0000..=0x1F_FFFF => 4,
+            0x20_0000..=0x3FF_FFFF => 5,
+            _ => 6,
+        };
+        let prefix = (0xFF00u32 >> len) & 0xFF;
+        self.write((prefix | (value >> (6 * (len - 1)))) as u64, 8);
+        for ix in (0..len - 1).rev() {
+            self.write((0x80 | ((value >> (6 * ix)) & 0x3F)) as u64, 8);
+        }
+    }
+
+    fn align(&mut self) {
+        if self.pending_bits > 0 {
+            self.write(0, 8 - self.pending_bits);
+        }
+    }
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for byte in bytes {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
@@ -0,0 +1,1303 @@
+use anyhow::{anyhow, Context as _};
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample,
+};
+use std::{
+    f32::consts::FRAC_PI_2,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use symphonia::core::{
+    audio::{SampleBuffer, SignalSpec},
+    codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::{MediaSource, MediaSourceStream},
+    meta::MetadataOptions,
+    probe::Hint,
+    units::Time,
+};
+
+use crate::{
+    diagnostics, BufferSize, DecodeAllocation, DspChain, Dynamics, DynamicsSettings, Heartbeat,
+    OutputFormat, OutputSettings, PlaybackClock, RadioHandle, RadioStream, SampleRing, StreamCache,
+    StreamCacheSettings, StreamStatus, TrackReader, Watchdog, WatchdogEvent,
+};
+
+// How far ahead of the device decoding runs. Enough to ride out a slow
+// disk or a busy machine, short enough that seeking still feels immediate.
+const READ_AHEAD: Duration = Duration::from_millis(500);
+// How long the engine thread waits for a command when there's nothing to
+// decode
+const IDLE_POLL: Duration = Duration::from_millis(5);
+// How often to look for the system's default output having changed, such
+// as headphones being plugged in
+const DEVICE_POLL: Duration = Duration::from_secs(2);
+// No track lined up to hand over to
+const NO_HANDOVER: usize = usize::MAX;
+// How long the watchdog waits for the engine thread to rebuild a stalled
+// stream before counting it as failed
+const RECOVER_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub enum EngineEvent {
+    // The file opened, with its length when the container gives one
+    Loaded { duration: Option<Duration> },
+    // Everything decoded from the current file has been played
+    Finished,
+    // The device has got to the file lined up with `set_next`, which is
+    // now the one playing, with its length when the container gives one
+    Advanced(PathBuf, Option<Duration>),
+    // How a radio stream's connection is getting on, whenever that changes
+    StreamStatus(StreamStatus),
+    // The song a radio station says is on
+    StreamTitle(String),
+    // The output was opened on a device other than the one before, by name
+    OutputDevice(String),
+    // The output keeps stalling and being rebuilt, this many times in the
+    // last minute
+    RecoveringRepeatedly(usize),
+    Error(String),
+}
+
+type EffectsBuilder = Box<dyn Fn(u32, u16) -> DspChain + Send>;
+
+enum Command {
+    Load {
+        path: PathBuf,
+        position: Duration,
+        play: bool,
+    },
+    LoadStream {
+        url: String,
+        record_to: Option<PathBuf>,
+        play: bool,
+    },
+    Play,
+    Pause,
+    Seek(Duration),
+    Stop,
+    SetNext {
+        path: Option<PathBuf>,
+        crossfade: Option<Duration>,
+    },
+    SetEffects(EffectsBuilder),
+    SetDynamics(DynamicsSettings),
+    SetOutput(OutputSettings),
+    SetStreamCache(StreamCacheSettings),
+    // From the watchdog, which waits on the reply
+    Recover(Sender<anyhow::Result<()>>),
+}
+
+// Shared between the engine thread, the output callback and the UI. All of
+// it is atomics, the callback never waits on anything.
+struct Shared {
+    playing: AtomicBool,
+    // A track is being decoded or a stream buffered, so the device running
+    // short is an underrun rather than the end of the track
+    decoding: AtomicBool,
+    // The output gain as `f32` bits, applied by the device callback so a
+    // change is heard straight away rather than after the read-ahead
+    gain: AtomicU32,
+    // From -1 (left only) to 1 (right only), also as `f32` bits and also
+    // applied by the callback
+    balance: AtomicU32,
+    clock: PlaybackClock,
+    // Where in the ring the next track starts, as a `SampleRing::written`
+    // position, or `NO_HANDOVER`. The callback starts the clock over when
+    // it gets there, and sets `handed_over` for the engine thread to report.
+    handover: AtomicUsize,
+    handed_over: AtomicBool,
+    // Beaten by the callback, so the watchdog can tell when it stops
+    heartbeat: Arc<Heartbeat>,
+}
+
+/// Decodes tracks and plays them through an output device, the system
+/// default unless another is named. All of the work happens on a thread of
+/// its own, the methods here only send it commands, so none of them block.
+pub struct PlaybackEngine {
+    commands: Sender<Command>,
+    events: Receiver<EngineEvent>,
+    shared: Arc<Shared>,
+    // Rebuilds the output when the callback stops being called, such as
+    // after a driver hiccup
+    watchdog: Watchdog,
+}
+
+impl PlaybackEngine {
+    pub fn spawn(output: OutputSettings, cache: StreamCache) -> Self {
+        Self::spawn_on(false, output, cache)
+    }
+
+    /// An engine for pre-listening, which plays through
+    /// `output.audition_device` when that's plugged in.
+    pub fn spawn_audition(output: OutputSettings, cache: StreamCache) -> Self {
+        Self::spawn_on(true, output, cache)
+    }
+
+    fn spawn_on(audition: bool, output: OutputSettings, cache: StreamCache) -> Self {
+        let (commands, command_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::channel();
+        let recover_commands: Sender<Command> = commands.clone();
+        let watchdog = Watchdog::spawn(move || {
+            let (reply, result) = mpsc::channel();
+            recover_commands
+                .send(Command::Recover(reply))
+                .map_err(|_| anyhow!("The playback engine has stopped"))?;
+            result
+                .recv_timeout(RECOVER_TIMEOUT)
+                .map_err(|_| anyhow!("The playback engine didn't respond"))?
+        });
+        let shared = Arc::new(Shared {
+            playing: AtomicBool::new(false),
+            decoding: AtomicBool::new(false),
+            gain: AtomicU32::new(1f32.to_bits()),
+            balance: AtomicU32::new(0f32.to_bits()),
+            clock: PlaybackClock::new(44_100),
+            handover: AtomicUsize::new(NO_HANDOVER),
+            handed_over: AtomicBool::new(false),
+            heartbeat: watchdog.heartbeat(),
+        });
+
+        // Built on the thread itself, output streams can't be sent between
+        // threads on every platform
+        let worker_shared = shared.clone();
+        thread::Builder::new()
+            .name("playback-engine".into())
+            .spawn(move || {
+                let worker = Worker {
+                    shared: worker_shared,
+                    events: event_tx,
+                    output,
+                    cache,
+                    stream: None,
+                    ring: Arc::new(SampleRing::new(0)),
+                    pending: Vec::new(),
+                    stream_failed: Arc::new(AtomicBool::new(false)),
+                    audition,
+                    device: None,
+                    device_checked: Instant::now(),
+                    decoder: None,
+                    path: None,
+                    radio: None,
+                    pending_radio: None,
+                    draining: false,
+                    next: None,
+                    fade: None,
+                    decoded_frames: 0,
+                    handing_over_to: None,
+                    resampler: None,
+                    effects: None,
+                    chain: DspChain::default(),
+                    dynamics: Dynamics::default(),
+                    mapped: Vec::new(),
+                    resampled: Vec::new(),
+                };
+                worker.run(command_rx)
+            })
+            .map_err(|e| eprintln!("Failed to start playback engine: {}", e))
+            .ok();
+
+        PlaybackEngine {
+            commands,
+            events,
+            shared,
+            watchdog,
+        }
+    }
+
+    fn send(&self, command: Command) {
+        // Only fails once the engine thread has gone, which it has already
+        // reported
+        self.commands.send(command).ok();
+    }
+
+    // A paused output is silent on purpose, so it's only watched while
+    // playing
+    fn watch(&self) {
+        let playing = self.is_playing();
+        if self.watchdog.is_watching() != playing {
+            self.watchdog.set_watching(playing);
+        }
+    }
+
+    /// Starts decoding `path` from `position`, replacing whatever was loaded.
+    pub fn load(&self, path: &Path, position: Duration, play: bool) {
+        // Set here as well so the position reads right before the engine
+        // thread gets to the file
+        self.shared.clock.seek(position);
+        self.shared.playing.store(play, Ordering::Relaxed);
+        self.watch();
+        self.send(Command::Load {
+            path: path.to_path_buf(),
+            position,
+            play,
+        });
+    }
+
+    /// Starts playing an internet radio stream, replacing whatever was
+    /// loaded. It's reconnected to whenever it drops, and recorded into
+    /// `record_to` when that's given.
+    pub fn load_stream(&self, url: &str, record_to: Option<PathBuf>, play: bool) {
+        self.shared.clock.seek(Duration::ZERO);
+        self.shared.playing.store(play, Ordering::Relaxed);
+        self.watch();
+        self.send(Command::LoadStream {
+            url: url.to_string(),
+            record_to,
+            play,
+        });
+    }
+
+    pub fn play(&self) {
+        self.shared.playing.store(true, Ordering::Relaxed);
+        self.watch();
+        self.send(Command::Play);
+    }
+
+    pub fn pause(&self) {
+        self.shared.playing.store(false, Ordering::Relaxed);
+        self.watch();
+        self.send(Command::Pause);
+    }
+
+    pub fn seek(&self, position: Duration) {
+        self.shared.clock.seek(position);
+        self.send(Command::Seek(position));
+    }
+
+    pub fn stop(&self) {
+        self.shared.playing.store(false, Ordering::Relaxed);
+        self.watch();
+        self.shared.clock.seek(Duration::ZERO);
+        self.send(Command::Stop);
+    }
+
+    /// Lines up the track to go on to when this one ends, so it starts
+    /// without a gap, or fades in over the end of this one when `crossfade`
+    /// is given. `Advanced` is sent once it's heard. With nothing lined up
+    /// the engine stops at the end of the track and sends `Finished`.
+    pub fn set_next(&self, path: Option<&Path>, crossfade: Option<Duration>) {
+        self.send(Command::SetNext {
+            path: path.map(Path::to_path_buf),
+            crossfade,
+        });
+    }
+
+    /// Sets how the effects chain is built. It's rebuilt with the output's
+    /// format whenever that changes.
+    pub fn set_effects(&self, build: impl Fn(u32, u16) -> DspChain + Send + 'static) {
+        self.send(Command::SetEffects(Box::new(build)));
+    }
+
+    /// Sets up the limiter and night mode, which run after the effects.
+    pub fn set_dynamics(&self, settings: DynamicsSettings) {
+        self.send(Command::SetDynamics(settings));
+    }
+
+    /// Reopens the output with new settings, such as another buffer size,
+    /// carrying on from where it was.
+    pub fn set_output(&self, output: OutputSettings) {
+        self.send(Command::SetOutput(output));
+    }
+
+    /// Changes how tracks on network drives are cached, evicting whatever
+    /// no longer fits. Tracks already open carry on as they were.
+    pub fn set_stream_cache(&self, settings: StreamCacheSettings) {
+        self.send(Command::SetStreamCache(settings));
+    }
+
+    /// Sets the output level from 0 to 1. It's squared into a gain, so equal
+    /// steps sound about equally far apart.
+    pub fn set_volume(&self, volume: f32) {
+        let volume = volume.clamp(0., 1.);
+        self.shared
+            .gain
+            .store((volume * volume).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Sets the stereo balance from -1, left only, to 1, right only. The
+    /// side it's moved towards stays at full level.
+    pub fn set_balance(&self, balance: f32) {
+        self.shared
+            .balance
+            .store(balance.clamp(-1., 1.).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.shared.playing.load(Ordering::Relaxed)
+    }
+
+    /// Shared with the callback, for how often the device has gone short.
+    pub fn heartbeat(&self) -> Arc<Heartbeat> {
+        self.shared.heartbeat.clone()
+    }
+
+    /// How far into the track the device has actually played.
+    pub fn position(&self) -> Duration {
+        self.shared.clock.position()
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = EngineEvent> + '_ {
+        // Playback can also stop on the engine thread, after an error
+        self.watch();
+        let recoveries = self.watchdog.events().filter_map(|event| match event {
+            WatchdogEvent::RecoveringRepeatedly { recoveries } => {
+                Some(EngineEvent::RecoveringRepeatedly(recoveries))
+            }
+            _ => None,
+        });
+        self.events.try_iter().chain(recoveries)
+    }
+}
+
+struct Worker {
+    shared: Arc<Shared>,
+    events: Sender<EngineEvent>,
+    output: OutputSettings,
+    cache: StreamCache,
+    // Opened with the first track and kept for the ones after it
+    stream: Option<(cpal::Stream, OutputFormat)>,
+    // Interleaved at the output's rate and channel count, read by the
+    // stream's callback. Made afresh with each stream.
+    ring: Arc<SampleRing>,
+    // Decoded but not yet fitted into the ring
+    pending: Vec<f32>,
+    stream_failed: Arc<AtomicBool>,
+    // Plays through the audition device whenever it's plugged in, rather
+    // than following the default as it changes. The main engine's output
+    // is the one diagnostics shows.
+    audition: bool,
+    // The device the output was last opened on, and when the default was
+    // last compared with it
+    device: Option<String>,
+    device_checked: Instant,
+    decoder: Option<TrackDecoder>,
+    path: Option<PathBuf>,
+    // The radio stream playing, with the status and title last reported
+    radio: Option<(RadioHandle, StreamStatus, Option<String>)>,
+    // Waits here until enough has buffered for the decoder to probe it
+    // without blocking
+    pending_radio: Option<RadioStream>,
+    // The file has been decoded to the end and is waiting to finish playing
+    draining: bool,
+    // The track to go on to, and how long to crossfade into it for
+    next: Option<(PathBuf, Option<Duration>)>,
+    // Under way over the end of the current track
+    fade: Option<Fade>,
+    // Output frames decoded from the current track, counted from its start
+    decoded_frames: u64,
+    // The track the ring hands over to, reported once the device gets there
+    handing_over_to: Option<(PathBuf, Option<Duration>)>,
+    resampler: Option<Resampler>,
+    effects: Option<EffectsBuilder>,
+    chain: DspChain,
+    dynamics: Dynamics,
+    // Reused between packets
+    mapped: Vec<f32>,
+    resampled: Vec<f32>,
+}
+
+impl Worker {
+    fn run(mut self, commands: Receiver<Command>) {
+        loop {
+            let timeout = if self.wants_samples() {
+                Duration::ZERO
+            } else {
+                IDLE_POLL
+            };
+            match commands.recv_timeout(timeout) {
+                Ok(command) => {
+                    if let Err(e) = self.handle(command) {
+                        self.fail(e);
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if let Err(e) = self.poll_radio() {
+                self.fail(e);
+            }
+            if let Err(e) = self.follow_device() {
+                self.fail(e);
+            }
+            if let Err(e) = self.fill() {
+                self.fail(e);
+            }
+            if self.draining && self.buffered() == 0 {
+                self.draining = false;
+                self.events.send(EngineEvent::Finished).ok();
+            }
+            if self.shared.handed_over.swap(false, Ordering::Acquire) {
+                if let Some((path, duration)) = self.handing_over_to.take() {
+                    self.events.send(EngineEvent::Advanced(path, duration)).ok();
+                }
+            }
+            let decoding = self.decoder.is_some() || self.pending_radio.is_some();
+            self.shared.decoding.store(decoding, Ordering::Relaxed);
+        }
+        if !self.audition {
+            diagnostics::set_output_format(None);
+        }
+    }
+
+    fn fail(&mut self, error: anyhow::Error) {
+        self.decoder = None;
+        self.draining = false;
+        self.shared.playing.store(false, Ordering::Relaxed);
+        self.events
+            .send(EngineEvent::Error(format!("{:#}", error)))
+            .ok();
+    }
+
+    fn handle(&mut self, command: Command) -> anyhow::Result<()> {
+        match command {
+            Command::Load {
+                path,
+                position,
+                play,
+            } => {
+                self.close_radio();
+                self.path = Some(path);
+                self.open(position)?;
+                self.next = None;
+                let duration = self.decoder.as_ref().and_then(|decoder| decoder.duration);
+                self.events.send(EngineEvent::Loaded { duration }).ok();
+                if play {
+                    self.play()?;
+                }
+            }
+            Command::LoadStream {
+                url,
+                record_to,
+                play,
+            } => {
+                self.close_radio();
+                self.decoder = None;
+                self.path = None;
+                self.ensure_stream()?;
+                self.clear(Duration::ZERO);
+                self.next = None;
+                let (stream, handle) = RadioStream::open(&url, record_to)
+                    .with_context(|| format!("Couldn't open {}", url))?;
+                self.radio = Some((handle, StreamStatus::Connecting, None));
+                self.pending_radio = Some(stream);
+                self.events
+                    .send(EngineEvent::StreamStatus(StreamStatus::Connecting))
+                    .ok();
+                self.events
+                    .send(EngineEvent::Loaded { duration: None })
+                    .ok();
+                if play {
+                    self.play()?;
+                }
+            }
+            Command::Play => self.play()?,
+            Command::Pause => {
+                if let Some((stream, _)) = &self.stream {
+                    stream.pause().ok();
+                }
+            }
+            // There's nothing to seek to in a live stream
+            Command::Seek(_) if self.radio.is_some() => {}
+            Command::Seek(position) => {
+                // Once the device is into the track being faded to, that's
+                // the one being seeked in
+                if self.fade.is_some()
+                    && self.shared.handover.load(Ordering::Acquire) == NO_HANDOVER
+                {
+                    if let Some(fade) = self.fade.take() {
+                        self.decoder = Some(fade.decoder);
+                        self.path = Some(fade.lined_up.0);
+                    }
+                }
+                if let Some(decoder) = &mut self.decoder {
+                    decoder.seek(position)?;
+                    self.clear(position);
+                } else if self.path.is_some() {
+                    // Seeking back into a track that finished decoding
+                    self.open(position)?;
+                }
+            }
+            Command::Stop => {
+                self.close_radio();
+                self.decoder = None;
+                self.path = None;
+                self.clear(Duration::ZERO);
+                self.next = None;
+                if let Some((stream, _)) = &self.stream {
+                    stream.pause().ok();
+                }
+            }
+            Command::SetNext { path, crossfade } => {
+                self.next = path.map(|path| (path, crossfade));
+            }
+            Command::SetEffects(build) => {
+                if let Some((_, format)) = &self.stream {
+                    self.chain = build(format.sample_rate, format.channels);
+                }
+                self.effects = Some(build);
+            }
+            Command::SetDynamics(settings) => {
+                self.dynamics = Dynamics::new(settings);
+                if let Some((_, format)) = &self.stream {
+                    self.dynamics.prepare(format.sample_rate);
+                }
+            }
+            Command::SetOutput(output) => {
+                if output != self.output {
+                    self.output = output;
+                    if self.stream.is_some() {
+                        self.rebuild_stream()?;
+                    }
+                }
+            }
+            Command::SetStreamCache(settings) => self.cache.set_settings(settings),
+            // Reported back to the watchdog rather than as a playback error
+            Command::Recover(reply) => {
+                reply.send(self.rebuild_stream()).ok();
+            }
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, position: Duration) -> anyhow::Result<()> {
+        self.decoder = None;
+        self.ensure_stream()?;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut decoder = self.open_decoder(path)?;
+        if !position.is_zero() {
+            decoder.seek(position)?;
+        }
+        self.decoder = Some(decoder);
+        self.clear(position);
+        Ok(())
+    }
+
+    fn open_decoder(&self, path: &Path) -> anyhow::Result<TrackDecoder> {
+        let reader = self
+            .cache
+            .open(path)
+            .with_context(|| format!("Couldn't open {}", path.display()))?;
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        TrackDecoder::open(Box::new(reader), extension, &path.display().to_string())
+    }
+
+    // Dropping the stream hangs up
+    fn close_radio(&mut self) {
+        self.radio = None;
+        self.pending_radio = None;
+    }
+
+    // Reports changes to the stream's status and title, and opens the
+    // decoder once there's enough to probe
+    fn poll_radio(&mut self) -> anyhow::Result<()> {
+        let Some((handle, status, title)) = &mut self.radio else {
+            return Ok(());
+        };
+        let current = handle.status();
+        if current != *status {
+            *status = current.clone();
+            self.events.send(EngineEvent::StreamStatus(current)).ok();
+        }
+        let current = handle.title();
+        if current != *title {
+            title.clone_from(&current);
+            if let Some(current) = current {
+                self.events.send(EngineEvent::StreamTitle(current)).ok();
+            }
+        }
+
+        if self.pending_radio.is_some() && handle.is_ready() {
+            let extension = handle.extension();
+            if let Some(stream) = self.pending_radio.take() {
+                self.decoder = Some(TrackDecoder::open(
+                    Box::new(stream),
+                    extension,
+                    "the radio stream",
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    // Moves over to the new default output, or to the named one once it's
+    // plugged back in
+    fn follow_device(&mut self) -> anyhow::Result<()> {
+        if self.device_checked.elapsed() < DEVICE_POLL {
+            return Ok(());
+        }
+        self.device_checked = Instant::now();
+        let Some((_, format)) = &self.stream else {
+            return Ok(());
+        };
+        let wanted = self.wanted_device().and_then(|device| device.name().ok());
+        if wanted.is_none_or(|name| name == format.device_name) {
+            return Ok(());
+        }
+        self.rebuild_stream()
+    }
+
+    // Opens the output afresh, picking up where the old one left off. What
+    // was buffered for the old one may not suit the new one.
+    fn rebuild_stream(&mut self) -> anyhow::Result<()> {
+        let position = self.shared.clock.position();
+        self.stream = None;
+        self.ensure_stream()?;
+        if self.radio.is_none() {
+            if let Some(decoder) = &mut self.decoder {
+                decoder.seek(position)?;
+            } else if self.path.is_some() {
+                self.open(position)?;
+            }
+        }
+        self.clear(position);
+        if self.shared.playing.load(Ordering::Relaxed) {
+            self.play()?;
+        }
+        Ok(())
+    }
+
+    fn play(&mut self) -> anyhow::Result<()> {
+        self.ensure_stream()?;
+        if let Some((stream, _)) = &self.stream {
+            stream.play().context("Couldn't start the output")?;
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, position: Duration) {
+        // Before the ring, so the callback can't take the skipped samples
+        // for the handover
+        self.shared.handover.store(NO_HANDOVER, Ordering::Release);
+        self.shared.handed_over.store(false, Ordering::Relaxed);
+        self.handing_over_to = None;
+        self.ring.clear();
+        self.pending.clear();
+        self.resampler = None;
+        self.draining = false;
+        // Started again once the end comes round
+        if let Some(fade) = self.fade.take() {
+            self.next = Some(fade.lined_up);
+        }
+        self.shared.clock.seek(position);
+        let sample_rate = self
+            .stream
+            .as_ref()
+            .map_or(0, |(_, format)| format.sample_rate);
+        self.decoded_frames = (position.as_secs_f64() * sample_rate as f64) as u64;
+    }
+
+    fn buffered(&self) -> usize {
+        self.ring.len() + self.pending.len()
+    }
+
+    fn wants_samples(&self) -> bool {
+        let Some((_, format)) = &self.stream else {
+            return false;
+        };
+        let wanted = read_ahead_samples(format.sample_rate, format.channels);
+        self.decoder.is_some() && self.radio_ready() && self.buffered() < wanted
+    }
+
+    // A stream that's run low is left to buffer rather than decoded from,
+    // which would block until the network caught up
+    fn radio_ready(&self) -> bool {
+        self.radio
+            .as_ref()
+            .is_none_or(|(handle, _, _)| handle.is_ready())
+    }
+
+    // The audition device when it's wanted and plugged in, otherwise the
+    // default
+    fn wanted_device(&self) -> Option<cpal::Device> {
+        let host = cpal::default_host();
+        self.output
+            .audition_device
+            .as_ref()
+            .filter(|_| self.audition)
+            .and_then(|name| {
+                host.output_devices()
+                    .ok()?
+                    .find(|device| device.name().is_ok_and(|device| device == *name))
+            })
+            .or_else(|| host.default_output_device())
+    }
+
+    // Opens the wanted device, or reopens it after an error such as it
+    // being unplugged
+    fn ensure_stream(&mut self) -> anyhow::Result<()> {
+        if self.stream.is_some() && !self.stream_failed.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.stream = None;
+
+        let device = self
+            .wanted_device()
+            .ok_or_else(|| anyhow!("No audio output device"))?;
+        let supported = device
+            .default_output_config()
+            .context("Couldn't read the output format")?;
+        let sample_format = supported.sample_format();
+        let mut config = supported.config();
+        if let BufferSize::Frames(frames) = self.output.buffer_size {
+            config.buffer_size = cpal::BufferSize::Fixed(frames);
+        }
+        // Twice the read-ahead, so a packet never has to wait for room
+        let read_ahead = read_ahead_samples(config.sample_rate.0, config.channels);
+        self.ring = Arc::new(SampleRing::new(read_ahead * 2));
+        self.pending.clear();
+
+        let stream = match sample_format {
+            SampleFormat::F32 => self.build_stream::<f32>(&device, &config),
+            SampleFormat::I16 => self.build_stream::<i16>(&device, &config),
+            SampleFormat::U16 => self.build_stream::<u16>(&device, &config),
+            SampleFormat::I32 => self.build_stream::<i32>(&device, &config),
+            other => return Err(anyhow!("Unsupported output format {}", other)),
+        }?;
+
+        let format = OutputFormat {
+            device_name: device.name().unwrap_or_else(|_| "Unknown".to_string()),
+            sample_rate: config.sample_rate.0,
+            channels: config.channels,
+            sample_format: sample_format.to_string(),
+            buffer_frames: match config.buffer_size {
+                cpal::BufferSize::Fixed(frames) => Some(frames),
+                cpal::BufferSize::Default => None,
+            },
+        };
+        if !self.audition {
+            diagnostics::set_output_format(Some(format.clone()));
+        }
+        if self.device.as_ref() != Some(&format.device_name) {
+            self.device = Some(format.device_name.clone());
+            self.events
+                .send(EngineEvent::OutputDevice(format.device_name.clone()))
+                .ok();
+        }
+        self.shared.clock.set_sample_rate(format.sample_rate);
+        if let Some(build) = &self.effects {
+            self.chain = build(format.sample_rate, format.channels);
+        }
+        self.dynamics.prepare(format.sample_rate);
+        self.resampler = None;
+        self.stream = Some((stream, format));
+        Ok(())
+    }
+
+    fn build_stream<T: SizedSample + FromSample<f32>>(
+        &self,
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+    ) -> anyhow::Result<cpal::Stream> {
+        let shared = self.shared.clone();
+        let ring = self.ring.clone();
+        let channels = config.channels as usize;
+        let failed = self.stream_failed.clone();
+        let events = self.events.clone();
+        let stream = device
+            .build_output_stream(
+                config,
+                move |data: &mut [T], _| write_output(data, &shared, &ring, channels),
+                move |e| {
+                    failed.store(true, Ordering::Relaxed);
+                    events
+                        .send(EngineEvent::Error(format!("Audio output failed: {}", e)))
+                        .ok();
+                },
+                None,
+            )
+            .context("Couldn't open the output")?;
+        Ok(stream)
+    }
+
+    // Decodes one packet and queues it for the device, converted to the
+    // output's format and run through the effects
+    fn fill(&mut self) -> anyhow::Result<()> {
+        // What didn't fit last time goes in before anything new is decoded
+        if !self.pending.is_empty() {
+            let pushed = self.ring.push(&self.pending);
+            self.pending.drain(..pushed);
+            return Ok(());
+        }
+        if !self.wants_samples() {
+            return Ok(());
+        }
+        let Some((_, format)) = &self.stream else {
+            return Ok(());
+        };
+        let (sample_rate, channels) = (format.sample_rate, format.channels);
+        if !self.radio_ready() {
+            return Ok(());
+        }
+        let Some(decoder) = &mut self.decoder else {
+            return Ok(());
+        };
+
+        self.resampled.clear();
+        let decoded = decode_packet(
+            decoder,
+            &mut self.resampler,
+            sample_rate,
+            channels as usize,
+            &mut self.mapped,
+            &mut self.resampled,
+        )?;
+        self.decoded_frames += (self.resampled.len() / channels as usize) as u64;
+        if let Some(frames) = self.crossfade_due(sample_rate) {
+            self.start_fade(frames);
+        }
+        if let Some(fade) = &mut self.fade {
+            fade.mix(
+                &mut self.resampled,
+                sample_rate,
+                channels as usize,
+                &mut self.mapped,
+            )?;
+        }
+        if !decoded {
+            self.hand_over();
+        }
+
+        if !self.chain.is_empty() {
+            self.chain.process(&mut self.resampled, channels);
+        }
+        self.dynamics.process(&mut self.resampled, channels);
+        let pushed = self.ring.push(&self.resampled);
+        self.pending.extend_from_slice(&self.resampled[pushed..]);
+        Ok(())
+    }
+
+    // The frames left of the current track, once it's near enough the end
+    // to start fading into the next one
+    fn crossfade_due(&self, sample_rate: u32) -> Option<u64> {
+        if self.fade.is_some() || self.radio.is_some() {
+            return None;
+        }
+        let crossfade = self.next.as_ref()?.1?;
+        let duration = self.decoder.as_ref()?.duration?;
+        let total = (duration.as_secs_f64() * sample_rate as f64) as u64;
+        let fade = (crossfade.as_secs_f64() * sample_rate as f64) as u64;
+        let left = total.saturating_sub(self.decoded_frames);
+        // Never more than half of a short track
+        (left <= fade.min(total / 2)).then_some(left.max(1))
+    }
+
+    // A track that won't open is left for the app to load, and report, once
+    // this one has finished
+    fn start_fade(&mut self, frames: u64) {
+        let Some(lined_up) = self.next.take() else {
+            return;
+        };
+        let decoder = match self.open_decoder(&lined_up.0) {
+            Ok(decoder) => decoder,
+            Err(e) => {
+                eprintln!("Couldn't crossfade into the next track: {:#}", e);
+                return;
+            }
+        };
+        self.mark_handover(&lined_up.0, decoder.duration);
+        self.fade = Some(Fade {
+            decoder,
+            resampler: None,
+            incoming: Vec::new(),
+            frames,
+            done: 0,
+            decoded_frames: 0,
+            ended: false,
+            lined_up,
+        });
+    }
+
+    // At the end of the current track. The next one carries straight on
+    // from it when there is one, rather than the device running dry while
+    // the app loads it.
+    fn hand_over(&mut self) {
+        if let Some(fade) = self.fade.take() {
+            // What's left of the fade goes out after the last of the mix
+            self.resampled.extend_from_slice(&fade.incoming);
+            self.decoder = Some(fade.decoder);
+            self.resampler = fade.resampler;
+            self.path = Some(fade.lined_up.0);
+            self.decoded_frames = fade.decoded_frames;
+            return;
+        }
+
+        self.decoder = None;
+        if let Some((path, _)) = self.next.take().filter(|_| self.radio.is_none()) {
+            match self.open_decoder(&path) {
+                Ok(decoder) => {
+                    self.mark_handover(&path, decoder.duration);
+                    self.decoder = Some(decoder);
+                    self.resampler = None;
+                    self.path = Some(path);
+                    self.decoded_frames = 0;
+                    return;
+                }
+                Err(e) => eprintln!("Couldn't go on to the next track: {:#}", e),
+            }
+        }
+        self.draining = true;
+    }
+
+    // The next track's first samples are the next to go into the ring
+    fn mark_handover(&mut self, path: &Path, duration: Option<Duration>) {
+        self.handing_over_to = Some((path.to_path_buf(), duration));
+        self.shared
+            .handover
+            .store(self.ring.written() + self.pending.len(), Ordering::Release);
+    }
+}
+
+// Decodes the next packet onto the end of `output`, in the output's format
+// but before any effects. False at the end of the track.
+fn decode_packet(
+    decoder: &mut TrackDecoder,
+    resampler: &mut Option<Resampler>,
+    sample_rate: u32,
+    channels: usize,
+    mapped: &mut Vec<f32>,
+    output: &mut Vec<f32>,
+) -> anyhow::Result<bool> {
+    let Some(packet) = decoder.next()? else {
+        return Ok(false);
+    };
+    map_channels(packet.samples, packet.channels, channels, mapped);
+    resampler
+        .get_or_insert_with(|| Resampler::new(packet.sample_rate, sample_rate))
+        .process(mapped, channels, output);
+    Ok(true)
+}
+
+// The next track coming in under the end of the current one
+struct Fade {
+    decoder: TrackDecoder,
+    resampler: Option<Resampler>,
+    // Decoded ahead of being mixed in
+    incoming: Vec<f32>,
+    // How long the fade lasts and how far into it the mix is, in frames
+    frames: u64,
+    done: u64,
+    // All of the next track decoded so far, mixed in or not
+    decoded_frames: u64,
+    ended: bool,
+    // Lined up again if the fade is cut short by a seek
+    lined_up: (PathBuf, Option<Duration>),
+}
+
+impl Fade {
+    // Equal power, so the level holds steady through the middle
+    fn mix(
+        &mut self,
+        output: &mut [f32],
+        sample_rate: u32,
+        channels: usize,
+        mapped: &mut Vec<f32>,
+    ) -> anyhow::Result<()> {
+        while self.incoming.len() < output.len() && !self.ended {
+            let before = self.incoming.len();
+            self.ended = !decode_packet(
+                &mut self.decoder,
+                &mut self.resampler,
+                sample_rate,
+                channels,
+                mapped,
+                &mut self.incoming,
+            )?;
+            self.decoded_frames += ((self.incoming.len() - before) / channels) as u64;
+        }
+
+        for (ix, frame) in output.chunks_exact_mut(channels).enumerate() {
+            let progress = (self.done + ix as u64) as f32 / self.frames.max(1) as f32;
+            let angle = progress.min(1.) * FRAC_PI_2;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let incoming = self
+                    .incoming
+                    .get(ix * channels + channel)
+                    .copied()
+                    .unwrap_or(0.);
+                *sample = *sample * angle.cos() + incoming * angle.sin();
+            }
+        }
+        self.done += (output.len() / channels) as u64;
+        let mixed = output.len().min(self.incoming.len());
+        self.incoming.drain(..mixed);
+        Ok(())
+    }
+}
+
+// Runs on the device's own thread, so it only reads the ring and atomics.
+// Whatever the ring is short of is filled with silence and counted.
+fn write_output<T: SizedSample + FromSample<f32>>(
+    data: &mut [T],
+    shared: &Shared,
+    ring: &SampleRing,
+    channels: usize,
+) {
+    shared.heartbeat.beat();
+    let mut written = 0;
+    if shared.playing.load(Ordering::Relaxed) {
+        let gain = f32::from_bits(shared.gain.load(Ordering::Relaxed));
+        let balance = f32::from_bits(shared.balance.load(Ordering::Relaxed));
+        // Only the first two channels are a stereo pair
+        let gains = [
+            gain * (1. - balance).min(1.),
+            gain * (1. + balance).min(1.),
+            gain,
+        ];
+        written = ring.pop(data.len(), channels, |ix, sample| {
+            let channel = if channels >= 2 {
+                (ix % channels).min(2)
+            } else {
+                2
+            };
+            data[ix] = T::from_sample(sample * gains[channel]);
+        });
+        shared.clock.advance((written / channels) as u64);
+        let handover = shared.handover.load(Ordering::Acquire);
+        if handover != NO_HANDOVER && ring.read_position() >= handover {
+            shared.handover.store(NO_HANDOVER, Ordering::Relaxed);
+            shared.clock.seek(Duration::ZERO);
+            shared
+                .clock
+                .advance(((ring.read_position() - handover) / channels) as u64);
+            shared.handed_over.store(true, Ordering::Release);
+        }
+        let wanted = data.len() / channels * channels;
+        if written < wanted && shared.decoding.load(Ordering::Relaxed) {
+            shared
+                .heartbeat
+                .record_underrun(((wanted - written) / channels) as u64);
+        }
+    }
+    for out in &mut data[written..] {
+        *out = T::from_sample(0f32);
+    }
+}
+
+// How many samples `READ_AHEAD` comes to
+fn read_ahead_samples(sample_rate: u32, channels: u16) -> usize {
+    (READ_AHEAD.as_secs_f64() * sample_rate as f64) as usize * channels as usize
+}
+
+// Mono is copied to every channel and anything past the output's channels
+// is dropped, which covers nearly every file and device pairing
+fn map_channels(input: &[f32], from: usize, to: usize, output: &mut Vec<f32>) {
+    output.clear();
+    if from == 0 {
+        return;
+    }
+    for frame in input.chunks_exact(from) {
+        for channel in 0..to {
+            output.push(match from {
+                1 => frame[0],
+                _ => frame.get(channel).copied().unwrap_or(0.),
+            });
+        }
+    }
+}
+
+// Linear interpolation between frames. Not audiophile grade, but only used
+// when a file's rate doesn't match the device's.
+struct Resampler {
+    // Input frames per output frame
+    step: f64,
+    // Where the next output frame falls, in input frames from the start of
+    // the next packet. Between -1 and 0 it falls after the last frame of
+    // the previous one.
+    position: f64,
+    previous: Vec<f32>,
+}
+
+impl Resampler {
+    fn new(from: u32, to: u32) -> Self {
+        Resampler {
+            step: from as f64 / to.max(1) as f64,
+            position: 0.,
+            previous: Vec::new(),
+        }
+    }
+
+    fn process(&mut self, input: &[f32], channels: usize, output: &mut Vec<f32>) {
+        if self.step == 1. {
+            output.extend_from_slice(input);
+            return;
+        }
+        let frames = input.len() / channels;
+        if frames == 0 {
+            return;
+        }
+
+        loop {
+            let index = self.position.floor() as isize;
+            if index + 1 >= frames as isize {
+                break;
+            }
+            let fraction = (self.position - index as f64) as f32;
+            for channel in 0..channels {
+                let a = if index < 0 {
+                    self.previous.get(channel).copied().unwrap_or(0.)
+                } else {
+                    input[index as usize * channels + channel]
+                };
+                let b = input[(index + 1) as usize * channels + channel];
+                output.push(a + (b - a) * fraction);
+            }
+            self.position += self.step;
+        }
+        self.position -= frames as f64;
+        self.previous.clear();
+        self.previous
+            .extend_from_slice(&input[(frames - 1) * channels..frames * channels]);
+    }
+}
+
+struct DecodedPacket<'a> {
+    samples: &'a [f32],
+    sample_rate: u32,
+    channels: usize,
+}
+
+struct TrackDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    duration: Option<Duration>,
+    buffer: Option<(SampleBuffer<f32>, SignalSpec, u64)>,
+    allocation: DecodeAllocation,
+}
+
+impl TrackDecoder {
+    // `name` is what errors call the source, a file's path or the stream
+    fn open(
+        source: Box<dyn MediaSource>,
+        extension: Option<&str>,
+        name: &str,
+    ) -> anyhow::Result<Self> {
+        let source = MediaSourceStream::new(source, Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = extension {
+            hint.with_extension(extension);
+        }
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                source,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .with_context(|| format!("Couldn't read {}", name))?;
+        let format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| anyhow!("No audio in {}", name))?;
+        let params = &track.codec_params;
+        let duration = params
+            .n_frames
+            .zip(params.sample_rate)
+            .map(|(frames, rate)| Duration::from_secs_f64(frames as f64 / rate as f64));
+        let decoder = symphonia::default::get_codecs()
+            .make(params, &DecoderOptions::default())
+            .with_context(|| format!("Can't decode {}", name))?;
+
+        Ok(TrackDecoder {
+            track_id: track.id,
+            format,
+            decoder,
+            duration,
+            buffer: None,
+            allocation: DecodeAllocation::new(0),
+        })
+    }
+
+    fn seek(&mut self, position: Duration) -> anyhow::Result<()> {
+        self.format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time {
+                    time: Time::new(position.as_secs(), position.subsec_nanos() as f64 / 1e9),
+                    track_id: Some(self.track_id),
+                },
+            )
+            .context("Couldn't seek")?;
+        self.decoder.reset();
+        Ok(())
+    }
+
+    /// The next packet as interleaved samples, or `None` at the end.
+    fn next(&mut self) -> anyhow::Result<Option<DecodedPacket<'_>>> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A damaged packet is skipped rather than ending the track
+                Err(SymphoniaError::DecodeError(e)) => {
+                    eprintln!("Skipping undecodable packet: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let spec = *decoded.spec();
+            let frames = decoded.capacity() as u64;
+            let fits = self
+                .buffer
+                .as_ref()
+                .is_some_and(|(_, buffer_spec, capacity)| {
+                    *buffer_spec == spec && *capacity >= frames
+                });
+            if !fits {
+                self.buffer = None;
+                self.allocation
+                    .resize(frames as usize * spec.channels.count() * std::mem::size_of::<f32>());
+            }
+            let (buffer, _, _) = self
+                .buffer
+                .get_or_insert_with(|| (SampleBuffer::new(frames, spec), spec, frames));
+            buffer.copy_interleaved_ref(decoded);
+            return Ok(Some(DecodedPacket {
+                samples: buffer.samples(),
+                sample_rate: spec.rate,
+                channels: spec.channels.count(),
+            }));
+        }
+    }
+}
+
+impl MediaSource for TrackReader {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        match self {
+            TrackReader::Direct(reader) => reader.get_ref().metadata().ok().map(|m| m.len()),
+            TrackReader::Mapped(reader) => Some(reader.len()),
+            TrackReader::Cached(reader) => Some(reader.len()),
+        }
+    }
+}
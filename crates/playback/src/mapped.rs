@@ -0,0 +1,69 @@
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+// Files past this size are mapped rather than buffered, so a multi-gigabyte
+// FLAC or ALAC rip never has to be resident in full.
+pub const MMAP_THRESHOLD_BYTES: u64 = 128 * 1024 * 1024;
+
+pub struct MappedReader {
+    map: Mmap,
+    position: usize,
+}
+
+impl MappedReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: the mapping is read-only. A file truncated underneath us
+        // would fault, which is the same risk every mmap-based player takes.
+        let map = unsafe { Mmap::map(&file)? };
+
+        // Lets the kernel read ahead aggressively and drop pages once decoded
+        #[cfg(unix)]
+        map.advise(memmap2::Advice::Sequential).ok();
+
+        Ok(MappedReader { map, position: 0 })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.map.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl Read for MappedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.map.get(self.position..).unwrap_or_default();
+        let len = buf.len().min(remaining.len());
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+impl Seek for MappedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len().checked_add_signed(offset),
+            SeekFrom::Current(offset) => (self.position as u64).checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position as usize;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
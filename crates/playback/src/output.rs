@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Offered in preferences, anything else can still be set by hand
+pub const BUFFER_SIZE_PRESETS: &[u32] = &[64, 128, 256, 512, 1024, 2048, 4096];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BufferSize {
+    DeviceDefault,
+    Frames(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputSettings {
+    pub buffer_size: BufferSize,
+    // Where previews go, usually headphones. `None` shares the main output.
+    pub audition_device: Option<String>,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        OutputSettings {
+            buffer_size: BufferSize::DeviceDefault,
+            audition_device: None,
+        }
+    }
+}
+
+/// What the output stream actually ended up with, which can differ from
+/// what was asked for when a device rejects a buffer size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputFormat {
+    pub device_name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: String,
+    pub buffer_frames: Option<u32>,
+}
+
+impl OutputFormat {
+    pub fn latency(&self) -> Option<Duration> {
+        let frames = self.buffer_frames?;
+        if self.sample_rate == 0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            frames as f64 / self.sample_rate as f64,
+        ))
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} kHz, {} ch, {}",
+            self.sample_rate as f32 / 1000.,
+            self.channels,
+            self.sample_format
+        )
+    }
+
+    pub fn describe_latency(&self) -> String {
+        match (self.buffer_frames, self.latency()) {
+            (Some(frames), Some(latency)) => {
+                format!(
+                    "{} frames ({:.1} ms)",
+                    frames,
+                    latency.as_secs_f64() * 1000.
+                )
+            }
+            _ => "Device default".to_string(),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+mod capture;
+mod clock;
+mod crossfade;
+mod diagnostics;
+mod dsp;
+mod dynamics;
+mod encoder;
+mod engine;
+mod mapped;
+mod output;
+mod radio;
+mod ring;
+mod stream_cache;
+mod watchdog;
+
+pub use capture::*;
+pub use clock::*;
+pub use crossfade::*;
+pub use diagnostics::*;
+pub use dsp::*;
+pub use dynamics::*;
+pub use encoder::*;
+pub use engine::*;
+pub use mapped::*;
+pub use output::*;
+pub use radio::*;
+pub use ring::*;
+pub use stream_cache::*;
+pub use watchdog::*;
@@ -0,0 +1,438 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+use symphonia::core::io::MediaSource;
+
+// Enough for a couple of seconds at typical bitrates, so a short stall
+// doesn't interrupt playback
+const START_BUFFERED: usize = 48 * 1024;
+// Decoding stops short of running dry, rather than waiting on the network
+const LOW_BUFFERED: usize = 4 * 1024;
+// The station is left waiting beyond this, there's no use racing ahead of
+// a live broadcast
+const MAX_BUFFERED: usize = 1024 * 1024;
+const READ_CHUNK: usize = 16 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// A connection that went quiet, such as across a sleep, is given up on
+// after this and made again
+const READ_TIMEOUT: Duration = Duration::from_secs(15);
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const MAX_RETRY: Duration = Duration::from_secs(60);
+// How often a wait between retries checks whether the stream was closed
+const CLOSE_POLL: Duration = Duration::from_millis(100);
+const USER_AGENT: &str = concat!("gpuiTunes/", env!("CARGO_PKG_VERSION"));
+
+/// How an internet radio stream is getting on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamStatus {
+    Connecting,
+    // Connected, waiting for enough to play without stuttering
+    Buffering,
+    Playing,
+    // Dropped, waiting longer after each failed attempt before trying again
+    Reconnecting { attempt: u32, retry_in: Duration },
+}
+
+struct StreamState {
+    buffer: VecDeque<u8>,
+    status: StreamStatus,
+    // Enough has come in to decode from. Kept apart from the status, so
+    // what's buffered still plays out while reconnecting.
+    ready: bool,
+    // The song the station says is on, from its ICY metadata
+    title: Option<String>,
+    content_type: Option<String>,
+    closed: bool,
+}
+
+struct Shared {
+    state: Mutex<StreamState>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn is_closed(&self) -> bool {
+        self.state.lock().map(|state| state.closed).unwrap_or(true)
+    }
+
+    fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+        }
+        self.changed.notify_all();
+    }
+
+    fn set_status(&self, status: StreamStatus) {
+        if let Ok(mut state) = self.state.lock() {
+            state.status = status;
+        }
+    }
+
+    // Blocks while the buffer is full, returning false once closed
+    fn push(&self, bytes: &[u8]) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        while state.buffer.len() >= MAX_BUFFERED && !state.closed {
+            state = match self.changed.wait(state) {
+                Ok(state) => state,
+                Err(_) => return false,
+            };
+        }
+        if state.closed {
+            return false;
+        }
+        state.buffer.extend(bytes);
+        if !state.ready && state.buffer.len() >= START_BUFFERED {
+            state.ready = true;
+            state.status = StreamStatus::Playing;
+        }
+        self.changed.notify_all();
+        true
+    }
+}
+
+/// Reads an internet radio stream, reconnecting whenever it drops. The
+/// station's song titles are taken out of the audio as it arrives, and
+/// it can be recorded to disk a file per song. Dropping it hangs up.
+pub struct RadioStream {
+    shared: Arc<Shared>,
+}
+
+/// Watches a [`RadioStream`] from outside the decoder.
+#[derive(Clone)]
+pub struct RadioHandle {
+    shared: Arc<Shared>,
+}
+
+impl RadioStream {
+    /// Starts connecting to `url` in the background. `record_to` is a folder
+    /// to save the stream into.
+    pub fn open(url: &str, record_to: Option<PathBuf>) -> io::Result<(RadioStream, RadioHandle)> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(StreamState {
+                buffer: VecDeque::new(),
+                status: StreamStatus::Connecting,
+                ready: false,
+                title: None,
+                content_type: None,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let receiver = Receiver {
+            url: url.to_string(),
+            shared: shared.clone(),
+            recorder: record_to.map(Recorder::new),
+        };
+        thread::Builder::new()
+            .name("radio-stream".into())
+            .spawn(move || receiver.run())?;
+
+        Ok((
+            RadioStream {
+                shared: shared.clone(),
+            },
+            RadioHandle { shared },
+        ))
+    }
+}
+
+impl RadioHandle {
+    pub fn status(&self) -> StreamStatus {
+        self.shared
+            .state
+            .lock()
+            .map(|state| state.status.clone())
+            .unwrap_or(StreamStatus::Connecting)
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.shared
+            .state
+            .lock()
+            .ok()
+            .and_then(|state| state.title.clone())
+    }
+
+    /// The file extension the stream's format goes by, once connected.
+    pub fn extension(&self) -> Option<&'static str> {
+        let state = self.shared.state.lock().ok()?;
+        Some(extension_for(state.content_type.as_deref()?))
+    }
+
+    /// Whether there's enough buffered to decode without blocking. Below
+    /// that it's back to buffering until the network catches up.
+    pub fn is_ready(&self) -> bool {
+        let Ok(mut state) = self.shared.state.lock() else {
+            return false;
+        };
+        if state.closed {
+            return true;
+        }
+        if state.ready && state.buffer.len() < LOW_BUFFERED {
+            state.ready = false;
+            if state.status == StreamStatus::Playing {
+                state.status = StreamStatus::Buffering;
+            }
+        }
+        state.ready
+    }
+}
+
+impl Drop for RadioStream {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+impl Read for RadioStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self
+            .shared
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("radio stream poisoned"))?;
+        while state.buffer.is_empty() && !state.closed {
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .map_err(|_| io::Error::other("radio stream poisoned"))?;
+        }
+        let len = buf.len().min(state.buffer.len());
+        for (byte, buffered) in buf.iter_mut().zip(state.buffer.drain(..len)) {
+            *byte = buffered;
+        }
+        self.shared.changed.notify_all();
+        Ok(len)
+    }
+}
+
+impl Seek for RadioStream {
+    fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Can't seek in a radio stream",
+        ))
+    }
+}
+
+impl MediaSource for RadioStream {
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        None
+    }
+}
+
+fn extension_for(content_type: &str) -> &'static str {
+    match content_type.split(';').next().unwrap_or_default().trim() {
+        "audio/aac" | "audio/aacp" | "audio/x-aac" => "aac",
+        "audio/ogg" | "application/ogg" => "ogg",
+        "audio/flac" => "flac",
+        _ => "mp3",
+    }
+}
+
+// The wait doubles after each failed attempt, up to a minute
+fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(6))
+        .min(MAX_RETRY)
+}
+
+// "StreamTitle='Artist - Title';StreamUrl='';"
+fn stream_title(metadata: &str) -> Option<String> {
+    let start = metadata.find("StreamTitle='")? + "StreamTitle='".len();
+    let end = metadata[start..]
+        .find("';")
+        .map_or(metadata.len(), |end| start + end);
+    let title = metadata[start..end].trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+struct Receiver {
+    url: String,
+    shared: Arc<Shared>,
+    recorder: Option<Recorder>,
+}
+
+impl Receiver {
+    fn run(mut self) {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .user_agent(USER_AGENT)
+            .build();
+        let mut attempt = 0;
+        while !self.shared.is_closed() {
+            if let Err(e) = self.receive(&agent, &mut attempt) {
+                eprintln!("Radio stream {} dropped: {}", self.url, e);
+            }
+            if self.shared.is_closed() {
+                break;
+            }
+
+            attempt += 1;
+            let retry_in = retry_delay(attempt);
+            self.shared
+                .set_status(StreamStatus::Reconnecting { attempt, retry_in });
+            let mut waited = Duration::ZERO;
+            while waited < retry_in && !self.shared.is_closed() {
+                thread::sleep(CLOSE_POLL);
+                waited += CLOSE_POLL;
+            }
+        }
+    }
+
+    // Returns when the connection ends, cleanly or not
+    fn receive(&mut self, agent: &ureq::Agent, attempt: &mut u32) -> anyhow::Result<()> {
+        let response = agent.get(&self.url).set("Icy-MetaData", "1").call()?;
+        let metaint: Option<usize> = response
+            .header("icy-metaint")
+            .and_then(|metaint| metaint.trim().parse().ok())
+            .filter(|metaint| *metaint > 0);
+        let content_type = response.content_type().to_string();
+        if let Some(recorder) = &mut self.recorder {
+            recorder.extension = extension_for(&content_type);
+        }
+        if let Ok(mut state) = self.shared.state.lock() {
+            state.content_type = Some(content_type);
+            state.status = if state.ready {
+                StreamStatus::Playing
+            } else {
+                StreamStatus::Buffering
+            };
+        }
+
+        let mut reader = response.into_reader();
+        let mut chunk = vec![0; READ_CHUNK];
+        // Audio bytes left before the next metadata block
+        let mut until_metadata = metaint.unwrap_or(usize::MAX);
+        loop {
+            let len = chunk.len().min(until_metadata);
+            let read = reader.read(&mut chunk[..len])?;
+            if read == 0 {
+                return Ok(());
+            }
+            // Only counted as reconnected once audio flows again
+            *attempt = 0;
+            if !self.shared.push(&chunk[..read]) {
+                return Ok(());
+            }
+            if let Some(recorder) = &mut self.recorder {
+                recorder.write(&chunk[..read]);
+            }
+
+            until_metadata -= read;
+            if let (0, Some(metaint)) = (until_metadata, metaint) {
+                self.read_metadata(&mut reader)?;
+                until_metadata = metaint;
+            }
+        }
+    }
+
+    // A length byte in sixteens, then that much text. Most blocks are
+    // empty, sent between title changes.
+    fn read_metadata(&mut self, reader: &mut impl Read) -> io::Result<()> {
+        let mut len = [0];
+        reader.read_exact(&mut len)?;
+        let mut metadata = vec![0; len[0] as usize * 16];
+        reader.read_exact(&mut metadata)?;
+        let Some(title) = stream_title(String::from_utf8_lossy(&metadata).trim_end_matches('\0'))
+        else {
+            return Ok(());
+        };
+
+        let Ok(mut state) = self.shared.state.lock() else {
+            return Ok(());
+        };
+        if state.title.as_ref() != Some(&title) {
+            if let Some(recorder) = &mut self.recorder {
+                recorder.start(&title);
+            }
+            state.title = Some(title);
+        }
+        Ok(())
+    }
+}
+
+/// Saves a station into a folder as it plays, starting a new file each
+/// time the song title changes.
+struct Recorder {
+    folder: PathBuf,
+    extension: &'static str,
+    file: Option<File>,
+    // Set when the folder can't be written to, so it isn't tried again
+    // with every chunk
+    failed: bool,
+}
+
+impl Recorder {
+    fn new(folder: PathBuf) -> Self {
+        Recorder {
+            folder,
+            extension: "mp3",
+            file: None,
+            failed: false,
+        }
+    }
+
+    fn start(&mut self, title: &str) {
+        self.file = None;
+        let name: String = title
+            .chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+                c if c.is_control() => ' ',
+                c => c,
+            })
+            .collect();
+        let name = name.trim();
+        let name = if name.is_empty() { "Untitled" } else { name };
+
+        // Songs that come round again are kept as separate takes
+        let mut path = self.folder.join(format!("{}.{}", name, self.extension));
+        let mut take = 2;
+        while path.exists() {
+            path = self
+                .folder
+                .join(format!("{} {}.{}", name, take, self.extension));
+            take += 1;
+        }
+        match fs::create_dir_all(&self.folder).and_then(|_| File::create(&path)) {
+            Ok(file) => self.file = Some(file),
+            Err(e) => {
+                eprintln!("Failed to record to {}: {}", path.display(), e);
+                self.failed = true;
+            }
+        }
+    }
+
+    // Until the station names a song, what's heard goes under "Untitled"
+    fn write(&mut self, bytes: &[u8]) {
+        if self.failed {
+            return;
+        }
+        if self.file.is_none() {
+            self.start("Untitled");
+        }
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.write_all(bytes) {
+                eprintln!("Failed to record: {}", e);
+                self.file = None;
+                self.failed = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// A fixed size queue of samples from one thread to one other, such as the
+/// engine thread to the audio callback. Neither side ever locks, blocks or
+/// allocates, so it's safe to read from on the real-time thread.
+///
+/// Positions count every sample ever pushed or popped, so they only grow,
+/// and a sample's slot is its position modulo the capacity.
+pub struct SampleRing {
+    // As `f32` bits. Atomic so a slot being reused mid-read is never a race.
+    slots: Box<[AtomicU32]>,
+    mask: usize,
+    write: AtomicUsize,
+    read: AtomicUsize,
+    // The reader skips whatever was written before this
+    discard: AtomicUsize,
+}
+
+impl SampleRing {
+    /// Rounded up to a power of two.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(2).next_power_of_two();
+        SampleRing {
+            slots: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            mask: capacity - 1,
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            discard: AtomicUsize::new(0),
+        }
+    }
+
+    // The reader may not have caught up with a clear yet, what's before it
+    // no longer counts either way
+    fn start(&self) -> usize {
+        self.read
+            .load(Ordering::Acquire)
+            .max(self.discard.load(Ordering::Acquire))
+    }
+
+    /// Samples waiting to be read.
+    pub fn len(&self) -> usize {
+        self.write
+            .load(Ordering::Acquire)
+            .saturating_sub(self.start())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writer only. Every sample ever pushed, as a position to compare with
+    /// `read_position`.
+    pub fn written(&self) -> usize {
+        self.write.load(Ordering::Relaxed)
+    }
+
+    /// Reader only. Every sample ever read, or skipped over by a clear.
+    pub fn read_position(&self) -> usize {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    /// Writer only. Queues as many of `samples` as there's room for,
+    /// giving back how many that was.
+    pub fn push(&self, samples: &[f32]) -> usize {
+        let write = self.write.load(Ordering::Relaxed);
+        let free = self.slots.len() - (write - self.start());
+        let count = samples.len().min(free);
+        for (ix, sample) in samples[..count].iter().enumerate() {
+            self.slots[(write + ix) & self.mask].store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.write.store(write + count, Ordering::Release);
+        count
+    }
+
+    /// Writer only. Drops everything pushed so far. A read already under
+    /// way can end up with some of what's pushed next, which is only ever
+    /// the new audio starting a moment early.
+    pub fn clear(&self) {
+        self.discard
+            .store(self.write.load(Ordering::Relaxed), Ordering::Release);
+    }
+
+    /// Reader only. Hands `each` up to `max` samples with their index,
+    /// taking only whole frames of `channels`, and gives back how many.
+    pub fn pop(&self, max: usize, channels: usize, mut each: impl FnMut(usize, f32)) -> usize {
+        let read = self
+            .read
+            .load(Ordering::Relaxed)
+            .max(self.discard.load(Ordering::Acquire));
+        let available = self.write.load(Ordering::Acquire) - read;
+        let count = available.min(max) / channels.max(1) * channels.max(1);
+        for ix in 0..count {
+            let bits = self.slots[(read + ix) & self.mask].load(Ordering::Relaxed);
+            each(ix, f32::from_bits(bits));
+        }
+        self.read.store(read + count, Ordering::Release);
+        count
+    }
+}
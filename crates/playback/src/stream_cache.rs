@@ -0,0 +1,520 @@
+use crate::mapped::{MappedReader, MMAP_THRESHOLD_BYTES};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, Weak,
+    },
+    thread,
+    time::SystemTime,
+};
+
+const FETCH_CHUNK_SIZE: usize = 64 * 1024;
+const PARTIAL_EXTENSION: &str = "partial";
+
+// Fetches under way, by their partial file. Both engines share the cache
+// directory, so the same track auditioned while it plays is fetched once.
+static FETCHES: Mutex<Vec<(PathBuf, Weak<Fetch>)>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamCacheSettings {
+    pub enabled: bool,
+    pub read_ahead_kb: u64,
+    pub max_cache_size_mb: u64,
+    pub cache_whole_track: bool,
+}
+
+impl Default for StreamCacheSettings {
+    fn default() -> Self {
+        StreamCacheSettings {
+            enabled: true,
+            read_ahead_kb: 4096,
+            max_cache_size_mb: 1024,
+            cache_whole_track: false,
+        }
+    }
+}
+
+impl StreamCacheSettings {
+    fn read_ahead_bytes(&self) -> u64 {
+        self.read_ahead_kb * 1024
+    }
+
+    fn max_cache_bytes(&self) -> u64 {
+        self.max_cache_size_mb * 1024 * 1024
+    }
+}
+
+/// Best-effort check for whether a file lives on a network mount,
+/// where reads can stall long enough to starve the decoder.
+pub fn is_remote_path(path: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        const REMOTE_FILESYSTEMS: &[&str] = &[
+            "nfs",
+            "nfs4",
+            "cifs",
+            "smb3",
+            "smbfs",
+            "9p",
+            "afs",
+            "davfs",
+            "fuse.sshfs",
+            "fuse.rclone",
+        ];
+
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let Ok(mounts) = fs::read_to_string("/proc/mounts") else {
+            return false;
+        };
+
+        // The longest mount point containing the path decides its filesystem
+        mounts
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let _device = fields.next()?;
+                let mount_point = fields.next()?;
+                let fs_type = fields.next()?;
+                Some((mount_point, fs_type))
+            })
+            .filter(|(mount_point, _)| path.starts_with(mount_point))
+            .max_by_key(|(mount_point, _)| mount_point.len())
+            .map(|(_, fs_type)| REMOTE_FILESYSTEMS.contains(&fs_type))
+            .unwrap_or(false)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // Network shares are mounted under /Volumes alongside external drives,
+        // treating both as slow is the safe choice.
+        path.starts_with("/Volumes") || path.starts_with("/Network")
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        path.to_string_lossy().starts_with(r"\\")
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+pub struct StreamCache {
+    dir: PathBuf,
+    settings: StreamCacheSettings,
+}
+
+impl StreamCache {
+    pub fn new(dir: PathBuf, settings: StreamCacheSettings) -> Self {
+        StreamCache { dir, settings }
+    }
+
+    pub fn settings(&self) -> &StreamCacheSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: StreamCacheSettings) {
+        self.settings = settings;
+        evict(&self.dir, self.settings.max_cache_bytes()).ok();
+    }
+
+    pub fn should_cache(&self, path: &Path) -> bool {
+        self.settings.enabled && is_remote_path(path)
+    }
+
+    pub fn open(&self, source: &Path) -> io::Result<TrackReader> {
+        let metadata = fs::metadata(source)?;
+        let len = metadata.len();
+
+        if !self.should_cache(source) {
+            return open_uncached(source, len);
+        }
+
+        let cached_path = self.dir.join(cache_key(source, &metadata));
+        let partial_path = cached_path.with_extension(PARTIAL_EXTENSION);
+
+        // Held until the fetch is registered, and by a finishing fetch while
+        // it renames or removes its partial file
+        let mut fetches = FETCHES.lock().unwrap();
+        fetches.retain(|(_, fetch)| fetch.strong_count() > 0);
+        let in_flight = fetches
+            .iter()
+            .find(|(path, _)| *path == partial_path)
+            .and_then(|(_, fetch)| fetch.upgrade());
+        if let Some(fetch) = in_flight {
+            // Still winding down after being called off, the partial file
+            // is about to go away
+            if fetch.cancelled.load(Ordering::SeqCst) {
+                return open_uncached(source, len);
+            }
+            fetch.readers.fetch_add(1, Ordering::SeqCst);
+            return Ok(TrackReader::Cached(CachedReader {
+                file: File::open(&partial_path)?,
+                position: 0,
+                len,
+                read_ahead: self.settings.read_ahead_bytes(),
+                fetch: Some(fetch),
+            }));
+        }
+
+        if fs::metadata(&cached_path)
+            .map(|m| m.len() == len)
+            .unwrap_or(false)
+        {
+            // Touch the entry so eviction treats it as recently used
+            File::options()
+                .append(true)
+                .open(&cached_path)
+                .and_then(|f| f.set_modified(SystemTime::now()))
+                .ok();
+
+            return Ok(TrackReader::Cached(CachedReader {
+                file: File::open(&cached_path)?,
+                position: 0,
+                len,
+                read_ahead: 0,
+                fetch: None,
+            }));
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        let destination = match create_partial(&partial_path) {
+            Ok(destination) => destination,
+            // Left behind by a crash, and nothing here is fetching into it
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                fs::remove_file(&partial_path).ok();
+                match create_partial(&partial_path) {
+                    Ok(destination) => destination,
+                    // Another copy of the app got there first
+                    Err(_) => return open_uncached(source, len),
+                }
+            }
+            Err(e) => return Err(e),
+        };
+        let file = File::open(&partial_path)?;
+
+        let fetch = Arc::new(Fetch {
+            state: Mutex::new(FetchState {
+                fetched: 0,
+                wanted: self.settings.read_ahead_bytes(),
+                finished: false,
+                error: None,
+            }),
+            changed: Condvar::new(),
+            cancelled: AtomicBool::new(false),
+            readers: AtomicUsize::new(1),
+            whole_track: self.settings.cache_whole_track,
+        });
+
+        let job = FetchJob {
+            source: source.to_path_buf(),
+            partial_path: partial_path.clone(),
+            cached_path,
+            cache_dir: self.dir.clone(),
+            max_cache_bytes: self.settings.max_cache_bytes(),
+            fetch: fetch.clone(),
+        };
+        if let Err(e) = thread::Builder::new()
+            .name("stream-cache-fetch".into())
+            .spawn(move || job.run(destination))
+        {
+            fs::remove_file(&partial_path).ok();
+            return Err(e);
+        }
+        fetches.push((partial_path, Arc::downgrade(&fetch)));
+
+        Ok(TrackReader::Cached(CachedReader {
+            file,
+            position: 0,
+            len,
+            read_ahead: self.settings.read_ahead_bytes(),
+            fetch: Some(fetch),
+        }))
+    }
+
+    pub fn size(&self) -> u64 {
+        cache_entries(&self.dir)
+            .map(|entries| entries.iter().map(|(_, len, _)| len).sum())
+            .unwrap_or(0)
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        for (path, _, _) in cache_entries(&self.dir)? {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn open_uncached(source: &Path, len: u64) -> io::Result<TrackReader> {
+    if len >= MMAP_THRESHOLD_BYTES {
+        return Ok(TrackReader::Mapped(MappedReader::open(source)?));
+    }
+    Ok(TrackReader::Direct(BufReader::new(File::open(source)?)))
+}
+
+// Fails if the partial file is already there rather than truncating it
+// under a fetch that's writing to it
+fn create_partial(path: &Path) -> io::Result<File> {
+    File::options().write(true).create_new(true).open(path)
+}
+
+fn cache_key(source: &Path, metadata: &fs::Metadata) -> String {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    metadata.modified().ok().hash(&mut hasher);
+
+    match source.extension() {
+        Some(extension) => format!("{:016x}.{}", hasher.finish(), extension.to_string_lossy()),
+        None => format!("{:016x}", hasher.finish()),
+    }
+}
+
+fn cache_entries(dir: &Path) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == PARTIAL_EXTENSION) {
+            continue;
+        }
+        let metadata = fs::metadata(&path)?;
+        if metadata.is_file() {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push((path, metadata.len(), modified));
+        }
+    }
+    Ok(entries)
+}
+
+// Drops the least recently used entries until the cache fits in `max_bytes`
+fn evict(dir: &Path, max_bytes: u64) -> io::Result<()> {
+    let mut entries = cache_entries(dir)?;
+    entries.sort_by_key(|(_, _, modified)| *modified);
+
+    let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+    for (path, len, _) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        fs::remove_file(path)?;
+        total -= len;
+    }
+    Ok(())
+}
+
+struct FetchState {
+    fetched: u64,
+    wanted: u64,
+    finished: bool,
+    error: Option<String>,
+}
+
+struct Fetch {
+    state: Mutex<FetchState>,
+    changed: Condvar,
+    cancelled: AtomicBool,
+    // Readers sharing the fetch, the last to go calls it off
+    readers: AtomicUsize,
+    whole_track: bool,
+}
+
+struct FetchJob {
+    source: PathBuf,
+    partial_path: PathBuf,
+    cached_path: PathBuf,
+    cache_dir: PathBuf,
+    max_cache_bytes: u64,
+    fetch: Arc<Fetch>,
+}
+
+impl FetchJob {
+    fn run(self, destination: File) {
+        let result = self.copy(destination);
+        let cancelled = self.fetch.cancelled.load(Ordering::SeqCst);
+
+        // No reader attaches to the partial file while it's renamed or removed
+        let mut fetches = FETCHES.lock().unwrap();
+        match &result {
+            Ok(()) if !cancelled => {
+                if fs::rename(&self.partial_path, &self.cached_path).is_ok() {
+                    evict(&self.cache_dir, self.max_cache_bytes).ok();
+                }
+            }
+            _ => {
+                fs::remove_file(&self.partial_path).ok();
+            }
+        }
+        fetches.retain(|(path, _)| *path != self.partial_path);
+        drop(fetches);
+
+        let mut state = self.fetch.state.lock().unwrap();
+        state.finished = true;
+        if let Err(error) = result {
+            state.error = Some(error.to_string());
+        }
+        self.fetch.changed.notify_all();
+    }
+
+    fn copy(&self, mut destination: File) -> io::Result<()> {
+        let mut source = File::open(&self.source)?;
+        let mut buffer = vec![0; FETCH_CHUNK_SIZE];
+
+        loop {
+            {
+                let mut state = self.fetch.state.lock().unwrap();
+                while !self.fetch.whole_track
+                    && state.fetched >= state.wanted
+                    && !self.fetch.cancelled.load(Ordering::SeqCst)
+                {
+                    state = self.fetch.changed.wait(state).unwrap();
+                }
+            }
+
+            if self.fetch.cancelled.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+
+            let read = source.read(&mut buffer)?;
+            if read == 0 {
+                destination.flush()?;
+                return Ok(());
+            }
+            destination.write_all(&buffer[..read])?;
+
+            let mut state = self.fetch.state.lock().unwrap();
+            state.fetched += read as u64;
+            self.fetch.changed.notify_all();
+        }
+    }
+}
+
+/// Reads a track from a local copy that is filled in the background,
+/// blocking only when playback catches up with the network.
+pub struct CachedReader {
+    file: File,
+    position: u64,
+    len: u64,
+    read_ahead: u64,
+    fetch: Option<Arc<Fetch>>,
+}
+
+impl CachedReader {
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn wait_for_data(&self, wanted_len: u64) -> io::Result<u64> {
+        let Some(fetch) = &self.fetch else {
+            return Ok(self.len);
+        };
+
+        let mut state = fetch.state.lock().unwrap();
+        state.wanted = state
+            .wanted
+            .max(self.position + wanted_len + self.read_ahead);
+        fetch.changed.notify_all();
+
+        while state.fetched <= self.position && !state.finished {
+            state = fetch.changed.wait(state).unwrap();
+        }
+
+        match &state.error {
+            Some(error) => Err(io::Error::other(error.clone())),
+            None => Ok(state.fetched),
+        }
+    }
+}
+
+impl Read for CachedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let available = self.wait_for_data(buf.len() as u64)?;
+        if available <= self.position {
+            return Ok(0);
+        }
+
+        let len = buf.len().min((available - self.position) as usize);
+        self.file.seek(SeekFrom::Start(self.position))?;
+        let read = self.file.read(&mut buf[..len])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for CachedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+impl Drop for CachedReader {
+    fn drop(&mut self) {
+        // A whole-track fetch keeps going so the next play is served locally
+        let Some(fetch) = &self.fetch else {
+            return;
+        };
+        if fetch.readers.fetch_sub(1, Ordering::SeqCst) == 1 && !fetch.whole_track {
+            fetch.cancelled.store(true, Ordering::SeqCst);
+            fetch.changed.notify_all();
+        }
+    }
+}
+
+pub enum TrackReader {
+    Direct(BufReader<File>),
+    Mapped(MappedReader),
+    Cached(CachedReader),
+}
+
+impl Read for TrackReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            TrackReader::Direct(reader) => reader.read(buf),
+            TrackReader::Mapped(reader) => reader.read(buf),
+            TrackReader::Cached(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl Seek for TrackReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            TrackReader::Direct(reader) => reader.seek(pos),
+            TrackReader::Mapped(reader) => reader.seek(pos),
+            TrackReader::Cached(reader) => reader.seek(pos),
+        }
+    }
+}
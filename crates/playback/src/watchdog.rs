@@ -0,0 +1,236 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::SuspendDetector;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const STALL_TIMEOUT: Duration = Duration::from_millis(750);
+const REPEAT_WINDOW: Duration = Duration::from_secs(60);
+const REPEAT_THRESHOLD: usize = 3;
+
+/// Times the device asked for audio while a track was playing and got
+/// less than it wanted, and how many frames of silence it got instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnderrunStats {
+    pub count: u64,
+    pub silent_frames: u64,
+}
+
+/// Shared with the audio callback, which beats once per buffer it fills.
+pub struct Heartbeat {
+    epoch: Instant,
+    last_beat_ms: AtomicU64,
+    underruns: AtomicU64,
+    underrun_frames: AtomicU64,
+}
+
+impl Heartbeat {
+    fn new() -> Self {
+        Heartbeat {
+            epoch: Instant::now(),
+            last_beat_ms: AtomicU64::new(0),
+            underruns: AtomicU64::new(0),
+            underrun_frames: AtomicU64::new(0),
+        }
+    }
+
+    pub fn beat(&self) {
+        self.last_beat_ms
+            .store(self.epoch.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_underrun(&self, silent_frames: u64) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+        self.underrun_frames
+            .fetch_add(silent_frames, Ordering::Relaxed);
+    }
+
+    pub fn underruns(&self) -> UnderrunStats {
+        UnderrunStats {
+            count: self.underruns.load(Ordering::Relaxed),
+            silent_frames: self.underrun_frames.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset_underruns(&self) {
+        self.underruns.store(0, Ordering::Relaxed);
+        self.underrun_frames.store(0, Ordering::Relaxed);
+    }
+
+    fn silent_for(&self) -> Duration {
+        let last_beat = Duration::from_millis(self.last_beat_ms.load(Ordering::Relaxed));
+        self.epoch.elapsed().saturating_sub(last_beat)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum WatchdogEvent {
+    Underrun { total: u64 },
+    Stalled { silent_for: Duration },
+    Recovered,
+    RecoveryFailed(String),
+    // Raised once recoveries start piling up, worth telling the user about
+    RecoveringRepeatedly { recoveries: usize },
+    // The system woke from sleep and the stream was rebuilt, as the device
+    // it was playing to may be gone or have lost its place
+    Resumed { suspended_for: Duration },
+}
+
+pub struct Watchdog {
+    heartbeat: Arc<Heartbeat>,
+    watching: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+    events: Receiver<WatchdogEvent>,
+}
+
+impl Watchdog {
+    /// Spawns the monitor thread. `recover` is called from that thread when
+    /// the output stalls and is expected to rebuild the stream at the current
+    /// position.
+    pub fn spawn(recover: impl FnMut() -> anyhow::Result<()> + Send + 'static) -> Self {
+        let heartbeat = Arc::new(Heartbeat::new());
+        let watching = Arc::new(AtomicBool::new(false));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let (sender, events) = mpsc::channel();
+
+        let monitor = Monitor {
+            heartbeat: heartbeat.clone(),
+            watching: watching.clone(),
+            shutdown: shutdown.clone(),
+            events: sender,
+            recoveries: VecDeque::new(),
+            seen_underruns: 0,
+            suspend: SuspendDetector::default(),
+        };
+        thread::Builder::new()
+            .name("audio-watchdog".into())
+            .spawn(move || monitor.run(recover))
+            .expect("failed to spawn audio watchdog");
+
+        Watchdog {
+            heartbeat,
+            watching,
+            shutdown,
+            events,
+        }
+    }
+
+    pub fn heartbeat(&self) -> Arc<Heartbeat> {
+        self.heartbeat.clone()
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.watching.load(Ordering::SeqCst)
+    }
+
+    // Only watch while audio is expected to flow, a paused stream is silent on purpose
+    pub fn set_watching(&self, watching: bool) {
+        if watching {
+            self.heartbeat.beat();
+        }
+        self.watching.store(watching, Ordering::SeqCst);
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = WatchdogEvent> + '_ {
+        self.events.try_iter()
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+    }
+}
+
+struct Monitor {
+    heartbeat: Arc<Heartbeat>,
+    watching: Arc<AtomicBool>,
+    shutdown: Arc<AtomicBool>,
+    events: Sender<WatchdogEvent>,
+    recoveries: VecDeque<Instant>,
+    seen_underruns: u64,
+    suspend: SuspendDetector,
+}
+
+impl Monitor {
+    fn run(mut self, mut recover: impl FnMut() -> anyhow::Result<()>) {
+        while !self.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(POLL_INTERVAL);
+
+            // Silence across a suspend isn't a stall and shouldn't count
+            // towards recovering repeatedly
+            if let Some(suspended_for) = self.suspend.check() {
+                eprintln!("Resumed after {:?} asleep", suspended_for);
+                if self.watching.load(Ordering::SeqCst) {
+                    if let Err(error) = recover() {
+                        eprintln!("Failed to recover audio output: {}", error);
+                        self.send(WatchdogEvent::RecoveryFailed(error.to_string()));
+                    }
+                }
+                self.heartbeat.beat();
+                self.send(WatchdogEvent::Resumed { suspended_for });
+                continue;
+            }
+
+            // Goes back down when diagnostics resets it
+            let underruns = self.heartbeat.underruns().count;
+            if underruns > self.seen_underruns {
+                eprintln!("Audio underrun ({} total)", underruns);
+                self.send(WatchdogEvent::Underrun { total: underruns });
+            }
+            self.seen_underruns = underruns;
+
+            if !self.watching.load(Ordering::SeqCst) {
+                continue;
+            }
+
+            let silent_for = self.heartbeat.silent_for();
+            if silent_for < STALL_TIMEOUT {
+                continue;
+            }
+
+            eprintln!(
+                "Audio output stalled for {:?}, rebuilding stream",
+                silent_for
+            );
+            self.send(WatchdogEvent::Stalled { silent_for });
+
+            match recover() {
+                Ok(()) => self.send(WatchdogEvent::Recovered),
+                Err(error) => {
+                    eprintln!("Failed to recover audio output: {}", error);
+                    self.send(WatchdogEvent::RecoveryFailed(error.to_string()));
+                }
+            }
+            // Give the new stream a full timeout before judging it
+            self.heartbeat.beat();
+
+            let now = Instant::now();
+            self.recoveries.push_back(now);
+            while self
+                .recoveries
+                .front()
+                .is_some_and(|at| now.duration_since(*at) > REPEAT_WINDOW)
+            {
+                self.recoveries.pop_front();
+            }
+            if self.recoveries.len() >= REPEAT_THRESHOLD {
+                self.send(WatchdogEvent::RecoveringRepeatedly {
+                    recoveries: self.recoveries.len(),
+                });
+            }
+        }
+    }
+
+    fn send(&self, event: WatchdogEvent) {
+        self.events.send(event).ok();
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{RemoteSnapshot, RemoteTrack};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    io,
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Mutex,
+    },
+    time::Duration,
+};
+use tungstenite::{Message, WebSocket};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RemoteEvent {
+    TrackChanged {
+        track: Option<RemoteTrack>,
+    },
+    Position {
+        current_time: i32,
+        duration: i32,
+        is_playing: bool,
+    },
+    QueueUpdated {
+        queue: Vec<RemoteTrack>,
+    },
+    VolumeChanged {
+        volume: f32,
+    },
+}
+
+impl RemoteEvent {
+    // The events a new subscriber needs to catch up with the current state
+    fn initial(snapshot: &RemoteSnapshot) -> Vec<RemoteEvent> {
+        let mut events = vec![RemoteEvent::TrackChanged {
+            track: snapshot.now_playing.track.clone(),
+        }];
+        events.extend(RemoteEvent::position(snapshot));
+        events.push(RemoteEvent::QueueUpdated {
+            queue: snapshot.queue.clone(),
+        });
+        events.push(RemoteEvent::VolumeChanged {
+            volume: snapshot.volume,
+        });
+        events
+    }
+
+    fn position(snapshot: &RemoteSnapshot) -> Option<RemoteEvent> {
+        let track = snapshot.now_playing.track.as_ref()?;
+        Some(RemoteEvent::Position {
+            current_time: snapshot.now_playing.current_time,
+            duration: track.duration,
+            is_playing: snapshot.now_playing.is_playing,
+        })
+    }
+
+    pub fn diff(previous: &RemoteSnapshot, next: &RemoteSnapshot) -> Vec<RemoteEvent> {
+        let mut events = Vec::new();
+
+        if previous.now_playing.track != next.now_playing.track {
+            events.push(RemoteEvent::TrackChanged {
+                track: next.now_playing.track.clone(),
+            });
+        }
+        // Playback time has a resolution of one second, so this fires once
+        // a second while playing
+        if previous.now_playing != next.now_playing {
+            events.extend(RemoteEvent::position(next));
+        }
+        if previous.queue != next.queue {
+            events.push(RemoteEvent::QueueUpdated {
+                queue: next.queue.clone(),
+            });
+        }
+        if previous.volume != next.volume {
+            events.push(RemoteEvent::VolumeChanged {
+                volume: next.volume,
+            });
+        }
+
+        events
+    }
+}
+
+// How long a client's socket is waited on for Close and Ping frames
+// before checking for events to send it
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+pub(crate) struct EventStream {
+    subscribers: Mutex<HashMap<usize, Sender<String>>>,
+    next_id: AtomicUsize,
+}
+
+impl EventStream {
+    // Runs on the client's own thread until it closes the socket or the
+    // server stops. Reading is what answers the client's pings and sees
+    // its close, so the socket is read between sends.
+    pub fn subscribe(&self, mut socket: WebSocket<TcpStream>, snapshot: &RemoteSnapshot) {
+        let (sender, messages) = mpsc::channel::<String>();
+        for event in RemoteEvent::initial(snapshot) {
+            sender.send(encode(&event)).ok();
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.subscribers.lock().unwrap().insert(id, sender);
+
+        if let Err(error) = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)) {
+            eprintln!("Failed to set websocket timeout: {}", error);
+        } else {
+            serve(&mut socket, &messages);
+        }
+        self.subscribers.lock().unwrap().remove(&id);
+    }
+
+    pub fn broadcast(&self, events: &[RemoteEvent]) {
+        if events.is_empty() {
+            return;
+        }
+
+        let messages: Vec<String> = events.iter().map(encode).collect();
+        for subscriber in self.subscribers.lock().unwrap().values() {
+            for message in &messages {
+                subscriber.send(message.clone()).ok();
+            }
+        }
+    }
+
+    // Dropping the senders ends each client's thread on its next poll
+    pub fn close_all(&self) {
+        self.subscribers.lock().unwrap().clear();
+    }
+}
+
+fn serve(socket: &mut WebSocket<TcpStream>, messages: &Receiver<String>) {
+    loop {
+        loop {
+            match messages.try_recv() {
+                Ok(message) => {
+                    if socket.send(Message::text(message)).is_err() {
+                        return;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    socket.close(None).ok();
+                    socket.flush().ok();
+                    return;
+                }
+            }
+        }
+
+        // Pings are answered and closes replied to as they're read. Once
+        // the close is done reading fails, which ends the subscription.
+        match socket.read() {
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(error))
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+fn encode(event: &RemoteEvent) -> String {
+    serde_json::to_string(event).unwrap_or_else(|_| "null".to_string())
+}
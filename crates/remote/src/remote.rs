@@ -0,0 +1,437 @@
+mod events;
+
+pub use events::RemoteEvent;
+
+use anyhow::Context as _;
+use events::EventStream;
+use serde::Serialize;
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tungstenite::{
+    handshake::server::{
+        ErrorResponse, Request as WebSocketRequest, Response as WebSocketResponse,
+    },
+    http::StatusCode,
+};
+
+const INDEX_HTML: &str = include_str!("web/index.html");
+// Where the page is handed the pairing token it was opened with
+const TOKEN_PLACEHOLDER: &str = "\"{{token}}\"";
+const TOKEN_HEADER: &str = "X-Remote-Token";
+const UNPAIRED_HTML: &str = "<!doctype html><p>Open the address shown in gpuiTunes under \
+     Remote Control to pair this device.</p>";
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(2);
+const STATS_TIMEOUT: Duration = Duration::from_secs(5);
+// How often the events listener checks whether the server has stopped
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RemoteTrack {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub duration: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RemoteNowPlaying {
+    pub track: Option<RemoteTrack>,
+    pub is_playing: bool,
+    pub current_time: i32,
+}
+
+/// Everything the remote can show, published by the app on each update tick.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RemoteSnapshot {
+    pub now_playing: RemoteNowPlaying,
+    pub queue: Vec<RemoteTrack>,
+    pub volume: f32,
+}
+
+#[derive(Debug)]
+pub enum RemoteCommand {
+    TogglePlayback,
+    Play,
+    Pause,
+    SkipNext,
+    SkipPrev,
+    PlayTrack(String),
+    Search {
+        query: String,
+        reply: Sender<Vec<RemoteTrack>>,
+    },
+    // Replied to with the library's statistics, already serialized since
+    // the server doesn't know their shape
+    Stats {
+        reply: Sender<serde_json::Value>,
+    },
+}
+
+// The HTTP server runs on its own thread and never touches app state
+// directly: reads come from the last published snapshot and writes are
+// queued as commands for the app to drain on the main thread.
+//
+// Every /api route wants the pairing token, which the page is given when
+// it's opened from the pairing address, so only devices the user paired
+// can see or control anything.
+//
+// Events are streamed over a websocket on the next port up, served apart
+// from the HTTP server so each client's socket can be read as well as
+// written, to answer its pings and notice when it closes.
+pub struct RemoteServer {
+    server: Arc<Server>,
+    snapshot: Arc<Mutex<RemoteSnapshot>>,
+    events: Arc<EventStream>,
+    commands: Receiver<RemoteCommand>,
+    token: Arc<Mutex<String>>,
+    stopped: Arc<AtomicBool>,
+}
+
+/// The port events are streamed on, for a server started on `port`.
+pub fn events_port(port: u16) -> u16 {
+    port.wrapping_add(1)
+}
+
+impl RemoteServer {
+    pub fn start(addr: impl ToSocketAddrs, token: String) -> anyhow::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .context("No address to serve the remote on")?;
+        let events_listener =
+            TcpListener::bind(SocketAddr::new(addr.ip(), events_port(addr.port())))?;
+        events_listener.set_nonblocking(true)?;
+        let server = Arc::new(Server::http(addr).map_err(|e| anyhow::anyhow!(e))?);
+        let snapshot = Arc::new(Mutex::new(RemoteSnapshot::default()));
+        let events = Arc::new(EventStream::default());
+        let token = Arc::new(Mutex::new(token));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let (sender, commands) = mpsc::channel();
+
+        let handler = Arc::new(Handler {
+            snapshot: snapshot.clone(),
+            events: events.clone(),
+            commands: sender,
+            token: token.clone(),
+        });
+        let incoming = server.clone();
+        let http_handler = handler.clone();
+        thread::Builder::new()
+            .name("remote-http".into())
+            .spawn(move || {
+                for request in incoming.incoming_requests() {
+                    http_handler.handle(request);
+                }
+            })?;
+
+        let events_stopped = stopped.clone();
+        thread::Builder::new()
+            .name("remote-events".into())
+            .spawn(move || listen_for_events(events_listener, handler, events_stopped))?;
+
+        Ok(RemoteServer {
+            server,
+            snapshot,
+            events,
+            commands,
+            token,
+            stopped,
+        })
+    }
+
+    /// Replaces the pairing token. Devices paired with the old one have to
+    /// be paired again.
+    pub fn set_token(&self, token: String) {
+        *self.token.lock().unwrap() = token;
+    }
+
+    pub fn publish(&self, snapshot: RemoteSnapshot) {
+        let mut current = self.snapshot.lock().unwrap();
+        self.events
+            .broadcast(&RemoteEvent::diff(&current, &snapshot));
+        *current = snapshot;
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = RemoteCommand> + '_ {
+        self.commands.try_iter()
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        self.stopped.store(true, Ordering::Relaxed);
+        self.events.close_all();
+    }
+}
+
+struct Handler {
+    snapshot: Arc<Mutex<RemoteSnapshot>>,
+    events: Arc<EventStream>,
+    commands: Sender<RemoteCommand>,
+    token: Arc<Mutex<String>>,
+}
+
+impl Handler {
+    fn handle(&self, request: Request) {
+        let (path, query) = match request.url().split_once('?') {
+            Some((path, query)) => (path.to_string(), query.to_string()),
+            None => (request.url().to_string(), String::new()),
+        };
+        let header = |field| header_value(&request, field);
+        let paired = self.is_paired(header, &query);
+        let is_command = *request.method() == Method::Post;
+        if let Some(status) = refusal(&path, paired, is_command, header) {
+            if let Err(error) = request.respond(Response::empty(status)) {
+                eprintln!("Failed to respond to remote request: {}", error);
+            }
+            return;
+        }
+
+        let result = match (request.method(), path.as_str()) {
+            (Method::Get, "/") if paired => {
+                let token = self.token.lock().unwrap().clone();
+                let page = INDEX_HTML.replace(
+                    TOKEN_PLACEHOLDER,
+                    &serde_json::to_string(&token).unwrap_or_default(),
+                );
+                request.respond(Response::from_string(page).with_header(content_type("text/html")))
+            }
+            (Method::Get, "/") => request.respond(
+                Response::from_string(UNPAIRED_HTML)
+                    .with_status_code(401)
+                    .with_header(content_type("text/html")),
+            ),
+            (Method::Get, "/api/state") => {
+                let snapshot = self.snapshot.lock().unwrap().clone();
+                request.respond(json(&snapshot))
+            }
+            (Method::Get, "/api/search") => {
+                let query = query_value(&query, "q").unwrap_or_default();
+                request.respond(json(&self.search(query)))
+            }
+            (Method::Get, "/api/stats") => match self.stats() {
+                Some(stats) => request.respond(json(&stats)),
+                None => request.respond(Response::empty(503)),
+            },
+            (Method::Post, path) => match command_for_path(path) {
+                Some(command) => {
+                    self.commands.send(command).ok();
+                    request.respond(Response::empty(204))
+                }
+                None => request.respond(Response::empty(404)),
+            },
+            _ => request.respond(Response::empty(404)),
+        };
+
+        if let Err(error) = result {
+            eprintln!("Failed to respond to remote request: {}", error);
+        }
+    }
+
+    // From the header the page sends, or from the address for the first
+    // load and for websockets, which can't send headers
+    fn is_paired(&self, header: impl Fn(&'static str) -> Option<String>, query: &str) -> bool {
+        let token = self.token.lock().unwrap();
+        if token.is_empty() {
+            return false;
+        }
+        header(TOKEN_HEADER).is_some_and(|value| value == *token)
+            || query_value(query, "token").is_some_and(|value| value == *token)
+    }
+
+    // Takes the client through the websocket handshake, held to the same
+    // pairing and origin checks as the HTTP routes, then streams events to
+    // it until it goes
+    #[allow(clippy::result_large_err)] // The error is tungstenite's to pick
+    fn subscribe(&self, stream: TcpStream) {
+        let accepted = tungstenite::accept_hdr(
+            stream,
+            |request: &WebSocketRequest, response: WebSocketResponse| {
+                let header = |field: &'static str| {
+                    request
+                        .headers()
+                        .get(field)
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_string)
+                };
+                let path = request.uri().path();
+                let query = request.uri().query().unwrap_or_default();
+                let status = if path != "/api/events" {
+                    Some(404)
+                } else {
+                    refusal(path, self.is_paired(header, query), true, header)
+                };
+                match status {
+                    Some(status) => {
+                        let mut refused = ErrorResponse::new(None);
+                        *refused.status_mut() =
+                            StatusCode::from_u16(status).unwrap_or(StatusCode::FORBIDDEN);
+                        Err(refused)
+                    }
+                    None => Ok(response),
+                }
+            },
+        );
+        match accepted {
+            Ok(socket) => {
+                let snapshot = self.snapshot.lock().unwrap().clone();
+                self.events.subscribe(socket, &snapshot);
+            }
+            Err(error) => eprintln!("Failed to accept remote events client: {}", error),
+        }
+    }
+
+    fn search(&self, query: String) -> Vec<RemoteTrack> {
+        if query.trim().is_empty() {
+            return Vec::new();
+        }
+
+        let (reply, results) = mpsc::channel();
+        self.commands
+            .send(RemoteCommand::Search { query, reply })
+            .ok();
+        results.recv_timeout(SEARCH_TIMEOUT).unwrap_or_default()
+    }
+
+    fn stats(&self) -> Option<serde_json::Value> {
+        let (reply, stats) = mpsc::channel();
+        self.commands.send(RemoteCommand::Stats { reply }).ok()?;
+        stats.recv_timeout(STATS_TIMEOUT).ok()
+    }
+}
+
+fn command_for_path(path: &str) -> Option<RemoteCommand> {
+    match path {
+        "/api/toggle" => Some(RemoteCommand::TogglePlayback),
+        "/api/play" => Some(RemoteCommand::Play),
+        "/api/pause" => Some(RemoteCommand::Pause),
+        "/api/next" => Some(RemoteCommand::SkipNext),
+        "/api/previous" => Some(RemoteCommand::SkipPrev),
+        _ => path
+            .strip_prefix("/api/play-track/")
+            .and_then(decode)
+            .map(RemoteCommand::PlayTrack),
+    }
+}
+
+// Listens until the server stops, checking in between clients since
+// accepting can't be interrupted otherwise
+fn listen_for_events(listener: TcpListener, handler: Arc<Handler>, stopped: Arc<AtomicBool>) {
+    while !stopped.load(Ordering::Relaxed) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+            Err(error) => {
+                eprintln!("Failed to accept remote events client: {}", error);
+                continue;
+            }
+        };
+        // Some platforms hand on the listener's non-blocking mode
+        if let Err(error) = stream.set_nonblocking(false) {
+            eprintln!("Failed to accept remote events client: {}", error);
+            continue;
+        }
+        let handler = handler.clone();
+        let spawned = thread::Builder::new()
+            .name("remote-websocket".into())
+            .spawn(move || handler.subscribe(stream));
+        if let Err(error) = spawned {
+            eprintln!("Failed to spawn websocket thread: {}", error);
+        }
+    }
+}
+
+// Anything unpaired is refused, and anything that acts or listens is
+// refused to other sites' pages too, which a browser on the same network
+// would otherwise let through
+fn refusal(
+    path: &str,
+    paired: bool,
+    is_command: bool,
+    header: impl Fn(&'static str) -> Option<String>,
+) -> Option<u16> {
+    if path.starts_with("/api/") && !paired {
+        Some(401)
+    } else if is_command && !is_same_origin(header) {
+        Some(403)
+    } else {
+        None
+    }
+}
+
+fn header_value(request: &Request, field: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(field))
+        .map(|header| header.value.as_str().to_string())
+}
+
+// Requests without an Origin don't come from a browser page, so there's no
+// other site to be wary of
+fn is_same_origin(header: impl Fn(&'static str) -> Option<String>) -> bool {
+    let Some(origin) = header("Origin") else {
+        return true;
+    };
+    let host = header("Host");
+    let origin_host = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"));
+    origin_host.is_some() && origin_host == host.as_deref()
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes(&b"Content-Type"[..], value.as_bytes()).unwrap()
+}
+
+fn json(value: &impl Serialize) -> Response<std::io::Cursor<Vec<u8>>> {
+    let body = serde_json::to_string(value).unwrap_or_else(|_| "null".to_string());
+    Response::from_string(body).with_header(content_type("application/json"))
+}
+
+fn query_value(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, value)| decode(value))
+}
+
+fn decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut ix = 0;
+    while ix < bytes.len() {
+        match bytes[ix] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(ix + 1..ix + 3)?).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                ix += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                ix += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                ix += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}